@@ -0,0 +1,236 @@
+//! Optional API keys and token-bucket rate limits for the HTTP API
+//!
+//! Everything is off by default; `create_app_with` only installs the middleware when
+//! `AccessConfig::is_enabled` is true.
+
+use parking_lot::Mutex;
+use std::collections::HashMap;
+use std::str::FromStr;
+use std::time::{Duration, Instant};
+
+/// Buckets kept before idle (full) ones are dropped, then the least recently used
+const MAX_CLIENTS: usize = 10_000;
+
+/// Sustained requests per second plus the burst allowed on top
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct RateLimit {
+    pub per_second: f64,
+    pub burst: u32,
+}
+
+impl FromStr for RateLimit {
+    type Err = String;
+
+    /// `RATE` or `RATE:BURST`, e.g. `20` or `0.5:10` (burst defaults to the rate, at least 1)
+    fn from_str(s: &str) -> Result<Self, String> {
+        let (rate, burst) = s.split_once(':').unwrap_or((s, ""));
+        let per_second: f64 = rate
+            .trim()
+            .parse()
+            .ok()
+            .filter(|rate: &f64| rate.is_finite() && *rate > 0.0)
+            .ok_or_else(|| format!("rate must be a positive number: {:?}", s))?;
+        let burst = match burst.trim() {
+            "" => (per_second.ceil() as u32).max(1),
+            burst => burst
+                .parse()
+                .ok()
+                .filter(|&burst| burst > 0)
+                .ok_or_else(|| format!("burst must be a positive integer: {:?}", s))?,
+        };
+        Ok(Self { per_second, burst })
+    }
+}
+
+/// API keys and rate limits (`ApiConfig::access`)
+#[derive(Clone, Debug, Default)]
+pub struct AccessConfig {
+    /// Accepted keys; when set, every non-GET route needs one
+    pub api_keys: Vec<String>,
+    /// Require a key for reads too
+    pub keys_for_reads: bool,
+    /// Limit per API key
+    pub key_rate: Option<RateLimit>,
+    /// Limit per client IP for requests without a key
+    pub anonymous_rate: Option<RateLimit>,
+}
+
+impl AccessConfig {
+    pub fn is_enabled(&self) -> bool {
+        !self.api_keys.is_empty() || self.key_rate.is_some() || self.anonymous_rate.is_some()
+    }
+
+    /// Keys from a file, one per line (blank lines and `#` comments skipped)
+    pub fn read_keys(path: &std::path::Path) -> std::io::Result<Vec<String>> {
+        Ok(std::fs::read_to_string(path)?
+            .lines()
+            .map(str::trim)
+            .filter(|line| !line.is_empty() && !line.starts_with('#'))
+            .map(str::to_string)
+            .collect())
+    }
+}
+
+struct Bucket {
+    tokens: f64,
+    updated: Instant,
+}
+
+/// Token buckets keyed by client (API key or IP)
+pub struct RateLimiter {
+    limit: RateLimit,
+    buckets: Mutex<HashMap<String, Bucket>>,
+}
+
+impl RateLimiter {
+    pub fn new(limit: RateLimit) -> Self {
+        Self {
+            limit,
+            buckets: Mutex::new(HashMap::new()),
+        }
+    }
+
+    /// Take one token for `client`, or return how long until the next one
+    pub fn acquire(&self, client: &str) -> Result<(), Duration> {
+        self.acquire_at(client, Instant::now())
+    }
+
+    fn acquire_at(&self, client: &str, now: Instant) -> Result<(), Duration> {
+        let RateLimit { per_second, burst } = self.limit;
+        let burst = f64::from(burst);
+        let refill = |bucket: &Bucket| {
+            let elapsed = now.saturating_duration_since(bucket.updated).as_secs_f64();
+            (bucket.tokens + elapsed * per_second).min(burst)
+        };
+
+        let mut buckets = self.buckets.lock();
+        if buckets.len() >= MAX_CLIENTS && !buckets.contains_key(client) {
+            buckets.retain(|_, bucket| refill(bucket) < burst);
+        }
+        if buckets.len() >= MAX_CLIENTS && !buckets.contains_key(client) {
+            // Still full of partly drained buckets (e.g. rotating IPs): drop the least
+            // recently updated tenth so the next evictions are a while away
+            let mut updated: Vec<Instant> = buckets.values().map(|bucket| bucket.updated).collect();
+            let (_, &mut cutoff, _) = updated.select_nth_unstable(MAX_CLIENTS / 10);
+            buckets.retain(|_, bucket| bucket.updated > cutoff);
+        }
+        let bucket = buckets.entry(client.to_string()).or_insert(Bucket {
+            tokens: burst,
+            updated: now,
+        });
+        bucket.tokens = refill(bucket);
+        bucket.updated = now;
+        if bucket.tokens >= 1.0 {
+            bucket.tokens -= 1.0;
+            Ok(())
+        } else {
+            Err(Duration::from_secs_f64((1.0 - bucket.tokens) / per_second))
+        }
+    }
+}
+
+/// Key lookup plus the two limiters, shared by the middleware
+pub struct AccessControl {
+    pub config: AccessConfig,
+    key_limiter: Option<RateLimiter>,
+    anonymous_limiter: Option<RateLimiter>,
+}
+
+impl AccessControl {
+    pub fn new(config: AccessConfig) -> Self {
+        Self {
+            key_limiter: config.key_rate.map(RateLimiter::new),
+            anonymous_limiter: config.anonymous_rate.map(RateLimiter::new),
+            config,
+        }
+    }
+
+    /// Index of `given` in the configured keys (compared in constant time)
+    pub fn find_key(&self, given: &str) -> Option<usize> {
+        self.config
+            .api_keys
+            .iter()
+            .position(|key| constant_time_eq(key.as_bytes(), given.as_bytes()))
+    }
+
+    pub fn acquire_for_key(&self, index: usize) -> Result<(), Duration> {
+        match &self.key_limiter {
+            Some(limiter) => limiter.acquire(&index.to_string()),
+            None => Ok(()),
+        }
+    }
+
+    pub fn acquire_anonymous(&self, client: &str) -> Result<(), Duration> {
+        match &self.anonymous_limiter {
+            Some(limiter) => limiter.acquire(client),
+            None => Ok(()),
+        }
+    }
+}
+
+pub fn constant_time_eq(a: &[u8], b: &[u8]) -> bool {
+    a.len() == b.len() && a.iter().zip(b).fold(0u8, |acc, (x, y)| acc | (x ^ y)) == 0
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn bucket_allows_burst_then_refills_at_rate() {
+        let limiter = RateLimiter::new("2:3".parse().unwrap());
+        let start = Instant::now();
+        for _ in 0..3 {
+            assert!(limiter.acquire_at("a", start).is_ok());
+        }
+        let wait = limiter.acquire_at("a", start).unwrap_err();
+        assert_eq!(wait, Duration::from_millis(500));
+        // Other clients have their own bucket
+        assert!(limiter.acquire_at("b", start).is_ok());
+
+        assert!(
+            limiter
+                .acquire_at("a", start + Duration::from_millis(500))
+                .is_ok()
+        );
+        assert!(
+            limiter
+                .acquire_at("a", start + Duration::from_millis(600))
+                .is_err()
+        );
+    }
+
+    #[test]
+    fn drained_buckets_are_evicted_least_recently_used_first() {
+        let limiter = RateLimiter::new("1:3".parse().unwrap());
+        let start = Instant::now();
+        for i in 0..MAX_CLIENTS {
+            let now = start + Duration::from_millis(i as u64);
+            assert!(limiter.acquire_at(&i.to_string(), now).is_ok());
+        }
+        let now = start + Duration::from_millis(MAX_CLIENTS as u64);
+        assert!(limiter.acquire_at("new", now).is_ok());
+
+        let buckets = limiter.buckets.lock();
+        assert!(buckets.len() <= MAX_CLIENTS);
+        assert!(!buckets.contains_key("0"));
+        assert!(buckets.contains_key(&(MAX_CLIENTS - 1).to_string()));
+        assert!(buckets.contains_key("new"));
+    }
+
+    #[test]
+    fn rate_limit_parses_rate_and_burst() {
+        assert_eq!(
+            "20".parse::<RateLimit>().unwrap(),
+            RateLimit {
+                per_second: 20.0,
+                burst: 20
+            }
+        );
+        assert_eq!("0.5:10".parse::<RateLimit>().unwrap().burst, 10);
+        assert_eq!("0.5".parse::<RateLimit>().unwrap().burst, 1);
+        for bad in ["", "0", "-1", "fast", "5:0", "5:x"] {
+            assert!(bad.parse::<RateLimit>().is_err(), "{:?}", bad);
+        }
+    }
+}
@@ -1,21 +1,264 @@
-use crate::store::FxStore;
-use crate::types::OHLCV;
+use crate::access::{AccessConfig, AccessControl, constant_time_eq};
+use crate::error::{FxStoreError, QueryError};
+use crate::metrics::ApiMetrics;
+use crate::query::{
+    Alignment, Cross, FillPolicy, RenkoDirection, TechnicalIndicators, chart_interval,
+    downsample_lttb, format_interval, parse_interval, resample, transform_heikin_ashi,
+    transform_renko,
+};
+use crate::store::{FxStore, PARALLEL_QUERY_DAYS};
+use crate::types::{
+    MAX_TS, NANOS_PER_DAY, OHLCV, PRICE_SCALE, PriceField, Tick, TimeRange, scale_price,
+    ts_to_datetime,
+};
+use anyhow::Context;
 use axum::{
-    extract::{Path, Query, State},
-    http::StatusCode,
-    response::Json,
-    routing::get,
-    Router,
+    Extension, Router,
+    body::{Body, Bytes, HttpBody},
+    extract::{
+        ConnectInfo, DefaultBodyLimit, FromRef, MatchedPath, Path, Query, RawPathParams, Request,
+        State,
+    },
+    http::{HeaderMap, HeaderName, HeaderValue, Method, StatusCode, header},
+    middleware::{self, Next},
+    response::{
+        Html, IntoResponse, Json, Response,
+        sse::{Event, KeepAlive, Sse},
+    },
+    routing::{get, post},
 };
 use chrono::{DateTime, Utc};
+use chrono_tz::Tz;
+use crossbeam::channel::RecvTimeoutError;
+use futures_util::{Stream, StreamExt};
 use serde::{Deserialize, Serialize};
-use std::collections::HashMap;
+use std::collections::{BTreeMap, HashMap};
+use std::convert::Infallible;
+use std::net::{IpAddr, Ipv4Addr, SocketAddr};
 use std::sync::Arc;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::time::{Duration, Instant};
 use tower_http::cors::CorsLayer;
+use tower_http::trace::TraceLayer;
+use tracing::field::Empty;
+use utoipa::openapi::security::{HttpAuthScheme, HttpBuilder, SecurityScheme};
+use utoipa::{IntoParams, Modify, OpenApi, ToSchema};
 
 pub type SharedStore = Arc<FxStore>;
 
-#[derive(Serialize)]
+/// Tunables for request handlers
+#[derive(Clone, Debug)]
+pub struct ApiConfig {
+    /// Upper bound on bars fed into `/indicator`; larger ranges need a coarser interval
+    pub max_indicator_points: usize,
+    /// Bearer token for `POST /bars` and `POST /ticks`; writes are disabled when unset
+    pub write_token: Option<String>,
+    /// Request bodies larger than this are rejected with 413
+    pub max_body_bytes: usize,
+    /// How far past the server clock an ingested timestamp may be
+    pub max_future_skew: Duration,
+    /// Longest `end - start` a read endpoint accepts
+    pub max_query_span: Duration,
+    /// API keys and rate limits, off by default
+    pub access: AccessConfig,
+    /// Bytes of whole read responses kept by `ResponseCache`; 0 turns the cache off
+    pub response_cache_bytes: usize,
+    /// How long a cached response whose range reaches into the current UTC day is served;
+    /// zero keeps such responses out of the cache
+    pub response_cache_live_ttl: Duration,
+    /// Browser origins allowed to call the API (e.g. `https://charts.example.com`); empty
+    /// allows any origin, which is only meant for local development
+    pub allowed_origins: Vec<String>,
+    /// Let browsers send credentials (cookies, `Authorization`) from `allowed_origins`;
+    /// `create_app_with` rejects it when `allowed_origins` is empty
+    pub allow_credentials: bool,
+}
+
+impl Default for ApiConfig {
+    fn default() -> Self {
+        Self {
+            max_indicator_points: 100_000,
+            write_token: None,
+            max_body_bytes: 8 << 20,
+            max_future_skew: Duration::from_secs(5),
+            max_query_span: Duration::from_secs(5 * 365 * 86_400),
+            access: AccessConfig::default(),
+            response_cache_bytes: 0,
+            response_cache_live_ttl: Duration::from_secs(10),
+            allowed_origins: Vec::new(),
+            allow_credentials: false,
+        }
+    }
+}
+
+/// Listening address plus handler tunables
+#[derive(Clone, Debug)]
+pub struct ServerConfig {
+    pub bind: IpAddr,
+    pub port: u16,
+    /// How long in-flight requests may run after shutdown starts before they are dropped
+    pub shutdown_timeout: Duration,
+    pub api: ApiConfig,
+}
+
+impl Default for ServerConfig {
+    fn default() -> Self {
+        Self {
+            bind: IpAddr::V4(Ipv4Addr::UNSPECIFIED),
+            port: 8080,
+            shutdown_timeout: Duration::from_secs(10),
+            api: ApiConfig::default(),
+        }
+    }
+}
+
+impl ServerConfig {
+    /// Defaults overridden by `FX_STORE_BIND`, `FX_STORE_PORT`, `FX_STORE_SHUTDOWN_SECS`,
+    /// `FX_STORE_WRITE_TOKEN`, `FX_STORE_MAX_QUERY_DAYS`, `FX_STORE_RESPONSE_CACHE_MB`,
+    /// `FX_STORE_RESPONSE_CACHE_TTL_SECS`, `FX_STORE_CORS_ORIGINS` (comma separated),
+    /// `FX_STORE_CORS_CREDENTIALS` and the access settings: `FX_STORE_API_KEYS` (comma
+    /// separated) or `FX_STORE_API_KEYS_FILE`, `FX_STORE_KEYS_FOR_READS`, `FX_STORE_KEY_RATE`
+    /// and `FX_STORE_ANON_RATE` (`RATE[:BURST]` per second)
+    pub fn from_env() -> anyhow::Result<Self> {
+        let mut config = Self::default();
+        if let Ok(bind) = std::env::var("FX_STORE_BIND") {
+            config.bind = bind
+                .parse()
+                .with_context(|| format!("invalid FX_STORE_BIND: {}", bind))?;
+        }
+        if let Ok(port) = std::env::var("FX_STORE_PORT") {
+            config.port = port
+                .parse()
+                .with_context(|| format!("invalid FX_STORE_PORT: {}", port))?;
+        }
+        if let Ok(secs) = std::env::var("FX_STORE_SHUTDOWN_SECS") {
+            let secs: u64 = secs
+                .parse()
+                .with_context(|| format!("invalid FX_STORE_SHUTDOWN_SECS: {}", secs))?;
+            config.shutdown_timeout = Duration::from_secs(secs);
+        }
+        config.api.write_token = std::env::var("FX_STORE_WRITE_TOKEN").ok();
+        if let Ok(days) = std::env::var("FX_STORE_MAX_QUERY_DAYS") {
+            let days: u64 = days
+                .parse()
+                .with_context(|| format!("invalid FX_STORE_MAX_QUERY_DAYS: {}", days))?;
+            config.api.max_query_span = Duration::from_secs(days * 86_400);
+        }
+        if let Ok(mb) = std::env::var("FX_STORE_RESPONSE_CACHE_MB") {
+            let mb: usize = mb
+                .parse()
+                .with_context(|| format!("invalid FX_STORE_RESPONSE_CACHE_MB: {}", mb))?;
+            config.api.response_cache_bytes = mb << 20;
+        }
+        if let Ok(secs) = std::env::var("FX_STORE_RESPONSE_CACHE_TTL_SECS") {
+            let secs: u64 = secs
+                .parse()
+                .with_context(|| format!("invalid FX_STORE_RESPONSE_CACHE_TTL_SECS: {}", secs))?;
+            config.api.response_cache_live_ttl = Duration::from_secs(secs);
+        }
+        // Checked with the rest of the CORS settings by `create_app_with`/`start_server`
+        if let Ok(origins) = std::env::var("FX_STORE_CORS_ORIGINS") {
+            config.api.allowed_origins.extend(
+                origins
+                    .split(',')
+                    .map(str::trim)
+                    .filter(|origin| !origin.is_empty())
+                    .map(str::to_string),
+            );
+        }
+        if let Ok(flag) = std::env::var("FX_STORE_CORS_CREDENTIALS") {
+            config.api.allow_credentials = matches!(flag.trim(), "1" | "true" | "yes");
+        }
+
+        let access = &mut config.api.access;
+        if let Ok(keys) = std::env::var("FX_STORE_API_KEYS") {
+            access.api_keys.extend(
+                keys.split(',')
+                    .map(str::trim)
+                    .filter(|key| !key.is_empty())
+                    .map(str::to_string),
+            );
+        }
+        if let Ok(path) = std::env::var("FX_STORE_API_KEYS_FILE") {
+            let keys = AccessConfig::read_keys(path.as_ref())
+                .with_context(|| format!("failed to read FX_STORE_API_KEYS_FILE: {}", path))?;
+            access.api_keys.extend(keys);
+        }
+        if let Ok(flag) = std::env::var("FX_STORE_KEYS_FOR_READS") {
+            access.keys_for_reads = matches!(flag.trim(), "1" | "true" | "yes");
+        }
+        for (key, rate) in [
+            ("FX_STORE_KEY_RATE", &mut access.key_rate),
+            ("FX_STORE_ANON_RATE", &mut access.anonymous_rate),
+        ] {
+            if let Ok(value) = std::env::var(key) {
+                *rate = Some(
+                    value
+                        .parse()
+                        .map_err(|e| anyhow::anyhow!("invalid {}: {}", key, e))?,
+                );
+            }
+        }
+        Ok(config)
+    }
+}
+
+#[derive(Clone)]
+pub struct AppState {
+    pub store: SharedStore,
+    pub config: Arc<ApiConfig>,
+    pub metrics: Arc<ApiMetrics>,
+    pub access: Arc<AccessControl>,
+    pub shutdown: ShutdownSignal,
+}
+
+/// Fires once the server starts shutting down, so open event streams can end and let
+/// the drain finish
+#[derive(Clone)]
+pub struct ShutdownSignal(Arc<tokio::sync::watch::Sender<bool>>);
+
+impl Default for ShutdownSignal {
+    fn default() -> Self {
+        Self(Arc::new(tokio::sync::watch::channel(false).0))
+    }
+}
+
+impl ShutdownSignal {
+    pub fn trigger(&self) {
+        self.0.send_replace(true);
+    }
+
+    /// Resolves once `trigger` has been called (immediately if it already was)
+    pub async fn triggered(&self) {
+        self.0.subscribe().wait_for(|&down| down).await.ok();
+    }
+}
+
+impl FromRef<AppState> for SharedStore {
+    fn from_ref(state: &AppState) -> Self {
+        Arc::clone(&state.store)
+    }
+}
+
+impl FromRef<AppState> for Arc<ApiConfig> {
+    fn from_ref(state: &AppState) -> Self {
+        Arc::clone(&state.config)
+    }
+}
+
+impl FromRef<AppState> for Arc<ApiMetrics> {
+    fn from_ref(state: &AppState) -> Self {
+        Arc::clone(&state.metrics)
+    }
+}
+
+impl FromRef<AppState> for ShutdownSignal {
+    fn from_ref(state: &AppState) -> Self {
+        state.shutdown.clone()
+    }
+}
+
+#[derive(Serialize, ToSchema)]
 pub struct PriceResponse {
     pub symbol: String,
     pub timestamp: i64,
@@ -23,152 +266,5342 @@ pub struct PriceResponse {
     pub high: f64,
     pub low: f64,
     pub close: f64,
-    pub volume: u32,
+    pub volume: u64,
+    /// Average ask - bid over the bar, omitted when the source had no quotes
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub spread: Option<f64>,
 }
 
-#[derive(Serialize)]
+#[derive(Serialize, ToSchema)]
 pub struct SymbolsResponse {
     pub symbols: Vec<String>,
 }
 
-#[derive(Deserialize)]
+/// `GET /symbols/{symbol}`; the time bounds are epoch seconds, null without bars
+#[derive(Serialize, ToSchema)]
+pub struct SymbolInfoResponse {
+    pub id: u16,
+    pub name: String,
+    pub base: String,
+    pub quote: String,
+    pub scale: u32,
+    pub first_ts: Option<i64>,
+    pub last_ts: Option<i64>,
+    pub block_count: usize,
+}
+
+#[derive(Deserialize, IntoParams)]
+#[into_params(parameter_in = Query)]
+pub struct PricesQuery {
+    /// Comma-separated symbol list, e.g. `EURUSD,XAUUSD`
+    pub symbols: String,
+}
+
+#[derive(Deserialize, IntoParams)]
+#[into_params(parameter_in = Query)]
 pub struct HistoryQuery {
     pub start: Option<String>,
     pub end: Option<String>,
     pub limit: Option<usize>,
+    pub interval: Option<String>,
+    /// IANA zone for `interval` bucket boundaries, e.g. `America/New_York` (default UTC)
+    pub align_tz: Option<String>,
+    /// Shift of bucket boundaries from local midnight, e.g. `17h` or `-30m`
+    pub session_offset: Option<String>,
+    /// `heikin_ashi` or `renko` (requires `brick`)
+    pub transform: Option<String>,
+    /// Renko brick size in units of the symbol's price scale
+    pub brick: Option<u32>,
+    /// Opaque `next_cursor` from a previous page
+    pub cursor: Option<String>,
+    /// Reduce the range to at most this many bars (3 to 10000), see `reduced_by`
+    pub max_points: Option<usize>,
+    /// `json` (default), `csv`, `ndjson` or `parquet`; overrides the `Accept` header
+    pub format: Option<String>,
+    /// Derive the symbol from two stored pairs sharing one currency, e.g. `EURUSD,USDJPY`
+    /// for `EURJPY`. Only minutes present in both legs produce a bar
+    pub synthesize: Option<String>,
+}
+
+/// One page of `/history/{symbol}`, oldest first
+#[derive(Serialize, ToSchema)]
+pub struct HistoryPage {
+    pub data: HistoryData,
+    /// Pass as `cursor` to fetch the bars preceding this page
+    pub next_cursor: Option<String>,
+    /// How the bars were cut down to `max_points`: `lttb`, or the coarser interval used
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub reduced_by: Option<String>,
+    /// Days left out because their stored block is corrupt (`YYYY-MM-DD`)
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub corrupt_days: Vec<String>,
+}
+
+#[derive(Deserialize, IntoParams)]
+#[into_params(parameter_in = Query)]
+pub struct MultiHistoryQuery {
+    /// Comma-separated symbol list, e.g. `EURUSD,XAUUSD`
+    pub symbols: String,
+    pub start: Option<String>,
+    pub end: Option<String>,
+    pub interval: Option<String>,
+    /// IANA zone for `interval` bucket boundaries, e.g. `America/New_York` (default UTC)
+    pub align_tz: Option<String>,
+    /// Shift of bucket boundaries from local midnight, e.g. `17h` or `-30m`
+    pub session_offset: Option<String>,
+    pub transform: Option<String>,
+    pub brick: Option<u32>,
+}
+
+#[derive(Serialize, ToSchema)]
+pub struct RenkoResponse {
+    pub symbol: String,
+    pub ts_open: i64,
+    pub ts_close: i64,
+    pub open: f64,
+    pub close: f64,
+    /// `up` or `down`
+    #[schema(value_type = String)]
+    pub direction: RenkoDirection,
+}
+
+/// Candles, or bricks when `transform=renko`
+#[derive(Serialize, ToSchema)]
+#[serde(untagged)]
+pub enum HistoryData {
+    Candles(Vec<PriceResponse>),
+    Bricks(Vec<RenkoResponse>),
+}
+
+/// Per-symbol entry of a multi-symbol history response
+#[derive(Serialize, ToSchema)]
+#[serde(untagged)]
+pub enum SymbolHistory {
+    Data(HistoryData),
+    Error { error: String },
+}
+
+/// Server-side candle transform requested via `transform=`
+#[derive(Clone, Copy)]
+enum Transform {
+    HeikinAshi,
+    Renko { brick: u32 },
+}
+
+#[derive(Deserialize, IntoParams)]
+#[into_params(parameter_in = Query)]
+pub struct CorrelationQuery {
+    pub a: String,
+    pub b: String,
+    pub window: Option<usize>,
+    pub start: Option<String>,
+    pub end: Option<String>,
+}
+
+#[derive(Serialize, ToSchema)]
+pub struct CorrelationPoint {
+    pub timestamp: i64,
+    /// `null` when either series is flat over the window
+    pub value: Option<f64>,
+}
+
+#[derive(Serialize, ToSchema)]
+pub struct CorrelationResponse {
+    pub a: String,
+    pub b: String,
+    pub window: usize,
+    pub points: Vec<CorrelationPoint>,
+}
+
+#[derive(Deserialize, IntoParams)]
+#[into_params(parameter_in = Query)]
+pub struct IndicatorQuery {
+    /// sma, ema, rsi, macd, bollinger, atr
+    pub name: String,
+    pub period: Option<usize>,
+    /// open, high, low, close (default close)
+    pub field: Option<String>,
+    pub fast: Option<usize>,
+    pub slow: Option<usize>,
+    pub signal: Option<usize>,
+    /// Bollinger band width in standard deviations
+    pub k: Option<f64>,
+    pub start: Option<String>,
+    pub end: Option<String>,
+    pub interval: Option<String>,
+}
+
+#[derive(Serialize, ToSchema)]
+pub struct IndicatorPoint {
+    pub timestamp: i64,
+    pub value: f64,
+}
+
+/// A single series, or named series for MACD/Bollinger
+#[derive(Serialize, ToSchema)]
+#[serde(untagged)]
+pub enum IndicatorResponse {
+    Series(Vec<IndicatorPoint>),
+    #[schema(value_type = BTreeMap<String, Vec<IndicatorPoint>>)]
+    Multi(BTreeMap<&'static str, Vec<IndicatorPoint>>),
+}
+
+/// One bar of a `POST /bars` body (`ts` in epoch seconds)
+#[derive(Deserialize, ToSchema)]
+pub struct BarInput {
+    pub ts: i64,
+    pub open: f64,
+    pub high: f64,
+    pub low: f64,
+    pub close: f64,
+    #[serde(default)]
+    pub volume: u32,
+}
+
+/// One quote of a `POST /ticks` body (`ts_ms` in epoch milliseconds)
+#[derive(Deserialize, ToSchema)]
+pub struct TickInput {
+    pub ts_ms: i64,
+    pub bid: f64,
+    pub ask: f64,
+    #[serde(default)]
+    pub volume: u32,
+}
+
+/// Per-item outcome of an ingestion request, in input order
+#[derive(Serialize, ToSchema)]
+pub struct IngestResult {
+    pub status: &'static str,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub reason: Option<String>,
+}
+
+impl IngestResult {
+    fn from_check(check: Result<(), String>) -> Self {
+        match check {
+            Ok(()) => Self {
+                status: "accepted",
+                reason: None,
+            },
+            Err(reason) => Self {
+                status: "rejected",
+                reason: Some(reason),
+            },
+        }
+    }
+}
+
+/// Response of `POST /ingest`: counts plus the reason for each rejected row
+#[derive(Default, Serialize, ToSchema)]
+pub struct IngestSummary {
+    pub accepted: usize,
+    pub rejected: usize,
+    pub errors: Vec<RejectedRow>,
+}
+
+/// A rejected `POST /ingest` row (`index` is its position in the body)
+#[derive(Serialize, ToSchema)]
+pub struct RejectedRow {
+    pub index: usize,
+    pub reason: String,
+}
+
+/// Body of every error response
+#[derive(Serialize, ToSchema)]
+pub struct ErrorResponse {
+    /// Stable machine-readable code, e.g. `symbol_not_found`
+    pub error: &'static str,
+    pub message: String,
+    /// Code-specific fields; always an object
+    #[schema(value_type = Object)]
+    pub detail: serde_json::Value,
+}
+
+/// Handler failure, rendered as an `ErrorResponse` with a matching status
+#[derive(Debug)]
+pub enum ApiError {
+    /// 404: the symbol was never registered
+    SymbolNotFound(String),
+    /// 404: the symbol has no bar at or before `ts` (nanoseconds)
+    NoData { symbol: String, ts: u64 },
+    /// 400: a date parameter could not be parsed
+    InvalidDate { param: &'static str, value: String },
+    /// 400: `start` is after `end` (nanoseconds)
+    InvalidRange { start: u64, end: u64 },
+    /// 400: `end - start` is longer than `ApiConfig::max_query_span` (nanoseconds)
+    SpanTooLarge { span: u64, max: u64 },
+    /// 400: any other malformed parameter
+    InvalidParameter {
+        param: &'static str,
+        message: String,
+    },
+    /// 422: `limit` must be positive
+    InvalidLimit(usize),
+    /// 413: the range would produce more than `max` points
+    RangeTooLarge { points: u64, max: usize },
+    /// 401: missing or wrong write token or API key
+    Unauthorized,
+    /// 403: no write token is configured
+    WritesDisabled,
+    /// 429: the client's rate limit is used up; retry after the given time
+    RateLimited { retry_after: Duration },
+    /// 503: the store is shedding load (compression queue full); retry later
+    Unavailable(String),
+    /// 500: the cause is logged under an id and not sent to the client
+    Internal(String),
+}
+
+impl ApiError {
+    fn invalid(param: &'static str, message: impl Into<String>) -> Self {
+        Self::InvalidParameter {
+            param,
+            message: message.into(),
+        }
+    }
+
+    pub fn status(&self) -> StatusCode {
+        match self {
+            Self::SymbolNotFound(_) | Self::NoData { .. } => StatusCode::NOT_FOUND,
+            Self::InvalidDate { .. }
+            | Self::InvalidRange { .. }
+            | Self::SpanTooLarge { .. }
+            | Self::InvalidParameter { .. } => StatusCode::BAD_REQUEST,
+            Self::InvalidLimit(_) => StatusCode::UNPROCESSABLE_ENTITY,
+            Self::RangeTooLarge { .. } => StatusCode::PAYLOAD_TOO_LARGE,
+            Self::Unauthorized => StatusCode::UNAUTHORIZED,
+            Self::WritesDisabled => StatusCode::FORBIDDEN,
+            Self::RateLimited { .. } => StatusCode::TOO_MANY_REQUESTS,
+            Self::Unavailable(_) => StatusCode::SERVICE_UNAVAILABLE,
+            Self::Internal(_) => StatusCode::INTERNAL_SERVER_ERROR,
+        }
+    }
+
+    pub fn code(&self) -> &'static str {
+        match self {
+            Self::SymbolNotFound(_) => "symbol_not_found",
+            Self::NoData { .. } => "no_data",
+            Self::InvalidDate { .. } => "invalid_date",
+            Self::InvalidRange { .. } => "invalid_range",
+            Self::SpanTooLarge { .. } => "span_too_large",
+            Self::InvalidParameter { .. } => "invalid_parameter",
+            Self::InvalidLimit(_) => "invalid_limit",
+            Self::RangeTooLarge { .. } => "range_too_large",
+            Self::Unauthorized => "unauthorized",
+            Self::WritesDisabled => "writes_disabled",
+            Self::RateLimited { .. } => "rate_limited",
+            Self::Unavailable(_) => "unavailable",
+            Self::Internal(_) => "internal",
+        }
+    }
+}
+
+impl std::fmt::Display for ApiError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::SymbolNotFound(symbol) => write!(f, "unknown symbol: {}", symbol),
+            Self::NoData { symbol, ts } => {
+                write!(f, "{} has no bar at or before {}", symbol, format_ts(*ts))
+            }
+            Self::InvalidDate { param, value } => {
+                write!(f, "{} is not a date or RFC 3339 datetime: {}", param, value)
+            }
+            Self::InvalidRange { start, end } => write!(
+                f,
+                "start ({}) is not before end ({})",
+                format_ts(*start),
+                format_ts(*end)
+            ),
+            Self::SpanTooLarge { span, max } => write!(
+                f,
+                "range spans {} days (max {}); split it into smaller queries",
+                span.div_ceil(NANOS_PER_DAY),
+                max / NANOS_PER_DAY
+            ),
+            Self::InvalidParameter { message, .. } => f.write_str(message),
+            Self::InvalidLimit(limit) => write!(f, "limit must be positive, got {}", limit),
+            Self::RangeTooLarge { points, max } => write!(
+                f,
+                "range spans ~{} bars (max {}); narrow the range or use a coarser interval",
+                points, max
+            ),
+            Self::Unauthorized => f.write_str("invalid or missing token"),
+            Self::WritesDisabled => f.write_str("writes are disabled"),
+            Self::RateLimited { retry_after } => write!(
+                f,
+                "rate limit exceeded; retry in {}s",
+                retry_after_secs(*retry_after)
+            ),
+            Self::Unavailable(reason) => write!(f, "service unavailable: {}", reason),
+            Self::Internal(_) => f.write_str("internal error"),
+        }
+    }
+}
+
+impl From<FxStoreError> for ApiError {
+    fn from(e: FxStoreError) -> Self {
+        match e {
+            FxStoreError::UnknownSymbol(symbol) => Self::SymbolNotFound(symbol),
+            FxStoreError::Query(e) => e.into(),
+            e @ (FxStoreError::Parse { .. } | FxStoreError::PriceOverflow { .. }) => {
+                Self::invalid("data", e.to_string())
+            }
+            e @ (FxStoreError::SymbolExists(_) | FxStoreError::SymbolLimit(_)) => {
+                Self::invalid("symbol", e.to_string())
+            }
+            e @ FxStoreError::QueueFull => Self::Unavailable(e.to_string()),
+            e @ FxStoreError::TimestampOutOfRange(_) => Self::invalid("ts", e.to_string()),
+            other => Self::Internal(other.to_string()),
+        }
+    }
+}
+
+impl From<QueryError> for ApiError {
+    fn from(e: QueryError) -> Self {
+        match e {
+            QueryError::InvertedRange { start, end } => Self::InvalidRange { start, end },
+            QueryError::RangeTooLarge { span, max } => Self::SpanTooLarge { span, max },
+        }
+    }
+}
+
+impl From<tokio::task::JoinError> for ApiError {
+    fn from(e: tokio::task::JoinError) -> Self {
+        Self::Internal(e.to_string())
+    }
+}
+
+impl IntoResponse for ApiError {
+    fn into_response(self) -> Response {
+        let detail = match &self {
+            Self::SymbolNotFound(symbol) => serde_json::json!({ "symbol": symbol }),
+            Self::NoData { symbol, ts } => {
+                serde_json::json!({ "symbol": symbol, "ts": format_ts(*ts) })
+            }
+            Self::InvalidDate { param, value } => {
+                serde_json::json!({ "param": param, "value": value })
+            }
+            Self::InvalidRange { start, end } => {
+                serde_json::json!({ "start": format_ts(*start), "end": format_ts(*end) })
+            }
+            Self::SpanTooLarge { span, max } => serde_json::json!({
+                "span_days": span.div_ceil(NANOS_PER_DAY),
+                "max_days": max / NANOS_PER_DAY,
+            }),
+            Self::InvalidParameter { param, .. } => serde_json::json!({ "param": param }),
+            Self::InvalidLimit(limit) => serde_json::json!({ "limit": limit }),
+            Self::RangeTooLarge { points, max } => {
+                serde_json::json!({ "points": points, "max": max })
+            }
+            Self::Unauthorized | Self::WritesDisabled | Self::Unavailable(_) => {
+                serde_json::json!({})
+            }
+            Self::RateLimited { retry_after } => {
+                serde_json::json!({ "retry_after_secs": retry_after_secs(*retry_after) })
+            }
+            Self::Internal(cause) => {
+                let id = next_error_id();
+                tracing::error!(%id, %cause, "internal error");
+                serde_json::json!({ "id": id })
+            }
+        };
+        let body = ErrorResponse {
+            error: self.code(),
+            message: self.to_string(),
+            detail,
+        };
+        let mut response = (self.status(), Json(body)).into_response();
+        if let Self::RateLimited { retry_after } = self {
+            response
+                .headers_mut()
+                .insert(header::RETRY_AFTER, retry_after_secs(retry_after).into());
+        }
+        response
+    }
+}
+
+/// Whole seconds for `Retry-After`, never 0
+fn retry_after_secs(retry_after: Duration) -> u64 {
+    retry_after.as_secs_f64().ceil().max(1.0) as u64
+}
+
+/// Opaque id tying a 500 response to its log line
+fn next_error_id() -> String {
+    static COUNTER: AtomicU64 = AtomicU64::new(0);
+    let n = COUNTER.fetch_add(1, Ordering::Relaxed);
+    let now = Utc::now().timestamp_nanos_opt().unwrap_or_default();
+    let seed = [now.to_le_bytes(), n.to_le_bytes()].concat();
+    format!("{:016x}", xxhash_rust::xxh64::xxh64(&seed, 0))
+}
+
+/// Nanosecond timestamp as RFC 3339, for error messages
+fn format_ts(ts: u64) -> String {
+    ts_to_datetime(ts).to_rfc3339()
+}
+
+/// Block date (`YYYYMMDD`) as `YYYY-MM-DD`
+fn format_date(date: u32) -> String {
+    format!(
+        "{:04}-{:02}-{:02}",
+        date / 10_000,
+        date / 100 % 100,
+        date % 100
+    )
+}
+
+#[derive(Deserialize, IntoParams)]
+#[into_params(parameter_in = Query)]
+pub struct AsofQuery {
+    pub ts: String,
+}
+
+#[derive(Deserialize, IntoParams)]
+#[into_params(parameter_in = Query)]
+pub struct CountQuery {
+    pub start: Option<String>,
+    pub end: Option<String>,
+}
+
+#[derive(Serialize, ToSchema)]
+pub struct CountResponse {
+    pub count: u64,
+}
+
+#[derive(Deserialize, IntoParams)]
+#[into_params(parameter_in = Query)]
+pub struct ScrubQuery {
+    /// Delete corrupt blocks so their days can be imported again
+    pub repair: Option<bool>,
+}
+
+/// Result of `POST /admin/scrub`
+#[derive(Serialize, ToSchema)]
+pub struct ScrubResponse {
+    pub blocks_checked: usize,
+    pub corrupt: Vec<CorruptBlockEntry>,
+    /// Blocks deleted by `repair=true`
+    pub removed: usize,
+}
+
+/// A block that failed its checksum or could not be decompressed
+#[derive(Serialize, ToSchema)]
+pub struct CorruptBlockEntry {
+    pub symbol: String,
+    /// `YYYY-MM-DD`
+    pub date: String,
+    pub reason: String,
+}
+
+#[derive(Deserialize, IntoParams)]
+#[into_params(parameter_in = Query)]
+pub struct HealthQuery {
+    /// Include the stored bar count per symbol
+    pub detail: Option<bool>,
+}
+
+#[derive(Serialize, ToSchema)]
+pub struct HealthResponse {
+    pub status: String,
+    pub service: String,
+    /// Same as `/ready`: initial loading and imports are done
+    pub ready: bool,
+    pub symbols_loaded: usize,
+    pub records_loaded: u64,
+    pub uptime_seconds: u64,
+    /// Day batches waiting for the compression workers
+    pub pending_compress_queue: u64,
+    /// Bars stored per symbol, with `detail=true`
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub symbols: Option<BTreeMap<String, u64>>,
+}
+
+#[derive(Deserialize, IntoParams)]
+#[into_params(parameter_in = Query)]
+pub struct AggregateQuery {
+    pub start: Option<String>,
+    pub end: Option<String>,
+    /// `open`, `high`, `low`, `close` (default) or `volume`
+    pub field: Option<String>,
+}
+
+#[derive(Deserialize, IntoParams)]
+#[into_params(parameter_in = Query)]
+pub struct ChartQuery {
+    pub start: Option<String>,
+    pub end: Option<String>,
+    /// Upper bound on returned bars (default 1500, at most 10000)
+    pub max_points: Option<usize>,
+}
+
+#[derive(Deserialize, IntoParams)]
+#[into_params(parameter_in = Query)]
+pub struct BasketQuery {
+    /// Comma-separated `SYMBOL:weight` list, e.g. `EURUSD:-0.5,USDJPY:0.01` (weight defaults to 1)
+    pub components: String,
+    pub start: Option<String>,
+    pub end: Option<String>,
+    /// Carry a component's last close over gaps up to this long, e.g. `5m`. Without it,
+    /// minutes where any component is missing are skipped
+    pub max_gap: Option<String>,
+    /// Symbol reported on the bars (default `BASKET`)
+    pub name: Option<String>,
+}
+
+#[derive(Serialize, ToSchema)]
+pub struct BasketComponent {
+    pub symbol: String,
+    pub weight: f64,
 }
 
-impl From<&OHLCV> for PriceResponse {
-    fn from(ohlcv: &OHLCV) -> Self {
+/// Weighted sum of the component closes per minute, as flat bars at 5 decimals
+#[derive(Serialize, ToSchema)]
+pub struct BasketResponse {
+    pub components: Vec<BasketComponent>,
+    pub bars: Vec<PriceResponse>,
+}
+
+/// Bars resampled so the range fits in `max_points`
+#[derive(Serialize, ToSchema)]
+pub struct ChartResponse {
+    pub symbol: String,
+    /// Chosen bar size, e.g. `15m`, `4h`, `1d`
+    pub timeframe: String,
+    pub interval_secs: u64,
+    pub bars: Vec<PriceResponse>,
+}
+
+/// Reduction of one field over a range; min/max/avg are null when the range is empty
+#[derive(Serialize, ToSchema)]
+pub struct AggregateResponse {
+    pub symbol: String,
+    pub field: String,
+    pub min: Option<f64>,
+    pub max: Option<f64>,
+    pub avg: Option<f64>,
+    pub count: u64,
+    pub sum: f64,
+}
+
+impl PriceResponse {
+    /// De-scale a stored bar, named after its `symbol_id` rather than the requested name
+    /// (so aliases and multi-symbol results report the symbol the bar actually belongs to)
+    fn from_record(store: &FxStore, ohlcv: &OHLCV, scale: u32) -> Self {
+        let symbol = store.symbol_name(ohlcv.symbol_id).unwrap_or_default();
+        Self::new(symbol, ohlcv, scale)
+    }
+
+    /// De-scale a bar with its symbol's `scale`
+    fn new(symbol: String, ohlcv: &OHLCV, scale: u32) -> Self {
+        let price = |raw: u32| raw as f64 / scale as f64;
         Self {
-            symbol: "".to_string(), // Will be filled by handler
+            symbol,
             timestamp: (ohlcv.ts / 1_000_000_000) as i64, // Convert to seconds
-            open: ohlcv.open as f64 / 100000.0,
-            high: ohlcv.high as f64 / 100000.0,
-            low: ohlcv.low as f64 / 100000.0,
-            close: ohlcv.close as f64 / 100000.0,
-            volume: ohlcv.volume,
+            open: price(ohlcv.open),
+            high: price(ohlcv.high),
+            low: price(ohlcv.low),
+            close: price(ohlcv.close),
+            volume: ohlcv.total_volume(),
+            spread: (ohlcv.spread != 0).then(|| price(u32::from(ohlcv.spread))),
         }
     }
 }
 
+/// OpenAPI document, built from the `utoipa::path` annotations on the handlers and the
+/// schema derives on the request/response types, so it can't drift from the routes
+#[derive(OpenApi)]
+#[openapi(
+    info(
+        title = "fx-store",
+        description = "Minute-bar FX history. With API keys configured, any route may also \
+                       answer 401, and rate-limited clients get 429 with `Retry-After`."
+    ),
+    paths(
+        get_symbols,
+        get_symbol_info,
+        get_current_price,
+        get_prices,
+        get_history_multi,
+        get_history,
+        get_asof,
+        get_count,
+        get_correlation,
+        get_basket,
+        get_indicator,
+        get_aggregate,
+        get_chart,
+        stream_bars,
+        post_bars,
+        post_ingest,
+        post_ticks,
+        post_scrub,
+        health_check,
+        ready_check,
+        get_metrics,
+    ),
+    components(schemas(
+        PriceResponse,
+        SymbolsResponse,
+        SymbolInfoResponse,
+        HistoryPage,
+        HistoryData,
+        SymbolHistory,
+        RenkoResponse,
+        CorrelationPoint,
+        CorrelationResponse,
+        BasketComponent,
+        BasketResponse,
+        IndicatorPoint,
+        IndicatorResponse,
+        AggregateResponse,
+        ChartResponse,
+        CountResponse,
+        ScrubResponse,
+        CorruptBlockEntry,
+        HealthResponse,
+        ReadyResponse,
+        BarInput,
+        TickInput,
+        IngestResult,
+        IngestSummary,
+        RejectedRow,
+        ErrorResponse,
+    )),
+    modifiers(&BearerAuth)
+)]
+pub struct ApiDoc;
+
+/// Registers the `bearer` scheme used by the write routes (write token or API key)
+struct BearerAuth;
+
+impl Modify for BearerAuth {
+    fn modify(&self, openapi: &mut utoipa::openapi::OpenApi) {
+        let components = openapi.components.get_or_insert_with(Default::default);
+        components.add_security_scheme(
+            "bearer",
+            SecurityScheme::Http(HttpBuilder::new().scheme(HttpAuthScheme::Bearer).build()),
+        );
+    }
+}
+
+/// Swagger UI for `/docs`; the assets come from a CDN so the binary stays self-contained
+const SWAGGER_UI_HTML: &str = r##"<!DOCTYPE html>
+<html lang="en">
+<head>
+  <meta charset="utf-8">
+  <title>fx-store API</title>
+  <link rel="stylesheet" href="https://unpkg.com/swagger-ui-dist@5/swagger-ui.css">
+</head>
+<body>
+  <div id="swagger-ui"></div>
+  <script src="https://unpkg.com/swagger-ui-dist@5/swagger-ui-bundle.js"></script>
+  <script>
+    window.ui = SwaggerUIBundle({ url: "openapi.json", dom_id: "#swagger-ui" });
+  </script>
+</body>
+</html>
+"##;
+
+/// Routes that skip API keys and rate limits
+const OPEN_ROUTES: [&str; 4] = ["/health", "/ready", "/openapi.json", "/docs"];
+
 pub fn create_app(store: SharedStore) -> Router {
-    Router::new()
+    create_app_with(store, ApiConfig::default()).expect("default config is valid")
+}
+
+/// Fails when the CORS settings are inconsistent (see `cors_layer`)
+pub fn create_app_with(store: SharedStore, config: ApiConfig) -> anyhow::Result<Router> {
+    build_router(store, config, ShutdownSignal::default())
+}
+
+fn build_router(
+    store: SharedStore,
+    config: ApiConfig,
+    shutdown: ShutdownSignal,
+) -> anyhow::Result<Router> {
+    let body_limit = DefaultBodyLimit::max(config.max_body_bytes);
+    let cors = cors_layer(&config)?;
+    let access = Arc::new(AccessControl::new(config.access.clone()));
+    let cache = (config.response_cache_bytes > 0).then(|| {
+        Arc::new(ResponseCache::new(
+            config.response_cache_bytes,
+            config.response_cache_live_ttl,
+        ))
+    });
+    let state = AppState {
+        store,
+        config: Arc::new(config),
+        metrics: Arc::new(ApiMetrics::default()),
+        access: Arc::clone(&access),
+        shutdown,
+    };
+
+    let mut router = Router::new()
         .route("/symbols", get(get_symbols))
+        .route("/symbols/:symbol", get(get_symbol_info))
         .route("/price/:symbol", get(get_current_price))
+        .route("/prices", get(get_prices))
+        .route("/history", get(get_history_multi))
         .route("/history/:symbol", get(get_history))
+        .route("/asof/:symbol", get(get_asof))
+        .route("/count/:symbol", get(get_count))
+        .route("/correlation", get(get_correlation))
+        .route("/basket", get(get_basket))
+        .route("/indicator/:symbol", get(get_indicator))
+        .route("/aggregate/:symbol", get(get_aggregate))
+        .route("/chart/:symbol", get(get_chart))
+        .route("/stream/:symbol", get(stream_bars))
+        .route("/bars/:symbol", post(post_bars))
+        .route("/ingest/:symbol", post(post_ingest))
+        .route("/ticks/:symbol", post(post_ticks))
+        .route("/admin/scrub", post(post_scrub))
         .route("/health", get(health_check))
-        .layer(CorsLayer::permissive())
-        .with_state(store)
+        .route("/ready", get(ready_check))
+        .route("/metrics", get(get_metrics))
+        .route("/openapi.json", get(get_openapi))
+        .route("/docs", get(get_docs));
+    // Inside the access check, so cached answers are still rate limited
+    if let Some(cache) = cache {
+        router = router.route_layer(middleware::from_fn_with_state(
+            (cache, Arc::clone(&state.store), Arc::clone(&state.metrics)),
+            cache_responses,
+        ));
+    }
+    // Innermost so rejected requests still show up in metrics and traces
+    if access.config.is_enabled() {
+        router = router.route_layer(middleware::from_fn_with_state(access, check_access));
+    }
+    Ok(router
+        .route_layer(middleware::from_fn_with_state(
+            Arc::clone(&state.metrics),
+            track_requests,
+        ))
+        .route_layer(
+            TraceLayer::new_for_http()
+                .make_span_with(request_span)
+                .on_response(|response: &Response, _: Duration, span: &tracing::Span| {
+                    span.record("status", response.status().as_u16());
+                }),
+        )
+        .layer(body_limit)
+        .layer(cors)
+        .with_state(state))
+}
+
+/// CORS for `allowed_origins`, or any origin when none are configured (development)
+///
+/// Requests from other origins are still served, but without `Access-Control-Allow-Origin`
+/// the browser keeps the response from the page. Preflights answer for the methods and
+/// headers the routes use, and the paging headers are exposed to scripts.
+fn cors_layer(config: &ApiConfig) -> anyhow::Result<CorsLayer> {
+    if config.allowed_origins.is_empty() {
+        // The permissive layer answers `*`, which browsers never pair with credentials
+        if config.allow_credentials {
+            anyhow::bail!(
+                "CORS allow_credentials needs allowed_origins \
+                 (FX_STORE_CORS_CREDENTIALS needs FX_STORE_CORS_ORIGINS)"
+            );
+        }
+        return Ok(CorsLayer::permissive());
+    }
+    let origins = config
+        .allowed_origins
+        .iter()
+        .map(|origin| {
+            // Browsers send the origin without a trailing slash
+            HeaderValue::from_str(origin.trim_end_matches('/'))
+                .with_context(|| format!("invalid CORS origin: {:?}", origin))
+        })
+        .collect::<anyhow::Result<Vec<_>>>()?;
+    Ok(CorsLayer::new()
+        .allow_origin(origins)
+        .allow_methods([Method::GET, Method::POST])
+        .allow_headers([
+            header::ACCEPT,
+            header::AUTHORIZATION,
+            header::CONTENT_TYPE,
+            HeaderName::from_static("last-event-id"),
+        ])
+        .expose_headers([
+            header::CONTENT_DISPOSITION,
+            header::RETRY_AFTER,
+            HeaderName::from_static("x-next-cursor"),
+            HeaderName::from_static("x-reduced-by"),
+            HeaderName::from_static("x-corrupt-days"),
+        ])
+        .allow_credentials(config.allow_credentials))
 }
 
 // GET /symbols - List all available symbols
-async fn get_symbols(State(store): State<SharedStore>) -> Result<Json<SymbolsResponse>, StatusCode> {
+#[utoipa::path(
+    get,
+    path = "/symbols",
+    responses(
+        (status = 200, description = "Registered symbols", body = SymbolsResponse),
+    )
+)]
+async fn get_symbols(State(store): State<SharedStore>) -> Result<Json<SymbolsResponse>, ApiError> {
     let symbols = store.get_symbols();
     Ok(Json(SymbolsResponse { symbols }))
 }
 
-// GET /price/{symbol} - Get current price for a symbol
+// GET /symbols/{symbol} - Metadata plus the stored date range, so clients know what to query
+#[utoipa::path(
+    get,
+    path = "/symbols/{symbol}",
+    params(
+        ("symbol" = String, Path, description = "Symbol or alias, e.g. `EURUSD`"),
+    ),
+    responses(
+        (status = 200, description = "Symbol metadata and stored range", body = SymbolInfoResponse),
+        (status = 404, description = "Unknown symbol", body = ErrorResponse),
+    )
+)]
+async fn get_symbol_info(
+    State(store): State<SharedStore>,
+    Path(symbol): Path<String>,
+) -> Result<Json<SymbolInfoResponse>, ApiError> {
+    let Some(info) = store.symbol(&symbol) else {
+        return Err(ApiError::SymbolNotFound(symbol));
+    };
+    let bounds = store.time_bounds(&symbol);
+    let secs = |ts: u64| (ts / 1_000_000_000) as i64;
+    Ok(Json(SymbolInfoResponse {
+        id: info.id,
+        name: info.name,
+        base: info.base,
+        quote: info.quote,
+        scale: info.scale,
+        first_ts: bounds.map(|(first, _)| secs(first)),
+        last_ts: bounds.map(|(_, last)| secs(last)),
+        block_count: store.block_count(&symbol),
+    }))
+}
+
+// GET /price/{symbol} - Get current price for a symbol (null if it has no bars yet)
+#[utoipa::path(
+    get,
+    path = "/price/{symbol}",
+    params(
+        ("symbol" = String, Path, description = "Symbol or alias, e.g. `EURUSD`"),
+    ),
+    responses(
+        (status = 200, description = "Latest bar, null before the first one", body = Option<PriceResponse>),
+        (status = 404, description = "Unknown symbol", body = ErrorResponse),
+    )
+)]
 async fn get_current_price(
     State(store): State<SharedStore>,
     Path(symbol): Path<String>,
-) -> Result<Json<PriceResponse>, StatusCode> {
-    let now = Utc::now().timestamp_nanos_opt().unwrap() as u64;
-    let one_hour_ago = now - 3600_000_000_000; // 1 hour in nanoseconds
+) -> Result<Json<Option<PriceResponse>>, ApiError> {
+    if !store.has_symbol(&symbol) {
+        return Err(ApiError::SymbolNotFound(symbol));
+    }
+    let scale = store.price_scale(&symbol);
+    Ok(Json(store.latest(&symbol).map(|latest| {
+        PriceResponse::from_record(&store, &latest, scale)
+    })))
+}
 
-    // Get latest record from last hour
-    let records: Vec<OHLCV> = store
-        .query_range(&symbol, one_hour_ago, now)
+// GET /prices?symbols=EURUSD,XAUUSD - Latest bar per symbol, null for unknown symbols
+#[utoipa::path(
+    get,
+    path = "/prices",
+    params(
+        PricesQuery,
+    ),
+    responses(
+        (status = 200, description = "Latest bar per symbol, null for unknown symbols", body = BTreeMap<String, PriceResponse>),
+        (status = 400, description = "Malformed parameter or range", body = ErrorResponse),
+    )
+)]
+async fn get_prices(
+    State(store): State<SharedStore>,
+    Query(params): Query<PricesQuery>,
+) -> Result<Json<BTreeMap<String, Option<PriceResponse>>>, ApiError> {
+    let prices: BTreeMap<String, Option<PriceResponse>> = split_symbols(&params.symbols)
+        .into_iter()
+        .map(|symbol| {
+            let scale = store.price_scale(&symbol);
+            let price = store
+                .latest(&symbol)
+                .map(|latest| PriceResponse::from_record(&store, &latest, scale));
+            (symbol, price)
+        })
         .collect();
 
-    if let Some(latest) = records.last() {
-        let mut response = PriceResponse::from(latest);
-        response.symbol = symbol;
-        Ok(Json(response))
-    } else {
-        Err(StatusCode::NOT_FOUND)
+    if prices.is_empty() {
+        return Err(ApiError::invalid("symbols", "symbols must not be empty"));
     }
+    Ok(Json(prices))
 }
 
-// GET /history/{symbol}?start=2024-01-01&end=2024-12-31&limit=1000
+// GET /history/{symbol}?start=2024-01-01&end=2024-12-31&limit=1000&cursor=...
+//
+// Pages run backwards: the newest `limit` bars first, then `next_cursor` for older ones.
+// Transforms apply within a page. CSV, NDJSON and Parquet pages are streamed and carry the
+// cursor in an `X-Next-Cursor` header instead. Days whose block is corrupt are left out and listed
+// in `corrupt_days` (`X-Corrupt-Days`); with `FX_STORE_ON_CORRUPT=error` the request fails.
+#[utoipa::path(
+    get,
+    path = "/history/{symbol}",
+    params(
+        ("symbol" = String, Path, description = "Symbol or alias, e.g. `EURUSD`"),
+        HistoryQuery,
+    ),
+    responses(
+        (status = 200, description = "One page of bars, oldest first", body = HistoryPage, content_type = ["application/json", "text/csv", "application/x-ndjson", "application/vnd.apache.parquet"]),
+        (status = 400, description = "Malformed parameter or range", body = ErrorResponse),
+        (status = 404, description = "Unknown symbol", body = ErrorResponse),
+        (status = 422, description = "`limit` is zero", body = ErrorResponse),
+    )
+)]
 async fn get_history(
     State(store): State<SharedStore>,
+    State(config): State<Arc<ApiConfig>>,
+    State(metrics): State<Arc<ApiMetrics>>,
     Path(symbol): Path<String>,
     Query(params): Query<HistoryQuery>,
-) -> Result<Json<Vec<PriceResponse>>, StatusCode> {
-    let end_ts = if let Some(end_str) = &params.end {
-        parse_datetime(end_str)
-            .map_err(|_| StatusCode::BAD_REQUEST)?
-            .timestamp_nanos_opt()
-            .unwrap() as u64
-    } else {
-        Utc::now().timestamp_nanos_opt().unwrap() as u64
-    };
+    headers: HeaderMap,
+) -> Result<Response, ApiError> {
+    let _timer = metrics.time_query("history");
+    let (start_ts, mut end_ts) = parse_range(
+        params.start.as_deref(),
+        params.end.as_deref(),
+        config.max_query_span,
+    )?;
+    let interval = parse_interval_param(params.interval.as_deref())?;
+    let alignment = parse_alignment(params.align_tz.as_deref(), params.session_offset.as_deref())?;
+    let transform = parse_transform(params.transform.as_deref(), params.brick)?;
+    let format = HistoryFormat::negotiate(params.format.as_deref(), &headers)?;
 
-    let start_ts = if let Some(start_str) = &params.start {
-        parse_datetime(start_str)
-            .map_err(|_| StatusCode::BAD_REQUEST)?
-            .timestamp_nanos_opt()
-            .unwrap() as u64
-    } else {
-        end_ts - 86400_000_000_000 // Default to 1 day ago
-    };
+    if params.limit == Some(0) {
+        return Err(ApiError::InvalidLimit(0));
+    }
+    if let Some(max_points) = params.max_points
+        && !(3..=MAX_CHART_POINTS).contains(&max_points)
+    {
+        return Err(ApiError::invalid(
+            "max_points",
+            format!("max_points must be between 3 and {}", MAX_CHART_POINTS),
+        ));
+    }
 
-    let mut records: Vec<OHLCV> = store
-        .query_range(&symbol, start_ts, end_ts)
-        .collect();
+    // Bricks have no OHLC columns to write
+    if format != HistoryFormat::Json && matches!(transform, Some(Transform::Renko { .. })) {
+        return Err(ApiError::invalid(
+            "format",
+            "renko bricks are only available as JSON",
+        ));
+    }
 
-    // Apply limit if specified
-    if let Some(limit) = params.limit {
-        if records.len() > limit {
-            let start_idx = records.len() - limit;
-            records = records.into_iter().skip(start_idx).collect();
-        }
+    // The cursor is the timestamp of the oldest bar already returned
+    if let Some(cursor) = params.cursor.as_deref() {
+        let before =
+            decode_cursor(cursor).ok_or_else(|| ApiError::invalid("cursor", "invalid cursor"))?;
+        end_ts = end_ts.min(before.saturating_sub(1));
     }
 
-    let responses: Vec<PriceResponse> = records
-        .iter()
-        .map(|ohlcv| {
-            let mut response = PriceResponse::from(ohlcv);
-            response.symbol = symbol.clone();
-            response
-        })
-        .collect();
+    // Wide raw JSON pages are written while the blocks are read instead of collected first
+    if format == HistoryFormat::Json
+        && end_ts.saturating_sub(start_ts) > STREAM_JSON_MIN_SPAN
+        && interval.is_none()
+        && transform.is_none()
+        && params.limit.is_none()
+        && params.max_points.is_none()
+        && params.synthesize.is_none()
+    {
+        return stream_history_page(store, symbol, start_ts, end_ts).await;
+    }
 
-    Ok(Json(responses))
-}
+    #[cfg(feature = "parquet")]
+    if format == HistoryFormat::Parquet
+        && interval.is_none()
+        && transform.is_none()
+        && params.limit.is_none()
+        && params.max_points.is_none()
+        && params.synthesize.is_none()
+    {
+        let filename = export_filename(&symbol, start_ts, end_ts, format);
+        let response = stream_parquet(store, symbol, start_ts, end_ts).await?;
+        return with_attachment(response, &filename);
+    }
 
-// GET /health - Health check
-async fn health_check() -> Json<HashMap<String, String>> {
-    let mut response = HashMap::new();
-    response.insert("status".to_string(), "ok".to_string());
-    response.insert("service".to_string(), "fx-store".to_string());
-    Json(response)
-}
+    // An empty range is still a 200; only unknown symbols are 404. Decompression runs on
+    // the blocking pool so wide pages don't stall other requests on this worker.
+    let mut raw = match params.synthesize.as_deref() {
+        Some(legs) => query_cross(&store, &symbol, legs, start_ts, end_ts).await?,
+        None => store.query_range_async(&symbol, start_ts, end_ts).await?,
+    };
+    let mut records = match interval {
+        Some(secs) => resample(&raw, secs, alignment),
+        None => std::mem::take(&mut raw),
+    };
 
-fn parse_datetime(date_str: &str) -> Result<DateTime<Utc>, anyhow::Error> {
-    // Try different formats
-    if let Ok(dt) = DateTime::parse_from_rfc3339(date_str) {
-        return Ok(dt.with_timezone(&Utc));
+    // Over budget: candles stay candles by moving to the smallest coarser interval that fits,
+    // which keeps every high and low. Raw bars are drawn as a line, where LTTB keeps the
+    // spikes that plain bucketing would average away.
+    let mut reduced_by = None;
+    if let Some(max_points) = params.max_points
+        && records.len() > max_points
+    {
+        if let Some(secs) = interval {
+            let coarser = chart_interval((end_ts - start_ts) / 1_000_000_000, max_points).max(secs);
+            records = resample(&raw, coarser, alignment);
+            reduced_by = Some(format_interval(coarser));
+        }
+        if records.len() > max_points {
+            records = downsample_lttb(&records, max_points);
+            reduced_by = Some("lttb".to_string());
+        }
     }
-    
-    if let Ok(dt) = chrono::NaiveDateTime::parse_from_str(date_str, "%Y-%m-%d %H:%M:%S") {
-        return Ok(dt.and_utc());
+    record_query(&symbol, start_ts, end_ts, records.len());
+    let corrupt_days: Vec<String> = store
+        .corrupt_days(&symbol, start_ts, end_ts)
+        .into_iter()
+        .map(format_date)
+        .collect();
+
+    let mut next_cursor = None;
+    if let Some(limit) = params.limit
+        && records.len() > limit
+    {
+        records.drain(..records.len() - limit);
+        next_cursor = records.first().map(|rec| encode_cursor(rec.ts));
     }
-    
-    if let Ok(date) = chrono::NaiveDate::parse_from_str(date_str, "%Y-%m-%d") {
-        return Ok(date.and_hms_opt(0, 0, 0).unwrap().and_utc());
+
+    if format == HistoryFormat::Json {
+        return Ok(Json(HistoryPage {
+            data: build_history(&store, &records, transform, store.price_scale(&symbol)),
+            next_cursor,
+            reduced_by,
+            corrupt_days,
+        })
+        .into_response());
     }
-    
-    Err(anyhow::anyhow!("Unable to parse date: {}", date_str))
+
+    if let Some(Transform::HeikinAshi) = transform {
+        records = transform_heikin_ashi(&records);
+    }
+    let scale = store.price_scale(&symbol);
+    let mut response = match format {
+        #[cfg(feature = "parquet")]
+        HistoryFormat::Parquet => {
+            let mut body = Vec::new();
+            crate::parquet_format::write_bars(&mut body, &symbol, scale, &records)?;
+            ([(header::CONTENT_TYPE, PARQUET_CONTENT_TYPE)], body).into_response()
+        }
+        _ => stream_history(&symbol, records, format, scale).into_response(),
+    };
+    if let Some(cursor) = next_cursor {
+        response.headers_mut().insert(
+            "x-next-cursor",
+            HeaderValue::from_str(&cursor).map_err(|e| ApiError::Internal(e.to_string()))?,
+        );
+    }
+    if let Some(reduced_by) = reduced_by {
+        response.headers_mut().insert(
+            "x-reduced-by",
+            HeaderValue::from_str(&reduced_by).map_err(|e| ApiError::Internal(e.to_string()))?,
+        );
+    }
+    if !corrupt_days.is_empty() {
+        response.headers_mut().insert(
+            "x-corrupt-days",
+            HeaderValue::from_str(&corrupt_days.join(","))
+                .map_err(|e| ApiError::Internal(e.to_string()))?,
+        );
+    }
+    if matches!(format, HistoryFormat::Csv | HistoryFormat::Parquet) {
+        let filename = export_filename(&symbol, start_ts, end_ts, format);
+        response = with_attachment(response, &filename)?;
+    }
+    Ok(response)
+}
+
+/// `EURUSD_20240101_20240131.csv` for a downloaded page
+fn export_filename(symbol: &str, start_ts: u64, end_ts: u64, format: HistoryFormat) -> String {
+    let day = |ts: u64| ts_to_datetime(ts).format("%Y%m%d");
+    let extension = match format {
+        HistoryFormat::Parquet => "parquet",
+        _ => "csv",
+    };
+    format!("{}_{}_{}.{}", symbol, day(start_ts), day(end_ts), extension)
+}
+
+fn with_attachment(mut response: Response, filename: &str) -> Result<Response, ApiError> {
+    let disposition = format!("attachment; filename=\"{}\"", filename);
+    response.headers_mut().insert(
+        header::CONTENT_DISPOSITION,
+        HeaderValue::from_str(&disposition)
+            .map_err(|_| ApiError::invalid("symbol", "symbol is not a valid file name"))?,
+    );
+    Ok(response)
+}
+
+// GET /history?symbols=EURUSD,XAUUSD&start=2024-01-01&end=2024-01-31&interval=1h
+#[utoipa::path(
+    get,
+    path = "/history",
+    params(
+        MultiHistoryQuery,
+    ),
+    responses(
+        (status = 200, description = "History per symbol, or an error entry", body = BTreeMap<String, SymbolHistory>),
+        (status = 400, description = "Malformed parameter or range", body = ErrorResponse),
+    )
+)]
+async fn get_history_multi(
+    State(store): State<SharedStore>,
+    State(config): State<Arc<ApiConfig>>,
+    State(metrics): State<Arc<ApiMetrics>>,
+    Query(params): Query<MultiHistoryQuery>,
+) -> Result<Json<BTreeMap<String, SymbolHistory>>, ApiError> {
+    let _timer = metrics.time_query("history_multi");
+    let (start_ts, end_ts) = parse_range(
+        params.start.as_deref(),
+        params.end.as_deref(),
+        config.max_query_span,
+    )?;
+    let interval = parse_interval_param(params.interval.as_deref())?;
+    let alignment = parse_alignment(params.align_tz.as_deref(), params.session_offset.as_deref())?;
+    let transform = parse_transform(params.transform.as_deref(), params.brick)?;
+
+    let symbols = split_symbols(&params.symbols);
+    if symbols.is_empty() {
+        return Err(ApiError::invalid("symbols", "symbols must not be empty"));
+    }
+
+    // Decompression is CPU-bound: one blocking task per symbol
+    let tasks: Vec<_> = symbols
+        .into_iter()
+        .map(|symbol| {
+            let store = Arc::clone(&store);
+            tokio::task::spawn_blocking(move || {
+                match query_records(&store, &symbol, start_ts, end_ts, interval, alignment) {
+                    Ok(records) => {
+                        let scale = store.price_scale(&symbol);
+                        let history = build_history(&store, &records, transform, scale);
+                        (symbol, SymbolHistory::Data(history), records.len())
+                    }
+                    Err(e) => (
+                        symbol,
+                        SymbolHistory::Error {
+                            error: e.to_string(),
+                        },
+                        0,
+                    ),
+                }
+            })
+        })
+        .collect();
+
+    let mut result = BTreeMap::new();
+    let mut rows = 0;
+    for task in tasks {
+        let (symbol, history, count) = task.await?;
+        result.insert(symbol, history);
+        rows += count;
+    }
+    record_query(&params.symbols, start_ts, end_ts, rows);
+
+    Ok(Json(result))
+}
+
+// GET /asof/{symbol}?ts=2024-01-05T12:00:00Z - Bar in effect at a point in time
+#[utoipa::path(
+    get,
+    path = "/asof/{symbol}",
+    params(
+        ("symbol" = String, Path, description = "Symbol or alias, e.g. `EURUSD`"),
+        AsofQuery,
+    ),
+    responses(
+        (status = 200, description = "Bar in effect at `ts`", body = PriceResponse),
+        (status = 400, description = "Malformed parameter or range", body = ErrorResponse),
+        (status = 404, description = "Unknown symbol or no bar at or before `ts`", body = ErrorResponse),
+    )
+)]
+async fn get_asof(
+    State(store): State<SharedStore>,
+    State(metrics): State<Arc<ApiMetrics>>,
+    Path(symbol): Path<String>,
+    Query(params): Query<AsofQuery>,
+) -> Result<Json<PriceResponse>, ApiError> {
+    let _timer = metrics.time_query("asof");
+    let invalid = || ApiError::InvalidDate {
+        param: "ts",
+        value: params.ts.clone(),
+    };
+    // Accept either a datetime string or epoch seconds
+    let ts = match params.ts.parse::<i64>() {
+        Ok(secs) => DateTime::from_timestamp(secs, 0).ok_or_else(invalid)?,
+        Err(_) => parse_datetime(&params.ts).map_err(|_| invalid())?,
+    };
+    let ts = ts
+        .timestamp_nanos_opt()
+        .and_then(|nanos| u64::try_from(nanos).ok())
+        .ok_or_else(invalid)?;
+
+    if !store.has_symbol(&symbol) {
+        return Err(ApiError::SymbolNotFound(symbol));
+    }
+    match store.query_asof(&symbol, ts) {
+        Some(record) => {
+            let scale = store.price_scale(&symbol);
+            Ok(Json(PriceResponse::from_record(&store, &record, scale)))
+        }
+        None => Err(ApiError::NoData { symbol, ts }),
+    }
+}
+
+// GET /count/{symbol}?start=2024-01-01&end=2024-01-31 - Bar count without fetching the bars
+//
+// Only blocks cut by the range edges are decompressed, so there is no span limit.
+#[utoipa::path(
+    get,
+    path = "/count/{symbol}",
+    params(
+        ("symbol" = String, Path, description = "Symbol or alias, e.g. `EURUSD`"),
+        CountQuery,
+    ),
+    responses(
+        (status = 200, description = "Bars stored in the range", body = CountResponse),
+        (status = 400, description = "Malformed parameter or range", body = ErrorResponse),
+        (status = 404, description = "Unknown symbol", body = ErrorResponse),
+    )
+)]
+async fn get_count(
+    State(store): State<SharedStore>,
+    State(metrics): State<Arc<ApiMetrics>>,
+    Path(symbol): Path<String>,
+    Query(params): Query<CountQuery>,
+) -> Result<Json<CountResponse>, ApiError> {
+    let _timer = metrics.time_query("count");
+    let (start_ts, end_ts) = parse_range(
+        params.start.as_deref(),
+        params.end.as_deref(),
+        Duration::MAX,
+    )?;
+    let count =
+        tokio::task::spawn_blocking(move || store.try_count_range(&symbol, start_ts, end_ts))
+            .await??;
+    Ok(Json(CountResponse { count }))
+}
+
+// GET /correlation?a=EURUSD&b=GBPUSD&window=60&start=2024-01-01&end=2024-01-31
+#[utoipa::path(
+    get,
+    path = "/correlation",
+    params(
+        CorrelationQuery,
+    ),
+    responses(
+        (status = 200, description = "Rolling correlation of the two closes", body = CorrelationResponse),
+        (status = 400, description = "Malformed parameter or range", body = ErrorResponse),
+        (status = 404, description = "Unknown symbol", body = ErrorResponse),
+    )
+)]
+async fn get_correlation(
+    State(store): State<SharedStore>,
+    State(config): State<Arc<ApiConfig>>,
+    State(metrics): State<Arc<ApiMetrics>>,
+    Query(params): Query<CorrelationQuery>,
+) -> Result<Json<CorrelationResponse>, ApiError> {
+    let _timer = metrics.time_query("correlation");
+    let window = params.window.unwrap_or(60);
+    if window < 2 {
+        return Err(ApiError::invalid("window", "window must be at least 2"));
+    }
+    for symbol in [&params.a, &params.b] {
+        if !store.has_symbol(symbol) {
+            return Err(ApiError::SymbolNotFound(symbol.clone()));
+        }
+    }
+    let (start_ts, end_ts) = parse_range(
+        params.start.as_deref(),
+        params.end.as_deref(),
+        config.max_query_span,
+    )?;
+
+    let (a, b) = (params.a.clone(), params.b.clone());
+    let (timestamps, values) =
+        tokio::task::spawn_blocking(move || store.correlation(&a, &b, start_ts, end_ts, window))
+            .await?;
+    record_query(
+        &format!("{},{}", params.a, params.b),
+        start_ts,
+        end_ts,
+        timestamps.len(),
+    );
+
+    let points = timestamps
+        .iter()
+        .zip(values)
+        .map(|(&ts, value)| CorrelationPoint {
+            timestamp: (ts / 1_000_000_000) as i64,
+            value: value.is_finite().then_some(value),
+        })
+        .collect();
+
+    Ok(Json(CorrelationResponse {
+        a: params.a,
+        b: params.b,
+        window,
+        points,
+    }))
+}
+
+/// Most symbols a `/basket` may combine
+const MAX_BASKET_COMPONENTS: usize = 32;
+
+// GET /basket?components=EURUSD:-0.5,USDJPY:0.01&start=2024-01-01&end=2024-01-31&max_gap=5m
+#[utoipa::path(
+    get,
+    path = "/basket",
+    params(
+        BasketQuery,
+    ),
+    responses(
+        (status = 200, description = "Weighted basket of the component closes", body = BasketResponse),
+        (status = 400, description = "Malformed parameter or range", body = ErrorResponse),
+        (status = 404, description = "Unknown component symbol", body = ErrorResponse),
+    )
+)]
+async fn get_basket(
+    State(store): State<SharedStore>,
+    State(config): State<Arc<ApiConfig>>,
+    State(metrics): State<Arc<ApiMetrics>>,
+    Query(params): Query<BasketQuery>,
+) -> Result<Json<BasketResponse>, ApiError> {
+    let _timer = metrics.time_query("basket");
+    let components = parse_components(&params.components)?;
+    for (symbol, _) in &components {
+        if !store.has_symbol(symbol) {
+            return Err(ApiError::SymbolNotFound(symbol.clone()));
+        }
+    }
+    let fill = match params.max_gap.as_deref() {
+        Some(gap) => FillPolicy::ForwardFill {
+            max_gap_secs: parse_interval(gap).ok_or_else(|| {
+                ApiError::invalid("max_gap", format!("invalid duration: {}", gap))
+            })?,
+        },
+        None => FillPolicy::Drop,
+    };
+    let (start_ts, end_ts) = parse_range(
+        params.start.as_deref(),
+        params.end.as_deref(),
+        config.max_query_span,
+    )?;
+
+    let task_components = components.clone();
+    let records =
+        tokio::task::spawn_blocking(move || store.basket(&task_components, start_ts, end_ts, fill))
+            .await?;
+    record_query(&params.components, start_ts, end_ts, records.len());
+
+    let name = params.name.unwrap_or_else(|| "BASKET".to_string());
+    Ok(Json(BasketResponse {
+        components: components
+            .into_iter()
+            .map(|(symbol, weight)| BasketComponent { symbol, weight })
+            .collect(),
+        bars: records
+            .iter()
+            .map(|rec| PriceResponse::new(name.clone(), rec, PRICE_SCALE))
+            .collect(),
+    }))
+}
+
+// GET /indicator/{symbol}?name=rsi&period=14&start=2024-01-01&end=2024-01-31&interval=1h
+#[utoipa::path(
+    get,
+    path = "/indicator/{symbol}",
+    params(
+        ("symbol" = String, Path, description = "Symbol or alias, e.g. `EURUSD`"),
+        IndicatorQuery,
+    ),
+    responses(
+        (status = 200, description = "Indicator series", body = IndicatorResponse),
+        (status = 400, description = "Malformed parameter or range", body = ErrorResponse),
+        (status = 404, description = "Unknown symbol", body = ErrorResponse),
+        (status = 413, description = "Range holds more than `max_indicator_points` bars", body = ErrorResponse),
+    )
+)]
+async fn get_indicator(
+    State(state): State<AppState>,
+    Path(symbol): Path<String>,
+    Query(params): Query<IndicatorQuery>,
+) -> Result<Json<IndicatorResponse>, ApiError> {
+    let _timer = state.metrics.time_query("indicator");
+
+    if !state.store.has_symbol(&symbol) {
+        return Err(ApiError::SymbolNotFound(symbol));
+    }
+    let (start_ts, end_ts) = parse_range(
+        params.start.as_deref(),
+        params.end.as_deref(),
+        state.config.max_query_span,
+    )?;
+    let interval = parse_interval_param(params.interval.as_deref())?;
+
+    // Estimate bar count up front so oversized requests never touch the store
+    let bar_secs = interval.unwrap_or(60);
+    let points = end_ts.saturating_sub(start_ts) / 1_000_000_000 / bar_secs;
+    if points > state.config.max_indicator_points as u64 {
+        return Err(ApiError::RangeTooLarge {
+            points,
+            max: state.config.max_indicator_points,
+        });
+    }
+
+    let field = match params.field.as_deref().unwrap_or("close") {
+        "open" => PriceField::Open,
+        "high" => PriceField::High,
+        "low" => PriceField::Low,
+        "close" => PriceField::Close,
+        _ => {
+            return Err(ApiError::invalid(
+                "field",
+                "field must be one of open, high, low, close",
+            ));
+        }
+    };
+
+    let scale = state.store.price_scale(&symbol);
+    let store = Arc::clone(&state.store);
+    let name = symbol.clone();
+    let records = tokio::task::spawn_blocking(move || {
+        query_records(&store, &name, start_ts, end_ts, interval, Alignment::UTC)
+    })
+    .await??;
+    record_query(&symbol, start_ts, end_ts, records.len());
+
+    let check_period = |period: usize, needed: usize| {
+        if period == 0 {
+            Err(ApiError::invalid("period", "period must be positive"))
+        } else if needed > records.len() {
+            Err(ApiError::invalid(
+                "period",
+                format!(
+                    "period needs {} bars but the range has {}",
+                    needed,
+                    records.len()
+                ),
+            ))
+        } else {
+            Ok(period)
+        }
+    };
+
+    let response = match params.name.as_str() {
+        "sma" => {
+            let period = params.period.unwrap_or(20);
+            let period = check_period(period, period)?;
+            let values = TechnicalIndicators::sma(&records, period, scale);
+            IndicatorResponse::Series(
+                records[period - 1..]
+                    .iter()
+                    .zip(values)
+                    .map(|(rec, value)| IndicatorPoint {
+                        timestamp: (rec.ts / 1_000_000_000) as i64,
+                        value,
+                    })
+                    .collect(),
+            )
+        }
+        "ema" => {
+            let period = params.period.unwrap_or(20);
+            let period = check_period(period, period)?;
+            let values = TechnicalIndicators::ema(&records, field, period, scale);
+            IndicatorResponse::Series(indicator_points(&records, &values))
+        }
+        "rsi" => {
+            let period = params.period.unwrap_or(14);
+            let period = check_period(period, period + 1)?;
+            let values = TechnicalIndicators::rsi(&records, field, period);
+            IndicatorResponse::Series(indicator_points(&records, &values))
+        }
+        "atr" => {
+            let period = params.period.unwrap_or(14);
+            let period = check_period(period, period + 1)?;
+            let values = TechnicalIndicators::atr(&records, period, scale);
+            IndicatorResponse::Series(indicator_points(&records, &values))
+        }
+        "bollinger" => {
+            let period = params.period.unwrap_or(20);
+            let period = check_period(period, period)?;
+            let k = params.k.unwrap_or(2.0);
+            if !k.is_finite() || k < 0.0 {
+                return Err(ApiError::invalid("k", "k must be a non-negative number"));
+            }
+            let bands = TechnicalIndicators::bollinger(&records, field, period, k, scale);
+            IndicatorResponse::Multi(BTreeMap::from([
+                ("middle", indicator_points(&records, &bands.middle)),
+                ("upper", indicator_points(&records, &bands.upper)),
+                ("lower", indicator_points(&records, &bands.lower)),
+            ]))
+        }
+        "macd" => {
+            let (fast, slow, signal) = (
+                params.fast.unwrap_or(12),
+                params.slow.unwrap_or(26),
+                params.signal.unwrap_or(9),
+            );
+            if fast >= slow {
+                return Err(ApiError::invalid("fast", "fast must be less than slow"));
+            }
+            check_period(fast, fast)?;
+            check_period(signal, slow + signal - 1)?;
+            let macd = TechnicalIndicators::macd(&records, field, fast, slow, signal, scale);
+            IndicatorResponse::Multi(BTreeMap::from([
+                ("macd", indicator_points(&records, &macd.macd)),
+                ("signal", indicator_points(&records, &macd.signal)),
+                ("histogram", indicator_points(&records, &macd.histogram)),
+            ]))
+        }
+        other => {
+            return Err(ApiError::invalid(
+                "name",
+                format!("unknown indicator: {}", other),
+            ));
+        }
+    };
+
+    Ok(Json(response))
+}
+
+/// Comment line sent on idle SSE streams so proxies keep the connection open
+const SSE_KEEPALIVE: Duration = Duration::from_secs(15);
+
+/// How often the forwarding thread checks whether the SSE client went away
+const SSE_POLL: Duration = Duration::from_secs(1);
+
+// GET /aggregate/{symbol}?start=2024-01-01&end=2024-01-31&field=close
+#[utoipa::path(
+    get,
+    path = "/aggregate/{symbol}",
+    params(
+        ("symbol" = String, Path, description = "Symbol or alias, e.g. `EURUSD`"),
+        AggregateQuery,
+    ),
+    responses(
+        (status = 200, description = "Reduction of one field over the range", body = AggregateResponse),
+        (status = 400, description = "Malformed parameter or range", body = ErrorResponse),
+        (status = 404, description = "Unknown symbol", body = ErrorResponse),
+    )
+)]
+async fn get_aggregate(
+    State(store): State<SharedStore>,
+    State(config): State<Arc<ApiConfig>>,
+    State(metrics): State<Arc<ApiMetrics>>,
+    Path(symbol): Path<String>,
+    Query(params): Query<AggregateQuery>,
+) -> Result<Json<AggregateResponse>, ApiError> {
+    let _timer = metrics.time_query("aggregate");
+    let (start_ts, end_ts) = parse_range(
+        params.start.as_deref(),
+        params.end.as_deref(),
+        config.max_query_span,
+    )?;
+    let field = params.field.unwrap_or_else(|| "close".to_string());
+    // Prices are de-scaled, volume is a plain count
+    let price_scale = store.price_scale(&symbol) as f64;
+    let (value, scale): (fn(&OHLCV) -> u64, f64) = match field.as_str() {
+        "open" => (|rec| u64::from(rec.open), price_scale),
+        "high" => (|rec| u64::from(rec.high), price_scale),
+        "low" => (|rec| u64::from(rec.low), price_scale),
+        "close" => (|rec| u64::from(rec.close), price_scale),
+        "volume" => (OHLCV::total_volume, 1.0),
+        _ => {
+            return Err(ApiError::invalid(
+                "field",
+                "field must be one of open, high, low, close, volume",
+            ));
+        }
+    };
+
+    // Single pass over the range iterator, nothing collected
+    let name = symbol.clone();
+    let (count, sum, min, max) = tokio::task::spawn_blocking(move || {
+        let records = store.try_query_range(&name, start_ts, end_ts)?;
+        Ok::<_, FxStoreError>(records.fold(
+            (0u64, 0u128, u64::MAX, 0u64),
+            |(n, sum, min, max), rec| {
+                let v = value(&rec);
+                (n + 1, sum + u128::from(v), min.min(v), max.max(v))
+            },
+        ))
+    })
+    .await??;
+    record_query(&symbol, start_ts, end_ts, count as usize);
+
+    let present = |v: u64| (count > 0).then(|| v as f64 / scale);
+    Ok(Json(AggregateResponse {
+        symbol,
+        field,
+        min: present(min),
+        max: present(max),
+        avg: (count > 0).then(|| sum as f64 / count as f64 / scale),
+        count,
+        sum: sum as f64 / scale,
+    }))
+}
+
+/// Default and upper bound for `/chart`'s `max_points`
+const CHART_POINTS: usize = 1500;
+const MAX_CHART_POINTS: usize = 10_000;
+
+// GET /chart/{symbol}?start=2023-01-01&end=2024-01-01&max_points=1500
+//
+// The timeframe is the smallest standard bar size that fits the range in `max_points`
+// bars, so a multi-year view comes back as daily or weekly candles.
+#[utoipa::path(
+    get,
+    path = "/chart/{symbol}",
+    params(
+        ("symbol" = String, Path, description = "Symbol or alias, e.g. `EURUSD`"),
+        ChartQuery,
+    ),
+    responses(
+        (status = 200, description = "At most `max_points` bars at the chosen timeframe", body = ChartResponse),
+        (status = 400, description = "Malformed parameter or range", body = ErrorResponse),
+        (status = 404, description = "Unknown symbol", body = ErrorResponse),
+    )
+)]
+async fn get_chart(
+    State(store): State<SharedStore>,
+    State(config): State<Arc<ApiConfig>>,
+    State(metrics): State<Arc<ApiMetrics>>,
+    Path(symbol): Path<String>,
+    Query(params): Query<ChartQuery>,
+) -> Result<Json<ChartResponse>, ApiError> {
+    let _timer = metrics.time_query("chart");
+    let (start_ts, end_ts) = parse_range(
+        params.start.as_deref(),
+        params.end.as_deref(),
+        config.max_query_span,
+    )?;
+    let max_points = params.max_points.unwrap_or(CHART_POINTS);
+    if !(2..=MAX_CHART_POINTS).contains(&max_points) {
+        return Err(ApiError::invalid(
+            "max_points",
+            format!("max_points must be between 2 and {}", MAX_CHART_POINTS),
+        ));
+    }
+
+    let interval_secs = chart_interval((end_ts - start_ts) / 1_000_000_000, max_points);
+    let records = store.query_range_async(&symbol, start_ts, end_ts).await?;
+    record_query(&symbol, start_ts, end_ts, records.len());
+    let scale = store.price_scale(&symbol);
+    let bars = resample(&records, interval_secs, Alignment::UTC)
+        .iter()
+        .map(|rec| PriceResponse::from_record(&store, rec, scale))
+        .collect();
+
+    Ok(Json(ChartResponse {
+        symbol,
+        timeframe: format_interval(interval_secs),
+        interval_secs,
+        bars,
+    }))
+}
+
+// GET /stream/{symbol} - Completed bars as Server-Sent Events
+//
+// The event id is the bar timestamp in seconds; a reconnecting client's `Last-Event-ID`
+// replays stored bars newer than that before switching to live ones. The stream ends
+// cleanly when the server shuts down, so clients can reconnect with their last id.
+#[utoipa::path(
+    get,
+    path = "/stream/{symbol}",
+    params(
+        ("symbol" = String, Path, description = "Symbol or alias, e.g. `EURUSD`"),
+        ("Last-Event-ID" = Option<u64>, Header, description = "Replay stored bars newer than this (epoch seconds)"),
+    ),
+    responses(
+        (status = 200, description = "Completed bars as server-sent events", body = PriceResponse, content_type = "text/event-stream"),
+        (status = 400, description = "Malformed parameter or range", body = ErrorResponse),
+        (status = 404, description = "Unknown symbol", body = ErrorResponse),
+    )
+)]
+async fn stream_bars(
+    State(store): State<SharedStore>,
+    State(shutdown): State<ShutdownSignal>,
+    Path(symbol): Path<String>,
+    headers: HeaderMap,
+) -> Result<Sse<impl Stream<Item = Result<Event, Infallible>>>, ApiError> {
+    if !store.has_symbol(&symbol) {
+        return Err(ApiError::SymbolNotFound(symbol));
+    }
+    let last_event_id = match headers.get("last-event-id") {
+        Some(value) => Some(
+            value
+                .to_str()
+                .ok()
+                .and_then(|id| id.trim().parse::<u64>().ok())
+                .ok_or_else(|| {
+                    ApiError::invalid("Last-Event-ID", "Last-Event-ID must be epoch seconds")
+                })?,
+        ),
+        None => None,
+    };
+
+    let scale = store.price_scale(&symbol);
+    let (tx, rx) = tokio::sync::mpsc::channel::<OHLCV>(256);
+    let names = Arc::clone(&store);
+    tokio::task::spawn_blocking(move || {
+        // Subscribe before replaying so no bar falls between the two
+        // (the symbol exists, so this only fails if it was removed meanwhile)
+        let Ok(live) = store.stream_realtime(&symbol) else {
+            return;
+        };
+        let mut last_ts = 0;
+
+        if let Some(after) = last_event_id {
+            let from = after.saturating_add(1).saturating_mul(1_000_000_000);
+            for bar in store.query_range(&symbol, from, u64::MAX) {
+                if tx.blocking_send(bar).is_err() {
+                    return;
+                }
+                last_ts = bar.ts;
+            }
+        }
+
+        loop {
+            match live.recv_timeout(SSE_POLL) {
+                Ok(bar) if bar.ts > last_ts => {
+                    last_ts = bar.ts;
+                    if tx.blocking_send(bar).is_err() {
+                        return;
+                    }
+                }
+                Ok(_) => {}
+                // Dropping `live` unsubscribes on the next published bar
+                Err(RecvTimeoutError::Timeout) if tx.is_closed() => return,
+                Err(RecvTimeoutError::Timeout) => {}
+                Err(RecvTimeoutError::Disconnected) => return,
+            }
+        }
+    });
+
+    let events = futures_util::stream::unfold((rx, names), move |(mut rx, names)| async move {
+        let bar = rx.recv().await?;
+        let response = PriceResponse::from_record(&names, &bar, scale);
+        let event = Event::default()
+            .id(response.timestamp.to_string())
+            .json_data(&response)
+            .unwrap_or_default();
+        Some((Ok(event), (rx, names)))
+    });
+    let events = events.take_until(async move { shutdown.triggered().await });
+
+    Ok(Sse::new(events).keep_alive(KeepAlive::new().interval(SSE_KEEPALIVE).text("keepalive")))
 }
 
-pub async fn start_server(store: SharedStore, port: u16) -> anyhow::Result<()> {
-    let app = create_app(store);
-    
-    let listener = tokio::net::TcpListener::bind(format!("0.0.0.0:{}", port)).await?;
-    println!("🚀 FX-Store API server running on http://0.0.0.0:{}", port);
-    
-    axum::serve(listener, app).await?;
-    Ok(())
-}
\ No newline at end of file
+// POST /bars/{symbol} - Append bars; each item is accepted or rejected on its own
+#[utoipa::path(
+    post,
+    path = "/bars/{symbol}",
+    request_body = Vec<BarInput>,
+    params(
+        ("symbol" = String, Path, description = "Symbol or alias, e.g. `EURUSD`"),
+    ),
+    responses(
+        (status = 200, description = "Every item accepted", body = Vec<IngestResult>),
+        (status = 207, description = "Some items rejected", body = Vec<IngestResult>),
+        (status = 400, description = "Malformed symbol", body = ErrorResponse),
+        (status = 401, description = "Missing or wrong write token or API key", body = ErrorResponse),
+        (status = 403, description = "Writes are disabled", body = ErrorResponse),
+    ),
+    security(("bearer" = [])),
+)]
+async fn post_bars(
+    State(state): State<AppState>,
+    Path(symbol): Path<String>,
+    headers: HeaderMap,
+    key: Option<Extension<VerifiedKey>>,
+    Json(bars): Json<Vec<BarInput>>,
+) -> Result<(StatusCode, Json<Vec<IngestResult>>), ApiError> {
+    authorize(&state.config, &headers, key.is_some())?;
+    check_symbol(&state.store, &symbol)?;
+    let latest = latest_allowed_ts(&state.config);
+
+    let store = Arc::clone(&state.store);
+    let results = tokio::task::spawn_blocking(move || {
+        let scale = store.price_scale(&symbol);
+        let checks: Vec<_> = bars
+            .iter()
+            .map(|bar| check_bar(bar, latest, scale))
+            .collect();
+        // Accepted bars go in as one batch; a store error rejects all of them
+        let accepted: Vec<OHLCV> = checks
+            .iter()
+            .filter_map(|c| c.as_ref().ok().copied())
+            .collect();
+        let stored = store
+            .insert_batch(&symbol, &accepted)
+            .map_err(|e| e.to_string());
+        checks
+            .into_iter()
+            .map(|check| IngestResult::from_check(check.and_then(|_| stored.clone())))
+            .collect::<Vec<_>>()
+    })
+    .await?;
+
+    Ok((ingest_status(&results), Json(results)))
+}
+
+// POST /ingest/{symbol} - Append bars to a known symbol and count them; malformed rows are
+// rejected one by one
+#[utoipa::path(
+    post,
+    path = "/ingest/{symbol}",
+    request_body = Vec<BarInput>,
+    params(
+        ("symbol" = String, Path, description = "Symbol or alias, e.g. `EURUSD`"),
+    ),
+    responses(
+        (status = 200, description = "Accepted and rejected counts", body = IngestSummary),
+        (status = 401, description = "Missing or wrong write token or API key", body = ErrorResponse),
+        (status = 403, description = "Writes are disabled", body = ErrorResponse),
+        (status = 404, description = "Unknown symbol", body = ErrorResponse),
+    ),
+    security(("bearer" = [])),
+)]
+async fn post_ingest(
+    State(state): State<AppState>,
+    Path(symbol): Path<String>,
+    headers: HeaderMap,
+    key: Option<Extension<VerifiedKey>>,
+    Json(rows): Json<Vec<serde_json::Value>>,
+) -> Result<Json<IngestSummary>, ApiError> {
+    authorize(&state.config, &headers, key.is_some())?;
+    // Unlike /bars, ingest never registers a symbol from the path
+    if !state.store.has_symbol(&symbol) {
+        return Err(ApiError::SymbolNotFound(symbol));
+    }
+    let latest = latest_allowed_ts(&state.config);
+
+    let store = Arc::clone(&state.store);
+    let summary = tokio::task::spawn_blocking(move || {
+        let scale = store.price_scale(&symbol);
+        let mut summary = IngestSummary::default();
+        let mut accepted = Vec::new();
+        for (index, row) in rows.into_iter().enumerate() {
+            // Parsed per row so a bad field only rejects its own row
+            let checked = serde_json::from_value::<BarInput>(row)
+                .map_err(|e| e.to_string())
+                .and_then(|bar| check_bar(&bar, latest, scale));
+            match checked {
+                Ok(rec) => accepted.push((index, rec)),
+                Err(reason) => summary.errors.push(RejectedRow { index, reason }),
+            }
+        }
+        let records: Vec<OHLCV> = accepted.iter().map(|&(_, rec)| rec).collect();
+        if let Err(e) = store.insert_batch(&symbol, &records) {
+            let reason = e.to_string();
+            summary
+                .errors
+                .extend(accepted.iter().map(|&(index, _)| RejectedRow {
+                    index,
+                    reason: reason.clone(),
+                }));
+            summary.errors.sort_by_key(|row| row.index);
+        } else {
+            summary.accepted = records.len();
+        }
+        summary.rejected = summary.errors.len();
+        summary
+    })
+    .await?;
+
+    Ok(Json(summary))
+}
+
+// POST /ticks/{symbol} - Feed quotes into the symbol's minute-bar aggregator
+#[utoipa::path(
+    post,
+    path = "/ticks/{symbol}",
+    request_body = Vec<TickInput>,
+    params(
+        ("symbol" = String, Path, description = "Symbol or alias, e.g. `EURUSD`"),
+    ),
+    responses(
+        (status = 200, description = "Every item accepted", body = Vec<IngestResult>),
+        (status = 207, description = "Some items rejected", body = Vec<IngestResult>),
+        (status = 400, description = "Malformed symbol", body = ErrorResponse),
+        (status = 401, description = "Missing or wrong write token or API key", body = ErrorResponse),
+        (status = 403, description = "Writes are disabled", body = ErrorResponse),
+    ),
+    security(("bearer" = [])),
+)]
+async fn post_ticks(
+    State(state): State<AppState>,
+    Path(symbol): Path<String>,
+    headers: HeaderMap,
+    key: Option<Extension<VerifiedKey>>,
+    Json(ticks): Json<Vec<TickInput>>,
+) -> Result<(StatusCode, Json<Vec<IngestResult>>), ApiError> {
+    authorize(&state.config, &headers, key.is_some())?;
+    check_symbol(&state.store, &symbol)?;
+    let latest = latest_allowed_ts(&state.config);
+
+    let store = Arc::clone(&state.store);
+    let results = tokio::task::spawn_blocking(move || {
+        ticks
+            .iter()
+            .map(|tick| {
+                let check = check_tick(tick, latest, store.price_scale(&symbol))
+                    .and_then(|tick| store.insert_tick(&symbol, tick).map_err(|e| e.to_string()));
+                IngestResult::from_check(check)
+            })
+            .collect::<Vec<_>>()
+    })
+    .await?;
+
+    Ok((ingest_status(&results), Json(results)))
+}
+
+// POST /admin/scrub?repair=true - Verify every block's checksum and decompression
+#[utoipa::path(
+    post,
+    path = "/admin/scrub",
+    params(
+        ScrubQuery,
+    ),
+    responses(
+        (status = 200, description = "Blocks checked and the ones found corrupt", body = ScrubResponse),
+        (status = 401, description = "Missing or wrong write token or API key", body = ErrorResponse),
+        (status = 403, description = "Writes are disabled", body = ErrorResponse),
+    ),
+    security(("bearer" = [])),
+)]
+async fn post_scrub(
+    State(state): State<AppState>,
+    Query(params): Query<ScrubQuery>,
+    headers: HeaderMap,
+    key: Option<Extension<VerifiedKey>>,
+) -> Result<Json<ScrubResponse>, ApiError> {
+    authorize(&state.config, &headers, key.is_some())?;
+    let store = Arc::clone(&state.store);
+    let repair = params.repair.unwrap_or(false);
+    let report = tokio::task::spawn_blocking(move || store.scrub(repair)).await??;
+
+    Ok(Json(ScrubResponse {
+        blocks_checked: report.blocks_checked,
+        corrupt: report
+            .corrupt
+            .into_iter()
+            .map(|block| CorruptBlockEntry {
+                symbol: block.symbol,
+                date: format_date(block.date),
+                reason: block.reason,
+            })
+            .collect(),
+        removed: report.removed,
+    }))
+}
+
+/// Set by `check_access` when the request carried a configured API key
+#[derive(Clone, Copy)]
+struct VerifiedKey;
+
+#[derive(Deserialize)]
+struct KeyQuery {
+    api_key: Option<String>,
+}
+
+// API keys (`Authorization: Bearer` or `?api_key=`) and rate limits. With keys configured,
+// every non-GET route needs one (they replace `write_token`), and reads do too when
+// `keys_for_reads` is set. Requests without a valid key are limited per client IP, and a
+// rejected key still costs its IP a token so keys cannot be guessed at full speed.
+async fn check_access(
+    State(access): State<Arc<AccessControl>>,
+    mut request: Request,
+    next: Next,
+) -> Result<Response, ApiError> {
+    if OPEN_ROUTES.contains(&request.uri().path()) {
+        return Ok(next.run(request).await);
+    }
+
+    let keys_required = !access.config.api_keys.is_empty();
+    let given = if keys_required {
+        bearer_token(request.headers())
+            .map(str::to_string)
+            .or_else(|| {
+                Query::<KeyQuery>::try_from_uri(request.uri())
+                    .ok()
+                    .and_then(|query| query.0.api_key)
+            })
+    } else {
+        None
+    };
+
+    let client = request
+        .extensions()
+        .get::<ConnectInfo<SocketAddr>>()
+        .map(|info| info.0.ip().to_string())
+        .unwrap_or_default();
+    let rate_limited = |retry_after| ApiError::RateLimited { retry_after };
+    let read = matches!(*request.method(), Method::GET | Method::HEAD);
+    let key = given.as_deref().map(|given| access.find_key(given));
+
+    let limited = match key {
+        Some(Some(index)) => {
+            request.extensions_mut().insert(VerifiedKey);
+            access.acquire_for_key(index)
+        }
+        Some(None) => {
+            access.acquire_anonymous(&client).map_err(rate_limited)?;
+            return Err(ApiError::Unauthorized);
+        }
+        None if keys_required && (!read || access.config.keys_for_reads) => {
+            access.acquire_anonymous(&client).map_err(rate_limited)?;
+            return Err(ApiError::Unauthorized);
+        }
+        None => access.acquire_anonymous(&client),
+    };
+    limited.map_err(rate_limited)?;
+    Ok(next.run(request).await)
+}
+
+// Request span, logged with its timing when it closes; query handlers fill in the
+// symbol, range and row count through `record_query`
+fn request_span(request: &Request) -> tracing::Span {
+    let route = request
+        .extensions()
+        .get::<MatchedPath>()
+        .map_or("", MatchedPath::as_str);
+    tracing::debug_span!(
+        "request",
+        method = %request.method(),
+        route,
+        symbol = Empty,
+        range_secs = Empty,
+        rows = Empty,
+        status = Empty,
+    )
+}
+
+fn record_query(symbol: &str, start_ts: u64, end_ts: u64, rows: usize) {
+    let span = tracing::Span::current();
+    span.record("symbol", symbol);
+    span.record(
+        "range_secs",
+        end_ts.saturating_sub(start_ts) / 1_000_000_000,
+    );
+    span.record("rows", rows);
+}
+
+// Per-route request counts and latency, keyed by the route pattern so symbols don't
+// multiply the series
+async fn track_requests(
+    State(metrics): State<Arc<ApiMetrics>>,
+    request: Request,
+    next: Next,
+) -> Response {
+    let route = request
+        .extensions()
+        .get::<MatchedPath>()
+        .map_or_else(String::new, |path| path.as_str().to_string());
+    let method = request.method().clone();
+    let started = Instant::now();
+
+    let response = next.run(request).await;
+    metrics.observe_request(
+        &route,
+        method.as_str(),
+        response.status().as_u16(),
+        started.elapsed().as_nanos() as u64,
+    );
+    response
+}
+
+/// Routes whose 200 responses depend only on the store's data for the one symbol in the
+/// path, the query string and `Accept`
+const CACHED_ROUTES: [&str; 5] = [
+    "/history/:symbol",
+    "/count/:symbol",
+    "/indicator/:symbol",
+    "/aggregate/:symbol",
+    "/chart/:symbol",
+];
+
+/// Whole responses of repeated read requests, least recently used evicted first
+///
+/// An entry remembers the symbol's write generation (`FxStore::write_generation`) it was
+/// computed under and is only served while the store still reports it, so any block
+/// written for the symbol invalidates every cached range of it. Ranges reaching into the
+/// current UTC day also expire after `live_ttl`, since a missing `end` means "now".
+pub struct ResponseCache {
+    capacity: usize,
+    live_ttl: Duration,
+    entries: parking_lot::Mutex<CacheEntries>,
+}
+
+#[derive(Default)]
+struct CacheEntries {
+    map: HashMap<CacheKey, CachedResponse>,
+    /// Last use -> key, oldest first
+    lru: BTreeMap<u64, CacheKey>,
+    clock: u64,
+    bytes: usize,
+}
+
+#[derive(Clone, PartialEq, Eq, Hash)]
+struct CacheKey {
+    route: String,
+    symbol: String,
+    /// Query parameters sorted by name, `api_key` left out
+    params: BTreeMap<String, String>,
+    accept: Option<String>,
+}
+
+struct CachedResponse {
+    symbol_id: u16,
+    generation: u64,
+    expires: Option<Instant>,
+    headers: HeaderMap,
+    body: Bytes,
+    size: usize,
+    last_used: u64,
+}
+
+impl CacheKey {
+    /// None for anything that isn't a GET on one of `CACHED_ROUTES`, or that synthesizes
+    /// a cross from other symbols
+    fn for_request(request: &Request, path_params: Option<&RawPathParams>) -> Option<Self> {
+        if request.method() != Method::GET {
+            return None;
+        }
+        let route = request.extensions().get::<MatchedPath>()?.as_str();
+        if !CACHED_ROUTES.contains(&route) {
+            return None;
+        }
+        let symbol = path_params?
+            .iter()
+            .find(|(name, _)| *name == "symbol")?
+            .1
+            .to_string();
+        let Query(mut params) =
+            Query::<BTreeMap<String, String>>::try_from_uri(request.uri()).ok()?;
+        if params.contains_key("synthesize") {
+            return None;
+        }
+        params.remove("api_key");
+        let accept = request
+            .headers()
+            .get(header::ACCEPT)
+            .and_then(|value| value.to_str().ok())
+            .map(str::to_string);
+        Some(Self {
+            route: route.to_string(),
+            symbol,
+            params,
+            accept,
+        })
+    }
+
+    /// Whether the requested range reaches into today (None if `end` doesn't parse)
+    fn reaches_today(&self) -> Option<bool> {
+        let Some(end) = self.params.get("end") else {
+            return Some(true);
+        };
+        let end = parse_datetime(end).ok()?;
+        let today = Utc::now().date_naive().and_hms_opt(0, 0, 0)?.and_utc();
+        Some(end > today)
+    }
+
+    fn size(&self) -> usize {
+        self.route.len()
+            + self.symbol.len()
+            + self.accept.as_ref().map_or(0, String::len)
+            + self
+                .params
+                .iter()
+                .map(|(name, value)| name.len() + value.len())
+                .sum::<usize>()
+    }
+}
+
+impl CacheEntries {
+    fn remove(&mut self, key: &CacheKey) {
+        if let Some(entry) = self.map.remove(key) {
+            self.lru.remove(&entry.last_used);
+            self.bytes -= entry.size;
+        }
+    }
+}
+
+impl ResponseCache {
+    pub fn new(capacity: usize, live_ttl: Duration) -> Self {
+        Self {
+            capacity,
+            live_ttl,
+            entries: parking_lot::Mutex::new(CacheEntries::default()),
+        }
+    }
+
+    /// Headers and body of a live entry computed under `generation`; stale entries are dropped
+    fn get(&self, key: &CacheKey, symbol_id: u16, generation: u64) -> Option<(HeaderMap, Bytes)> {
+        let mut guard = self.entries.lock();
+        let entries = &mut *guard;
+        let entry = entries.map.get(key)?;
+        let fresh = entry.symbol_id == symbol_id
+            && entry.generation == generation
+            && entry.expires.is_none_or(|expires| Instant::now() < expires);
+        if !fresh {
+            entries.remove(key);
+            return None;
+        }
+
+        entries.clock += 1;
+        let entry = entries.map.get_mut(key)?;
+        entries.lru.remove(&entry.last_used);
+        entry.last_used = entries.clock;
+        entries.lru.insert(entries.clock, key.clone());
+        Some((entry.headers.clone(), entry.body.clone()))
+    }
+
+    /// Store a response, evicting the least recently used ones to make room. Returns the
+    /// bytes now held.
+    fn insert(&self, key: CacheKey, mut response: CachedResponse) -> usize {
+        let mut guard = self.entries.lock();
+        let entries = &mut *guard;
+        entries.remove(&key);
+        if response.size > self.capacity {
+            return entries.bytes;
+        }
+        while entries.bytes + response.size > self.capacity {
+            let Some((_, oldest)) = entries.lru.pop_first() else {
+                break;
+            };
+            entries.remove(&oldest);
+        }
+
+        entries.clock += 1;
+        response.last_used = entries.clock;
+        entries.bytes += response.size;
+        entries.lru.insert(entries.clock, key.clone());
+        entries.map.insert(key, response);
+        entries.bytes
+    }
+}
+
+/// Answer repeated reads from `ResponseCache`. Only fully buffered 200 responses are kept;
+/// streamed CSV and NDJSON pages pass through.
+async fn cache_responses(
+    State((cache, store, metrics)): State<(Arc<ResponseCache>, SharedStore, Arc<ApiMetrics>)>,
+    path_params: Option<RawPathParams>,
+    request: Request,
+    next: Next,
+) -> Response {
+    let Some(key) = CacheKey::for_request(&request, path_params.as_ref()) else {
+        return next.run(request).await;
+    };
+    let expires = match key.reaches_today() {
+        Some(false) => None,
+        Some(true) if !cache.live_ttl.is_zero() => Some(Instant::now() + cache.live_ttl),
+        _ => return next.run(request).await,
+    };
+    // Unknown symbols fall through to the handler's 404. The generation is read before the
+    // handler runs, so a write landing meanwhile leaves the entry already stale.
+    let Some((symbol_id, generation)) = store.write_generation(&key.symbol) else {
+        return next.run(request).await;
+    };
+
+    if let Some((headers, body)) = cache.get(&key, symbol_id, generation) {
+        metrics.observe_response_cache(true);
+        let mut response = Response::new(Body::from(body));
+        *response.headers_mut() = headers;
+        return response;
+    }
+    metrics.observe_response_cache(false);
+
+    let response = next.run(request).await;
+    if response.status() != StatusCode::OK {
+        return response;
+    }
+    let (parts, body) = response.into_parts();
+    let Some(size) = HttpBody::size_hint(&body)
+        .exact()
+        .and_then(|size| usize::try_from(size).ok())
+        .filter(|&size| size <= cache.capacity)
+    else {
+        return Response::from_parts(parts, body);
+    };
+    let body = match axum::body::to_bytes(body, size).await {
+        Ok(body) => body,
+        Err(e) => return ApiError::Internal(e.to_string()).into_response(),
+    };
+
+    let size = body.len()
+        + key.size()
+        + parts
+            .headers
+            .iter()
+            .map(|(name, value)| name.as_str().len() + value.len())
+            .sum::<usize>();
+    let held = cache.insert(
+        key,
+        CachedResponse {
+            symbol_id,
+            generation,
+            expires,
+            headers: parts.headers.clone(),
+            body: body.clone(),
+            size,
+            last_used: 0,
+        },
+    );
+    metrics.set_response_cache_bytes(held);
+    Response::from_parts(parts, Body::from(body))
+}
+
+// GET /metrics - Prometheus text exposition
+#[utoipa::path(
+    get,
+    path = "/metrics",
+    responses(
+        (status = 200, description = "Prometheus text exposition", body = String, content_type = "text/plain"),
+    )
+)]
+async fn get_metrics(State(state): State<AppState>) -> impl IntoResponse {
+    let body = state.metrics.render(&state.store.stats());
+    ([(header::CONTENT_TYPE, prometheus::TEXT_FORMAT)], body)
+}
+
+// GET /health?detail=true - Health check, optionally with bar counts per symbol
+#[utoipa::path(
+    get,
+    path = "/health",
+    params(
+        HealthQuery,
+    ),
+    responses(
+        (status = 200, description = "Service is up", body = HealthResponse),
+    )
+)]
+async fn health_check(
+    State(store): State<SharedStore>,
+    Query(params): Query<HealthQuery>,
+) -> Result<Json<HealthResponse>, ApiError> {
+    // Whole-symbol counts come from block metadata, nothing is decompressed
+    let symbols = match params.detail {
+        Some(true) => Some({
+            let store = Arc::clone(&store);
+            tokio::task::spawn_blocking(move || {
+                store
+                    .get_symbols()
+                    .into_iter()
+                    .map(|symbol| {
+                        let count = store.count_range(&symbol, 0, u64::MAX);
+                        (symbol, count)
+                    })
+                    .collect()
+            })
+            .await?
+        }),
+        _ => None,
+    };
+    let stats = store.stats();
+    Ok(Json(HealthResponse {
+        status: "ok".to_string(),
+        service: "fx-store".to_string(),
+        ready: store.is_ready(),
+        symbols_loaded: store.get_symbols().len(),
+        records_loaded: stats.total_records,
+        uptime_seconds: store.uptime().as_secs(),
+        pending_compress_queue: stats.compress_queue_depth,
+        symbols,
+    }))
+}
+
+#[derive(Serialize, ToSchema)]
+pub struct ReadyResponse {
+    pub ready: bool,
+}
+
+// GET /ready - Readiness probe: 503 until initial imports have finished
+#[utoipa::path(
+    get,
+    path = "/ready",
+    responses(
+        (status = 200, description = "Data is loaded", body = ReadyResponse),
+        (status = 503, description = "Still loading", body = ErrorResponse),
+    )
+)]
+async fn ready_check(State(store): State<SharedStore>) -> Result<Json<ReadyResponse>, ApiError> {
+    if !store.is_ready() {
+        return Err(ApiError::Unavailable("store is still loading".to_string()));
+    }
+    Ok(Json(ReadyResponse { ready: true }))
+}
+
+// GET /openapi.json - The OpenAPI document
+async fn get_openapi() -> Json<utoipa::openapi::OpenApi> {
+    Json(ApiDoc::openapi())
+}
+
+// GET /docs - Swagger UI over /openapi.json
+async fn get_docs() -> Html<&'static str> {
+    Html(SWAGGER_UI_HTML)
+}
+
+/// Resolve optional start/end strings into a nanosecond range (default: last day)
+///
+/// The range is half-open: a bar stamped exactly at `end` belongs to the next range, so
+/// consecutive windows never repeat a bar. A start before 1970 is clamped to the epoch;
+/// start must be before end and the span no longer than `max_span`. Returns the inclusive
+/// bounds `(start, end - 1)` the store's range queries take.
+fn parse_range(
+    start: Option<&str>,
+    end: Option<&str>,
+    max_span: Duration,
+) -> Result<(u64, u64), ApiError> {
+    let parse = |param, value: &str| {
+        parse_datetime(value)
+            .ok()
+            .and_then(|dt| dt.timestamp_nanos_opt())
+            .ok_or_else(|| ApiError::InvalidDate {
+                param,
+                value: value.to_string(),
+            })
+    };
+
+    let end_ts = match end {
+        Some(end_str) => {
+            u64::try_from(parse("end", end_str)?).map_err(|_| ApiError::InvalidDate {
+                param: "end",
+                value: end_str.to_string(),
+            })?
+        }
+        None => Utc::now()
+            .timestamp_nanos_opt()
+            .map_or(MAX_TS, |now| now.max(0) as u64),
+    };
+
+    let start_ts = match start {
+        Some(start_str) => parse("start", start_str)?.max(0) as u64,
+        None => end_ts.saturating_sub(NANOS_PER_DAY), // Default to 1 day ago
+    };
+
+    let range = TimeRange::new(start_ts, end_ts);
+    let max_span = u64::try_from(max_span.as_nanos()).unwrap_or(u64::MAX);
+    range.check(Some(max_span))?;
+    Ok((range.start, range.end - 1))
+}
+
+const CURSOR_ALPHABET: &[u8; 64] =
+    b"ABCDEFGHIJKLMNOPQRSTUVWXYZabcdefghijklmnopqrstuvwxyz0123456789-_";
+
+/// Nanosecond timestamp → URL-safe unpadded base64 of its big-endian bytes
+fn encode_cursor(ts: u64) -> String {
+    let bytes = ts.to_be_bytes();
+    let mut out = String::with_capacity(11);
+    for chunk in bytes.chunks(3) {
+        let mut group = [0u8; 3];
+        group[..chunk.len()].copy_from_slice(chunk);
+        let n = u32::from_be_bytes([0, group[0], group[1], group[2]]);
+        for i in 0..=chunk.len() {
+            out.push(CURSOR_ALPHABET[(n >> (18 - 6 * i) & 0x3f) as usize] as char);
+        }
+    }
+    out
+}
+
+fn decode_cursor(cursor: &str) -> Option<u64> {
+    if cursor.len() != 11 {
+        return None;
+    }
+    let mut bits: u128 = 0;
+    for c in cursor.bytes() {
+        let value = CURSOR_ALPHABET.iter().position(|&a| a == c)?;
+        bits = bits << 6 | value as u128;
+    }
+    // 66 bits encoded, the trailing 2 are padding
+    if bits & 0b11 != 0 {
+        return None;
+    }
+    Some((bits >> 2) as u64)
+}
+
+/// Require `Authorization: Bearer <write_token>` on write endpoints
+fn authorize(config: &ApiConfig, headers: &HeaderMap, verified_key: bool) -> Result<(), ApiError> {
+    if verified_key {
+        return Ok(());
+    }
+    let Some(token) = config.write_token.as_deref() else {
+        return Err(ApiError::WritesDisabled);
+    };
+    match bearer_token(headers) {
+        Some(given) if constant_time_eq(given.as_bytes(), token.as_bytes()) => Ok(()),
+        _ => Err(ApiError::Unauthorized),
+    }
+}
+
+fn bearer_token(headers: &HeaderMap) -> Option<&str> {
+    headers
+        .get(header::AUTHORIZATION)
+        .and_then(|value| value.to_str().ok())
+        .and_then(|value| value.strip_prefix("Bearer "))
+}
+
+/// Newest timestamp (nanoseconds) ingestion accepts right now
+fn latest_allowed_ts(config: &ApiConfig) -> u64 {
+    let now = Utc::now().timestamp_nanos_opt().unwrap_or(i64::MAX);
+    let skew = u64::try_from(config.max_future_skew.as_nanos()).unwrap_or(u64::MAX);
+    u64::try_from(now)
+        .unwrap_or(0)
+        .saturating_add(skew)
+        .min(MAX_TS)
+}
+
+/// 200 when everything was accepted, 207 when some items were rejected
+fn ingest_status(results: &[IngestResult]) -> StatusCode {
+    if results.iter().all(|result| result.reason.is_none()) {
+        StatusCode::OK
+    } else {
+        StatusCode::MULTI_STATUS
+    }
+}
+
+fn check_ts(ts: u64, latest: u64) -> Result<u64, String> {
+    if ts == 0 {
+        Err("timestamp must be after the epoch".to_string())
+    } else if ts > latest {
+        Err("timestamp is in the future".to_string())
+    } else {
+        Ok(ts)
+    }
+}
+
+/// Accept a write path symbol that already exists (or is an alias), or a new one
+/// spelled as six ASCII letters (`EURUSD`) or `BASE/QUOTE` (`XAU/USD`)
+fn check_symbol(store: &FxStore, symbol: &str) -> Result<(), ApiError> {
+    let code = |part: &str| {
+        (1..=8).contains(&part.len()) && part.bytes().all(|b| b.is_ascii_alphanumeric())
+    };
+    let well_formed = match symbol.split_once('/') {
+        Some((base, quote)) => code(base) && code(quote),
+        None => symbol.len() == 6 && symbol.bytes().all(|b| b.is_ascii_alphabetic()),
+    };
+    if well_formed || store.has_symbol(symbol) {
+        Ok(())
+    } else {
+        Err(ApiError::invalid(
+            "symbol",
+            "expected six ASCII letters like EURUSD or BASE/QUOTE",
+        ))
+    }
+}
+
+fn check_bar(bar: &BarInput, latest: u64, scale: u32) -> Result<OHLCV, String> {
+    let prices = [bar.open, bar.high, bar.low, bar.close];
+    if prices.iter().any(|p| !p.is_finite() || *p <= 0.0) {
+        return Err("prices must be positive numbers".to_string());
+    }
+    if bar.high < bar.low {
+        return Err("high is below low".to_string());
+    }
+    if bar.high < bar.open.max(bar.close) {
+        return Err("high is below open/close".to_string());
+    }
+    if bar.low > bar.open.min(bar.close) {
+        return Err("low is above open/close".to_string());
+    }
+    let ts = u64::try_from(bar.ts)
+        .ok()
+        .and_then(|secs| secs.checked_mul(1_000_000_000))
+        .ok_or("timestamp out of range")?;
+    let ts = check_ts(ts, latest)?;
+
+    let scaled = |price: f64| scale_price(price, scale).map_err(|e| e.to_string());
+    let [open, high, low, close] = [
+        scaled(bar.open)?,
+        scaled(bar.high)?,
+        scaled(bar.low)?,
+        scaled(bar.close)?,
+    ];
+    Ok(OHLCV {
+        ts,
+        open,
+        high,
+        low,
+        close,
+        volume: bar.volume,
+        ..Default::default()
+    })
+}
+
+fn check_tick(tick: &TickInput, latest: u64, scale: u32) -> Result<Tick, String> {
+    if !(tick.bid.is_finite() && tick.ask.is_finite() && tick.bid > 0.0) {
+        return Err("bid/ask must be positive numbers".to_string());
+    }
+    if tick.ask < tick.bid {
+        return Err("ask is below bid".to_string());
+    }
+    let ts = u64::try_from(tick.ts_ms)
+        .ok()
+        .and_then(|ms| ms.checked_mul(1_000_000))
+        .ok_or("timestamp out of range")?;
+    let ts = check_ts(ts, latest)?;
+
+    let scaled = |price: f64| scale_price(price, scale).map_err(|e| e.to_string());
+    Ok(Tick {
+        ts,
+        bid: scaled(tick.bid)?,
+        ask: scaled(tick.ask)?,
+        volume: tick.volume,
+        ..Default::default()
+    })
+}
+
+/// `EURUSD, XAUUSD,` → `["EURUSD", "XAUUSD"]`
+fn split_symbols(symbols: &str) -> Vec<String> {
+    symbols
+        .split(',')
+        .map(str::trim)
+        .filter(|s| !s.is_empty())
+        .map(str::to_string)
+        .collect()
+}
+
+fn parse_transform(
+    transform: Option<&str>,
+    brick: Option<u32>,
+) -> Result<Option<Transform>, ApiError> {
+    match transform {
+        None => Ok(None),
+        Some("heikin_ashi") => Ok(Some(Transform::HeikinAshi)),
+        Some("renko") => match brick {
+            Some(brick) if brick > 0 => Ok(Some(Transform::Renko { brick })),
+            _ => Err(ApiError::invalid(
+                "brick",
+                "renko needs a positive brick size",
+            )),
+        },
+        Some(other) => Err(ApiError::invalid(
+            "transform",
+            format!("unknown transform: {}", other),
+        )),
+    }
+}
+
+/// Body encoding of `/history/{symbol}`
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum HistoryFormat {
+    Json,
+    Csv,
+    Ndjson,
+    /// Only negotiated when built with the `parquet` feature
+    Parquet,
+}
+
+/// Media type of a Parquet file
+const PARQUET_CONTENT_TYPE: &str = "application/vnd.apache.parquet";
+
+impl HistoryFormat {
+    /// `format=` wins over `Accept`; anything unrecognised in `Accept` falls back to JSON
+    fn negotiate(format: Option<&str>, headers: &HeaderMap) -> Result<Self, ApiError> {
+        match format {
+            Some("json") => return Ok(Self::Json),
+            Some("csv") => return Ok(Self::Csv),
+            Some("ndjson") => return Ok(Self::Ndjson),
+            Some("parquet") if cfg!(feature = "parquet") => return Ok(Self::Parquet),
+            Some(other) => {
+                return Err(ApiError::invalid(
+                    "format",
+                    format!("format must be json, csv, ndjson or parquet, got {}", other),
+                ));
+            }
+            None => {}
+        }
+        let accept = headers
+            .get(header::ACCEPT)
+            .and_then(|value| value.to_str().ok())
+            .unwrap_or("");
+        if accept.contains("text/csv") {
+            Ok(Self::Csv)
+        } else if accept.contains("application/x-ndjson") {
+            Ok(Self::Ndjson)
+        } else if cfg!(feature = "parquet") && accept.contains(PARQUET_CONTENT_TYPE) {
+            Ok(Self::Parquet)
+        } else {
+            Ok(Self::Json)
+        }
+    }
+}
+
+/// Bars formatted per chunk of the streamed CSV/NDJSON body
+const STREAM_CHUNK_BARS: usize = 4096;
+
+/// Stream candles as CSV or NDJSON without building the whole body in memory
+fn stream_history(
+    symbol: &str,
+    records: Vec<OHLCV>,
+    format: HistoryFormat,
+    scale: u32,
+) -> impl IntoResponse {
+    let decimals = price_decimals(&records, scale);
+    let symbol = symbol.to_string();
+    let content_type = match format {
+        HistoryFormat::Csv => "text/csv; charset=utf-8",
+        _ => "application/x-ndjson",
+    };
+
+    let header_line = (format == HistoryFormat::Csv)
+        .then(|| Bytes::from_static(b"ts,open,high,low,close,volume\n"));
+    let chunks = (0..records.len())
+        .step_by(STREAM_CHUNK_BARS)
+        .map(move |start| {
+            let end = (start + STREAM_CHUNK_BARS).min(records.len());
+            let mut out = String::with_capacity((end - start) * 96);
+            for rec in &records[start..end] {
+                write_history_line(&mut out, &symbol, rec, decimals, scale, format);
+            }
+            Bytes::from(out)
+        });
+    let body = futures_util::stream::iter(
+        header_line
+            .into_iter()
+            .chain(chunks)
+            .map(Ok::<_, Infallible>),
+    );
+
+    (
+        [(header::CONTENT_TYPE, content_type)],
+        Body::from_stream(body),
+    )
+}
+
+/// Raw `/history` JSON pages spanning more than this are streamed by `stream_history_page`
+const STREAM_JSON_MIN_SPAN: u64 = NANOS_PER_DAY;
+
+/// Chunks a streamed JSON page may run ahead of the client
+const STREAM_JSON_BACKLOG: usize = 4;
+
+/// Stream a `HistoryPage` straight off the block iterator, `STREAM_CHUNK_BARS` candles per
+/// chunk, so memory stays bounded however wide the range is
+///
+/// Query errors (unknown symbol, a corrupt block with `FX_STORE_ON_CORRUPT=error`) arrive
+/// before the first chunk and still become an error status. `corrupt_days` follows the
+/// array, once every block has been read.
+async fn stream_history_page(
+    store: SharedStore,
+    symbol: String,
+    start_ts: u64,
+    end_ts: u64,
+) -> Result<Response, ApiError> {
+    let (tx, mut rx) =
+        tokio::sync::mpsc::channel::<Result<Bytes, FxStoreError>>(STREAM_JSON_BACKLOG);
+    let span = tracing::Span::current();
+    tokio::task::spawn_blocking(move || {
+        let records = match store.try_query_range(&symbol, start_ts, end_ts) {
+            Ok(records) => records,
+            Err(e) => {
+                tx.blocking_send(Err(e)).ok();
+                return;
+            }
+        };
+        let scale = store.price_scale(&symbol);
+        let mut out = b"{\"data\":[".to_vec();
+        let mut rows = 0;
+        for rec in records {
+            if rows > 0 {
+                out.push(b',');
+            }
+            let candle = PriceResponse::from_record(&store, &rec, scale);
+            serde_json::to_writer(&mut out, &candle).expect("candles serialize");
+            rows += 1;
+            // A failed send means the client went away
+            if rows % STREAM_CHUNK_BARS == 0
+                && tx
+                    .blocking_send(Ok(Bytes::from(std::mem::take(&mut out))))
+                    .is_err()
+            {
+                return;
+            }
+        }
+        span.in_scope(|| record_query(&symbol, start_ts, end_ts, rows));
+
+        out.extend_from_slice(b"],\"next_cursor\":null");
+        let corrupt_days: Vec<String> = store
+            .corrupt_days(&symbol, start_ts, end_ts)
+            .into_iter()
+            .map(format_date)
+            .collect();
+        if !corrupt_days.is_empty() {
+            out.extend_from_slice(b",\"corrupt_days\":");
+            serde_json::to_writer(&mut out, &corrupt_days).expect("dates serialize");
+        }
+        out.push(b'}');
+        tx.blocking_send(Ok(Bytes::from(out))).ok();
+    });
+
+    let first = match rx.recv().await {
+        Some(first) => first?,
+        None => return Err(ApiError::Internal("history stream ended early".to_string())),
+    };
+    let rest = futures_util::stream::unfold(rx, |mut rx| async move {
+        rx.recv().await.map(|chunk| (chunk, rx))
+    });
+    let body = futures_util::stream::once(std::future::ready(Ok(first))).chain(rest);
+    Ok((
+        [(header::CONTENT_TYPE, "application/json")],
+        Body::from_stream(body),
+    )
+        .into_response())
+}
+
+/// Bytes buffered before a streamed Parquet body hands a chunk to the client
+#[cfg(feature = "parquet")]
+const STREAM_PARQUET_CHUNK: usize = 1 << 20;
+
+/// `Write` end of a streamed body: buffers the encoder's output and sends it in chunks
+#[cfg(feature = "parquet")]
+struct ChunkWriter {
+    tx: tokio::sync::mpsc::Sender<Result<Bytes, FxStoreError>>,
+    buf: Vec<u8>,
+}
+
+#[cfg(feature = "parquet")]
+impl std::io::Write for ChunkWriter {
+    fn write(&mut self, data: &[u8]) -> std::io::Result<usize> {
+        self.buf.extend_from_slice(data);
+        if self.buf.len() >= STREAM_PARQUET_CHUNK {
+            self.flush()?;
+        }
+        Ok(data.len())
+    }
+
+    fn flush(&mut self) -> std::io::Result<()> {
+        if self.buf.is_empty() {
+            return Ok(());
+        }
+        // A failed send means the client went away
+        self.tx
+            .blocking_send(Ok(Bytes::from(std::mem::take(&mut self.buf))))
+            .map_err(|_| std::io::Error::from(std::io::ErrorKind::BrokenPipe))
+    }
+}
+
+/// Stream raw bars as a Parquet file written block by block with `FxStore::write_parquet`,
+/// so memory stays at about one block plus one chunk however wide the range is
+///
+/// Query errors arrive before the first chunk and still become an error status.
+#[cfg(feature = "parquet")]
+async fn stream_parquet(
+    store: SharedStore,
+    symbol: String,
+    start_ts: u64,
+    end_ts: u64,
+) -> Result<Response, ApiError> {
+    let (tx, mut rx) =
+        tokio::sync::mpsc::channel::<Result<Bytes, FxStoreError>>(STREAM_JSON_BACKLOG);
+    let span = tracing::Span::current();
+    tokio::task::spawn_blocking(move || {
+        let writer = ChunkWriter {
+            tx: tx.clone(),
+            buf: Vec::new(),
+        };
+        match store.write_parquet(&symbol, start_ts, end_ts, writer) {
+            Ok(rows) => span.in_scope(|| record_query(&symbol, start_ts, end_ts, rows)),
+            Err(e) => {
+                tx.blocking_send(Err(e)).ok();
+            }
+        }
+    });
+
+    let first = match rx.recv().await {
+        Some(first) => first?,
+        None => return Err(ApiError::Internal("parquet stream ended early".to_string())),
+    };
+    let rest = futures_util::stream::unfold(rx, |mut rx| async move {
+        rx.recv().await.map(|chunk| (chunk, rx))
+    });
+    let body = futures_util::stream::once(std::future::ready(Ok(first))).chain(rest);
+    Ok((
+        [(header::CONTENT_TYPE, PARQUET_CONTENT_TYPE)],
+        Body::from_stream(body),
+    )
+        .into_response())
+}
+
+pub(crate) fn write_history_line(
+    out: &mut String,
+    symbol: &str,
+    rec: &OHLCV,
+    decimals: usize,
+    scale: u32,
+    format: HistoryFormat,
+) {
+    use std::fmt::Write;
+
+    let ts = rec.ts / 1_000_000_000;
+    let [open, high, low, close] =
+        [rec.open, rec.high, rec.low, rec.close].map(|p| format_price(p, decimals, scale));
+    let volume = rec.total_volume();
+    let _ = match format {
+        HistoryFormat::Csv => {
+            writeln!(out, "{},{},{},{},{},{}", ts, open, high, low, close, volume)
+        }
+        // Same shape as the JSON candles, spread included only when known and at full
+        // precision, since it is far smaller than the prices `decimals` was fitted to
+        _ if rec.spread != 0 => writeln!(
+            out,
+            r#"{{"symbol":"{}","timestamp":{},"open":{},"high":{},"low":{},"close":{},"volume":{},"spread":{}}}"#,
+            symbol,
+            ts,
+            open,
+            high,
+            low,
+            close,
+            volume,
+            format_price(u32::from(rec.spread), scale.ilog10() as usize, scale)
+        ),
+        _ => writeln!(
+            out,
+            r#"{{"symbol":"{}","timestamp":{},"open":{},"high":{},"low":{},"close":{},"volume":{}}}"#,
+            symbol, ts, open, high, low, close, volume
+        ),
+    };
+}
+
+/// Decimal places the prices actually use (at most the `scale` digits the store keeps)
+pub(crate) fn price_decimals(records: &[OHLCV], scale: u32) -> usize {
+    let digits = scale.ilog10() as usize;
+    let trailing_zeros = |price: u32| {
+        (1..=digits as u32)
+            .take_while(|&k| price.is_multiple_of(10u32.pow(k)))
+            .count()
+    };
+    records
+        .iter()
+        .flat_map(|rec| [rec.open, rec.high, rec.low, rec.close])
+        .map(|price| digits - trailing_zeros(price))
+        .max()
+        .unwrap_or(0)
+}
+
+/// Exact decimal rendering of a `scale`d price with `decimals` places
+fn format_price(price: u32, decimals: usize, scale: u32) -> String {
+    let int = price / scale;
+    if decimals == 0 {
+        return int.to_string();
+    }
+    let digits = scale.ilog10() as usize;
+    let frac = format!("{:0width$}", price % scale, width = digits);
+    format!("{}.{}", int, &frac[..decimals])
+}
+
+/// Query a range, resampling when an interval is given
+///
+/// Unknown symbols are an error; a known symbol with nothing in range is an empty Vec.
+fn query_records(
+    store: &FxStore,
+    symbol: &str,
+    start_ts: u64,
+    end_ts: u64,
+    interval: Option<u64>,
+    alignment: Alignment,
+) -> Result<Vec<OHLCV>, FxStoreError> {
+    let records: Vec<OHLCV> =
+        if end_ts.saturating_sub(start_ts) > PARALLEL_QUERY_DAYS * 86_400_000_000_000 {
+            if !store.has_symbol(symbol) {
+                return Err(FxStoreError::UnknownSymbol(symbol.to_string()));
+            }
+            store.query_range_par(symbol, start_ts, end_ts)
+        } else {
+            store.try_query_range(symbol, start_ts, end_ts)?.collect()
+        };
+
+    Ok(match interval {
+        Some(secs) => resample(&records, secs, alignment),
+        None => records,
+    })
+}
+
+/// Apply the optional transform and convert for the response
+fn build_history(
+    store: &FxStore,
+    records: &[OHLCV],
+    transform: Option<Transform>,
+    scale: u32,
+) -> HistoryData {
+    match transform {
+        Some(Transform::Renko { brick }) => {
+            // Bricks span several bars, all of one symbol
+            let symbol = records
+                .first()
+                .and_then(|rec| store.symbol_name(rec.symbol_id))
+                .unwrap_or_default();
+            let bricks = transform_renko(records, brick);
+            HistoryData::Bricks(
+                bricks
+                    .iter()
+                    .map(|brick| RenkoResponse {
+                        symbol: symbol.clone(),
+                        ts_open: (brick.ts_open / 1_000_000_000) as i64,
+                        ts_close: (brick.ts_close / 1_000_000_000) as i64,
+                        open: brick.open as f64 / scale as f64,
+                        close: brick.close as f64 / scale as f64,
+                        direction: brick.direction,
+                    })
+                    .collect(),
+            )
+        }
+        transform => {
+            let candles = match transform {
+                Some(Transform::HeikinAshi) => transform_heikin_ashi(records),
+                _ => records.to_vec(),
+            };
+            HistoryData::Candles(
+                candles
+                    .iter()
+                    .map(|ohlcv| PriceResponse::from_record(store, ohlcv, scale))
+                    .collect(),
+            )
+        }
+    }
+}
+
+/// Pair indicator values with bar timestamps, dropping the warm-up period
+fn indicator_points(records: &[OHLCV], values: &[Option<f64>]) -> Vec<IndicatorPoint> {
+    records
+        .iter()
+        .zip(values)
+        .filter_map(|(rec, value)| {
+            Some(IndicatorPoint {
+                timestamp: (rec.ts / 1_000_000_000) as i64,
+                value: (*value)?,
+            })
+        })
+        .collect()
+}
+
+fn parse_interval_param(interval: Option<&str>) -> Result<Option<u64>, ApiError> {
+    interval
+        .map(|interval| {
+            parse_interval(interval).ok_or_else(|| {
+                ApiError::invalid("interval", format!("invalid interval: {}", interval))
+            })
+        })
+        .transpose()
+}
+
+/// `SYMBOL:weight` pairs of `/basket`; a missing weight is 1
+fn parse_components(list: &str) -> Result<Vec<(String, f64)>, ApiError> {
+    let components = list
+        .split(',')
+        .map(str::trim)
+        .filter(|item| !item.is_empty())
+        .map(|item| {
+            let (symbol, weight) = item.split_once(':').unwrap_or((item, "1"));
+            match weight.trim().parse::<f64>() {
+                Ok(weight) if weight.is_finite() => Ok((symbol.trim().to_string(), weight)),
+                _ => Err(ApiError::invalid(
+                    "components",
+                    format!("invalid weight in {:?}", item),
+                )),
+            }
+        })
+        .collect::<Result<Vec<_>, _>>()?;
+    if !(1..=MAX_BASKET_COMPONENTS).contains(&components.len()) {
+        return Err(ApiError::invalid(
+            "components",
+            format!(
+                "components must list 1 to {} symbols",
+                MAX_BASKET_COMPONENTS
+            ),
+        ));
+    }
+    Ok(components)
+}
+
+/// Bars of the cross `symbol` built from the `synthesize=A,B` legs
+///
+/// Unknown legs are 404. Legs that don't share exactly one currency, or that make a
+/// different cross than the path asks for, are 400.
+async fn query_cross(
+    store: &SharedStore,
+    symbol: &str,
+    legs: &str,
+    start_ts: u64,
+    end_ts: u64,
+) -> Result<Vec<OHLCV>, ApiError> {
+    let Some((first, second)) = legs.split_once(',') else {
+        return Err(ApiError::invalid(
+            "synthesize",
+            format!("expected two comma-separated pairs, got {:?}", legs),
+        ));
+    };
+    let (first, second) = (first.trim().to_string(), second.trim().to_string());
+    let leg = |name: &str| {
+        store
+            .symbol(name)
+            .ok_or_else(|| ApiError::SymbolNotFound(name.to_string()))
+    };
+    let cross = Cross::new(&leg(&first)?, &leg(&second)?).ok_or_else(|| {
+        ApiError::invalid(
+            "synthesize",
+            format!("{} and {} do not share exactly one currency", first, second),
+        )
+    })?;
+    if cross.name() != symbol {
+        return Err(ApiError::invalid(
+            "synthesize",
+            format!("{},{} make {}, not {}", first, second, cross.name(), symbol),
+        ));
+    }
+
+    let store = Arc::clone(store);
+    Ok(tokio::task::spawn_blocking(move || {
+        store.synthetic_cross(&first, &second, start_ts, end_ts)
+    })
+    .await??)
+}
+
+fn parse_alignment(
+    align_tz: Option<&str>,
+    session_offset: Option<&str>,
+) -> Result<Alignment, ApiError> {
+    let tz = align_tz
+        .map(|name| {
+            name.parse::<Tz>()
+                .map_err(|_| ApiError::invalid("align_tz", format!("unknown time zone: {}", name)))
+        })
+        .transpose()?;
+    let session_offset_secs = match session_offset {
+        Some(offset) => {
+            let (sign, magnitude) = match offset.strip_prefix('-') {
+                Some(magnitude) => (-1, magnitude),
+                None => (1, offset.strip_prefix('+').unwrap_or(offset)),
+            };
+            let secs = parse_interval(magnitude).ok_or_else(|| {
+                ApiError::invalid(
+                    "session_offset",
+                    format!("invalid session offset: {}", offset),
+                )
+            })?;
+            sign * secs as i64
+        }
+        None => 0,
+    };
+    Ok(Alignment {
+        tz,
+        session_offset_secs,
+    })
+}
+
+pub fn parse_datetime(date_str: &str) -> Result<DateTime<Utc>, anyhow::Error> {
+    // Try different formats
+    if let Ok(dt) = DateTime::parse_from_rfc3339(date_str) {
+        return Ok(dt.with_timezone(&Utc));
+    }
+
+    if let Ok(dt) = chrono::NaiveDateTime::parse_from_str(date_str, "%Y-%m-%d %H:%M:%S") {
+        return Ok(dt.and_utc());
+    }
+
+    if let Ok(date) = chrono::NaiveDate::parse_from_str(date_str, "%Y-%m-%d") {
+        return Ok(date.and_hms_opt(0, 0, 0).unwrap().and_utc());
+    }
+
+    Err(anyhow::anyhow!("Unable to parse date: {}", date_str))
+}
+
+/// Bind and start serving in the background
+///
+/// Returns once the listener is bound, so `port: 0` can be used and read back from
+/// `RunningServer::addr`. Once `shutdown` resolves the listener closes, event streams
+/// end, and in-flight requests get `shutdown_timeout` to finish before they are dropped.
+/// The store is then flushed, so queued blocks are compressed and written out.
+pub async fn start_server(
+    store: SharedStore,
+    config: ServerConfig,
+    shutdown: impl Future<Output = ()> + Send + 'static,
+) -> anyhow::Result<RunningServer> {
+    let signal = ShutdownSignal::default();
+    let app = build_router(Arc::clone(&store), config.api, signal.clone())?;
+
+    let listener = tokio::net::TcpListener::bind(SocketAddr::new(config.bind, config.port)).await?;
+    let addr = listener.local_addr()?;
+    let drain_timeout = config.shutdown_timeout;
+    let task = tokio::spawn(async move {
+        let serve = axum::serve(
+            listener,
+            app.into_make_service_with_connect_info::<SocketAddr>(),
+        )
+        .with_graceful_shutdown({
+            let signal = signal.clone();
+            async move {
+                shutdown.await;
+                tracing::info!("shutting down, draining in-flight requests");
+                signal.trigger();
+            }
+        });
+        let deadline = async {
+            signal.triggered().await;
+            tokio::time::sleep(drain_timeout).await;
+        };
+        let served = tokio::select! {
+            served = serve => served,
+            _ = deadline => {
+                tracing::warn!(
+                    timeout_secs = drain_timeout.as_secs_f64(),
+                    "requests still running at the shutdown deadline were dropped"
+                );
+                Ok(())
+            }
+        };
+
+        let flushed = tokio::task::spawn_blocking(move || {
+            store.finalize_realtime();
+            store.flush_now()
+        })
+        .await
+        .unwrap_or_else(|e| std::panic::resume_unwind(e.into_panic()));
+        match flushed {
+            Ok(blocks) => tracing::info!(blocks, "store flushed"),
+            Err(e) => tracing::error!(error = %e, "failed to flush the store on shutdown"),
+        }
+        served
+    });
+
+    Ok(RunningServer { addr, task })
+}
+
+/// Handle to a server started by `start_server`
+pub struct RunningServer {
+    /// Actually bound address
+    pub addr: SocketAddr,
+    task: tokio::task::JoinHandle<std::io::Result<()>>,
+}
+
+impl RunningServer {
+    /// Wait until the server has shut down
+    pub async fn wait(self) -> anyhow::Result<()> {
+        self.task.await??;
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use axum::body::{Body, to_bytes};
+    use axum::http::Request;
+    use tower::ServiceExt;
+
+    const MINUTE: u64 = 60_000_000_000;
+    // 2024-01-02 00:00:00 UTC
+    const DAY_START: u64 = 1_704_153_600_000_000_000;
+
+    /// `count` minute bars of EURUSD from DAY_START, loaded through a CSV import
+    fn app_with_bars(count: u64, config: ApiConfig) -> Router {
+        create_app_with(Arc::new(store_with_bars(count)), config).unwrap()
+    }
+
+    fn store_with_bars(count: u64) -> FxStore {
+        use std::fmt::Write;
+
+        let mut csv = String::from("time,open,high,low,close,volume\n");
+        for i in 0..count {
+            let close = 110_000 + i % 7 * 10;
+            let ts = chrono::DateTime::from_timestamp_nanos((DAY_START + i * MINUTE) as i64);
+            writeln!(
+                csv,
+                "{},1.{:05},1.{:05},1.{:05},1.{:05},1",
+                ts.to_rfc3339(),
+                close - 100_000,
+                close - 100_000 + 20,
+                close - 100_000 - 20,
+                close - 100_000
+            )
+            .unwrap();
+        }
+        let path = std::env::temp_dir().join(format!(
+            "fx-store-api-{}-{:?}.csv",
+            std::process::id(),
+            std::thread::current().id()
+        ));
+        std::fs::write(&path, csv).unwrap();
+
+        let store = FxStore::new();
+        store.import_csv(path.to_str().unwrap(), "EURUSD").unwrap();
+        store.flush();
+        std::fs::remove_file(&path).ok();
+        store
+    }
+
+    async fn get_json(app: Router, uri: &str) -> (StatusCode, serde_json::Value) {
+        let response = app
+            .oneshot(Request::get(uri).body(Body::empty()).unwrap())
+            .await
+            .unwrap();
+        let status = response.status();
+        let body = to_bytes(response.into_body(), usize::MAX).await.unwrap();
+        (status, serde_json::from_slice(&body).unwrap_or_default())
+    }
+
+    const RANGE: &str = "start=2024-01-02&end=2024-01-03";
+
+    #[tokio::test]
+    async fn indicator_returns_series_after_warm_up() {
+        let app = app_with_bars(100, ApiConfig::default());
+        let (status, body) = get_json(
+            app,
+            &format!("/indicator/EURUSD?name=rsi&period=14&{}", RANGE),
+        )
+        .await;
+        assert_eq!(status, StatusCode::OK);
+
+        let points = body.as_array().unwrap();
+        assert_eq!(points.len(), 100 - 14);
+        assert_eq!(
+            points[0]["timestamp"],
+            ((DAY_START + 14 * MINUTE) / 1_000_000_000) as i64
+        );
+    }
+
+    #[tokio::test]
+    async fn indicator_returns_named_series_for_macd() {
+        let app = app_with_bars(100, ApiConfig::default());
+        let (status, body) = get_json(app, &format!("/indicator/EURUSD?name=macd&{}", RANGE)).await;
+        assert_eq!(status, StatusCode::OK);
+        assert_eq!(body["macd"].as_array().unwrap().len(), 100 - 25);
+        assert_eq!(body["signal"].as_array().unwrap().len(), 100 - 33);
+    }
+
+    #[tokio::test]
+    async fn indicator_reports_prices_at_the_symbol_scale() {
+        use std::fmt::Write;
+
+        let mut csv = String::new();
+        for minute in 0..30 {
+            let ts = chrono::DateTime::from_timestamp_nanos((DAY_START + minute * MINUTE) as i64);
+            writeln!(
+                csv,
+                "{},150.125,150.200,150.050,150.125,1",
+                ts.format("%Y%m%d %H%M%S")
+            )
+            .unwrap();
+        }
+        let path =
+            std::env::temp_dir().join(format!("fx-store-api-jpy-{}.csv", std::process::id()));
+        std::fs::write(&path, csv).unwrap();
+        let store = FxStore::new();
+        let options = crate::store::ImportOptions {
+            detect_scale: true,
+            ..Default::default()
+        };
+        store
+            .import_csv_with(path.to_str().unwrap(), "USDJPY", &options)
+            .unwrap();
+        store.flush();
+        std::fs::remove_file(&path).ok();
+        assert_eq!(store.price_scale("USDJPY"), 1_000);
+        let app = create_app(Arc::new(store));
+
+        for name in ["sma", "ema", "bollinger"] {
+            let uri = format!("/indicator/USDJPY?name={}&period=5&{}", name, RANGE);
+            let (status, body) = get_json(app.clone(), &uri).await;
+            assert_eq!(status, StatusCode::OK, "{}", name);
+            let points = body
+                .as_array()
+                .or_else(|| body["middle"].as_array())
+                .unwrap();
+            assert!(
+                points
+                    .iter()
+                    .all(|point| (point["value"].as_f64().unwrap() - 150.125).abs() < 1e-9),
+                "{}: {}",
+                name,
+                points[0]
+            );
+        }
+        let uri = format!("/indicator/USDJPY?name=atr&period=5&{}", RANGE);
+        let (_, body) = get_json(app, &uri).await;
+        let atr = body[0]["value"].as_f64().unwrap();
+        assert!((atr - 0.15).abs() < 1e-9, "{}", atr);
+    }
+
+    #[tokio::test]
+    async fn indicator_rejects_bad_parameters_with_json_error() {
+        let app = app_with_bars(10, ApiConfig::default());
+        for query in ["name=nope", "name=rsi&period=0", "name=sma&period=50"] {
+            let (status, body) = get_json(
+                app.clone(),
+                &format!("/indicator/EURUSD?{}&{}", query, RANGE),
+            )
+            .await;
+            assert_eq!(status, StatusCode::BAD_REQUEST, "{}", query);
+            assert!(body["error"].is_string());
+        }
+    }
+
+    #[tokio::test]
+    async fn indicator_caps_point_count() {
+        let config = ApiConfig {
+            max_indicator_points: 60,
+            ..Default::default()
+        };
+        let app = app_with_bars(10, config);
+        let uri = format!("/indicator/EURUSD?name=sma&{}", RANGE);
+        let (status, _) = get_json(app.clone(), &uri).await;
+        assert_eq!(status, StatusCode::PAYLOAD_TOO_LARGE);
+
+        let (status, _) = get_json(app, &format!("{}&interval=1h&period=2", uri)).await;
+        assert_eq!(status, StatusCode::BAD_REQUEST);
+    }
+
+    #[tokio::test]
+    async fn history_applies_transforms() {
+        let app = app_with_bars(100, ApiConfig::default());
+
+        let (status, body) = get_json(
+            app.clone(),
+            &format!("/history/EURUSD?transform=heikin_ashi&limit=10&{}", RANGE),
+        )
+        .await;
+        assert_eq!(status, StatusCode::OK);
+        assert_eq!(body["data"].as_array().unwrap().len(), 10);
+
+        let (status, body) = get_json(
+            app.clone(),
+            &format!("/history/EURUSD?transform=renko&brick=2&{}", RANGE),
+        )
+        .await;
+        assert_eq!(status, StatusCode::OK);
+        let bricks = body["data"].as_array().unwrap();
+        assert!(!bricks.is_empty());
+        assert!(bricks[0]["direction"].is_string());
+
+        for query in [
+            "transform=renko",
+            "transform=renko&brick=0",
+            "transform=kagi",
+        ] {
+            let (status, _) =
+                get_json(app.clone(), &format!("/history/EURUSD?{}&{}", query, RANGE)).await;
+            assert_eq!(status, StatusCode::BAD_REQUEST, "{}", query);
+        }
+    }
+
+    #[tokio::test]
+    async fn prices_returns_null_for_unknown_symbols() {
+        let store = FxStore::new();
+        for (symbol, close) in [("EURUSD", 1.1), ("XAUUSD", 2050.5)] {
+            let rec = OHLCV::from_prices(DAY_START, close, close, close, close, 1, 0).unwrap();
+            store.insert(symbol, rec).unwrap();
+        }
+        let app = create_app(Arc::new(store));
+
+        let (status, body) = get_json(app, "/prices?symbols=EURUSD,XAUUSD,BTCUSD").await;
+        assert_eq!(status, StatusCode::OK);
+
+        let prices = body.as_object().unwrap();
+        assert_eq!(prices.len(), 3);
+        assert_eq!(prices["EURUSD"]["close"], 1.1);
+        assert_eq!(prices["XAUUSD"]["symbol"], "XAUUSD");
+        assert_eq!(
+            prices["XAUUSD"]["timestamp"],
+            (DAY_START / 1_000_000_000) as i64
+        );
+        assert!(prices["BTCUSD"].is_null());
+    }
+
+    #[tokio::test]
+    async fn responses_name_bars_after_their_symbol_id() {
+        let store = Arc::new(FxStore::new());
+        for (symbol, close) in [("EURUSD", 1.1), ("USDJPY", 145.2)] {
+            for minute in 0..3 {
+                let ts = DAY_START + minute * MINUTE;
+                let rec = OHLCV::from_prices(ts, close, close, close, close, 1, 0).unwrap();
+                store.insert(symbol, rec).unwrap();
+            }
+        }
+        store.add_alias("FIBER", "EURUSD").unwrap();
+        store.flush();
+        let app = create_app(Arc::clone(&store));
+
+        let uri = format!("/history?symbols=EURUSD,USDJPY&{}", RANGE);
+        let (status, body) = get_json(app.clone(), &uri).await;
+        assert_eq!(status, StatusCode::OK);
+        for symbol in ["EURUSD", "USDJPY"] {
+            let records: Vec<OHLCV> = store
+                .query_range(symbol, DAY_START, DAY_START + 3 * MINUTE)
+                .collect();
+            let candles = body[symbol].as_array().unwrap();
+            assert_eq!(candles.len(), records.len());
+            for (candle, rec) in candles.iter().zip(&records) {
+                let name = store.symbol_name(rec.symbol_id).unwrap();
+                assert_eq!(candle["symbol"], name.as_str());
+                assert_eq!(name, symbol);
+            }
+        }
+
+        // An alias reports the symbol the bars are stored under
+        let (status, body) = get_json(app.clone(), "/price/FIBER").await;
+        assert_eq!(status, StatusCode::OK);
+        assert_eq!(body["symbol"], "EURUSD");
+        let (_, body) = get_json(app, &format!("/history/FIBER?{}", RANGE)).await;
+        assert_eq!(body["data"][0]["symbol"], "EURUSD");
+    }
+
+    #[test]
+    fn cursor_round_trip() {
+        for ts in [0, 1, DAY_START, u64::MAX] {
+            assert_eq!(decode_cursor(&encode_cursor(ts)), Some(ts));
+        }
+        assert_eq!(decode_cursor("not-a-cursor"), None);
+        assert_eq!(decode_cursor("AAAAAAAAAAB"), None);
+    }
+
+    #[tokio::test]
+    async fn history_pages_backwards_without_gaps() {
+        let app = app_with_bars(2500, ApiConfig::default());
+        let range = "start=2024-01-02&end=2024-01-04";
+
+        let mut pages = Vec::new();
+        let mut cursor: Option<String> = None;
+        loop {
+            let mut uri = format!("/history/EURUSD?limit=1000&{}", range);
+            if let Some(cursor) = &cursor {
+                uri.push_str(&format!("&cursor={}", cursor));
+            }
+            let (status, body) = get_json(app.clone(), &uri).await;
+            assert_eq!(status, StatusCode::OK);
+
+            let timestamps: Vec<i64> = body["data"]
+                .as_array()
+                .unwrap()
+                .iter()
+                .map(|bar| bar["timestamp"].as_i64().unwrap())
+                .collect();
+            pages.push(timestamps);
+            match body["next_cursor"].as_str() {
+                Some(next) => cursor = Some(next.to_string()),
+                None => break,
+            }
+        }
+
+        assert_eq!(
+            pages.iter().map(Vec::len).collect::<Vec<_>>(),
+            [1000, 1000, 500]
+        );
+        let mut all: Vec<i64> = pages.into_iter().rev().flatten().collect();
+        let first = (DAY_START / 1_000_000_000) as i64;
+        assert_eq!(all.len(), 2500);
+        assert!(
+            all.iter()
+                .enumerate()
+                .all(|(i, &ts)| ts == first + 60 * i as i64)
+        );
+        all.dedup();
+        assert_eq!(all.len(), 2500);
+    }
+
+    #[tokio::test]
+    async fn unknown_symbol_is_404_but_empty_range_is_200() {
+        let app = app_with_bars(10, ApiConfig::default());
+
+        let (status, _) = get_json(app.clone(), &format!("/history/EURUSX?{}", RANGE)).await;
+        assert_eq!(status, StatusCode::NOT_FOUND);
+        let (status, _) = get_json(app.clone(), "/price/EURUSX").await;
+        assert_eq!(status, StatusCode::NOT_FOUND);
+
+        let (status, body) = get_json(
+            app.clone(),
+            "/history/EURUSD?start=2024-02-01&end=2024-02-02",
+        )
+        .await;
+        assert_eq!(status, StatusCode::OK);
+        assert_eq!(body["data"], serde_json::json!([]));
+
+        let (status, body) = get_json(
+            app,
+            "/history?symbols=EURUSD,EURUSX&start=2024-02-01&end=2024-02-02",
+        )
+        .await;
+        assert_eq!(status, StatusCode::OK);
+        assert_eq!(body["EURUSD"], serde_json::json!([]));
+        assert_eq!(body["EURUSX"]["error"], "unknown symbol: EURUSX");
+    }
+
+    #[tokio::test]
+    async fn errors_are_json_with_code_message_and_detail() {
+        let app = app_with_bars(10, ApiConfig::default());
+        let cases = [
+            (
+                format!("/history/EURUSX?{}", RANGE),
+                StatusCode::NOT_FOUND,
+                "symbol_not_found",
+                serde_json::json!({ "symbol": "EURUSX" }),
+            ),
+            (
+                "/history/EURUSD?start=yesterday".to_string(),
+                StatusCode::BAD_REQUEST,
+                "invalid_date",
+                serde_json::json!({ "param": "start", "value": "yesterday" }),
+            ),
+            (
+                "/history/EURUSD?start=2024-01-03&end=2024-01-02".to_string(),
+                StatusCode::BAD_REQUEST,
+                "invalid_range",
+                serde_json::json!({
+                    "start": "2024-01-03T00:00:00+00:00",
+                    "end": "2024-01-02T00:00:00+00:00",
+                }),
+            ),
+            (
+                format!("/history/EURUSD?limit=0&{}", RANGE),
+                StatusCode::UNPROCESSABLE_ENTITY,
+                "invalid_limit",
+                serde_json::json!({ "limit": 0 }),
+            ),
+            (
+                format!("/history/EURUSD?interval=7x&{}", RANGE),
+                StatusCode::BAD_REQUEST,
+                "invalid_parameter",
+                serde_json::json!({ "param": "interval" }),
+            ),
+            (
+                format!(
+                    "/history/EURUSD?interval=1d&align_tz=Mars/Olympus&{}",
+                    RANGE
+                ),
+                StatusCode::BAD_REQUEST,
+                "invalid_parameter",
+                serde_json::json!({ "param": "align_tz" }),
+            ),
+            (
+                format!("/history/EURUSD?interval=1d&session_offset=5pm&{}", RANGE),
+                StatusCode::BAD_REQUEST,
+                "invalid_parameter",
+                serde_json::json!({ "param": "session_offset" }),
+            ),
+            (
+                "/asof/EURUSD?ts=2024-01-01".to_string(),
+                StatusCode::NOT_FOUND,
+                "no_data",
+                serde_json::json!({ "symbol": "EURUSD", "ts": "2024-01-01T00:00:00+00:00" }),
+            ),
+        ];
+        for (uri, expected_status, code, detail) in cases {
+            let (status, body) = get_json(app.clone(), &uri).await;
+            assert_eq!(status, expected_status, "{}", uri);
+            assert_eq!(body["error"], code, "{}", uri);
+            assert!(body["message"].as_str().is_some_and(|m| !m.is_empty()));
+            assert_eq!(body["detail"], detail, "{}", uri);
+        }
+    }
+
+    #[tokio::test]
+    async fn query_span_is_capped_per_deployment() {
+        let config = ApiConfig {
+            max_query_span: Duration::from_secs(30 * 86_400),
+            ..Default::default()
+        };
+        let app = app_with_bars(10, config);
+
+        let (status, body) = get_json(
+            app.clone(),
+            "/history/EURUSD?start=2024-01-01&end=2024-03-01",
+        )
+        .await;
+        assert_eq!(status, StatusCode::BAD_REQUEST);
+        assert_eq!(body["error"], "span_too_large");
+        assert_eq!(
+            body["detail"],
+            serde_json::json!({ "span_days": 60, "max_days": 30 })
+        );
+        let (status, body) = get_json(
+            app.clone(),
+            "/aggregate/EURUSD?start=2024-01-01&end=2024-03-01",
+        )
+        .await;
+        assert_eq!(status, StatusCode::BAD_REQUEST);
+        assert_eq!(body["error"], "span_too_large");
+
+        // A start before 1970 means "from the beginning" but still counts against the cap
+        let (status, body) = get_json(
+            app.clone(),
+            "/history/EURUSD?start=1960-01-01&end=1970-01-02",
+        )
+        .await;
+        assert_eq!(status, StatusCode::OK);
+        assert_eq!(body["data"], serde_json::json!([]));
+        let (status, body) = get_json(app, "/history/EURUSD?start=1960-01-01&end=2024-01-03").await;
+        assert_eq!(status, StatusCode::BAD_REQUEST);
+        assert_eq!(body["error"], "span_too_large");
+    }
+
+    #[tokio::test]
+    async fn internal_errors_hide_the_cause_behind_an_id() {
+        let response = ApiError::Internal("block 3 checksum mismatch".to_string()).into_response();
+        assert_eq!(response.status(), StatusCode::INTERNAL_SERVER_ERROR);
+        let body = to_bytes(response.into_body(), usize::MAX).await.unwrap();
+        let body: serde_json::Value = serde_json::from_slice(&body).unwrap();
+        assert_eq!(body["error"], "internal");
+        assert_eq!(body["message"], "internal error");
+        assert_eq!(body["detail"]["id"].as_str().map(str::len), Some(16));
+        assert!(!body.to_string().contains("checksum"));
+    }
+
+    #[test]
+    fn store_errors_map_to_status_by_variant() {
+        let status = |e: FxStoreError| ApiError::from(e).status();
+        assert_eq!(
+            status(FxStoreError::TimestampOutOfRange(u64::MAX)),
+            StatusCode::BAD_REQUEST
+        );
+        assert_eq!(
+            status(FxStoreError::UnknownSymbol("XAUUSD".into())),
+            StatusCode::NOT_FOUND
+        );
+        assert_eq!(
+            status(FxStoreError::Query(QueryError::InvertedRange {
+                start: 2,
+                end: 1
+            })),
+            StatusCode::BAD_REQUEST
+        );
+        assert_eq!(
+            status(FxStoreError::parse("bad close")),
+            StatusCode::BAD_REQUEST
+        );
+        assert_eq!(
+            status(FxStoreError::PriceOverflow {
+                line: 4,
+                price: "1e12".into()
+            }),
+            StatusCode::BAD_REQUEST
+        );
+        assert_eq!(
+            status(FxStoreError::QueueFull),
+            StatusCode::SERVICE_UNAVAILABLE
+        );
+        assert_eq!(
+            status(FxStoreError::Corruption("block 3".into())),
+            StatusCode::INTERNAL_SERVER_ERROR
+        );
+    }
+
+    /// Read SSE chunks until `count` events have arrived in total
+    async fn read_events(
+        body: &mut axum::body::BodyDataStream,
+        text: &mut String,
+        count: usize,
+    ) -> String {
+        use futures_util::StreamExt;
+
+        while text.matches("data:").count() < count {
+            let chunk = tokio::time::timeout(Duration::from_secs(5), body.next())
+                .await
+                .expect("timed out waiting for event")
+                .unwrap()
+                .unwrap();
+            text.push_str(std::str::from_utf8(&chunk).unwrap());
+        }
+        text.clone()
+    }
+
+    #[tokio::test]
+    async fn stream_replays_from_last_event_id_then_goes_live() {
+        use crate::store::StoreConfig;
+        use crate::types::Tick;
+
+        let store = Arc::new(FxStore::with_config(StoreConfig {
+            tick_tolerance: Duration::ZERO,
+            tick_idle_timeout: Duration::from_millis(50),
+            ..Default::default()
+        }));
+        for i in 0..4 {
+            let rec = OHLCV::from_prices(DAY_START + i * MINUTE, 1.1, 1.1, 1.1, 1.1, 1, 0).unwrap();
+            store.insert("EURUSD", rec).unwrap();
+        }
+        let app = create_app(Arc::clone(&store));
+
+        let (status, _) = get_json(app.clone(), "/stream/EURUSX").await;
+        assert_eq!(status, StatusCode::NOT_FOUND);
+
+        let first_id = DAY_START / 1_000_000_000 + 60;
+        let response = app
+            .oneshot(
+                Request::get("/stream/EURUSD")
+                    .header("Last-Event-ID", first_id.to_string())
+                    .body(Body::empty())
+                    .unwrap(),
+            )
+            .await
+            .unwrap();
+        assert_eq!(response.status(), StatusCode::OK);
+        assert_eq!(response.headers()["content-type"], "text/event-stream");
+
+        let mut body = response.into_body().into_data_stream();
+        let mut text = String::new();
+        // Bars at +2 and +3 minutes are newer than the last seen id (+1 minute)
+        let replayed = read_events(&mut body, &mut text, 2).await;
+        assert!(replayed.contains(&format!("id: {}", first_id + 60)));
+        assert!(replayed.contains(&format!("id: {}", first_id + 120)));
+        assert!(!replayed.contains(&format!("id: {}\n", first_id)));
+
+        let live_ts = DAY_START + 10 * MINUTE;
+        store
+            .insert_tick("EURUSD", Tick::from_quote(live_ts, 1.2, 1.2, 1, 0).unwrap())
+            .unwrap();
+        let all = read_events(&mut body, &mut text, 3).await;
+        assert!(all.contains(&format!("id: {}", live_ts / 1_000_000_000)));
+        assert!(all.contains("\"symbol\":\"EURUSD\""));
+    }
+
+    async fn post_json(
+        app: Router,
+        uri: &str,
+        token: Option<&str>,
+        body: String,
+    ) -> (StatusCode, serde_json::Value) {
+        let mut request = Request::post(uri).header("content-type", "application/json");
+        if let Some(token) = token {
+            request = request.header("authorization", format!("Bearer {}", token));
+        }
+        let response = app
+            .oneshot(request.body(Body::from(body)).unwrap())
+            .await
+            .unwrap();
+        let status = response.status();
+        let body = to_bytes(response.into_body(), usize::MAX).await.unwrap();
+        (status, serde_json::from_slice(&body).unwrap_or_default())
+    }
+
+    fn writable_app(store: SharedStore) -> Router {
+        let config = ApiConfig {
+            write_token: Some("secret".to_string()),
+            max_body_bytes: 4096,
+            ..Default::default()
+        };
+        create_app_with(store, config).unwrap()
+    }
+
+    #[tokio::test]
+    async fn post_bars_requires_token() {
+        let body = format!(r#"[{{"ts":{},"open":1,"high":1,"low":1,"close":1}}]"#, 1);
+        let (status, _) = post_json(
+            create_app(Arc::new(FxStore::new())),
+            "/bars/EURUSD",
+            Some("secret"),
+            body.clone(),
+        )
+        .await;
+        assert_eq!(status, StatusCode::FORBIDDEN);
+
+        let app = writable_app(Arc::new(FxStore::new()));
+        for token in [None, Some("wrong")] {
+            let (status, body) = post_json(app.clone(), "/bars/EURUSD", token, body.clone()).await;
+            assert_eq!(status, StatusCode::UNAUTHORIZED);
+            assert!(body["error"].is_string());
+        }
+    }
+
+    #[tokio::test]
+    async fn post_bars_reports_each_item() {
+        let store = Arc::new(FxStore::new());
+        let app = writable_app(Arc::clone(&store));
+        let ts = (DAY_START / 1_000_000_000) as i64;
+        let future = Utc::now().timestamp() + 3600;
+        let body = serde_json::json!([
+            {"ts": ts, "open": 1.1, "high": 1.2, "low": 1.0, "close": 1.125, "volume": 3},
+            {"ts": ts + 60, "open": 1.1, "high": 1.0, "low": 1.05, "close": 1.1},
+            {"ts": ts + 120, "open": 1.1, "high": 1.12, "low": 1.0, "close": 1.15},
+            {"ts": future, "open": 1.1, "high": 1.1, "low": 1.1, "close": 1.1},
+            {"ts": ts + 180, "open": 1.1, "high": 1.1, "low": 1.1, "close": 1.1},
+        ])
+        .to_string();
+
+        let (status, body) = post_json(app.clone(), "/bars/EURUSD", Some("secret"), body).await;
+        assert_eq!(status, StatusCode::MULTI_STATUS);
+        let statuses: Vec<&str> = body
+            .as_array()
+            .unwrap()
+            .iter()
+            .map(|item| item["status"].as_str().unwrap())
+            .collect();
+        assert_eq!(
+            statuses,
+            ["accepted", "rejected", "rejected", "rejected", "accepted"]
+        );
+        assert_eq!(body[3]["reason"], "timestamp is in the future");
+        assert!(body[0].get("reason").is_none());
+
+        let stored: Vec<OHLCV> = store.query_range("EURUSD", 0, u64::MAX).collect();
+        assert_eq!(stored.len(), 2);
+        assert_eq!(({ stored[0].close }, { stored[0].volume }), (112_500, 3));
+
+        // 반올림해 담고, u32를 넘는 가격은 포화 대신 거절
+        let body = serde_json::json!([
+            {"ts": ts + 240, "open": 1.15, "high": 1.15, "low": 1.15, "close": 1.15},
+            {"ts": ts + 300, "open": 1.1, "high": 50000.0, "low": 1.1, "close": 1.1},
+        ])
+        .to_string();
+        let (_, body) = post_json(app.clone(), "/bars/EURUSD", Some("secret"), body).await;
+        assert_eq!(body[0]["status"], "accepted");
+        assert_eq!(body[1]["reason"], "price 50000 out of range");
+        let last = store.latest("EURUSD").unwrap();
+        assert_eq!({ last.close }, 115_000);
+
+        let (status, _) = post_json(
+            app,
+            "/bars/EURUSD",
+            Some("secret"),
+            format!(r#"[{{"ts":{},"open":1,"high":1,"low":1,"close":1}}]"#, ts),
+        )
+        .await;
+        assert_eq!(status, StatusCode::OK);
+    }
+
+    #[tokio::test]
+    async fn writes_reject_malformed_new_symbols() {
+        let store = Arc::new(FxStore::new());
+        let rec = OHLCV::from_prices(DAY_START, 1.1, 1.1, 1.1, 1.1, 1, 0).unwrap();
+        store.insert("AB", rec).unwrap();
+        let app = writable_app(Arc::clone(&store));
+        let ts = (DAY_START / 1_000_000_000) as i64;
+        let bars = format!(r#"[{{"ts":{},"open":1,"high":1,"low":1,"close":1}}]"#, ts);
+        let ticks = format!(r#"[{{"ts_ms":{},"bid":1,"ask":1}}]"#, ts * 1000);
+
+        for path in [
+            "/bars/%C3%A9UR",
+            "/bars/EUR",
+            "/bars/EUR%2F",
+            "/bars/EURUSD1",
+        ] {
+            let (status, body) = post_json(app.clone(), path, Some("secret"), bars.clone()).await;
+            assert_eq!(status, StatusCode::BAD_REQUEST, "{path}");
+            assert!(body["error"].is_string());
+        }
+        let (status, _) = post_json(app.clone(), "/ticks/X", Some("secret"), ticks.clone()).await;
+        assert_eq!(status, StatusCode::BAD_REQUEST);
+        assert_eq!(store.get_symbols().len(), 1);
+
+        // 이미 있는 심볼과 BASE/QUOTE 형식은 그대로 받음
+        for path in ["/bars/AB", "/bars/XAU%2FUSD", "/ticks/GBPJPY"] {
+            let body = if path.starts_with("/ticks") {
+                &ticks
+            } else {
+                &bars
+            };
+            let (status, _) = post_json(app.clone(), path, Some("secret"), body.clone()).await;
+            assert_eq!(status, StatusCode::OK, "{path}");
+        }
+        assert!(store.has_symbol("XAU/USD"));
+    }
+
+    #[tokio::test]
+    async fn ingest_counts_rows_and_bars_show_up_in_history() {
+        let store = Arc::new(FxStore::new());
+        store
+            .register_symbol("EURUSD", crate::types::Granularity::default())
+            .unwrap();
+        let app = writable_app(Arc::clone(&store));
+        let ts = (DAY_START / 1_000_000_000) as i64;
+        let body = serde_json::json!([
+            {"ts": ts, "open": 1.1, "high": 1.2, "low": 1.0, "close": 1.125, "volume": 3},
+            {"ts": ts + 60, "open": "1.1", "high": 1.2, "low": 1.0, "close": 1.1},
+            {"ts": ts + 120, "open": 1.1, "high": 1.0, "low": 1.05, "close": 1.1},
+            {"ts": ts + 180, "open": 1.1, "high": 1.1, "low": 1.1, "close": 1.1},
+        ])
+        .to_string();
+
+        let (status, body) = post_json(app.clone(), "/ingest/EURUSD", Some("secret"), body).await;
+        assert_eq!(status, StatusCode::OK);
+        assert_eq!(
+            (body["accepted"].as_u64(), body["rejected"].as_u64()),
+            (Some(2), Some(2))
+        );
+        let rejected: Vec<u64> = body["errors"]
+            .as_array()
+            .unwrap()
+            .iter()
+            .map(|row| row["index"].as_u64().unwrap())
+            .collect();
+        assert_eq!(rejected, [1, 2]);
+        assert_eq!(body["errors"][1]["reason"], "high is below low");
+
+        let row = format!(r#"[{{"ts":{},"open":1,"high":1,"low":1,"close":1}}]"#, ts);
+        let (status, body) = post_json(app.clone(), "/ingest/GBPUSD", Some("secret"), row).await;
+        assert_eq!(status, StatusCode::NOT_FOUND);
+        assert_eq!(body["error"], "symbol_not_found");
+        assert!(!store.has_symbol("GBPUSD"));
+
+        store.flush();
+        let (status, body) = get_json(
+            app,
+            "/history/EURUSD?start=2024-01-02T00:00:00Z&end=2024-01-02T00:10:00Z",
+        )
+        .await;
+        assert_eq!(status, StatusCode::OK);
+        let closes: Vec<f64> = body["data"]
+            .as_array()
+            .unwrap()
+            .iter()
+            .map(|bar| bar["close"].as_f64().unwrap())
+            .collect();
+        assert_eq!(closes, [1.125, 1.1]);
+    }
+
+    #[tokio::test]
+    async fn post_ticks_feeds_aggregator_and_limits_body_size() {
+        let store = Arc::new(FxStore::new());
+        let app = writable_app(Arc::clone(&store));
+        let ts_ms = (DAY_START / 1_000_000) as i64;
+        let body = serde_json::json!([
+            {"ts_ms": ts_ms, "bid": 1.1, "ask": 1.1002},
+            {"ts_ms": ts_ms + 1000, "bid": 1.1, "ask": 1.0},
+        ])
+        .to_string();
+
+        let (status, body) = post_json(app.clone(), "/ticks/EURUSD", Some("secret"), body).await;
+        assert_eq!(status, StatusCode::MULTI_STATUS);
+        assert_eq!(body[0]["status"], "accepted");
+        assert_eq!(body[1]["reason"], "ask is below bid");
+        assert_eq!(store.query_ticks("EURUSD", 0, u64::MAX).len(), 1);
+
+        let oversized = format!("[{}]", vec!["{}"; 2048].join(","));
+        let (status, _) = post_json(app, "/ticks/EURUSD", Some("secret"), oversized).await;
+        assert_eq!(status, StatusCode::PAYLOAD_TOO_LARGE);
+    }
+
+    #[test]
+    fn prices_render_with_the_precision_they_use() {
+        let bar = |price: u32| OHLCV {
+            ts: DAY_START,
+            open: price,
+            high: price,
+            low: price,
+            close: price,
+            ..Default::default()
+        };
+        assert_eq!(
+            price_decimals(&[bar(110_005), bar(110_000)], PRICE_SCALE),
+            5
+        );
+        assert_eq!(
+            price_decimals(&[bar(205_012_000), bar(205_050_000)], PRICE_SCALE),
+            2
+        );
+        assert_eq!(price_decimals(&[bar(205_000_000)], PRICE_SCALE), 0);
+        assert_eq!(format_price(110_005, 5, PRICE_SCALE), "1.10005");
+        assert_eq!(format_price(205_012_000, 2, PRICE_SCALE), "2050.12");
+        assert_eq!(format_price(205_000_000, 0, PRICE_SCALE), "2050");
+        assert_eq!(format_price(5, 5, PRICE_SCALE), "0.00005");
+    }
+
+    #[tokio::test]
+    async fn history_streams_csv_and_ndjson() {
+        let app = app_with_bars(10, ApiConfig::default());
+
+        let response = app
+            .clone()
+            .oneshot(
+                Request::get(format!("/history/EURUSD?limit=4&{}", RANGE))
+                    .header("accept", "text/csv")
+                    .body(Body::empty())
+                    .unwrap(),
+            )
+            .await
+            .unwrap();
+        assert_eq!(response.status(), StatusCode::OK);
+        let headers = response.headers().clone();
+        assert_eq!(headers["content-type"], "text/csv; charset=utf-8");
+        assert_eq!(
+            headers["content-disposition"],
+            "attachment; filename=\"EURUSD_20240102_20240102.csv\""
+        );
+        assert!(headers.contains_key("x-next-cursor"));
+        let body = to_bytes(response.into_body(), usize::MAX).await.unwrap();
+        let csv = std::str::from_utf8(&body).unwrap();
+        let lines: Vec<&str> = csv.lines().collect();
+        assert_eq!(lines.len(), 5);
+        assert_eq!(lines[0], "ts,open,high,low,close,volume");
+        // Newest 4 of 10 bars start at i = 6; every price ends in 0, so 4 decimals
+        assert_eq!(
+            lines[1],
+            format!(
+                "{},1.1006,1.1008,1.1004,1.1006,1",
+                (DAY_START + 6 * MINUTE) / 1_000_000_000
+            )
+        );
+
+        let response = app
+            .clone()
+            .oneshot(
+                Request::get(format!("/history/EURUSD?format=ndjson&{}", RANGE))
+                    .body(Body::empty())
+                    .unwrap(),
+            )
+            .await
+            .unwrap();
+        assert_eq!(response.headers()["content-type"], "application/x-ndjson");
+        let body = to_bytes(response.into_body(), usize::MAX).await.unwrap();
+        let rows: Vec<serde_json::Value> = std::str::from_utf8(&body)
+            .unwrap()
+            .lines()
+            .map(|line| serde_json::from_str(line).unwrap())
+            .collect();
+        assert_eq!(rows.len(), 10);
+        assert_eq!(rows[1]["close"], 1.1001);
+        assert_eq!(rows[1]["symbol"], "EURUSD");
+
+        for query in ["format=xml", "format=csv&transform=renko&brick=2"] {
+            let (status, _) =
+                get_json(app.clone(), &format!("/history/EURUSD?{}&{}", query, RANGE)).await;
+            assert_eq!(status, StatusCode::BAD_REQUEST, "{}", query);
+        }
+    }
+
+    #[cfg(feature = "parquet")]
+    #[tokio::test]
+    async fn history_downloads_parquet() {
+        use arrow_array::cast::AsArray;
+        use arrow_array::types::{Float64Type, TimestampNanosecondType};
+        use parquet::arrow::arrow_reader::ParquetRecordBatchReaderBuilder;
+
+        let app = app_with_bars(100, ApiConfig::default());
+        let get = |uri: String| {
+            let app = app.clone();
+            async move {
+                let response = app
+                    .oneshot(Request::get(uri).body(Body::empty()).unwrap())
+                    .await
+                    .unwrap();
+                assert_eq!(response.status(), StatusCode::OK);
+                let headers = response.headers().clone();
+                let body = to_bytes(response.into_body(), usize::MAX).await.unwrap();
+                let batches: Vec<_> = ParquetRecordBatchReaderBuilder::try_new(body)
+                    .unwrap()
+                    .build()
+                    .unwrap()
+                    .collect::<Result<_, _>>()
+                    .unwrap();
+                (headers, batches)
+            }
+        };
+
+        // Raw bars stream straight from the blocks
+        let (headers, batches) = get(format!("/history/EURUSD?format=parquet&{}", RANGE)).await;
+        assert_eq!(headers["content-type"], PARQUET_CONTENT_TYPE);
+        assert_eq!(
+            headers["content-disposition"],
+            "attachment; filename=\"EURUSD_20240102_20240102.parquet\""
+        );
+        let closes: Vec<f64> = batches
+            .iter()
+            .flat_map(|batch| {
+                batch
+                    .column(4)
+                    .as_primitive::<Float64Type>()
+                    .values()
+                    .to_vec()
+            })
+            .collect();
+        assert_eq!(closes.len(), 100);
+        assert_eq!(closes[1], 1.1001);
+        let first_ts = batches[0]
+            .column(0)
+            .as_primitive::<TimestampNanosecondType>();
+        assert_eq!(first_ts.value(0), DAY_START as i64);
+
+        // Resampled pages are encoded from the page itself
+        let (_, batches) = get(format!(
+            "/history/EURUSD?format=parquet&interval=1h&{}",
+            RANGE
+        ))
+        .await;
+        assert_eq!(batches.iter().map(|b| b.num_rows()).sum::<usize>(), 2);
+
+        let (status, body) = get_json(
+            app.clone(),
+            &format!("/history/EURUSX?format=parquet&{}", RANGE),
+        )
+        .await;
+        assert_eq!(status, StatusCode::NOT_FOUND);
+        assert!(body["error"].is_string());
+    }
+
+    #[tokio::test]
+    async fn history_reports_spread_only_when_known() {
+        let store = FxStore::new();
+        let bar = |minute: u64| {
+            OHLCV::from_prices(DAY_START + minute * MINUTE, 1.1, 1.1, 1.1, 1.1, 1, 0).unwrap()
+        };
+        store
+            .insert("EURUSD", bar(0).with_spread(0.00012, PRICE_SCALE))
+            .unwrap();
+        store.insert("EURUSD", bar(1)).unwrap();
+        let app = create_app_with(Arc::new(store), ApiConfig::default()).unwrap();
+
+        let (status, body) = get_json(app.clone(), &format!("/history/EURUSD?{}", RANGE)).await;
+        assert_eq!(status, StatusCode::OK);
+        assert_eq!(body["data"][0]["spread"], 0.00012);
+        assert!(body["data"][1].get("spread").is_none());
+
+        let response = app
+            .oneshot(
+                Request::get(format!("/history/EURUSD?format=ndjson&{}", RANGE))
+                    .body(Body::empty())
+                    .unwrap(),
+            )
+            .await
+            .unwrap();
+        let body = to_bytes(response.into_body(), usize::MAX).await.unwrap();
+        let rows: Vec<serde_json::Value> = std::str::from_utf8(&body)
+            .unwrap()
+            .lines()
+            .map(|line| serde_json::from_str(line).unwrap())
+            .collect();
+        assert_eq!(rows[0]["spread"], 0.00012);
+        assert!(rows[1].get("spread").is_none());
+    }
+
+    #[tokio::test]
+    async fn aggregate_matches_hand_computation() {
+        // Closes cycle 1.10000..1.10060 (i % 7 * 10); first 5 bars: 0, 10, 20, 30, 40
+        let app = app_with_bars(10, ApiConfig::default());
+        let range = "start=2024-01-02T00:00:00Z&end=2024-01-02T00:05:00Z";
+
+        let (status, body) = get_json(app.clone(), &format!("/aggregate/EURUSD?{}", range)).await;
+        assert_eq!(status, StatusCode::OK);
+        assert_eq!(body["field"], "close");
+        assert_eq!(body["count"], 5);
+        assert_eq!(body["min"], 1.1);
+        assert_eq!(body["max"], 1.1004);
+        assert!((body["avg"].as_f64().unwrap() - 1.1002).abs() < 1e-12);
+        assert!((body["sum"].as_f64().unwrap() - 5.501).abs() < 1e-12);
+
+        let (_, body) = get_json(
+            app.clone(),
+            &format!("/aggregate/EURUSD?field=high&{}", range),
+        )
+        .await;
+        assert_eq!(body["max"], 1.1006);
+
+        let (_, body) = get_json(
+            app.clone(),
+            &format!("/aggregate/EURUSD?field=volume&{}", range),
+        )
+        .await;
+        assert_eq!(
+            (body["count"].clone(), body["sum"].clone()),
+            (5.into(), 5.0.into())
+        );
+
+        let (status, body) = get_json(
+            app.clone(),
+            "/aggregate/EURUSD?start=2024-02-01&end=2024-02-02",
+        )
+        .await;
+        assert_eq!(status, StatusCode::OK);
+        assert_eq!(body["count"], 0);
+        assert!(body["avg"].is_null());
+
+        let (status, _) = get_json(app.clone(), "/aggregate/EURUSD?field=spread").await;
+        assert_eq!(status, StatusCode::BAD_REQUEST);
+        let (status, _) = get_json(app, "/aggregate/EURUSX").await;
+        assert_eq!(status, StatusCode::NOT_FOUND);
+    }
+
+    #[tokio::test]
+    async fn metrics_exposes_store_and_query_counters() {
+        let app = app_with_bars(10, ApiConfig::default());
+        for uri in [
+            "/history/EURUSD?start=2024-01-02&end=2024-01-03",
+            "/history/EURUSD?start=2024-01-02&end=2024-01-03&format=csv",
+            "/aggregate/EURUSD?start=2024-01-02&end=2024-01-03",
+        ] {
+            let response = app
+                .clone()
+                .oneshot(Request::get(uri).body(Body::empty()).unwrap())
+                .await
+                .unwrap();
+            assert_eq!(response.status(), StatusCode::OK);
+        }
+
+        let response = app
+            .oneshot(Request::get("/metrics").body(Body::empty()).unwrap())
+            .await
+            .unwrap();
+        assert_eq!(response.status(), StatusCode::OK);
+        assert!(
+            response.headers()[header::CONTENT_TYPE]
+                .to_str()
+                .unwrap()
+                .starts_with("text/plain; version=0.0.4")
+        );
+        let body = to_bytes(response.into_body(), usize::MAX).await.unwrap();
+        let text = String::from_utf8(body.to_vec()).unwrap();
+
+        for line in [
+            "# TYPE fx_store_records gauge",
+            "fx_store_records 10",
+            "# TYPE fx_store_block_cache_hits_total counter",
+            "fx_store_imported_records_total 10",
+            "fx_store_blocks{symbol=\"EURUSD\"} 1",
+            "# TYPE fx_store_query_duration_seconds histogram",
+            "fx_store_query_duration_seconds_count{handler=\"history\"} 2",
+            "fx_store_query_duration_seconds_count{handler=\"aggregate\"} 1",
+            "fx_store_query_duration_seconds_bucket{handler=\"history\",le=\"+Inf\"} 2",
+        ] {
+            assert!(text.contains(line), "missing {:?} in\n{}", line, text);
+        }
+        // Every sample line is `name{labels} value` with a numeric value
+        for line in text.lines().filter(|line| !line.starts_with('#')) {
+            let value = line.rsplit(' ').next().unwrap();
+            assert!(value.parse::<f64>().is_ok(), "bad sample {:?}", line);
+        }
+    }
+
+    #[tokio::test]
+    async fn server_reports_bound_port_and_shuts_down() {
+        use tokio::io::{AsyncReadExt, AsyncWriteExt};
+
+        let config = ServerConfig {
+            bind: IpAddr::V4(Ipv4Addr::LOCALHOST),
+            port: 0,
+            ..Default::default()
+        };
+        let (stop, stopped) = tokio::sync::oneshot::channel::<()>();
+        let server = start_server(Arc::new(FxStore::new()), config, async {
+            stopped.await.ok();
+        })
+        .await
+        .unwrap();
+        assert_ne!(server.addr.port(), 0);
+
+        let mut conn = tokio::net::TcpStream::connect(server.addr).await.unwrap();
+        conn.write_all(b"GET /health HTTP/1.1\r\nHost: localhost\r\nConnection: close\r\n\r\n")
+            .await
+            .unwrap();
+        let mut response = String::new();
+        conn.read_to_string(&mut response).await.unwrap();
+        assert!(response.starts_with("HTTP/1.1 200"), "{}", response);
+
+        stop.send(()).unwrap();
+        tokio::time::timeout(Duration::from_secs(5), server.wait())
+            .await
+            .expect("server did not shut down")
+            .unwrap();
+    }
+
+    #[tokio::test]
+    async fn metrics_counters_move_with_requests() {
+        async fn scrape(app: &Router) -> String {
+            let response = app
+                .clone()
+                .oneshot(Request::get("/metrics").body(Body::empty()).unwrap())
+                .await
+                .unwrap();
+            let body = to_bytes(response.into_body(), usize::MAX).await.unwrap();
+            String::from_utf8(body.to_vec()).unwrap()
+        }
+        fn sample(text: &str, name: &str) -> f64 {
+            text.lines()
+                .find_map(|line| line.strip_prefix(name)?.strip_prefix(' '))
+                .map_or(0.0, |value| value.parse().unwrap())
+        }
+
+        let app = app_with_bars(10, ApiConfig::default());
+        let before = scrape(&app).await;
+        for uri in [
+            "/history/EURUSD?start=2024-01-02&end=2024-01-03",
+            "/history/EURUSD?start=2024-01-02&end=2024-01-03",
+            "/history/EURUSX",
+        ] {
+            app.clone()
+                .oneshot(Request::get(uri).body(Body::empty()).unwrap())
+                .await
+                .unwrap();
+        }
+        let after = scrape(&app).await;
+
+        let moved = |name: &str| sample(&after, name) - sample(&before, name);
+        assert_eq!(moved("fx_store_query_records_total"), 20.0);
+        assert_eq!(
+            moved("fx_store_blocks_decompressed_total") + moved("fx_store_block_cache_hits_total"),
+            2.0
+        );
+        assert!(sample(&after, "fx_store_resident_block_bytes") > 0.0);
+        assert_eq!(sample(&after, "fx_store_compress_queue_depth"), 0.0);
+        for (name, value) in [
+            (
+                "fx_store_http_requests_total{method=\"GET\",route=\"/history/:symbol\",status=\"200\"}",
+                2.0,
+            ),
+            (
+                "fx_store_http_requests_total{method=\"GET\",route=\"/history/:symbol\",status=\"404\"}",
+                1.0,
+            ),
+            (
+                "fx_store_http_request_duration_seconds_count{route=\"/history/:symbol\"}",
+                3.0,
+            ),
+            (
+                "fx_store_http_requests_total{method=\"GET\",route=\"/metrics\",status=\"200\"}",
+                1.0,
+            ),
+        ] {
+            assert_eq!(sample(&after, name), value, "{} in\n{}", name, after);
+        }
+    }
+
+    #[tokio::test]
+    async fn api_keys_guard_writes_and_optionally_reads() {
+        let access = |keys_for_reads| AccessConfig {
+            api_keys: vec!["k1".to_string(), "k2".to_string()],
+            keys_for_reads,
+            ..Default::default()
+        };
+        let app = |keys_for_reads| {
+            create_app_with(
+                Arc::new(FxStore::new()),
+                ApiConfig {
+                    access: access(keys_for_reads),
+                    ..Default::default()
+                },
+            )
+            .unwrap()
+        };
+        let body = format!(
+            r#"[{{"ts":{},"open":1,"high":1,"low":1,"close":1}}]"#,
+            1_704_153_600
+        );
+
+        // Keys replace the write token, so writes work without one configured
+        for (token, expected) in [
+            (None, StatusCode::UNAUTHORIZED),
+            (Some("wrong"), StatusCode::UNAUTHORIZED),
+            (Some("k2"), StatusCode::OK),
+        ] {
+            let (status, _) = post_json(app(false), "/bars/EURUSD", token, body.clone()).await;
+            assert_eq!(status, expected, "{:?}", token);
+        }
+
+        let (status, _) = get_json(app(false), "/symbols").await;
+        assert_eq!(status, StatusCode::OK);
+        let (status, body) = get_json(app(true), "/symbols").await;
+        assert_eq!(status, StatusCode::UNAUTHORIZED);
+        assert_eq!(body["error"], "unauthorized");
+        let (status, _) = get_json(app(true), "/symbols?api_key=k1").await;
+        assert_eq!(status, StatusCode::OK);
+        let (status, _) = get_json(app(true), "/symbols?api_key=k3").await;
+        assert_eq!(status, StatusCode::UNAUTHORIZED);
+        let (status, _) = get_json(app(true), "/health").await;
+        assert_eq!(status, StatusCode::OK);
+        let (status, _) = get_json(app(true), "/ready").await;
+        assert_eq!(status, StatusCode::OK);
+    }
+
+    #[tokio::test]
+    async fn rate_limits_answer_429_with_retry_after() {
+        let app = create_app_with(
+            Arc::new(FxStore::new()),
+            ApiConfig {
+                access: AccessConfig {
+                    api_keys: vec!["k1".to_string()],
+                    key_rate: Some("0.01:2".parse().unwrap()),
+                    anonymous_rate: Some("0.01:1".parse().unwrap()),
+                    ..Default::default()
+                },
+                ..Default::default()
+            },
+        )
+        .unwrap();
+        let send = |key: Option<&str>, ip: [u8; 4]| {
+            let mut request = Request::get("/symbols");
+            if let Some(key) = key {
+                request = request.header("authorization", format!("Bearer {}", key));
+            }
+            let mut request = request.body(Body::empty()).unwrap();
+            request
+                .extensions_mut()
+                .insert(ConnectInfo(SocketAddr::from((ip, 40_000))));
+            app.clone().oneshot(request)
+        };
+
+        for _ in 0..2 {
+            assert_eq!(
+                send(Some("k1"), [10, 0, 0, 1]).await.unwrap().status(),
+                StatusCode::OK
+            );
+        }
+        let response = send(Some("k1"), [10, 0, 0, 2]).await.unwrap();
+        assert_eq!(response.status(), StatusCode::TOO_MANY_REQUESTS);
+        let retry_after: u64 = response.headers()[header::RETRY_AFTER]
+            .to_str()
+            .unwrap()
+            .parse()
+            .unwrap();
+        assert!((90..=100).contains(&retry_after), "{}", retry_after);
+        let body = to_bytes(response.into_body(), usize::MAX).await.unwrap();
+        let body: serde_json::Value = serde_json::from_slice(&body).unwrap();
+        assert_eq!(body["error"], "rate_limited");
+        assert_eq!(body["detail"]["retry_after_secs"], retry_after);
+
+        // Anonymous reads are limited per IP, separately from the key
+        assert_eq!(
+            send(None, [10, 0, 0, 1]).await.unwrap().status(),
+            StatusCode::OK
+        );
+        assert_eq!(
+            send(None, [10, 0, 0, 1]).await.unwrap().status(),
+            StatusCode::TOO_MANY_REQUESTS
+        );
+        assert_eq!(
+            send(None, [10, 0, 0, 2]).await.unwrap().status(),
+            StatusCode::OK
+        );
+
+        // Wrong keys are charged to the IP's bucket, so guessing is throttled too
+        assert_eq!(
+            send(Some("k2"), [10, 0, 0, 3]).await.unwrap().status(),
+            StatusCode::UNAUTHORIZED
+        );
+        assert_eq!(
+            send(Some("k3"), [10, 0, 0, 3]).await.unwrap().status(),
+            StatusCode::TOO_MANY_REQUESTS
+        );
+    }
+
+    #[tokio::test]
+    async fn cors_allows_only_configured_origins() {
+        const ALLOWED: &str = "https://charts.example.com";
+        let app = create_app_with(
+            Arc::new(FxStore::new()),
+            ApiConfig {
+                allowed_origins: vec![format!("{}/", ALLOWED)],
+                allow_credentials: true,
+                ..Default::default()
+            },
+        )
+        .unwrap();
+        let send = |method: Method, origin: &str| {
+            let request = Request::builder()
+                .method(method)
+                .uri("/symbols")
+                .header(header::ORIGIN, origin)
+                .header(header::ACCESS_CONTROL_REQUEST_METHOD, "GET")
+                .body(Body::empty())
+                .unwrap();
+            app.clone().oneshot(request)
+        };
+
+        let response = send(Method::GET, ALLOWED).await.unwrap();
+        assert_eq!(response.status(), StatusCode::OK);
+        let headers = response.headers();
+        assert_eq!(headers[header::ACCESS_CONTROL_ALLOW_ORIGIN], ALLOWED);
+        assert_eq!(headers[header::ACCESS_CONTROL_ALLOW_CREDENTIALS], "true");
+        let exposed = headers[header::ACCESS_CONTROL_EXPOSE_HEADERS]
+            .to_str()
+            .unwrap();
+        assert!(exposed.contains("x-next-cursor"), "{}", exposed);
+
+        let preflight = send(Method::OPTIONS, ALLOWED).await.unwrap();
+        assert_eq!(preflight.status(), StatusCode::OK);
+        assert_eq!(
+            preflight.headers()[header::ACCESS_CONTROL_ALLOW_ORIGIN],
+            ALLOWED
+        );
+
+        // Other origins get no allow header, so the browser withholds the response
+        for method in [Method::GET, Method::OPTIONS] {
+            let response = send(method, "https://evil.example.com").await.unwrap();
+            assert!(
+                !response
+                    .headers()
+                    .contains_key(header::ACCESS_CONTROL_ALLOW_ORIGIN)
+            );
+        }
+
+        // Without a list any origin may call, as before
+        let app = create_app(Arc::new(FxStore::new()));
+        let request = Request::get("/symbols")
+            .header(header::ORIGIN, "https://evil.example.com")
+            .body(Body::empty())
+            .unwrap();
+        let response = app.oneshot(request).await.unwrap();
+        assert_eq!(response.headers()[header::ACCESS_CONTROL_ALLOW_ORIGIN], "*");
+
+        // Credentials cannot go to any origin, and every origin must be a valid header value
+        for config in [
+            ApiConfig {
+                allow_credentials: true,
+                ..Default::default()
+            },
+            ApiConfig {
+                allowed_origins: vec![ALLOWED.to_string(), "https://bad\norigin".to_string()],
+                ..Default::default()
+            },
+        ] {
+            let error = create_app_with(Arc::new(FxStore::new()), config).unwrap_err();
+            assert!(error.to_string().contains("CORS"), "{}", error);
+        }
+    }
+
+    #[tokio::test]
+    async fn symbol_info_reports_metadata_and_stored_range() {
+        // 1500 minutes spill into a second day
+        let app = app_with_bars(1500, ApiConfig::default());
+        let (status, body) = get_json(app.clone(), "/symbols/EURUSD").await;
+        assert_eq!(status, StatusCode::OK);
+        assert_eq!(body["name"], "EURUSD");
+        assert_eq!(body["base"], "EUR");
+        assert_eq!(body["quote"], "USD");
+        assert_eq!(body["scale"], PRICE_SCALE);
+        let first = DAY_START / 1_000_000_000;
+        assert_eq!(body["first_ts"], first);
+        assert_eq!(body["last_ts"], first + 1499 * 60);
+        assert_eq!(body["block_count"], 2);
+
+        let (status, body) = get_json(app, "/symbols/EURUSX").await;
+        assert_eq!(status, StatusCode::NOT_FOUND);
+        assert_eq!(body["error"], "symbol_not_found");
+    }
+
+    #[tokio::test]
+    async fn openapi_documents_routes_and_history_parameters() {
+        let app = app_with_bars(1, ApiConfig::default());
+        let (status, spec) = get_json(app.clone(), "/openapi.json").await;
+        assert_eq!(status, StatusCode::OK);
+
+        let history = &spec["paths"]["/history/{symbol}"]["get"];
+        let params: Vec<&str> = history["parameters"]
+            .as_array()
+            .unwrap()
+            .iter()
+            .map(|param| param["name"].as_str().unwrap())
+            .collect();
+        for name in ["symbol", "start", "end", "limit", "interval"] {
+            assert!(params.contains(&name), "{} missing from {:?}", name, params);
+        }
+        assert!(history["responses"]["404"].is_object());
+        assert!(spec["components"]["schemas"]["ErrorResponse"].is_object());
+        assert!(spec["paths"]["/bars/{symbol}"]["post"]["requestBody"].is_object());
+
+        let response = app
+            .oneshot(Request::builder().uri("/docs").body(Body::empty()).unwrap())
+            .await
+            .unwrap();
+        assert_eq!(response.status(), StatusCode::OK);
+        let body = to_bytes(response.into_body(), usize::MAX).await.unwrap();
+        assert!(String::from_utf8_lossy(&body).contains("openapi.json"));
+    }
+
+    #[cfg(feature = "persistence")]
+    #[tokio::test]
+    async fn shutdown_ends_streams_and_flushes_the_store() {
+        use crate::store::{BLOCK_FILE, StoreConfig};
+        use tokio::io::{AsyncBufReadExt, AsyncReadExt, AsyncWriteExt, BufReader};
+
+        let dir =
+            std::env::temp_dir().join(format!("fx-store-api-{}-shutdown", std::process::id()));
+        std::fs::remove_dir_all(&dir).ok();
+        let store = Arc::new(FxStore::open(&dir, StoreConfig::default()).unwrap());
+        store
+            .insert(
+                "EURUSD",
+                OHLCV::from_prices(DAY_START, 1.1, 1.1, 1.1, 1.1, 1, 0).unwrap(),
+            )
+            .unwrap();
+
+        let config = ServerConfig {
+            bind: IpAddr::V4(Ipv4Addr::LOCALHOST),
+            port: 0,
+            ..Default::default()
+        };
+        let (stop, stopped) = tokio::sync::oneshot::channel::<()>();
+        let server = start_server(Arc::clone(&store), config, async {
+            stopped.await.ok();
+        })
+        .await
+        .unwrap();
+
+        // An event stream never ends on its own
+        let mut conn = tokio::net::TcpStream::connect(server.addr).await.unwrap();
+        conn.write_all(b"GET /stream/EURUSD HTTP/1.1\r\nHost: localhost\r\n\r\n")
+            .await
+            .unwrap();
+        let mut reader = BufReader::new(conn);
+        let mut status = String::new();
+        reader.read_line(&mut status).await.unwrap();
+        assert!(status.starts_with("HTTP/1.1 200"), "{}", status);
+
+        stop.send(()).unwrap();
+        let mut rest = String::new();
+        tokio::time::timeout(Duration::from_secs(5), reader.read_to_string(&mut rest))
+            .await
+            .expect("stream did not end")
+            .unwrap();
+        // Chunked body closed properly rather than cut off
+        assert!(rest.ends_with("0\r\n\r\n"), "{:?}", rest);
+
+        tokio::time::timeout(Duration::from_secs(5), server.wait())
+            .await
+            .expect("server did not shut down")
+            .unwrap();
+        assert!(std::fs::metadata(dir.join(BLOCK_FILE)).unwrap().len() > 0);
+        drop(store);
+        std::fs::remove_dir_all(&dir).ok();
+    }
+
+    #[tokio::test]
+    async fn wide_json_history_is_streamed_as_one_valid_page() {
+        let bars = 3 * STREAM_CHUNK_BARS as u64 + 17;
+        let store = Arc::new(store_with_bars(bars));
+        let app = create_app_with(Arc::clone(&store), ApiConfig::default()).unwrap();
+        let uri = "/history/EURUSD?start=2024-01-02&end=2024-01-12";
+
+        let response = app
+            .clone()
+            .oneshot(Request::get(uri).body(Body::empty()).unwrap())
+            .await
+            .unwrap();
+        assert_eq!(response.status(), StatusCode::OK);
+        assert_eq!(response.headers()[header::CONTENT_TYPE], "application/json");
+        assert!(response.headers().get(header::CONTENT_LENGTH).is_none());
+        let mut body = response.into_body().into_data_stream();
+        let mut chunks = 0;
+        let mut bytes = Vec::new();
+        while let Some(chunk) = body.next().await {
+            bytes.extend_from_slice(&chunk.unwrap());
+            chunks += 1;
+        }
+        assert!(chunks > 1, "expected a chunked body, got {} chunk", chunks);
+
+        let page: serde_json::Value = serde_json::from_slice(&bytes).unwrap();
+        assert!(page["next_cursor"].is_null());
+        let data = page["data"].as_array().unwrap();
+        assert_eq!(data.len() as u64, bars);
+        let expected: Vec<serde_json::Value> = store
+            .query_range("EURUSD", DAY_START, u64::MAX)
+            .map(|rec| {
+                serde_json::to_value(PriceResponse::new("EURUSD".to_string(), &rec, PRICE_SCALE))
+                    .unwrap()
+            })
+            .collect();
+        assert_eq!(*data, expected);
+
+        // Errors still come back as a status, not a truncated body
+        let (status, body) = get_json(app, "/history/NOPE?start=2024-01-02&end=2024-01-12").await;
+        assert_eq!(status, StatusCode::NOT_FOUND);
+        assert!(body["error"].is_string());
+    }
+
+    async fn metric(app: Router, name: &str) -> u64 {
+        let response = app
+            .oneshot(Request::get("/metrics").body(Body::empty()).unwrap())
+            .await
+            .unwrap();
+        let body = to_bytes(response.into_body(), usize::MAX).await.unwrap();
+        let text = String::from_utf8(body.to_vec()).unwrap();
+        text.lines()
+            .find_map(|line| line.strip_prefix(name)?.strip_prefix(' ')?.parse().ok())
+            .unwrap_or_else(|| panic!("missing {} in\n{}", name, text))
+    }
+
+    #[tokio::test]
+    async fn response_cache_serves_repeats_until_new_data_lands() {
+        let store = Arc::new(FxStore::new());
+        for i in 0..10 {
+            store
+                .insert(
+                    "EURUSD",
+                    OHLCV::from_prices(DAY_START + i * MINUTE, 1.1, 1.1, 1.1, 1.1, 1, 0).unwrap(),
+                )
+                .unwrap();
+        }
+        let app = create_app_with(
+            Arc::clone(&store),
+            ApiConfig {
+                response_cache_bytes: 1 << 20,
+                ..ApiConfig::default()
+            },
+        )
+        .unwrap();
+        let uri = format!("/history/EURUSD?{}&interval=1h", RANGE);
+
+        let (_, first) = get_json(app.clone(), &uri).await;
+        // Same parameters in another order hit the same entry
+        let (_, second) = get_json(
+            app.clone(),
+            "/history/EURUSD?interval=1h&end=2024-01-03&start=2024-01-02",
+        )
+        .await;
+        assert_eq!(first, second);
+        assert_eq!(first["data"][0]["volume"], 10);
+        assert_eq!(
+            metric(app.clone(), "fx_store_response_cache_hits_total").await,
+            1
+        );
+        assert_eq!(
+            metric(app.clone(), "fx_store_response_cache_misses_total").await,
+            1
+        );
+        assert!(metric(app.clone(), "fx_store_response_cache_bytes").await > 0);
+
+        // A write inside the cached range invalidates it
+        store
+            .insert(
+                "EURUSD",
+                OHLCV::from_prices(DAY_START + 30 * MINUTE, 1.1, 1.1, 1.1, 1.1, 5, 0).unwrap(),
+            )
+            .unwrap();
+        let (_, third) = get_json(app.clone(), &uri).await;
+        assert_eq!(third["data"][0]["volume"], 15);
+        assert_eq!(
+            metric(app.clone(), "fx_store_response_cache_misses_total").await,
+            2
+        );
+
+        // Another symbol's writes leave the entry alone
+        store
+            .insert(
+                "GBPUSD",
+                OHLCV::from_prices(DAY_START, 1.2, 1.2, 1.2, 1.2, 1, 0).unwrap(),
+            )
+            .unwrap();
+        get_json(app.clone(), &uri).await;
+        assert_eq!(
+            metric(app.clone(), "fx_store_response_cache_hits_total").await,
+            2
+        );
+
+        // Ranges ending today aren't kept when the live TTL is zero
+        let app = create_app_with(
+            store,
+            ApiConfig {
+                response_cache_bytes: 1 << 20,
+                response_cache_live_ttl: Duration::ZERO,
+                ..ApiConfig::default()
+            },
+        )
+        .unwrap();
+        for _ in 0..2 {
+            get_json(app.clone(), "/count/EURUSD").await;
+        }
+        assert_eq!(
+            metric(app.clone(), "fx_store_response_cache_hits_total").await,
+            0
+        );
+        assert_eq!(metric(app, "fx_store_response_cache_misses_total").await, 0);
+    }
+
+    #[test]
+    fn response_cache_evicts_least_recently_used() {
+        let cache = ResponseCache::new(250, Duration::ZERO);
+        let key = |symbol: &str| CacheKey {
+            route: "/count/:symbol".to_string(),
+            symbol: symbol.to_string(),
+            params: BTreeMap::new(),
+            accept: None,
+        };
+        let entry = |generation| CachedResponse {
+            symbol_id: 0,
+            generation,
+            expires: None,
+            headers: HeaderMap::new(),
+            body: Bytes::from_static(b"{}"),
+            size: 100,
+            last_used: 0,
+        };
+
+        cache.insert(key("A"), entry(0));
+        cache.insert(key("B"), entry(0));
+        assert!(cache.get(&key("A"), 0, 0).is_some());
+        assert_eq!(cache.insert(key("C"), entry(0)), 200);
+        assert!(cache.get(&key("B"), 0, 0).is_none());
+        assert!(cache.get(&key("A"), 0, 0).is_some());
+
+        // A newer generation drops the entry
+        assert!(cache.get(&key("C"), 0, 1).is_none());
+        assert!(cache.get(&key("C"), 0, 0).is_none());
+    }
+
+    #[cfg(feature = "persistence")]
+    #[tokio::test]
+    async fn corrupt_days_are_annotated_and_scrubbed() {
+        use crate::store::{BLOCK_FILE, StoreConfig};
+
+        let dir = std::env::temp_dir().join(format!("fx-store-api-{}-scrub", std::process::id()));
+        std::fs::remove_dir_all(&dir).ok();
+        {
+            let store = FxStore::open(&dir, StoreConfig::default()).unwrap();
+            for day in 0..2 {
+                let ts = DAY_START + day * NANOS_PER_DAY;
+                store
+                    .insert(
+                        "EURUSD",
+                        OHLCV::from_prices(ts, 1.1, 1.1, 1.1, 1.1, 1, 0).unwrap(),
+                    )
+                    .unwrap();
+            }
+            store.flush_now().unwrap();
+        }
+        // The second day's data is the last record in the block file
+        let path = dir.join(BLOCK_FILE);
+        let mut bytes = std::fs::read(&path).unwrap();
+        *bytes.last_mut().unwrap() ^= 0x01;
+        std::fs::write(&path, bytes).unwrap();
+
+        let store = Arc::new(FxStore::open(&dir, StoreConfig::default()).unwrap());
+        let app = writable_app(Arc::clone(&store));
+        let uri = "/history/EURUSD?start=2024-01-02&end=2024-01-04";
+        let (status, body) = get_json(app.clone(), uri).await;
+        assert_eq!(status, StatusCode::OK);
+        assert_eq!(body["data"].as_array().unwrap().len(), 1);
+        assert_eq!(body["corrupt_days"], serde_json::json!(["2024-01-03"]));
+
+        let response = app
+            .clone()
+            .oneshot(
+                Request::get(format!("{}&format=csv", uri))
+                    .body(Body::empty())
+                    .unwrap(),
+            )
+            .await
+            .unwrap();
+        assert_eq!(response.headers()["x-corrupt-days"], "2024-01-03");
+
+        let (status, _) = post_json(app.clone(), "/admin/scrub", None, String::new()).await;
+        assert_eq!(status, StatusCode::UNAUTHORIZED);
+        let (status, body) =
+            post_json(app.clone(), "/admin/scrub", Some("secret"), String::new()).await;
+        assert_eq!(status, StatusCode::OK);
+        assert_eq!(body["blocks_checked"], 2);
+        assert_eq!(body["corrupt"][0]["date"], "2024-01-03");
+        assert_eq!(body["removed"], 0);
+
+        let (_, body) = post_json(
+            app.clone(),
+            "/admin/scrub?repair=true",
+            Some("secret"),
+            String::new(),
+        )
+        .await;
+        assert_eq!(body["removed"], 1);
+        let (_, body) = get_json(app, uri).await;
+        assert!(body.get("corrupt_days").is_none());
+
+        drop(store);
+        std::fs::remove_dir_all(&dir).ok();
+    }
+
+    #[tokio::test]
+    async fn chart_downsamples_a_year_to_max_points() {
+        use std::fmt::Write;
+
+        // A year of hourly bars through a CSV import
+        let mut csv = String::from("time,open,high,low,close,volume\n");
+        for hour in 0..365 * 24u64 {
+            let ts = DateTime::from_timestamp_nanos((DAY_START + hour * 60 * MINUTE) as i64);
+            writeln!(csv, "{},1.10000,1.10020,1.09980,1.10010,1", ts.to_rfc3339()).unwrap();
+        }
+        let path =
+            std::env::temp_dir().join(format!("fx-store-api-{}-chart.csv", std::process::id()));
+        std::fs::write(&path, csv).unwrap();
+        let store = FxStore::new();
+        store.import_csv(path.to_str().unwrap(), "EURUSD").unwrap();
+        store.flush();
+        std::fs::remove_file(&path).ok();
+        let app = create_app_with(Arc::new(store), ApiConfig::default()).unwrap();
+
+        let (status, body) = get_json(
+            app.clone(),
+            "/chart/EURUSD?start=2024-01-02&end=2025-01-01&max_points=1000",
+        )
+        .await;
+        assert_eq!(status, StatusCode::OK);
+        let bars = body["bars"].as_array().unwrap();
+        assert!(
+            !bars.is_empty() && bars.len() <= 1000,
+            "{} bars",
+            bars.len()
+        );
+        assert!(body["interval_secs"].as_u64().unwrap() >= 86_400);
+        assert_eq!(body["timeframe"], "1d");
+        assert_eq!(bars[0]["high"], 1.1002);
+
+        // A day fits at the native resolution (the end is exclusive)
+        let (_, body) =
+            get_json(app.clone(), "/chart/EURUSD?start=2024-01-02&end=2024-01-03").await;
+        assert_eq!(body["timeframe"], "1m");
+        assert_eq!(body["bars"].as_array().unwrap().len(), 24);
+
+        let (status, body) = get_json(app, "/chart/EURUSD?max_points=1").await;
+        assert_eq!(status, StatusCode::BAD_REQUEST);
+        assert_eq!(body["detail"]["param"], "max_points");
+    }
+
+    #[tokio::test]
+    async fn history_buckets_align_to_the_requested_zone() {
+        let app = app_with_bars(600, ApiConfig::default());
+        let starts = |bars: &serde_json::Value| -> Vec<(i64, u64)> {
+            bars.as_array()
+                .unwrap()
+                .iter()
+                .map(|bar| {
+                    let start = bar["timestamp"].as_i64().unwrap();
+                    (start, bar["volume"].as_u64().unwrap())
+                })
+                .collect()
+        };
+        let day = (DAY_START / 1_000_000_000) as i64;
+        let minutes = |m: i64| day + m * 60;
+
+        // Kolkata (+05:30) midnight is 18:30 UTC, so 4h buckets start at :30 UTC
+        let uri = format!(
+            "/history/EURUSD?interval=4h&align_tz=Asia/Kolkata&{}",
+            RANGE
+        );
+        let (status, body) = get_json(app.clone(), &uri).await;
+        assert_eq!(status, StatusCode::OK);
+        assert_eq!(
+            starts(&body["data"]),
+            [
+                (minutes(-90), 150),
+                (minutes(150), 240),
+                (minutes(390), 210)
+            ]
+        );
+
+        // FX day rolling at 17:00 New York (22:00 UTC in January)
+        let uri = format!(
+            "/history?symbols=EURUSD&interval=1d&align_tz=America/New_York&session_offset=17h&{}",
+            RANGE
+        );
+        let (status, body) = get_json(app, &uri).await;
+        assert_eq!(status, StatusCode::OK);
+        assert_eq!(starts(&body["EURUSD"]), [(minutes(-120), 600)]);
+    }
+
+    #[tokio::test]
+    async fn history_synthesizes_a_cross_from_two_legs() {
+        let store = FxStore::new();
+        for minute in 0..10 {
+            let ts = DAY_START + minute * MINUTE;
+            store
+                .insert(
+                    "EURUSD",
+                    OHLCV::from_prices(ts, 1.1, 1.1, 1.1, 1.1, 1, 0).unwrap(),
+                )
+                .unwrap();
+            if minute % 5 != 4 {
+                store
+                    .insert(
+                        "USDJPY",
+                        OHLCV::from_prices(ts, 150.0, 150.0, 150.0, 150.0, 1, 0).unwrap(),
+                    )
+                    .unwrap();
+            }
+        }
+        store
+            .insert(
+                "GBPCHF",
+                OHLCV::from_prices(DAY_START, 1.1, 1.1, 1.1, 1.1, 1, 0).unwrap(),
+            )
+            .unwrap();
+        let app = create_app_with(Arc::new(store), ApiConfig::default()).unwrap();
+
+        let uri = format!("/history/EURJPY?synthesize=EURUSD,USDJPY&{}", RANGE);
+        let (status, body) = get_json(app.clone(), &uri).await;
+        assert_eq!(status, StatusCode::OK);
+        let bars = body["data"].as_array().unwrap();
+        assert_eq!(bars.len(), 8);
+        assert!(
+            bars.iter()
+                .all(|bar| bar["close"] == 165.0 && bar["symbol"] == "EURJPY")
+        );
+
+        for (uri, status) in [
+            ("/history/EURJPY?synthesize=EURUSD", StatusCode::BAD_REQUEST),
+            (
+                "/history/EURJPY?synthesize=USDJPY,EURUSD",
+                StatusCode::BAD_REQUEST,
+            ),
+            (
+                "/history/EURJPY?synthesize=EURUSD,GBPCHF",
+                StatusCode::BAD_REQUEST,
+            ),
+            (
+                "/history/EURJPY?synthesize=EURUSD,NZDJPY",
+                StatusCode::NOT_FOUND,
+            ),
+        ] {
+            let (got, body) = get_json(app.clone(), &format!("{}&{}", uri, RANGE)).await;
+            assert_eq!(got, status, "{}: {}", uri, body);
+        }
+    }
+
+    #[tokio::test]
+    async fn basket_sums_weighted_closes() {
+        let store = FxStore::new();
+        for minute in 0..4 {
+            let ts = DAY_START + minute * MINUTE;
+            store
+                .insert(
+                    "EURUSD",
+                    OHLCV::from_prices(ts, 1.1, 1.1, 1.1, 1.1, 1, 0).unwrap(),
+                )
+                .unwrap();
+            if minute != 2 {
+                store
+                    .insert(
+                        "GBPUSD",
+                        OHLCV::from_prices(ts, 1.3, 1.3, 1.3, 1.3, 1, 0).unwrap(),
+                    )
+                    .unwrap();
+            }
+        }
+        let app = create_app_with(Arc::new(store), ApiConfig::default()).unwrap();
+
+        let uri = format!("/basket?components=EURUSD:0.5,GBPUSD:0.5&{}", RANGE);
+        let (status, body) = get_json(app.clone(), &uri).await;
+        assert_eq!(status, StatusCode::OK);
+        assert_eq!(body["components"][1]["weight"], 0.5);
+        let closes: Vec<f64> = body["bars"]
+            .as_array()
+            .unwrap()
+            .iter()
+            .map(|bar| bar["close"].as_f64().unwrap())
+            .collect();
+        assert_eq!(closes, [1.2, 1.2, 1.2]);
+        assert_eq!(body["bars"][0]["symbol"], "BASKET");
+
+        let uri = format!(
+            "/basket?components=EURUSD,GBPUSD:-0.5&max_gap=1m&name=EURGBP_SPREAD&{}",
+            RANGE
+        );
+        let (status, body) = get_json(app.clone(), &uri).await;
+        assert_eq!(status, StatusCode::OK);
+        let bars = body["bars"].as_array().unwrap();
+        assert_eq!(bars.len(), 4);
+        assert_eq!(bars[2]["symbol"], "EURGBP_SPREAD");
+        assert_eq!(bars[2]["close"], 0.45);
+
+        for (query, status) in [
+            ("components=", StatusCode::BAD_REQUEST),
+            ("components=EURUSD:heavy", StatusCode::BAD_REQUEST),
+            ("components=EURUSD&max_gap=soon", StatusCode::BAD_REQUEST),
+            ("components=EURUSD,NZDUSD", StatusCode::NOT_FOUND),
+        ] {
+            let (got, body) = get_json(app.clone(), &format!("/basket?{}&{}", query, RANGE)).await;
+            assert_eq!(got, status, "{}: {}", query, body);
+        }
+    }
+
+    #[tokio::test]
+    async fn count_and_health_detail_report_stored_bars() {
+        let app = app_with_bars(600, ApiConfig::default());
+
+        let (status, body) = get_json(app.clone(), &format!("/count/EURUSD?{}", RANGE)).await;
+        assert_eq!(status, StatusCode::OK);
+        assert_eq!(body, serde_json::json!({ "count": 600 }));
+
+        let uri = "/count/EURUSD?start=2024-01-02T01:00:00Z&end=2024-01-02T01:59:59Z";
+        let (_, body) = get_json(app.clone(), uri).await;
+        assert_eq!(body["count"], 60);
+
+        // Ranges are half-open, so windows that share a boundary never count a bar twice
+        for hour in ["00", "01", "02"] {
+            let next = format!("{:02}", hour.parse::<u32>().unwrap() + 1);
+            let uri = format!(
+                "/count/EURUSD?start=2024-01-02T{}:00:00Z&end=2024-01-02T{}:00:00Z",
+                hour, next
+            );
+            let (_, body) = get_json(app.clone(), &uri).await;
+            assert_eq!(body["count"], 60, "{}", uri);
+        }
+        let (status, body) = get_json(
+            app.clone(),
+            "/count/EURUSD?start=2024-01-02T01:00:00Z&end=2024-01-02T01:00:00Z",
+        )
+        .await;
+        assert_eq!(status, StatusCode::BAD_REQUEST);
+        assert_eq!(body["error"], "invalid_range");
+
+        let (status, _) = get_json(app.clone(), &format!("/count/GBPUSD?{}", RANGE)).await;
+        assert_eq!(status, StatusCode::NOT_FOUND);
+
+        let (_, body) = get_json(app.clone(), "/health").await;
+        assert!(body.get("symbols").is_none());
+        let (status, body) = get_json(app, "/health?detail=true").await;
+        assert_eq!(status, StatusCode::OK);
+        assert_eq!(body["status"], "ok");
+        assert_eq!(body["symbols"], serde_json::json!({ "EURUSD": 600 }));
+    }
+
+    #[tokio::test]
+    async fn ready_is_503_until_loading_finishes() {
+        let store = Arc::new(FxStore::new());
+        store
+            .insert(
+                "EURUSD",
+                OHLCV::from_prices(DAY_START, 1.1, 1.1, 1.1, 1.1, 1, 0).unwrap(),
+            )
+            .unwrap();
+        store.set_ready(false);
+        let app = create_app(Arc::clone(&store));
+
+        let (status, body) = get_json(app.clone(), "/ready").await;
+        assert_eq!(status, StatusCode::SERVICE_UNAVAILABLE);
+        assert_eq!(body["error"], "unavailable");
+        let (status, body) = get_json(app.clone(), "/health").await;
+        assert_eq!(status, StatusCode::OK);
+        assert_eq!(body["ready"], false);
+        assert_eq!(body["symbols_loaded"], 1);
+        assert_eq!(body["records_loaded"], 1);
+        assert!(body["uptime_seconds"].is_u64());
+        assert!(body["pending_compress_queue"].is_u64());
+
+        store.set_ready(true);
+        let (status, body) = get_json(app.clone(), "/ready").await;
+        assert_eq!(status, StatusCode::OK);
+        assert_eq!(body, serde_json::json!({ "ready": true }));
+        let (_, body) = get_json(app, "/health").await;
+        assert_eq!(body["ready"], true);
+    }
+
+    #[tokio::test]
+    async fn history_max_points_coarsens_candles_or_applies_lttb() {
+        let app = app_with_bars(600, ApiConfig::default());
+        let page = |query: &str| {
+            let app = app.clone();
+            let uri = format!("/history/EURUSD?{}&{}", query, RANGE);
+            async move {
+                let (status, body) = get_json(app, &uri).await;
+                assert_eq!(status, StatusCode::OK, "{}", uri);
+                (
+                    body["data"].as_array().unwrap().len(),
+                    body["reduced_by"].clone(),
+                )
+            }
+        };
+
+        assert_eq!(page("max_points=50").await, (50, "lttb".into()));
+        // 1m candles over a one-day range need 30m to fit in 50 points
+        assert_eq!(page("interval=1m&max_points=50").await, (20, "30m".into()));
+        assert_eq!(
+            page("interval=1h&max_points=50").await,
+            (10, serde_json::Value::Null)
+        );
+        assert_eq!(page("max_points=600").await, (600, serde_json::Value::Null));
+
+        let (status, body) = get_json(
+            app.clone(),
+            &format!("/history/EURUSD?max_points=2&{}", RANGE),
+        )
+        .await;
+        assert_eq!(status, StatusCode::BAD_REQUEST);
+        assert_eq!(body["detail"]["param"], "max_points");
+    }
+}
@@ -1,35 +1,294 @@
-#[cfg(test)]
-mod benches {
-    use super::*;
-    use test::Bencher;
-    
-    #[bench]
-    fn bench_import_1m_records(b: &mut Bencher) {
-        let store = FxStore::new();
-        let data = generate_test_data(1_000_000);
-        
-        b.iter(|| {
-            for record in &data {
-                store.insert(*record);
+//! 간이 벤치마크 (stable 툴체인이라 `#[bench]` 대신 무시된 테스트로 실행)
+//!
+//! `just bench` 또는 `cargo test --release -- --ignored --nocapture bench_`
+
+use crate::block::Columns;
+use crate::query::SimdFilter;
+use crate::store::FxStore;
+use crate::testutil::{generate_bars, load_store};
+use crate::types::{OHLCV, PRICE_SCALE, PriceField};
+use std::alloc::{GlobalAlloc, Layout, System};
+use std::cell::Cell;
+use std::hint::black_box;
+use std::time::{Duration, Instant};
+
+/// 현재 스레드의 할당 바이트를 세는 할당자 (테스트 바이너리 전용)
+struct CountingAlloc;
+
+thread_local! {
+    static ALLOCATED: Cell<u64> = const { Cell::new(0) };
+}
+
+unsafe impl GlobalAlloc for CountingAlloc {
+    unsafe fn alloc(&self, layout: Layout) -> *mut u8 {
+        ALLOCATED.with(|n| n.set(n.get() + layout.size() as u64));
+        unsafe { System.alloc(layout) }
+    }
+
+    unsafe fn dealloc(&self, ptr: *mut u8, layout: Layout) {
+        unsafe { System.dealloc(ptr, layout) }
+    }
+}
+
+#[global_allocator]
+static GLOBAL: CountingAlloc = CountingAlloc;
+
+/// f 실행 중 이 스레드가 할당한 바이트
+fn allocated_during<T>(f: impl FnOnce() -> T) -> (T, u64) {
+    let before = ALLOCATED.with(Cell::get);
+    let result = f();
+    (result, ALLOCATED.with(Cell::get) - before)
+}
+
+const MINUTE: u64 = 60_000_000_000;
+// 2024-01-02 00:00:00 UTC
+const DAY_START: u64 = 1_704_153_600_000_000_000;
+
+/// 워밍업 1회 후 iters회 평균 시간 출력
+fn measure<T>(name: &str, iters: u32, mut f: impl FnMut() -> T) -> Duration {
+    black_box(f());
+    let start = Instant::now();
+    for _ in 0..iters {
+        black_box(f());
+    }
+    let per_iter = start.elapsed() / iters;
+    println!("{:<32} {:>12.3?}/iter", name, per_iter);
+    per_iter
+}
+
+/// 결정적 합성 1분봉 (랜덤워크)
+fn generate_records(count: usize) -> Vec<OHLCV> {
+    generate_bars(0, DAY_START, count, 0)
+}
+
+/// 합성 데이터를 CSV 임포트로 적재한 스토어
+fn setup_store(days: usize) -> FxStore {
+    load_store(&[("EURUSD", &generate_records(days * 1440))])
+}
+
+#[test]
+#[ignore]
+fn bench_simd_vs_scalar() {
+    let records = generate_records(1_000_000);
+    let (min, max) = (109_900, 110_100);
+
+    let pairs = [
+        (
+            "count_in_range",
+            measure("count_in_range (simd)", 50, || {
+                SimdFilter::count_in_range(&records, min, max)
+            }),
+            measure("count_in_range (scalar)", 50, || {
+                SimdFilter::count_in_range_scalar(&records, min, max)
+            }),
+        ),
+        (
+            "minmax",
+            measure("minmax (simd)", 50, || {
+                SimdFilter::minmax(&records, PriceField::Low)
+            }),
+            measure("minmax (scalar)", 50, || {
+                SimdFilter::minmax_scalar(&records, PriceField::Low)
+            }),
+        ),
+        (
+            "sum_volume",
+            measure("sum_volume (simd)", 50, || SimdFilter::sum_volume(&records)),
+            measure("sum_volume (scalar)", 50, || {
+                SimdFilter::sum_volume_scalar(&records)
+            }),
+        ),
+    ];
+
+    for (name, simd, scalar) in pairs {
+        println!(
+            "{:<32} {:>11.2}x",
+            name,
+            scalar.as_secs_f64() / simd.as_secs_f64()
+        );
+    }
+}
+
+#[test]
+#[ignore]
+fn bench_columnar_filter() {
+    let records = generate_records(1_000_000);
+    let columns = Columns::from_records(&records);
+    let (min, max) = (109_900, 110_100);
+
+    let set = measure("filter_by_price (set_epi32)", 20, || {
+        SimdFilter::filter_by_price(&records, min, max).len()
+    });
+    let load = measure("filter_columns (loadu)", 20, || {
+        SimdFilter::filter_columns(&columns, min, max).len()
+    });
+    println!(
+        "{:<32} {:>11.2}x",
+        "columnar speedup",
+        set.as_secs_f64() / load.as_secs_f64()
+    );
+}
+
+/// 캐시된 블록 쿼리는 블록 복사 없이 Arc만 공유
+#[test]
+fn query_range_does_not_copy_blocks() {
+    let store = setup_store(5);
+    let end = DAY_START + 5 * 1440 * MINUTE - 1;
+    assert_eq!(
+        store.query_range("EURUSD", DAY_START, end).count(),
+        5 * 1440
+    );
+
+    let (count, bytes) = allocated_during(|| store.query_range("EURUSD", DAY_START, end).count());
+    assert_eq!(count, 5 * 1440);
+    // 블록 하나를 Vec으로 복사하는 것(1440 × 40 B)보다도 적음
+    let one_block_copy = (1440 * std::mem::size_of::<OHLCV>()) as u64;
+    println!("query_range 5d (cached): {} B allocated", bytes);
+    assert!(bytes < one_block_copy, "{} B allocated", bytes);
+}
+
+#[test]
+#[ignore]
+fn bench_query_range() {
+    let store = setup_store(30);
+    let end = DAY_START + 30 * 1440 * MINUTE - 1;
+
+    measure("query_range 30d", 20, || {
+        store.query_range("EURUSD", DAY_START, end).count()
+    });
+    measure("query_stats 30d", 20, || {
+        store.query_stats("EURUSD", DAY_START, end).unwrap()
+    });
+}
+
+#[test]
+#[ignore]
+fn bench_query_range_par() {
+    let end = DAY_START + 365 * 1440 * MINUTE - 1;
+
+    // 해제 결과는 블록에 캐시되므로 콜드 측정은 스토어마다 한 번씩
+    let cold = |parallel: bool| {
+        let store = setup_store(365);
+        let start = Instant::now();
+        let count = if parallel {
+            store.query_range_par("EURUSD", DAY_START, end).len()
+        } else {
+            store.query_range("EURUSD", DAY_START, end).count()
+        };
+        black_box(count);
+        start.elapsed()
+    };
+
+    let sequential = cold(false);
+    let parallel = cold(true);
+    println!("{:<32} {:>12.3?}", "query_range 1y (cold)", sequential);
+    println!("{:<32} {:>12.3?}", "query_range_par 1y (cold)", parallel);
+    println!(
+        "{:<32} {:>11.2}x ({} threads)",
+        "parallel speedup",
+        sequential.as_secs_f64() / parallel.as_secs_f64(),
+        rayon::current_num_threads()
+    );
+}
+
+#[test]
+#[ignore]
+fn bench_sma_streaming() {
+    use crate::query::TechnicalIndicators;
+    use std::collections::VecDeque;
+
+    const PERIOD: usize = 20;
+    let store = setup_store(365);
+    let end = DAY_START + 365 * 1440 * MINUTE - 1;
+
+    measure("sma 1y (collect)", 5, || {
+        let records: Vec<OHLCV> = store.query_range("EURUSD", DAY_START, end).collect();
+        TechnicalIndicators::sma(&records, PERIOD, PRICE_SCALE)
+    });
+    measure("sma 1y (for_each_in_range)", 5, || {
+        let mut window = VecDeque::with_capacity(PERIOD);
+        let (mut sum, mut last) = (0.0, 0.0);
+        store.for_each_in_range("EURUSD", DAY_START, end, |rec| {
+            let close = rec.price_f64(PriceField::Close, PRICE_SCALE);
+            window.push_back(close);
+            sum += close;
+            if window.len() > PERIOD {
+                sum -= window.pop_front().unwrap();
+            }
+            if window.len() == PERIOD {
+                last = sum / PERIOD as f64;
             }
         });
+        last
+    });
+}
+
+#[test]
+#[ignore]
+fn bench_import_ticks_day() {
+    use std::fmt::Write;
+
+    // 2M 틱 ≈ 하루 평균 23틱/초
+    const TICKS: u64 = 2_000_000;
+    let mut csv = String::with_capacity(TICKS as usize * 36);
+    let step = 86_400_000 / TICKS;
+    for i in 0..TICKS {
+        let ms = i * step;
+        let bid = 110_000 + (i * 7919 % 200);
+        writeln!(
+            csv,
+            "20240102 {:02}{:02}{:02}{:03},{}.{:05},{}.{:05},0",
+            ms / 3_600_000,
+            ms / 60_000 % 60,
+            ms / 1000 % 60,
+            ms % 1000,
+            bid / 100_000,
+            bid % 100_000,
+            (bid + 12) / 100_000,
+            (bid + 12) % 100_000,
+        )
+        .unwrap();
     }
-    
-    #[bench]
-    fn bench_query_range(b: &mut Bencher) {
-        let store = setup_test_store();
-        
-        b.iter(|| {
-            let start = chrono::Utc::now() - chrono::Duration::days(30);
-            let end = chrono::Utc::now();
-            
-            let count = store.query_range(
-                "EURUSD",
-                start.timestamp_nanos() as u64,
-                end.timestamp_nanos() as u64
-            ).count();
-            
-            assert!(count > 0);
+    let path = std::env::temp_dir().join(format!("fx-store-ticks-{}.csv", std::process::id()));
+    std::fs::write(&path, csv).unwrap();
+
+    let store = FxStore::new();
+    let start = Instant::now();
+    let report = store
+        .import_ticks_csv(path.to_str().unwrap(), "EURUSD")
+        .unwrap();
+    store.flush();
+    println!("{:<32} {:>12.3?}", "import_ticks_csv 2M", start.elapsed());
+    assert_eq!(report.imported, TICKS as usize);
+
+    measure("query_ticks 1d", 5, || {
+        store
+            .query_ticks("EURUSD", DAY_START, DAY_START + 1440 * MINUTE)
+            .len()
+    });
+    std::fs::remove_file(&path).ok();
+}
+
+#[test]
+#[ignore]
+fn bench_decompress_codecs() {
+    use crate::block::{BlockCodec, BlockLayout, CompressedBlock};
+
+    let records = generate_records(1440);
+    for codec in BlockCodec::ALL {
+        let layout = BlockLayout {
+            codec,
+            ..Default::default()
+        };
+        let block = CompressedBlock::new(20240102, 0, &records, layout);
+        println!(
+            "{:<32} {:>12} bytes",
+            format!("{:?}", codec),
+            block.data.len()
+        );
+        measure(&format!("decompress {:?}", codec), 1000, || {
+            block.evict();
+            block.try_decompress_shared().unwrap()
         });
     }
-}
\ No newline at end of file
+}
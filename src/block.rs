@@ -1,59 +1,940 @@
-use crate::types::OHLCV;
-use parking_lot::RwLock;
+use crate::error::{FxStoreError, Result};
+use crate::gorilla;
+use crate::types::{Granularity, OHLCV, Tick, date_to_ts};
+use parking_lot::{Mutex, RwLock};
+use serde::{Deserialize, Serialize};
 use std::sync::Arc;
+use xxhash_rust::xxh64::xxh64;
 use zstd::bulk::{compress, decompress};
 
-const BLOCK_SIZE: usize = 1440; // 1일 = 1440분
+pub const BLOCK_SIZE: usize = 1440; // 1분 간격 기본 블록 = 1440분
 
-/// 압축된 일일 블록
+/// 블록 압축 방식 (블록 파일·스냅샷에 블록마다 기록되므로 방식이 다른 블록이 섞여도 됨)
+///
+/// 직렬화는 variant 순서를 쓰므로 새 방식은 끝에만 추가
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub enum BlockCodec {
+    /// 고정폭 컬럼 배열(길이 접두사 없음) + zstd
+    #[default]
+    ZstdColumns,
+    /// delta-of-delta 타임스탬프 + XOR 컬럼 (`gorilla`)
+    Gorilla,
+    /// 고정폭 컬럼 배열 + LZ4 (압축률은 zstd보다 낮고 해제가 빠름)
+    Lz4Columns,
+    /// 압축하지 않은 고정폭 컬럼 배열 (메모리가 넉넉한 핫 데이터용)
+    RawColumns,
+}
+
+impl BlockCodec {
+    pub const ALL: [BlockCodec; 4] = [
+        BlockCodec::ZstdColumns,
+        BlockCodec::Gorilla,
+        BlockCodec::Lz4Columns,
+        BlockCodec::RawColumns,
+    ];
+}
+
+impl std::str::FromStr for BlockCodec {
+    type Err = String;
+
+    /// `zstd`, `gorilla`, `lz4`, `none`
+    fn from_str(s: &str) -> std::result::Result<Self, Self::Err> {
+        match s.to_ascii_lowercase().as_str() {
+            "zstd" => Ok(BlockCodec::ZstdColumns),
+            "gorilla" => Ok(BlockCodec::Gorilla),
+            "lz4" => Ok(BlockCodec::Lz4Columns),
+            "none" | "raw" => Ok(BlockCodec::RawColumns),
+            other => Err(format!(
+                "unknown codec {:?} (expected zstd, gorilla, lz4 or none)",
+                other
+            )),
+        }
+    }
+}
+
+/// zstd 기본 압축 레벨 (레벨 3이 속도/압축률 균형 최적)
+pub const DEFAULT_ZSTD_LEVEL: i32 = 3;
+
+/// 블록 압축 방식과 슬롯 간격 (심볼마다 고정)
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct BlockLayout {
+    pub codec: BlockCodec,
+    pub granularity: Granularity,
+    /// `ZstdColumns`의 압축 레벨 (해제에는 영향 없음)
+    pub zstd_level: i32,
+}
+
+impl Default for BlockLayout {
+    fn default() -> Self {
+        Self {
+            codec: BlockCodec::default(),
+            granularity: Granularity::default(),
+            zstd_level: DEFAULT_ZSTD_LEVEL,
+        }
+    }
+}
+
+/// 압축된 일일 블록 (`granularity.slots_per_day()`개 슬롯)
 #[derive(Clone)]
 pub struct CompressedBlock {
     pub date: u32, // YYYYMMDD
     pub symbol_id: u16,
+    pub layout: BlockLayout,
     pub data: Arc<Vec<u8>>,
-    cached: Arc<RwLock<Option<Box<[OHLCV; BLOCK_SIZE]>>>>,
+    /// 압축 데이터의 XXH64 (해제 전에 검증)
+    pub checksum: u64,
+    /// 비어있지 않은 슬롯의 가장 낮은/높은 가격 (OHLC 전체, 빈 블록은 min > max)
+    pub min_price: u32,
+    pub max_price: u32,
+    /// 비어있지 않은 슬롯의 첫/마지막 ts (빈 블록은 min > max)
+    pub min_ts: u64,
+    pub max_ts: u64,
+    /// 비어있지 않은 슬롯 수 (`from_parts` 직후에는 모름)
+    pub records: Option<u32>,
+    cached: Arc<RwLock<Option<Arc<[OHLCV]>>>>,
+    /// 해제는 한 스레드만 (기다린 스레드는 캐시를 공유). 값은 실제로 해제한 횟수
+    decoding: Arc<Mutex<u32>>,
 }
 
 impl CompressedBlock {
-    pub fn new(date: u32, symbol_id: u16, records: &[OHLCV]) -> Self {
-        let mut block = Box::new([OHLCV::default(); BLOCK_SIZE]);
+    pub fn new(date: u32, symbol_id: u16, records: &[OHLCV], layout: BlockLayout) -> Self {
+        let mut block = vec![OHLCV::default(); layout.granularity.slots_per_day()];
+        place_records(&mut block, records, layout.granularity);
+        Self::from_slots(date, symbol_id, layout, &block)
+    }
 
-        // 1분 간격으로 정렬
-        for rec in records {
-            let minute_of_day = ((rec.ts / 1_000_000_000) % 86400) / 60;
-            block[minute_of_day as usize] = *rec;
-        }
+    /// 기존 블록에 레코드를 덮어써 새 블록 생성 (같은 슬롯은 새 레코드 우선,
+    /// 기존 블록이 손상됐으면 `BlockCorrupt`)
+    pub fn try_merge(&self, records: &[OHLCV]) -> Result<Self> {
+        let mut block = self.try_decompress_shared()?.to_vec();
+        place_records(&mut block, records, self.layout.granularity);
+        Ok(Self::from_slots(
+            self.date,
+            self.symbol_id,
+            self.layout,
+            &block,
+        ))
+    }
 
-        // 압축 (레벨 3이 속도/압축률 균형 최적)
-        let serialized = bincode::serialize(&block.to_vec()).unwrap();
-        let compressed = compress(&serialized, 3).unwrap();
+    fn from_slots(date: u32, symbol_id: u16, layout: BlockLayout, block: &[OHLCV]) -> Self {
+        let _span = tracing::trace_span!("compress_block", date, symbol_id).entered();
+        let compressed = match layout.codec {
+            BlockCodec::ZstdColumns => compress(&encode_columns(block), layout.zstd_level).unwrap(),
+            BlockCodec::Gorilla => gorilla::encode(block),
+            BlockCodec::Lz4Columns => lz4_flex::compress(&encode_columns(block)),
+            BlockCodec::RawColumns => encode_columns(block),
+        };
 
         Self {
             date,
             symbol_id,
+            layout,
+            checksum: xxh64(&compressed, 0),
             data: Arc::new(compressed),
+            min_price: 0,
+            max_price: u32::MAX,
+            min_ts: 0,
+            max_ts: u64::MAX,
+            records: None,
+            cached: Arc::new(RwLock::new(None)),
+            decoding: Arc::new(Mutex::new(0)),
+        }
+        .with_bounds(block)
+    }
+
+    /// 저장해 둔 압축 데이터로 복원 (체크섬은 해제할 때 검증)
+    ///
+    /// 가격/시각 범위와 레코드 수는 모르므로 전부 포함으로 두고, 해제한 뒤 `with_bounds`로 채움
+    pub fn from_parts(
+        date: u32,
+        symbol_id: u16,
+        layout: BlockLayout,
+        data: Vec<u8>,
+        checksum: u64,
+    ) -> Self {
+        Self {
+            date,
+            symbol_id,
+            layout,
+            checksum,
+            data: Arc::new(data),
+            min_price: 0,
+            max_price: u32::MAX,
+            min_ts: 0,
+            max_ts: u64::MAX,
+            records: None,
             cached: Arc::new(RwLock::new(None)),
+            decoding: Arc::new(Mutex::new(0)),
+        }
+    }
+
+    /// 해제된 슬롯으로 가격/시각 범위와 레코드 수 메타데이터 계산
+    pub fn with_bounds(mut self, slots: &[OHLCV]) -> Self {
+        (self.min_price, self.max_price) = (u32::MAX, 0);
+        (self.min_ts, self.max_ts) = (u64::MAX, 0);
+        let mut records = 0;
+        for rec in slots.iter().filter(|rec| rec.ts != 0) {
+            records += 1;
+            let prices = [rec.open, rec.high, rec.low, rec.close];
+            self.min_price = prices.into_iter().fold(self.min_price, u32::min);
+            self.max_price = prices.into_iter().fold(self.max_price, u32::max);
+            self.min_ts = self.min_ts.min(rec.ts);
+            self.max_ts = self.max_ts.max(rec.ts);
+        }
+        self.records = Some(records);
+        self
+    }
+
+    /// [start_ts, end_ts]가 블록의 레코드를 모두 덮는지 (레코드 수를 아는 블록만)
+    pub fn is_covered_by(&self, start_ts: u64, end_ts: u64) -> bool {
+        self.records.is_some() && self.min_ts >= start_ts && self.max_ts <= end_ts
+    }
+
+    /// [min_price, max_price]에 드는 가격이 있을 수 있는지 (해제 없이 판단)
+    pub fn may_contain_price(&self, min_price: u32, max_price: u32) -> bool {
+        self.min_price <= max_price && self.max_price >= min_price
+    }
+
+    /// [start_ts, end_ts]에 드는 레코드가 있을 수 있는지 (해제 없이 판단)
+    pub fn may_contain_ts(&self, start_ts: u64, end_ts: u64) -> bool {
+        self.min_ts <= end_ts && self.max_ts >= start_ts
+    }
+
+    /// 해제된 슬롯의 사본 (수정용, 손상은 `BlockCorrupt`)
+    pub fn decompress(&self) -> Result<Vec<OHLCV>> {
+        Ok(self.try_decompress_shared()?.to_vec())
+    }
+
+    /// 압축 데이터가 기록된 체크섬과 일치하는지 확인
+    pub fn verify(&self) -> Result<()> {
+        let actual = xxh64(&self.data, 0);
+        if actual != self.checksum {
+            return Err(self.corrupt(format!(
+                "checksum mismatch: expected {:016x}, got {:016x}",
+                self.checksum, actual
+            )));
         }
+        Ok(())
     }
 
-    pub fn decompress(&self) -> Box<[OHLCV; BLOCK_SIZE]> {
+    /// 체크섬과 해제까지 확인 (캐시는 건드리지 않음, `FxStore::scrub`용)
+    pub fn check(&self) -> Result<()> {
+        self.verify()?;
+        self.decode().map(drop)
+    }
+
+    fn corrupt(&self, reason: impl Into<String>) -> FxStoreError {
+        FxStoreError::BlockCorrupt {
+            symbol_id: self.symbol_id,
+            date: self.date,
+            reason: reason.into(),
+        }
+    }
+
+    /// 캐시된 해제 결과를 복사 없이 공유 (손상은 `BlockCorrupt`)
+    pub fn try_decompress_shared(&self) -> Result<Arc<[OHLCV]>> {
         // 캐시 확인
         if let Some(cached) = self.cached.read().as_ref() {
-            return cached.clone();
+            return Ok(Arc::clone(cached));
         }
+        // 같은 블록을 동시에 해제하지 않도록, 잠금을 얻은 뒤 캐시를 다시 확인
+        let mut decodes = self.decoding.lock();
+        if let Some(cached) = self.cached.read().as_ref() {
+            return Ok(Arc::clone(cached));
+        }
+        *decodes += 1;
+
+        let _span = tracing::trace_span!(
+            "decompress_block",
+            date = self.date,
+            symbol_id = self.symbol_id
+        )
+        .entered();
+        self.verify()?;
+        let block = self.decode()?;
+
+        // 캐시 저장
+        let block: Arc<[OHLCV]> = Arc::from(block);
+        *self.cached.write() = Some(Arc::clone(&block));
+        Ok(block)
+    }
+
+    /// 압축 해제 (체크섬은 호출 측에서 검증)
+    fn decode(&self) -> Result<Vec<OHLCV>> {
+        let slots = self.layout.granularity.slots_per_day();
+        let columns = |bytes: &[u8]| {
+            decode_columns(bytes, slots).ok_or_else(|| self.corrupt("malformed columns"))
+        };
+        let mut block = match self.layout.codec {
+            BlockCodec::ZstdColumns => columns(
+                &decompress(&self.data, slots * COLUMN_ROW_BYTES)
+                    .map_err(|e| self.corrupt(e.to_string()))?,
+            )?,
+            BlockCodec::Gorilla => gorilla::decode(&self.data, slots)
+                .ok_or_else(|| self.corrupt("malformed gorilla stream"))?,
+            BlockCodec::Lz4Columns => columns(
+                &lz4_flex::decompress(&self.data, slots * COLUMN_ROW_BYTES)
+                    .map_err(|e| self.corrupt(e.to_string()))?,
+            )?,
+            BlockCodec::RawColumns => columns(&self.data)?,
+        };
+        // 다른 스토어에서 가져온 블록은 레코드의 symbol_id가 이 스토어의 것과 다를 수 있음
+        for rec in block.iter_mut().filter(|rec| rec.ts != 0) {
+            rec.symbol_id = self.symbol_id;
+        }
+        Ok(block)
+    }
+
+    /// 범위 안의 비어있지 않은 레코드 (캐시된 해제 버퍼를 Arc로 공유, 슬롯 복사 없음)
+    ///
+    /// 이터레이터가 Arc를 들고 있으므로 블록보다 오래 살아도 됨. 해제에 실패하면 `BlockCorrupt`
+    pub fn iter_range(
+        &self,
+        start_ts: u64,
+        end_ts: u64,
+    ) -> Result<impl Iterator<Item = OHLCV> + use<>> {
+        let data = self.try_decompress_shared()?;
+        let granularity = self.layout.granularity;
+        let day_start = date_to_ts(self.date);
+        let day_end = day_start + 86_400_000_000_000 - 1;
+
+        // 하루 경계 안쪽이면 해당 슬롯부터/까지만 훑음
+        let first = if start_ts > day_start {
+            granularity.slot(start_ts)
+        } else {
+            0
+        };
+        let last = if end_ts < day_start {
+            0
+        } else if end_ts < day_end {
+            granularity.slot(end_ts) + 1
+        } else {
+            data.len()
+        };
+
+        Ok((first..last.max(first))
+            .map(move |i| data[i])
+            .filter(move |rec| rec.ts != 0 && rec.ts >= start_ts && rec.ts <= end_ts))
+    }
 
-        // 압축 해제
-        let decompressed = decompress(&self.data, BLOCK_SIZE * 40).unwrap();
-        let records: Vec<OHLCV> = bincode::deserialize(&decompressed).unwrap();
-        let mut block = Box::new([OHLCV::default(); BLOCK_SIZE]);
-        for (i, record) in records.into_iter().enumerate() {
-            if i < BLOCK_SIZE {
-                block[i] = record;
+    /// 해제 결과가 캐시에 있는지
+    pub fn is_cached(&self) -> bool {
+        self.cached.read().is_some()
+    }
+
+    /// 캐시된 해제 결과 버리기 (이미 공유된 Arc는 유지됨)
+    pub fn evict(&self) {
+        *self.cached.write() = None;
+    }
+
+    /// 해제해 캐시에 올렸을 때의 크기
+    pub fn decompressed_bytes(&self) -> usize {
+        self.layout.granularity.slots_per_day() * std::mem::size_of::<OHLCV>()
+    }
+
+    /// 해제된 슬롯을 컬럼 배열로 변환 (SIMD 연속 로드용, 손상은 `BlockCorrupt`)
+    pub fn columns(&self) -> Result<Columns> {
+        Ok(Columns::from_records(&self.try_decompress_shared()?[..]))
+    }
+
+    /// 블록 내 가장 이른 레코드 (비어있는 슬롯 제외, 손상된 블록은 None)
+    pub fn first_record(&self) -> Option<OHLCV> {
+        self.try_decompress_shared()
+            .ok()?
+            .iter()
+            .find(|rec| rec.ts != 0)
+            .copied()
+    }
+
+    /// 블록 내 가장 최근 레코드 (비어있는 슬롯 제외, 손상된 블록은 None)
+    pub fn last_record(&self) -> Option<OHLCV> {
+        self.try_decompress_shared()
+            .ok()?
+            .iter()
+            .rev()
+            .find(|rec| rec.ts != 0)
+            .copied()
+    }
+}
+
+/// 압축된 일일 틱 블록 (ts순 정렬된 가변 길이)
+#[derive(Clone)]
+pub struct TickBlock {
+    pub date: u32, // YYYYMMDD
+    pub symbol_id: u16,
+    pub len: usize,
+    pub data: Arc<Vec<u8>>,
+    cached: Arc<RwLock<Option<Arc<[Tick]>>>>,
+    /// 해제는 한 스레드만 (`CompressedBlock::decoding`과 같음)
+    decoding: Arc<Mutex<()>>,
+}
+
+impl TickBlock {
+    pub fn new(date: u32, symbol_id: u16, ticks: &[Tick]) -> Self {
+        Self::from_unsorted(date, symbol_id, ticks.to_vec())
+    }
+
+    /// 기존 틱에 추가 (같은 ts는 도착 순서 유지)
+    pub fn merge(&self, ticks: &[Tick]) -> Self {
+        let mut all = self.decompress_shared().to_vec();
+        all.extend_from_slice(ticks);
+        Self::from_unsorted(self.date, self.symbol_id, all)
+    }
+
+    fn from_unsorted(date: u32, symbol_id: u16, mut ticks: Vec<Tick>) -> Self {
+        ticks.sort_by_key(|tick| tick.ts);
+        let serialized = bincode::serialize(&ticks).unwrap();
+        let compressed = compress(&serialized, DEFAULT_ZSTD_LEVEL).unwrap();
+
+        Self {
+            date,
+            symbol_id,
+            len: ticks.len(),
+            data: Arc::new(compressed),
+            cached: Arc::new(RwLock::new(None)),
+            decoding: Arc::new(Mutex::new(())),
+        }
+    }
+
+    /// 캐시된 해제 결과를 복사 없이 공유
+    pub fn decompress_shared(&self) -> Arc<[Tick]> {
+        if let Some(cached) = self.cached.read().as_ref() {
+            return Arc::clone(cached);
+        }
+        let _decoding = self.decoding.lock();
+        if let Some(cached) = self.cached.read().as_ref() {
+            return Arc::clone(cached);
+        }
+
+        // bincode Vec 직렬화는 u64 길이 접두사 포함
+        let decompressed = decompress(&self.data, self.len * 32 + 8).unwrap();
+        let ticks: Vec<Tick> = bincode::deserialize(&decompressed).unwrap();
+        let ticks: Arc<[Tick]> = Arc::from(ticks);
+        *self.cached.write() = Some(Arc::clone(&ticks));
+        ticks
+    }
+}
+
+/// 필드별 연속 배열 (struct-of-arrays)
+#[derive(Clone, Debug, Default, Serialize, Deserialize)]
+pub struct Columns {
+    pub ts: Vec<u64>,
+    pub open: Vec<u32>,
+    pub high: Vec<u32>,
+    pub low: Vec<u32>,
+    pub close: Vec<u32>,
+    pub volume: Vec<u32>,
+    pub volume_hi: Vec<u32>,
+    pub spread: Vec<u16>,
+    pub symbol_id: Vec<u16>,
+}
+
+impl Columns {
+    pub fn from_records(records: &[OHLCV]) -> Self {
+        let mut columns = Self::default();
+        for rec in records {
+            columns.ts.push(rec.ts);
+            columns.open.push(rec.open);
+            columns.high.push(rec.high);
+            columns.low.push(rec.low);
+            columns.close.push(rec.close);
+            columns.volume.push(rec.volume);
+            columns.volume_hi.push(rec.volume_hi);
+            columns.spread.push(rec.spread);
+            columns.symbol_id.push(rec.symbol_id);
+        }
+        columns
+    }
+
+    pub fn len(&self) -> usize {
+        self.ts.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.ts.is_empty()
+    }
+
+    /// i번째 행을 레코드로 재조립
+    #[inline]
+    pub fn record(&self, i: usize) -> OHLCV {
+        OHLCV {
+            ts: self.ts[i],
+            open: self.open[i],
+            high: self.high[i],
+            low: self.low[i],
+            close: self.close[i],
+            volume: self.volume[i],
+            volume_hi: self.volume_hi[i],
+            spread: self.spread[i],
+            symbol_id: self.symbol_id[i],
+            ..Default::default()
+        }
+    }
+}
+
+/// 고정폭 컬럼 인코딩의 행당 바이트 (패딩 제외)
+const COLUMN_ROW_BYTES: usize = 36;
+
+/// 필드별로 슬롯 수만큼의 little-endian 값을 이어 붙임 (길이 접두사 없음)
+///
+/// 순서: ts, open, high, low, close, volume, volume_hi, spread, symbol_id
+fn encode_columns(block: &[OHLCV]) -> Vec<u8> {
+    let mut out = Vec::with_capacity(block.len() * COLUMN_ROW_BYTES);
+    out.extend(block.iter().flat_map(|rec| { rec.ts }.to_le_bytes()));
+    for field in [
+        |rec: &OHLCV| rec.open,
+        |rec: &OHLCV| rec.high,
+        |rec: &OHLCV| rec.low,
+        |rec: &OHLCV| rec.close,
+        |rec: &OHLCV| rec.volume,
+        |rec: &OHLCV| rec.volume_hi,
+    ] {
+        out.extend(block.iter().flat_map(|rec| field(rec).to_le_bytes()));
+    }
+    out.extend(block.iter().flat_map(|rec| { rec.spread }.to_le_bytes()));
+    out.extend(block.iter().flat_map(|rec| { rec.symbol_id }.to_le_bytes()));
+    out
+}
+
+/// `encode_columns`의 역, 길이가 `slots`와 맞지 않으면 None
+fn decode_columns(bytes: &[u8], slots: usize) -> Option<Vec<OHLCV>> {
+    if bytes.len() != slots * COLUMN_ROW_BYTES {
+        return None;
+    }
+    let (ts, rest) = bytes.split_at(slots * 8);
+    let (u32s, rest) = rest.split_at(slots * 24);
+    let (spread, symbol_id) = rest.split_at(slots * 2);
+
+    let u32_at = |col: usize, i: usize| {
+        let at = (col * slots + i) * 4;
+        u32::from_le_bytes(u32s[at..at + 4].try_into().unwrap())
+    };
+    let u16_at = |col: &[u8], i: usize| u16::from_le_bytes([col[i * 2], col[i * 2 + 1]]);
+
+    let records = ts
+        .chunks_exact(8)
+        .enumerate()
+        .map(|(i, ts)| OHLCV {
+            ts: u64::from_le_bytes(ts.try_into().unwrap()),
+            open: u32_at(0, i),
+            high: u32_at(1, i),
+            low: u32_at(2, i),
+            close: u32_at(3, i),
+            volume: u32_at(4, i),
+            volume_hi: u32_at(5, i),
+            spread: u16_at(spread, i),
+            symbol_id: u16_at(symbol_id, i),
+            ..Default::default()
+        })
+        .collect();
+    Some(records)
+}
+
+/// 같은 슬롯(UTC)에 떨어지는 레코드를 하나의 봉으로 병합하고 ts순으로 정렬, 병합된 레코드 수 반환
+pub(crate) fn coalesce_bars(records: &mut Vec<OHLCV>, granularity: Granularity) -> usize {
+    let width = granularity.nanos();
+
+    let before = records.len();
+    records.sort_by_key(|rec| rec.ts);
+    records.dedup_by(|rec, bar| {
+        let same_slot = rec.ts / width == bar.ts / width;
+        if same_slot {
+            merge_bar(bar, rec);
+        }
+        same_slot
+    });
+    before - records.len()
+}
+
+/// 두 봉 병합: 시가는 앞선 ts, 종가/스프레드는 늦은 ts 기준, 거래량 합산
+fn merge_bar(bar: &mut OHLCV, rec: &OHLCV) {
+    let (first, last) = if rec.ts < bar.ts {
+        (*rec, *bar)
+    } else {
+        (*bar, *rec)
+    };
+    let mut merged = OHLCV {
+        ts: first.ts,
+        open: first.open,
+        high: first.high.max(last.high),
+        low: first.low.min(last.low),
+        close: last.close,
+        spread: last.spread,
+        symbol_id: last.symbol_id,
+        ..Default::default()
+    };
+    merged.set_total_volume(first.total_volume() + last.total_volume());
+    *bar = merged;
+}
+
+/// 슬롯 간격으로 정렬 (같은 배치 안의 충돌은 병합, 기존 슬롯은 새 레코드로 교체)
+fn place_records(block: &mut [OHLCV], records: &[OHLCV], granularity: Granularity) {
+    let mut filled = vec![false; block.len()];
+    for rec in records {
+        let slot = granularity.slot(rec.ts);
+        if filled[slot] {
+            merge_bar(&mut block[slot], rec);
+        } else {
+            block[slot] = *rec;
+            filled[slot] = true;
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // 2024-01-02 00:00:00 UTC
+    const DAY_START: u64 = 1_704_153_600_000_000_000;
+    const MINUTE: u64 = 60_000_000_000;
+
+    fn layout(codec: BlockCodec) -> BlockLayout {
+        BlockLayout {
+            codec,
+            ..Default::default()
+        }
+    }
+
+    /// 주말 전후처럼 일부 분이 빠진 랜덤워크 1분봉 하루치
+    fn realistic_day() -> Vec<OHLCV> {
+        let mut state = 0x2545_f491_4f6c_dd1du64;
+        let mut close = 109_850u32;
+        (0..BLOCK_SIZE as u64)
+            .filter_map(|minute| {
+                state ^= state << 13;
+                state ^= state >> 7;
+                state ^= state << 17;
+                if state.is_multiple_of(50) {
+                    return None;
+                }
+                let open = close;
+                close = (close as i64 + (state % 13) as i64 - 6) as u32;
+                let rec = OHLCV {
+                    ts: DAY_START + minute * MINUTE,
+                    open,
+                    high: open.max(close) + (state >> 8) as u32 % 4,
+                    low: open.min(close) - (state >> 16) as u32 % 4,
+                    close,
+                    volume: (state >> 24) as u32 % 200,
+                    symbol_id: 3,
+                    spread: 12 + (state >> 32) as u16 % 3,
+                    ..Default::default()
+                };
+                Some(rec)
+            })
+            .collect()
+    }
+
+    #[test]
+    fn gorilla_round_trips_and_beats_zstd_columns() {
+        let records = realistic_day();
+        let zstd = CompressedBlock::new(20240102, 3, &records, layout(BlockCodec::ZstdColumns));
+        let gorilla = CompressedBlock::new(20240102, 3, &records, layout(BlockCodec::Gorilla));
+
+        let (a, b) = (zstd.decompress().unwrap(), gorilla.decompress().unwrap());
+        for (x, y) in a.iter().zip(b.iter()) {
+            assert_eq!(
+                bincode::serialize(x).unwrap(),
+                bincode::serialize(y).unwrap()
+            );
+        }
+        assert_eq!(a.iter().filter(|r| r.ts != 0).count(), records.len());
+
+        assert!(
+            gorilla.data.len() * 10 < zstd.data.len() * 7,
+            "gorilla {} bytes vs zstd-columns {} bytes",
+            gorilla.data.len(),
+            zstd.data.len()
+        );
+    }
+
+    #[test]
+    fn every_codec_round_trips() {
+        let records = realistic_day();
+        let mut sizes = Vec::new();
+        for codec in BlockCodec::ALL {
+            let block = CompressedBlock::new(20240102, 3, &records, layout(codec));
+            let slots = block.decompress().unwrap();
+            let stored: Vec<_> = slots.iter().filter(|r| r.ts != 0).collect();
+            assert_eq!(stored.len(), records.len(), "{:?}", codec);
+            for (x, y) in stored.iter().zip(&records) {
+                assert_eq!(
+                    bincode::serialize(*x).unwrap(),
+                    bincode::serialize(y).unwrap(),
+                    "{:?}",
+                    codec
+                );
             }
+            sizes.push(block.data.len());
         }
+        // zstd < lz4 < 무압축
+        assert!(sizes[0] < sizes[2] && sizes[2] < sizes[3], "{:?}", sizes);
+        assert_eq!(sizes[3], BLOCK_SIZE * COLUMN_ROW_BYTES);
 
-        // 캐시 저장
-        *self.cached.write() = Some(block.clone());
-        block
+        assert_eq!("LZ4".parse(), Ok(BlockCodec::Lz4Columns));
+        assert_eq!("none".parse(), Ok(BlockCodec::RawColumns));
+        assert!("brotli".parse::<BlockCodec>().is_err());
+    }
+
+    #[test]
+    fn fixed_columns_round_trip_byte_exact() {
+        let mut slots = vec![OHLCV::default(); BLOCK_SIZE];
+        place_records(&mut slots, &realistic_day(), Granularity::Minute);
+        for (i, rec) in slots.iter_mut().enumerate().filter(|(_, rec)| rec.ts != 0) {
+            rec.spread = i as u16;
+            rec.volume_hi = u32::MAX - i as u32;
+        }
+
+        let encoded = encode_columns(&slots);
+        assert_eq!(encoded.len(), BLOCK_SIZE * COLUMN_ROW_BYTES);
+        let decoded = decode_columns(&encoded, BLOCK_SIZE).unwrap();
+        assert!(decode_columns(&encoded[1..], BLOCK_SIZE).is_none());
+
+        let block =
+            CompressedBlock::from_slots(20240102, 3, layout(BlockCodec::ZstdColumns), &slots);
+        for decoded in [decoded, block.decompress().unwrap()] {
+            assert_eq!(
+                bincode::serialize(&decoded).unwrap(),
+                bincode::serialize(&slots).unwrap()
+            );
+        }
+    }
+
+    #[test]
+    fn columns_round_trip() {
+        let records = realistic_day();
+        let columns = Columns::from_records(&records);
+        assert_eq!(columns.len(), records.len());
+        for (i, rec) in records.iter().enumerate() {
+            assert_eq!(
+                bincode::serialize(&columns.record(i)).unwrap(),
+                bincode::serialize(rec).unwrap()
+            );
+        }
+
+        let block = CompressedBlock::new(20240102, 3, &records, layout(BlockCodec::ZstdColumns));
+        let close: Vec<u32> = block
+            .columns()
+            .unwrap()
+            .close
+            .into_iter()
+            .filter(|&c| c != 0)
+            .collect();
+        let expected: Vec<u32> = records.iter().map(|r| r.close).collect();
+        assert_eq!(close, expected);
+    }
+
+    fn tick(ts: u64, open: u32, high: u32, low: u32, close: u32, volume: u32) -> OHLCV {
+        OHLCV {
+            ts,
+            open,
+            high,
+            low,
+            close,
+            volume,
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn colliding_records_merge_into_one_bar() {
+        let second = 1_000_000_000;
+        // 순서가 섞인 초 단위 봉 3개 + 다음 분 1개
+        let mut records = vec![
+            tick(DAY_START + 30 * second, 105, 108, 101, 102, 3),
+            tick(DAY_START, 100, 104, 99, 103, 1),
+            tick(DAY_START + 59 * second, 102, 103, 97, 98, u32::MAX),
+            tick(DAY_START + MINUTE, 98, 99, 98, 99, 7),
+        ];
+
+        let block = CompressedBlock::new(20240102, 0, &records, layout(BlockCodec::ZstdColumns));
+        let merged = coalesce_bars(&mut records, Granularity::Minute);
+        assert_eq!(merged, 2);
+        assert_eq!(records.len(), 2);
+
+        let slot = block.decompress().unwrap()[0];
+        for bar in [records[0], slot] {
+            assert_eq!(
+                ({ bar.ts }, { bar.open }, { bar.high }, { bar.low }, {
+                    bar.close
+                }),
+                (DAY_START, 100, 108, 97, 98)
+            );
+            assert_eq!(bar.total_volume(), u32::MAX as u64 + 4);
+        }
+        assert_eq!({ records[1].close }, 99);
+    }
+
+    #[test]
+    fn merge_replaces_existing_slot() {
+        let block = CompressedBlock::new(
+            20240102,
+            0,
+            &[tick(DAY_START, 100, 100, 100, 100, 1)],
+            layout(BlockCodec::ZstdColumns),
+        );
+        let merged = block
+            .try_merge(&[tick(DAY_START, 200, 200, 200, 200, 2)])
+            .unwrap();
+        let slot = merged.decompress().unwrap()[0];
+        assert_eq!(({ slot.close }, { slot.volume }), (200, 2));
+    }
+
+    #[test]
+    fn tick_block_sorts_and_merges() {
+        let quote = |ts: u64, bid: f64| Tick::from_quote(ts, bid, bid + 0.0001, 0, 1).unwrap();
+        let block = TickBlock::new(
+            20240102,
+            1,
+            &[quote(DAY_START + 5, 1.1), quote(DAY_START + 1, 1.2)],
+        );
+        let merged = block.merge(&[quote(DAY_START + 3, 1.3), quote(DAY_START + 5, 1.4)]);
+
+        let ticks = merged.decompress_shared();
+        assert_eq!(merged.len, 4);
+        let order: Vec<(u64, u32)> = ticks.iter().map(|t| (t.ts - DAY_START, t.bid)).collect();
+        assert_eq!(
+            order,
+            [(1, 120_000), (3, 130_000), (5, 110_000), (5, 140_000)]
+        );
+        assert_eq!(block.decompress_shared().len(), 2);
+    }
+
+    #[test]
+    fn merge_keeps_codec() {
+        let records = realistic_day();
+        let block = CompressedBlock::new(20240102, 3, &records[..10], layout(BlockCodec::Gorilla));
+        let merged = block.try_merge(&records[10..]).unwrap();
+        assert_eq!(merged.layout.codec, BlockCodec::Gorilla);
+        assert_eq!(
+            merged.last_record().map(|r| r.ts),
+            records.last().map(|r| r.ts)
+        );
+    }
+
+    #[test]
+    fn corrupt_blocks_return_typed_errors() {
+        let records = realistic_day();
+        for codec in BlockCodec::ALL {
+            let mut block = CompressedBlock::new(20240102, 3, &records[..10], layout(codec));
+            block.data = Arc::new(block.data[..block.data.len() / 2].to_vec());
+            // 체크섬을 맞춰도 해제 단계에서 걸림
+            block.checksum = xxh64(&block.data, 0);
+            assert!(matches!(
+                block.try_decompress_shared(),
+                Err(FxStoreError::BlockCorrupt {
+                    symbol_id: 3,
+                    date: 20240102,
+                    ..
+                })
+            ));
+            assert!(block.check().is_err());
+            assert!(!block.is_cached());
+            assert!(block.first_record().is_none());
+            assert!(block.try_merge(&records[..1]).is_err());
+            assert!(block.iter_range(0, u64::MAX).is_err());
+            assert!(block.columns().is_err());
+        }
+    }
+
+    #[test]
+    fn flipped_byte_fails_checksum() {
+        let records = realistic_day();
+        for codec in BlockCodec::ALL {
+            let mut block = CompressedBlock::new(20240102, 3, &records, layout(codec));
+            assert!(block.verify().is_ok());
+
+            let mut data = block.data.to_vec();
+            let mid = data.len() / 2;
+            data[mid] ^= 0x01;
+            block.data = Arc::new(data);
+            assert!(matches!(
+                block.verify(),
+                Err(FxStoreError::BlockCorrupt { .. })
+            ));
+            assert!(matches!(
+                block.try_decompress_shared(),
+                Err(FxStoreError::BlockCorrupt { .. })
+            ));
+        }
+    }
+
+    #[test]
+    fn checksum_is_seed_zero_xxh64() {
+        // 블록 파일·스냅샷에 기록된 체크섬과 맞아야 하므로 알고리즘과 seed를 고정
+        let block = CompressedBlock::new(
+            20240102,
+            3,
+            &realistic_day()[..10],
+            layout(BlockCodec::ZstdColumns),
+        );
+        assert_eq!(block.checksum, xxh64(&block.data, 0));
+        assert_eq!(xxh64(b"", 0), 0xEF46_DB37_51D8_E999);
+        assert_eq!(xxh64(b"abc", 0), 0x44BC_2CF5_AD77_0999);
+    }
+
+    #[test]
+    fn iter_range_clips_to_slots_in_range() {
+        let records = realistic_day();
+        let block = CompressedBlock::new(20240102, 3, &records, layout(BlockCodec::ZstdColumns));
+        let expected = |start: u64, end: u64| {
+            records
+                .iter()
+                .filter(|rec| rec.ts >= start && rec.ts <= end)
+                .map(|rec| rec.ts)
+                .collect::<Vec<_>>()
+        };
+
+        for (start, end) in [
+            (0, u64::MAX),
+            (DAY_START + 100 * MINUTE, DAY_START + 200 * MINUTE),
+            (DAY_START + 100 * MINUTE + 1, DAY_START + 200 * MINUTE - 1),
+            (DAY_START + 1439 * MINUTE, u64::MAX),
+            (0, DAY_START - 1),
+            (DAY_START + 200 * MINUTE, DAY_START + 100 * MINUTE),
+        ] {
+            let got: Vec<u64> = block
+                .iter_range(start, end)
+                .unwrap()
+                .map(|rec| rec.ts)
+                .collect();
+            assert_eq!(got, expected(start, end), "{}..={}", start, end);
+        }
+    }
+
+    #[test]
+    fn concurrent_readers_of_a_cold_block_decompress_once() {
+        let records: Vec<OHLCV> = (0..BLOCK_SIZE as u64)
+            .map(|minute| OHLCV {
+                ts: DAY_START + minute * MINUTE,
+                close: 110_000 + minute as u32,
+                ..Default::default()
+            })
+            .collect();
+        let block = CompressedBlock::new(20240102, 0, &records, BlockLayout::default());
+        block.evict();
+
+        const THREADS: usize = 16;
+        let barrier = std::sync::Barrier::new(THREADS);
+        let decoded: Vec<Arc<[OHLCV]>> = std::thread::scope(|scope| {
+            let handles: Vec<_> = (0..THREADS)
+                .map(|_| {
+                    scope.spawn(|| {
+                        barrier.wait();
+                        block.try_decompress_shared().unwrap()
+                    })
+                })
+                .collect();
+            handles.into_iter().map(|h| h.join().unwrap()).collect()
+        });
+
+        assert_eq!(*block.decoding.lock(), 1);
+        assert!(decoded.iter().all(|slots| Arc::ptr_eq(slots, &decoded[0])));
+
+        // 캐시를 비우면 다시 해제
+        block.evict();
+        block.try_decompress_shared().unwrap();
+        assert_eq!(*block.decoding.lock(), 2);
     }
 }
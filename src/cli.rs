@@ -0,0 +1,518 @@
+//! `fx-store` 바이너리 서브커맨드
+//!
+//! ```text
+//! fx-store serve  [--port 8080] [--bind 0.0.0.0] [--data-dir ./store] [--import-dir DIR]
+//! fx-store import --symbol XAUUSD [--format auto|histdata] [--granularity second|minute|hour]
+//!                  [--force] [--data-dir ./store] FILE...
+//! fx-store query  --symbol XAUUSD --start 2024-01-01 --end 2024-02-01 [--format csv|ndjson]
+//! fx-store stats  [--data-dir ./store]
+//! fx-store scrub  [--repair] [--data-dir ./store]
+//! ```
+//!
+//! 인자는 clap derive로 파싱 (`fx-store --help`), 플래그가 없으면
+//! `StoreConfig::from_env`/`ServerConfig::from_env`와 `FX_STORE_DATA_DIR` 사용
+
+use crate::api::{
+    HistoryFormat, ServerConfig, parse_datetime, price_decimals, start_server, write_history_line,
+};
+use crate::store::{ExistingDays, FxStore, ImportOptions, StoreConfig};
+use crate::types::{Granularity, MAX_TS, TimeRange, histdata_est};
+use anyhow::{Context, anyhow, bail};
+use clap::{Parser, Subcommand, ValueEnum};
+use std::io::Write;
+use std::net::IpAddr;
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+
+/// `--help` 끝에 붙는 환경 변수 설명
+const ENV_HELP: &str = "\
+Writes go to the WAL in --data-dir and are flushed to its block file every
+FX_STORE_FLUSH_SECS (30) and on exit. FX_STORE_WAL_FSYNC sets when the WAL reaches disk:
+always, every N ms, or seal (on block flush, the default). import reads .parquet files by
+their column names and skips CSV files whose contents were already imported for the symbol
+unless --force is given; serve --import-dir also skips days that are already stored. scrub
+checks every stored block and exits non-zero if any is corrupt; --repair deletes those so
+their days can be imported again. query prints the bars from --start up to but not including
+--end, and skips corrupt days unless FX_STORE_ON_CORRUPT=error. On SIGINT/SIGTERM serve
+stops accepting connections and gives in-flight requests FX_STORE_SHUTDOWN_SECS (10) to
+finish. FX_STORE_PORT, FX_STORE_BIND, FX_STORE_CACHE_MB, FX_STORE_CODEC
+(zstd|gorilla|lz4|none), FX_STORE_ZSTD_LEVEL, FX_STORE_COMPRESS_WORKERS,
+FX_STORE_COMPRESS_QUEUE, FX_STORE_WRITE_TOKEN, FX_STORE_MAX_QUERY_DAYS,
+FX_STORE_RESPONSE_CACHE_MB and FX_STORE_RESPONSE_CACHE_TTL_SECS configure the rest;
+FX_STORE_API_KEYS[_FILE], FX_STORE_KEYS_FOR_READS, FX_STORE_KEY_RATE and FX_STORE_ANON_RATE
+turn on API keys and rate limits. FX_STORE_CORS_ORIGINS (comma separated) limits browser
+callers to those origins, with FX_STORE_CORS_CREDENTIALS=1 letting them send credentials;
+unset, any origin may call. Logs go to stderr, filtered by RUST_LOG (e.g. fx_store=debug for
+a line per request) and formatted by FX_STORE_LOG_FORMAT=text|json.";
+
+const DEFAULT_DATA_DIR: &str = "./store";
+
+/// CSV 임포트 시 시간대 해석
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, ValueEnum)]
+pub enum ImportFormat {
+    /// 컬럼 자동 감지, 파일에 시간대가 없으면 UTC
+    #[default]
+    #[value(help = "detect the columns; UTC unless the file names a time zone")]
+    Auto,
+    /// HISTDATA ASCII M1 (EST 고정)
+    #[value(help = "HISTDATA ASCII M1 bars in fixed EST")]
+    Histdata,
+}
+
+impl ImportFormat {
+    fn options(self) -> ImportOptions {
+        ImportOptions {
+            source_tz: (self == Self::Histdata).then(histdata_est),
+            ..Default::default()
+        }
+    }
+}
+
+#[derive(Clone, Debug, PartialEq, Eq, Subcommand)]
+pub enum Command {
+    /// HTTP API 서버 (`import_dir`의 CSV는 백그라운드에서 HISTDATA로 임포트)
+    #[command(about = "Serve the HTTP API")]
+    Serve {
+        /// None이면 `ServerConfig::from_env`의 값
+        #[arg(long, help = "Port to listen on [default: $FX_STORE_PORT or 8080]")]
+        port: Option<u16>,
+        #[arg(
+            long,
+            value_name = "ADDR",
+            help = "Address to bind [default: $FX_STORE_BIND]"
+        )]
+        bind: Option<IpAddr>,
+        #[arg(
+            long,
+            value_name = "DIR",
+            help = "Import the HISTDATA CSV files in DIR in the background"
+        )]
+        import_dir: Option<PathBuf>,
+    },
+    #[command(about = "Import CSV or Parquet files without starting the server")]
+    Import {
+        #[arg(long, help = "Symbol the files belong to")]
+        symbol: String,
+        #[arg(long, value_enum, default_value_t, help = "How to read CSV timestamps")]
+        format: ImportFormat,
+        /// 새 심볼의 봉 간격 (None이면 1분, 이미 있는 심볼은 무시)
+        #[arg(
+            long,
+            value_parser = parse_granularity,
+            help = "Bar interval of a new symbol: second, minute (default) or hour"
+        )]
+        granularity: Option<Granularity>,
+        /// 이미 임포트한 내용의 파일도 다시 임포트
+        #[arg(long, help = "Import files whose contents were already imported")]
+        force: bool,
+        #[arg(required = true, value_name = "FILE", help = "CSV or .parquet files")]
+        files: Vec<PathBuf>,
+    },
+    /// 범위 쿼리를 stdout으로 출력
+    #[command(about = "Print the bars in a range to stdout")]
+    Query {
+        #[arg(long)]
+        symbol: String,
+        #[arg(long, value_name = "DATE", help = "First bar to print")]
+        start: String,
+        #[arg(long, value_name = "DATE", help = "Stop before this bar")]
+        end: String,
+        #[arg(
+            long,
+            value_parser = parse_query_format,
+            default_value = "csv",
+            help = "Output format: csv or ndjson"
+        )]
+        format: HistoryFormat,
+    },
+    /// `FxStore::stats()`를 JSON으로 출력
+    #[command(about = "Print store statistics as JSON")]
+    Stats,
+    /// `FxStore::scrub()` 결과를 JSON으로 출력 (손상된 블록이 남으면 실패)
+    #[command(about = "Check every stored block and print the result as JSON")]
+    Scrub {
+        /// 손상된 블록 삭제
+        #[arg(long, help = "Delete corrupt blocks")]
+        repair: bool,
+    },
+}
+
+/// `fx-store` 인자 (`--data-dir`는 서브커맨드 앞뒤 어디든)
+#[derive(Clone, Debug, PartialEq, Eq, Parser)]
+#[command(name = "fx-store", version, about = "FX time series store", after_help = ENV_HELP)]
+pub struct Cli {
+    #[arg(
+        long,
+        global = true,
+        value_name = "DIR",
+        env = "FX_STORE_DATA_DIR",
+        default_value = DEFAULT_DATA_DIR,
+        help = "Store directory"
+    )]
+    pub data_dir: PathBuf,
+    #[command(subcommand)]
+    pub command: Command,
+}
+
+fn parse_granularity(value: &str) -> Result<Granularity, String> {
+    match value {
+        "second" => Ok(Granularity::Second),
+        "minute" => Ok(Granularity::Minute),
+        "hour" => Ok(Granularity::Hour),
+        other => Err(format!("unknown granularity: {}", other)),
+    }
+}
+
+/// query는 텍스트 형식만 (Parquet은 HTTP `format=parquet`이나 `export_parquet`)
+fn parse_query_format(value: &str) -> Result<HistoryFormat, String> {
+    match value {
+        "csv" => Ok(HistoryFormat::Csv),
+        "ndjson" => Ok(HistoryFormat::Ndjson),
+        other => Err(format!("unknown query format: {}", other)),
+    }
+}
+
+/// SIGINT(Ctrl-C) 또는 SIGTERM(컨테이너 종료)에 완료
+async fn shutdown_signal() {
+    #[cfg(unix)]
+    {
+        use tokio::signal::unix::{SignalKind, signal};
+        match signal(SignalKind::terminate()) {
+            Ok(mut terminate) => {
+                tokio::select! {
+                    _ = tokio::signal::ctrl_c() => {}
+                    _ = terminate.recv() => {}
+                }
+                return;
+            }
+            Err(e) => tracing::warn!(error = %e, "SIGTERM handler unavailable, using Ctrl-C only"),
+        }
+    }
+    tokio::signal::ctrl_c().await.ok();
+}
+
+/// 서브커맨드 실행 (serve만 tokio 런타임 사용)
+pub fn run(cli: Cli) -> anyhow::Result<()> {
+    let store = FxStore::open(&cli.data_dir, StoreConfig::from_env()?)
+        .with_context(|| format!("failed to open store in {}", cli.data_dir.display()))?;
+
+    match cli.command {
+        Command::Serve {
+            port,
+            bind,
+            import_dir,
+        } => {
+            let mut config = ServerConfig::from_env()?;
+            config.port = port.unwrap_or(config.port);
+            config.bind = bind.unwrap_or(config.bind);
+
+            let store = Arc::new(store);
+            // 임포트가 끝날 때까지 /ready는 503 (서버는 그동안에도 응답)
+            if let Some(dir) = import_dir {
+                store.set_ready(false);
+                let import_store = Arc::clone(&store);
+                std::thread::spawn(move || {
+                    import_dir_in_background(&import_store, &dir);
+                    import_store.set_ready(true);
+                });
+            }
+            tokio::runtime::Runtime::new()?.block_on(async {
+                let server = start_server(store, config, shutdown_signal()).await?;
+                tracing::info!(addr = %server.addr, "API server listening");
+                server.wait().await
+            })
+        }
+        Command::Import {
+            symbol,
+            format,
+            granularity,
+            force,
+            files,
+        } => {
+            if let Some(granularity) = granularity {
+                store.register_symbol(&symbol, granularity)?;
+            }
+            let options = ImportOptions {
+                force,
+                ..format.options()
+            };
+            for file in &files {
+                let path = file.to_string_lossy();
+                // .parquet 파일은 컬럼명으로 매핑을 추정해 읽음 (--format 무시)
+                #[cfg(feature = "parquet")]
+                if file.extension().is_some_and(|ext| ext == "parquet") {
+                    store
+                        .import_parquet(&path, &symbol, None)
+                        .with_context(|| format!("failed to import {}", path))?;
+                    continue;
+                }
+                let report = store
+                    .import_csv_with(&path, &symbol, &options)
+                    .with_context(|| format!("failed to import {}", path))?;
+                if report.already_imported {
+                    eprintln!("{}: already imported (use --force to import again)", path);
+                }
+            }
+            store.flush();
+            Ok(())
+        }
+        Command::Query {
+            symbol,
+            start,
+            end,
+            format,
+        } => {
+            // 1970 이전은 0으로, chrono 범위 밖은 양끝으로
+            let start = parse_datetime(&start)?
+                .timestamp_nanos_opt()
+                .map_or(0, |ts| ts.max(0) as u64);
+            let end = parse_datetime(&end)?
+                .timestamp_nanos_opt()
+                .map_or(MAX_TS, |ts| ts.max(0) as u64);
+            // HTTP `end`와 같이 --end 시각의 봉은 포함하지 않음
+            let last = TimeRange::new(start, end)
+                .last()
+                .ok_or_else(|| anyhow!("--start must be before --end"))?;
+            let records: Vec<_> = store.try_query_range(&symbol, start, last)?.collect();
+            let scale = store.price_scale(&symbol);
+            write_records(
+                &mut std::io::stdout().lock(),
+                &symbol,
+                &records,
+                scale,
+                format,
+            )?;
+            Ok(())
+        }
+        Command::Stats => {
+            println!("{}", serde_json::to_string_pretty(&store.stats())?);
+            Ok(())
+        }
+        Command::Scrub { repair } => {
+            let report = store.scrub(repair)?;
+            println!("{}", serde_json::to_string_pretty(&report)?);
+            if !repair && !report.corrupt.is_empty() {
+                bail!(
+                    "{} of {} blocks are corrupt (use --repair to delete them)",
+                    report.corrupt.len(),
+                    report.blocks_checked
+                );
+            }
+            Ok(())
+        }
+    }
+}
+
+/// 쿼리 결과를 `/history`의 CSV/NDJSON과 같은 형식으로 기록
+fn write_records(
+    out: &mut impl Write,
+    symbol: &str,
+    records: &[crate::types::OHLCV],
+    scale: u32,
+    format: HistoryFormat,
+) -> std::io::Result<()> {
+    let decimals = price_decimals(records, scale);
+    let mut out = std::io::BufWriter::new(out);
+    if format == HistoryFormat::Csv {
+        out.write_all(b"ts,open,high,low,close,volume\n")?;
+    }
+    let mut line = String::new();
+    for rec in records {
+        line.clear();
+        write_history_line(&mut line, symbol, rec, decimals, scale, format);
+        out.write_all(line.as_bytes())?;
+    }
+    out.flush()
+}
+
+/// 디렉터리의 `*.csv`를 파일 이름의 심볼로 임포트 (심볼을 알 수 없는 파일은 건너뜀)
+///
+/// 재시작마다 다시 읽지 않도록 임포트한 파일은 건너뛰고, 이미 저장된 날짜의 행도 건너뜀
+fn import_dir_in_background(store: &FxStore, dir: &Path) {
+    let entries = match std::fs::read_dir(dir) {
+        Ok(entries) => entries,
+        Err(e) => {
+            tracing::error!(dir = %dir.display(), error = %e, "failed to read import dir");
+            return;
+        }
+    };
+    let mut files: Vec<PathBuf> = entries
+        .filter_map(|entry| entry.ok().map(|entry| entry.path()))
+        .filter(|path| path.extension().is_some_and(|ext| ext == "csv"))
+        .collect();
+    files.sort();
+
+    let options = ImportOptions {
+        existing_days: ExistingDays::Skip,
+        ..ImportFormat::Histdata.options()
+    };
+    for file in files {
+        let Some(symbol) = file
+            .file_stem()
+            .and_then(|stem| symbol_from_file_name(&stem.to_string_lossy()))
+        else {
+            tracing::warn!(file = %file.display(), "skipping file without a symbol in its name");
+            continue;
+        };
+        let path = file.to_string_lossy();
+        match store.import_csv_with(&path, &symbol, &options) {
+            Ok(report) if report.days_present > 0 => {
+                tracing::info!(%path, days = report.days_present, "skipped days already stored");
+            }
+            Ok(_) => {}
+            Err(e) => tracing::error!(%path, error = %e, "import failed"),
+        }
+    }
+    tracing::info!(dir = %dir.display(), "import dir done");
+}
+
+/// `DAT_ASCII_XAUUSD_M1_2023` → `XAUUSD`, `EURUSD_2024` → `EURUSD`
+fn symbol_from_file_name(stem: &str) -> Option<String> {
+    stem.split(['_', '-', '.'])
+        .find(|token| token.len() == 6 && token.chars().all(|c| c.is_ascii_alphabetic()))
+        .map(str::to_ascii_uppercase)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn parse(args: &str) -> Result<Cli, clap::Error> {
+        Cli::try_parse_from(std::iter::once("fx-store").chain(args.split_whitespace()))
+    }
+
+    #[test]
+    fn parses_each_subcommand() {
+        let cli = parse("serve --port 9090 --data-dir /tmp/fx").unwrap();
+        assert_eq!(cli.data_dir, PathBuf::from("/tmp/fx"));
+        assert_eq!(
+            cli.command,
+            Command::Serve {
+                port: Some(9090),
+                bind: None,
+                import_dir: None
+            }
+        );
+
+        let cli = parse("import --symbol XAUUSD --format histdata a.csv b.csv").unwrap();
+        assert_eq!(cli.data_dir, PathBuf::from(DEFAULT_DATA_DIR));
+        assert_eq!(
+            cli.command,
+            Command::Import {
+                symbol: "XAUUSD".into(),
+                format: ImportFormat::Histdata,
+                granularity: None,
+                force: false,
+                files: vec!["a.csv".into(), "b.csv".into()],
+            }
+        );
+        let cli = parse("import --symbol BTCUSD --granularity second a.csv").unwrap();
+        assert!(matches!(
+            cli.command,
+            Command::Import {
+                granularity: Some(Granularity::Second),
+                ..
+            }
+        ));
+        let cli = parse("import --force --symbol XAUUSD a.csv").unwrap();
+        assert!(matches!(cli.command, Command::Import { force: true, .. }));
+
+        let cli = parse("query --symbol=XAUUSD --start 2024-01-01 --end 2024-02-01").unwrap();
+        assert_eq!(
+            cli.command,
+            Command::Query {
+                symbol: "XAUUSD".into(),
+                start: "2024-01-01".into(),
+                end: "2024-02-01".into(),
+                format: HistoryFormat::Csv,
+            }
+        );
+
+        assert_eq!(parse("stats").unwrap().command, Command::Stats);
+        let cli = parse("--data-dir /tmp/fx stats").unwrap();
+        assert_eq!(cli.data_dir, PathBuf::from("/tmp/fx"));
+        assert_eq!(
+            parse("scrub --repair").unwrap().command,
+            Command::Scrub { repair: true }
+        );
+    }
+
+    #[test]
+    fn rejects_bad_arguments() {
+        for args in [
+            "",
+            "export",
+            "serve --port http",
+            "serve --bind localhost",
+            "serve --symbol XAUUSD",
+            "import --symbol XAUUSD",
+            "import a.csv",
+            "import --symbol XAUUSD --format parquet a.csv",
+            "import --symbol XAUUSD --granularity tick a.csv",
+            "query --symbol XAUUSD --start 2024-01-01",
+            "stats extra",
+            "scrub --symbol XAUUSD",
+            "stats --data-dir",
+        ] {
+            assert!(parse(args).is_err(), "{:?} should fail", args);
+        }
+    }
+
+    #[test]
+    fn import_then_query_without_server() {
+        let dir = std::env::temp_dir().join(format!("fx_store_cli_{}", std::process::id()));
+        std::fs::remove_dir_all(&dir).ok();
+        std::fs::create_dir_all(&dir).unwrap();
+        let csv = dir.join("DAT_ASCII_EURUSD_M1_2024.csv");
+        std::fs::write(
+            &csv,
+            "20240102 000000,1.10000,1.10010,1.09990,1.10005,10\n\
+             20240102 000100,1.10005,1.10020,1.10000,1.10015,20\n",
+        )
+        .unwrap();
+
+        let data_dir = dir.join("store");
+        let cli = Cli {
+            data_dir: data_dir.clone(),
+            command: Command::Import {
+                symbol: "EURUSD".into(),
+                format: ImportFormat::Auto,
+                granularity: None,
+                force: false,
+                files: vec![csv],
+            },
+        };
+        run(cli).unwrap();
+
+        // 새 프로세스처럼 WAL에서 다시 열기
+        let store = FxStore::open(&data_dir, StoreConfig::default()).unwrap();
+        let records: Vec<_> = store
+            .try_query_range("EURUSD", 0, u64::MAX)
+            .unwrap()
+            .collect();
+        let mut out = Vec::new();
+        let scale = store.price_scale("EURUSD");
+        write_records(&mut out, "EURUSD", &records, scale, HistoryFormat::Csv).unwrap();
+        assert_eq!(
+            String::from_utf8(out).unwrap(),
+            "ts,open,high,low,close,volume\n\
+             1704153600,1.10000,1.10010,1.09990,1.10005,10\n\
+             1704153660,1.10005,1.10020,1.10000,1.10015,20\n"
+        );
+
+        std::fs::remove_dir_all(&dir).ok();
+    }
+
+    #[test]
+    fn symbol_comes_from_file_name() {
+        assert_eq!(
+            symbol_from_file_name("DAT_ASCII_XAUUSD_M1_2023").as_deref(),
+            Some("XAUUSD")
+        );
+        assert_eq!(
+            symbol_from_file_name("eurusd-2024").as_deref(),
+            Some("EURUSD")
+        );
+        assert_eq!(symbol_from_file_name("m1_bars_2024"), None);
+    }
+}
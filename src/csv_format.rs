@@ -0,0 +1,534 @@
+//! CSV 컬럼 레이아웃 기술자와 자동 감지
+//!
+//! HISTDATA(헤더 없음, `YYYYMMDD HHMMSS`), Dukascopy(`Gmt time`), OANDA(RFC 3339),
+//! MetaTrader(날짜/시간 분리 컬럼) 등을 하나의 파서로 처리
+
+use crate::error::{FxStoreError, Result};
+use crate::types::{OHLCV, Tick, local_to_ts, scale_price};
+use chrono::{DateTime, NaiveDateTime};
+use chrono_tz::Tz;
+
+/// 감지 시 시도하는 구분자 (빈도가 가장 높은 것 선택)
+const SEPARATORS: [char; 4] = [',', ';', '\t', '|'];
+
+/// 감지 시 시도하는 현지 시각 포맷 (앞에서부터)
+const NAIVE_FORMATS: [&str; 9] = [
+    "%Y%m%d %H%M%S",
+    "%Y-%m-%d %H:%M:%S%.f",
+    "%Y-%m-%dT%H:%M:%S%.f",
+    "%Y-%m-%d %H:%M",
+    "%d.%m.%Y %H:%M:%S%.f",
+    "%Y.%m.%d %H:%M:%S",
+    "%Y.%m.%d %H:%M",
+    "%Y%m%d %H:%M:%S",
+    "%Y%m%d %H:%M",
+];
+
+/// 스케일 자동 감지 시 소수 자릿수 상한
+pub const MAX_PRICE_DECIMALS: u32 = 8;
+
+/// 타임스탬프 표기
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum TsFormat {
+    /// 오프셋 없는 현지 시각 (chrono strftime 포맷)
+    Naive(String),
+    /// 오프셋 포함 (`2024-01-02T00:00:00Z`), source_tz 무시
+    Rfc3339,
+}
+
+/// CSV 한 행을 OHLCV로 읽기 위한 레이아웃
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct CsvFormat {
+    pub separator: char,
+    /// 첫 행이 헤더인지 (임포트 시 건너뜀)
+    pub has_header: bool,
+    pub ts_col: usize,
+    /// 날짜와 시간이 분리된 경우 시간 컬럼 (`ts_col` 값 뒤에 공백으로 이어 붙임)
+    pub time_col: Option<usize>,
+    pub ts_fmt: TsFormat,
+    /// 파일 자체가 명시하는 시간대 (예: Dukascopy `Gmt time`)
+    pub tz: Option<Tz>,
+    pub open_col: usize,
+    pub high_col: usize,
+    pub low_col: usize,
+    pub close_col: usize,
+    pub volume_col: Option<usize>,
+    /// ask - bid (가격 단위), 없으면 0
+    pub spread_col: Option<usize>,
+}
+
+impl Default for CsvFormat {
+    /// HISTDATA ASCII M1: `YYYYMMDD HHMMSS;Open;High;Low;Close;Volume[;Spread]`
+    fn default() -> Self {
+        Self {
+            separator: ',',
+            has_header: false,
+            ts_col: 0,
+            time_col: None,
+            ts_fmt: TsFormat::Naive(NAIVE_FORMATS[0].to_string()),
+            tz: None,
+            open_col: 1,
+            high_col: 2,
+            low_col: 3,
+            close_col: 4,
+            volume_col: Some(5),
+            spread_col: Some(6),
+        }
+    }
+}
+
+impl CsvFormat {
+    /// 첫 행(헤더 또는 데이터)과 첫 데이터 행으로 레이아웃 추정
+    ///
+    /// 컬럼명을 인식하지 못하면 HISTDATA 레이아웃으로 대체하며,
+    /// 첫 행이 그 레이아웃으로 파싱되지 않으면 헤더로 간주해 건너뜀
+    pub fn detect(first: &str, second: Option<&str>) -> Self {
+        // 동률이면 앞쪽(쉼표) 우선, 헤더가 한 컬럼뿐이면 데이터 행 기준
+        let sample = second.unwrap_or(first);
+        let separator = SEPARATORS
+            .into_iter()
+            .rev()
+            .max_by_key(|&sep| (first.matches(sep).count(), sample.matches(sep).count()))
+            .unwrap();
+
+        let fallback = Self {
+            separator,
+            ..Self::default()
+        };
+        if fallback.parse_ts(first, Tz::UTC).is_some() {
+            return fallback;
+        }
+
+        let names: Vec<String> = first.split(separator).map(normalize_name).collect();
+        let Some(format) = Self::from_header(&names, separator) else {
+            return Self {
+                has_header: true,
+                ..fallback
+            };
+        };
+
+        // 첫 데이터 행으로 타임스탬프 포맷 결정
+        let ts = second.and_then(|line| format.ts_field(line));
+        let ts_fmt = match ts {
+            Some(ts) if DateTime::parse_from_rfc3339(&ts).is_ok() => TsFormat::Rfc3339,
+            Some(ts) => NAIVE_FORMATS
+                .iter()
+                .find(|fmt| NaiveDateTime::parse_from_str(&ts, fmt).is_ok())
+                .map(|fmt| TsFormat::Naive(fmt.to_string()))
+                .unwrap_or(format.ts_fmt),
+            None => format.ts_fmt,
+        };
+        Self { ts_fmt, ..format }
+    }
+
+    /// 정규화된 컬럼명으로 인덱스 매핑 (시각/OHLC 중 하나라도 없으면 None)
+    fn from_header(names: &[String], separator: char) -> Option<Self> {
+        let find =
+            |candidates: &[&str]| names.iter().position(|n| candidates.contains(&n.as_str()));
+
+        let time = find(&["time"]);
+        let date = find(&["date", "day"]);
+        let (ts_col, time_col) = match (
+            find(&["timestamp", "datetime", "gmttime", "utctime", "localtime"]),
+            date,
+            time,
+        ) {
+            (Some(ts), _, _) => (ts, None),
+            (None, Some(date), Some(time)) => (date, Some(time)),
+            (None, None, Some(time)) => (time, None),
+            (None, Some(date), None) => (date, None),
+            (None, None, None) => return None,
+        };
+
+        let tz = names[ts_col..=time_col.unwrap_or(ts_col)]
+            .iter()
+            .any(|n| n.starts_with("gmt") || n.starts_with("utc"))
+            .then_some(Tz::UTC);
+
+        Some(Self {
+            separator,
+            has_header: true,
+            ts_col,
+            time_col,
+            tz,
+            open_col: find(&["open", "o", "bidopen"])?,
+            high_col: find(&["high", "h", "bidhigh"])?,
+            low_col: find(&["low", "l", "bidlow"])?,
+            close_col: find(&["close", "c", "bidclose"])?,
+            volume_col: find(&["volume", "vol", "v", "tickvol", "tickvolume"]),
+            spread_col: find(&["spread"]),
+            ts_fmt: Self::default().ts_fmt,
+        })
+    }
+
+    /// 한 행 파싱 (source_tz는 `TsFormat::Naive`이고 `tz`가 없을 때 적용, 가격은 `scale` 배)
+    pub(crate) fn parse_line(
+        &self,
+        line: &str,
+        symbol_id: u16,
+        source_tz: Tz,
+        scale: u32,
+    ) -> Result<OHLCV> {
+        let parts: Vec<&str> = line.split(self.separator).map(str::trim).collect();
+        let col = |idx: usize| {
+            parts
+                .get(idx)
+                .copied()
+                .ok_or_else(|| FxStoreError::parse("Invalid CSV format"))
+        };
+
+        let ts = self
+            .parse_ts(line, source_tz)
+            .ok_or_else(|| FxStoreError::parse("Invalid datetime"))?;
+        let open = parse_price(col(self.open_col)?, scale)?;
+        let high = parse_price(col(self.high_col)?, scale)?;
+        let low = parse_price(col(self.low_col)?, scale)?;
+        let close = parse_price(col(self.close_col)?, scale)?;
+        // 거래량은 정수 또는 실수(틱 볼륨 합계)로 기록됨
+        let volume = self
+            .volume_col
+            .and_then(|idx| parts.get(idx))
+            .and_then(|v| {
+                v.parse::<u32>()
+                    .ok()
+                    .or_else(|| v.parse::<f64>().ok().map(|v| v as u32))
+            })
+            .unwrap_or(0);
+        let spread = match self.spread_col.and_then(|idx| parts.get(idx)) {
+            Some(v) if !v.is_empty() => parse_price(v, scale)?.min(u32::from(u16::MAX)) as u16,
+            _ => 0,
+        };
+
+        Ok(OHLCV {
+            ts,
+            open,
+            high,
+            low,
+            close,
+            volume,
+            symbol_id,
+            spread,
+            ..Default::default()
+        })
+    }
+
+    /// 표본 행의 OHLC 소수 자릿수로 가격 스케일 추정
+    ///
+    /// `10^최대 자릿수` (`MAX_PRICE_DECIMALS`까지), 표본의 최고가가 u32에 들어가도록 줄임.
+    /// 가격을 하나도 읽지 못하면 None
+    pub fn detect_scale<'a>(&self, lines: impl IntoIterator<Item = &'a str>) -> Option<u32> {
+        let mut decimals = 0;
+        let mut max_int = 0u64;
+        let mut seen = false;
+        for line in lines {
+            let parts: Vec<&str> = line.split(self.separator).map(str::trim).collect();
+            for idx in [self.open_col, self.high_col, self.low_col, self.close_col] {
+                let Some(field) = parts.get(idx).filter(|f| f.parse::<f64>().is_ok()) else {
+                    continue;
+                };
+                let (int, frac) = field.split_once('.').unwrap_or((field, ""));
+                decimals = decimals.max(frac.len() as u32);
+                max_int = max_int.max(int.parse().unwrap_or(0));
+                seen = true;
+            }
+        }
+        if !seen {
+            return None;
+        }
+
+        let mut decimals = decimals.min(MAX_PRICE_DECIMALS);
+        while decimals > 0 && (max_int + 1) * 10u64.pow(decimals) > u64::from(u32::MAX) {
+            decimals -= 1;
+        }
+        Some(10u32.pow(decimals))
+    }
+
+    /// 같은 날짜 행인지 판단할 키 (타임스탬프의 날짜 부분)
+    pub fn day_key<'a>(&self, line: &'a str) -> Option<&'a str> {
+        let field = line.split(self.separator).nth(self.ts_col)?.trim();
+        if self.time_col.is_some() {
+            return Some(field);
+        }
+        Some(field.split([' ', 'T']).next().unwrap_or(field))
+    }
+
+    fn ts_field(&self, line: &str) -> Option<String> {
+        let mut parts = line.split(self.separator).map(str::trim);
+        let date = parts.clone().nth(self.ts_col)?;
+        match self.time_col {
+            Some(idx) => Some(format!("{} {}", date, parts.nth(idx)?)),
+            None => Some(date.to_string()),
+        }
+    }
+
+    fn parse_ts(&self, line: &str, source_tz: Tz) -> Option<u64> {
+        let field = self.ts_field(line)?;
+        match &self.ts_fmt {
+            TsFormat::Rfc3339 => {
+                let nanos = DateTime::parse_from_rfc3339(&field)
+                    .ok()?
+                    .timestamp_nanos_opt()?;
+                u64::try_from(nanos).ok()
+            }
+            TsFormat::Naive(fmt) => {
+                let local = NaiveDateTime::parse_from_str(&field, fmt).ok()?;
+                local_to_ts(&local, self.tz.unwrap_or(source_tz))
+            }
+        }
+    }
+}
+
+/// `<DATE>`, `Gmt time`, `tick_volume` → `date`, `gmttime`, `tickvolume`
+pub(crate) fn normalize_name(name: &str) -> String {
+    name.chars()
+        .filter(|c| c.is_ascii_alphanumeric())
+        .map(|c| c.to_ascii_lowercase())
+        .collect()
+}
+
+/// 십진 문자열을 `scale`배 정수로 (부동소수 오차 없음, 넘치는 자릿수는 반올림)
+///
+/// 지수 표기 등 일반 소수가 아닌 값은 실수로 파싱, u32를 넘거나 음수면 `PriceOverflow`
+fn parse_price(field: &str, scale: u32) -> Result<u32> {
+    let (int, frac) = field.split_once('.').unwrap_or((field, ""));
+    let is_digits = |s: &str| s.bytes().all(|b| b.is_ascii_digit());
+    if (int.is_empty() && frac.is_empty()) || !is_digits(int) || !is_digits(frac) {
+        let price: f64 = field.parse()?;
+        return scale_price(price, scale).map_err(|_| FxStoreError::PriceOverflow {
+            line: 0,
+            price: field.to_string(),
+        });
+    }
+
+    let digits = scale.ilog10() as usize;
+    let mut frac = frac.bytes().chain(std::iter::repeat(b'0'));
+    let kept = frac
+        .by_ref()
+        .take(digits)
+        .fold(0u64, |acc, b| acc * 10 + u64::from(b - b'0'));
+    let round_up = frac.next().is_some_and(|b| b >= b'5');
+    let int: u64 = if int.is_empty() { 0 } else { int.parse()? };
+    int.checked_mul(u64::from(scale))
+        .and_then(|v| u32::try_from(v + kept + u64::from(round_up)).ok())
+        .ok_or_else(|| FxStoreError::PriceOverflow {
+            line: 0,
+            price: field.to_string(),
+        })
+}
+
+/// HISTDATA 틱 한 행 (`YYYYMMDD HHMMSSmmm,bid,ask,volume`, source_tz 현지 시각)
+pub(crate) fn parse_histdata_tick(
+    line: &str,
+    symbol_id: u16,
+    source_tz: Tz,
+    scale: u32,
+) -> Result<Tick> {
+    let mut parts = line.split(',').map(str::trim);
+    let mut next = |name: &str| {
+        parts
+            .next()
+            .ok_or_else(|| FxStoreError::parse(format!("missing {} column", name)))
+    };
+
+    let local = NaiveDateTime::parse_from_str(next("timestamp")?, "%Y%m%d %H%M%S%3f")?;
+    let ts = local_to_ts(&local, source_tz)
+        .ok_or_else(|| FxStoreError::parse("timestamp out of range"))?;
+    let bid = parse_price(next("bid")?, scale)?;
+    let ask = parse_price(next("ask")?, scale)?;
+    let volume: u32 = match parts.next() {
+        Some(v) if !v.is_empty() => v.parse()?,
+        _ => 0,
+    };
+    Ok(Tick {
+        ts,
+        bid,
+        ask,
+        volume,
+        symbol_id,
+        ..Default::default()
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::types::PRICE_SCALE;
+
+    fn utc() -> Tz {
+        Tz::UTC
+    }
+
+    fn parse_all(lines: &[&str]) -> Vec<OHLCV> {
+        let format = CsvFormat::detect(lines[0], lines.get(1).copied());
+        let data = if format.has_header {
+            &lines[1..]
+        } else {
+            lines
+        };
+        data.iter()
+            .map(|line| format.parse_line(line, 7, utc(), PRICE_SCALE).unwrap())
+            .collect()
+    }
+
+    fn fields(rec: &OHLCV) -> (u64, u32, u32, u32, u32, u32, u16) {
+        (
+            rec.ts,
+            rec.open,
+            rec.high,
+            rec.low,
+            rec.close,
+            rec.volume,
+            rec.symbol_id,
+        )
+    }
+
+    #[test]
+    fn headerless_histdata_falls_back_to_default_layout() {
+        let format = CsvFormat::detect("20240102 000000;1.10000;1.10010;1.09990;1.10005;0", None);
+        assert_eq!(format.separator, ';');
+        assert!(!format.has_header);
+        assert_eq!(format.ts_fmt, CsvFormat::default().ts_fmt);
+    }
+
+    #[test]
+    fn unknown_header_is_skipped_with_default_layout() {
+        let format = CsvFormat::detect("header", Some("20240102 000000,1.1,1.1,1.1,1.1,0"));
+        assert!(format.has_header);
+        assert_eq!(format.open_col, 1);
+    }
+
+    #[test]
+    fn distinct_layouts_parse_to_identical_records() {
+        let histdata = parse_all(&[
+            "20240102 000000,1.10000,1.10010,1.09990,1.10005,12",
+            "20240102 000100,1.10005,1.10020,1.10000,1.10015,8",
+        ]);
+        let dukascopy = parse_all(&[
+            "Gmt time,Open,High,Low,Close,Volume",
+            "02.01.2024 00:00:00.000,1.10000,1.10010,1.09990,1.10005,12.0",
+            "02.01.2024 00:01:00.000,1.10005,1.10020,1.10000,1.10015,8.0",
+        ]);
+        let oanda = parse_all(&[
+            "time;volume;open;high;low;close",
+            "2024-01-02T00:00:00.000000000Z;12;1.10000;1.10010;1.09990;1.10005",
+            "2024-01-02T00:01:00.000000000Z;8;1.10005;1.10020;1.10000;1.10015",
+        ]);
+        let metatrader = parse_all(&[
+            "<DATE>\t<TIME>\t<OPEN>\t<HIGH>\t<LOW>\t<CLOSE>\t<TICKVOL>",
+            "2024.01.02\t00:00\t1.10000\t1.10010\t1.09990\t1.10005\t12",
+            "2024.01.02\t00:01\t1.10005\t1.10020\t1.10000\t1.10015\t8",
+        ]);
+
+        let expected: Vec<_> = histdata.iter().map(fields).collect();
+        assert_eq!(expected[0].0, 1_704_153_600_000_000_000);
+        for layout in [&dukascopy, &oanda, &metatrader] {
+            assert_eq!(layout.iter().map(fields).collect::<Vec<_>>(), expected);
+        }
+    }
+
+    #[test]
+    fn gmt_header_overrides_source_tz() {
+        let lines = [
+            "Gmt time,Open,High,Low,Close,Volume",
+            "02.01.2024 00:00:00.000,1.10000,1.10010,1.09990,1.10005,12",
+        ];
+        let format = CsvFormat::detect(lines[0], Some(lines[1]));
+        let est = Tz::EST;
+        let rec = format.parse_line(lines[1], 0, est, PRICE_SCALE).unwrap();
+        assert_eq!({ rec.ts }, 1_704_153_600_000_000_000);
+    }
+
+    #[test]
+    fn day_key_uses_date_part_of_timestamp() {
+        let iso = CsvFormat::detect(
+            "time,open,high,low,close",
+            Some("2024-01-02T00:00:00Z,1,1,1,1"),
+        );
+        assert_eq!(
+            iso.day_key("2024-01-02T23:59:00Z,1,1,1,1"),
+            Some("2024-01-02")
+        );
+
+        let histdata = CsvFormat::default();
+        assert_eq!(
+            histdata.day_key("20240102 235900,1,1,1,1,0"),
+            Some("20240102")
+        );
+    }
+
+    #[test]
+    fn parses_histdata_ticks() {
+        let est = Tz::EST;
+        let tick = parse_histdata_tick("20240101 170000123,1.10412,1.10425,0", 2, est, PRICE_SCALE)
+            .unwrap();
+        // 2024-01-01 17:00:00.123 EST = 2024-01-01 22:00:00.123 UTC
+        assert_eq!({ tick.ts }, 1_704_146_400_123_000_000);
+        assert_eq!(
+            ({ tick.bid }, { tick.ask }, { tick.symbol_id }),
+            (110_412, 110_425, 2)
+        );
+        assert_eq!(tick.spread(), 13);
+
+        assert!(parse_histdata_tick("20240101 170000,1.1,1.2,0", 0, est, PRICE_SCALE).is_err());
+        assert!(parse_histdata_tick("20240101 170000123,1.1", 0, est, PRICE_SCALE).is_err());
+    }
+
+    #[test]
+    fn detect_scale_follows_price_decimals() {
+        let format = CsvFormat::default();
+        let scale = |lines: &[&str]| format.detect_scale(lines.iter().copied());
+
+        assert_eq!(
+            scale(&["20240102 000000,150.123,150.2,150.1,150.15,0"]),
+            Some(1_000)
+        );
+        assert_eq!(
+            scale(&["20240102 000000,1.10000,1.1001,1.0999,1.1,0"]),
+            Some(PRICE_SCALE)
+        );
+        // 8자리 상한, u32에 들어가도록 축소
+        assert_eq!(
+            scale(&["20240102 000000,0.0000000123,0.1,0.0,0.1,0"]),
+            Some(100_000_000)
+        );
+        assert_eq!(
+            scale(&["20240102 000000,65000.12345,65001.5,64999.0,65000.0,0"]),
+            Some(10_000)
+        );
+        assert_eq!(scale(&["header only"]), None);
+    }
+
+    #[test]
+    fn prices_beyond_u32_are_price_overflow() {
+        let format = CsvFormat::default();
+        let parse = |line: &str| format.parse_line(line, 7, utc(), PRICE_SCALE);
+
+        // 100000.0 * 10^5 > u32::MAX
+        let err = parse("20240102 000000,100000.0,1.1,1.1,1.1,0").unwrap_err();
+        assert!(matches!(
+            err.at_line(3),
+            FxStoreError::PriceOverflow { line: 3, ref price } if price == "100000.0"
+        ));
+        // 실수 경로 (지수 표기, 음수)도 포화 대신 오류
+        for price in ["1e9", "-1.1"] {
+            let line = format!("20240102 000000,{},1.1,1.1,1.1,0", price);
+            assert!(matches!(
+                parse(&line),
+                Err(FxStoreError::PriceOverflow { .. })
+            ));
+        }
+        assert!(parse("20240102 000000,1.1e0,1.1,1.1,1.1,0").is_ok());
+    }
+
+    #[test]
+    fn extra_price_digits_round_to_the_scale() {
+        assert_eq!(parse_price("1.123456", PRICE_SCALE).unwrap(), 112_346);
+        assert_eq!(parse_price("1.123454", PRICE_SCALE).unwrap(), 112_345);
+        assert_eq!(parse_price("1.999995", PRICE_SCALE).unwrap(), 200_000);
+        assert_eq!(parse_price("1.15e0", PRICE_SCALE).unwrap(), 115_000);
+        assert!(matches!(
+            parse_price("42949.672955", PRICE_SCALE),
+            Err(FxStoreError::PriceOverflow { .. })
+        ));
+    }
+}
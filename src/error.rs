@@ -0,0 +1,199 @@
+/// 공개 API 오류
+#[derive(Debug, thiserror::Error)]
+pub enum FxStoreError {
+    /// 파일/WAL 입출력 실패
+    #[error("I/O error: {0}")]
+    Io(#[from] std::io::Error),
+    /// CSV 행 파싱 실패 (line은 1부터, 행 번호를 모르면 0)
+    #[error("parse error{}: {reason}", line_suffix(*.line))]
+    Parse { line: usize, reason: String },
+    /// 가격이 `scale`배 정수(u32) 범위를 벗어남 (line은 `Parse`와 같음)
+    #[error("price {price} out of range{}", line_suffix(*.line))]
+    PriceOverflow { line: usize, price: String },
+    /// 등록되지 않은 심볼
+    #[error("unknown symbol: {0}")]
+    UnknownSymbol(String),
+    /// 이미 심볼 이름이나 별칭으로 쓰이는 이름
+    #[error("symbol already exists: {0}")]
+    SymbolExists(String),
+    /// 블록 압축/해제 실패
+    #[error("compression error: {0}")]
+    Compression(String),
+    /// 저장된 데이터(블록, WAL 엔트리)를 해석할 수 없음
+    #[error("corrupt data: {0}")]
+    Corruption(String),
+    /// 일일 블록의 체크섬이 맞지 않거나 해제할 수 없음 (date는 YYYYMMDD)
+    #[error("corrupt block {date} of symbol {symbol_id}: {reason}")]
+    BlockCorrupt {
+        symbol_id: u16,
+        date: u32,
+        reason: String,
+    },
+    /// 잘못된 쿼리 범위
+    #[error("invalid query range: {0}")]
+    Query(#[from] QueryError),
+    /// 가져온 데이터의 슬롯 간격이나 가격 스케일이 로컬 심볼과 다름
+    #[error("incompatible data: {0}")]
+    Incompatible(String),
+    /// 압축 대기열이 가득 참 (`OverflowPolicy::Error`)
+    #[error("compression queue is full")]
+    QueueFull,
+    /// 저장할 수 없는 시각 (0이거나 `types::MAX_TS`보다 늦음)
+    #[error("timestamp {0}ns is outside 1970-01-01..2262-04-11")]
+    TimestampOutOfRange(u64),
+    /// Arrow 배치나 Parquet 파일을 만들거나 읽지 못함
+    #[error("parquet error: {0}")]
+    Parquet(String),
+    /// 블록 파일 없이 연 스토어라 블록을 영속화할 수 없음 (`checkpoint`)
+    #[error("store has no block file to persist blocks to")]
+    NotPersistent,
+    /// 심볼 id(u16)를 모두 써서 새 심볼을 등록할 수 없음
+    #[error("cannot register {0}: all 65536 symbol ids are in use")]
+    SymbolLimit(String),
+}
+
+/// 행 번호를 알 때만 붙이는 메시지 꼬리 (" at line N")
+fn line_suffix(line: usize) -> String {
+    match line {
+        0 => String::new(),
+        line => format!(" at line {}", line),
+    }
+}
+
+pub type Result<T> = std::result::Result<T, FxStoreError>;
+
+/// 쿼리 범위 검증 오류 (시각은 epoch nanos)
+#[derive(Debug, Clone, Copy, PartialEq, Eq, thiserror::Error)]
+pub enum QueryError {
+    /// start가 end보다 늦음 (반열린 `TimeRange`는 같아도 빈 구간이라 오류)
+    #[error("start {start} {} end {end}", if .start == .end { "equals" } else { "is after" })]
+    InvertedRange { start: u64, end: u64 },
+    /// end - start가 허용 길이 `max`를 넘음
+    #[error("range of {span}ns exceeds the {max}ns limit")]
+    RangeTooLarge { span: u64, max: u64 },
+}
+
+impl QueryError {
+    /// 양끝 포함 범위 검사: start <= end, 그리고 `max_span`이 있으면 그 길이 이하인지
+    /// (start == end는 한 시각만 묻는 유효한 범위, 반열린 범위는 `TimeRange::check`)
+    pub fn check(start: u64, end: u64, max_span: Option<u64>) -> std::result::Result<(), Self> {
+        if start > end {
+            return Err(Self::InvertedRange { start, end });
+        }
+        match max_span {
+            Some(max) if end - start > max => Err(Self::RangeTooLarge {
+                span: end - start,
+                max,
+            }),
+            _ => Ok(()),
+        }
+    }
+}
+
+impl FxStoreError {
+    /// 행 번호 없는 파싱 오류 (`at_line`으로 나중에 채움)
+    pub fn parse(reason: impl Into<String>) -> Self {
+        Self::Parse {
+            line: 0,
+            reason: reason.into(),
+        }
+    }
+
+    /// 파싱 오류에 행 번호 기록 (다른 변형은 그대로)
+    pub fn at_line(self, line: usize) -> Self {
+        match self {
+            Self::Parse { reason, .. } => Self::Parse { line, reason },
+            Self::PriceOverflow { price, .. } => Self::PriceOverflow { line, price },
+            other => other,
+        }
+    }
+}
+
+impl From<bincode::Error> for FxStoreError {
+    fn from(e: bincode::Error) -> Self {
+        match *e {
+            bincode::ErrorKind::Io(e) => Self::Io(e),
+            other => Self::Corruption(other.to_string()),
+        }
+    }
+}
+
+#[cfg(feature = "parquet")]
+impl From<parquet::errors::ParquetError> for FxStoreError {
+    fn from(e: parquet::errors::ParquetError) -> Self {
+        match e {
+            parquet::errors::ParquetError::External(e) => match e.downcast::<std::io::Error>() {
+                Ok(e) => Self::Io(*e),
+                Err(e) => Self::Parquet(e.to_string()),
+            },
+            other => Self::Parquet(other.to_string()),
+        }
+    }
+}
+
+#[cfg(feature = "parquet")]
+impl From<arrow_schema::ArrowError> for FxStoreError {
+    fn from(e: arrow_schema::ArrowError) -> Self {
+        match e {
+            arrow_schema::ArrowError::IoError(_, e) => Self::Io(e),
+            other => Self::Parquet(other.to_string()),
+        }
+    }
+}
+
+impl From<std::num::ParseFloatError> for FxStoreError {
+    fn from(e: std::num::ParseFloatError) -> Self {
+        Self::parse(e.to_string())
+    }
+}
+
+impl From<std::num::ParseIntError> for FxStoreError {
+    fn from(e: std::num::ParseIntError) -> Self {
+        Self::parse(e.to_string())
+    }
+}
+
+impl From<chrono::ParseError> for FxStoreError {
+    fn from(e: chrono::ParseError) -> Self {
+        Self::parse(e.to_string())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn messages_mention_the_line_only_when_known() {
+        let parse = FxStoreError::parse("bad field");
+        assert_eq!(parse.to_string(), "parse error: bad field");
+        assert_eq!(
+            parse.at_line(7).to_string(),
+            "parse error at line 7: bad field"
+        );
+
+        let overflow = FxStoreError::PriceOverflow {
+            line: 0,
+            price: "1e12".to_string(),
+        };
+        assert_eq!(overflow.to_string(), "price 1e12 out of range");
+        assert_eq!(
+            overflow.at_line(3).to_string(),
+            "price 1e12 out of range at line 3"
+        );
+    }
+
+    #[test]
+    fn range_errors_say_how_the_range_is_wrong() {
+        assert_eq!(QueryError::check(5, 5, None), Ok(()));
+        let inverted = QueryError::check(6, 5, None).unwrap_err();
+        assert_eq!(inverted.to_string(), "start 6 is after end 5");
+
+        let empty = QueryError::InvertedRange { start: 5, end: 5 };
+        assert_eq!(empty.to_string(), "start 5 equals end 5");
+        assert_eq!(
+            FxStoreError::from(empty).to_string(),
+            "invalid query range: start 5 equals end 5"
+        );
+    }
+}
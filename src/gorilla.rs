@@ -0,0 +1,298 @@
+//! Gorilla 스타일 블록 인코딩 (delta-of-delta 타임스탬프 + XOR 컬럼)
+//!
+//! 레이아웃: 플래그 바이트(0 = 원본, 1 = zstd) + 비트스트림
+//! (슬롯 점유 비트맵, ts, open, high, low, close, volume, volume_hi, spread, symbol_id)
+
+use crate::types::OHLCV;
+
+const RAW: u8 = 0;
+const ZSTD: u8 = 1;
+
+/// 슬롯당 최대 바이트 (ts 68 + 8컬럼 × 44 + 점유 1비트) 기준 상한
+const MAX_BYTES_PER_SLOT: usize = 64;
+
+/// 슬롯 배열 인코딩 (가벼운 zstd가 더 작을 때만 적용)
+pub fn encode(block: &[OHLCV]) -> Vec<u8> {
+    let mut w = BitWriter::default();
+
+    for rec in block.iter() {
+        w.write(u64::from(rec.ts != 0), 1);
+    }
+    let present: Vec<&OHLCV> = block.iter().filter(|rec| rec.ts != 0).collect();
+
+    write_ts(&mut w, present.iter().map(|rec| rec.ts));
+    let columns: [fn(&OHLCV) -> u32; 8] = [
+        |r| r.open,
+        |r| r.high,
+        |r| r.low,
+        |r| r.close,
+        |r| r.volume,
+        |r| r.volume_hi,
+        |r| u32::from(r.spread),
+        |r| u32::from(r.symbol_id),
+    ];
+    for column in columns {
+        write_xor(&mut w, present.iter().map(|rec| column(rec)));
+    }
+
+    let raw = w.buf;
+    match zstd::bulk::compress(&raw, 1) {
+        Ok(packed) if packed.len() < raw.len() => [&[ZSTD][..], &packed].concat(),
+        _ => [&[RAW][..], &raw].concat(),
+    }
+}
+
+/// `encode` 역변환 (slots는 인코딩한 슬롯 수, 손상된 입력이면 None)
+pub fn decode(data: &[u8], slots: usize) -> Option<Vec<OHLCV>> {
+    let (&flag, body) = data.split_first()?;
+    let unpacked;
+    let bits = match flag {
+        RAW => body,
+        ZSTD => {
+            unpacked = zstd::bulk::decompress(body, slots * MAX_BYTES_PER_SLOT).ok()?;
+            &unpacked[..]
+        }
+        _ => return None,
+    };
+    let mut r = BitReader { data: bits, pos: 0 };
+
+    let mut present = Vec::new();
+    for slot in 0..slots {
+        if r.read(1)? == 1 {
+            present.push(slot);
+        }
+    }
+
+    let mut block = vec![OHLCV::default(); slots];
+    let ts = read_ts(&mut r, present.len())?;
+    for (&slot, ts) in present.iter().zip(ts) {
+        block[slot].ts = ts;
+    }
+
+    let columns: [fn(&mut OHLCV, u32); 8] = [
+        |r, v| r.open = v,
+        |r, v| r.high = v,
+        |r, v| r.low = v,
+        |r, v| r.close = v,
+        |r, v| r.volume = v,
+        |r, v| r.volume_hi = v,
+        |r, v| r.spread = v as u16,
+        |r, v| r.symbol_id = v as u16,
+    ];
+    for column in columns {
+        for (&slot, value) in present.iter().zip(read_xor(&mut r, present.len())?) {
+            column(&mut block[slot], value);
+        }
+    }
+    Some(block)
+}
+
+/// delta-of-delta 구간: (접두 비트, 접두 길이, 값 비트)
+const DOD_BUCKETS: [(u64, u32, u32); 3] = [(0b10, 2, 7), (0b110, 3, 9), (0b1110, 4, 12)];
+
+fn write_ts(w: &mut BitWriter, values: impl Iterator<Item = u64>) {
+    let (mut prev, mut prev_delta) = (0u64, 0i64);
+    for ts in values {
+        let delta = ts.wrapping_sub(prev) as i64;
+        let dod = delta.wrapping_sub(prev_delta);
+        (prev, prev_delta) = (ts, delta);
+
+        if dod == 0 {
+            w.write(0, 1);
+            continue;
+        }
+        match DOD_BUCKETS
+            .iter()
+            .find(|&&(_, _, bits)| dod >= -(1 << (bits - 1)) && dod < 1 << (bits - 1))
+        {
+            Some(&(prefix, prefix_len, bits)) => {
+                w.write(prefix, prefix_len);
+                w.write(dod as u64, bits);
+            }
+            None => {
+                w.write(0b1111, 4);
+                w.write(dod as u64, 64);
+            }
+        }
+    }
+}
+
+fn read_ts(r: &mut BitReader, count: usize) -> Option<Vec<u64>> {
+    let (mut prev, mut prev_delta) = (0u64, 0i64);
+    let mut out = Vec::with_capacity(count);
+    for _ in 0..count {
+        let mut prefix_len = 0;
+        while prefix_len < 4 && r.read(1)? == 1 {
+            prefix_len += 1;
+        }
+        let dod = match prefix_len {
+            0 => 0,
+            4 => r.read(64)? as i64,
+            n => {
+                let bits = DOD_BUCKETS[n - 1].2;
+                let raw = r.read(bits)?;
+                ((raw << (64 - bits)) as i64) >> (64 - bits)
+            }
+        };
+        let delta = prev_delta.wrapping_add(dod);
+        let ts = prev.wrapping_add(delta as u64);
+        (prev, prev_delta) = (ts, delta);
+        out.push(ts);
+    }
+    Some(out)
+}
+
+/// XOR 인코딩: 0 → '0', 이전 유효 비트 창 재사용 → '10', 새 창 → '11' + 선행 0 개수(5) + 길이-1(5)
+fn write_xor(w: &mut BitWriter, values: impl Iterator<Item = u32>) {
+    let mut prev = 0u32;
+    let mut window: Option<(u32, u32)> = None;
+    for value in values {
+        let xor = value ^ prev;
+        prev = value;
+        if xor == 0 {
+            w.write(0, 1);
+            continue;
+        }
+
+        let (lead, trail) = (xor.leading_zeros(), xor.trailing_zeros());
+        match window {
+            Some((l, t)) if lead >= l && trail >= t => {
+                w.write(0b10, 2);
+                w.write(u64::from(xor >> t), 32 - l - t);
+            }
+            _ => {
+                let len = 32 - lead - trail;
+                w.write(0b11, 2);
+                w.write(u64::from(lead), 5);
+                w.write(u64::from(len - 1), 5);
+                w.write(u64::from(xor >> trail), len);
+                window = Some((lead, trail));
+            }
+        }
+    }
+}
+
+fn read_xor(r: &mut BitReader, count: usize) -> Option<Vec<u32>> {
+    let mut prev = 0u32;
+    let mut window = (0u32, 0u32);
+    let mut out = Vec::with_capacity(count);
+    for _ in 0..count {
+        if r.read(1)? == 1 {
+            if r.read(1)? == 1 {
+                let lead = r.read(5)? as u32;
+                let len = r.read(5)? as u32 + 1;
+                window = (lead, 32u32.checked_sub(lead + len)?);
+            }
+            let (lead, trail) = window;
+            prev ^= (r.read(32 - lead - trail)? as u32) << trail;
+        }
+        out.push(prev);
+    }
+    Some(out)
+}
+
+/// MSB 우선 비트 기록기
+#[derive(Default)]
+struct BitWriter {
+    buf: Vec<u8>,
+    bit: u32, // 마지막 바이트에서 사용한 비트 수 (0이면 새 바이트 필요)
+}
+
+impl BitWriter {
+    fn write(&mut self, value: u64, count: u32) {
+        for i in (0..count).rev() {
+            if self.bit == 0 {
+                self.buf.push(0);
+            }
+            if (value >> i) & 1 == 1 {
+                *self.buf.last_mut().unwrap() |= 0x80 >> self.bit;
+            }
+            self.bit = (self.bit + 1) % 8;
+        }
+    }
+}
+
+struct BitReader<'a> {
+    data: &'a [u8],
+    pos: usize,
+}
+
+impl BitReader<'_> {
+    fn read(&mut self, count: u32) -> Option<u64> {
+        let mut value = 0u64;
+        for _ in 0..count {
+            let byte = *self.data.get(self.pos / 8)?;
+            value = (value << 1) | u64::from((byte >> (7 - self.pos % 8)) & 1);
+            self.pos += 1;
+        }
+        Some(value)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::block::BLOCK_SIZE;
+
+    fn slots(records: &[(usize, OHLCV)]) -> Vec<OHLCV> {
+        let mut block = vec![OHLCV::default(); BLOCK_SIZE];
+        for &(slot, rec) in records {
+            block[slot] = rec;
+        }
+        block
+    }
+
+    fn assert_same(a: &[OHLCV], b: &[OHLCV]) {
+        assert_eq!(a.len(), b.len());
+        for (x, y) in a.iter().zip(b.iter()) {
+            assert_eq!(
+                bincode::serialize(x).unwrap(),
+                bincode::serialize(y).unwrap()
+            );
+        }
+    }
+
+    #[test]
+    fn empty_block_round_trips() {
+        let block = slots(&[]);
+        let encoded = encode(&block);
+        assert!(encoded.len() <= BLOCK_SIZE / 8 + 2);
+        assert_same(&decode(&encoded, BLOCK_SIZE).unwrap(), &block);
+    }
+
+    #[test]
+    fn extreme_values_and_gaps_round_trip() {
+        let rec = |ts: u64, price: u32| OHLCV {
+            ts,
+            open: price,
+            high: u32::MAX,
+            low: 1,
+            close: price,
+            volume: u32::MAX,
+            symbol_id: u16::MAX,
+            spread: 7,
+            volume_hi: 0x8000_0001,
+            ..Default::default()
+        };
+        let block = slots(&[
+            (0, rec(u64::MAX - 5, 0)),
+            (1, rec(1, u32::MAX)),
+            (2, rec(61, 0x8000_0000)),
+            (700, rec(1_000_000_000_000, 1)),
+            (1439, rec(1_000_000_000_001, 110_000)),
+        ]);
+        assert_same(&decode(&encode(&block), BLOCK_SIZE).unwrap(), &block);
+    }
+
+    #[test]
+    fn rejects_truncated_input() {
+        let block = slots(&[(
+            3,
+            OHLCV::from_prices(180_000_000_000, 1.1, 1.2, 1.0, 1.1, 5, 0).unwrap(),
+        )]);
+        let encoded = encode(&block);
+        assert!(decode(&[], BLOCK_SIZE).is_none());
+        assert!(decode(&[9], BLOCK_SIZE).is_none());
+        assert!(decode(&encoded[..encoded.len() / 2], BLOCK_SIZE).is_none());
+    }
+}
@@ -0,0 +1,55 @@
+//! 1분봉 FX 시세 저장소
+//!
+//! 라이브러리로 쓸 때는 `fx_store::prelude::*`에서 시작. 기능 플래그 (`test-util` 외에는 기본으로 켜짐):
+//! - `api`: axum HTTP 서버 (`create_app`, `start_server`), `fx-store` 실행 파일과 로그 출력 (`logging`)
+//! - `simd`: `SimdFilter`의 AVX2/NEON 경로 (끄면 스칼라 루프만)
+//! - `persistence`: 블록 파일·WAL·스냅샷 (`FxStore::open`, `recover`, `export_snapshot`),
+//!   끄면 메모리 전용 스토어
+//! - `parquet`: Arrow 배치와 Parquet 내보내기·가져오기 (`to_arrow`, `export_parquet`, `import_parquet`, `format=parquet`)
+//! - `test-util`: 합성 봉 생성기와 테스트 스토어 (`testutil`)
+
+#[cfg(feature = "api")]
+pub mod access;
+#[cfg(feature = "api")]
+pub mod api;
+#[cfg(test)]
+mod bench;
+pub mod block;
+#[cfg(all(feature = "api", feature = "persistence"))]
+pub mod cli;
+pub mod csv_format;
+pub mod error;
+mod gorilla;
+#[cfg(feature = "api")]
+pub mod logging;
+#[cfg(feature = "api")]
+pub mod metrics;
+#[cfg(feature = "persistence")]
+mod mmap_format;
+#[cfg(feature = "parquet")]
+pub mod parquet_format;
+pub mod query;
+#[cfg(feature = "persistence")]
+mod snapshot;
+pub mod store;
+#[cfg(any(test, feature = "test-util"))]
+pub mod testutil;
+pub mod types;
+#[cfg(feature = "persistence")]
+pub mod wal;
+
+/// 스토어를 임베드할 때 필요한 타입 모음
+pub mod prelude {
+    pub use crate::block::{BlockCodec, CompressedBlock};
+    pub use crate::error::{FxStoreError, QueryError, Result};
+    pub use crate::query::{Alignment, SimdFilter, TechnicalIndicators, resample};
+    pub use crate::store::{FxStore, ImportOptions, ImportReport, StoreConfig};
+    pub use crate::types::{
+        Granularity, OHLCV, PRICE_SCALE, PriceField, Symbol, Tick, TimeRange, scale_price,
+    };
+
+    #[cfg(feature = "api")]
+    pub use crate::api::{ApiConfig, ServerConfig, create_app, create_app_with, start_server};
+    #[cfg(feature = "persistence")]
+    pub use crate::wal::FsyncPolicy;
+}
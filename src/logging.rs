@@ -0,0 +1,181 @@
+//! `tracing` subscriber: `RUST_LOG` filtering through `EnvFilter`, one text or JSON line per
+//! event on stderr
+//!
+//! Spans also print a line with `time.busy`/`time.idle` when they close, so a request or import
+//! span gives one structured line per operation with its timing.
+
+use crate::error::{FxStoreError, Result};
+use std::str::FromStr;
+use tracing::Subscriber;
+use tracing::level_filters::LevelFilter;
+use tracing_subscriber::EnvFilter;
+use tracing_subscriber::fmt::MakeWriter;
+use tracing_subscriber::fmt::format::FmtSpan;
+
+/// Output line format (`FX_STORE_LOG_FORMAT`)
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum LogFormat {
+    /// `2024-01-02T00:00:00.000000Z  INFO span{k=v}: target: message k=v`
+    #[default]
+    Text,
+    /// One JSON object per line, for log shippers
+    Json,
+}
+
+impl FromStr for LogFormat {
+    type Err = FxStoreError;
+
+    fn from_str(s: &str) -> Result<Self> {
+        match s.trim().to_ascii_lowercase().as_str() {
+            "text" => Ok(Self::Text),
+            "json" => Ok(Self::Json),
+            other => Err(FxStoreError::parse(format!(
+                "log format must be text or json, got {:?}",
+                other
+            ))),
+        }
+    }
+}
+
+/// Parse `RUST_LOG` directives (`warn`, `fx_store=debug,tower_http=info`); `info` when empty
+pub fn env_filter(spec: &str) -> Result<EnvFilter> {
+    EnvFilter::builder()
+        .with_default_directive(LevelFilter::INFO.into())
+        .parse(spec)
+        .map_err(|e| FxStoreError::parse(format!("invalid RUST_LOG {:?}: {}", spec, e)))
+}
+
+/// The subscriber `init` installs, writing each line to `writer`
+pub fn subscriber<W>(
+    filter: EnvFilter,
+    format: LogFormat,
+    writer: W,
+) -> Box<dyn Subscriber + Send + Sync>
+where
+    W: for<'w> MakeWriter<'w> + Send + Sync + 'static,
+{
+    let builder = tracing_subscriber::fmt()
+        .with_env_filter(filter)
+        .with_writer(writer)
+        .with_ansi(false)
+        .with_span_events(FmtSpan::CLOSE);
+    match format {
+        LogFormat::Text => Box::new(builder.finish()),
+        LogFormat::Json => Box::new(builder.json().finish()),
+    }
+}
+
+/// Install the subscriber from `RUST_LOG` and `FX_STORE_LOG_FORMAT` (keeps any already set)
+pub fn init() -> Result<()> {
+    let filter = env_filter(&std::env::var("RUST_LOG").unwrap_or_default())?;
+    let format = match std::env::var("FX_STORE_LOG_FORMAT") {
+        Ok(format) => format.parse()?,
+        Err(_) => LogFormat::default(),
+    };
+    let _ = tracing::subscriber::set_global_default(subscriber(filter, format, std::io::stderr));
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use parking_lot::Mutex;
+    use serde_json::Value;
+    use std::sync::Arc;
+
+    /// Collects written bytes; `lines` splits them once the subscriber is gone
+    #[derive(Clone, Default)]
+    struct Capture(Arc<Mutex<Vec<u8>>>);
+
+    impl std::io::Write for Capture {
+        fn write(&mut self, buf: &[u8]) -> std::io::Result<usize> {
+            self.0.lock().extend_from_slice(buf);
+            Ok(buf.len())
+        }
+
+        fn flush(&mut self) -> std::io::Result<()> {
+            Ok(())
+        }
+    }
+
+    impl Capture {
+        fn lines(&self) -> Vec<String> {
+            String::from_utf8(self.0.lock().clone())
+                .unwrap()
+                .lines()
+                .map(str::to_string)
+                .collect()
+        }
+    }
+
+    fn capture(filter: &str, format: LogFormat) -> (Box<dyn Subscriber + Send + Sync>, Capture) {
+        let sink = Capture::default();
+        let writer = sink.clone();
+        let subscriber = subscriber(env_filter(filter).unwrap(), format, move || writer.clone());
+        (subscriber, sink)
+    }
+
+    #[test]
+    fn bad_settings_are_rejected() {
+        assert!(env_filter("warn,fx_store=debug,fx_store::block=trace").is_ok());
+        assert!(env_filter("").is_ok());
+        assert!(env_filter("fx_store=loud").is_err());
+        assert!("xml".parse::<LogFormat>().is_err());
+        assert_eq!(" JSON ".parse::<LogFormat>().unwrap(), LogFormat::Json);
+    }
+
+    #[test]
+    fn spans_log_their_fields_and_timing_on_close() {
+        let (subscriber, sink) = capture("info,fx_store=debug", LogFormat::Json);
+        tracing::subscriber::with_default(subscriber, || {
+            let span = tracing::debug_span!(
+                "request",
+                route = "/history/:symbol",
+                rows = tracing::field::Empty
+            );
+            let _entered = span.enter();
+            tracing::info!(day = 3, "batch done");
+            tracing::trace!("filtered out");
+            span.record("rows", 42);
+        });
+
+        let lines = sink.lines();
+        assert_eq!(lines.len(), 2, "{:?}", lines);
+        let event: Value = serde_json::from_str(&lines[0]).unwrap();
+        assert_eq!(event["fields"]["message"], "batch done");
+        assert_eq!(event["fields"]["day"], 3);
+        assert_eq!(event["span"]["name"], "request");
+        assert_eq!(event["span"]["route"], "/history/:symbol");
+
+        let close: Value = serde_json::from_str(&lines[1]).unwrap();
+        assert_eq!(close["fields"]["message"], "close");
+        assert_eq!(close["level"], "DEBUG");
+        assert_eq!(close["span"]["rows"], 42);
+        assert!(close["fields"]["time.busy"].is_string());
+    }
+
+    #[test]
+    fn text_lines_nest_span_fields_before_the_message() {
+        let (subscriber, sink) = capture("debug", LogFormat::Text);
+        tracing::subscriber::with_default(subscriber, || {
+            let _span = tracing::info_span!("import_csv", symbol = "EURUSD").entered();
+            tracing::warn!(skipped = 2, "rows skipped");
+        });
+
+        let lines = sink.lines();
+        assert!(
+            lines[0].ends_with(
+                " WARN import_csv{symbol=\"EURUSD\"}: fx_store::logging::tests: rows skipped skipped=2"
+            ),
+            "{}",
+            lines[0]
+        );
+        assert!(
+            lines[1].contains(
+                "import_csv{symbol=\"EURUSD\"}: fx_store::logging::tests: close time.busy="
+            ),
+            "{}",
+            lines[1]
+        );
+    }
+}
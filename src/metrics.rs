@@ -0,0 +1,318 @@
+//! Prometheus metrics for `GET /metrics`, exposed with the `prometheus` crate's text encoder
+
+use crate::store::StatsSnapshot;
+use prometheus::core::Collector;
+use prometheus::{
+    Encoder, Gauge, Histogram, HistogramOpts, HistogramVec, IntCounter, IntCounterVec, IntGauge,
+    IntGaugeVec, Opts, Registry, TextEncoder,
+};
+use std::time::Instant;
+
+/// Upper bounds (seconds) of the latency buckets; `+Inf` is implicit
+const LATENCY_BUCKETS: [f64; 12] = [
+    0.0005, 0.001, 0.0025, 0.005, 0.01, 0.025, 0.05, 0.1, 0.25, 0.5, 1.0, 5.0,
+];
+
+/// Metrics recorded by the HTTP layer (store-side counters come from `FxStore::stats`)
+pub struct ApiMetrics {
+    registry: Registry,
+    /// Latency per query handler
+    queries: HistogramVec,
+    /// Latency per matched route pattern (`/history/:symbol`, not the raw path)
+    routes: HistogramVec,
+    /// Responses per (route, method, status)
+    requests: IntCounterVec,
+    response_cache_hits: IntCounter,
+    response_cache_misses: IntCounter,
+    /// Bytes held by the response cache, set after every insert or eviction
+    response_cache_bytes: IntGauge,
+}
+
+impl Default for ApiMetrics {
+    fn default() -> Self {
+        let registry = Registry::new();
+        let latency = |name: &str, help: &str, label: &str| {
+            let opts = HistogramOpts::new(name, help).buckets(LATENCY_BUCKETS.to_vec());
+            register(&registry, HistogramVec::new(opts, &[label]))
+        };
+        Self {
+            queries: latency(
+                "fx_store_query_duration_seconds",
+                "Query handler latency",
+                "handler",
+            ),
+            routes: latency(
+                "fx_store_http_request_duration_seconds",
+                "HTTP latency per route",
+                "route",
+            ),
+            requests: register(
+                &registry,
+                IntCounterVec::new(
+                    Opts::new(
+                        "fx_store_http_requests_total",
+                        "HTTP responses by route, method and status",
+                    ),
+                    &["route", "method", "status"],
+                ),
+            ),
+            response_cache_hits: register(
+                &registry,
+                IntCounter::new(
+                    "fx_store_response_cache_hits_total",
+                    "Read requests answered from the response cache",
+                ),
+            ),
+            response_cache_misses: register(
+                &registry,
+                IntCounter::new(
+                    "fx_store_response_cache_misses_total",
+                    "Cacheable read requests that ran their handler",
+                ),
+            ),
+            response_cache_bytes: register(
+                &registry,
+                IntGauge::new(
+                    "fx_store_response_cache_bytes",
+                    "Response bytes held by the HTTP response cache",
+                ),
+            ),
+            registry,
+        }
+    }
+}
+
+impl ApiMetrics {
+    /// Time a query handler until the returned guard drops, error paths included
+    pub fn time_query(&self, handler: &'static str) -> QueryTimer {
+        QueryTimer {
+            histogram: self.queries.with_label_values(&[handler]),
+            started: Instant::now(),
+        }
+    }
+
+    /// Count one response and its latency, called by the request middleware
+    pub fn observe_request(&self, route: &str, method: &str, status: u16, nanos: u64) {
+        self.routes
+            .with_label_values(&[route])
+            .observe(nanos as f64 / 1e9);
+        self.requests
+            .with_label_values(&[route, method, &status.to_string()])
+            .inc();
+    }
+
+    /// Count one lookup in the response cache
+    pub fn observe_response_cache(&self, hit: bool) {
+        if hit {
+            self.response_cache_hits.inc();
+        } else {
+            self.response_cache_misses.inc();
+        }
+    }
+
+    pub fn set_response_cache_bytes(&self, bytes: usize) {
+        self.response_cache_bytes.set(bytes as i64);
+    }
+
+    /// Render the HTTP metrics plus a snapshot of the store's in the Prometheus text format
+    pub fn render(&self, stats: &StatsSnapshot) -> String {
+        let mut families = store_registry(stats).gather();
+        families.extend(self.registry.gather());
+        families.sort_by(|a, b| a.name().cmp(b.name()));
+
+        let mut out = Vec::new();
+        // Only fails on malformed families, which the registries never produce
+        TextEncoder::new()
+            .encode(&families, &mut out)
+            .expect("encode metrics");
+        String::from_utf8(out).expect("metrics are UTF-8")
+    }
+}
+
+/// Records the elapsed time into `ApiMetrics` on drop
+pub struct QueryTimer {
+    histogram: Histogram,
+    started: Instant,
+}
+
+impl Drop for QueryTimer {
+    fn drop(&mut self) {
+        self.histogram.observe(self.started.elapsed().as_secs_f64());
+    }
+}
+
+/// Names are constants, so a registration failure is a bug
+fn register<C: Collector + Clone + 'static>(
+    registry: &Registry,
+    metric: prometheus::Result<C>,
+) -> C {
+    let metric = metric.expect("valid metric");
+    registry
+        .register(Box::new(metric.clone()))
+        .expect("unique metric name");
+    metric
+}
+
+/// Store counters are cumulative in `StatsSnapshot`, so each scrape builds them fresh
+fn store_registry(stats: &StatsSnapshot) -> Registry {
+    let registry = Registry::new();
+
+    let gauges = [
+        ("fx_store_records", "Records stored", stats.total_records),
+        (
+            "fx_store_compressed_bytes",
+            "Compressed block bytes",
+            stats.compressed_bytes,
+        ),
+        (
+            "fx_store_last_price_entries",
+            "Symbols with a cached last price",
+            stats.last_price_entries,
+        ),
+        (
+            "fx_store_subscribers",
+            "Connected realtime subscribers",
+            stats.subscribers.len() as u64,
+        ),
+        (
+            "fx_store_resident_block_bytes",
+            "Decompressed block bytes held in memory",
+            stats.resident_bytes,
+        ),
+        (
+            "fx_store_compress_queue_depth",
+            "Compression jobs waiting for the background thread",
+            stats.compress_queue_depth,
+        ),
+    ];
+    for (name, help, value) in gauges {
+        register(&registry, IntGauge::new(name, help)).set(value as i64);
+    }
+
+    let counters = [
+        (
+            "fx_store_block_cache_hits_total",
+            "Block reads served from the decompressed cache",
+            stats.cache_hits,
+        ),
+        (
+            "fx_store_blocks_decompressed_total",
+            "Block reads that had to decompress",
+            stats.blocks_decompressed,
+        ),
+        (
+            "fx_store_query_records_total",
+            "Records returned by range queries",
+            stats.query_records,
+        ),
+        (
+            "fx_store_last_price_hits_total",
+            "Last price lookups served from the cache",
+            stats.last_price_hits,
+        ),
+        (
+            "fx_store_last_price_misses_total",
+            "Last price lookups that scanned blocks",
+            stats.last_price_misses,
+        ),
+        (
+            "fx_store_late_ticks_total",
+            "Ticks dropped because their minute was already emitted",
+            stats.late_ticks,
+        ),
+        (
+            "fx_store_dropped_bars_total",
+            "Bars skipped for subscribers with a full channel",
+            stats.dropped_bars,
+        ),
+        (
+            "fx_store_dropped_batches_total",
+            "Import day batches dropped from a full compression queue",
+            stats.dropped_batches,
+        ),
+        (
+            "fx_store_imported_records_total",
+            "Bars and ticks read by CSV and Parquet imports",
+            stats.imported_records,
+        ),
+    ];
+    for (name, help, value) in counters {
+        register(&registry, IntCounter::new(name, help)).inc_by(value);
+    }
+
+    register(
+        &registry,
+        prometheus::Counter::new(
+            "fx_store_import_duration_seconds_total",
+            "Time spent in CSV and Parquet imports",
+        ),
+    )
+    .inc_by(stats.import_nanos as f64 / 1e9);
+
+    let reads = stats.cache_hits + stats.blocks_decompressed;
+    register(
+        &registry,
+        Gauge::new(
+            "fx_store_block_cache_hit_ratio",
+            "Share of block reads served from the cache",
+        ),
+    )
+    .set(if reads == 0 {
+        0.0
+    } else {
+        stats.cache_hits as f64 / reads as f64
+    });
+
+    let blocks = register(
+        &registry,
+        IntGaugeVec::new(
+            Opts::new("fx_store_blocks", "Compressed daily blocks per symbol"),
+            &["symbol"],
+        ),
+    );
+    for (symbol, count) in &stats.blocks_by_symbol {
+        blocks.with_label_values(&[symbol]).set(*count as i64);
+    }
+
+    registry
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn histogram_buckets_are_cumulative() {
+        let metrics = ApiMetrics::default();
+        let history = metrics.queries.with_label_values(&["history"]);
+        history.observe(0.0002);
+        history.observe(0.003);
+        history.observe(10.0);
+
+        let text = metrics.render(&StatsSnapshot::default());
+        for line in [
+            "fx_store_query_duration_seconds_bucket{handler=\"history\",le=\"0.0005\"} 1",
+            "fx_store_query_duration_seconds_bucket{handler=\"history\",le=\"0.005\"} 2",
+            "fx_store_query_duration_seconds_bucket{handler=\"history\",le=\"5\"} 2",
+            "fx_store_query_duration_seconds_bucket{handler=\"history\",le=\"+Inf\"} 3",
+            "fx_store_query_duration_seconds_count{handler=\"history\"} 3",
+            "fx_store_query_duration_seconds_sum{handler=\"history\"} 10.0032",
+        ] {
+            assert!(text.contains(line), "missing {:?} in\n{}", line, text);
+        }
+    }
+
+    #[test]
+    fn labels_are_escaped() {
+        let metrics = ApiMetrics::default();
+        metrics.observe_request("/a\"b", "GET", 200, 1_000);
+        let text = metrics.render(&StatsSnapshot::default());
+        assert!(
+            text.contains(
+                "fx_store_http_requests_total{method=\"GET\",route=\"/a\\\"b\",status=\"200\"} 1"
+            ),
+            "{}",
+            text
+        );
+    }
+}
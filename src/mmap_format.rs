@@ -1,41 +1,432 @@
-use memmap2::{MmapMut, MmapOptions};
-use std::fs::OpenOptions;
+//! 블록 파일: 16바이트 헤더 + append-only 블록 레코드
+//!
+//! 레코드는 WAL과 같은 길이 접두사 + bincode. 같은 (심볼, 날짜)는 뒤 레코드가 앞 레코드를 대체하고,
+//! 데이터가 빈 레코드는 삭제 표시. 열 때 대체된 레코드가 살아있는 것보다 많으면 다시 씀
+//!
+//! 버전 기록:
+//! - v1: 레코드에 블록 체크섬 없음
+//! - v2: `BlockRecord::checksum` 추가
+//! - v3: `BlockRecord::summary` 추가 (열 때 해제 없이 블록 메타데이터 복원)
+//!
+//! `FORMAT_VERSION`보다 오래된 파일은 열 때 레코드를 현재 레이아웃으로 올려 다시 씀
 
-/// 영속성을 위한 mmap 파일 구조
-#[repr(C, packed)]
-struct MmapHeader {
-    magic: [u8; 8], // "FXSTORE1"
-    version: u32,
-    symbol_count: u32,
-    block_count: u64,
-    index_offset: u64,
-    data_offset: u64,
+use crate::block::{BlockCodec, BlockLayout, CompressedBlock};
+use crate::error::{FxStoreError, Result};
+use crate::types::Granularity;
+use memmap2::Mmap;
+use parking_lot::Mutex;
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::fs::{File, OpenOptions};
+use std::io::{BufWriter, Write};
+use std::path::{Path, PathBuf};
+use xxhash_rust::xxh64::xxh64;
+
+const MAGIC: [u8; 8] = *b"FXSTORE1";
+/// 현재 블록 파일 레이아웃 버전 (레이아웃을 바꾸면 올리고 `upgrade_record`에 변환 추가)
+pub const FORMAT_VERSION: u32 = 3;
+const HEADER_LEN: usize = 16; // magic + version + reserved
+
+/// 블록 하나의 영속 레코드
+///
+/// symbol_id는 프로세스마다 달라질 수 있으므로 심볼 이름과 메타데이터를 함께 기록
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct BlockRecord {
+    pub symbol: String,
+    pub granularity: Granularity,
+    /// 심볼 가격 스케일 (`Symbol::scale`)
+    pub scale: u32,
+    pub date: u32, // YYYYMMDD
+    pub codec: BlockCodec,
+    /// 압축 데이터의 XXH64
+    pub checksum: u64,
+    /// 레코드 수와 시각·가격 범위 (삭제 표시나 모르는 블록은 None)
+    pub summary: Option<BlockSummary>,
+    /// 압축된 블록 (비어 있으면 삭제 표시)
+    pub data: Vec<u8>,
+}
+
+impl BlockRecord {
+    pub fn is_tombstone(&self) -> bool {
+        self.data.is_empty()
+    }
+}
+
+/// 블록 메타데이터 (`CompressedBlock`의 같은 이름 필드, 빈 블록은 min > max)
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct BlockSummary {
+    pub records: u32,
+    pub min_ts: u64,
+    pub max_ts: u64,
+    pub min_price: u32,
+    pub max_price: u32,
+}
+
+impl BlockSummary {
+    /// 블록의 메타데이터 (레코드 수를 모르는 블록은 None)
+    pub fn of(block: &CompressedBlock) -> Option<Self> {
+        Some(Self {
+            records: block.records?,
+            min_ts: block.min_ts,
+            max_ts: block.max_ts,
+            min_price: block.min_price,
+            max_price: block.max_price,
+        })
+    }
+
+    /// 해제하지 않고 블록에 메타데이터 채우기
+    pub fn apply_to(self, block: &mut CompressedBlock) {
+        block.records = Some(self.records);
+        (block.min_ts, block.max_ts) = (self.min_ts, self.max_ts);
+        (block.min_price, block.max_price) = (self.min_price, self.max_price);
+    }
+}
+
+/// v2 레코드 (요약 없음)
+#[derive(Serialize, Deserialize)]
+struct BlockRecordV2 {
+    symbol: String,
+    granularity: Granularity,
+    scale: u32,
+    date: u32,
+    codec: BlockCodec,
+    checksum: u64,
+    data: Vec<u8>,
 }
 
+impl From<BlockRecordV2> for BlockRecord {
+    /// 블록을 한 번 해제해 요약 계산 (손상된 블록은 요약 없이 두고 쿼리나 `scrub`이 찾게 함)
+    fn from(v2: BlockRecordV2) -> Self {
+        let summary = if v2.data.is_empty() {
+            None
+        } else {
+            let layout = BlockLayout {
+                codec: v2.codec,
+                granularity: v2.granularity,
+                ..Default::default()
+            };
+            let block =
+                CompressedBlock::from_parts(v2.date, 0, layout, v2.data.clone(), v2.checksum);
+            block
+                .try_decompress_shared()
+                .ok()
+                .and_then(|slots| BlockSummary::of(&block.with_bounds(&slots)))
+        };
+        Self {
+            symbol: v2.symbol,
+            granularity: v2.granularity,
+            scale: v2.scale,
+            date: v2.date,
+            codec: v2.codec,
+            checksum: v2.checksum,
+            summary,
+            data: v2.data,
+        }
+    }
+}
+
+/// v1 레코드 (체크섬 없음)
+#[derive(Serialize, Deserialize)]
+struct BlockRecordV1 {
+    symbol: String,
+    granularity: Granularity,
+    scale: u32,
+    date: u32,
+    codec: BlockCodec,
+    data: Vec<u8>,
+}
+
+impl From<BlockRecordV1> for BlockRecordV2 {
+    fn from(v1: BlockRecordV1) -> Self {
+        let checksum = if v1.data.is_empty() {
+            0
+        } else {
+            xxh64(&v1.data, 0)
+        };
+        Self {
+            symbol: v1.symbol,
+            granularity: v1.granularity,
+            scale: v1.scale,
+            date: v1.date,
+            codec: v1.codec,
+            checksum,
+            data: v1.data,
+        }
+    }
+}
+
+/// `version` 레이아웃으로 기록된 레코드를 현재 레이아웃으로 읽기
+fn upgrade_record(version: u32, payload: &[u8]) -> Result<BlockRecord> {
+    match version {
+        1 => Ok(BlockRecordV2::from(bincode::deserialize::<BlockRecordV1>(payload)?).into()),
+        2 => Ok(bincode::deserialize::<BlockRecordV2>(payload)?.into()),
+        _ => Ok(bincode::deserialize(payload)?),
+    }
+}
+
+/// 블록 파일 핸들 (쓰기는 append만)
 pub struct PersistentStore {
-    mmap: MmapMut,
-    header: *mut MmapHeader,
+    path: PathBuf,
+    file: Mutex<BufWriter<File>>,
 }
 
 impl PersistentStore {
-    pub unsafe fn create(path: &str, size: usize) -> anyhow::Result<Self> {
+    /// 파일을 열고 살아있는 레코드를 (심볼, 날짜)순으로 반환 (없으면 새로 생성)
+    ///
+    /// 마지막 레코드가 잘려 있으면 그 앞까지 자름
+    pub fn open(path: impl AsRef<Path>) -> Result<(Self, Vec<BlockRecord>)> {
+        let path = path.as_ref().to_path_buf();
         let file = OpenOptions::new()
             .read(true)
-            .write(true)
+            .append(true)
             .create(true)
-            .open(path)?;
-        file.set_len(size as u64)?;
+            .open(&path)?;
 
-        let mut mmap = unsafe { MmapOptions::new().len(size).map_mut(&file)? };
+        if file.metadata()?.len() == 0 {
+            write_header(&file)?;
+        }
 
-        // 헤더 초기화
-        let header = unsafe { &mut *(mmap.as_mut_ptr() as *mut MmapHeader) };
-        header.magic = *b"FXSTORE1";
-        header.version = 1;
+        // SAFETY: 블록 파일은 이 핸들로만 append되고, 매핑은 읽기가 끝나면 바로 해제
+        let mmap = unsafe { Mmap::map(&file)? };
+        let (live, total, valid_len, version) = read_records(&mmap)?;
+        drop(mmap);
+        if valid_len < file.metadata()?.len() {
+            file.set_len(valid_len)?;
+        }
 
-        Ok(Self {
-            header: mmap.as_mut_ptr() as *mut MmapHeader,
-            mmap,
-        })
+        let store = Self {
+            path,
+            file: Mutex::new(BufWriter::new(file)),
+        };
+        let live: Vec<BlockRecord> = live.into_values().collect();
+        if version < FORMAT_VERSION {
+            tracing::info!(
+                path = %store.path.display(),
+                from = version,
+                to = FORMAT_VERSION,
+                "migrating block file"
+            );
+            store.compact(&live)?;
+        } else if total > 2 * live.len() {
+            store.compact(&live)?;
+        }
+        Ok((store, live))
+    }
+
+    /// 레코드를 이어 쓰고 디스크까지 동기화
+    pub fn append(&self, records: &[BlockRecord]) -> Result<()> {
+        let mut file = self.file.lock();
+        for record in records {
+            let payload = bincode::serialize(record)?;
+            file.write_all(&(payload.len() as u32).to_le_bytes())?;
+            file.write_all(&payload)?;
+        }
+        file.flush()?;
+        file.get_ref().sync_data()?;
+        Ok(())
+    }
+
+    /// 살아있는 레코드만 새 파일에 쓰고 교체
+    pub fn compact(&self, live: &[BlockRecord]) -> Result<()> {
+        let mut file = self.file.lock();
+        let tmp_path = self.path.with_extension("compact");
+        let tmp = File::create(&tmp_path)?;
+        write_header(&tmp)?;
+        let mut writer = BufWriter::new(tmp);
+        for record in live {
+            let payload = bincode::serialize(record)?;
+            writer.write_all(&(payload.len() as u32).to_le_bytes())?;
+            writer.write_all(&payload)?;
+        }
+        writer.flush()?;
+        writer.get_ref().sync_all()?;
+        std::fs::rename(&tmp_path, &self.path)?;
+
+        *file = BufWriter::new(OpenOptions::new().append(true).open(&self.path)?);
+        Ok(())
+    }
+}
+
+fn write_header(file: &File) -> Result<()> {
+    let mut header = [0u8; HEADER_LEN];
+    header[..8].copy_from_slice(&MAGIC);
+    header[8..12].copy_from_slice(&FORMAT_VERSION.to_le_bytes());
+    let mut file = file;
+    file.write_all(&header)?;
+    file.sync_data()?;
+    Ok(())
+}
+
+/// (살아있는 레코드, 전체 레코드 수, 온전한 부분의 길이, 파일 버전)
+type ReadResult = (BTreeMap<(String, u32), BlockRecord>, usize, u64, u32);
+
+fn read_records(bytes: &[u8]) -> Result<ReadResult> {
+    if bytes.len() < HEADER_LEN || bytes[..8] != MAGIC {
+        return Err(FxStoreError::Corruption(
+            "not an fx-store block file".into(),
+        ));
+    }
+    let version = u32::from_le_bytes(bytes[8..12].try_into().unwrap());
+    if version == 0 || version > FORMAT_VERSION {
+        return Err(FxStoreError::Corruption(format!(
+            "unsupported block file version {}",
+            version
+        )));
+    }
+
+    let mut live = BTreeMap::new();
+    let mut total = 0;
+    let mut pos = HEADER_LEN;
+    while let Some(len_bytes) = bytes.get(pos..pos + 4) {
+        let len = u32::from_le_bytes(len_bytes.try_into().unwrap()) as usize;
+        let Some(payload) = bytes.get(pos + 4..pos + 4 + len) else {
+            break;
+        };
+        let record = upgrade_record(version, payload)?;
+        pos += 4 + len;
+        total += 1;
+
+        let key = (record.symbol.clone(), record.date);
+        if record.is_tombstone() {
+            live.remove(&key);
+        } else {
+            live.insert(key, record);
+        }
+    }
+    Ok((live, total, pos as u64, version))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn record(date: u32, data: &[u8]) -> BlockRecord {
+        BlockRecord {
+            symbol: "EURUSD".to_string(),
+            granularity: Granularity::Minute,
+            scale: 100_000,
+            date,
+            codec: BlockCodec::ZstdColumns,
+            checksum: 0,
+            summary: None,
+            data: data.to_vec(),
+        }
+    }
+
+    #[test]
+    fn later_records_win_and_torn_tail_is_dropped() {
+        let path = std::env::temp_dir().join(format!("fx-store-{}-blocks.fxd", std::process::id()));
+        std::fs::remove_file(&path).ok();
+
+        let (store, live) = PersistentStore::open(&path).unwrap();
+        assert!(live.is_empty());
+        store
+            .append(&[record(20240102, b"a"), record(20240103, b"b")])
+            .unwrap();
+        store
+            .append(&[record(20240102, b"c"), record(20240103, b"")])
+            .unwrap();
+        drop(store);
+
+        // 쓰다 만 레코드
+        let intact = std::fs::metadata(&path).unwrap().len();
+        let mut file = OpenOptions::new().append(true).open(&path).unwrap();
+        file.write_all(&[200, 0, 0, 0, 1, 2]).unwrap();
+        drop(file);
+
+        let (_, live) = PersistentStore::open(&path).unwrap();
+        assert_eq!(live.len(), 1);
+        assert_eq!(
+            (live[0].date, live[0].data.as_slice()),
+            (20240102, &b"c"[..])
+        );
+        // 4개 중 1개만 살아 있어 다시 씀
+        assert!(std::fs::metadata(&path).unwrap().len() < intact);
+
+        std::fs::remove_file(&path).ok();
+    }
+
+    #[test]
+    fn v1_file_is_migrated_on_open() {
+        use crate::block::{BlockLayout, CompressedBlock};
+        use crate::types::OHLCV;
+
+        let path = std::env::temp_dir().join(format!("fx-store-{}-v1.fxd", std::process::id()));
+        let records: Vec<OHLCV> = (0..30)
+            .map(|i| OHLCV {
+                ts: 1_704_153_600_000_000_000 + i * 60_000_000_000,
+                open: 110_000 + i as u32,
+                high: 110_010 + i as u32,
+                low: 109_990 + i as u32,
+                close: 110_005 + i as u32,
+                volume: 1,
+                ..Default::default()
+            })
+            .collect();
+        let block = CompressedBlock::new(20240102, 0, &records, BlockLayout::default());
+
+        // v1 헤더 + 체크섬 없는 레코드
+        let mut bytes = MAGIC.to_vec();
+        bytes.extend(1u32.to_le_bytes());
+        bytes.resize(HEADER_LEN, 0);
+        for (date, data) in [(20240102, block.data.to_vec()), (20240103, vec![1, 2, 3])] {
+            let payload = bincode::serialize(&BlockRecordV1 {
+                symbol: "EURUSD".to_string(),
+                granularity: Granularity::Minute,
+                scale: 100_000,
+                date,
+                codec: BlockCodec::ZstdColumns,
+                data,
+            })
+            .unwrap();
+            bytes.extend((payload.len() as u32).to_le_bytes());
+            bytes.extend(payload);
+        }
+        std::fs::write(&path, &bytes).unwrap();
+
+        let (_, live) = PersistentStore::open(&path).unwrap();
+        assert_eq!(live.len(), 2);
+        assert_eq!(live[0].checksum, block.checksum);
+        // 요약은 옮길 때 한 번 해제해 채우고, 해제할 수 없는 블록은 비워 둠
+        assert_eq!(live[0].summary, BlockSummary::of(&block));
+        assert_eq!(live[0].summary.map(|summary| summary.records), Some(30));
+        assert_eq!(live[1].summary, None);
+        let restored = CompressedBlock::from_parts(
+            live[0].date,
+            0,
+            BlockLayout::default(),
+            live[0].data.clone(),
+            live[0].checksum,
+        );
+        let restored: Vec<u64> = restored
+            .decompress()
+            .unwrap()
+            .iter()
+            .filter(|r| r.ts != 0)
+            .map(|r| r.ts)
+            .collect();
+        assert_eq!(restored, records.iter().map(|r| r.ts).collect::<Vec<_>>());
+
+        // 현재 버전으로 다시 쓰였으므로 두 번째로 열 때는 그대로 읽힘
+        let header = std::fs::read(&path).unwrap();
+        assert_eq!(header[8..12], FORMAT_VERSION.to_le_bytes());
+        let (_, reopened) = PersistentStore::open(&path).unwrap();
+        assert_eq!(reopened.len(), 2);
+        assert_eq!(reopened[1].checksum, xxh64(&[1, 2, 3], 0));
+
+        // 모르는 magic이나 더 새 버전은 거부
+        for header in [*b"NOTFXSTR\x02\0\0\0\0\0\0\0", {
+            let mut h = [0u8; HEADER_LEN];
+            h[..8].copy_from_slice(&MAGIC);
+            h[8..12].copy_from_slice(&(FORMAT_VERSION + 1).to_le_bytes());
+            h
+        }] {
+            std::fs::write(&path, header).unwrap();
+            assert!(matches!(
+                PersistentStore::open(&path),
+                Err(FxStoreError::Corruption(_))
+            ));
+        }
+        std::fs::remove_file(&path).ok();
     }
 }
@@ -0,0 +1,434 @@
+//! Arrow 레코드 배치와 Parquet 파일 변환
+//!
+//! 내보내는 컬럼: ts (Timestamp ns, UTC), open/high/low/close (Float64, 심볼 스케일을 푼 가격),
+//! volume (UInt32), symbol (Dictionary<UInt16, Utf8>). 가져올 때는 `ColumnMapping`으로
+//! 컬럼 이름과 타임스탬프 해석을 지정
+
+use crate::csv_format::normalize_name;
+use crate::error::{FxStoreError, Result};
+use crate::types::{MAX_TS, OHLCV, local_to_ts, scale_price};
+use arrow_array::builder::{
+    ArrayBuilder, Float64Builder, StringDictionaryBuilder, TimestampNanosecondBuilder,
+    UInt32Builder,
+};
+use arrow_array::cast::AsArray;
+use arrow_array::types::{Float64Type, Int64Type, UInt16Type};
+use arrow_array::{Array, ArrayRef, RecordBatch};
+use arrow_schema::{DataType, Field, Schema, SchemaRef, TimeUnit};
+use chrono::DateTime;
+use chrono_tz::Tz;
+use parquet::arrow::arrow_reader::{ParquetRecordBatchReader, ParquetRecordBatchReaderBuilder};
+use parquet::arrow::{ArrowWriter, ProjectionMask};
+use parquet::basic::{Compression, ZstdLevel};
+use parquet::file::properties::WriterProperties;
+use std::io::Write;
+use std::sync::Arc;
+
+/// 행 그룹 하나의 봉 수 (1분봉 한 달 남짓)
+const ROW_GROUP_BARS: usize = 1440 * 32;
+
+/// 가져올 때 한 번에 변환하는 행 수
+const READ_BATCH_ROWS: usize = 64 * 1024;
+
+/// 정수 epoch가 이 값 이상이면 ns, 미만이면 ms (ms로 5138년, ns로 1970-01-02)
+const EPOCH_NANOS_MIN: u64 = 100_000_000_000_000;
+
+/// 내보내는 배치의 스키마
+pub fn bar_schema() -> SchemaRef {
+    let price = |name: &str| Field::new(name, DataType::Float64, false);
+    Arc::new(Schema::new(vec![
+        Field::new(
+            "ts",
+            DataType::Timestamp(TimeUnit::Nanosecond, Some("UTC".into())),
+            false,
+        ),
+        price("open"),
+        price("high"),
+        price("low"),
+        price("close"),
+        Field::new("volume", DataType::UInt32, false),
+        Field::new_dictionary("symbol", DataType::UInt16, DataType::Utf8, false),
+    ]))
+}
+
+/// 봉을 하나씩 받아 Arrow 컬럼에 쌓는 빌더 (블록 단위로 채우고 `finish`로 배치를 꺼냄)
+pub(crate) struct BarBatchBuilder {
+    symbol: String,
+    scale: f64,
+    ts: TimestampNanosecondBuilder,
+    open: Float64Builder,
+    high: Float64Builder,
+    low: Float64Builder,
+    close: Float64Builder,
+    volume: UInt32Builder,
+    symbols: StringDictionaryBuilder<UInt16Type>,
+}
+
+impl BarBatchBuilder {
+    pub(crate) fn new(symbol: &str, scale: u32) -> Self {
+        Self {
+            symbol: symbol.to_string(),
+            scale: f64::from(scale),
+            ts: TimestampNanosecondBuilder::new().with_timezone("UTC"),
+            open: Float64Builder::new(),
+            high: Float64Builder::new(),
+            low: Float64Builder::new(),
+            close: Float64Builder::new(),
+            volume: UInt32Builder::new(),
+            symbols: StringDictionaryBuilder::new(),
+        }
+    }
+
+    /// 봉 한 개 추가 (거래량이 u32를 넘는 집계 봉은 `Parquet` 오류)
+    pub(crate) fn append(&mut self, rec: &OHLCV) -> Result<()> {
+        let volume = u32::try_from(rec.total_volume()).map_err(|_| {
+            FxStoreError::Parquet(format!(
+                "volume {} at {} does not fit the UInt32 volume column",
+                rec.total_volume(),
+                { rec.ts }
+            ))
+        })?;
+        self.ts.append_value(rec.ts as i64);
+        self.open.append_value(f64::from(rec.open) / self.scale);
+        self.high.append_value(f64::from(rec.high) / self.scale);
+        self.low.append_value(f64::from(rec.low) / self.scale);
+        self.close.append_value(f64::from(rec.close) / self.scale);
+        self.volume.append_value(volume);
+        self.symbols.append_value(&self.symbol);
+        Ok(())
+    }
+
+    pub(crate) fn len(&self) -> usize {
+        self.ts.len()
+    }
+
+    /// 쌓인 봉을 배치로 꺼내고 빌더를 비움
+    pub(crate) fn finish(&mut self) -> RecordBatch {
+        RecordBatch::try_new(
+            bar_schema(),
+            vec![
+                Arc::new(self.ts.finish()),
+                Arc::new(self.open.finish()),
+                Arc::new(self.high.finish()),
+                Arc::new(self.low.finish()),
+                Arc::new(self.close.finish()),
+                Arc::new(self.volume.finish()),
+                Arc::new(self.symbols.finish()),
+            ],
+        )
+        .expect("builders match the bar schema")
+    }
+}
+
+/// `bar_schema`로 쓰는 zstd 압축 Parquet 기록기
+pub(crate) fn bar_writer<W: Write + Send>(writer: W) -> Result<ArrowWriter<W>> {
+    let props = WriterProperties::builder()
+        .set_compression(Compression::ZSTD(ZstdLevel::default()))
+        .set_max_row_group_size(ROW_GROUP_BARS)
+        .build();
+    Ok(ArrowWriter::try_new(writer, bar_schema(), Some(props))?)
+}
+
+/// 메모리에 있는 봉들을 Parquet으로 기록 (기록한 봉 수 반환)
+#[cfg(feature = "api")]
+pub(crate) fn write_bars<W: Write + Send>(
+    writer: W,
+    symbol: &str,
+    scale: u32,
+    records: &[OHLCV],
+) -> Result<usize> {
+    let mut writer = bar_writer(writer)?;
+    let mut builder = BarBatchBuilder::new(symbol, scale);
+    for chunk in records.chunks(ROW_GROUP_BARS) {
+        for rec in chunk {
+            builder.append(rec)?;
+        }
+        writer.write(&builder.finish())?;
+    }
+    writer.into_inner()?.flush()?;
+    Ok(records.len())
+}
+
+/// 정수 epoch 타임스탬프 컬럼의 단위
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum EpochUnit {
+    Millis,
+    Nanos,
+}
+
+/// Parquet 임포트 컬럼 매핑 (이름은 파일의 컬럼명 그대로)
+///
+/// ts 컬럼은 Timestamp(시간대가 있으면 그 시각, 없으면 `source_tz` 현지 시각)
+/// 또는 정수 epoch. 가격과 거래량은 Float64로 변환해 읽으며 거래량은 반올림
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct ColumnMapping {
+    pub ts: String,
+    pub open: String,
+    pub high: String,
+    pub low: String,
+    pub close: String,
+    /// None이면 거래량 0
+    pub volume: Option<String>,
+    /// 정수 epoch 컬럼의 단위 (None이면 값마다 크기로 판단, `EPOCH_NANOS_MIN` 참고)
+    pub epoch_unit: Option<EpochUnit>,
+    /// 시간대 없는 Timestamp 컬럼의 현지 시간대 (None이면 `StoreConfig::source_tz`)
+    pub source_tz: Option<Tz>,
+}
+
+impl Default for ColumnMapping {
+    fn default() -> Self {
+        Self {
+            ts: "ts".to_string(),
+            open: "open".to_string(),
+            high: "high".to_string(),
+            low: "low".to_string(),
+            close: "close".to_string(),
+            volume: Some("volume".to_string()),
+            epoch_unit: None,
+            source_tz: None,
+        }
+    }
+}
+
+impl ColumnMapping {
+    /// 컬럼명으로 매핑 추정 (대소문자·기호 무시, 시각/OHLC 중 하나라도 없으면 None)
+    pub fn detect(schema: &Schema) -> Option<Self> {
+        let find = |candidates: &[&str]| {
+            schema
+                .fields()
+                .iter()
+                .find(|field| candidates.contains(&normalize_name(field.name()).as_str()))
+                .map(|field| field.name().clone())
+        };
+        Some(Self {
+            ts: find(&[
+                "ts",
+                "timestamp",
+                "datetime",
+                "time",
+                "date",
+                "gmttime",
+                "utctime",
+            ])?,
+            open: find(&["open", "o", "bidopen"])?,
+            high: find(&["high", "h", "bidhigh"])?,
+            low: find(&["low", "l", "bidlow"])?,
+            close: find(&["close", "c", "bidclose"])?,
+            volume: find(&["volume", "vol", "v", "tickvol", "tickvolume"]),
+            ..Self::default()
+        })
+    }
+
+    fn columns(&self) -> impl Iterator<Item = &str> {
+        [&self.ts, &self.open, &self.high, &self.low, &self.close]
+            .into_iter()
+            .chain(&self.volume)
+            .map(String::as_str)
+    }
+}
+
+/// ts 컬럼 값의 해석
+#[derive(Clone, Copy)]
+enum TsKind {
+    /// UTC 기준 시각, 단위당 나노초
+    Instant(i64),
+    /// 현지 시각, 단위당 나노초
+    Local(i64),
+    /// 정수 epoch
+    Epoch(Option<EpochUnit>),
+}
+
+impl TsKind {
+    fn of(data_type: &DataType, unit: Option<EpochUnit>) -> Result<Self> {
+        let nanos = |unit: &TimeUnit| match unit {
+            TimeUnit::Second => 1_000_000_000,
+            TimeUnit::Millisecond => 1_000_000,
+            TimeUnit::Microsecond => 1_000,
+            TimeUnit::Nanosecond => 1,
+        };
+        match data_type {
+            DataType::Timestamp(unit, Some(_)) => Ok(Self::Instant(nanos(unit))),
+            DataType::Timestamp(unit, None) => Ok(Self::Local(nanos(unit))),
+            DataType::Int64 | DataType::UInt64 | DataType::Int32 | DataType::UInt32 => {
+                Ok(Self::Epoch(unit))
+            }
+            other => Err(FxStoreError::Parquet(format!(
+                "ts column must be a timestamp or integer epoch, not {}",
+                other
+            ))),
+        }
+    }
+
+    /// 컬럼 값 → UTC epoch nanos (저장할 수 없는 시각이면 None)
+    fn to_ts(self, raw: i64, tz: Tz) -> Option<u64> {
+        let ts = match self {
+            Self::Instant(nanos) => u64::try_from(raw.checked_mul(nanos)?).ok()?,
+            Self::Local(nanos) => {
+                let local = DateTime::from_timestamp_nanos(raw.checked_mul(nanos)?).naive_utc();
+                local_to_ts(&local, tz)?
+            }
+            Self::Epoch(unit) => {
+                let raw = u64::try_from(raw).ok()?;
+                let unit = unit.unwrap_or(if raw >= EPOCH_NANOS_MIN {
+                    EpochUnit::Nanos
+                } else {
+                    EpochUnit::Millis
+                });
+                match unit {
+                    EpochUnit::Millis => raw.checked_mul(1_000_000)?,
+                    EpochUnit::Nanos => raw,
+                }
+            }
+        };
+        (1..=MAX_TS).contains(&ts).then_some(ts)
+    }
+}
+
+/// Parquet 파일을 행 그룹 순서대로 읽어 (행 번호, 봉) 묶음으로 바꾸는 리더
+///
+/// 한 번에 `READ_BATCH_ROWS`행씩 읽어 메모리는 배치 하나 분량. 행 번호는 1부터
+pub(crate) struct BarReader {
+    batches: ParquetRecordBatchReader,
+    mapping: ColumnMapping,
+    ts_kind: TsKind,
+    source_tz: Tz,
+    scale: u32,
+    symbol_id: u16,
+    next_row: usize,
+}
+
+impl BarReader {
+    /// `mapping`이 None이면 컬럼명으로 추정, `source_tz`는 매핑에 시간대가 없을 때 적용
+    pub(crate) fn open(
+        path: &str,
+        mapping: Option<ColumnMapping>,
+        source_tz: Tz,
+        scale: u32,
+        symbol_id: u16,
+    ) -> Result<Self> {
+        let builder = ParquetRecordBatchReaderBuilder::try_new(std::fs::File::open(path)?)?;
+        let schema = builder.schema().clone();
+        let mapping = match mapping {
+            Some(mapping) => mapping,
+            None => ColumnMapping::detect(&schema).ok_or_else(|| {
+                FxStoreError::Parquet(
+                    "no ts/open/high/low/close columns found; pass a ColumnMapping".to_string(),
+                )
+            })?,
+        };
+        let mut roots = Vec::new();
+        for name in mapping.columns() {
+            let index = schema
+                .index_of(name)
+                .map_err(|_| FxStoreError::Parquet(format!("no column named {}", name)))?;
+            roots.push(index);
+        }
+        let ts_kind = TsKind::of(schema.field(roots[0]).data_type(), mapping.epoch_unit)?;
+        let projection = ProjectionMask::roots(builder.parquet_schema(), roots);
+        let batches = builder
+            .with_projection(projection)
+            .with_batch_size(READ_BATCH_ROWS)
+            .build()?;
+
+        Ok(Self {
+            batches,
+            source_tz: mapping.source_tz.unwrap_or(source_tz),
+            mapping,
+            ts_kind,
+            scale,
+            symbol_id,
+            next_row: 1,
+        })
+    }
+
+    /// 배치 하나를 행별 결과로 (컬럼 전체를 읽지 못하면 오류, 행 단위 문제는 그 행의 Err)
+    fn convert(&mut self, batch: &RecordBatch) -> Result<Vec<(usize, Result<OHLCV>)>> {
+        let column = |name: &str| -> Result<ArrayRef> {
+            let column = batch
+                .column_by_name(name)
+                .ok_or_else(|| FxStoreError::Parquet(format!("no column named {}", name)))?;
+            let target = match column.data_type() {
+                DataType::Timestamp(..) | DataType::Int64 | DataType::UInt64 => DataType::Int64,
+                DataType::Int32 | DataType::UInt32 if name == self.mapping.ts => DataType::Int64,
+                _ => DataType::Float64,
+            };
+            Ok(arrow_cast::cast(column, &target)?)
+        };
+        let ts = column(&self.mapping.ts)?;
+        let ts = ts.as_primitive::<Int64Type>();
+        let prices = [
+            &self.mapping.open,
+            &self.mapping.high,
+            &self.mapping.low,
+            &self.mapping.close,
+        ]
+        .map(|name| column(name));
+        let [open, high, low, close] = prices;
+        let (open, high, low, close) = (open?, high?, low?, close?);
+        let volume = match &self.mapping.volume {
+            Some(name) => Some(arrow_cast::cast(&column(name)?, &DataType::Float64)?),
+            None => None,
+        };
+
+        let first_row = self.next_row;
+        self.next_row += batch.num_rows();
+        let row = |i: usize| -> Result<OHLCV> {
+            let missing = |name: &str| FxStoreError::parse(format!("missing {}", name));
+            if ts.is_null(i) {
+                return Err(missing(&self.mapping.ts));
+            }
+            let ts = self
+                .ts_kind
+                .to_ts(ts.value(i), self.source_tz)
+                .ok_or_else(|| {
+                    FxStoreError::parse(format!("timestamp {} out of range", ts.value(i)))
+                })?;
+            let price = |array: &ArrayRef, name: &str| -> Result<u32> {
+                let array = array.as_primitive::<Float64Type>();
+                if array.is_null(i) {
+                    return Err(missing(name));
+                }
+                scale_price(array.value(i), self.scale)
+            };
+            let volume = match &volume {
+                Some(array) if !array.is_null(i) => {
+                    let value = array.as_primitive::<Float64Type>().value(i);
+                    let rounded = value.round();
+                    if !(0.0..=f64::from(u32::MAX)).contains(&rounded) {
+                        return Err(FxStoreError::parse(format!(
+                            "volume {} out of range",
+                            value
+                        )));
+                    }
+                    rounded as u32
+                }
+                _ => 0,
+            };
+            Ok(OHLCV {
+                ts,
+                open: price(&open, &self.mapping.open)?,
+                high: price(&high, &self.mapping.high)?,
+                low: price(&low, &self.mapping.low)?,
+                close: price(&close, &self.mapping.close)?,
+                volume,
+                symbol_id: self.symbol_id,
+                ..Default::default()
+            })
+        };
+        Ok((0..batch.num_rows())
+            .map(|i| (first_row + i, row(i)))
+            .collect())
+    }
+}
+
+impl Iterator for BarReader {
+    type Item = Result<Vec<(usize, Result<OHLCV>)>>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let batch = match self.batches.next()? {
+            Ok(batch) => batch,
+            Err(e) => return Some(Err(e.into())),
+        };
+        Some(self.convert(&batch))
+    }
+}
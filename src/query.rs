@@ -1,20 +1,60 @@
-use crate::types::OHLCV;
-use std::arch::x86_64::*;
+use crate::block::Columns;
+use crate::types::{OHLCV, PriceField, Symbol, Tick};
+use chrono::{DateTime, LocalResult, NaiveTime, Offset, TimeZone, Timelike};
+use chrono_tz::Tz;
+use serde::Serialize;
+use std::collections::BTreeMap;
 
 /// SIMD 가속 필터링
 pub struct SimdFilter;
 
 impl SimdFilter {
+    /// close가 [min_price, max_price] (양끝 포함)인 레코드만 추출
+    ///
+    /// `simd` 기능이 켜져 있으면 x86_64에서는 AVX2를, aarch64에서는 NEON을 런타임 감지해 사용하고,
+    /// 그 외에는 스칼라 루프
+    pub fn filter_by_price(records: &[OHLCV], min_price: u32, max_price: u32) -> Vec<OHLCV> {
+        #[cfg(all(feature = "simd", target_arch = "x86_64"))]
+        if is_x86_feature_detected!("avx2") {
+            // SAFETY: AVX2 지원을 방금 확인함
+            return unsafe { Self::filter_by_price_avx2(records, min_price, max_price) };
+        }
+        #[cfg(all(feature = "simd", target_arch = "aarch64"))]
+        if is_aarch64_feature_detected!("neon") {
+            // SAFETY: NEON 지원을 방금 확인함
+            return unsafe { Self::filter_by_price_neon(records, min_price, max_price) };
+        }
+
+        Self::filter_by_price_scalar(records, min_price, max_price)
+    }
+
+    fn filter_by_price_scalar(records: &[OHLCV], min_price: u32, max_price: u32) -> Vec<OHLCV> {
+        records
+            .iter()
+            .filter(|rec| (min_price..=max_price).contains(&{ rec.close }))
+            .copied()
+            .collect()
+    }
+
+    #[cfg(all(feature = "simd", target_arch = "x86_64"))]
     #[target_feature(enable = "avx2")]
-    unsafe fn filter_by_price(records: &[OHLCV], min_price: u32, max_price: u32) -> Vec<OHLCV> {
+    unsafe fn filter_by_price_avx2(
+        records: &[OHLCV],
+        min_price: u32,
+        max_price: u32,
+    ) -> Vec<OHLCV> {
+        use std::arch::x86_64::*;
+
         let mut result = Vec::with_capacity(records.len());
 
         // 8개씩 SIMD 처리
         let chunks = records.chunks_exact(8);
         let remainder = chunks.remainder();
 
-        let min_vec = _mm256_set1_epi32(min_price as i32);
-        let max_vec = _mm256_set1_epi32(max_price as i32);
+        // AVX2에는 부호 없는 비교가 없으므로 부호 비트를 뒤집어 부호 있는 비교로 변환
+        let bias = _mm256_set1_epi32(i32::MIN);
+        let min_vec = _mm256_set1_epi32((min_price ^ 0x8000_0000) as i32);
+        let max_vec = _mm256_set1_epi32((max_price ^ 0x8000_0000) as i32);
 
         for chunk in chunks {
             // close 가격 추출
@@ -28,62 +68,2525 @@ impl SimdFilter {
                 chunk[1].close as i32,
                 chunk[0].close as i32,
             );
+            let prices = _mm256_xor_si256(prices, bias);
 
-            // 범위 체크
-            let ge_min = _mm256_cmpgt_epi32(prices, min_vec);
-            let le_max = _mm256_cmpgt_epi32(max_vec, prices);
-            let mask = _mm256_and_si256(ge_min, le_max);
+            // 범위 밖: min > price 또는 price > max (경계값은 포함)
+            let below = _mm256_cmpgt_epi32(min_vec, prices);
+            let above = _mm256_cmpgt_epi32(prices, max_vec);
+            let outside = _mm256_or_si256(below, above);
 
-            let mask_bits = _mm256_movemask_ps(_mm256_castsi256_ps(mask));
+            let outside_bits = _mm256_movemask_ps(_mm256_castsi256_ps(outside));
 
             // 마스크에 따라 선택적 복사
-            for i in 0..8 {
-                if mask_bits & (1 << i) != 0 {
-                    result.push(chunk[i]);
+            for (i, rec) in chunk.iter().enumerate() {
+                if outside_bits & (1 << i) == 0 {
+                    result.push(*rec);
                 }
             }
         }
 
         // 나머지 스칼라 처리
-        for &rec in remainder {
-            if rec.close >= min_price && rec.close <= max_price {
-                result.push(rec);
+        result.extend(Self::filter_by_price_scalar(
+            remainder, min_price, max_price,
+        ));
+
+        result
+    }
+
+    /// `filter_by_price`의 컬럼 버전: 연속된 close 배열을 8개(NEON은 4개)씩 바로 로드
+    pub fn filter_columns(columns: &Columns, min_price: u32, max_price: u32) -> Vec<OHLCV> {
+        #[cfg(all(feature = "simd", target_arch = "x86_64"))]
+        if is_x86_feature_detected!("avx2") {
+            // SAFETY: AVX2 지원을 방금 확인함
+            return unsafe { Self::filter_columns_avx2(columns, min_price, max_price) };
+        }
+        #[cfg(all(feature = "simd", target_arch = "aarch64"))]
+        if is_aarch64_feature_detected!("neon") {
+            // SAFETY: NEON 지원을 방금 확인함
+            return unsafe { Self::filter_columns_neon(columns, min_price, max_price) };
+        }
+
+        Self::filter_columns_scalar(columns, min_price, max_price, 0)
+    }
+
+    fn filter_columns_scalar(
+        columns: &Columns,
+        min_price: u32,
+        max_price: u32,
+        from: usize,
+    ) -> Vec<OHLCV> {
+        (from..columns.len())
+            .filter(|&i| (min_price..=max_price).contains(&columns.close[i]))
+            .map(|i| columns.record(i))
+            .collect()
+    }
+
+    #[cfg(all(feature = "simd", target_arch = "x86_64"))]
+    #[target_feature(enable = "avx2")]
+    unsafe fn filter_columns_avx2(columns: &Columns, min_price: u32, max_price: u32) -> Vec<OHLCV> {
+        use std::arch::x86_64::*;
+
+        let mut result = Vec::with_capacity(columns.len());
+
+        let bias = _mm256_set1_epi32(i32::MIN);
+        let min_vec = _mm256_set1_epi32((min_price ^ 0x8000_0000) as i32);
+        let max_vec = _mm256_set1_epi32((max_price ^ 0x8000_0000) as i32);
+
+        let chunks = columns.close.chunks_exact(8);
+        let tail = columns.len() - chunks.remainder().len();
+        for (c, chunk) in chunks.enumerate() {
+            // SAFETY: chunk는 u32 8개(32바이트), loadu는 정렬 불필요
+            let prices = unsafe { _mm256_loadu_si256(chunk.as_ptr() as *const __m256i) };
+            let prices = _mm256_xor_si256(prices, bias);
+
+            let below = _mm256_cmpgt_epi32(min_vec, prices);
+            let above = _mm256_cmpgt_epi32(prices, max_vec);
+            let outside = _mm256_or_si256(below, above);
+            let outside_bits = _mm256_movemask_ps(_mm256_castsi256_ps(outside));
+
+            for i in 0..8 {
+                if outside_bits & (1 << i) == 0 {
+                    result.push(columns.record(c * 8 + i));
+                }
+            }
+        }
+
+        result.extend(Self::filter_columns_scalar(
+            columns, min_price, max_price, tail,
+        ));
+        result
+    }
+
+    /// close가 [min_price, max_price] (양끝 포함)인 레코드 수
+    pub fn count_in_range(records: &[OHLCV], min_price: u32, max_price: u32) -> usize {
+        #[cfg(all(feature = "simd", target_arch = "x86_64"))]
+        if is_x86_feature_detected!("avx2") {
+            // SAFETY: AVX2 지원을 방금 확인함
+            return unsafe { Self::count_in_range_avx2(records, min_price, max_price) };
+        }
+        #[cfg(all(feature = "simd", target_arch = "aarch64"))]
+        if is_aarch64_feature_detected!("neon") {
+            // SAFETY: NEON 지원을 방금 확인함
+            return unsafe { Self::count_in_range_neon(records, min_price, max_price) };
+        }
+
+        Self::count_in_range_scalar(records, min_price, max_price)
+    }
+
+    /// 가격 필드의 (최솟값, 최댓값), 빈 입력이면 (u32::MAX, 0)
+    pub fn minmax(records: &[OHLCV], field: PriceField) -> (u32, u32) {
+        #[cfg(all(feature = "simd", target_arch = "x86_64"))]
+        if is_x86_feature_detected!("avx2") {
+            // SAFETY: AVX2 지원을 방금 확인함
+            return unsafe { Self::minmax_avx2(records, field) };
+        }
+
+        Self::minmax_scalar(records, field)
+    }
+
+    /// `total_volume` 합계
+    pub fn sum_volume(records: &[OHLCV]) -> u64 {
+        #[cfg(all(feature = "simd", target_arch = "x86_64"))]
+        if is_x86_feature_detected!("avx2") {
+            // SAFETY: AVX2 지원을 방금 확인함
+            return unsafe { Self::sum_volume_avx2(records) };
+        }
+
+        Self::sum_volume_scalar(records)
+    }
+
+    pub(crate) fn count_in_range_scalar(
+        records: &[OHLCV],
+        min_price: u32,
+        max_price: u32,
+    ) -> usize {
+        records
+            .iter()
+            .filter(|rec| (min_price..=max_price).contains(&{ rec.close }))
+            .count()
+    }
+
+    pub(crate) fn minmax_scalar(records: &[OHLCV], field: PriceField) -> (u32, u32) {
+        records
+            .iter()
+            .map(|rec| rec.price(field))
+            .fold((u32::MAX, u32::MIN), |(min, max), v| {
+                (min.min(v), max.max(v))
+            })
+    }
+
+    pub(crate) fn sum_volume_scalar(records: &[OHLCV]) -> u64 {
+        records.iter().map(OHLCV::total_volume).sum()
+    }
+
+    #[cfg(all(feature = "simd", target_arch = "x86_64"))]
+    #[target_feature(enable = "avx2")]
+    unsafe fn count_in_range_avx2(records: &[OHLCV], min_price: u32, max_price: u32) -> usize {
+        use std::arch::x86_64::*;
+
+        let chunks = records.chunks_exact(8);
+        let remainder = chunks.remainder();
+
+        let bias = _mm256_set1_epi32(i32::MIN);
+        let min_vec = _mm256_set1_epi32((min_price ^ 0x8000_0000) as i32);
+        let max_vec = _mm256_set1_epi32((max_price ^ 0x8000_0000) as i32);
+        let offset = std::mem::offset_of!(OHLCV, close);
+
+        let mut outside = 0;
+        for chunk in chunks {
+            // SAFETY: chunk는 레코드 8개, offset은 u32 필드 위치
+            let prices = _mm256_xor_si256(unsafe { gather_u32(chunk, offset) }, bias);
+            let below = _mm256_cmpgt_epi32(min_vec, prices);
+            let above = _mm256_cmpgt_epi32(prices, max_vec);
+            let mask = _mm256_or_si256(below, above);
+            outside += _mm256_movemask_ps(_mm256_castsi256_ps(mask)).count_ones() as usize;
+        }
+
+        records.len() - remainder.len() - outside
+            + Self::count_in_range_scalar(remainder, min_price, max_price)
+    }
+
+    #[cfg(all(feature = "simd", target_arch = "aarch64"))]
+    #[target_feature(enable = "neon")]
+    unsafe fn filter_by_price_neon(
+        records: &[OHLCV],
+        min_price: u32,
+        max_price: u32,
+    ) -> Vec<OHLCV> {
+        use std::arch::aarch64::*;
+
+        let mut result = Vec::with_capacity(records.len());
+
+        // 4개씩 SIMD 처리 (NEON은 부호 없는 비교가 있어 부호 비트 변환 불필요)
+        let chunks = records.chunks_exact(4);
+        let remainder = chunks.remainder();
+        let min_vec = vdupq_n_u32(min_price);
+        let max_vec = vdupq_n_u32(max_price);
+
+        for chunk in chunks {
+            let closes = [
+                chunk[0].close,
+                chunk[1].close,
+                chunk[2].close,
+                chunk[3].close,
+            ];
+            // SAFETY: u32 4개 배열에서 로드
+            let prices = unsafe { vld1q_u32(closes.as_ptr()) };
+            let inside = vandq_u32(vcgeq_u32(prices, min_vec), vcleq_u32(prices, max_vec));
+
+            let mut lanes = [0u32; 4];
+            // SAFETY: u32 4개 배열에 저장
+            unsafe { vst1q_u32(lanes.as_mut_ptr(), inside) };
+            for (rec, lane) in chunk.iter().zip(lanes) {
+                if lane != 0 {
+                    result.push(*rec);
+                }
+            }
+        }
+
+        result.extend(Self::filter_by_price_scalar(
+            remainder, min_price, max_price,
+        ));
+        result
+    }
+
+    #[cfg(all(feature = "simd", target_arch = "aarch64"))]
+    #[target_feature(enable = "neon")]
+    unsafe fn filter_columns_neon(columns: &Columns, min_price: u32, max_price: u32) -> Vec<OHLCV> {
+        use std::arch::aarch64::*;
+
+        let mut result = Vec::with_capacity(columns.len());
+        let min_vec = vdupq_n_u32(min_price);
+        let max_vec = vdupq_n_u32(max_price);
+
+        let chunks = columns.close.chunks_exact(4);
+        let tail = columns.len() - chunks.remainder().len();
+        for (c, chunk) in chunks.enumerate() {
+            // SAFETY: chunk는 u32 4개(16바이트), vld1q는 정렬 불필요
+            let prices = unsafe { vld1q_u32(chunk.as_ptr()) };
+            let inside = vandq_u32(vcgeq_u32(prices, min_vec), vcleq_u32(prices, max_vec));
+
+            let mut lanes = [0u32; 4];
+            // SAFETY: u32 4개 배열에 저장
+            unsafe { vst1q_u32(lanes.as_mut_ptr(), inside) };
+            for (i, lane) in lanes.into_iter().enumerate() {
+                if lane != 0 {
+                    result.push(columns.record(c * 4 + i));
+                }
             }
         }
 
+        result.extend(Self::filter_columns_scalar(
+            columns, min_price, max_price, tail,
+        ));
         result
     }
+
+    #[cfg(all(feature = "simd", target_arch = "aarch64"))]
+    #[target_feature(enable = "neon")]
+    unsafe fn count_in_range_neon(records: &[OHLCV], min_price: u32, max_price: u32) -> usize {
+        use std::arch::aarch64::*;
+
+        let chunks = records.chunks_exact(4);
+        let remainder = chunks.remainder();
+        let min_vec = vdupq_n_u32(min_price);
+        let max_vec = vdupq_n_u32(max_price);
+
+        // 범위 안 레인은 u32::MAX(-1)이므로 빼면 1씩 늘어남
+        let mut counts = vdupq_n_u32(0);
+        for chunk in chunks {
+            let closes = [
+                chunk[0].close,
+                chunk[1].close,
+                chunk[2].close,
+                chunk[3].close,
+            ];
+            // SAFETY: u32 4개 배열에서 로드
+            let prices = unsafe { vld1q_u32(closes.as_ptr()) };
+            let inside = vandq_u32(vcgeq_u32(prices, min_vec), vcleq_u32(prices, max_vec));
+            counts = vsubq_u32(counts, inside);
+        }
+
+        let mut lanes = [0u32; 4];
+        // SAFETY: u32 4개 배열에 저장
+        unsafe { vst1q_u32(lanes.as_mut_ptr(), counts) };
+        lanes.iter().map(|&n| n as usize).sum::<usize>()
+            + Self::count_in_range_scalar(remainder, min_price, max_price)
+    }
+
+    #[cfg(all(feature = "simd", target_arch = "x86_64"))]
+    #[target_feature(enable = "avx2")]
+    unsafe fn minmax_avx2(records: &[OHLCV], field: PriceField) -> (u32, u32) {
+        use std::arch::x86_64::*;
+
+        let chunks = records.chunks_exact(8);
+        let remainder = chunks.remainder();
+        let offset = field.offset();
+
+        let mut min_vec = _mm256_set1_epi32(-1);
+        let mut max_vec = _mm256_setzero_si256();
+        for chunk in chunks {
+            // SAFETY: chunk는 레코드 8개, offset은 u32 필드 위치
+            let values = unsafe { gather_u32(chunk, offset) };
+            min_vec = _mm256_min_epu32(min_vec, values);
+            max_vec = _mm256_max_epu32(max_vec, values);
+        }
+
+        let (mut mins, mut maxs) = ([0u32; 8], [0u32; 8]);
+        // SAFETY: 32바이트 배열에 비정렬 저장
+        unsafe {
+            _mm256_storeu_si256(mins.as_mut_ptr().cast(), min_vec);
+            _mm256_storeu_si256(maxs.as_mut_ptr().cast(), max_vec);
+        }
+
+        let (rem_min, rem_max) = Self::minmax_scalar(remainder, field);
+        (
+            mins.into_iter().fold(rem_min, u32::min),
+            maxs.into_iter().fold(rem_max, u32::max),
+        )
+    }
+
+    #[cfg(all(feature = "simd", target_arch = "x86_64"))]
+    #[target_feature(enable = "avx2")]
+    unsafe fn sum_volume_avx2(records: &[OHLCV]) -> u64 {
+        use std::arch::x86_64::*;
+
+        let chunks = records.chunks_exact(8);
+        let remainder = chunks.remainder();
+        let (lo_offset, hi_offset) = (
+            std::mem::offset_of!(OHLCV, volume),
+            std::mem::offset_of!(OHLCV, volume_hi),
+        );
+
+        // u32 8개를 u64 4개씩 두 번 더함 (레인당 2^32개 청크까지 오버플로 없음)
+        let widen_add = |acc: __m256i, values: __m256i| {
+            let low = _mm256_cvtepu32_epi64(_mm256_castsi256_si128(values));
+            let high = _mm256_cvtepu32_epi64(_mm256_extracti128_si256::<1>(values));
+            _mm256_add_epi64(acc, _mm256_add_epi64(low, high))
+        };
+
+        let mut lo_sum = _mm256_setzero_si256();
+        let mut hi_sum = _mm256_setzero_si256();
+        for chunk in chunks {
+            // SAFETY: chunk는 레코드 8개, offset은 u32 필드 위치
+            let (lo, hi) = unsafe { (gather_u32(chunk, lo_offset), gather_u32(chunk, hi_offset)) };
+            lo_sum = widen_add(lo_sum, lo);
+            hi_sum = widen_add(hi_sum, hi);
+        }
+
+        let (mut lo_lanes, mut hi_lanes) = ([0u64; 4], [0u64; 4]);
+        // SAFETY: 32바이트 배열에 비정렬 저장
+        unsafe {
+            _mm256_storeu_si256(lo_lanes.as_mut_ptr().cast(), lo_sum);
+            _mm256_storeu_si256(hi_lanes.as_mut_ptr().cast(), hi_sum);
+        }
+
+        let lo: u64 = lo_lanes.iter().sum();
+        let hi: u64 = hi_lanes.iter().sum();
+        lo.wrapping_add(hi << 32) + Self::sum_volume_scalar(remainder)
+    }
+}
+
+/// 레코드 8개에서 byte_offset 위치의 u32 필드를 모음
+///
+/// # Safety
+/// AVX2 필요, chunk는 레코드 8개 이상, byte_offset은 레코드 내 u32 필드 위치
+#[cfg(all(feature = "simd", target_arch = "x86_64"))]
+#[target_feature(enable = "avx2")]
+unsafe fn gather_u32(chunk: &[OHLCV], byte_offset: usize) -> std::arch::x86_64::__m256i {
+    use std::arch::x86_64::*;
+
+    const STRIDE: i32 = std::mem::size_of::<OHLCV>() as i32;
+    let index = _mm256_setr_epi32(
+        0,
+        STRIDE,
+        2 * STRIDE,
+        3 * STRIDE,
+        4 * STRIDE,
+        5 * STRIDE,
+        6 * STRIDE,
+        7 * STRIDE,
+    );
+    // SAFETY: 8개 레코드 범위 안의 4바이트 읽기 (gather는 정렬 요구 없음)
+    unsafe {
+        let base = chunk.as_ptr().cast::<u8>().add(byte_offset);
+        _mm256_i32gather_epi32::<1>(base.cast(), index)
+    }
 }
 
 /// 이동평균 등 기술적 지표
 pub struct TechnicalIndicators;
 
 impl TechnicalIndicators {
-    pub fn sma(records: &[OHLCV], period: usize) -> Vec<f64> {
+    /// 종가 단순이동평균 (`scale`은 심볼의 가격 배율)
+    pub fn sma(records: &[OHLCV], period: usize, scale: u32) -> Vec<f64> {
         if records.len() < period {
             return vec![];
         }
 
         let mut result = Vec::with_capacity(records.len() - period + 1);
         let mut sum = 0u64;
+        let divisor = period as f64 * f64::from(scale);
 
         // 초기 윈도우
-        for i in 0..period {
-            sum += records[i].close as u64;
+        for rec in &records[..period] {
+            sum += rec.close as u64;
         }
-        result.push(sum as f64 / period as f64 / 100000.0);
+        result.push(sum as f64 / divisor);
 
         // 슬라이딩 윈도우
         for i in period..records.len() {
             sum = sum - records[i - period].close as u64 + records[i].close as u64;
-            result.push(sum as f64 / period as f64 / 100000.0);
+            result.push(sum as f64 / divisor);
+        }
+
+        result
+    }
+
+    /// 롤링 피어슨 상관계수 (누적합 기반 O(n))
+    ///
+    /// 결과 i는 입력 구간 `[i, i + window)`에 대응 (warm-up offset = window - 1).
+    /// 분산이 0인 구간은 NaN.
+    pub fn rolling_correlation(a: &[f64], b: &[f64], window: usize) -> Vec<f64> {
+        let n = a.len().min(b.len());
+        if window < 2 || n < window {
+            return vec![];
+        }
+
+        let (mut sa, mut sb, mut saa, mut sbb, mut sab) = (0.0, 0.0, 0.0, 0.0, 0.0);
+        let mut result = Vec::with_capacity(n - window + 1);
+        let w = window as f64;
+
+        for i in 0..n {
+            let (x, y) = (a[i], b[i]);
+            sa += x;
+            sb += y;
+            saa += x * x;
+            sbb += y * y;
+            sab += x * y;
+
+            if i >= window {
+                let (x, y) = (a[i - window], b[i - window]);
+                sa -= x;
+                sb -= y;
+                saa -= x * x;
+                sbb -= y * y;
+                sab -= x * y;
+            }
+
+            if i + 1 >= window {
+                let var_a = saa - sa * sa / w;
+                let var_b = sbb - sb * sb / w;
+                let cov = sab - sa * sb / w;
+
+                // 누적합 반올림 오차를 감안해 상대 기준으로 0 분산 판정
+                let flat_a = var_a <= saa * 1e-12;
+                let flat_b = var_b <= sbb * 1e-12;
+                if flat_a || flat_b {
+                    result.push(f64::NAN);
+                } else {
+                    result.push((cov / (var_a * var_b).sqrt()).clamp(-1.0, 1.0));
+                }
+            }
+        }
+
+        result
+    }
+
+    /// 지수이동평균 (k = 2/(period+1)), 첫 값은 처음 period개의 SMA
+    ///
+    /// 입력과 같은 길이, 앞의 period-1개는 None
+    pub fn ema(
+        records: &[OHLCV],
+        field: PriceField,
+        period: usize,
+        scale: u32,
+    ) -> Vec<Option<f64>> {
+        ema_values(&field_values(records, field, scale), period)
+    }
+
+    /// Wilder 평활 RSI, 첫 값은 인덱스 period (처음 period개 변화량의 평균으로 시작)
+    ///
+    /// 상승/하락이 모두 0이면 50
+    pub fn rsi(records: &[OHLCV], field: PriceField, period: usize) -> Vec<Option<f64>> {
+        // 상승/하락 비율이라 가격 배율과 무관 (정수 가격 그대로)
+        let values = field_values(records, field, 1);
+        let mut result = vec![None; values.len()];
+        if period == 0 || values.len() <= period {
+            return result;
+        }
+
+        let rsi = |gain: f64, loss: f64| {
+            if loss == 0.0 {
+                if gain == 0.0 { 50.0 } else { 100.0 }
+            } else {
+                100.0 - 100.0 / (1.0 + gain / loss)
+            }
+        };
+
+        let (mut avg_gain, mut avg_loss) = (0.0, 0.0);
+        for i in 1..=period {
+            let change = values[i] - values[i - 1];
+            avg_gain += change.max(0.0);
+            avg_loss += (-change).max(0.0);
+        }
+        avg_gain /= period as f64;
+        avg_loss /= period as f64;
+        result[period] = Some(rsi(avg_gain, avg_loss));
+
+        let n = period as f64;
+        for i in period + 1..values.len() {
+            let change = values[i] - values[i - 1];
+            avg_gain = (avg_gain * (n - 1.0) + change.max(0.0)) / n;
+            avg_loss = (avg_loss * (n - 1.0) + (-change).max(0.0)) / n;
+            result[i] = Some(rsi(avg_gain, avg_loss));
+        }
+
+        result
+    }
+
+    /// MACD (기본 12/26/9): macd = EMA(fast) - EMA(slow), signal = macd의 EMA(signal)
+    ///
+    /// macd는 인덱스 slow-1부터, signal/histogram은 slow+signal-2부터 값이 있음
+    pub fn macd(
+        records: &[OHLCV],
+        field: PriceField,
+        fast: usize,
+        slow: usize,
+        signal: usize,
+        scale: u32,
+    ) -> Macd {
+        let values = field_values(records, field, scale);
+        let fast_ema = ema_values(&values, fast);
+        let slow_ema = ema_values(&values, slow);
+
+        let macd: Vec<Option<f64>> = fast_ema
+            .iter()
+            .zip(&slow_ema)
+            .map(|(f, s)| Some((*f)? - (*s)?))
+            .collect();
+
+        // signal은 macd가 정의된 구간에서만 계산
+        let first = macd.iter().position(Option::is_some).unwrap_or(macd.len());
+        let defined: Vec<f64> = macd[first..].iter().flatten().copied().collect();
+        let mut signal_line = vec![None; first];
+        signal_line.extend(ema_values(&defined, signal));
+
+        let histogram = macd
+            .iter()
+            .zip(&signal_line)
+            .map(|(m, s)| Some((*m)? - (*s)?))
+            .collect();
+
+        Macd {
+            macd,
+            signal: signal_line,
+            histogram,
+        }
+    }
+
+    /// 볼린저 밴드: SMA(period) ± k·σ (모표준편차)
+    pub fn bollinger(
+        records: &[OHLCV],
+        field: PriceField,
+        period: usize,
+        k: f64,
+        scale: u32,
+    ) -> BollingerBands {
+        let values = field_values(records, field, scale);
+        let mut bands = BollingerBands {
+            middle: vec![None; values.len()],
+            upper: vec![None; values.len()],
+            lower: vec![None; values.len()],
+        };
+        if period == 0 || values.len() < period {
+            return bands;
+        }
+
+        for end in period..=values.len() {
+            let window = &values[end - period..end];
+            let mean = window.iter().sum::<f64>() / period as f64;
+            let variance = window.iter().map(|v| (v - mean).powi(2)).sum::<f64>() / period as f64;
+            let sd = variance.sqrt();
+
+            bands.middle[end - 1] = Some(mean);
+            bands.upper[end - 1] = Some(mean + k * sd);
+            bands.lower[end - 1] = Some(mean - k * sd);
+        }
+
+        bands
+    }
+
+    /// Wilder ATR: 첫 값은 인덱스 period (TR[1..=period]의 평균)
+    pub fn atr(records: &[OHLCV], period: usize, scale: u32) -> Vec<Option<f64>> {
+        let mut result = vec![None; records.len()];
+        if period == 0 || records.len() <= period {
+            return result;
+        }
+
+        let true_range = |i: usize| {
+            let high = records[i].price_f64(PriceField::High, scale);
+            let low = records[i].price_f64(PriceField::Low, scale);
+            let prev_close = records[i - 1].price_f64(PriceField::Close, scale);
+            (high - low)
+                .max((high - prev_close).abs())
+                .max((low - prev_close).abs())
+        };
+
+        let n = period as f64;
+        let mut atr = (1..=period).map(true_range).sum::<f64>() / n;
+        result[period] = Some(atr);
+
+        for (i, slot) in result.iter_mut().enumerate().skip(period + 1) {
+            atr = (atr * (n - 1.0) + true_range(i)) / n;
+            *slot = Some(atr);
+        }
+
+        result
+    }
+
+    /// 세션 VWAP: Σ(typical·volume) / Σvolume, typical = (H+L+C)/3
+    ///
+    /// session_reset 시각(UTC)을 지날 때마다 누적 초기화 (None이면 전체 누적).
+    /// 세션 누적 거래량이 0이면 세션 내 typical 단순 평균으로 대체
+    pub fn vwap(records: &[OHLCV], session_reset: Option<NaiveTime>, scale: u32) -> Vec<f64> {
+        const DAY_NANOS: u64 = 86_400 * 1_000_000_000;
+        let reset_nanos =
+            session_reset.map(|t| t.num_seconds_from_midnight() as u64 * 1_000_000_000);
+
+        let mut result = Vec::with_capacity(records.len());
+        let mut session = None;
+        let (mut pv, mut volume, mut price_sum, mut count) = (0.0, 0u64, 0.0, 0u64);
+
+        for rec in records {
+            // 세션 번호 = reset 시각 기준으로 민 날짜
+            let current = reset_nanos.map(|reset| (rec.ts + DAY_NANOS - reset) / DAY_NANOS);
+            if current != session {
+                session = current;
+                (pv, volume, price_sum, count) = (0.0, 0, 0.0, 0);
+            }
+
+            let typical = (rec.price_f64(PriceField::High, scale)
+                + rec.price_f64(PriceField::Low, scale)
+                + rec.price_f64(PriceField::Close, scale))
+                / 3.0;
+            pv += typical * rec.total_volume() as f64;
+            volume += rec.total_volume();
+            price_sum += typical;
+            count += 1;
+
+            result.push(if volume > 0 {
+                pv / volume as f64
+            } else {
+                price_sum / count as f64
+            });
+        }
+
+        result
+    }
+
+    /// 종가 로그 수익률 ln(close_i / close_{i-1}), 길이는 입력 - 1
+    ///
+    /// 가격이 0인 쌍은 NaN/무한대 대신 0.0
+    pub fn log_returns(records: &[OHLCV]) -> Vec<f64> {
+        log_returns_checked(records)
+            .into_iter()
+            .map(Option::unwrap_or_default)
+            .collect()
+    }
+
+    /// 로그 수익률 표본표준편차 × √bars_per_year (슬라이딩 Welford, 단일 패스)
+    ///
+    /// 결과 i는 수익률 `(i-window, i]` 구간, 첫 값은 인덱스 window.
+    /// 연율화하지 않으려면 bars_per_year = 1.0, 가격이 0인 봉이 걸친 구간은 None
+    pub fn rolling_volatility(
+        records: &[OHLCV],
+        window: usize,
+        bars_per_year: f64,
+    ) -> Vec<Option<f64>> {
+        let mut result = vec![None; records.len()];
+        if window < 2 || records.len() <= window {
+            return result;
+        }
+
+        // returns[i - 1]이 봉 i의 수익률
+        let returns = log_returns_checked(records);
+        let log_return = |i: usize| returns[i - 1].unwrap_or_default();
+        let annualize = bars_per_year.sqrt();
+
+        let (mut n, mut mean, mut m2) = (0.0, 0.0, 0.0);
+        let mut invalid = 0;
+        for (i, slot) in result.iter_mut().enumerate().skip(1) {
+            let x = log_return(i);
+            invalid += usize::from(returns[i - 1].is_none());
+            n += 1.0;
+            let delta = x - mean;
+            mean += delta / n;
+            m2 += delta * (x - mean);
+
+            if i > window {
+                // 윈도우 밖으로 나간 수익률 제거
+                let y = log_return(i - window);
+                invalid -= usize::from(returns[i - window - 1].is_none());
+                let old_mean = mean;
+                n -= 1.0;
+                mean -= (y - mean) / n;
+                m2 -= (y - old_mean) * (y - mean);
+            }
+
+            if i >= window && invalid == 0 {
+                let variance = (m2 / (n - 1.0)).max(0.0);
+                *slot = Some(variance.sqrt() * annualize);
+            }
         }
 
         result
     }
+}
+
+/// `TechnicalIndicators::macd` 결과 (입력과 같은 길이)
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct Macd {
+    pub macd: Vec<Option<f64>>,
+    pub signal: Vec<Option<f64>>,
+    pub histogram: Vec<Option<f64>>,
+}
+
+/// `TechnicalIndicators::bollinger` 결과 (입력과 같은 길이)
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct BollingerBands {
+    pub middle: Vec<Option<f64>>,
+    pub upper: Vec<Option<f64>>,
+    pub lower: Vec<Option<f64>>,
+}
+
+/// 종가 로그 수익률 (가격이 0인 쌍은 None)
+fn log_returns_checked(records: &[OHLCV]) -> Vec<Option<f64>> {
+    records
+        .windows(2)
+        .map(|w| {
+            let (prev, cur) = (w[0].close, w[1].close);
+            (prev > 0 && cur > 0).then(|| (f64::from(cur) / f64::from(prev)).ln())
+        })
+        .collect()
+}
+
+fn field_values(records: &[OHLCV], field: PriceField, scale: u32) -> Vec<f64> {
+    records
+        .iter()
+        .map(|rec| rec.price_f64(field, scale))
+        .collect()
+}
+
+fn ema_values(values: &[f64], period: usize) -> Vec<Option<f64>> {
+    let mut result = vec![None; values.len()];
+    if period == 0 || values.len() < period {
+        return result;
+    }
+
+    let k = 2.0 / (period as f64 + 1.0);
+    let mut ema = values[..period].iter().sum::<f64>() / period as f64;
+    result[period - 1] = Some(ema);
+
+    for i in period..values.len() {
+        ema += k * (values[i] - ema);
+        result[i] = Some(ema);
+    }
+
+    result
+}
+
+/// "1m", "15m", "1h", "4h", "1d" 형식의 봉 간격 → 초
+pub fn parse_interval(interval: &str) -> Option<u64> {
+    let split = interval.find(|c: char| !c.is_ascii_digit())?;
+    let (count, unit) = interval.split_at(split);
+    let count: u64 = count.parse().ok().filter(|&n| n > 0)?;
+
+    let unit_secs = match unit {
+        "s" => 1,
+        "m" => 60,
+        "h" => 3600,
+        "d" => 86400,
+        "w" => 7 * 86400,
+        _ => return None,
+    };
+    Some(count * unit_secs)
+}
+
+/// 차트 봉 간격 후보 (초), 작은 것부터
+const CHART_INTERVALS: [u64; 8] = [60, 300, 900, 1800, 3600, 4 * 3600, 86400, 7 * 86400];
+
+/// span_secs 길이 범위를 max_points개 이하 봉으로 그릴 수 있는 가장 작은 간격 (초)
+///
+/// 경계에 걸친 봉까지 세어 `span / 간격`을 올림한 값 + 1개를 넘지 않게 고름.
+/// 1주로도 넘치면 주 단위 배수. max_points는 2 이상
+pub fn chart_interval(span_secs: u64, max_points: usize) -> u64 {
+    const WEEK: u64 = 7 * 86400;
+    let max_buckets = (max_points.max(2) - 1) as u64;
+    CHART_INTERVALS
+        .into_iter()
+        .find(|&interval| span_secs.div_ceil(interval) <= max_buckets)
+        .unwrap_or_else(|| span_secs.div_ceil(WEEK * max_buckets).max(1) * WEEK)
+}
+
+/// 초 단위 간격을 `parse_interval` 형식으로 ("15m", "4h", "1d", "2w", 나누어떨어지지 않으면 "90s")
+pub fn format_interval(secs: u64) -> String {
+    [(7 * 86400, "w"), (86400, "d"), (3600, "h"), (60, "m")]
+        .into_iter()
+        .find(|&(unit, _)| secs >= unit && secs.is_multiple_of(unit))
+        .map_or_else(
+            || format!("{}s", secs),
+            |(unit, suffix)| format!("{}{}", secs / unit, suffix),
+        )
+}
+
+/// 틱 → 1분봉 스트리밍 집계
+///
+/// 분 m의 봉은 `m + 1분 + tolerance` 이후의 틱이 들어오거나 `flush`할 때 완성됨.
+/// 그 사이 늦게 도착한 틱은 ts 순서대로 반영하고, 이미 내보낸 분의 틱은 버리고 집계.
+/// 봉의 spread는 bid/ask가 모두 있는 틱들의 평균 (호가 틱이 없으면 0)
+#[derive(Default)]
+pub struct TickAggregator {
+    tolerance: u64,
+    /// 분 시작 ts → 집계 중인 봉
+    pending: BTreeMap<u64, PendingBar>,
+    /// 이 ts 이전(미포함)의 분은 이미 내보냄
+    emitted_until: u64,
+    max_ts: u64,
+    late_ticks: u64,
+}
+
+/// 집계 중인 1분봉과 open/close/spread를 정하는 데 필요한 상태
+struct PendingBar {
+    bar: OHLCV,
+    first_ts: u64,
+    last_ts: u64,
+    spread_sum: u64,
+    quotes: u64,
+}
+
+impl PendingBar {
+    fn finish(mut self) -> OHLCV {
+        self.bar.spread = mean_spread(self.spread_sum, self.quotes);
+        self.bar
+    }
+}
+
+/// 스프레드 합 / 개수 (반올림, 개수가 0이면 미기록인 0)
+fn mean_spread(sum: u64, count: u64) -> u16 {
+    (sum + count / 2)
+        .checked_div(count)
+        .map_or(0, |mean| mean.min(u64::from(u16::MAX)) as u16)
+}
+
+impl TickAggregator {
+    const MINUTE: u64 = 60_000_000_000;
+
+    /// tolerance_nanos: 분이 바뀐 뒤에도 이전 분의 틱을 받아주는 시간
+    pub fn new(tolerance_nanos: u64) -> Self {
+        Self {
+            tolerance: tolerance_nanos,
+            ..Default::default()
+        }
+    }
+
+    /// 이미 내보낸 분에 속하는 틱인지
+    pub fn is_late(&self, tick: &Tick) -> bool {
+        tick.ts < self.emitted_until
+    }
+
+    /// 지금까지 버린 늦은 틱 수
+    pub fn late_ticks(&self) -> u64 {
+        self.late_ticks
+    }
+
+    /// 틱 반영 후 완성된 봉 반환 (시간순)
+    pub fn push(&mut self, tick: &Tick) -> Vec<OHLCV> {
+        if self.is_late(tick) {
+            self.late_ticks += 1;
+            return Vec::new();
+        }
+
+        let minute = tick.ts - tick.ts % Self::MINUTE;
+        let price = tick.price();
+        let pending = self.pending.entry(minute).or_insert_with(|| PendingBar {
+            bar: OHLCV {
+                ts: minute,
+                open: price,
+                high: price,
+                low: price,
+                close: price,
+                symbol_id: tick.symbol_id,
+                ..Default::default()
+            },
+            first_ts: tick.ts,
+            last_ts: tick.ts,
+            spread_sum: 0,
+            quotes: 0,
+        });
+
+        let bar = &mut pending.bar;
+        bar.high = bar.high.max(price);
+        bar.low = bar.low.min(price);
+        bar.set_total_volume(bar.total_volume() + u64::from(tick.volume));
+        if tick.ts < pending.first_ts {
+            pending.first_ts = tick.ts;
+            bar.open = price;
+        }
+        if tick.ts >= pending.last_ts {
+            pending.last_ts = tick.ts;
+            bar.close = price;
+        }
+        // 체결가만 있는 틱은 스프레드를 모름
+        if tick.bid != 0 && tick.ask != 0 {
+            pending.spread_sum += u64::from(tick.spread());
+            pending.quotes += 1;
+        }
+
+        self.max_ts = self.max_ts.max(tick.ts);
+        let watermark = self.max_ts.saturating_sub(self.tolerance);
+        self.emit_before(watermark - watermark % Self::MINUTE)
+    }
+
+    /// 진행 중인 봉을 모두 완성
+    pub fn flush(&mut self) -> Vec<OHLCV> {
+        match self.pending.last_key_value() {
+            Some((&minute, _)) => self.emit_before(minute + Self::MINUTE),
+            None => Vec::new(),
+        }
+    }
+
+    fn emit_before(&mut self, until: u64) -> Vec<OHLCV> {
+        if until <= self.emitted_until {
+            return Vec::new();
+        }
+        self.emitted_until = until;
+        let open = self.pending.split_off(&until);
+        std::mem::replace(&mut self.pending, open)
+            .into_values()
+            .map(PendingBar::finish)
+            .collect()
+    }
+}
+
+/// 리샘플 버킷 경계 기준
+///
+/// 경계는 `tz` 현지 시각으로 1970-01-01 00:00 + `session_offset_secs`부터 interval마다 두고,
+/// 봉 시각은 그 경계를 UTC로 되돌린 값. 뉴욕 17:00 롤오버 일봉은
+/// `tz = America/New_York`, `session_offset_secs = 17 * 3600` (일광 절약 시간 반영)
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct Alignment {
+    /// 경계를 계산할 시간대 (None이면 UTC)
+    pub tz: Option<Tz>,
+    /// 현지 자정 기준 경계 이동 (초, 음수 가능)
+    pub session_offset_secs: i64,
+}
+
+impl Alignment {
+    /// UTC 자정 기준 (기본값)
+    pub const UTC: Alignment = Alignment {
+        tz: None,
+        session_offset_secs: 0,
+    };
+
+    /// ts(ns)가 속한 interval_secs 버킷의 시작 시각 (ns)
+    fn bucket_start(&self, ts: u64, interval_secs: u64) -> u64 {
+        const NANOS: u64 = 1_000_000_000;
+        let interval = interval_secs.max(1) as i64;
+        let offset = self.session_offset_secs;
+        let floor = |secs: i64| (secs - offset).div_euclid(interval) * interval + offset;
+        let secs = (ts / NANOS) as i64;
+
+        let start = match self.tz {
+            None => floor(secs),
+            Some(tz) => {
+                let utc = DateTime::from_timestamp(secs, 0).unwrap_or_default();
+                let local_offset = i64::from(
+                    tz.offset_from_utc_datetime(&utc.naive_utc())
+                        .fix()
+                        .local_minus_utc(),
+                );
+                let local_start = floor(secs + local_offset);
+                let naive = DateTime::from_timestamp(local_start, 0).map(|dt| dt.naive_utc());
+                match naive.map(|naive| tz.from_local_datetime(&naive)) {
+                    Some(LocalResult::Single(start)) => start.timestamp(),
+                    // 서머타임 해제로 두 번 오는 현지 시각: ts를 넘지 않는 쪽
+                    Some(LocalResult::Ambiguous(early, late)) => if late.timestamp() <= secs {
+                        late
+                    } else {
+                        early
+                    }
+                    .timestamp(),
+                    // 서머타임 시작으로 건너뛴 현지 시각: ts의 오프셋으로 환산
+                    _ => local_start - local_offset,
+                }
+            }
+        };
+        (start.max(0) as u64).saturating_mul(NANOS)
+    }
+}
+
+/// 시간순 레코드를 interval_secs 단위 봉으로 리샘플 (빈 슬롯은 무시)
+///
+/// 버킷 경계는 `alignment` 기준. 거래량은 `total_volume` 기준 64비트로 합산하고,
+/// spread는 스프레드가 기록된(0이 아닌) 봉들의 평균
+pub fn resample(records: &[OHLCV], interval_secs: u64, alignment: Alignment) -> Vec<OHLCV> {
+    let mut result: Vec<OHLCV> = Vec::new();
+    // 현재 버킷의 (스프레드 합, 스프레드가 있는 봉 수)
+    let mut spreads = (0u64, 0u64);
+
+    for rec in records.iter().filter(|rec| rec.ts != 0) {
+        let bucket_ts = alignment.bucket_start(rec.ts, interval_secs);
+
+        match result.last_mut() {
+            Some(bar) if bar.ts == bucket_ts => {
+                bar.high = bar.high.max(rec.high);
+                bar.low = bar.low.min(rec.low);
+                bar.close = rec.close;
+                // 1분봉 1440개 합은 u32를 넘을 수 있으므로 64비트로 누적
+                bar.set_total_volume(bar.total_volume().saturating_add(rec.total_volume()));
+            }
+            last => {
+                if let Some(bar) = last {
+                    bar.spread = mean_spread(spreads.0, spreads.1);
+                }
+                spreads = (0, 0);
+                result.push(OHLCV {
+                    ts: bucket_ts,
+                    ..*rec
+                });
+            }
+        }
+        if rec.spread != 0 {
+            spreads.0 += u64::from(rec.spread);
+            spreads.1 += 1;
+        }
+    }
+    if let Some(bar) = result.last_mut() {
+        bar.spread = mean_spread(spreads.0, spreads.1);
+    }
+
+    result
+}
+
+/// Largest-Triangle-Three-Buckets로 close 시계열을 n개 봉으로 줄임 (차트용)
+///
+/// 첫/마지막 봉은 남기고 가운데를 n-2개 구간으로 나눠, 구간마다 직전에 고른 봉과
+/// 다음 구간 평균점이 이루는 삼각형이 가장 큰 봉 하나를 고름. 고른 봉은 원본 그대로라
+/// ts가 유지되고 튀는 값이 살아남음. 빈 슬롯은 무시, n < 3이거나 이미 n개 이하면 그대로
+pub fn downsample_lttb(records: &[OHLCV], n: usize) -> Vec<OHLCV> {
+    let points: Vec<OHLCV> = records.iter().filter(|rec| rec.ts != 0).copied().collect();
+    if n < 3 || points.len() <= n {
+        return points;
+    }
+
+    // ts를 첫 봉 기준으로 옮겨 f64 정밀도 유지
+    let origin = points[0].ts;
+    let xy = |rec: &OHLCV| ((rec.ts - origin) as f64, rec.close as f64);
+    // i번째 구간의 시작 인덱스 (구간 n-2개가 [1, len-1)을 나눔)
+    let bound = |i: usize| 1 + i * (points.len() - 2) / (n - 2);
+
+    let mut result = Vec::with_capacity(n);
+    result.push(points[0]);
+    let mut selected = 0;
+    for i in 0..n - 2 {
+        let next = &points[bound(i + 1)..bound(i + 2).min(points.len()).max(bound(i + 1) + 1)];
+        let (next_x, next_y) = next.iter().map(xy).fold((0.0, 0.0), |(sx, sy), (x, y)| {
+            (sx + x / next.len() as f64, sy + y / next.len() as f64)
+        });
+        let (ax, ay) = xy(&points[selected]);
+
+        let area = |rec: &OHLCV| {
+            let (bx, by) = xy(rec);
+            ((ax - next_x) * (by - ay) - (ax - bx) * (next_y - ay)).abs()
+        };
+        selected = (bound(i)..bound(i + 1))
+            .max_by(|&a, &b| area(&points[a]).total_cmp(&area(&points[b])))
+            .unwrap_or(bound(i));
+        result.push(points[selected]);
+    }
+    result.push(points[points.len() - 1]);
+    result
+}
+
+/// 하이킨 아시 봉 변환 (첫 봉의 open = (O+C)/2로 시작)
+///
+/// HA close = (O+H+L+C)/4, HA open = (직전 HA open + 직전 HA close)/2
+pub fn transform_heikin_ashi(records: &[OHLCV]) -> Vec<OHLCV> {
+    let mut result = Vec::with_capacity(records.len());
+    let mut prev: Option<(f64, f64)> = None;
+
+    for rec in records.iter().filter(|rec| rec.ts != 0) {
+        let (open, high, low, close) = (
+            rec.open as f64,
+            rec.high as f64,
+            rec.low as f64,
+            rec.close as f64,
+        );
+        let ha_close = (open + high + low + close) / 4.0;
+        let ha_open = match prev {
+            Some((prev_open, prev_close)) => (prev_open + prev_close) / 2.0,
+            None => (open + close) / 2.0,
+        };
+        prev = Some((ha_open, ha_close));
+
+        result.push(OHLCV {
+            open: ha_open.round() as u32,
+            high: high.max(ha_open).max(ha_close).round() as u32,
+            low: low.min(ha_open).min(ha_close).round() as u32,
+            close: ha_close.round() as u32,
+            ..*rec
+        });
+    }
+
+    result
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum RenkoDirection {
+    Up,
+    Down,
+}
+
+/// 렌코 벽돌 (가격은 OHLCV와 같은 스케일)
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct RenkoBrick {
+    pub open: u32,
+    pub close: u32,
+    /// 직전 벽돌이 완성된 봉 (첫 벽돌은 첫 봉)
+    pub ts_open: u64,
+    /// 이 벽돌을 완성시킨 봉
+    pub ts_close: u64,
+    pub direction: RenkoDirection,
+}
+
+/// 종가 기준 클래식 렌코 (첫 종가를 기준선으로 시작)
+///
+/// 같은 방향은 1칸, 반전은 2칸 이동해야 새 벽돌. 경계값과 정확히 같아도 벽돌 생성,
+/// 한 봉에서 여러 칸 움직이면 그만큼 벽돌을 연속으로 생성
+pub fn transform_renko(records: &[OHLCV], brick_size: u32) -> Vec<RenkoBrick> {
+    let mut bricks = Vec::new();
+    let mut records = records.iter().filter(|rec| rec.ts != 0);
+    let Some(first) = records.next() else {
+        return bricks;
+    };
+    if brick_size == 0 {
+        return bricks;
+    }
+
+    let brick = brick_size as i64;
+    let (mut top, mut bottom) = (first.close as i64, first.close as i64);
+    let mut last_ts = first.ts;
+
+    for rec in records {
+        let price = rec.close as i64;
+
+        while price >= top + brick {
+            bricks.push(RenkoBrick {
+                open: top as u32,
+                close: (top + brick) as u32,
+                ts_open: last_ts,
+                ts_close: rec.ts,
+                direction: RenkoDirection::Up,
+            });
+            (bottom, top) = (top, top + brick);
+            last_ts = rec.ts;
+        }
+
+        while price <= bottom - brick && bottom - brick >= 0 {
+            bricks.push(RenkoBrick {
+                open: bottom as u32,
+                close: (bottom - brick) as u32,
+                ts_open: last_ts,
+                ts_close: rec.ts,
+                direction: RenkoDirection::Down,
+            });
+            (top, bottom) = (bottom, bottom - brick);
+            last_ts = rec.ts;
+        }
+    }
+
+    bricks
+}
+
+/// 두 심볼로 만드는 합성 시리즈
+#[derive(Clone, Debug, PartialEq)]
+pub enum SyntheticSpec {
+    /// a / b (예: XAUUSD/XAGUSD)
+    Ratio { a: String, b: String },
+    /// a - b + offset (가격이 u32라 음수가 될 수 있는 스프레드는 offset으로 올려 담음, 그래도 음수면 0)
+    Difference { a: String, b: String, offset: f64 },
+}
+
+impl SyntheticSpec {
+    pub fn symbols(&self) -> [&str; 2] {
+        match self {
+            Self::Ratio { a, b } | Self::Difference { a, b, .. } => [a, b],
+        }
+    }
+
+    fn combine(&self, x: u32, y: u32) -> u32 {
+        match self {
+            Self::Ratio { .. } if y == 0 => 0,
+            Self::Ratio { .. } => ((u64::from(x) * 100_000 + u64::from(y) / 2) / u64::from(y))
+                .min(u64::from(u32::MAX)) as u32,
+            Self::Difference { offset, .. } => {
+                let offset = (offset * 100_000.0).round() as i64;
+                (i64::from(x) - i64::from(y) + offset).clamp(0, i64::from(u32::MAX)) as u32
+            }
+        }
+    }
+}
+
+/// 공통 타임스탬프의 봉끼리 필드별로 합성 (high/low는 합성된 네 가격의 최대/최소, 거래량은 0)
+pub fn synthesize(spec: &SyntheticSpec, a: &[OHLCV], b: &[OHLCV]) -> Vec<OHLCV> {
+    join_by_ts(a, b, |x, y| {
+        let prices = [
+            spec.combine(x.open, y.open),
+            spec.combine(x.high, y.high),
+            spec.combine(x.low, y.low),
+            spec.combine(x.close, y.close),
+        ];
+        OHLCV {
+            ts: x.ts,
+            open: prices[0],
+            high: prices.into_iter().max().unwrap_or_default(),
+            low: prices.into_iter().min().unwrap_or_default(),
+            close: prices[3],
+            ..Default::default()
+        }
+    })
+}
+
+/// 두 시계열에 모두 있는 타임스탬프의 봉 쌍만 `combine`으로 합침 (한쪽에만 있는 봉은 버림)
+fn join_by_ts(
+    a: &[OHLCV],
+    b: &[OHLCV],
+    mut combine: impl FnMut(&OHLCV, &OHLCV) -> OHLCV,
+) -> Vec<OHLCV> {
+    let mut result = Vec::with_capacity(a.len().min(b.len()));
+    let (mut i, mut j) = (0, 0);
+    while i < a.len() && j < b.len() {
+        let (x, y) = (&a[i], &b[j]);
+        match { x.ts }.cmp(&{ y.ts }) {
+            std::cmp::Ordering::Less => i += 1,
+            std::cmp::Ordering::Greater => j += 1,
+            std::cmp::Ordering::Equal => {
+                result.push(combine(x, y));
+                i += 1;
+                j += 1;
+            }
+        }
+    }
+    result
+}
+
+/// 공통 통화를 사이에 둔 두 통화쌍으로 만드는 크로스 환율 (EURUSD × USDJPY = EURJPY)
+///
+/// 첫 다리에서 공통 통화가 아닌 쪽이 base, 둘째 다리에서 공통 통화가 아닌 쪽이 quote
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Cross {
+    pub base: String,
+    pub quote: String,
+    /// 결과 가격 스케일: 둘째 다리가 크로스와 같은 quote 통화면 그 스케일, 아니면 두 다리 중 큰 쪽
+    pub scale: u32,
+    legs: [Leg; 2],
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+struct Leg {
+    scale: u32,
+    /// 다리를 역수로 씀 (USDJPY, USDCHF → JPYCHF의 첫 다리, EURUSD, GBPUSD → EURGBP의 둘째 다리)
+    inverted: bool,
+}
+
+impl Cross {
+    /// 두 통화쌍이 정확히 한 통화만 공유할 때만 Some
+    pub fn new(first: &Symbol, second: &Symbol) -> Option<Self> {
+        let common = [&first.base, &first.quote]
+            .into_iter()
+            .filter(|ccy| **ccy == second.base || **ccy == second.quote)
+            .collect::<Vec<_>>();
+        let [common] = common[..] else {
+            return None;
+        };
+        let (base, invert_first) = if first.quote == *common {
+            (first.base.clone(), false)
+        } else {
+            (first.quote.clone(), true)
+        };
+        let (quote, invert_second) = if second.base == *common {
+            (second.quote.clone(), false)
+        } else {
+            (second.base.clone(), true)
+        };
+        if base == quote || base == *common || quote == *common {
+            return None;
+        }
+
+        Some(Self {
+            base,
+            quote,
+            scale: if invert_second {
+                first.scale.max(second.scale)
+            } else {
+                second.scale
+            },
+            legs: [
+                Leg {
+                    scale: first.scale,
+                    inverted: invert_first,
+                },
+                Leg {
+                    scale: second.scale,
+                    inverted: invert_second,
+                },
+            ],
+        })
+    }
+
+    /// 크로스 심볼 이름 (예: EURJPY)
+    pub fn name(&self) -> String {
+        format!("{}{}", self.base, self.quote)
+    }
+
+    /// 두 다리의 정수 가격 → 크로스의 정수 가격 (`scale` 기준, 0 가격의 역수는 0)
+    fn price(&self, x: u32, y: u32) -> u32 {
+        let leg = |raw: u32, leg: Leg| {
+            let price = f64::from(raw) / f64::from(leg.scale);
+            if leg.inverted { price.recip() } else { price }
+        };
+        let price = leg(x, self.legs[0]) * leg(y, self.legs[1]) * f64::from(self.scale);
+        if price.is_finite() {
+            price.round().clamp(0.0, f64::from(u32::MAX)) as u32
+        } else {
+            0
+        }
+    }
+}
+
+/// 두 다리에 모두 봉이 있는 분만 크로스 봉으로 합성 (거래량 0)
+///
+/// open/close는 각 다리의 open끼리, close끼리. high/low는 두 다리의 high/low 네 조합 중
+/// 최대/최소라 한 분 안에서 두 다리가 어떤 순서로 움직였든 실제 크로스 범위를 덮음
+pub fn synthesize_cross(cross: &Cross, a: &[OHLCV], b: &[OHLCV], symbol_id: u16) -> Vec<OHLCV> {
+    join_by_ts(a, b, |x, y| {
+        let corners = [
+            cross.price(x.high, y.high),
+            cross.price(x.high, y.low),
+            cross.price(x.low, y.high),
+            cross.price(x.low, y.low),
+        ];
+        OHLCV {
+            ts: x.ts,
+            open: cross.price(x.open, y.open),
+            high: corners.into_iter().max().unwrap_or_default(),
+            low: corners.into_iter().min().unwrap_or_default(),
+            close: cross.price(x.close, y.close),
+            symbol_id,
+            ..Default::default()
+        }
+    })
+}
+
+/// 정렬/채움 쿼리에서 빈 분 처리 방식
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum FillPolicy {
+    /// 빈 칸은 None으로 유지 (`fill_minutes`는 아무것도 내보내지 않음)
+    None,
+    /// 한 심볼이라도 비어 있는 분은 제외
+    Drop,
+    /// 직전 값으로 채움 (max_gap_secs보다 긴 공백은 채우지 않음)
+    ForwardFill { max_gap_secs: u64 },
+    /// 0으로 채움
+    Zero,
+}
+
+/// 공통 타임스탬프 축에 정렬된 심볼별 종가
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct AlignedFrame {
+    pub symbols: Vec<String>,
+    pub timestamps: Vec<u64>,
+    /// symbols와 같은 순서, 각 열은 timestamps와 같은 길이
+    pub columns: Vec<Vec<Option<f64>>>,
+}
+
+impl AlignedFrame {
+    pub fn column(&self, symbol: &str) -> Option<&[Option<f64>]> {
+        let idx = self.symbols.iter().position(|s| s == symbol)?;
+        Some(&self.columns[idx])
+    }
+}
+
+/// 시간순 시리즈들을 타임스탬프 합집합 축에 정렬 (종가는 시리즈별 `scales`로 실수 환산)
+pub fn align_closes(
+    symbols: &[&str],
+    series: &[Vec<OHLCV>],
+    scales: &[u32],
+    fill: FillPolicy,
+) -> AlignedFrame {
+    let mut timestamps: Vec<u64> = series
+        .iter()
+        .flatten()
+        .map(|rec| rec.ts)
+        .filter(|&ts| ts != 0)
+        .collect();
+    timestamps.sort_unstable();
+    timestamps.dedup();
+
+    let mut columns: Vec<Vec<Option<f64>>> = series
+        .iter()
+        .zip(scales)
+        .map(|(records, &scale)| {
+            let mut column = vec![None; timestamps.len()];
+            for rec in records.iter().filter(|rec| rec.ts != 0) {
+                if let Ok(i) = timestamps.binary_search(&{ rec.ts }) {
+                    column[i] = Some(f64::from(rec.close) / f64::from(scale));
+                }
+            }
+            column
+        })
+        .collect();
+
+    match fill {
+        FillPolicy::None => {}
+        FillPolicy::Zero => {
+            for value in columns.iter_mut().flatten() {
+                value.get_or_insert(0.0);
+            }
+        }
+        FillPolicy::Drop => {
+            let keep: Vec<bool> = (0..timestamps.len())
+                .map(|i| columns.iter().all(|column| column[i].is_some()))
+                .collect();
+            let mut flags = keep.iter();
+            timestamps.retain(|_| *flags.next().unwrap());
+            for column in &mut columns {
+                let mut flags = keep.iter();
+                column.retain(|_| *flags.next().unwrap());
+            }
+        }
+        FillPolicy::ForwardFill { max_gap_secs } => {
+            let max_gap = max_gap_secs.saturating_mul(1_000_000_000);
+            for column in &mut columns {
+                let mut last: Option<(u64, f64)> = None;
+                for (value, &ts) in column.iter_mut().zip(&timestamps) {
+                    match *value {
+                        Some(v) => last = Some((ts, v)),
+                        None => {
+                            if let Some((last_ts, v)) = last
+                                && ts - last_ts <= max_gap
+                            {
+                                *value = Some(v);
+                            }
+                        }
+                    }
+                }
+            }
+        }
+    }
+
+    AlignedFrame {
+        symbols: symbols.iter().map(|s| s.to_string()).collect(),
+        timestamps,
+        columns,
+    }
+}
+
+/// [start_ts, end_ts]의 1분 격자에서 봉이 없는 분을 fill 방식으로 채움
+///
+/// `records`는 시간순, `prev`는 범위 직전 봉 (선두 공백의 forward fill용).
+/// forward fill은 직전 종가로 평평한 OHLC + 거래량 0, Zero는 가격/거래량이 모두 0인 봉
+pub fn fill_minutes(
+    records: &[OHLCV],
+    prev: Option<OHLCV>,
+    start_ts: u64,
+    end_ts: u64,
+    fill: FillPolicy,
+) -> Vec<OHLCV> {
+    const MINUTE: u64 = 60_000_000_000;
+
+    let mut bars = records.iter().filter(|rec| rec.ts != 0).copied().peekable();
+    if matches!(fill, FillPolicy::None | FillPolicy::Drop) {
+        return bars.collect();
+    }
+
+    let symbol_id = records
+        .first()
+        .or(prev.as_ref())
+        .map_or(0, |rec| rec.symbol_id);
+    let mut last = prev;
+    let mut result = Vec::with_capacity(records.len());
+    let mut minute = start_ts.div_ceil(MINUTE).saturating_mul(MINUTE);
+    while minute <= end_ts {
+        let next_minute = minute.saturating_add(MINUTE);
+        let mut covered = false;
+        while let Some(rec) = bars.next_if(|rec| rec.ts < next_minute) {
+            covered |= rec.ts >= minute;
+            result.push(rec);
+            last = Some(rec);
+        }
+        if !covered {
+            let filler = match fill {
+                FillPolicy::ForwardFill { max_gap_secs } => last
+                    .filter(|rec| minute - rec.ts <= max_gap_secs.saturating_mul(1_000_000_000))
+                    .map(|rec| OHLCV {
+                        ts: minute,
+                        open: rec.close,
+                        high: rec.close,
+                        low: rec.close,
+                        close: rec.close,
+                        symbol_id,
+                        ..Default::default()
+                    }),
+                _ => Some(OHLCV {
+                    ts: minute,
+                    symbol_id,
+                    ..Default::default()
+                }),
+            };
+            result.extend(filler);
+        }
+        if next_minute == u64::MAX {
+            break;
+        }
+        minute = next_minute;
+    }
+    result.extend(bars);
+    result
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::types::PRICE_SCALE;
+
+    const MINUTE: u64 = 60_000_000_000;
+
+    fn bar(ts: u64, open: u32, high: u32, low: u32, close: u32, volume: u32) -> OHLCV {
+        OHLCV {
+            ts,
+            open,
+            high,
+            low,
+            close,
+            volume,
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn parse_interval_units() {
+        assert_eq!(parse_interval("1m"), Some(60));
+        assert_eq!(parse_interval("4h"), Some(4 * 3600));
+        assert_eq!(parse_interval("1d"), Some(86400));
+        assert_eq!(parse_interval("0m"), None);
+        assert_eq!(parse_interval("h"), None);
+        assert_eq!(parse_interval("5x"), None);
+    }
+
+    #[test]
+    fn resample_merges_buckets() {
+        let records = [
+            bar(0, 0, 0, 0, 0, 0), // 빈 슬롯
+            bar(60 * MINUTE, 10, 15, 9, 12, 1),
+            bar(61 * MINUTE, 12, 20, 11, 13, 2),
+            bar(62 * MINUTE, 13, 14, 5, 7, 3),
+            bar(120 * MINUTE, 7, 8, 6, 8, 4),
+        ];
+
+        let hourly = resample(&records, 3600, Alignment::UTC);
+        assert_eq!(hourly.len(), 2);
+
+        let first = hourly[0];
+        assert_eq!({ first.ts }, 60 * MINUTE);
+        assert_eq!(
+            (first.open, first.high, first.low, first.close, first.volume),
+            (10, 20, 5, 7, 6)
+        );
+        assert_eq!({ hourly[1].ts }, 120 * MINUTE);
+    }
+
+    fn closes(ts_minutes: &[u64], close: u32) -> Vec<OHLCV> {
+        ts_minutes
+            .iter()
+            .map(|&m| bar(m * MINUTE, close, close, close, close, 1))
+            .collect()
+    }
+
+    #[test]
+    fn align_mismatched_coverage() {
+        // a: 1,2,3,4 / b: 2,4,10
+        let a = closes(&[1, 2, 3, 4], 100_000);
+        let b = closes(&[2, 4, 10], 200_000);
+        let series = vec![a, b];
+
+        let frame = align_closes(&["A", "B"], &series, &[100_000; 2], FillPolicy::None);
+        let minutes: Vec<u64> = frame.timestamps.iter().map(|ts| ts / MINUTE).collect();
+        assert_eq!(minutes, vec![1, 2, 3, 4, 10]);
+        assert_eq!(
+            frame.column("A").unwrap(),
+            &[Some(1.0), Some(1.0), Some(1.0), Some(1.0), None]
+        );
+        assert_eq!(
+            frame.column("B").unwrap(),
+            &[None, Some(2.0), None, Some(2.0), Some(2.0)]
+        );
+
+        let dropped = align_closes(&["A", "B"], &series, &[100_000; 2], FillPolicy::Drop);
+        let minutes: Vec<u64> = dropped.timestamps.iter().map(|ts| ts / MINUTE).collect();
+        assert_eq!(minutes, vec![2, 4]);
+        assert_eq!(dropped.columns[0].len(), 2);
+        assert_eq!(dropped.columns[1].len(), 2);
+    }
+
+    #[test]
+    fn forward_fill_respects_max_gap() {
+        let series = vec![closes(&[1, 2, 3, 4], 100_000), closes(&[2, 4, 10], 200_000)];
+        let frame = align_closes(
+            &["A", "B"],
+            &series,
+            &[100_000; 2],
+            FillPolicy::ForwardFill { max_gap_secs: 180 },
+        );
+
+        // 첫 값 이전은 채우지 않음, B의 3분은 1분 공백이라 채움
+        assert_eq!(
+            frame.column("B").unwrap(),
+            &[None, Some(2.0), Some(2.0), Some(2.0), Some(2.0)]
+        );
+        // A는 4분 → 10분 공백이 3분을 넘으므로 채우지 않음
+        assert_eq!(frame.column("A").unwrap()[4], None);
+    }
+
+    #[test]
+    fn rolling_correlation_matches_direct_pearson() {
+        let a: Vec<f64> = (0..50)
+            .map(|i| 1.0 + (i as f64 * 0.37).sin() * 0.01)
+            .collect();
+        let b: Vec<f64> = (0..50)
+            .map(|i| 2.0 + (i as f64 * 0.21).cos() * 0.02)
+            .collect();
+        let window = 10;
+
+        let rolling = TechnicalIndicators::rolling_correlation(&a, &b, window);
+        assert_eq!(rolling.len(), 41);
+
+        for (i, &value) in rolling.iter().enumerate() {
+            let (xa, xb) = (&a[i..i + window], &b[i..i + window]);
+            let (ma, mb) = (
+                xa.iter().sum::<f64>() / window as f64,
+                xb.iter().sum::<f64>() / window as f64,
+            );
+            let cov: f64 = xa.iter().zip(xb).map(|(x, y)| (x - ma) * (y - mb)).sum();
+            let va: f64 = xa.iter().map(|x| (x - ma).powi(2)).sum();
+            let vb: f64 = xb.iter().map(|y| (y - mb).powi(2)).sum();
+            assert!((value - cov / (va * vb).sqrt()).abs() < 1e-6);
+        }
+    }
+
+    #[test]
+    fn rolling_correlation_flat_window_is_nan() {
+        let a = vec![1.1; 5];
+        let b = vec![1.0, 2.0, 3.0, 4.0, 5.0];
+        let rolling = TechnicalIndicators::rolling_correlation(&a, &b, 3);
+        assert_eq!(rolling.len(), 3);
+        assert!(rolling.iter().all(|v| v.is_nan()));
+
+        let inverse: Vec<f64> = b.iter().map(|x| -x).collect();
+        let rolling = TechnicalIndicators::rolling_correlation(&b, &inverse, 3);
+        assert!(rolling.iter().all(|v| (v + 1.0).abs() < 1e-9));
+    }
+
+    // TA-Lib 규칙(SMA 시드, Wilder 평활)으로 독립 계산한 기준값과 비교
+    const CLOSES: [u32; 15] = [
+        110000, 110120, 110080, 110250, 110190, 110300, 110210, 110050, 110110, 110400, 110380,
+        110290, 110450, 110500, 110420,
+    ];
+
+    fn series() -> Vec<OHLCV> {
+        CLOSES
+            .iter()
+            .enumerate()
+            .map(|(i, &c)| bar(i as u64 * MINUTE, c, c + 80, c - 60, c, 1))
+            .collect()
+    }
+
+    fn assert_close(actual: Option<f64>, expected: f64) {
+        let actual = actual.expect("value expected");
+        assert!(
+            (actual - expected).abs() < 1e-9,
+            "{} != {}",
+            actual,
+            expected
+        );
+    }
+
+    #[test]
+    fn ema_seeded_with_sma() {
+        let ema = TechnicalIndicators::ema(&series(), PriceField::Close, 5, PRICE_SCALE);
+        assert_eq!(ema.len(), 15);
+        assert!(ema[..4].iter().all(Option::is_none));
+        assert_close(ema[4], 1.10128);
+        assert_close(ema[5], 1.1018533333333336);
+        assert_close(ema[14], 1.1040074534708462);
+    }
+
+    #[test]
+    fn rsi_wilder_smoothing() {
+        let rsi = TechnicalIndicators::rsi(&series(), PriceField::Close, 5);
+        assert!(rsi[..5].iter().all(Option::is_none));
+        assert_close(rsi[5], 80.00000000000088);
+        assert_close(rsi[6], 65.30612244898086);
+        assert_close(rsi[7], 46.376811594202415);
+        assert_close(rsi[14], 60.97946039974674);
+    }
+
+    #[test]
+    fn macd_signal_alignment() {
+        let macd = TechnicalIndicators::macd(&series(), PriceField::Close, 3, 6, 3, PRICE_SCALE);
+        assert_eq!(macd.macd.iter().position(Option::is_some), Some(5));
+        assert_eq!(macd.signal.iter().position(Option::is_some), Some(7));
+        assert_eq!(macd.histogram.iter().position(Option::is_some), Some(7));
+        assert_close(macd.macd[14], 0.0004490440402282747);
+        assert_close(macd.signal[14], 0.0005263849448548733);
+        assert_close(macd.histogram[14], -7.734090462659863e-05);
+    }
+
+    #[test]
+    fn bollinger_population_stddev() {
+        let bands =
+            TechnicalIndicators::bollinger(&series(), PriceField::Close, 5, 2.0, PRICE_SCALE);
+        assert!(bands.middle[3].is_none());
+        assert_close(bands.middle[4], 1.1012800000000003);
+        assert_close(bands.upper[4], 1.1030115888657532);
+        assert_close(bands.lower[4], 1.0995484111342473);
+        assert_close(bands.upper[14], 1.1054964744967704);
+        assert_close(bands.lower[14], 1.10266352550323);
+    }
+
+    #[test]
+    fn atr_wilder_smoothing() {
+        let atr = TechnicalIndicators::atr(&series(), 5, PRICE_SCALE);
+        assert!(atr[..5].iter().all(Option::is_none));
+        assert_close(atr[5], 0.0018399999999998418);
+        assert_close(atr[9], 0.002152863999999898);
+        assert_close(atr[14], 0.001784938475519876);
+    }
+
+    #[test]
+    fn indicators_read_prices_at_the_given_scale() {
+        // 같은 정수 가격도 JPY(3자리)로 읽으면 100배
+        let records = series();
+        let jpy = 1_000;
+        let ratio = f64::from(PRICE_SCALE / jpy);
+
+        let sma = TechnicalIndicators::sma(&records, 5, jpy);
+        assert_close(Some(sma[0]), 110.128);
+        assert_close(
+            TechnicalIndicators::atr(&records, 5, jpy)[14],
+            TechnicalIndicators::atr(&records, 5, PRICE_SCALE)[14].unwrap() * ratio,
+        );
+        assert_close(
+            TechnicalIndicators::ema(&records, PriceField::Close, 5, jpy)[14],
+            1.1040074534708462 * ratio,
+        );
+        let vwap = TechnicalIndicators::vwap(&records, None, jpy);
+        let vwap_fx = TechnicalIndicators::vwap(&records, None, PRICE_SCALE);
+        assert_close(Some(vwap[14]), vwap_fx[14] * ratio);
+        assert_close(
+            TechnicalIndicators::rsi(&records, PriceField::Close, 5)[14],
+            60.97946039974674,
+        );
+    }
+
+    #[test]
+    fn indicators_handle_short_input() {
+        let short = &series()[..3];
+        assert!(
+            TechnicalIndicators::rsi(short, PriceField::Close, 5)
+                .iter()
+                .all(Option::is_none)
+        );
+        assert!(
+            TechnicalIndicators::atr(short, 0, PRICE_SCALE)
+                .iter()
+                .all(Option::is_none)
+        );
+        assert_eq!(
+            TechnicalIndicators::ema(short, PriceField::Close, 5, PRICE_SCALE).len(),
+            3
+        );
+    }
+
+    #[test]
+    fn vwap_resets_at_session_boundary() {
+        // 21:58, 21:59 | 22:00, 22:01 (UTC)
+        let base = 21 * 60 + 58;
+        let records = [
+            bar((base) * MINUTE, 0, 110_030, 109_970, 110_000, 1),
+            bar((base + 1) * MINUTE, 0, 110_330, 110_270, 110_300, 2),
+            bar((base + 2) * MINUTE, 0, 120_030, 119_970, 120_000, 3),
+            bar((base + 3) * MINUTE, 0, 121_030, 120_970, 121_000, 1),
+        ];
+
+        let reset = NaiveTime::from_hms_opt(22, 0, 0);
+        let vwap = TechnicalIndicators::vwap(&records, reset, PRICE_SCALE);
+        assert_close(Some(vwap[0]), 1.1);
+        assert_close(Some(vwap[1]), (1.1 + 2.0 * 1.103) / 3.0);
+        assert_close(Some(vwap[2]), 1.2);
+        assert_close(Some(vwap[3]), (3.0 * 1.2 + 1.21) / 4.0);
+
+        let cumulative = TechnicalIndicators::vwap(&records, None, PRICE_SCALE);
+        assert_close(
+            Some(cumulative[3]),
+            (1.1 + 2.0 * 1.103 + 3.0 * 1.2 + 1.21) / 7.0,
+        );
+    }
+
+    #[test]
+    fn vwap_falls_back_to_mean_without_volume() {
+        let records: Vec<OHLCV> = [110_000, 110_300, 110_600]
+            .iter()
+            .enumerate()
+            .map(|(i, &c)| bar(i as u64 * MINUTE, c, c, c, c, 0))
+            .collect();
+        let vwap = TechnicalIndicators::vwap(&records, None, PRICE_SCALE);
+        assert_close(Some(vwap[0]), 1.1);
+        assert_close(Some(vwap[2]), 1.103);
+        assert!(vwap.iter().all(|v| v.is_finite()));
+    }
+
+    #[test]
+    fn rolling_volatility_matches_two_pass() {
+        let records = series();
+        let (window, bars_per_year) = (5, 252.0 * 1440.0);
+        let vol = TechnicalIndicators::rolling_volatility(&records, window, bars_per_year);
+        assert!(vol[..window].iter().all(Option::is_none));
+
+        let closes: Vec<f64> = records
+            .iter()
+            .map(|r| r.price_f64(PriceField::Close, PRICE_SCALE))
+            .collect();
+        for (i, &actual) in vol.iter().enumerate().skip(window) {
+            let returns: Vec<f64> = (i + 1 - window..=i)
+                .map(|j| (closes[j] / closes[j - 1]).ln())
+                .collect();
+            let mean = returns.iter().sum::<f64>() / window as f64;
+            let variance =
+                returns.iter().map(|r| (r - mean).powi(2)).sum::<f64>() / (window - 1) as f64;
+            assert_close(actual, variance.sqrt() * bars_per_year.sqrt());
+        }
+    }
+
+    #[test]
+    fn log_returns_and_volatility_on_known_series() {
+        // 수익률 ln(1.1), ln(1/1.1), ln(1.1), ln(1/1.1) → ±a, a = ln 1.1
+        let closes = [100_000, 110_000, 100_000, 110_000, 100_000];
+        let records: Vec<OHLCV> = closes
+            .iter()
+            .enumerate()
+            .map(|(i, &c)| bar(i as u64 * MINUTE, c, c, c, c, 1))
+            .collect();
+
+        let a = 1.1f64.ln();
+        let returns = TechnicalIndicators::log_returns(&records);
+        assert_eq!(returns.len(), 4);
+        for (r, expected) in returns.iter().zip([a, -a, a, -a]) {
+            assert!((r - expected).abs() < 1e-12);
+        }
+
+        // 윈도우 4: 평균 0, 표본분산 = 4a² / 3
+        let vol = TechnicalIndicators::rolling_volatility(&records, 4, 1.0);
+        assert_eq!(vol[..4], [None; 4]);
+        assert_close(vol[4], a * (4.0f64 / 3.0).sqrt());
+        let annual = TechnicalIndicators::rolling_volatility(&records, 4, 252.0);
+        assert_close(annual[4], a * (4.0f64 / 3.0).sqrt() * 252f64.sqrt());
+
+        // 입력보다 긴 윈도우
+        assert!(
+            TechnicalIndicators::rolling_volatility(&records, 5, 1.0)
+                .iter()
+                .all(Option::is_none)
+        );
+    }
+
+    #[test]
+    fn log_returns_guard_zero_prices() {
+        let records: Vec<OHLCV> = [100_000, 0, 100_000, 101_000, 102_000, 103_000]
+            .iter()
+            .enumerate()
+            .map(|(i, &c)| bar(i as u64 * MINUTE, c, c, c, c, 1))
+            .collect();
+
+        let returns = TechnicalIndicators::log_returns(&records);
+        assert!(returns.iter().all(|r| r.is_finite()));
+        assert_eq!(returns[..2], [0.0, 0.0]);
+
+        // 0 가격이 걸친 윈도우는 None, 벗어나면 다시 계산
+        let vol = TechnicalIndicators::rolling_volatility(&records, 2, 1.0);
+        assert_eq!(vol[..4], [None; 4]);
+        assert!(vol[4].is_some_and(f64::is_finite));
+        assert!(vol[5].is_some());
+    }
+
+    #[test]
+    fn heikin_ashi_recursion() {
+        let records = [
+            bar(MINUTE, 100, 110, 90, 104, 1),
+            bar(2 * MINUTE, 104, 120, 100, 116, 1),
+            bar(3 * MINUTE, 116, 118, 60, 70, 1),
+        ];
+        let ha = transform_heikin_ashi(&records);
+        let ohlc = |b: &OHLCV| (b.open, b.high, b.low, b.close);
+
+        // close=(100+110+90+104)/4=101, open=(100+104)/2=102
+        assert_eq!(ohlc(&ha[0]), (102, 110, 90, 101));
+        // close=110, open=(102+101)/2=101.5→102
+        assert_eq!(ohlc(&ha[1]), (102, 120, 100, 110));
+        // close=91, open=(101.5+110)/2=105.75→106, high=max(118,..)
+        assert_eq!(ohlc(&ha[2]), (106, 118, 60, 91));
+        assert_eq!({ ha[2].ts }, 3 * MINUTE);
+    }
+
+    fn renko_path(closes: &[u32]) -> Vec<OHLCV> {
+        closes
+            .iter()
+            .enumerate()
+            .map(|(i, &c)| bar((i as u64 + 1) * MINUTE, c, c, c, c, 1))
+            .collect()
+    }
+
+    fn brick_summary(bricks: &[RenkoBrick]) -> Vec<(u32, u32, u64, u64)> {
+        bricks
+            .iter()
+            .map(|b| (b.open, b.close, b.ts_open / MINUTE, b.ts_close / MINUTE))
+            .collect()
+    }
+
+    #[test]
+    fn renko_exact_boundary_emits_brick() {
+        let bricks = transform_renko(&renko_path(&[1000, 1049, 1050, 1099]), 50);
+        assert_eq!(brick_summary(&bricks), vec![(1000, 1050, 1, 3)]);
+        assert_eq!(bricks[0].direction, RenkoDirection::Up);
+    }
+
+    #[test]
+    fn renko_gap_emits_multiple_bricks() {
+        // +3.4칸 갭 → 3개, 이후 반전은 2칸(bottom - brick) 이하에서만
+        let bricks = transform_renko(&renko_path(&[1000, 1170, 1110, 1100, 1000]), 50);
+        assert_eq!(
+            brick_summary(&bricks),
+            vec![
+                (1000, 1050, 1, 2),
+                (1050, 1100, 2, 2),
+                (1100, 1150, 2, 2),
+                (1100, 1050, 2, 5),
+                (1050, 1000, 5, 5),
+            ]
+        );
+        assert!(
+            bricks[3..]
+                .iter()
+                .all(|b| b.direction == RenkoDirection::Down)
+        );
+    }
+
+    #[test]
+    fn renko_ignores_noise_inside_brick() {
+        assert!(transform_renko(&renko_path(&[1000, 1040, 960, 1049, 951]), 50).is_empty());
+        assert!(transform_renko(&renko_path(&[1000, 2000]), 0).is_empty());
+    }
+
+    /// xorshift64 (테스트용 의사난수)
+    fn xorshift(state: &mut u64) -> u64 {
+        *state ^= *state << 13;
+        *state ^= *state >> 7;
+        *state ^= *state << 17;
+        *state
+    }
+
+    #[test]
+    fn simd_filter_matches_scalar_on_random_data() {
+        let mut state = 0x9e37_79b9_7f4a_7c15;
+        for round in 0..200 {
+            let len = (xorshift(&mut state) % 100) as usize;
+            // 좁은 범위에서 뽑아 경계값과 같은 가격이 자주 나오도록
+            let spread = if round % 2 == 0 { 16 } else { u32::MAX as u64 };
+            let records: Vec<OHLCV> = (0..len)
+                .map(|i| {
+                    let close = (xorshift(&mut state) % spread) as u32;
+                    bar(i as u64, close, close, close, close, 1)
+                })
+                .collect();
+            let a = (xorshift(&mut state) % spread) as u32;
+            let b = (xorshift(&mut state) % spread) as u32;
+            let (min, max) = (a.min(b), a.max(b));
+
+            let expected = SimdFilter::filter_by_price_scalar(&records, min, max);
+            let actual = SimdFilter::filter_by_price(&records, min, max);
+            let columnar = SimdFilter::filter_columns(&Columns::from_records(&records), min, max);
+            let ts = |recs: &[OHLCV]| recs.iter().map(|r| r.ts).collect::<Vec<_>>();
+            assert_eq!(ts(&actual), ts(&expected), "min={} max={}", min, max);
+            assert_eq!(ts(&columnar), ts(&expected), "min={} max={}", min, max);
+        }
+    }
+
+    /// 이 아키텍처에서 쓸 수 있는 SIMD 경로를 디스패처를 거치지 않고 직접 스칼라와 비교
+    #[test]
+    fn each_simd_backend_matches_scalar() {
+        // u32 상위 비트 근처 값으로 부호 없는 비교 확인
+        let closes = [
+            0,
+            1,
+            0x7fff_ffff,
+            0x8000_0000,
+            0x8000_0001,
+            u32::MAX - 1,
+            u32::MAX,
+            42,
+            0x8000_0000,
+            7,
+            u32::MAX,
+        ];
+        let records: Vec<OHLCV> = closes
+            .iter()
+            .enumerate()
+            .map(|(i, &c)| bar(i as u64, c, c, c, c, 1))
+            .collect();
+        let columns = Columns::from_records(&records);
+        let ts = |recs: &[OHLCV]| recs.iter().map(|r| r.ts).collect::<Vec<_>>();
+
+        for (min, max) in [
+            (0, u32::MAX),
+            (1, 0x7fff_ffff),
+            (0x8000_0000, u32::MAX - 1),
+            (u32::MAX, u32::MAX),
+            (43, 6),
+        ] {
+            let expected = ts(&SimdFilter::filter_by_price_scalar(&records, min, max));
+            let count = SimdFilter::count_in_range_scalar(&records, min, max);
+
+            #[cfg(all(feature = "simd", target_arch = "x86_64"))]
+            if is_x86_feature_detected!("avx2") {
+                // SAFETY: AVX2 지원을 방금 확인함
+                unsafe {
+                    let filtered = SimdFilter::filter_by_price_avx2(&records, min, max);
+                    assert_eq!(ts(&filtered), expected, "avx2 {}..={}", min, max);
+                    let filtered = SimdFilter::filter_columns_avx2(&columns, min, max);
+                    assert_eq!(ts(&filtered), expected, "avx2 columns {}..={}", min, max);
+                    let counted = SimdFilter::count_in_range_avx2(&records, min, max);
+                    assert_eq!(counted, count, "avx2 count {}..={}", min, max);
+                }
+            }
+            #[cfg(all(feature = "simd", target_arch = "aarch64"))]
+            if is_aarch64_feature_detected!("neon") {
+                // SAFETY: NEON 지원을 방금 확인함
+                unsafe {
+                    let filtered = SimdFilter::filter_by_price_neon(&records, min, max);
+                    assert_eq!(ts(&filtered), expected, "neon {}..={}", min, max);
+                    let filtered = SimdFilter::filter_columns_neon(&columns, min, max);
+                    assert_eq!(ts(&filtered), expected, "neon columns {}..={}", min, max);
+                    let counted = SimdFilter::count_in_range_neon(&records, min, max);
+                    assert_eq!(counted, count, "neon count {}..={}", min, max);
+                }
+            }
+
+            let filtered = SimdFilter::filter_columns(&columns, min, max);
+            assert_eq!(ts(&filtered), expected, "dispatch {}..={}", min, max);
+            let counted = SimdFilter::count_in_range(&records, min, max);
+            assert_eq!(counted, count, "dispatch count {}..={}", min, max);
+        }
+    }
+
+    #[test]
+    fn simd_filter_bounds_are_inclusive() {
+        let records: Vec<OHLCV> = [5, 10, 15, 20, 25, 30, 35, 40, 45, 10]
+            .iter()
+            .enumerate()
+            .map(|(i, &c)| bar(i as u64, c, c, c, c, 1))
+            .collect();
+        let closes: Vec<u32> = SimdFilter::filter_by_price(&records, 10, 40)
+            .iter()
+            .map(|r| r.close)
+            .collect();
+        assert_eq!(closes, [10, 15, 20, 25, 30, 35, 40, 10]);
+    }
+
+    #[test]
+    fn resample_volume_does_not_wrap() {
+        let records: Vec<OHLCV> = (0..1440)
+            .map(|m| bar((1440 + m) * MINUTE, 10, 10, 10, 10, u32::MAX - 1))
+            .collect();
+
+        let daily = resample(&records, 86400, Alignment::UTC);
+        assert_eq!(daily.len(), 1);
+        assert_eq!(daily[0].total_volume(), 1440 * (u32::MAX as u64 - 1));
+
+        // 집계 봉을 다시 집계해도 상위 비트 유지
+        let weekly = resample(&[daily[0], daily[0]], 7 * 86400, Alignment::UTC);
+        assert_eq!(weekly[0].total_volume(), 2 * 1440 * (u32::MAX as u64 - 1));
+
+        let vwap = TechnicalIndicators::vwap(&daily, None, PRICE_SCALE);
+        assert_close(Some(vwap[0]), 0.0001);
+    }
+
+    #[test]
+    fn simd_reductions_match_scalar_on_random_data() {
+        let mut state = 0x2545_f491_4f6c_dd1d;
+        for _ in 0..100 {
+            let len = (xorshift(&mut state) % 200) as usize;
+            let records: Vec<OHLCV> = (0..len)
+                .map(|i| {
+                    let mut rec = bar(
+                        i as u64,
+                        xorshift(&mut state) as u32,
+                        xorshift(&mut state) as u32,
+                        xorshift(&mut state) as u32,
+                        (xorshift(&mut state) % 64) as u32,
+                        xorshift(&mut state) as u32,
+                    );
+                    rec.volume_hi = (xorshift(&mut state) % 4) as u32;
+                    rec
+                })
+                .collect();
+
+            let (min, max) = (16, 48);
+            assert_eq!(
+                SimdFilter::count_in_range(&records, min, max),
+                SimdFilter::count_in_range_scalar(&records, min, max)
+            );
+            for field in [
+                PriceField::Open,
+                PriceField::High,
+                PriceField::Low,
+                PriceField::Close,
+            ] {
+                assert_eq!(
+                    SimdFilter::minmax(&records, field),
+                    SimdFilter::minmax_scalar(&records, field)
+                );
+            }
+            assert_eq!(
+                SimdFilter::sum_volume(&records),
+                SimdFilter::sum_volume_scalar(&records)
+            );
+        }
+        assert_eq!(SimdFilter::minmax(&[], PriceField::Low), (u32::MAX, 0));
+    }
+
+    #[test]
+    fn tick_aggregator_builds_minute_bars() {
+        let second = 1_000_000_000;
+        let quote = |ts: u64, bid: u32, volume: u32| Tick {
+            ts,
+            bid,
+            ask: bid + 2,
+            volume,
+            ..Default::default()
+        };
+
+        let mut agg = TickAggregator::new(5 * second);
+        let mut bars = Vec::new();
+        for tick in [
+            quote(60 * MINUTE + 20 * second, 110, 2),
+            quote(60 * MINUTE + second, 100, 1),
+            quote(60 * MINUTE + 59 * second, 95, 4),
+            // 다음 분이지만 허용 시간 안이라 60분 봉은 아직 열려 있음
+            quote(61 * MINUTE + 2 * second, 130, 1),
+            quote(60 * MINUTE + 40 * second, 90, 3),
+            quote(62 * MINUTE + 5 * second, 120, 5),
+            // 이미 내보낸 분
+            quote(60 * MINUTE + 50 * second, 500, 9),
+        ] {
+            bars.extend(agg.push(&tick));
+        }
+        assert_eq!(bars.len(), 2);
+        assert_eq!(agg.late_ticks(), 1);
+        bars.extend(agg.flush());
+
+        let first = bars[0];
+        assert_eq!(
+            (
+                { first.ts },
+                { first.open },
+                { first.high },
+                { first.low },
+                { first.close }
+            ),
+            (60 * MINUTE, 101, 111, 91, 96)
+        );
+        assert_eq!((first.total_volume(), { first.spread }), (10, 2));
+        assert_eq!(({ bars[1].ts }, { bars[1].close }), (61 * MINUTE, 131));
+        assert_eq!(({ bars[2].ts }, { bars[2].close }), (62 * MINUTE, 121));
+        assert!(agg.flush().is_empty());
+    }
+
+    #[test]
+    fn spreads_average_over_quotes_and_bars() {
+        let second = 1_000_000_000;
+        let quote = |ts: u64, bid: u32, ask: u32, last: u32| Tick {
+            ts,
+            bid,
+            ask,
+            last,
+            volume: 1,
+            ..Default::default()
+        };
+
+        let mut agg = TickAggregator::new(0);
+        let mut bars = Vec::new();
+        for tick in [
+            quote(second, 100, 101, 0),
+            quote(2 * second, 100, 104, 0),
+            // 체결가만 있는 틱은 평균에서 제외
+            quote(3 * second, 0, 0, 102),
+            quote(4 * second, 100, 102, 0),
+            quote(MINUTE + second, 0, 0, 110),
+        ] {
+            bars.extend(agg.push(&tick));
+        }
+        bars.extend(agg.flush());
+        assert_eq!(({ bars[0].spread }, { bars[0].close }), (2, 101));
+        assert_eq!({ bars[1].spread }, 0);
+
+        // 스프레드가 없는 봉은 빼고 평균
+        let mut minutes = [
+            bar(60 * MINUTE, 10, 10, 10, 10, 1),
+            bar(61 * MINUTE, 10, 10, 10, 10, 1),
+            bar(62 * MINUTE, 10, 10, 10, 10, 1),
+            bar(65 * MINUTE, 10, 10, 10, 10, 1),
+        ];
+        minutes[0].spread = 3;
+        minutes[2].spread = 6;
+        let resampled = resample(&minutes, 300, Alignment::UTC);
+        assert_eq!(({ resampled[0].spread }, { resampled[1].spread }), (5, 0));
+    }
+
+    #[test]
+    fn synthesize_intersects_and_combines_fields() {
+        let a = [
+            bar(0, 200_000, 210_000, 190_000, 200_000, 1),
+            bar(MINUTE, 200_000, 200_000, 200_000, 200_000, 1),
+            bar(2 * MINUTE, 150_000, 150_000, 150_000, 150_000, 1),
+        ];
+        let b = [
+            bar(MINUTE, 50_000, 50_000, 50_000, 50_000, 1),
+            bar(2 * MINUTE, 100_000, 110_000, 90_000, 100_000, 1),
+        ];
+
+        let ratio = SyntheticSpec::Ratio {
+            a: "A".into(),
+            b: "B".into(),
+        };
+        let bars = synthesize(&ratio, &a, &b);
+        assert_eq!(bars.len(), 2);
+        assert_eq!(({ bars[0].ts }, { bars[0].close }), (MINUTE, 400_000));
+        // 필드별 합성 후 high/low 재계산: 150/110 < 150/90
+        assert_eq!(
+            ({ bars[1].open }, { bars[1].high }, { bars[1].low }, {
+                bars[1].close
+            }),
+            (150_000, 166_667, 136_364, 150_000)
+        );
+
+        let spread = SyntheticSpec::Difference {
+            a: "B".into(),
+            b: "A".into(),
+            offset: 1.0,
+        };
+        let bars = synthesize(&spread, &b, &a);
+        // 0.5 - 2.0 + 1.0 < 0 → 0으로 포화, 1.0 - 1.5 + 1.0 = 0.5
+        assert_eq!({ bars[0].close }, 0);
+        assert_eq!({ bars[1].close }, 50_000);
+    }
+
+    #[test]
+    fn cross_follows_currency_algebra() {
+        let pair = |name: &str, scale| Symbol {
+            id: 0,
+            name: name.to_string(),
+            base: name[..3].to_string(),
+            quote: name[3..].to_string(),
+            granularity: Default::default(),
+            scale,
+        };
+        let (eurusd, gbpusd) = (pair("EURUSD", 100_000), pair("GBPUSD", 100_000));
+        let (usdjpy, usdchf) = (pair("USDJPY", 1_000), pair("USDCHF", 100_000));
+
+        // EURUSD × USDJPY, 둘째 다리가 JPY 표시라 그 스케일을 따름
+        let eurjpy = Cross::new(&eurusd, &usdjpy).unwrap();
+        assert_eq!((eurjpy.name(), eurjpy.scale), ("EURJPY".to_string(), 1_000));
+        assert_eq!(eurjpy.price(110_000, 150_000), 165_000);
+        // EURUSD / GBPUSD
+        let eurgbp = Cross::new(&eurusd, &gbpusd).unwrap();
+        assert_eq!(
+            (eurgbp.name(), eurgbp.scale),
+            ("EURGBP".to_string(), 100_000)
+        );
+        assert_eq!(eurgbp.price(110_000, 125_000), 88_000);
+        // USDCHF / USDJPY
+        let jpychf = Cross::new(&usdjpy, &usdchf).unwrap();
+        assert_eq!(jpychf.name(), "JPYCHF");
+        assert_eq!(jpychf.price(125_000, 90_000), 720);
+        // 1 / (USDJPY × EURUSD)
+        let jpyeur = Cross::new(&usdjpy, &eurusd).unwrap();
+        assert_eq!(jpyeur.name(), "JPYEUR");
+        assert_eq!(jpyeur.price(125_000, 100_000), 800);
+        assert_eq!(jpyeur.price(0, 100_000), 0);
+
+        // 공유 통화가 없거나 둘 다 같은 통화쌍
+        assert_eq!(Cross::new(&usdjpy, &pair("EURGBP", 100_000)), None);
+        assert_eq!(Cross::new(&eurusd, &pair("USDEUR", 100_000)), None);
+
+        // high/low는 네 조합의 최대/최소, 빈 분은 버림
+        let a = [
+            bar(0, 110_000, 111_000, 109_000, 110_500, 7),
+            bar(MINUTE, 110_000, 110_000, 110_000, 110_000, 7),
+        ];
+        let b = [
+            bar(0, 150_000, 152_000, 148_000, 151_000, 3),
+            bar(2 * MINUTE, 150_000, 150_000, 150_000, 150_000, 3),
+        ];
+        let bars = synthesize_cross(&eurjpy, &a, &b, 9);
+        assert_eq!(bars.len(), 1);
+        let bar = bars[0];
+        assert_eq!(
+            ({ bar.open }, { bar.high }, { bar.low }, { bar.close }),
+            (165_000, 168_720, 161_320, 166_855)
+        );
+        assert_eq!(({ bar.ts }, { bar.volume }, { bar.symbol_id }), (0, 0, 9));
+        assert!(bar.is_valid());
+    }
+
+    #[test]
+    fn fill_minutes_covers_a_gap() {
+        // 1, 2, (3, 4, 5 비어 있음), 6
+        let records = closes(&[1, 2, 6], 100_000);
+        let (start, end) = (MINUTE, 6 * MINUTE);
+
+        let sparse = fill_minutes(&records, None, start, end, FillPolicy::None);
+        assert_eq!(sparse.len(), 3);
+
+        let zero = fill_minutes(&records, None, start, end, FillPolicy::Zero);
+        let minutes: Vec<u64> = zero.iter().map(|rec| rec.ts / MINUTE).collect();
+        assert_eq!(minutes, vec![1, 2, 3, 4, 5, 6]);
+        assert!(
+            zero[2..5]
+                .iter()
+                .all(|rec| { rec.close } == 0 && { rec.volume } == 0)
+        );
+
+        // 2분 공백까지만 채움
+        let limited = fill_minutes(
+            &records,
+            None,
+            start,
+            end,
+            FillPolicy::ForwardFill { max_gap_secs: 120 },
+        );
+        let minutes: Vec<u64> = limited.iter().map(|rec| rec.ts / MINUTE).collect();
+        assert_eq!(minutes, vec![1, 2, 3, 4, 6]);
+    }
+
+    #[test]
+    fn chart_interval_fits_max_points() {
+        assert_eq!(chart_interval(3600, 1500), 60);
+        assert_eq!(chart_interval(7 * 86400, 1500), 900);
+        assert_eq!(chart_interval(365 * 86400, 1000), 86400);
+        // 주 단위로도 넘치면 주의 배수
+        let interval = chart_interval(10 * 365 * 86400, 100);
+        assert_eq!(interval % (7 * 86400), 0);
+        assert!((10 * 365 * 86400u64).div_ceil(interval) < 100);
+
+        for secs in [60, 900, 4 * 3600, 86400, 3 * 7 * 86400, 90] {
+            assert_eq!(parse_interval(&format_interval(secs)), Some(secs));
+        }
+        assert_eq!(format_interval(4 * 3600), "4h");
+    }
+
+    #[test]
+    fn new_york_session_days_follow_dst_transitions() {
+        const HOUR: u64 = 3600 * 1_000_000_000;
+        let utc = |y, m, d, h| {
+            chrono::NaiveDate::from_ymd_opt(y, m, d)
+                .unwrap()
+                .and_hms_opt(h, 0, 0)
+                .unwrap()
+                .and_utc()
+                .timestamp_nanos_opt()
+                .unwrap() as u64
+        };
+        let hourly = |from: u64, to: u64| -> Vec<OHLCV> {
+            (from..to)
+                .step_by(HOUR as usize)
+                .map(|ts| bar(ts, 10, 10, 10, 10, 1))
+                .collect()
+        };
+        let new_york = Alignment {
+            tz: Some(chrono_tz::America::New_York),
+            session_offset_secs: 17 * 3600,
+        };
+        let days = |records: &[OHLCV]| -> Vec<(u64, u64)> {
+            resample(records, 86400, new_york)
+                .iter()
+                .map(|bar| (bar.ts, bar.total_volume()))
+                .collect()
+        };
+
+        // 2024-03-10 02:00 EST -> EDT: 17:00 경계가 22:00 UTC에서 21:00 UTC로
+        let spring = hourly(utc(2024, 3, 7, 22), utc(2024, 3, 12, 21));
+        assert_eq!(
+            days(&spring),
+            [
+                (utc(2024, 3, 7, 22), 24),
+                (utc(2024, 3, 8, 22), 24),
+                (utc(2024, 3, 9, 22), 23),
+                (utc(2024, 3, 10, 21), 24),
+                (utc(2024, 3, 11, 21), 24),
+            ]
+        );
+
+        // 2024-11-03 02:00 EDT -> EST: 21:00 UTC에서 22:00 UTC로
+        let fall = hourly(utc(2024, 11, 1, 21), utc(2024, 11, 5, 22));
+        assert_eq!(
+            days(&fall),
+            [
+                (utc(2024, 11, 1, 21), 24),
+                (utc(2024, 11, 2, 21), 25),
+                (utc(2024, 11, 3, 22), 24),
+                (utc(2024, 11, 4, 22), 24),
+            ]
+        );
+
+        // 두 번 오는 01:00 (EDT, EST)은 서로 다른 시간봉
+        let hours: Vec<u64> = resample(
+            &hourly(utc(2024, 11, 3, 4), utc(2024, 11, 3, 8)),
+            3600,
+            new_york,
+        )
+        .iter()
+        .map(|bar| bar.ts)
+        .collect();
+        assert_eq!(
+            hours,
+            (4..8).map(|h| utc(2024, 11, 3, h)).collect::<Vec<_>>()
+        );
+
+        // 시간대 없이 오프셋만 주면 고정 UTC 경계
+        let fixed = Alignment {
+            tz: None,
+            session_offset_secs: 22 * 3600,
+        };
+        let starts: Vec<u64> = resample(&spring, 86400, fixed)
+            .iter()
+            .map(|bar| bar.ts)
+            .collect();
+        assert_eq!(
+            starts[..4],
+            [
+                utc(2024, 3, 7, 22),
+                utc(2024, 3, 8, 22),
+                utc(2024, 3, 9, 22),
+                utc(2024, 3, 10, 22)
+            ]
+        );
+    }
+
+    #[test]
+    fn lttb_keeps_spikes_and_endpoints() {
+        // 평평한 시계열 속 급등 하나, 급락 하나
+        let records: Vec<OHLCV> = (1..=1000u64)
+            .map(|i| {
+                let close = match i {
+                    317 => 500,
+                    733 => 1,
+                    _ => 100 + (i % 3) as u32,
+                };
+                bar(i * MINUTE, close, close, close, close, 1)
+            })
+            .collect();
+
+        let reduced = downsample_lttb(&records, 20);
+        assert_eq!(reduced.len(), 20);
+        assert_eq!({ reduced[0].ts }, MINUTE);
+        assert_eq!({ reduced[19].ts }, 1000 * MINUTE);
+        assert!(reduced.windows(2).all(|w| w[0].ts < w[1].ts));
+        // 고른 봉은 원본 그대로
+        assert!(reduced.iter().all(|rec| {
+            let original = records[(rec.ts / MINUTE - 1) as usize];
+            (original.ts, original.close) == (rec.ts, rec.close)
+        }));
+        let closes: Vec<u32> = reduced.iter().map(|rec| rec.close).collect();
+        assert!(closes.contains(&500) && closes.contains(&1), "{:?}", closes);
+
+        // 톱니파는 구간마다 꼭짓점(0, 1, 9, 10) 근처가 아닌 봉을 고르지 않음
+        let saw: Vec<OHLCV> = (0..=40u64)
+            .map(|i| {
+                let close = if (i / 10) % 2 == 0 {
+                    i % 10
+                } else {
+                    10 - i % 10
+                } as u32;
+                bar((i + 1) * MINUTE, close, close, close, close, 1)
+            })
+            .collect();
+        let peaks: Vec<u32> = downsample_lttb(&saw, 6)
+            .iter()
+            .map(|rec| rec.close)
+            .collect();
+        assert_eq!(peaks.len(), 6);
+        assert!(
+            peaks.iter().all(|close| [0, 1, 9, 10].contains(close)),
+            "{:?}",
+            peaks
+        );
 
-    pub fn rsi(records: &[OHLCV], period: usize) -> Vec<f64> {
-        // RSI 계산 로직...
-        vec![]
+        // 이미 예산 안이거나 예산이 너무 작으면 그대로 (빈 슬롯만 빠짐)
+        let mut short = records[..5].to_vec();
+        short.push(OHLCV::default());
+        assert_eq!(downsample_lttb(&short, 10).len(), 5);
+        assert_eq!(downsample_lttb(&records, 2).len(), 1000);
     }
 }
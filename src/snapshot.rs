@@ -0,0 +1,191 @@
+//! 스냅샷 파일: 심볼 몇 개의 압축 블록을 그대로 담은 이식용 단일 파일
+//!
+//! 16바이트 헤더(magic + version + reserved), 인덱스 길이(u64) + bincode 인덱스,
+//! 그 뒤에 블록 데이터를 이어 붙임. 인덱스의 offset은 데이터 영역 시작 기준
+
+use crate::block::BlockCodec;
+use crate::error::{FxStoreError, Result};
+use crate::types::Granularity;
+use serde::{Deserialize, Serialize};
+use std::fs::File;
+use std::io::{BufWriter, Write};
+use std::path::Path;
+use std::sync::Arc;
+
+const MAGIC: [u8; 8] = *b"FXSNAP01";
+const VERSION: u32 = 1;
+const HEADER_LEN: usize = 16; // magic + version + reserved
+
+/// 스냅샷에 담긴 심볼 (symbol_id는 가져오는 쪽에서 새로 매김)
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct SnapshotSymbol {
+    pub name: String,
+    pub granularity: Granularity,
+    /// 블록 가격의 스케일 (`Symbol::scale`)
+    pub scale: u32,
+}
+
+/// 압축 블록 하나 (`symbol`은 `SnapshotSymbol` 목록의 인덱스)
+#[derive(Clone, Debug)]
+pub struct SnapshotBlock {
+    pub symbol: u16,
+    pub date: u32, // YYYYMMDD
+    pub codec: BlockCodec,
+    /// 압축 데이터의 XXH64
+    pub checksum: u64,
+    pub data: Arc<Vec<u8>>,
+}
+
+#[derive(Serialize, Deserialize)]
+struct IndexEntry {
+    symbol: u16,
+    date: u32,
+    codec: BlockCodec,
+    checksum: u64,
+    offset: u64,
+    len: u64,
+}
+
+#[derive(Serialize, Deserialize)]
+struct Index {
+    symbols: Vec<SnapshotSymbol>,
+    blocks: Vec<IndexEntry>,
+}
+
+/// 스냅샷 쓰기 (임시 파일에 쓴 뒤 이름 변경)
+pub fn write(
+    path: impl AsRef<Path>,
+    symbols: &[SnapshotSymbol],
+    blocks: &[SnapshotBlock],
+) -> Result<()> {
+    let path = path.as_ref();
+    let mut offset = 0;
+    let entries = blocks
+        .iter()
+        .map(|block| {
+            let entry = IndexEntry {
+                symbol: block.symbol,
+                date: block.date,
+                codec: block.codec,
+                checksum: block.checksum,
+                offset,
+                len: block.data.len() as u64,
+            };
+            offset += entry.len;
+            entry
+        })
+        .collect();
+    let index = bincode::serialize(&Index {
+        symbols: symbols.to_vec(),
+        blocks: entries,
+    })?;
+
+    let tmp_path = path.with_extension("tmp");
+    let mut writer = BufWriter::new(File::create(&tmp_path)?);
+    let mut header = [0u8; HEADER_LEN];
+    header[..8].copy_from_slice(&MAGIC);
+    header[8..12].copy_from_slice(&VERSION.to_le_bytes());
+    writer.write_all(&header)?;
+    writer.write_all(&(index.len() as u64).to_le_bytes())?;
+    writer.write_all(&index)?;
+    for block in blocks {
+        writer.write_all(&block.data)?;
+    }
+    writer.flush()?;
+    writer.get_ref().sync_all()?;
+    std::fs::rename(&tmp_path, path)?;
+    Ok(())
+}
+
+/// 스냅샷 읽기 (블록 체크섬은 블록을 해제할 때 검증)
+pub fn read(path: impl AsRef<Path>) -> Result<(Vec<SnapshotSymbol>, Vec<SnapshotBlock>)> {
+    let bytes = std::fs::read(path)?;
+    if bytes.len() < HEADER_LEN + 8 || bytes[..8] != MAGIC {
+        return Err(FxStoreError::Corruption("not an fx-store snapshot".into()));
+    }
+    let version = u32::from_le_bytes(bytes[8..12].try_into().unwrap());
+    if version != VERSION {
+        return Err(FxStoreError::Corruption(format!(
+            "unsupported snapshot version {}",
+            version
+        )));
+    }
+
+    let index_len = u64::from_le_bytes(bytes[HEADER_LEN..HEADER_LEN + 8].try_into().unwrap());
+    let index_start = HEADER_LEN + 8;
+    let data_start = usize::try_from(index_len)
+        .ok()
+        .and_then(|len| index_start.checked_add(len))
+        .filter(|&end| end <= bytes.len())
+        .ok_or_else(|| FxStoreError::Corruption("truncated snapshot index".into()))?;
+    let index: Index = bincode::deserialize(&bytes[index_start..data_start])?;
+    let data = &bytes[data_start..];
+
+    let blocks = index
+        .blocks
+        .into_iter()
+        .map(|entry| {
+            let slice = usize::try_from(entry.offset)
+                .ok()
+                .zip(usize::try_from(entry.len).ok())
+                .and_then(|(offset, len)| data.get(offset..offset.checked_add(len)?));
+            match slice {
+                Some(slice) if (entry.symbol as usize) < index.symbols.len() => Ok(SnapshotBlock {
+                    symbol: entry.symbol,
+                    date: entry.date,
+                    codec: entry.codec,
+                    checksum: entry.checksum,
+                    data: Arc::new(slice.to_vec()),
+                }),
+                _ => Err(FxStoreError::Corruption(format!(
+                    "snapshot block {} is out of bounds",
+                    entry.date
+                ))),
+            }
+        })
+        .collect::<Result<Vec<_>>>()?;
+    Ok((index.symbols, blocks))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn blocks_round_trip_and_truncation_is_detected() {
+        let path =
+            std::env::temp_dir().join(format!("fx-store-{}-snapshot.fxs", std::process::id()));
+        let symbols = vec![SnapshotSymbol {
+            name: "XAUUSD".to_string(),
+            granularity: Granularity::Minute,
+            scale: 100,
+        }];
+        let block = |date, data: &[u8]| SnapshotBlock {
+            symbol: 0,
+            date,
+            codec: BlockCodec::Gorilla,
+            checksum: 7,
+            data: Arc::new(data.to_vec()),
+        };
+        write(
+            &path,
+            &symbols,
+            &[block(20240102, b"abc"), block(20240103, b"de")],
+        )
+        .unwrap();
+
+        let (read_symbols, blocks) = read(&path).unwrap();
+        assert_eq!(read_symbols, symbols);
+        let blocks: Vec<_> = blocks.iter().map(|b| (b.date, b.data.as_slice())).collect();
+        assert_eq!(blocks, [(20240102, &b"abc"[..]), (20240103, &b"de"[..])]);
+
+        // 마지막 블록 데이터가 잘림
+        let bytes = std::fs::read(&path).unwrap();
+        std::fs::write(&path, &bytes[..bytes.len() - 1]).unwrap();
+        assert!(matches!(read(&path), Err(FxStoreError::Corruption(_))));
+
+        std::fs::write(&path, b"FXSTORE1 not a snapshot").unwrap();
+        assert!(matches!(read(&path), Err(FxStoreError::Corruption(_))));
+        std::fs::remove_file(&path).ok();
+    }
+}
@@ -1,202 +1,6566 @@
-use crate::block::CompressedBlock;
-use crate::types::{OHLCV, Symbol};
+use crate::block::{
+    BlockCodec, BlockLayout, CompressedBlock, DEFAULT_ZSTD_LEVEL, TickBlock, coalesce_bars,
+};
+use crate::csv_format::{CsvFormat, parse_histdata_tick};
+use crate::error::{FxStoreError, QueryError, Result};
+#[cfg(feature = "persistence")]
+use crate::mmap_format::{BlockRecord, BlockSummary, PersistentStore};
+#[cfg(feature = "parquet")]
+use crate::parquet_format::{BarBatchBuilder, BarReader, ColumnMapping, bar_writer};
+use crate::query::{
+    AlignedFrame, Alignment, Cross, FillPolicy, SimdFilter, SyntheticSpec, TechnicalIndicators,
+    TickAggregator, align_closes, fill_minutes, resample, synthesize, synthesize_cross,
+};
+#[cfg(feature = "persistence")]
+use crate::snapshot::{self, SnapshotBlock, SnapshotSymbol};
+use crate::types::{
+    Granularity, MAX_TS, NANOS_PER_DAY, OHLCV, PRICE_SCALE, PriceField, Symbol, Tick, TimeRange,
+    date_to_ts, ts_to_date,
+};
+#[cfg(feature = "persistence")]
+use crate::wal::{FsyncPolicy, Wal, WalEntry};
 use ahash::RandomState;
-use crossbeam::channel::{Receiver, Sender, bounded};
+#[cfg(feature = "parquet")]
+use arrow_array::RecordBatch;
+use chrono_tz::Tz;
+#[cfg(feature = "persistence")]
+use crossbeam::channel::RecvTimeoutError;
+use crossbeam::channel::{Receiver, Sender, TrySendError, bounded, select, unbounded};
 use dashmap::DashMap;
-use std::sync::atomic::AtomicU64;
+use dashmap::mapref::entry::Entry;
+use dashmap::mapref::one::Ref;
+use parking_lot::{Mutex, RwLock};
+use serde::{Deserialize, Serialize};
+use std::borrow::Cow;
+use std::collections::{BTreeMap, BTreeSet, HashMap, HashSet, VecDeque};
+use std::sync::Arc;
+use std::sync::atomic::{AtomicBool, AtomicU32, AtomicU64, Ordering};
+use std::time::{Duration, Instant};
+use xxhash_rust::xxh64::xxh64;
+
+type SymbolBlocks = DashMap<u32, CompressedBlock, RandomState>;
+type BlockMap = DashMap<u16, SymbolBlocks, RandomState>;
+type LastPriceMap = DashMap<u16, OHLCV, RandomState>;
+type TickBlockMap = DashMap<u16, DashMap<u32, TickBlock, RandomState>, RandomState>;
+type DirtySet = Mutex<HashSet<(u16, u32)>>;
+/// 틱 입력 채널과 마감 요청 채널 (요청마다 완료 알림용 Sender를 보냄)
+type TickChannels = (Sender<Tick>, Sender<Sender<()>>);
 
 pub struct FxStore {
     /// symbol_id -> date -> block
-    blocks: DashMap<u16, DashMap<u32, CompressedBlock, RandomState>, RandomState>,
+    blocks: Arc<BlockMap>,
 
     /// 심볼 테이블
-    symbols: DashMap<String, Symbol>,
+    symbols: Arc<DashMap<String, Symbol>>,
+
+    /// symbol_id -> 심볼 이름 (`symbol_name`, `symbols`와 함께 갱신)
+    symbol_names: DashMap<u16, String, RandomState>,
+
+    /// 다음에 등록할 심볼의 id (동시 등록이나 이름 변경 중에도 겹치지 않게 테이블 크기 대신 사용,
+    /// u16 범위를 넘으면 더 등록하지 않음)
+    next_symbol_id: AtomicU32,
+
+    /// 별칭 -> 심볼 이름 (`add_alias`)
+    aliases: DashMap<String, String>,
+
+    /// symbol_id -> date -> 틱 블록
+    tick_blocks: Arc<TickBlockMap>,
+
+    /// symbol_id -> 실시간 틱 집계 스레드 입력 채널과 마감 요청 채널
+    tick_senders: DashMap<u16, TickChannels, RandomState>,
+
+    /// `stream_realtime`/`subscribe_all` 구독자
+    subscribers: Arc<Subscribers>,
+
+    /// symbol_id -> 최신 바 (쓰기 시 갱신, /price O(1) 조회용)
+    last_prices: Arc<LastPriceMap>,
 
     /// 통계
-    stats: StoreStats,
+    stats: Arc<StoreStats>,
+
+    /// 해제 캐시 사용량 (`StoreConfig::cache_bytes`가 있을 때만 추적)
+    cache: Mutex<BlockCache>,
+
+    /// 심볼 -> 보존 일수 (`set_retention`)
+    retention: DashMap<String, u32>,
+
+    /// 스토어 설정
+    config: StoreConfig,
+
+    /// 크래시 복구용 WAL (`recover`로 연 경우에만)
+    #[cfg(feature = "persistence")]
+    wal: Option<Arc<Wal>>,
+
+    /// 블록 파일에 아직 기록하지 않은 (symbol_id, 날짜)
+    dirty: Arc<DirtySet>,
+
+    /// WAL 기록과 블록 반영을 묶는 잠금 (쓰기는 read, 플러시는 write)
+    gate: Arc<RwLock<()>>,
+
+    /// 블록 파일 (`open`으로 연 경우에만)
+    #[cfg(feature = "persistence")]
+    flusher: Option<Arc<BlockFlusher>>,
+
+    /// 주기적 플러시 스레드 (Sender를 버리면 종료)
+    flush_thread: Option<(Sender<()>, std::thread::JoinHandle<()>)>,
+
+    /// 백그라운드 압축 대기열 (워커마다 하나)
+    compress: CompressQueue,
+    compress_handles: Vec<std::thread::JoinHandle<()>>,
+
+    /// (심볼, 파일 내용 XXH64) -> 임포트 기록 (같은 내용은 `ImportOptions::force` 없이 다시 임포트하지 않음)
+    imported: Mutex<HashMap<(String, u64), ImportedFile>>,
+
+    /// 임포트 기록 파일 (`open`으로 연 경우에만)
+    imports_file: Option<std::path::PathBuf>,
+
+    /// 손상이 확인된 (symbol_id, 날짜) (블록이 교체되면 `corrupt_days`가 지움)
+    corrupt: Mutex<BTreeSet<(u16, u32)>>,
+
+    /// 초기 데이터 적재가 끝났는지 (`set_ready`)
+    ready: AtomicBool,
+
+    /// 스토어를 만든 시각 (`uptime`)
+    created_at: Instant,
+}
+
+/// 압축 워커로 보내는 작업
+enum CompressJob {
+    Batch {
+        date: u32,
+        symbol_id: u16,
+        layout: BlockLayout,
+        records: Vec<OHLCV>,
+    },
+    Ticks {
+        date: u32,
+        symbol_id: u16,
+        ticks: Vec<Tick>,
+    },
+    /// 앞선 작업이 모두 처리되면 응답
+    Flush(Sender<()>),
+    /// 앞선 작업을 모두 처리한 뒤 워커 종료 (스토어 해제 시)
+    Stop,
+}
+
+impl CompressJob {
+    /// 같은 심볼·날짜의 작업은 같은 워커로 보내 병합 순서를 유지
+    fn shard(&self, workers: usize) -> usize {
+        match self {
+            Self::Batch {
+                date, symbol_id, ..
+            }
+            | Self::Ticks {
+                date, symbol_id, ..
+            } => (*symbol_id as usize * 31 + *date as usize) % workers,
+            Self::Flush(_) | Self::Stop => 0,
+        }
+    }
+}
+
+/// 압축 워커별 bounded 채널과 가득 찼을 때의 정책
+#[derive(Clone)]
+struct CompressQueue {
+    /// DropOldest가 가장 오래된 작업을 꺼낼 수 있도록 Receiver도 보관
+    shards: Arc<[(Sender<CompressJob>, Receiver<CompressJob>)]>,
+    policy: OverflowPolicy,
+}
+
+impl CompressQueue {
+    fn new(workers: usize, capacity: usize, policy: OverflowPolicy) -> Self {
+        let shards = (0..workers.max(1))
+            .map(|_| bounded(capacity.max(1)))
+            .collect();
+        Self { shards, policy }
+    }
+
+    /// 배치 작업을 넣고, 자리를 만들려고 버린 앞선 배치 수를 반환
+    ///
+    /// 워커가 종료된 뒤(스토어 해제 중)의 작업은 조용히 버림
+    fn send(&self, job: CompressJob) -> Result<u64> {
+        let (tx, rx) = &self.shards[job.shard(self.shards.len())];
+        match self.policy {
+            OverflowPolicy::Block => {
+                tx.send(job).ok();
+                Ok(0)
+            }
+            OverflowPolicy::Error => match tx.try_send(job) {
+                Err(TrySendError::Full(_)) => Err(FxStoreError::QueueFull),
+                _ => Ok(0),
+            },
+            OverflowPolicy::DropOldest => {
+                let mut job = job;
+                let mut dropped = 0;
+                // 연달아 다시 넣은 배리어 수 (대기열이 배리어뿐이면 워커를 기다림)
+                let mut barriers = 0;
+                loop {
+                    match tx.try_send(job) {
+                        Err(TrySendError::Full(rejected))
+                            if barriers < tx.capacity().unwrap_or(0) =>
+                        {
+                            job = rejected;
+                            match rx.try_recv() {
+                                // Flush/Stop은 버리지 않고 뒤에 다시 넣음 (앞선 작업을 기다리는 건 그대로)
+                                Ok(barrier @ (CompressJob::Flush(_) | CompressJob::Stop)) => {
+                                    barriers += 1;
+                                    tx.send(barrier).ok();
+                                }
+                                Ok(_) => {
+                                    barriers = 0;
+                                    dropped += 1;
+                                }
+                                Err(_) => {}
+                            }
+                        }
+                        Err(TrySendError::Full(rejected)) => {
+                            tx.send(rejected).ok();
+                            return Ok(dropped);
+                        }
+                        _ => return Ok(dropped),
+                    }
+                }
+            }
+        }
+    }
+
+    /// 모든 워커가 앞선 작업을 처리할 때까지 대기 (정책과 무관하게 블로킹 전송)
+    fn barrier(&self) {
+        let acks: Vec<Receiver<()>> = self
+            .shards
+            .iter()
+            .filter_map(|(tx, _)| {
+                let (done, ack) = bounded(1);
+                tx.send(CompressJob::Flush(done)).is_ok().then_some(ack)
+            })
+            .collect();
+        for ack in acks {
+            ack.recv().ok();
+        }
+    }
+
+    /// 워커마다 종료 작업을 보냄 (정책과 무관하게 블로킹 전송)
+    fn stop(&self) {
+        for (tx, _) in self.shards.iter() {
+            tx.send(CompressJob::Stop).ok();
+        }
+    }
+
+    fn len(&self) -> usize {
+        self.shards.iter().map(|(tx, _)| tx.len()).sum()
+    }
+}
+
+/// 잘못된 OHLC 행 처리 방식
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum OnInvalid {
+    /// 건너뛰고 개수만 집계
+    #[default]
+    Skip,
+    /// 첫 잘못된 행에서 임포트 중단 (`FxStoreError::Parse`/`PriceOverflow`, 실패 행 포함)
+    Error,
+}
+
+/// 임포트한 행의 날짜에 이미 블록이 있을 때 처리 방식
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum ExistingDays {
+    /// 기존 블록과 병합 (같은 슬롯은 새 행 우선)
+    #[default]
+    Merge,
+    /// 그 날짜의 행은 모두 건너뜀 (증분 임포트)
+    Skip,
+}
+
+/// 쿼리가 손상된 블록(체크섬 불일치, 해제 실패)을 만났을 때 처리 방식
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum OnCorrupt {
+    /// 그 날짜를 빼고 나머지를 반환 (`FxStore::corrupt_days`로 확인)
+    #[default]
+    Skip,
+    /// 쿼리를 `FxStoreError::BlockCorrupt`로 중단
+    Error,
+}
+
+impl std::str::FromStr for OnCorrupt {
+    type Err = String;
+
+    /// `skip`, `error`
+    fn from_str(s: &str) -> std::result::Result<Self, Self::Err> {
+        match s {
+            "skip" => Ok(Self::Skip),
+            "error" => Ok(Self::Error),
+            _ => Err(format!("expected skip or error, got {:?}", s)),
+        }
+    }
+}
+
+/// 압축 대기열이 가득 찼을 때 임포트 배치 처리 방식
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum OverflowPolicy {
+    /// 자리가 날 때까지 임포트가 대기 (배압)
+    #[default]
+    Block,
+    /// 가장 오래된 대기 배치를 버리고 넣음 (`ImportReport::dropped_batches`에 집계).
+    /// `flush` 대기는 버리지 않으므로 대기열이 그것뿐이면 `Block`처럼 기다림
+    DropOldest,
+    /// 임포트를 `FxStoreError::QueueFull`로 중단
+    Error,
+}
+
+/// 스토어 설정
+#[derive(Clone, Debug)]
+pub struct StoreConfig {
+    /// 임포트 시 OHLC 불변식 검사 (low <= open/close <= high)
+    pub validate: bool,
+    pub on_invalid: OnInvalid,
+    /// CSV 타임스탬프의 기본 시간대 (HISTDATA는 `histdata_est()`, 서머타임은 시각마다 적용)
+    pub source_tz: Tz,
+    /// 실시간 틱 집계: 분이 바뀐 뒤에도 이전 분의 틱을 받아주는 시간
+    pub tick_tolerance: Duration,
+    /// 실시간 틱 집계: 틱이 이 시간 동안 없으면 진행 중인 봉을 완성 (이후 그 분의 틱은 버림)
+    pub tick_idle_timeout: Duration,
+    /// 실시간 구독자 채널 크기 (가득 차면 봉을 건너뜀)
+    pub subscriber_capacity: usize,
+    /// 새로 만드는 블록의 압축 방식 (기존 블록은 병합 시에도 자기 방식 유지)
+    pub codec: BlockCodec,
+    /// `BlockCodec::ZstdColumns` 압축 레벨
+    pub zstd_level: i32,
+    /// 해제된 블록 캐시 상한 (None이면 무제한, 넘으면 먼저 캐시된 블록부터 해제)
+    pub cache_bytes: Option<usize>,
+    /// 압축 워커당 대기열 크기 (가득 차면 `compress_overflow`에 따름)
+    pub compress_queue: usize,
+    pub compress_overflow: OverflowPolicy,
+    /// 압축 워커 스레드 수 (임포트 시 zstd 압축이 병목)
+    pub compress_workers: usize,
+    /// `open`으로 연 스토어가 변경된 블록을 블록 파일에 쓰는 주기 (0이면 `flush_now`로만)
+    pub flush_interval: Duration,
+    /// WAL fsync 시점 (삽입·임포트·실시간 집계 봉의 전원 장애 내구성과 처리량의 균형)
+    #[cfg(feature = "persistence")]
+    pub fsync_policy: FsyncPolicy,
+    /// 손상된 블록을 만난 쿼리 처리 (`open`도 손상된 블록을 건너뛸지 여기에 따름)
+    pub on_corrupt: OnCorrupt,
+}
+
+impl Default for StoreConfig {
+    fn default() -> Self {
+        Self {
+            validate: false,
+            on_invalid: OnInvalid::Skip,
+            source_tz: Tz::UTC,
+            tick_tolerance: Duration::from_secs(2),
+            tick_idle_timeout: Duration::from_secs(60),
+            subscriber_capacity: 10_000,
+            codec: BlockCodec::default(),
+            zstd_level: DEFAULT_ZSTD_LEVEL,
+            cache_bytes: None,
+            compress_queue: 1000,
+            compress_overflow: OverflowPolicy::Block,
+            compress_workers: std::thread::available_parallelism().map_or(1, |n| n.get().min(4)),
+            flush_interval: Duration::from_secs(30),
+            #[cfg(feature = "persistence")]
+            fsync_policy: FsyncPolicy::OnSeal,
+            on_corrupt: OnCorrupt::Skip,
+        }
+    }
+}
+
+impl StoreConfig {
+    /// 기본값에 환경 변수 적용 (`FX_STORE_CACHE_MB`, `FX_STORE_CODEC`, `FX_STORE_ZSTD_LEVEL`,
+    /// `FX_STORE_FLUSH_SECS`, `FX_STORE_COMPRESS_QUEUE`, `FX_STORE_COMPRESS_WORKERS`, `FX_STORE_WAL_FSYNC`,
+    /// `FX_STORE_ON_CORRUPT`)
+    pub fn from_env() -> Result<Self> {
+        let mut config = Self::default();
+        if let Some(mb) = env_var::<usize>("FX_STORE_CACHE_MB")? {
+            config.cache_bytes = Some(mb << 20);
+        }
+        if let Some(codec) = env_var("FX_STORE_CODEC")? {
+            config.codec = codec;
+        }
+        if let Some(level) = env_var("FX_STORE_ZSTD_LEVEL")? {
+            config.zstd_level = level;
+        }
+        if let Some(secs) = env_var("FX_STORE_FLUSH_SECS")? {
+            config.flush_interval = Duration::from_secs(secs);
+        }
+        if let Some(capacity) = env_var("FX_STORE_COMPRESS_QUEUE")? {
+            config.compress_queue = capacity;
+        }
+        if let Some(workers) = env_var("FX_STORE_COMPRESS_WORKERS")? {
+            config.compress_workers = workers;
+        }
+        #[cfg(feature = "persistence")]
+        if let Some(policy) = env_var("FX_STORE_WAL_FSYNC")? {
+            config.fsync_policy = policy;
+        }
+        if let Some(policy) = env_var("FX_STORE_ON_CORRUPT")? {
+            config.on_corrupt = policy;
+        }
+        Ok(config)
+    }
+}
+
+/// 설정된 환경 변수 파싱 (없으면 None)
+fn env_var<T>(key: &str) -> Result<Option<T>>
+where
+    T: std::str::FromStr,
+    T::Err: std::fmt::Display,
+{
+    match std::env::var(key) {
+        Ok(value) => value
+            .trim()
+            .parse()
+            .map(Some)
+            .map_err(|e| FxStoreError::parse(format!("{}={:?}: {}", key, value, e))),
+        Err(_) => Ok(None),
+    }
+}
+
+/// 캐시에 올라간 순서대로 기억해 상한을 넘으면 오래된 것부터 해제
+#[derive(Default)]
+struct BlockCache {
+    order: VecDeque<CompressedBlock>,
+    bytes: usize,
+}
+
+impl BlockCache {
+    fn admit(&mut self, block: &CompressedBlock, budget: usize) {
+        self.order.push_back(block.clone());
+        self.bytes += block.decompressed_bytes();
+        // 방금 올린 블록은 남김 (예산이 블록 하나보다 작아도 쿼리는 동작)
+        while self.bytes > budget && self.order.len() > 1 {
+            let evicted = self.order.pop_front().unwrap();
+            self.bytes -= evicted.decompressed_bytes();
+            evicted.evict();
+        }
+    }
+
+    /// 삭제된 블록을 예산에서 제외
+    fn forget(&mut self, symbol_id: u16, date: u32) {
+        let bytes = &mut self.bytes;
+        self.order.retain(|block| {
+            let keep = block.symbol_id != symbol_id || block.date != date;
+            if !keep {
+                *bytes -= block.decompressed_bytes();
+            }
+            keep
+        });
+    }
+}
+
+/// 파일 단위 임포트 옵션 (None이면 `StoreConfig` 값 사용)
+#[derive(Clone, Debug, Default)]
+pub struct ImportOptions {
+    /// 명시하면 파일 포맷 시간대보다 우선
+    pub source_tz: Option<Tz>,
+    /// None이면 첫 행으로 자동 감지
+    pub format: Option<CsvFormat>,
+    /// 같은 심볼로 이미 임포트한 파일과 내용이 같아도 다시 임포트
+    pub force: bool,
+    /// 스토어에 이미 있는 날짜의 행 처리 (기본은 기존 블록과 병합)
+    pub existing_days: ExistingDays,
+    /// 앞쪽 `SCALE_SAMPLE_ROWS`행의 소수 자릿수로 심볼 가격 스케일 결정
+    /// (심볼에 아직 데이터가 없을 때만 적용, 이후 행의 넘치는 자릿수는 반올림)
+    pub detect_scale: bool,
+}
+
+/// 가격 스케일 감지에 쓰는 행 수
+pub const SCALE_SAMPLE_ROWS: usize = 100;
+
+/// `import_csv` 결과
+#[derive(Clone, Debug, Default, Serialize)]
+pub struct ImportReport {
+    pub imported: usize,
+    /// 파싱 실패로 건너뛴 행 수
+    pub skipped: usize,
+    /// (라인 번호, 사유), 최대 `MAX_REPORTED_ERRORS`개
+    pub errors: Vec<(usize, String)>,
+    /// 처리한 일 단위 배치 수
+    pub days: usize,
+    /// OHLC 검증에서 거부된 행 수
+    pub rejected: usize,
+    /// 같은 슬롯(기본 1분)의 다른 행과 하나의 봉으로 병합된 행 수 (`imported`에 포함)
+    pub merged: usize,
+    /// `OverflowPolicy::DropOldest`로 대기열에서 밀려난 일 배치 수
+    pub dropped_batches: u64,
+    /// 같은 내용의 파일을 이미 임포트해 아무것도 하지 않음
+    pub already_imported: bool,
+    /// 이미 블록이 있어 건너뛴 UTC 날짜 수 (`ExistingDays::Skip`)
+    pub days_present: usize,
+}
+
+/// 임포트한 CSV 파일 기록 (`IMPORTS_FILE`의 한 줄)
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct ImportedFile {
+    pub symbol: String,
+    /// 파일 내용의 XXH64
+    pub hash: u64,
+    pub path: String,
+    /// 임포트할 때의 파일 크기와 수정 시각 (epoch nanos, 모르면 0)
+    #[serde(default)]
+    pub size: u64,
+    #[serde(default)]
+    pub mtime: u64,
+    pub imported: usize,
+}
 
-    /// 백그라운드 압축 채널
-    compress_tx: Sender<(u32, u16, Vec<OHLCV>)>,
-    compress_handle: Option<std::thread::JoinHandle<()>>,
+/// `import_snapshot` 결과
+#[derive(Clone, Debug, Default, Serialize)]
+pub struct SnapshotReport {
+    /// 스냅샷에 담긴 심볼 수
+    pub symbols: usize,
+    /// 가져온 블록 수
+    pub blocks: usize,
+    /// 그중 기존 날짜와 병합한 블록 수
+    pub merged: usize,
+    /// 가져온 봉 수
+    pub records: usize,
 }
 
+/// `FxStore::open`이 데이터 디렉터리에 두는 임포트 기록 파일 이름
+pub const IMPORTS_FILE: &str = "fx-store.imports";
+
+/// `FxStore::open`이 데이터 디렉터리에 두는 WAL 파일 이름
+pub const WAL_FILE: &str = "fx-store.wal";
+
+/// `open`이 데이터 디렉터리에 두는 블록 파일
+pub const BLOCK_FILE: &str = "fx-store.fxd";
+
+/// `query_range_async`가 병렬 해제로 넘어가는 범위 길이 (일)
+pub const PARALLEL_QUERY_DAYS: u64 = 7;
+
+/// ImportReport에 사유를 남기는 최대 오류 수 (개수는 `skipped`에 모두 집계)
+const MAX_REPORTED_ERRORS: usize = 100;
+
 #[derive(Default)]
 struct StoreStats {
     total_records: AtomicU64,
     compressed_bytes: AtomicU64,
     cache_hits: AtomicU64,
+    blocks_decompressed: AtomicU64,
+    query_records: AtomicU64,
+    last_price_hits: AtomicU64,
+    last_price_misses: AtomicU64,
+    late_ticks: AtomicU64,
+    dropped_bars: AtomicU64,
+    dropped_batches: AtomicU64,
+    imported_records: AtomicU64,
+    import_nanos: AtomicU64,
+    /// symbol_id -> 블록이 바뀐 횟수 (`write_generation`)
+    block_writes: DashMap<u16, u64, RandomState>,
+}
+
+impl StoreStats {
+    /// 블록 변경이 보이게 된 뒤에 호출 (먼저 올리면 캐시가 변경 전 결과를 새 세대로 저장할 수 있음)
+    fn bump_generation(&self, symbol_id: u16) {
+        *self.block_writes.entry(symbol_id).or_insert(0) += 1;
+    }
+}
+
+/// 과거 봉 재생 속도 (`FxStore::replay`, 봉 사이 대기는 ts 차이 기준)
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum ReplaySpeed {
+    /// 기록된 시간 그대로 (1분봉은 1분에 한 개)
+    Realtime,
+    /// 기록된 시간보다 n배 빠르게 (0 이하나 NaN은 `AsFastAsPossible`)
+    Multiplier(f64),
+    /// 기다리지 않음 (수신 측이 읽는 만큼)
+    AsFastAsPossible,
+}
+
+impl ReplaySpeed {
+    /// 첫 봉보다 `elapsed_nanos` 늦은 봉을 보낼 시점 (재생 시작 기준, None이면 바로)
+    fn offset(self, elapsed_nanos: u64) -> Option<Duration> {
+        let multiplier = match self {
+            ReplaySpeed::Realtime => 1.0,
+            ReplaySpeed::Multiplier(m) if m > 0.0 => m,
+            _ => return None,
+        };
+        Duration::try_from_secs_f64(elapsed_nanos as f64 / 1e9 / multiplier).ok()
+    }
+}
+
+/// 캐시된 해제 블록의 일부를 빌린 뷰 (Arc가 블록을 유지)
+pub struct BlockView {
+    data: Arc<[OHLCV]>,
+    range: std::ops::Range<usize>,
+}
+
+impl std::ops::Deref for BlockView {
+    type Target = [OHLCV];
+
+    fn deref(&self) -> &[OHLCV] {
+        &self.data[self.range.clone()]
+    }
+}
+
+/// `FxStore::query_stats` 결과
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Serialize)]
+pub struct RangeStats {
+    pub min_low: u32,
+    pub max_high: u32,
+    pub total_volume: u64,
+    pub count: usize,
+}
+
+/// `FxStore::blocks_info` 항목 (일 블록 하나)
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Serialize)]
+pub struct BlockInfo {
+    /// YYYYMMDD
+    pub date: u32,
+    pub compressed_bytes: usize,
+    /// 비어있지 않은 슬롯 수
+    pub record_count: u32,
+    /// OHLC 전체의 최저/최고가 (봉이 없으면 min_price > max_price)
+    pub min_price: u32,
+    pub max_price: u32,
+}
+
+/// `FxStore::scrub` 결과
+#[derive(Clone, Debug, Default, Serialize)]
+pub struct ScrubReport {
+    pub blocks_checked: usize,
+    /// 손상된 블록 (심볼, 날짜순)
+    pub corrupt: Vec<CorruptBlock>,
+    /// `repair`로 지운 블록 수
+    pub removed: usize,
+}
+
+/// 검사에 실패한 블록
+#[derive(Clone, Debug, Serialize)]
+pub struct CorruptBlock {
+    pub symbol: String,
+    /// YYYYMMDD
+    pub date: u32,
+    pub reason: String,
+}
+
+/// 실시간 구독자별 상태
+#[derive(Clone, Debug, Default, Serialize)]
+pub struct SubscriberStats {
+    pub id: u64,
+    /// None이면 `subscribe_all`
+    pub symbol: Option<String>,
+    /// 채널이 가득 차 건너뛴 봉 수
+    pub dropped: u64,
+}
+
+/// `FxStore::stats()` 스냅샷
+#[derive(Clone, Debug, Default, Serialize)]
+pub struct StatsSnapshot {
+    pub total_records: u64,
+    pub compressed_bytes: u64,
+    pub cache_hits: u64,
+    /// 캐시에 없어 새로 해제한 블록 수 (적중률 = cache_hits / (cache_hits + 이 값))
+    pub blocks_decompressed: u64,
+    /// 쿼리가 반환한 레코드 수 (누적)
+    pub query_records: u64,
+    /// 해제된 채 메모리에 있는 블록 바이트
+    pub resident_bytes: u64,
+    /// 압축 대기열에 쌓인 작업 수 (모든 워커 합)
+    pub compress_queue_depth: u64,
+    /// `OverflowPolicy::DropOldest`로 버린 일 배치 수 (누적)
+    pub dropped_batches: u64,
+    pub block_count: u64,
+    /// 심볼별 블록 수
+    pub blocks_by_symbol: BTreeMap<String, u64>,
+    pub last_price_entries: u64,
+    pub last_price_hits: u64,
+    pub last_price_misses: u64,
+    /// 실시간 집계에서 이미 내보낸 분이라 버린 틱 수
+    pub late_ticks: u64,
+    /// 모든 구독자가 건너뛴 봉 수 (해제된 구독자 포함)
+    pub dropped_bars: u64,
+    /// 현재 연결된 구독자
+    pub subscribers: Vec<SubscriberStats>,
+    /// CSV 임포트로 읽은 봉/틱 수 (누적)
+    pub imported_records: u64,
+    /// CSV 임포트에 걸린 시간 (누적, 처리량 = imported_records / import_nanos)
+    pub import_nanos: u64,
+}
+
+impl Default for FxStore {
+    fn default() -> Self {
+        Self::new()
+    }
 }
 
 impl FxStore {
     pub fn new() -> Self {
-        let (tx, rx) = bounded(1000);
+        Self::with_config(StoreConfig::default())
+    }
 
-        // 백그라운드 압축 스레드
-        let handle = std::thread::spawn(move || {
-            compress_worker(rx);
-        });
+    pub fn with_config(config: StoreConfig) -> Self {
+        let compress = CompressQueue::new(
+            config.compress_workers,
+            config.compress_queue,
+            config.compress_overflow,
+        );
+        let blocks: Arc<BlockMap> = Arc::new(DashMap::with_hasher(RandomState::new()));
+        let last_prices: Arc<LastPriceMap> = Arc::new(DashMap::with_hasher(RandomState::new()));
+        let tick_blocks: Arc<TickBlockMap> = Arc::new(DashMap::with_hasher(RandomState::new()));
+        let stats = Arc::new(StoreStats::default());
+
+        // 백그라운드 압축 스레드 (워커마다 자기 대기열)
+        let compress_handles = compress
+            .shards
+            .iter()
+            .map(|(_, rx)| {
+                let rx = rx.clone();
+                let worker_blocks = Arc::clone(&blocks);
+                let worker_last_prices = Arc::clone(&last_prices);
+                let worker_tick_blocks = Arc::clone(&tick_blocks);
+                let worker_stats = Arc::clone(&stats);
+                std::thread::spawn(move || {
+                    compress_worker(
+                        rx,
+                        &worker_blocks,
+                        &worker_last_prices,
+                        &worker_tick_blocks,
+                        &worker_stats,
+                    );
+                })
+            })
+            .collect();
 
         Self {
-            blocks: DashMap::with_hasher(RandomState::new()),
-            symbols: DashMap::new(),
-            stats: StoreStats::default(),
-            compress_tx: tx,
-            compress_handle: Some(handle),
+            blocks,
+            symbols: Arc::new(DashMap::new()),
+            symbol_names: DashMap::with_hasher(RandomState::new()),
+            next_symbol_id: AtomicU32::new(0),
+            aliases: DashMap::new(),
+            tick_blocks,
+            tick_senders: DashMap::with_hasher(RandomState::new()),
+            subscribers: Arc::new(Subscribers::default()),
+            last_prices,
+            stats,
+            cache: Mutex::new(BlockCache::default()),
+            retention: DashMap::new(),
+            config,
+            #[cfg(feature = "persistence")]
+            wal: None,
+            dirty: Arc::new(Mutex::new(HashSet::new())),
+            gate: Arc::new(RwLock::new(())),
+            #[cfg(feature = "persistence")]
+            flusher: None,
+            flush_thread: None,
+            compress,
+            compress_handles,
+            imported: Mutex::new(HashMap::new()),
+            imports_file: None,
+            corrupt: Mutex::new(BTreeSet::new()),
+            ready: AtomicBool::new(true),
+            created_at: Instant::now(),
         }
     }
+}
 
-    fn get_or_create_symbol(&self, symbol: &str) -> u16 {
-        if let Some(sym) = self.symbols.get(symbol) {
-            return sym.id;
-        }
-
-        let id = self.symbols.len() as u16;
-        let parts: Vec<&str> = symbol.split('/').collect();
-        let (base, quote) = if parts.len() == 2 {
-            (parts[0].to_string(), parts[1].to_string())
-        } else {
-            (symbol[..3].to_string(), symbol[3..].to_string())
-        };
+#[cfg(feature = "persistence")]
+impl FxStore {
+    /// 데이터 디렉터리의 블록 파일과 WAL로 스토어 열기 (디렉터리가 없으면 생성)
+    ///
+    /// 블록 파일을 읽은 뒤 WAL을 재생하고, `flush_interval`마다 변경된 블록을 블록 파일에 씀
+    pub fn open(data_dir: impl AsRef<std::path::Path>, config: StoreConfig) -> Result<Self> {
+        let data_dir = data_dir.as_ref();
+        std::fs::create_dir_all(data_dir)?;
+        let mut store = Self::with_config(config);
 
-        let sym = Symbol {
-            id,
-            name: symbol.to_string(),
-            base,
-            quote,
-        };
+        let (file, records) = PersistentStore::open(data_dir.join(BLOCK_FILE))?;
+        store.load_blocks(records)?;
+        store.warm_last_prices();
+        store.replay_wal(data_dir.join(WAL_FILE))?;
+        store.load_imports(data_dir.join(IMPORTS_FILE))?;
 
-        self.symbols.insert(symbol.to_string(), sym);
-        id
+        let flusher = Arc::new(BlockFlusher {
+            file,
+            wal: Arc::clone(store.wal.as_ref().unwrap()),
+            blocks: Arc::clone(&store.blocks),
+            symbols: Arc::clone(&store.symbols),
+            dirty: Arc::clone(&store.dirty),
+            gate: Arc::clone(&store.gate),
+            compress: store.compress.clone(),
+            retired: Mutex::new(Vec::new()),
+        });
+        let interval = store.config.flush_interval;
+        if !interval.is_zero() {
+            let (stop_tx, stop_rx) = bounded::<()>(0);
+            let thread_flusher = Arc::clone(&flusher);
+            let handle = std::thread::spawn(move || {
+                // 실패한 블록은 dirty에 남아 다음 주기에 다시 시도
+                while let Err(RecvTimeoutError::Timeout) = stop_rx.recv_timeout(interval) {
+                    thread_flusher.flush().ok();
+                }
+            });
+            store.flush_thread = Some((stop_tx, handle));
+        }
+        store.flusher = Some(flusher);
+        Ok(store)
     }
 
-    /// CSV 임포트 (rayon 병렬)
-    pub fn import_csv(&self, path: &str, symbol: &str) -> anyhow::Result<()> {
-        use rayon::prelude::*;
-        use std::fs::File;
-        use std::io::{BufRead, BufReader};
+    /// 블록 파일의 블록을 그대로 등록 (범위 메타데이터는 레코드의 요약으로, 해제하지 않음)
+    ///
+    /// 손상된 블록은 처음 읽는 쿼리나 `scrub`이 체크섬으로 찾아 `OnCorrupt`대로 처리
+    fn load_blocks(&self, records: Vec<BlockRecord>) -> Result<()> {
+        for record in records {
+            let sym_id = self.register_symbol(&record.symbol, record.granularity)?.id;
+            if let Some(mut sym) = self.symbols.get_mut(&record.symbol) {
+                sym.scale = record.scale;
+            }
+            let layout = BlockLayout {
+                codec: record.codec,
+                granularity: record.granularity,
+                zstd_level: self.config.zstd_level,
+            };
+            let mut block = CompressedBlock::from_parts(
+                record.date,
+                sym_id,
+                layout,
+                record.data,
+                record.checksum,
+            );
+            if let Some(summary) = record.summary {
+                summary.apply_to(&mut block);
+            }
 
-        let sym_id = self.get_or_create_symbol(symbol);
-        let file = File::open(path)?;
-        let reader = BufReader::new(file);
+            self.stats
+                .total_records
+                .fetch_add(u64::from(block.records.unwrap_or(0)), Ordering::Relaxed);
+            self.stats
+                .compressed_bytes
+                .fetch_add(block.data.len() as u64, Ordering::Relaxed);
+            self.blocks
+                .entry(sym_id)
+                .or_insert_with(|| DashMap::with_hasher(RandomState::new()))
+                .insert(record.date, block);
+        }
+        Ok(())
+    }
 
-        // 라인을 일별로 그룹화
-        let daily_groups: DashMap<u32, Vec<String>> = DashMap::new();
+    /// 기본 설정으로 `open` (디렉터리가 없으면 새 스토어, 있으면 블록 파일·WAL·임포트 기록을 읽음)
+    pub fn open_or_create(data_dir: impl AsRef<std::path::Path>) -> Result<Self> {
+        Self::open(data_dir, StoreConfig::default())
+    }
 
-        for line in reader.lines().skip(1) {
-            let line = line?;
-            let date = line[0..8].parse::<u32>()?;
-            daily_groups.entry(date).or_default().push(line);
+    /// 임포트 기록 파일 읽기 (한 줄에 JSON 하나, 쓰다 만 마지막 줄은 무시), 이후 기록은 같은 파일에 추가
+    fn load_imports(&mut self, path: std::path::PathBuf) -> Result<()> {
+        match std::fs::read_to_string(&path) {
+            Ok(text) => {
+                let mut imported = self.imported.lock();
+                for line in text.lines() {
+                    if let Ok(file) = serde_json::from_str::<ImportedFile>(line) {
+                        imported.insert((file.symbol.clone(), file.hash), file);
+                    }
+                }
+            }
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => {}
+            Err(e) => return Err(e.into()),
         }
+        self.imports_file = Some(path);
+        Ok(())
+    }
 
-        // 병렬 파싱 및 압축
-        daily_groups.into_iter().for_each(|(date, lines)| {
-            let records: Vec<OHLCV> = lines
-                .par_iter()
-                .filter_map(|line| parse_line(line, sym_id).ok())
-                .collect();
+    /// WAL을 재생해 스토어 복구 (파일이 없으면 새로 생성), 이후 쓰기는 같은 WAL에 기록
+    pub fn recover(wal_path: impl AsRef<std::path::Path>) -> Result<Self> {
+        Self::recover_with(StoreConfig::default(), wal_path)
+    }
 
-            self.compress_tx.send((date, sym_id, records)).ok();
-        });
+    /// 설정을 지정한 `recover`
+    pub fn recover_with(
+        config: StoreConfig,
+        wal_path: impl AsRef<std::path::Path>,
+    ) -> Result<Self> {
+        let mut store = Self::with_config(config);
+        store.replay_wal(wal_path)?;
+        Ok(store)
+    }
 
+    /// WAL 엔트리를 블록에 병합하고 같은 WAL을 쓰기용으로 유지 (깨진 꼬리는 `Wal::recover`가 잘라냄)
+    fn replay_wal(&mut self, wal_path: impl AsRef<std::path::Path>) -> Result<()> {
+        let (wal, entries) = Wal::recover(wal_path, self.config.fsync_policy)?;
+        let store = &*self;
+        for entry in entries {
+            let sym_id = store.register_symbol(&entry.symbol, entry.granularity)?.id;
+            if let Some(mut sym) = store.symbols.get_mut(&entry.symbol) {
+                sym.scale = entry.scale;
+            }
+            let layout = store.layout(&entry.symbol);
+            let mut daily: BTreeMap<u32, Vec<OHLCV>> = BTreeMap::new();
+            for mut rec in entry.records {
+                rec.symbol_id = sym_id;
+                daily.entry(ts_to_date(rec.ts)).or_default().push(rec);
+            }
+            for (date, mut records) in daily {
+                coalesce_bars(&mut records, layout.granularity);
+                store_batch(
+                    &store.blocks,
+                    &store.last_prices,
+                    &store.stats,
+                    layout,
+                    date,
+                    sym_id,
+                    &records,
+                );
+                store.dirty.lock().insert((sym_id, date));
+            }
+        }
+
+        self.wal = Some(Arc::new(wal));
         Ok(())
     }
 
-    /// 시간 범위 쿼리 (zero-copy 이터레이터)
-    pub fn query_range(
+    /// 심볼들의 `start_date..=end_date`(YYYYMMDD) 블록을 압축된 그대로 스냅샷 파일에 쓰기
+    ///
+    /// 내보낸 블록 수 반환
+    pub fn export_snapshot(
         &self,
-        symbol: &str,
-        start_ts: u64,
-        end_ts: u64,
-    ) -> impl Iterator<Item = OHLCV> + '_ {
-        let sym_id = match self.symbols.get(symbol) {
-            Some(s) => s.id,
-            None => return Box::new(std::iter::empty()) as Box<dyn Iterator<Item = OHLCV>>,
-        };
+        path: impl AsRef<std::path::Path>,
+        symbols: &[&str],
+        start_date: u32,
+        end_date: u32,
+    ) -> Result<usize> {
+        self.flush();
+        let mut metas: Vec<SnapshotSymbol> = Vec::with_capacity(symbols.len());
+        let mut blocks = Vec::new();
+        for symbol in symbols {
+            let sym = self
+                .symbol(symbol)
+                .ok_or_else(|| FxStoreError::UnknownSymbol(symbol.to_string()))?;
+            if metas.iter().any(|meta| meta.name == sym.name) {
+                continue;
+            }
+            let index = metas.len() as u16;
+            metas.push(SnapshotSymbol {
+                name: sym.name,
+                granularity: sym.granularity,
+                scale: sym.scale,
+            });
 
-        let start_date = ts_to_date(start_ts);
-        let end_date = ts_to_date(end_ts);
+            let mut days: Vec<SnapshotBlock> = self
+                .blocks
+                .get(&sym.id)
+                .map(|blocks| {
+                    blocks
+                        .iter()
+                        .filter(|entry| (start_date..=end_date).contains(entry.key()))
+                        .map(|entry| SnapshotBlock {
+                            symbol: index,
+                            date: *entry.key(),
+                            codec: entry.layout.codec,
+                            checksum: entry.checksum,
+                            data: Arc::clone(&entry.data),
+                        })
+                        .collect()
+                })
+                .unwrap_or_default();
+            days.sort_unstable_by_key(|block| block.date);
+            blocks.extend(days);
+        }
+        snapshot::write(path, &metas, &blocks)?;
+        Ok(blocks.len())
+    }
 
-        let symbol_blocks = match self.blocks.get(&sym_id) {
-            Some(b) => b,
-            None => return Box::new(std::iter::empty()) as Box<dyn Iterator<Item = OHLCV>>,
-        };
+    /// 스냅샷의 심볼을 등록(symbol_id는 로컬 것으로)하고 블록을 다시 압축하지 않고 넣기
+    ///
+    /// 이미 있는 날짜는 CSV 재임포트처럼 병합 (같은 슬롯은 스냅샷 봉 우선).
+    /// 슬롯 간격이나 가격 스케일(로컬에 봉이 있을 때)이 다르면 아무것도 넣지 않고 `Incompatible`
+    pub fn import_snapshot(&self, path: impl AsRef<std::path::Path>) -> Result<SnapshotReport> {
+        let (metas, blocks) = snapshot::read(path)?;
+        for block in &blocks {
+            if xxh64(&block.data, 0) != block.checksum {
+                return Err(FxStoreError::Corruption(format!(
+                    "snapshot block {} checksum mismatch",
+                    block.date
+                )));
+            }
+        }
+        self.flush();
+        for meta in &metas {
+            let Some(sym) = self.symbol(&meta.name) else {
+                continue;
+            };
+            let has_data = self
+                .blocks
+                .get(&sym.id)
+                .is_some_and(|blocks| !blocks.is_empty());
+            if sym.granularity != meta.granularity || (has_data && sym.scale != meta.scale) {
+                return Err(FxStoreError::Incompatible(format!(
+                    "{} is {:?} at scale {} in the snapshot but {:?} at scale {} here",
+                    meta.name, meta.granularity, meta.scale, sym.granularity, sym.scale
+                )));
+            }
+        }
 
-        // 날짜 범위의 블록들을 순회
-        let blocks: Vec<_> = symbol_blocks
+        let ids: Vec<u16> = metas
             .iter()
-            .filter(|entry| *entry.key() >= start_date && *entry.key() <= end_date)
-            .map(|entry| entry.value().clone())
-            .collect();
+            .map(|meta| {
+                let id = self.register_symbol(&meta.name, meta.granularity)?.id;
+                self.set_scale_if_empty(&meta.name, id, meta.scale);
+                Ok(id)
+            })
+            .collect::<Result<_>>()?;
 
-        Box::new(blocks.into_iter().flat_map(move |block| {
-            let data = block.decompress();
-            data.to_vec()
-                .into_iter()
-                .filter(move |rec| rec.ts >= start_ts && rec.ts <= end_ts)
-        }))
+        let mut report = SnapshotReport {
+            symbols: metas.len(),
+            ..Default::default()
+        };
+        let _gate = self.gate.read();
+        for block in blocks {
+            let meta = &metas[block.symbol as usize];
+            let sym_id = ids[block.symbol as usize];
+            let layout = BlockLayout {
+                codec: block.codec,
+                granularity: meta.granularity,
+                zstd_level: self.config.zstd_level,
+            };
+            let date = block.date;
+            let block = CompressedBlock::from_parts(
+                date,
+                sym_id,
+                layout,
+                Arc::unwrap_or_clone(block.data),
+                block.checksum,
+            );
+            let slots = block.try_decompress_shared()?;
+            let records: Vec<OHLCV> = slots.iter().filter(|rec| rec.ts != 0).copied().collect();
+            let block = block.with_bounds(&slots);
+            block.evict();
+
+            let symbol_blocks = self
+                .blocks
+                .entry(sym_id)
+                .or_insert_with(|| DashMap::with_hasher(RandomState::new()));
+            let (old_bytes, new_bytes) = match symbol_blocks.entry(date) {
+                Entry::Occupied(mut entry) => {
+                    let merged = entry.get().try_merge(&records)?;
+                    let sizes = (entry.get().data.len() as u64, merged.data.len() as u64);
+                    entry.insert(merged);
+                    report.merged += 1;
+                    sizes
+                }
+                Entry::Vacant(entry) => {
+                    let size = block.data.len() as u64;
+                    entry.insert(block);
+                    (0, size)
+                }
+            };
+            drop(symbol_blocks);
+            self.stats.bump_generation(sym_id);
+
+            self.stats
+                .total_records
+                .fetch_add(records.len() as u64, Ordering::Relaxed);
+            self.stats
+                .compressed_bytes
+                .fetch_add(new_bytes, Ordering::Relaxed);
+            self.stats
+                .compressed_bytes
+                .fetch_sub(old_bytes, Ordering::Relaxed);
+            if let Some(newest) = records.last() {
+                update_last_price(&self.last_prices, newest);
+            }
+            self.dirty.lock().insert((sym_id, date));
+            report.blocks += 1;
+            report.records += records.len();
+        }
+        drop(_gate);
+
+        // WAL을 거치지 않으므로 블록 파일이 있으면 바로 기록
+        if self.flusher.is_some() {
+            self.flush_now()?;
+        }
+        Ok(report)
     }
+}
 
-    /// Get all available symbols
-    pub fn get_symbols(&self) -> Vec<String> {
-        self.symbols
-            .iter()
-            .map(|entry| entry.key().clone())
-            .collect()
+impl FxStore {
+    /// 임포트한 파일 기록 (임포트 기록 파일이 있으면 한 줄 추가)
+    fn record_import(&self, file: ImportedFile) -> Result<()> {
+        if let Some(path) = &self.imports_file {
+            use std::io::Write;
+            let mut line = serde_json::to_string(&file).map_err(std::io::Error::from)?;
+            line.push('\n');
+            let mut out = std::fs::OpenOptions::new()
+                .create(true)
+                .append(true)
+                .open(path)?;
+            out.write_all(line.as_bytes())?;
+            out.sync_data()?;
+        }
+        self.imported
+            .lock()
+            .insert((file.symbol.clone(), file.hash), file);
+        Ok(())
     }
 
-    /// 리얼타임 스트리밍 (tick-to-1min 집계)
-    pub fn stream_realtime(&self, symbol: &str) -> Receiver<OHLCV> {
-        let (tx, rx) = bounded(10000);
-        let sym_id = self.get_or_create_symbol(symbol);
+    /// 블록을 블록 파일에 쓴 뒤 WAL 비우기 (`flush_now`)
+    ///
+    /// `recover`로 WAL만 연 스토어는 블록이 메모리에만 있어 WAL을 비우면 크래시 때 잃으므로
+    /// WAL을 그대로 두고 `NotPersistent`, WAL도 없는 메모리 전용 스토어는 대기 중인 압축만 반영
+    pub fn checkpoint(&self) -> Result<()> {
+        #[cfg(feature = "persistence")]
+        {
+            if self.flusher.is_some() {
+                return self.flush_now().map(|_| ());
+            }
+            if self.wal.is_some() {
+                return Err(FxStoreError::NotPersistent);
+            }
+        }
+        self.flush();
+        Ok(())
+    }
 
-        // 실시간 집계 스레드
-        std::thread::spawn(move || {
-            aggregate_ticks_to_minutes(sym_id, tx);
-        });
+    /// 변경된 블록을 지금 블록 파일에 쓰고 WAL 비우기, 기록한 블록 수 반환
+    ///
+    /// 블록 파일 없이 연 스토어는 대기 중인 압축만 반영하고 0
+    pub fn flush_now(&self) -> Result<usize> {
+        #[cfg(feature = "persistence")]
+        if let Some(flusher) = &self.flusher {
+            return flusher.flush();
+        }
+        self.flush();
+        Ok(0)
+    }
 
-        rx
+    #[cfg(feature = "persistence")]
+    fn log_records(&self, symbol: &str, records: &[OHLCV]) -> Result<()> {
+        if let Some(wal) = &self.wal {
+            wal.append(&WalEntry {
+                symbol: self.resolve(symbol).into_owned(),
+                granularity: self.layout(symbol).granularity,
+                scale: self.price_scale(symbol),
+                records: records.to_vec(),
+            })?;
+        }
+        Ok(())
     }
-}
 
-/// 백그라운드 압축 워커
-fn compress_worker(rx: Receiver<(u32, u16, Vec<OHLCV>)>) {
-    while let Ok((date, symbol_id, records)) = rx.recv() {
-        let block = CompressedBlock::new(date, symbol_id, &records);
-        // 저장 로직...
+    /// WAL 없이 빌드하면 기록할 곳이 없음
+    #[cfg(not(feature = "persistence"))]
+    fn log_records(&self, _symbol: &str, _records: &[OHLCV]) -> Result<()> {
+        Ok(())
     }
-}
 
-/// 타임스탬프 → YYYYMMDD 변환
-#[inline]
-fn ts_to_date(ts: u64) -> u32 {
-    use chrono::DateTime;
-    let dt = DateTime::from_timestamp_nanos(ts as i64);
-    dt.format("%Y%m%d").to_string().parse().unwrap()
-}
+    fn symbol_id(&self, symbol: &str) -> Result<u16> {
+        self.lookup(symbol)
+            .map(|s| s.id)
+            .ok_or_else(|| FxStoreError::UnknownSymbol(symbol.to_string()))
+    }
 
-/// CSV 라인 파싱 (HISTDATA 형식: YYYYMMDD HHMMSS,Open,High,Low,Close,Volume)
-fn parse_line(line: &str, symbol_id: u16) -> Result<OHLCV, Box<dyn std::error::Error>> {
-    // 세미콜론 또는 쉼표로 구분된 데이터 처리
-    let separator = if line.contains(';') { ';' } else { ',' };
-    let parts: Vec<&str> = line.split(separator).collect();
-    if parts.len() < 6 {
-        return Err("Invalid CSV format".into());
+    /// 별칭이면 가리키는 심볼 이름, 아니면 그대로
+    fn resolve<'a>(&self, symbol: &'a str) -> Cow<'a, str> {
+        match self.aliases.get(symbol) {
+            Some(canonical) => Cow::Owned(canonical.clone()),
+            None => Cow::Borrowed(symbol),
+        }
     }
 
-    let datetime = parts[0];
-    let open: f64 = parts[1].parse()?;
-    let high: f64 = parts[2].parse()?;
-    let low: f64 = parts[3].parse()?;
-    let close: f64 = parts[4].parse()?;
-    let volume: u32 = parts[5].parse().unwrap_or(0);
+    /// 심볼 이름 또는 별칭으로 조회
+    fn lookup(&self, symbol: &str) -> Option<Ref<'_, String, Symbol>> {
+        self.symbols.get(self.resolve(symbol).as_ref())
+    }
 
-    Ok(OHLCV::from_fx(
-        datetime, open, high, low, close, volume, symbol_id,
-    ))
-}
+    fn get_or_create_symbol(&self, symbol: &str) -> Result<u16> {
+        if let Some(sym) = self.lookup(symbol) {
+            return Ok(sym.id);
+        }
+        Ok(self.register_symbol(symbol, Granularity::default())?.id)
+    }
+
+    /// 슬롯 간격을 지정해 심볼 등록 (이미 있으면 기존 설정 그대로 반환)
+    ///
+    /// 실시간 틱 집계는 1분봉을 만들므로 더 굵은 간격에서는 같은 슬롯의 이전 봉을 덮어씀.
+    /// 여러 스레드가 같은 심볼을 동시에 등록해도 한 번만 만들어져 모두 같은 id를 받음.
+    /// id 65,536개를 모두 쓰면 `SymbolLimit`
+    pub fn register_symbol(&self, symbol: &str, granularity: Granularity) -> Result<Symbol> {
+        if let Some(sym) = self.lookup(symbol) {
+            return Ok(sym.clone());
+        }
+
+        let parts: Vec<&str> = symbol.split('/').collect();
+        let (base, quote) = if parts.len() == 2 {
+            (parts[0].to_string(), parts[1].to_string())
+        } else {
+            // 6글자 코드는 앞 3글자가 기준 통화 (짧거나 문자 경계가 아니면 전체를 기준 통화로)
+            let (base, quote) = symbol.split_at_checked(3).unwrap_or((symbol, ""));
+            (base.to_string(), quote.to_string())
+        };
+
+        // id는 실제로 삽입할 때만 할당 (entry 가드가 같은 이름의 동시 등록을 직렬화)
+        match self.symbols.entry(symbol.to_string()) {
+            Entry::Occupied(entry) => Ok(entry.get().clone()),
+            Entry::Vacant(entry) => {
+                let id = self
+                    .next_symbol_id
+                    .fetch_update(Ordering::Relaxed, Ordering::Relaxed, |next| {
+                        (next <= u32::from(u16::MAX)).then_some(next + 1)
+                    })
+                    .map_err(|_| FxStoreError::SymbolLimit(symbol.to_string()))?
+                    as u16;
+                self.symbol_names.insert(id, symbol.to_string());
+                Ok(entry
+                    .insert(Symbol {
+                        id,
+                        name: symbol.to_string(),
+                        base,
+                        quote,
+                        granularity,
+                        scale: PRICE_SCALE,
+                    })
+                    .clone())
+            }
+        }
+    }
+
+    /// `alias`로 조회하면 `canonical` 심볼을 가리키도록 등록 (별칭의 별칭은 원래 심볼로)
+    ///
+    /// 별칭은 메모리에만 있고 WAL/블록 파일에는 정식 이름으로 기록됨
+    pub fn add_alias(&self, alias: &str, canonical: &str) -> Result<()> {
+        let target = self
+            .lookup(canonical)
+            .map(|sym| sym.name.clone())
+            .ok_or_else(|| FxStoreError::UnknownSymbol(canonical.to_string()))?;
+        if self.symbols.contains_key(alias) {
+            return Err(FxStoreError::SymbolExists(alias.to_string()));
+        }
+        self.aliases.insert(alias.to_string(), target);
+        Ok(())
+    }
+
+    /// 심볼 이름 변경 (블록은 symbol_id 기준이라 그대로 두고 심볼 테이블만 갱신)
+    ///
+    /// 별칭과 보존 기간은 새 이름을 따라가고, 블록 파일은 다음 플러시에 새 이름으로 다시 기록
+    pub fn rename_symbol(&self, old: &str, new: &str) -> Result<()> {
+        let old = self.resolve(old).into_owned();
+        if self.has_symbol(new) {
+            return Err(FxStoreError::SymbolExists(new.to_string()));
+        }
+        // 이름이 바뀌는 동안 WAL에 옛 이름이 기록되지 않도록 쓰기를 막음
+        let _gate = self.gate.write();
+        let Some((_, mut sym)) = self.symbols.remove(&old) else {
+            return Err(FxStoreError::UnknownSymbol(old));
+        };
+        sym.name = new.to_string();
+        self.symbols.insert(new.to_string(), sym.clone());
+        self.symbol_names.insert(sym.id, new.to_string());
+
+        for mut target in self.aliases.iter_mut() {
+            if *target == old {
+                *target = new.to_string();
+            }
+        }
+        if let Some((_, days)) = self.retention.remove(&old) {
+            self.retention.insert(new.to_string(), days);
+        }
+
+        #[cfg(feature = "persistence")]
+        if let Some(flusher) = &self.flusher
+            && let Some(blocks) = self.blocks.get(&sym.id)
+        {
+            let mut dirty = self.dirty.lock();
+            let mut retired = flusher.retired.lock();
+            for entry in blocks.iter() {
+                dirty.insert((sym.id, *entry.key()));
+                retired.push(BlockRecord {
+                    symbol: old.clone(),
+                    granularity: sym.granularity,
+                    scale: sym.scale,
+                    date: *entry.key(),
+                    codec: Default::default(),
+                    checksum: 0,
+                    summary: None,
+                    data: Vec::new(),
+                });
+            }
+        }
+        Ok(())
+    }
+
+    /// symbol_id의 심볼 이름 (쿼리 결과의 `OHLCV::symbol_id`를 이름으로 되돌릴 때, 미등록이면 None)
+    pub fn symbol_name(&self, id: u16) -> Option<String> {
+        self.symbol_names.get(&id).map(|name| name.clone())
+    }
 
-/// 실시간 틱 데이터를 1분 바로 집계 (스텁 구현)
-fn aggregate_ticks_to_minutes(_symbol_id: u16, _tx: Sender<OHLCV>) {
-    // TODO: 실제 틱 데이터 수신 및 집계 로직 구현
-    // 현재는 스텁 구현
+    /// 심볼 메타데이터 (미등록이면 None)
+    pub fn symbol(&self, symbol: &str) -> Option<Symbol> {
+        self.lookup(symbol).map(|sym| sym.clone())
+    }
+
+    /// 심볼의 가격 스케일 (미등록이면 `PRICE_SCALE`)
+    pub fn price_scale(&self, symbol: &str) -> u32 {
+        self.lookup(symbol).map_or(PRICE_SCALE, |sym| sym.scale)
+    }
+
+    /// 저장된 봉이 없을 때만 스케일 변경 (기존 블록과 스케일이 섞이지 않도록)
+    fn set_scale_if_empty(&self, symbol: &str, sym_id: u16, scale: u32) {
+        self.flush();
+        let empty = self
+            .blocks
+            .get(&sym_id)
+            .is_none_or(|blocks| blocks.is_empty());
+        if empty && let Some(mut sym) = self.symbols.get_mut(self.resolve(symbol).as_ref()) {
+            sym.scale = scale;
+        }
+    }
+
+    /// 심볼 블록의 압축 방식과 슬롯 간격 (미등록이면 기본 1분)
+    fn layout(&self, symbol: &str) -> BlockLayout {
+        BlockLayout {
+            codec: self.config.codec,
+            granularity: self
+                .lookup(symbol)
+                .map(|sym| sym.granularity)
+                .unwrap_or_default(),
+            zstd_level: self.config.zstd_level,
+        }
+    }
+
+    /// CSV 임포트 (rayon 병렬)
+    pub fn import_csv(&self, path: &str, symbol: &str) -> Result<ImportReport> {
+        self.import_csv_with(path, symbol, &ImportOptions::default())
+    }
+
+    /// 옵션을 지정한 CSV 임포트
+    pub fn import_csv_with(
+        &self,
+        path: &str,
+        symbol: &str,
+        options: &ImportOptions,
+    ) -> Result<ImportReport> {
+        use rayon::prelude::*;
+
+        let _span = tracing::info_span!("import_csv", path, symbol).entered();
+        let started = Instant::now();
+        let canonical = self.resolve(symbol).into_owned();
+        let already = || {
+            tracing::info!("already imported, skipping");
+            Ok(ImportReport {
+                already_imported: true,
+                ..Default::default()
+            })
+        };
+
+        // 경로·크기·수정 시각이 기록과 같으면 읽지도 않고, 다르면 내용 해시로 비교
+        let (size, mtime) = file_stamp(path)?;
+        if !options.force && mtime != 0 {
+            let unchanged = self.imported.lock().values().any(|file| {
+                file.symbol == canonical
+                    && file.path == path
+                    && (file.size, file.mtime) == (size, mtime)
+            });
+            if unchanged {
+                return already();
+            }
+        }
+        let hash = file_hash(path)?;
+        if !options.force {
+            let known = self
+                .imported
+                .lock()
+                .get(&(canonical.clone(), hash))
+                .cloned();
+            if let Some(file) = known {
+                // 내용은 같고 위치나 수정 시각만 바뀐 파일은 다음부터 읽지 않도록 다시 기록
+                self.record_import(ImportedFile {
+                    path: path.to_string(),
+                    size,
+                    mtime,
+                    ..file
+                })?;
+                return already();
+            }
+        }
+        let sym_id = self.get_or_create_symbol(symbol)?;
+        let layout = self.layout(symbol);
+
+        // 이미 블록이 있는 날짜 (`ExistingDays::Skip`일 때만, 이 임포트가 쓰기 전 기준)
+        let present: Option<HashSet<u32>> =
+            (options.existing_days == ExistingDays::Skip).then(|| {
+                self.flush();
+                self.blocks
+                    .get(&sym_id)
+                    .map(|blocks| blocks.iter().map(|entry| *entry.key()).collect())
+                    .unwrap_or_default()
+            });
+        let mut present_days = HashSet::new();
+
+        // 첫 두 줄(스케일 감지 시 표본 행까지)로 레이아웃 감지 후 다시 스트림 앞에 붙임
+        let mut lines = open_lines(path)?;
+        let head_rows = if options.detect_scale {
+            SCALE_SAMPLE_ROWS + 1
+        } else {
+            2
+        };
+        let head: Vec<std::io::Result<String>> = lines.by_ref().take(head_rows).collect();
+        let format = match &options.format {
+            Some(format) => format.clone(),
+            None => {
+                let first = head.first().and_then(|l| l.as_ref().ok());
+                let second = head.get(1).and_then(|l| l.as_ref().ok());
+                match first {
+                    Some(first) => CsvFormat::detect(first, second.map(String::as_str)),
+                    None => CsvFormat::default(),
+                }
+            }
+        };
+        let source_tz = options
+            .source_tz
+            .or(format.tz)
+            .unwrap_or(self.config.source_tz);
+        let skip = usize::from(format.has_header);
+        if options.detect_scale {
+            let sample = head.iter().skip(skip).filter_map(|l| l.as_ref().ok());
+            if let Some(scale) = format.detect_scale(sample.map(String::as_str)) {
+                self.set_scale_if_empty(symbol, sym_id, scale);
+            }
+        }
+        let scale = self.price_scale(symbol);
+        let lines = head.into_iter().chain(lines).skip(skip);
+
+        let mut report = ImportReport::default();
+
+        // 하루치씩 스트리밍 (메모리는 하루 분량으로 제한), 하루 안에서는 병렬 파싱
+        for lines in DayChunks::new(lines, &format, 1 + skip) {
+            let lines = lines?;
+            report.days += 1;
+            let _day = tracing::debug_span!("import_day", day = report.days, lines = lines.len())
+                .entered();
+
+            let parsed: Vec<(usize, Result<OHLCV>)> = lines
+                .par_iter()
+                .map(|(line_no, line)| {
+                    (*line_no, format.parse_line(line, sym_id, source_tz, scale))
+                })
+                .collect();
+
+            self.ingest_parsed(
+                symbol,
+                sym_id,
+                layout,
+                parsed,
+                present.as_ref(),
+                &mut present_days,
+                &mut report,
+            )?;
+        }
+
+        if self.retention.contains_key(self.resolve(symbol).as_ref()) {
+            self.flush();
+            self.apply_retention(symbol);
+        }
+        report.days_present = present_days.len();
+        self.note_import(&report, started);
+        self.record_import(ImportedFile {
+            symbol: canonical,
+            hash,
+            path: path.to_string(),
+            size,
+            mtime,
+            imported: report.imported,
+        })?;
+        Ok(report)
+    }
+
+    /// Parquet 파일 임포트 (행 그룹 순서대로 스트리밍, `mapping`이 None이면 컬럼명으로 추정)
+    ///
+    /// 검증·병합·WAL 기록은 CSV 임포트와 같고 `errors`의 번호는 1부터 센 행 번호.
+    /// 가격은 심볼의 현재 스케일로 반올림
+    #[cfg(feature = "parquet")]
+    pub fn import_parquet(
+        &self,
+        path: &str,
+        symbol: &str,
+        mapping: Option<ColumnMapping>,
+    ) -> Result<ImportReport> {
+        let _span = tracing::info_span!("import_parquet", path, symbol).entered();
+        let started = Instant::now();
+        let sym_id = self.get_or_create_symbol(symbol)?;
+        let layout = self.layout(symbol);
+        let scale = self.price_scale(symbol);
+        let reader = BarReader::open(path, mapping, self.config.source_tz, scale, sym_id)?;
+
+        let mut report = ImportReport::default();
+        for parsed in reader {
+            report.days += self.ingest_parsed(
+                symbol,
+                sym_id,
+                layout,
+                parsed?,
+                None,
+                &mut HashSet::new(),
+                &mut report,
+            )?;
+        }
+
+        if self.retention.contains_key(self.resolve(symbol).as_ref()) {
+            self.flush();
+            self.apply_retention(symbol);
+        }
+        self.note_import(&report, started);
+        Ok(report)
+    }
+
+    /// 파싱한 행들을 검증해 WAL에 기록하고 UTC 날짜별 배치로 압축 대기열에 보냄
+    /// (보낸 일 배치 수 반환)
+    ///
+    /// `present`가 있으면 그 날짜의 봉은 버리고 `present_days`에 기록 (`ExistingDays::Skip`)
+    #[allow(clippy::too_many_arguments)]
+    fn ingest_parsed(
+        &self,
+        symbol: &str,
+        sym_id: u16,
+        layout: BlockLayout,
+        parsed: Vec<(usize, Result<OHLCV>)>,
+        present: Option<&HashSet<u32>>,
+        present_days: &mut HashSet<u32>,
+        report: &mut ImportReport,
+    ) -> Result<usize> {
+        let strict = self.config.on_invalid == OnInvalid::Error;
+        let mut records = Vec::with_capacity(parsed.len());
+        for (line_no, result) in parsed {
+            match result {
+                Ok(rec) if self.config.validate && !rec.is_valid() => {
+                    if strict {
+                        return Err(FxStoreError::Parse {
+                            line: line_no,
+                            reason: format!("invalid OHLC bar at ts {}", { rec.ts }),
+                        });
+                    }
+                    report.rejected += 1;
+                }
+                Ok(rec) => records.push(rec),
+                Err(e) if strict => return Err(e.at_line(line_no)),
+                Err(e) => {
+                    report.skipped += 1;
+                    if report.errors.len() < MAX_REPORTED_ERRORS {
+                        report.errors.push((line_no, e.to_string()));
+                    }
+                }
+            }
+        }
+        if let Some(present) = present {
+            records.retain(|rec| {
+                let date = ts_to_date(rec.ts);
+                let keep = !present.contains(&date);
+                if !keep {
+                    present_days.insert(date);
+                }
+                keep
+            });
+        }
+        report.imported += records.len();
+
+        let _gate = self.gate.read();
+        self.log_records(symbol, &records)?;
+
+        // 현지 날짜와 UTC 날짜가 다를 수 있으므로 UTC 기준으로 다시 분할
+        let mut utc_days: BTreeMap<u32, Vec<OHLCV>> = BTreeMap::new();
+        for rec in records {
+            utc_days.entry(ts_to_date(rec.ts)).or_default().push(rec);
+        }
+        let days = utc_days.len();
+        for (date, mut records) in utc_days {
+            report.merged += coalesce_bars(&mut records, layout.granularity);
+            self.dirty.lock().insert((sym_id, date));
+            report.dropped_batches += self.compress.send(CompressJob::Batch {
+                date,
+                symbol_id: sym_id,
+                layout,
+                records,
+            })?;
+        }
+        Ok(days)
+    }
+
+    /// HISTDATA 틱 CSV 임포트 (`YYYYMMDD HHMMSSmmm,bid,ask,volume`, `StoreConfig::source_tz` 기준)
+    ///
+    /// 틱은 WAL에 기록하지 않음
+    pub fn import_ticks_csv(&self, path: &str, symbol: &str) -> Result<ImportReport> {
+        use rayon::prelude::*;
+
+        let _span = tracing::info_span!("import_ticks_csv", path, symbol).entered();
+        let started = Instant::now();
+        let sym_id = self.get_or_create_symbol(symbol)?;
+        let scale = self.price_scale(symbol);
+        let format = CsvFormat::default();
+        let mut report = ImportReport::default();
+
+        for lines in DayChunks::new(open_lines(path)?, &format, 1) {
+            let lines = lines?;
+            report.days += 1;
+
+            let parsed: Vec<(usize, Result<Tick>)> = lines
+                .par_iter()
+                .map(|(line_no, line)| {
+                    let result = parse_histdata_tick(line, sym_id, self.config.source_tz, scale);
+                    (*line_no, result)
+                })
+                .collect();
+
+            let mut utc_days: BTreeMap<u32, Vec<Tick>> = BTreeMap::new();
+            for (line_no, result) in parsed {
+                match result {
+                    Ok(tick) => utc_days.entry(ts_to_date(tick.ts)).or_default().push(tick),
+                    Err(e) if self.config.on_invalid == OnInvalid::Error => {
+                        return Err(e.at_line(line_no));
+                    }
+                    Err(e) => {
+                        report.skipped += 1;
+                        if report.errors.len() < MAX_REPORTED_ERRORS {
+                            report.errors.push((line_no, e.to_string()));
+                        }
+                    }
+                }
+            }
+
+            for (date, ticks) in utc_days {
+                report.imported += ticks.len();
+                report.dropped_batches += self.compress.send(CompressJob::Ticks {
+                    date,
+                    symbol_id: sym_id,
+                    ticks,
+                })?;
+            }
+        }
+
+        self.note_import(&report, started);
+        Ok(report)
+    }
+
+    fn note_import(&self, report: &ImportReport, started: Instant) {
+        let elapsed = started.elapsed().as_nanos() as u64;
+        self.stats
+            .imported_records
+            .fetch_add(report.imported as u64, Ordering::Relaxed);
+        self.stats
+            .import_nanos
+            .fetch_add(elapsed, Ordering::Relaxed);
+        tracing::info!(
+            imported = report.imported,
+            days = report.days,
+            skipped = report.skipped,
+            rejected = report.rejected,
+            "import finished"
+        );
+    }
+
+    /// 단일 틱 삽입 (틱 블록에 저장하고 실시간 집계에도 전달)
+    pub fn insert_tick(&self, symbol: &str, mut tick: Tick) -> Result<()> {
+        if tick.ts == 0 || tick.ts > MAX_TS {
+            return Err(FxStoreError::TimestampOutOfRange(tick.ts));
+        }
+        let sym_id = self.get_or_create_symbol(symbol)?;
+        tick.symbol_id = sym_id;
+        store_ticks(&self.tick_blocks, ts_to_date(tick.ts), sym_id, &[tick]);
+        self.tick_sender(symbol)?.send(tick).ok();
+        Ok(())
+    }
+
+    /// 심볼의 실시간 틱 입력 채널 (처음 호출 시 집계 스레드 시작)
+    ///
+    /// 완성된 1분봉은 블록에 저장되어 `query_range`로 조회되고 `stream_realtime` 구독자에게 전달됨.
+    /// 이 채널로 보낸 틱 자체는 틱 블록에 저장하지 않음
+    pub fn tick_sender(&self, symbol: &str) -> Result<Sender<Tick>> {
+        let sym_id = self.get_or_create_symbol(symbol)?;
+        Ok(self
+            .tick_senders
+            .entry(sym_id)
+            .or_insert_with(|| {
+                let (tx, rx) = unbounded();
+                let (finalize_tx, finalize_rx) = unbounded();
+                let pipeline = TickPipeline {
+                    symbol: self.resolve(symbol).into_owned(),
+                    symbol_id: sym_id,
+                    blocks: Arc::clone(&self.blocks),
+                    last_prices: Arc::clone(&self.last_prices),
+                    stats: Arc::clone(&self.stats),
+                    subscribers: Arc::clone(&self.subscribers),
+                    dirty: Arc::clone(&self.dirty),
+                    layout: self.layout(symbol),
+                    #[cfg(feature = "persistence")]
+                    wal: self.wal.clone(),
+                    #[cfg(feature = "persistence")]
+                    scale: self.price_scale(symbol),
+                    gate: Arc::clone(&self.gate),
+                };
+                let tolerance = self.config.tick_tolerance.as_nanos() as u64;
+                let idle_timeout = self.config.tick_idle_timeout;
+                std::thread::spawn(move || {
+                    aggregate_ticks_to_minutes(rx, finalize_rx, &pipeline, tolerance, idle_timeout);
+                });
+                (tx, finalize_tx)
+            })
+            .0
+            .clone())
+    }
+
+    /// 모든 실시간 집계의 진행 중인 분을 (틱이 하나라도 있으면) 봉으로 마감해 저장·전달
+    ///
+    /// 세션을 끝낼 때 마지막 분을 잃지 않도록 종료 직전에 호출 (`Drop`도 호출).
+    /// 마감한 분에 뒤늦게 온 틱은 늦은 틱으로 버려짐. 마감한 심볼 수 반환
+    pub fn finalize_realtime(&self) -> usize {
+        let acks: Vec<Receiver<()>> = self
+            .tick_senders
+            .iter()
+            .filter_map(|entry| {
+                let (done_tx, done_rx) = bounded(1);
+                entry.value().1.send(done_tx).ok().map(|()| done_rx)
+            })
+            .collect();
+        acks.iter().filter(|ack| ack.recv().is_ok()).count()
+    }
+
+    /// 시간 범위의 틱 (ts순, 날짜 경계를 넘어도 이어서 반환, 뒤집힌 범위는 빈 결과)
+    pub fn query_ticks(&self, symbol: &str, start_ts: u64, end_ts: u64) -> Vec<Tick> {
+        let Some(sym_id) = self.lookup(symbol).map(|s| s.id) else {
+            return Vec::new();
+        };
+        // 같은 날 안에서 뒤집힌 범위는 슬라이스 구간도 뒤집힘
+        if start_ts > end_ts {
+            return Vec::new();
+        }
+        let Some(symbol_blocks) = self.tick_blocks.get(&sym_id) else {
+            return Vec::new();
+        };
+        let (start_date, end_date) = (ts_to_date(start_ts), ts_to_date(end_ts));
+        let mut blocks: Vec<TickBlock> = symbol_blocks
+            .iter()
+            .filter(|entry| *entry.key() >= start_date && *entry.key() <= end_date)
+            .map(|entry| entry.value().clone())
+            .collect();
+        drop(symbol_blocks);
+        blocks.sort_unstable_by_key(|block| block.date);
+
+        let mut result = Vec::new();
+        for block in blocks {
+            let ticks = block.decompress_shared();
+            let from = ticks.partition_point(|t| t.ts < start_ts);
+            let to = ticks.partition_point(|t| t.ts <= end_ts);
+            result.extend_from_slice(&ticks[from..to]);
+        }
+        self.note_served(result.len());
+        result
+    }
+
+    /// 반열린 구간 `[start, end)` 쿼리 (zero-copy 이터레이터, 미등록 심볼이나 빈 구간은 빈 이터레이터)
+    pub fn query(&self, symbol: &str, range: TimeRange) -> impl Iterator<Item = OHLCV> + '_ {
+        let records = range
+            .last()
+            .map(|last| self.query_range(symbol, range.start, last));
+        records.into_iter().flatten()
+    }
+
+    /// 반열린 구간의 봉 개수 (미등록 심볼이나 빈 구간은 0)
+    pub fn count(&self, symbol: &str, range: TimeRange) -> u64 {
+        range
+            .last()
+            .map_or(0, |last| self.count_range(symbol, range.start, last))
+    }
+
+    /// `after`(미포함) 이후 `range` 안의 봉을 최대 `limit`개
+    ///
+    /// 받은 마지막 봉의 ts를 다음 호출의 `after`로 넘기면 빠짐이나 중복 없이 이어짐
+    pub fn query_page(
+        &self,
+        symbol: &str,
+        range: TimeRange,
+        after: Option<u64>,
+        limit: usize,
+    ) -> Vec<OHLCV> {
+        let range = after.map_or(range, |ts| range.after(ts));
+        self.query(symbol, range).take(limit).collect()
+    }
+
+    /// 양끝 포함 `[start_ts, end_ts]` 쿼리 (zero-copy 이터레이터, 미등록 심볼이나 뒤집힌 범위는
+    /// 빈 이터레이터). 이어지는 구간을 나눠 읽을 때는 경계 봉이 겹치지 않는 `query`를 사용
+    pub fn query_range(
+        &self,
+        symbol: &str,
+        start_ts: u64,
+        end_ts: u64,
+    ) -> impl Iterator<Item = OHLCV> + '_ {
+        self.try_query_range(symbol, start_ts, end_ts)
+            .into_iter()
+            .flatten()
+    }
+
+    /// 시간 범위 쿼리 (미등록 심볼은 `UnknownSymbol`, start > end는 `QueryError::InvertedRange`,
+    /// 데이터가 없는 범위는 빈 이터레이터)
+    pub fn try_query_range(
+        &self,
+        symbol: &str,
+        start_ts: u64,
+        end_ts: u64,
+    ) -> Result<impl Iterator<Item = OHLCV> + '_> {
+        QueryError::check(start_ts, end_ts, None)?;
+        let sym_id = self.symbol_id(symbol)?;
+        let blocks = self.blocks_in_range(sym_id, start_ts, end_ts)?;
+
+        // 해제 중에 드러난 손상은 이터레이터가 오류를 낼 수 없으므로 건너뛰고 기록만 함
+        Ok(blocks.into_iter().flat_map(move |block| {
+            self.load_range(&block, start_ts, end_ts)
+                .ok()
+                .flatten()
+                .into_iter()
+                .flatten()
+                .inspect(move |_| self.note_served(1))
+        }))
+    }
+
+    /// 양끝 포함 범위의 봉 개수 (미등록 심볼이나 뒤집힌 범위는 0, 반열린 구간은 `count`)
+    pub fn count_range(&self, symbol: &str, start_ts: u64, end_ts: u64) -> u64 {
+        self.try_count_range(symbol, start_ts, end_ts).unwrap_or(0)
+    }
+
+    /// `count_range`의 오류 반환 버전
+    ///
+    /// 범위가 레코드를 모두 덮는 블록은 메타데이터의 레코드 수를 쓰고,
+    /// 범위 가장자리에 걸친 블록만 해제해 셈
+    pub fn try_count_range(&self, symbol: &str, start_ts: u64, end_ts: u64) -> Result<u64> {
+        QueryError::check(start_ts, end_ts, None)?;
+        let sym_id = self.symbol_id(symbol)?;
+
+        let mut count = 0;
+        for block in self.blocks_in_range(sym_id, start_ts, end_ts)? {
+            count += match block.records {
+                Some(records) if block.is_covered_by(start_ts, end_ts) => u64::from(records),
+                _ => self
+                    .load_range(&block, start_ts, end_ts)?
+                    .map_or(0, |bars| bars.count() as u64),
+            };
+        }
+        Ok(count)
+    }
+
+    /// 해당 날짜(YYYYMMDD)에 봉이 하나라도 있는지 (해제 없이 판단)
+    pub fn has_data(&self, symbol: &str, date: u32) -> bool {
+        let Ok(sym_id) = self.symbol_id(symbol) else {
+            return false;
+        };
+        self.blocks
+            .get(&sym_id)
+            .and_then(|blocks| blocks.get(&date).map(|block| block.min_ts <= block.max_ts))
+            .unwrap_or(false)
+    }
+
+    /// close가 [min_price, max_price] (양끝 포함)인 봉만 반환
+    ///
+    /// 가격 범위 메타데이터가 겹치지 않는 블록은 해제하지 않고 건너뜀
+    pub fn query_range_filtered(
+        &self,
+        symbol: &str,
+        start_ts: u64,
+        end_ts: u64,
+        min_price: u32,
+        max_price: u32,
+    ) -> Result<Vec<OHLCV>> {
+        QueryError::check(start_ts, end_ts, None)?;
+        let sym_id = self.symbol_id(symbol)?;
+
+        let mut result = Vec::new();
+        for block in self.blocks_in_range(sym_id, start_ts, end_ts)? {
+            if !block.may_contain_price(min_price, max_price) {
+                continue;
+            }
+            let Some(bars) = self.load_range(&block, start_ts, end_ts)? else {
+                continue;
+            };
+            let records: Vec<OHLCV> = bars.collect();
+            result.extend(SimdFilter::filter_by_price(&records, min_price, max_price));
+        }
+        self.note_served(result.len());
+        Ok(result)
+    }
+
+    /// 블록 단위로 빌린 슬라이스를 순회 (중간 Vec 없음)
+    ///
+    /// 각 뷰는 범위 안의 첫/마지막 레코드 사이 슬롯이며, 사이의 빈 슬롯(ts == 0)을 포함
+    pub fn query_chunks(
+        &self,
+        symbol: &str,
+        start_ts: u64,
+        end_ts: u64,
+    ) -> impl Iterator<Item = BlockView> + '_ {
+        let blocks = match self.lookup(symbol) {
+            Some(s) => self
+                .blocks_in_range(s.id, start_ts, end_ts)
+                .unwrap_or_default(),
+            None => Vec::new(),
+        };
+
+        blocks.into_iter().filter_map(move |block| {
+            let data = self.load(&block).ok().flatten()?;
+            let in_range = |rec: &OHLCV| rec.ts != 0 && rec.ts >= start_ts && rec.ts <= end_ts;
+            let first = data.iter().position(in_range)?;
+            let last = data.iter().rposition(in_range)?;
+            Some(BlockView {
+                data,
+                range: first..last + 1,
+            })
+        })
+    }
+
+    /// 범위 내 레코드마다 콜백 호출 (빈 슬롯 제외, 할당 없음)
+    pub fn for_each_in_range(
+        &self,
+        symbol: &str,
+        start_ts: u64,
+        end_ts: u64,
+        mut f: impl FnMut(&OHLCV),
+    ) {
+        let mut served = 0;
+        for view in self.query_chunks(symbol, start_ts, end_ts) {
+            view.iter().filter(|rec| rec.ts != 0).for_each(|rec| {
+                served += 1;
+                f(rec);
+            });
+        }
+        self.note_served(served);
+    }
+
+    /// `query_range`의 병렬 버전: 블록을 rayon으로 동시에 해제한 뒤 날짜순으로 이어 붙임
+    pub fn query_range_par(&self, symbol: &str, start_ts: u64, end_ts: u64) -> Vec<OHLCV> {
+        use rayon::prelude::*;
+
+        let Some(sym_id) = self.lookup(symbol).map(|s| s.id) else {
+            return Vec::new();
+        };
+
+        let chunks: Vec<Vec<OHLCV>> = self
+            .blocks_in_range(sym_id, start_ts, end_ts)
+            .unwrap_or_default()
+            .par_iter()
+            .map(|block| match self.load_range(block, start_ts, end_ts) {
+                Ok(Some(bars)) => bars.collect(),
+                _ => Vec::new(),
+            })
+            .collect();
+        let records = chunks.concat();
+        self.note_served(records.len());
+        records
+    }
+
+    /// tokio 런타임용 비동기 범위 쿼리: zstd 해제와 수집을 `spawn_blocking` 풀에서 수행
+    ///
+    /// `PARALLEL_QUERY_DAYS`보다 긴 범위는 `query_range_par`. 오류는 `try_query_range`와 같음
+    pub async fn query_range_async(
+        self: &Arc<Self>,
+        symbol: &str,
+        start_ts: u64,
+        end_ts: u64,
+    ) -> Result<Vec<OHLCV>> {
+        let store = Arc::clone(self);
+        let symbol = symbol.to_string();
+        let task = tokio::task::spawn_blocking(move || {
+            if end_ts.saturating_sub(start_ts) > PARALLEL_QUERY_DAYS * NANOS_PER_DAY {
+                QueryError::check(start_ts, end_ts, None)?;
+                store.symbol_id(&symbol)?;
+                Ok(store.query_range_par(&symbol, start_ts, end_ts))
+            } else {
+                Ok(store.try_query_range(&symbol, start_ts, end_ts)?.collect())
+            }
+        });
+        task.await
+            .unwrap_or_else(|e| std::panic::resume_unwind(e.into_panic()))
+    }
+
+    /// 범위의 블록을 미리 해제해 캐시에 올림 (rayon 병렬)
+    ///
+    /// 새로 해제한 블록 수를 반환. `cache_bytes`를 넘는 범위는 앞쪽 블록이 다시 밀려남
+    pub fn warm(&self, symbol: &str, start_ts: u64, end_ts: u64) -> usize {
+        use rayon::prelude::*;
+
+        let Some(sym_id) = self.lookup(symbol).map(|s| s.id) else {
+            return 0;
+        };
+
+        self.blocks_in_range(sym_id, start_ts, end_ts)
+            .unwrap_or_default()
+            .par_iter()
+            .filter(|block| !block.is_cached())
+            .filter(|block| self.load(block).is_ok_and(|data| data.is_some()))
+            .count()
+    }
+
+    /// 범위 통계 (블록별로 SIMD 리덕션)
+    ///
+    /// 빈 범위는 count 0, min_low u32::MAX (미등록 심볼은 `UnknownSymbol`,
+    /// start > end는 `QueryError::InvertedRange`)
+    pub fn query_stats(&self, symbol: &str, start_ts: u64, end_ts: u64) -> Result<RangeStats> {
+        QueryError::check(start_ts, end_ts, None)?;
+        let sym_id = self.symbol_id(symbol)?;
+        let mut stats = RangeStats {
+            min_low: u32::MAX,
+            ..Default::default()
+        };
+
+        let mut in_range = Vec::with_capacity(self.layout(symbol).granularity.slots_per_day());
+        for block in self.blocks_in_range(sym_id, start_ts, end_ts)? {
+            let Some(data) = self.load(&block)? else {
+                continue;
+            };
+            in_range.clear();
+            in_range.extend(
+                data.iter()
+                    .filter(|rec| rec.ts != 0 && rec.ts >= start_ts && rec.ts <= end_ts),
+            );
+
+            let (min_low, _) = SimdFilter::minmax(&in_range, PriceField::Low);
+            let (_, max_high) = SimdFilter::minmax(&in_range, PriceField::High);
+            stats.min_low = stats.min_low.min(min_low);
+            stats.max_high = stats.max_high.max(max_high);
+            stats.total_volume += SimdFilter::sum_volume(&in_range);
+            stats.count += in_range.len();
+        }
+
+        Ok(stats)
+    }
+
+    /// 범위의 봉을 Arrow 배치 하나로 (양끝 포함, `Vec<OHLCV>`를 거치지 않고 블록마다 빌더에 쌓음)
+    ///
+    /// 가격은 심볼 스케일을 푼 실수, symbol 컬럼은 별칭이 아닌 저장 이름
+    #[cfg(feature = "parquet")]
+    pub fn to_arrow(&self, symbol: &str, start_ts: u64, end_ts: u64) -> Result<RecordBatch> {
+        QueryError::check(start_ts, end_ts, None)?;
+        let sym = self
+            .symbol(symbol)
+            .ok_or_else(|| FxStoreError::UnknownSymbol(symbol.to_string()))?;
+        let mut builder = BarBatchBuilder::new(&sym.name, sym.scale);
+        for block in self.blocks_in_range(sym.id, start_ts, end_ts)? {
+            for rec in self
+                .load_range(&block, start_ts, end_ts)?
+                .into_iter()
+                .flatten()
+            {
+                builder.append(&rec)?;
+            }
+        }
+        Ok(builder.finish())
+    }
+
+    /// 범위의 봉을 Parquet 파일로 저장 (기록한 봉 수 반환, 컬럼은 `to_arrow`와 같음)
+    #[cfg(feature = "parquet")]
+    pub fn export_parquet(
+        &self,
+        symbol: &str,
+        start_ts: u64,
+        end_ts: u64,
+        path: impl AsRef<std::path::Path>,
+    ) -> Result<usize> {
+        let sym = self
+            .symbol(symbol)
+            .ok_or_else(|| FxStoreError::UnknownSymbol(symbol.to_string()))?;
+        QueryError::check(start_ts, end_ts, None)?;
+        let file = std::fs::File::create(path)?;
+        self.write_symbol_parquet(&sym, start_ts, end_ts, std::io::BufWriter::new(file))
+    }
+
+    /// 범위의 봉을 Parquet으로 기록 (블록마다 배치 하나를 써서 메모리는 블록 한 개 분량)
+    ///
+    /// 기록을 시작하기 전에 심볼과 범위를 검사하므로 오류 없이 첫 바이트가 나가면 파일이 완성됨
+    #[cfg(feature = "parquet")]
+    pub fn write_parquet<W: std::io::Write + Send>(
+        &self,
+        symbol: &str,
+        start_ts: u64,
+        end_ts: u64,
+        writer: W,
+    ) -> Result<usize> {
+        QueryError::check(start_ts, end_ts, None)?;
+        let sym = self
+            .symbol(symbol)
+            .ok_or_else(|| FxStoreError::UnknownSymbol(symbol.to_string()))?;
+        self.write_symbol_parquet(&sym, start_ts, end_ts, writer)
+    }
+
+    #[cfg(feature = "parquet")]
+    fn write_symbol_parquet<W: std::io::Write + Send>(
+        &self,
+        sym: &Symbol,
+        start_ts: u64,
+        end_ts: u64,
+        writer: W,
+    ) -> Result<usize> {
+        let blocks = self.blocks_in_range(sym.id, start_ts, end_ts)?;
+        let mut writer = bar_writer(writer)?;
+        let mut builder = BarBatchBuilder::new(&sym.name, sym.scale);
+        let mut rows = 0;
+        for block in blocks {
+            for rec in self
+                .load_range(&block, start_ts, end_ts)?
+                .into_iter()
+                .flatten()
+            {
+                builder.append(&rec)?;
+            }
+            if builder.len() > 0 {
+                rows += builder.len();
+                writer.write(&builder.finish())?;
+            }
+        }
+        writer.into_inner()?.flush()?;
+        Ok(rows)
+    }
+
+    /// 가격대별 거래량 (volume profile) → (구간 하단 가격, 거래량), 가격 오름차순
+    ///
+    /// 봉마다 대표가격 (고가+저가+종가)/3이 속한 `bucket_size` 폭(스케일 적용 전 가격 단위)의
+    /// 구간에 거래량 전체를 더함. 미등록 심볼이나 0 이하·NaN인 `bucket_size`는 빈 결과
+    pub fn volume_profile(
+        &self,
+        symbol: &str,
+        start_ts: u64,
+        end_ts: u64,
+        bucket_size: f64,
+    ) -> Vec<(f64, u64)> {
+        let scale = f64::from(self.price_scale(symbol));
+        // 원시 가격 단위로 나눠야 1.1 / 0.0001 같은 부동소수 오차로 구간이 밀리지 않음
+        let bucket = bucket_size * scale;
+        if !(bucket > 0.0 && bucket.is_finite()) {
+            return Vec::new();
+        }
+
+        let mut volumes: BTreeMap<i64, u64> = BTreeMap::new();
+        self.for_each_in_range(symbol, start_ts, end_ts, |rec| {
+            let typical =
+                (u64::from(rec.high) + u64::from(rec.low) + u64::from(rec.close)) as f64 / 3.0;
+            *volumes
+                .entry((typical / bucket).floor() as i64)
+                .or_default() += rec.total_volume();
+        });
+        volumes
+            .into_iter()
+            .map(|(index, volume)| (index as f64 * bucket / scale, volume))
+            .collect()
+    }
+
+    /// 블록 해제 (캐시 적중 시 cache_hits 집계)
+    ///
+    /// 체크섬은 맞지만 해제에 실패한 블록은 `check_block`처럼 기록하고 `OnCorrupt::Skip`이면 None
+    fn load(&self, block: &CompressedBlock) -> Result<Option<Arc<[OHLCV]>>> {
+        self.note_cache_hit(block);
+        self.skip_corrupt(block, block.try_decompress_shared())
+    }
+
+    /// `load`의 범위 버전 (`CompressedBlock::iter_range`)
+    fn load_range(
+        &self,
+        block: &CompressedBlock,
+        start_ts: u64,
+        end_ts: u64,
+    ) -> Result<Option<impl Iterator<Item = OHLCV> + use<>>> {
+        self.note_cache_hit(block);
+        self.skip_corrupt(block, block.iter_range(start_ts, end_ts))
+    }
+
+    /// 블록을 읽기 직전 호출: 캐시 적중을 집계하고, 새로 해제될 블록은 캐시 예산에 등록
+    fn note_cache_hit(&self, block: &CompressedBlock) {
+        if block.is_cached() {
+            self.stats.cache_hits.fetch_add(1, Ordering::Relaxed);
+            return;
+        }
+        self.stats
+            .blocks_decompressed
+            .fetch_add(1, Ordering::Relaxed);
+        if let Some(budget) = self.config.cache_bytes {
+            self.cache.lock().admit(block, budget);
+        }
+    }
+
+    /// 쿼리가 반환한 레코드 수 집계
+    fn note_served(&self, records: usize) {
+        self.stats
+            .query_records
+            .fetch_add(records as u64, Ordering::Relaxed);
+    }
+
+    /// 날짜 범위에 걸친 블록 (날짜순, DashMap 순회 순서는 보장되지 않음)
+    ///
+    /// 범위 안에 레코드가 없는 블록은 ts 메타데이터로 걸러 해제하지 않음.
+    /// 체크섬이 맞지 않는 블록은 `config.on_corrupt`에 따라 빼거나 `BlockCorrupt`
+    fn blocks_in_range(
+        &self,
+        sym_id: u16,
+        start_ts: u64,
+        end_ts: u64,
+    ) -> Result<Vec<CompressedBlock>> {
+        let start_date = ts_to_date(start_ts);
+        let end_date = ts_to_date(end_ts);
+
+        let Some(symbol_blocks) = self.blocks.get(&sym_id) else {
+            return Ok(Vec::new());
+        };
+        let mut blocks: Vec<_> = symbol_blocks
+            .iter()
+            .filter(|entry| *entry.key() >= start_date && *entry.key() <= end_date)
+            .filter(|entry| entry.may_contain_ts(start_ts, end_ts))
+            .map(|entry| entry.value().clone())
+            .collect();
+        drop(symbol_blocks);
+        blocks.sort_unstable_by_key(|block| block.date);
+
+        let mut readable = Vec::with_capacity(blocks.len());
+        for block in blocks {
+            if self.check_block(&block)? {
+                readable.push(block);
+            }
+        }
+        Ok(readable)
+    }
+
+    /// 블록을 읽어도 되는지 (해제 전 체크섬 검사, 캐시된 블록은 이미 검증됨)
+    ///
+    /// 손상된 블록은 기록한 뒤 `OnCorrupt::Skip`이면 false, `Error`면 `BlockCorrupt`
+    fn check_block(&self, block: &CompressedBlock) -> Result<bool> {
+        if block.is_cached() {
+            return Ok(true);
+        }
+        Ok(self.skip_corrupt(block, block.verify())?.is_some())
+    }
+
+    /// 손상된 블록을 기록하고 `OnCorrupt::Skip`이면 None, `Error`면 그 오류
+    fn skip_corrupt<T>(&self, block: &CompressedBlock, result: Result<T>) -> Result<Option<T>> {
+        match result {
+            Ok(value) => Ok(Some(value)),
+            Err(e) => {
+                tracing::warn!(error = %e, "query hit a corrupt block");
+                self.corrupt.lock().insert((block.symbol_id, block.date));
+                match self.config.on_corrupt {
+                    OnCorrupt::Skip => Ok(None),
+                    OnCorrupt::Error => Err(e),
+                }
+            }
+        }
+    }
+
+    /// 범위 안에서 손상돼 쿼리가 건너뛴 날짜 (YYYYMMDD, 날짜순)
+    ///
+    /// 쿼리나 `scrub`이 발견한 블록만 보고하며, 그 사이 다시 임포트돼 읽을 수 있게 된 날짜는 지움
+    pub fn corrupt_days(&self, symbol: &str, start_ts: u64, end_ts: u64) -> Vec<u32> {
+        let Some(sym_id) = self.lookup(symbol).map(|s| s.id) else {
+            return Vec::new();
+        };
+        if start_ts > end_ts {
+            return Vec::new();
+        }
+        let range = (sym_id, ts_to_date(start_ts))..=(sym_id, ts_to_date(end_ts));
+        let flagged: Vec<(u16, u32)> = self.corrupt.lock().range(range).copied().collect();
+        if flagged.is_empty() {
+            return Vec::new();
+        }
+
+        let symbol_blocks = self.blocks.get(&sym_id);
+        let (corrupt, repaired): (Vec<_>, Vec<_>) = flagged.into_iter().partition(|(_, date)| {
+            symbol_blocks
+                .as_ref()
+                .and_then(|blocks| blocks.get(date).map(|block| block.check().is_err()))
+                .unwrap_or(false)
+        });
+        drop(symbol_blocks);
+        let mut set = self.corrupt.lock();
+        for key in &repaired {
+            set.remove(key);
+        }
+        corrupt.into_iter().map(|(_, date)| date).collect()
+    }
+
+    /// 모든 블록의 체크섬과 해제를 검사 (캐시된 블록도 압축 데이터부터 다시 확인)
+    ///
+    /// `repair`면 손상된 블록을 지우고 블록 파일에도 삭제를 기록해, 그 날짜를 다시 임포트할 수 있게 함
+    pub fn scrub(&self, repair: bool) -> Result<ScrubReport> {
+        let names: HashMap<u16, String> = self
+            .symbols
+            .iter()
+            .map(|entry| (entry.id, entry.key().clone()))
+            .collect();
+        let mut blocks: Vec<CompressedBlock> = self
+            .blocks
+            .iter()
+            .flat_map(|symbol_blocks| {
+                symbol_blocks
+                    .iter()
+                    .map(|entry| entry.value().clone())
+                    .collect::<Vec<_>>()
+            })
+            .collect();
+        blocks.sort_unstable_by_key(|block| (block.symbol_id, block.date));
+
+        let mut report = ScrubReport {
+            blocks_checked: blocks.len(),
+            ..Default::default()
+        };
+        for block in blocks {
+            let Err(e) = block.check() else {
+                self.corrupt.lock().remove(&(block.symbol_id, block.date));
+                continue;
+            };
+            tracing::warn!(error = %e, "scrub found a corrupt block");
+            let reason = match e {
+                FxStoreError::BlockCorrupt { reason, .. } => reason,
+                other => other.to_string(),
+            };
+            report.corrupt.push(CorruptBlock {
+                symbol: names.get(&block.symbol_id).cloned().unwrap_or_default(),
+                date: block.date,
+                reason,
+            });
+
+            if !repair {
+                self.corrupt.lock().insert((block.symbol_id, block.date));
+                continue;
+            }
+            // 검사 중 교체된 블록은 지우지 않음
+            let removed = self.blocks.get(&block.symbol_id).and_then(|blocks| {
+                blocks.remove_if(&block.date, |_, current| {
+                    Arc::ptr_eq(&current.data, &block.data)
+                })
+            });
+            if removed.is_some() {
+                if let Some(records) = block.records {
+                    self.stats
+                        .total_records
+                        .fetch_sub(u64::from(records), Ordering::Relaxed);
+                }
+                self.stats
+                    .compressed_bytes
+                    .fetch_sub(block.data.len() as u64, Ordering::Relaxed);
+                self.cache.lock().forget(block.symbol_id, block.date);
+                // 지운 블록에서 온 최신가는 버리고 다음 `latest` 때 남은 블록에서 다시 찾음
+                self.last_prices
+                    .remove_if(&block.symbol_id, |_, rec| ts_to_date(rec.ts) == block.date);
+                self.dirty.lock().insert((block.symbol_id, block.date));
+                self.stats.bump_generation(block.symbol_id);
+                report.removed += 1;
+            }
+            self.corrupt.lock().remove(&(block.symbol_id, block.date));
+        }
+
+        #[cfg(feature = "persistence")]
+        if report.removed > 0 && self.flusher.is_some() {
+            self.flush_now()?;
+        }
+        Ok(report)
+    }
+
+    /// 단일 레코드 삽입 (해당 날짜 블록에 병합)
+    pub fn insert(&self, symbol: &str, record: OHLCV) -> Result<()> {
+        self.insert_batch(symbol, &[record])
+    }
+
+    /// 여러 레코드를 한 번에 삽입 (WAL 항목 하나, 날짜별로 블록 병합 한 번)
+    ///
+    /// 범위를 벗어난 타임스탬프가 하나라도 있으면 아무것도 넣지 않음
+    pub fn insert_batch(&self, symbol: &str, records: &[OHLCV]) -> Result<()> {
+        if let Some(bad) = records.iter().find(|r| r.ts == 0 || r.ts > MAX_TS) {
+            return Err(FxStoreError::TimestampOutOfRange(bad.ts));
+        }
+        if records.is_empty() {
+            return Ok(());
+        }
+        let sym_id = self.get_or_create_symbol(symbol)?;
+        let records: Vec<OHLCV> = records
+            .iter()
+            .map(|&r| OHLCV {
+                symbol_id: sym_id,
+                ..r
+            })
+            .collect();
+        let gate = self.gate.read();
+        self.log_records(symbol, &records)?;
+
+        let mut daily: BTreeMap<u32, Vec<OHLCV>> = BTreeMap::new();
+        for record in records {
+            daily.entry(ts_to_date(record.ts)).or_default().push(record);
+        }
+        let layout = self.layout(symbol);
+        for (date, records) in daily {
+            store_batch(
+                &self.blocks,
+                &self.last_prices,
+                &self.stats,
+                layout,
+                date,
+                sym_id,
+                &records,
+            );
+            self.dirty.lock().insert((sym_id, date));
+        }
+        drop(gate);
+        self.apply_retention(symbol);
+        Ok(())
+    }
+
+    /// 심볼의 최신 날짜 기준 `max_days`일만 보존 (0이면 해제)
+    ///
+    /// 이후 삽입/임포트마다 그보다 오래된 봉/틱 블록을 삭제하고 캐시도 해제.
+    /// WAL은 `checkpoint` 전까지 삭제된 봉도 담고 있음
+    pub fn set_retention(&self, symbol: &str, max_days: u32) {
+        let symbol = self.resolve(symbol);
+        if max_days == 0 {
+            self.retention.remove(symbol.as_ref());
+            return;
+        }
+        self.retention.insert(symbol.to_string(), max_days);
+        self.flush();
+        self.apply_retention(&symbol);
+    }
+
+    /// 보존 기간이 설정된 전체 심볼 정리 (주기적 스윕용), 삭제한 블록 수 반환
+    pub fn enforce_retention(&self) -> usize {
+        self.flush();
+        let symbols: Vec<String> = self.retention.iter().map(|e| e.key().clone()).collect();
+        symbols
+            .iter()
+            .map(|symbol| self.apply_retention(symbol))
+            .sum()
+    }
+
+    /// 보존 기간 밖의 블록 삭제 (대기 중인 압축 작업은 반영하지 않음)
+    fn apply_retention(&self, symbol: &str) -> usize {
+        let Some(max_days) = self
+            .retention
+            .get(self.resolve(symbol).as_ref())
+            .map(|days| *days)
+        else {
+            return 0;
+        };
+        let Some(sym_id) = self.lookup(symbol).map(|s| s.id) else {
+            return 0;
+        };
+        let Some(symbol_blocks) = self.blocks.get(&sym_id) else {
+            return 0;
+        };
+        let Some(newest) = symbol_blocks.iter().map(|entry| *entry.key()).max() else {
+            return 0;
+        };
+        let cutoff = ts_to_date(
+            date_to_ts(newest)
+                .saturating_sub(u64::from(max_days - 1).saturating_mul(NANOS_PER_DAY)),
+        );
+
+        let expired: Vec<u32> = symbol_blocks
+            .iter()
+            .map(|entry| *entry.key())
+            .filter(|&date| date < cutoff)
+            .collect();
+        for &date in &expired {
+            let Some((_, block)) = symbol_blocks.remove(&date) else {
+                continue;
+            };
+            let records = block
+                .try_decompress_shared()
+                .map_or(0, |slots| slots.iter().filter(|rec| rec.ts != 0).count());
+            self.stats
+                .total_records
+                .fetch_sub(records as u64, Ordering::Relaxed);
+            self.stats
+                .compressed_bytes
+                .fetch_sub(block.data.len() as u64, Ordering::Relaxed);
+            self.cache.lock().forget(sym_id, date);
+            self.dirty.lock().insert((sym_id, date));
+            self.stats.bump_generation(sym_id);
+            block.evict();
+        }
+        drop(symbol_blocks);
+
+        if let Some(tick_blocks) = self.tick_blocks.get(&sym_id) {
+            tick_blocks.retain(|&date, _| date >= cutoff);
+        }
+        expired.len()
+    }
+
+    /// 최신 바 조회 (캐시 우선, 콜드 캐시면 최신 블록 스캔)
+    pub fn latest(&self, symbol: &str) -> Option<OHLCV> {
+        let sym_id = self.lookup(symbol)?.id;
+
+        if let Some(rec) = self.last_prices.get(&sym_id) {
+            self.stats.last_price_hits.fetch_add(1, Ordering::Relaxed);
+            return Some(*rec);
+        }
+
+        self.stats.last_price_misses.fetch_add(1, Ordering::Relaxed);
+        let rec = self.scan_latest(sym_id)?;
+        update_last_price(&self.last_prices, &rec);
+        Some(rec)
+    }
+
+    /// 블록 요약으로 전체 심볼의 최신가 캐시 채우기 (영속 스토어 로드 직후 등)
+    pub fn warm_last_prices(&self) {
+        let sym_ids: Vec<u16> = self.blocks.iter().map(|entry| *entry.key()).collect();
+        for sym_id in sym_ids {
+            if let Some(rec) = self.scan_latest(sym_id) {
+                update_last_price(&self.last_prices, &rec);
+            }
+        }
+    }
+
+    fn scan_latest(&self, sym_id: u16) -> Option<OHLCV> {
+        let symbol_blocks = self.blocks.get(&sym_id)?;
+        let mut dates: Vec<u32> = symbol_blocks.iter().map(|entry| *entry.key()).collect();
+        dates.sort_unstable();
+
+        // 최신 날짜부터, 메타데이터상 빈 블록은 해제하지 않고 건너뜀
+        dates.into_iter().rev().find_map(|date| {
+            let block = symbol_blocks.get(&date)?;
+            (block.min_ts <= block.max_ts)
+                .then(|| block.last_record())
+                .flatten()
+        })
+    }
+
+    /// 저장된 첫 봉과 마지막 봉의 ts (미등록이거나 봉이 없으면 None)
+    pub fn time_bounds(&self, symbol: &str) -> Option<(u64, u64)> {
+        let sym_id = self.lookup(symbol)?.id;
+        let symbol_blocks = self.blocks.get(&sym_id)?;
+        let mut dates: Vec<u32> = symbol_blocks.iter().map(|entry| *entry.key()).collect();
+        dates.sort_unstable();
+
+        let first = dates
+            .iter()
+            .find_map(|date| symbol_blocks.get(date)?.first_record())?;
+        let last = dates
+            .iter()
+            .rev()
+            .find_map(|date| symbol_blocks.get(date)?.last_record())?;
+        Some((first.ts, last.ts))
+    }
+
+    /// 심볼의 일 블록 목록 (날짜순, 미등록이면 빈 목록)
+    ///
+    /// 블록 메타데이터를 쓰고, 메타데이터가 없는 블록만 해제해 셈 (손상된 블록은 0개)
+    pub fn blocks_info(&self, symbol: &str) -> Vec<BlockInfo> {
+        let Some(sym_id) = self.lookup(symbol).map(|s| s.id) else {
+            return Vec::new();
+        };
+        let Some(symbol_blocks) = self.blocks.get(&sym_id) else {
+            return Vec::new();
+        };
+        let blocks: Vec<CompressedBlock> = symbol_blocks
+            .iter()
+            .map(|entry| entry.value().clone())
+            .collect();
+        drop(symbol_blocks);
+
+        let mut infos: Vec<BlockInfo> = blocks
+            .into_iter()
+            .map(|block| {
+                let block = match block.records {
+                    Some(_) => block,
+                    None => match block.try_decompress_shared() {
+                        Ok(slots) => block.with_bounds(&slots),
+                        Err(_) => block.with_bounds(&[]),
+                    },
+                };
+                BlockInfo {
+                    date: block.date,
+                    compressed_bytes: block.data.len(),
+                    record_count: block.records.unwrap_or(0),
+                    min_price: block.min_price,
+                    max_price: block.max_price,
+                }
+            })
+            .collect();
+        infos.sort_unstable_by_key(|info| info.date);
+        infos
+    }
+
+    /// 심볼의 일 블록 수 (미등록이면 0)
+    pub fn block_count(&self, symbol: &str) -> usize {
+        self.lookup(symbol)
+            .and_then(|sym| self.blocks.get(&sym.id).map(|blocks| blocks.len()))
+            .unwrap_or(0)
+    }
+
+    /// ts 시점에 유효한 바 (ts 이하의 마지막 바, 주말 등 빈 구간은 이전 날짜로 거슬러 탐색)
+    pub fn query_asof(&self, symbol: &str, ts: u64) -> Option<OHLCV> {
+        self.query_asof_many(symbol, &[ts]).pop().flatten()
+    }
+
+    /// 여러 시점의 as-of 조회 (입력 순서대로 반환, 해제한 블록은 재사용)
+    pub fn query_asof_many(&self, symbol: &str, timestamps: &[u64]) -> Vec<Option<OHLCV>> {
+        let mut result = vec![None; timestamps.len()];
+
+        let Some(sym_id) = self.lookup(symbol).map(|s| s.id) else {
+            return result;
+        };
+        let Some(symbol_blocks) = self.blocks.get(&sym_id) else {
+            return result;
+        };
+
+        let mut dates: Vec<u32> = symbol_blocks.iter().map(|entry| *entry.key()).collect();
+        dates.sort_unstable();
+
+        let mut order: Vec<usize> = (0..timestamps.len()).collect();
+        order.sort_unstable_by_key(|&i| timestamps[i]);
+
+        let mut decompressed: HashMap<u32, Arc<[OHLCV]>> = HashMap::new();
+        for i in order {
+            let ts = timestamps[i];
+            let upto = dates.partition_point(|&date| date_to_ts(date) <= ts);
+
+            result[i] = dates[..upto].iter().rev().find_map(|&date| {
+                let records = match decompressed.entry(date) {
+                    std::collections::hash_map::Entry::Occupied(e) => e.into_mut(),
+                    std::collections::hash_map::Entry::Vacant(e) => {
+                        let block = symbol_blocks.get(&date)?;
+                        if !self.check_block(&block).unwrap_or(false) {
+                            return None;
+                        }
+                        e.insert(self.load(&block).ok().flatten()?)
+                    }
+                };
+                records
+                    .iter()
+                    .rev()
+                    .find(|rec| rec.ts != 0 && rec.ts <= ts)
+                    .copied()
+            });
+        }
+
+        result
+    }
+
+    /// 대기 중인 압축 작업이 모두 반영될 때까지 블록
+    pub fn flush(&self) {
+        self.compress.barrier();
+    }
+
+    /// 쿼리에 응할 준비가 됐는지 (`/ready`)
+    pub fn is_ready(&self) -> bool {
+        self.ready.load(Ordering::Acquire)
+    }
+
+    /// 준비 상태 설정
+    ///
+    /// 열린 스토어는 블록 파일과 WAL을 이미 읽었으므로 준비된 상태로 시작.
+    /// 백그라운드 임포트처럼 뒤이어 데이터를 채울 때는 시작 전에 false, 끝나면 true
+    pub fn set_ready(&self, ready: bool) {
+        self.ready.store(ready, Ordering::Release);
+    }
+
+    /// 스토어를 만든 뒤 지난 시간
+    pub fn uptime(&self) -> Duration {
+        self.created_at.elapsed()
+    }
+
+    pub fn stats(&self) -> StatsSnapshot {
+        StatsSnapshot {
+            total_records: self.stats.total_records.load(Ordering::Relaxed),
+            compressed_bytes: self.stats.compressed_bytes.load(Ordering::Relaxed),
+            cache_hits: self.stats.cache_hits.load(Ordering::Relaxed),
+            blocks_decompressed: self.stats.blocks_decompressed.load(Ordering::Relaxed),
+            query_records: self.stats.query_records.load(Ordering::Relaxed),
+            resident_bytes: self
+                .blocks
+                .iter()
+                .map(|entry| {
+                    entry
+                        .iter()
+                        .filter(|block| block.is_cached())
+                        .map(|block| block.decompressed_bytes() as u64)
+                        .sum::<u64>()
+                })
+                .sum(),
+            compress_queue_depth: self.compress.len() as u64,
+            dropped_batches: self.stats.dropped_batches.load(Ordering::Relaxed),
+            block_count: self.blocks.iter().map(|entry| entry.len() as u64).sum(),
+            blocks_by_symbol: self
+                .symbols
+                .iter()
+                .map(|entry| {
+                    let blocks = self.blocks.get(&entry.id).map_or(0, |b| b.len() as u64);
+                    (entry.key().clone(), blocks)
+                })
+                .collect(),
+            last_price_entries: self.last_prices.len() as u64,
+            last_price_hits: self.stats.last_price_hits.load(Ordering::Relaxed),
+            last_price_misses: self.stats.last_price_misses.load(Ordering::Relaxed),
+            late_ticks: self.stats.late_ticks.load(Ordering::Relaxed),
+            dropped_bars: self.stats.dropped_bars.load(Ordering::Relaxed),
+            subscribers: self.subscribers.snapshot(),
+            imported_records: self.stats.imported_records.load(Ordering::Relaxed),
+            import_nanos: self.stats.import_nanos.load(Ordering::Relaxed),
+        }
+    }
+
+    /// interval_secs 단위로 리샘플한 구간 쿼리 (버킷 경계는 `alignment` 기준)
+    ///
+    /// 버킷도 `[버킷 시작, 다음 버킷 시작)`이라, `range`가 버킷 경계에서 끝나면 마지막 버킷이 온전함
+    pub fn query_resampled(
+        &self,
+        symbol: &str,
+        range: TimeRange,
+        interval_secs: u64,
+        alignment: Alignment,
+    ) -> Vec<OHLCV> {
+        let records: Vec<OHLCV> = self.query(symbol, range).collect();
+        resample(&records, interval_secs, alignment)
+    }
+
+    /// 날짜별 일봉 (UTC 자정 기준, 블록마다 한 개씩 접어 범위 전체를 펼치지 않음)
+    ///
+    /// 초 단위 심볼처럼 슬롯이 많은 경우에도 하루치 이상을 한꺼번에 들고 있지 않음
+    pub fn query_daily(&self, symbol: &str, start_ts: u64, end_ts: u64) -> Vec<OHLCV> {
+        let Ok(sym_id) = self.symbol_id(symbol) else {
+            return Vec::new();
+        };
+        let mut daily = Vec::new();
+        let mut records = Vec::new();
+        for block in self
+            .blocks_in_range(sym_id, start_ts, end_ts)
+            .unwrap_or_default()
+        {
+            records.clear();
+            if let Ok(Some(bars)) = self.load_range(&block, start_ts, end_ts) {
+                records.extend(bars);
+            }
+            self.note_served(records.len());
+            daily.extend(resample(&records, 86_400, Alignment::UTC));
+        }
+        daily
+    }
+
+    /// 빈 분을 fill 방식으로 채운 1분 봉 시계열 (차트용)
+    ///
+    /// forward fill은 범위 직전의 봉도 이어받으므로 범위 첫머리의 공백도 채움.
+    /// 결과 길이는 범위 길이(분)에 비례하므로 호출 측에서 범위를 제한할 것
+    pub fn query_range_filled(
+        &self,
+        symbol: &str,
+        start_ts: u64,
+        end_ts: u64,
+        fill: FillPolicy,
+    ) -> Vec<OHLCV> {
+        if !self.has_symbol(symbol) {
+            return Vec::new();
+        }
+        let records: Vec<OHLCV> = self.query_range(symbol, start_ts, end_ts).collect();
+        let prev = match fill {
+            FillPolicy::ForwardFill { .. } if start_ts > 0 => self.query_asof(symbol, start_ts - 1),
+            _ => None,
+        };
+        fill_minutes(&records, prev, start_ts, end_ts, fill)
+    }
+
+    /// 여러 심볼 병렬 쿼리 (입력 순서대로, 미등록 심볼은 None)
+    pub fn query_range_multi(
+        &self,
+        symbols: &[&str],
+        start_ts: u64,
+        end_ts: u64,
+    ) -> Vec<Option<Vec<OHLCV>>> {
+        use rayon::prelude::*;
+
+        symbols
+            .par_iter()
+            .map(|symbol| {
+                self.has_symbol(symbol)
+                    .then(|| self.query_range(symbol, start_ts, end_ts).collect())
+            })
+            .collect()
+    }
+
+    /// 두 심볼의 비율/차이 합성 봉 (두 심볼 모두 봉이 있는 분만)
+    pub fn synthetic(&self, spec: SyntheticSpec, start_ts: u64, end_ts: u64) -> Vec<OHLCV> {
+        let series = self.query_range_multi(&spec.symbols(), start_ts, end_ts);
+        match (&series[0], &series[1]) {
+            (Some(a), Some(b)) => synthesize(&spec, a, b),
+            _ => Vec::new(),
+        }
+    }
+
+    /// 두 통화쌍의 크로스 환율 봉 (EURUSD, USDJPY → EURJPY), 두 다리 모두 봉이 있는 분만
+    ///
+    /// 크로스 심볼을 `Cross::scale`로 등록하고 봉에 그 id를 붙임 (이미 봉이 있는 심볼이면 기존 스케일).
+    /// 초 단위 다리는 1분봉으로 리샘플해 맞춤. 공유하는 통화가 정확히 하나가 아니면 `Incompatible`
+    pub fn synthetic_cross(
+        &self,
+        base_pair: &str,
+        quote_pair: &str,
+        start_ts: u64,
+        end_ts: u64,
+    ) -> Result<Vec<OHLCV>> {
+        QueryError::check(start_ts, end_ts, None)?;
+        let leg = |name: &str| {
+            self.symbol(name)
+                .ok_or_else(|| FxStoreError::UnknownSymbol(name.to_string()))
+        };
+        let (first, second) = (leg(base_pair)?, leg(quote_pair)?);
+        let mut cross = Cross::new(&first, &second).ok_or_else(|| {
+            FxStoreError::Incompatible(format!(
+                "{} and {} do not share exactly one currency",
+                first.name, second.name
+            ))
+        })?;
+
+        let sym = self.register_symbol(&cross.name(), Granularity::Minute)?;
+        self.set_scale_if_empty(&sym.name, sym.id, cross.scale);
+        cross.scale = self.price_scale(&sym.name);
+
+        let [a, b] = [&first, &second].map(|leg| {
+            if leg.granularity.seconds() < 60 {
+                self.query_resampled(
+                    &leg.name,
+                    TimeRange::inclusive(start_ts, end_ts),
+                    60,
+                    Alignment::UTC,
+                )
+            } else {
+                self.query_range(&leg.name, start_ts, end_ts).collect()
+            }
+        });
+        Ok(synthesize_cross(&cross, &a, &b, sym.id))
+    }
+
+    /// 저장된 봉을 `stream_realtime`과 같은 채널로 재생 (백테스트용)
+    ///
+    /// 블록은 재생이 그 날짜에 닿을 때 해제하고, 캐시에 없던 블록은 캐시에 남기지 않음.
+    /// 채널이 가득 차면 수신 측을 기다리고(봉을 버리지 않음), 수신자를 drop하면 다음 봉에서 멈춤.
+    /// 호출 시점에 있던 블록만 재생
+    pub fn replay(
+        &self,
+        symbol: &str,
+        start_ts: u64,
+        end_ts: u64,
+        speed: ReplaySpeed,
+    ) -> Result<Receiver<OHLCV>> {
+        self.spawn_replay(symbol, start_ts, end_ts, speed, false)
+    }
+
+    /// `replay`와 같되 재생하는 봉을 실시간 구독자(`stream_realtime`, `subscribe_all`)에게도 전달
+    ///
+    /// `/stream` 구독 중인 차트에 과거 구간을 흘려보낼 때 사용. 실시간 봉과 섞여 나감
+    pub fn replay_broadcast(
+        &self,
+        symbol: &str,
+        start_ts: u64,
+        end_ts: u64,
+        speed: ReplaySpeed,
+    ) -> Result<Receiver<OHLCV>> {
+        self.spawn_replay(symbol, start_ts, end_ts, speed, true)
+    }
+
+    fn spawn_replay(
+        &self,
+        symbol: &str,
+        start_ts: u64,
+        end_ts: u64,
+        speed: ReplaySpeed,
+        broadcast: bool,
+    ) -> Result<Receiver<OHLCV>> {
+        QueryError::check(start_ts, end_ts, None)?;
+        let sym_id = self.symbol_id(symbol)?;
+        let blocks = self.blocks_in_range(sym_id, start_ts, end_ts)?;
+        let (tx, rx) = bounded(self.config.subscriber_capacity);
+        let stats = Arc::clone(&self.stats);
+        let broadcast = broadcast.then(|| {
+            (
+                self.resolve(symbol).into_owned(),
+                Arc::clone(&self.subscribers),
+            )
+        });
+
+        std::thread::spawn(move || {
+            let mut clock = None;
+            for block in blocks {
+                let bars = if block.is_cached() {
+                    stats.cache_hits.fetch_add(1, Ordering::Relaxed);
+                    block.iter_range(start_ts, end_ts)
+                } else {
+                    stats.blocks_decompressed.fetch_add(1, Ordering::Relaxed);
+                    // 이터레이터가 해제 결과를 들고 있으므로 캐시에서는 바로 내려도 됨
+                    let bars = block.iter_range(start_ts, end_ts);
+                    block.evict();
+                    bars
+                };
+                let Ok(bars) = bars
+                    .inspect_err(|e| tracing::warn!(error = %e, "replay skipped a corrupt block"))
+                else {
+                    continue;
+                };
+                for bar in bars {
+                    let (started, first_ts) = *clock.get_or_insert((Instant::now(), bar.ts));
+                    if let Some(offset) = speed.offset(bar.ts - first_ts) {
+                        let Some(due) = started.checked_add(offset) else {
+                            return;
+                        };
+                        std::thread::sleep(due.saturating_duration_since(Instant::now()));
+                    }
+                    if tx.send(bar).is_err() {
+                        return;
+                    }
+                    stats.query_records.fetch_add(1, Ordering::Relaxed);
+                    if let Some((symbol, subscribers)) = &broadcast {
+                        subscribers.publish(symbol, sym_id, &[bar], &stats);
+                    }
+                }
+            }
+        });
+        Ok(rx)
+    }
+
+    /// 여러 심볼의 종가(심볼별 스케일로 환산)를 같은 타임스탬프 축으로 정렬
+    pub fn query_aligned(
+        &self,
+        symbols: &[&str],
+        start_ts: u64,
+        end_ts: u64,
+        fill: FillPolicy,
+    ) -> AlignedFrame {
+        let series: Vec<Vec<OHLCV>> = self
+            .query_range_multi(symbols, start_ts, end_ts)
+            .into_iter()
+            .map(Option::unwrap_or_default)
+            .collect();
+        let scales: Vec<u32> = symbols.iter().map(|s| self.price_scale(s)).collect();
+        align_closes(symbols, &series, &scales, fill)
+    }
+
+    /// 가중 바스켓 지수 (예: 여러 통화쌍으로 만드는 USD 강세 지수), 분마다 Σ weight × 종가
+    ///
+    /// 종가만으로 만들므로 OHLC가 같은 평평한 봉이고 거래량은 0. 값은 `PRICE_SCALE`로 다시
+    /// 스케일하며 음수는 0. `fill`을 거친 뒤에도 빈 구성 종목이 있는 분은 제외
+    /// (`FillPolicy::None`은 `Drop`과 같음). 미등록 심볼은 모든 분이 빈 종목
+    pub fn basket(
+        &self,
+        components: &[(String, f64)],
+        start_ts: u64,
+        end_ts: u64,
+        fill: FillPolicy,
+    ) -> Vec<OHLCV> {
+        let symbols: Vec<&str> = components.iter().map(|(s, _)| s.as_str()).collect();
+        let frame = self.query_aligned(&symbols, start_ts, end_ts, fill);
+
+        frame
+            .timestamps
+            .iter()
+            .enumerate()
+            .filter_map(|(i, &ts)| {
+                let value = frame
+                    .columns
+                    .iter()
+                    .zip(components)
+                    .map(|(column, (_, weight))| column[i].map(|close| close * weight))
+                    .sum::<Option<f64>>()?;
+                let close = (value * f64::from(PRICE_SCALE))
+                    .round()
+                    .clamp(0.0, f64::from(u32::MAX)) as u32;
+                Some(OHLCV {
+                    ts,
+                    open: close,
+                    high: close,
+                    low: close,
+                    close,
+                    ..Default::default()
+                })
+            })
+            .collect()
+    }
+
+    /// 두 심볼 종가의 롤링 상관계수 → (구간 끝 타임스탬프, 상관계수)
+    ///
+    /// 1시간 이내 공백은 직전 값으로 채우고, 나머지 빈 분은 제외
+    pub fn correlation(
+        &self,
+        sym_a: &str,
+        sym_b: &str,
+        start_ts: u64,
+        end_ts: u64,
+        window: usize,
+    ) -> (Vec<u64>, Vec<f64>) {
+        let frame = self.query_aligned(
+            &[sym_a, sym_b],
+            start_ts,
+            end_ts,
+            FillPolicy::ForwardFill { max_gap_secs: 3600 },
+        );
+
+        let mut timestamps = Vec::with_capacity(frame.timestamps.len());
+        let (mut a, mut b) = (Vec::new(), Vec::new());
+        for (i, &ts) in frame.timestamps.iter().enumerate() {
+            if let (Some(x), Some(y)) = (frame.columns[0][i], frame.columns[1][i]) {
+                timestamps.push(ts);
+                a.push(x);
+                b.push(y);
+            }
+        }
+
+        let correlations = TechnicalIndicators::rolling_correlation(&a, &b, window);
+        let offset = timestamps.len() - correlations.len();
+        (timestamps.split_off(offset), correlations)
+    }
+
+    /// 두 심볼 종가 로그수익률의 피어슨 상관계수 (구간 전체)
+    ///
+    /// 두 심볼 모두 봉이 있는 분만 사용. 겹치는 분이 부족하거나 한쪽 수익률이 일정하면 None
+    pub fn return_correlation(
+        &self,
+        sym_a: &str,
+        sym_b: &str,
+        start_ts: u64,
+        end_ts: u64,
+    ) -> Option<f64> {
+        let frame = self.query_aligned(&[sym_a, sym_b], start_ts, end_ts, FillPolicy::Drop);
+        let log_returns = |column: &[Option<f64>]| -> Vec<f64> {
+            column
+                .windows(2)
+                .map(|w| (w[1].unwrap_or_default() / w[0].unwrap_or_default()).ln())
+                .collect()
+        };
+        let (a, b) = (
+            log_returns(&frame.columns[0]),
+            log_returns(&frame.columns[1]),
+        );
+
+        TechnicalIndicators::rolling_correlation(&a, &b, a.len())
+            .first()
+            .copied()
+            .filter(|r| r.is_finite())
+    }
+
+    /// 심볼의 (symbol_id, 쓰기 세대), 미등록이면 None
+    ///
+    /// 세대는 블록이 추가·병합·삭제될 때마다 올라가므로, 같은 세대에서 계산한 쿼리 결과는
+    /// 다시 계산해도 같음 (HTTP 응답 캐시의 무효화 기준)
+    pub fn write_generation(&self, symbol: &str) -> Option<(u16, u64)> {
+        let sym_id = self.lookup(symbol)?.id;
+        let generation = self
+            .stats
+            .block_writes
+            .get(&sym_id)
+            .map_or(0, |writes| *writes);
+        Some((sym_id, generation))
+    }
+
+    /// 심볼 이름이나 별칭이 등록되어 있는지
+    pub fn has_symbol(&self, symbol: &str) -> bool {
+        self.lookup(symbol).is_some()
+    }
+
+    /// Get all available symbols
+    pub fn get_symbols(&self) -> Vec<String> {
+        self.symbols
+            .iter()
+            .map(|entry| entry.key().clone())
+            .collect()
+    }
+
+    /// 리얼타임 스트리밍 (실시간 틱 집계로 완성되는 1분봉 구독)
+    ///
+    /// 스토어가 닫히면 진행 중인 봉까지 받은 뒤 끊김 (`finalize_realtime`로 미리 마감 가능).
+    /// 수신자를 버리면 다음 봉 때 구독 해제되고,
+    /// 채널이 가득 차면 그 봉은 건너뛰고 `stats().subscribers`에 집계
+    pub fn stream_realtime(&self, symbol: &str) -> Result<Receiver<OHLCV>> {
+        let (tx, rx) = bounded(self.config.subscriber_capacity);
+        let sym_id = self.get_or_create_symbol(symbol)?;
+        let subscriber = self.subscribers.register(Some(symbol), tx);
+        self.subscribers
+            .by_symbol
+            .entry(sym_id)
+            .or_default()
+            .push(subscriber);
+        Ok(rx)
+    }
+
+    /// 모든 심볼의 실시간 1분봉 구독 (심볼 이름과 함께)
+    pub fn subscribe_all(&self) -> Receiver<(String, OHLCV)> {
+        let (tx, rx) = bounded(self.config.subscriber_capacity);
+        let subscriber = self.subscribers.register(None, tx);
+        self.subscribers.all.lock().push(subscriber);
+        rx
+    }
+}
+
+/// 시간순 CSV 라인을 날짜 키(`CsvFormat::day_key`)가 바뀔 때마다 끊어 하루치씩 반환
+///
+/// 각 라인은 (1부터 시작하는 라인 번호, 내용)
+struct DayChunks<'f, I> {
+    lines: I,
+    format: &'f CsvFormat,
+    next_line_no: usize,
+    pending: Option<(usize, String)>,
+}
+
+impl<'f, I> DayChunks<'f, I> {
+    fn new(lines: I, format: &'f CsvFormat, first_line_no: usize) -> Self {
+        Self {
+            lines,
+            format,
+            next_line_no: first_line_no,
+            pending: None,
+        }
+    }
+}
+
+impl<I: Iterator<Item = std::io::Result<String>>> Iterator for DayChunks<'_, I> {
+    type Item = std::io::Result<Vec<(usize, String)>>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let mut chunk: Vec<(usize, String)> = self.pending.take().into_iter().collect();
+
+        loop {
+            match self.lines.next() {
+                None => return (!chunk.is_empty()).then_some(Ok(chunk)),
+                Some(Err(e)) => return Some(Err(e)),
+                Some(Ok(line)) => {
+                    let line_no = self.next_line_no;
+                    self.next_line_no += 1;
+
+                    if let Some((_, first)) = chunk.first()
+                        && self.format.day_key(first) != self.format.day_key(&line)
+                    {
+                        self.pending = Some((line_no, line));
+                        return Some(Ok(chunk));
+                    }
+                    chunk.push((line_no, line));
+                }
+            }
+        }
+    }
+}
+
+/// 변경된 블록을 블록 파일에 쓰고 WAL을 비움 (`flush_now`와 주기적 플러시 스레드가 공유)
+#[cfg(feature = "persistence")]
+struct BlockFlusher {
+    file: PersistentStore,
+    wal: Arc<Wal>,
+    blocks: Arc<BlockMap>,
+    symbols: Arc<DashMap<String, Symbol>>,
+    dirty: Arc<DirtySet>,
+    gate: Arc<RwLock<()>>,
+    compress: CompressQueue,
+    /// 이름이 바뀐 심볼의 옛 이름 삭제 표시 (새 이름 레코드 뒤에 기록)
+    retired: Mutex<Vec<BlockRecord>>,
+}
+
+#[cfg(feature = "persistence")]
+impl BlockFlusher {
+    fn flush(&self) -> Result<usize> {
+        // 새 쓰기를 막아 WAL을 비울 때 블록 파일에 없는 엔트리가 남지 않게 함
+        let _gate = self.gate.write();
+        self.compress.barrier();
+
+        let mut dirty: Vec<(u16, u32)> = self.dirty.lock().drain().collect();
+        let retired = std::mem::take(&mut *self.retired.lock());
+        if dirty.is_empty() && retired.is_empty() {
+            return Ok(0);
+        }
+        dirty.sort_unstable();
+        let symbols: HashMap<u16, Symbol> = self
+            .symbols
+            .iter()
+            .map(|entry| (entry.id, entry.value().clone()))
+            .collect();
+
+        // 사라진 블록(보존 기간 만료)은 삭제 표시로 기록
+        let mut records: Vec<BlockRecord> = dirty
+            .iter()
+            .filter_map(|&(sym_id, date)| {
+                let symbol = symbols.get(&sym_id)?;
+                let block = self
+                    .blocks
+                    .get(&sym_id)
+                    .and_then(|blocks| blocks.get(&date).map(|block| block.clone()));
+                Some(BlockRecord {
+                    symbol: symbol.name.clone(),
+                    granularity: symbol.granularity,
+                    scale: symbol.scale,
+                    date,
+                    codec: block
+                        .as_ref()
+                        .map_or_else(Default::default, |b| b.layout.codec),
+                    checksum: block.as_ref().map_or(0, |b| b.checksum),
+                    summary: block.as_ref().and_then(BlockSummary::of),
+                    data: block.map_or_else(Vec::new, |b| b.data.to_vec()),
+                })
+            })
+            .collect();
+        let retired_count = retired.len();
+        records.extend(retired);
+
+        let written = self
+            .file
+            .append(&records)
+            .and_then(|()| self.wal.truncate());
+        if let Err(e) = written {
+            self.dirty.lock().extend(dirty);
+            let start = records.len() - retired_count;
+            self.retired.lock().extend(records.drain(start..));
+            return Err(e);
+        }
+        Ok(records.len())
+    }
+}
+
+impl Drop for FxStore {
+    /// 진행 중인 실시간 봉을 마감하고, 플러시 스레드를 멈춘 뒤 남은 변경을 블록 파일에 기록,
+    /// 마지막으로 압축 워커를 종료해 합류
+    fn drop(&mut self) {
+        self.finalize_realtime();
+        if let Some((stop, handle)) = self.flush_thread.take() {
+            drop(stop);
+            handle.join().ok();
+        }
+        #[cfg(feature = "persistence")]
+        if let Some(flusher) = &self.flusher {
+            flusher.flush().ok();
+        }
+        self.compress.stop();
+        for handle in self.compress_handles.drain(..) {
+            handle.join().ok();
+        }
+    }
+}
+
+/// 백그라운드 압축 워커
+fn compress_worker(
+    rx: Receiver<CompressJob>,
+    blocks: &BlockMap,
+    last_prices: &LastPriceMap,
+    tick_blocks: &TickBlockMap,
+    stats: &StoreStats,
+) {
+    while let Ok(job) = rx.recv() {
+        match job {
+            CompressJob::Batch {
+                date,
+                symbol_id,
+                layout,
+                records,
+            } => store_batch(
+                blocks,
+                last_prices,
+                stats,
+                layout,
+                date,
+                symbol_id,
+                &records,
+            ),
+            CompressJob::Ticks {
+                date,
+                symbol_id,
+                ticks,
+            } => store_ticks(tick_blocks, date, symbol_id, &ticks),
+            CompressJob::Flush(done) => {
+                done.send(()).ok();
+            }
+            CompressJob::Stop => break,
+        }
+    }
+}
+
+/// 일일 배치를 블록으로 저장 (기존 블록이 있으면 병합)
+fn store_batch(
+    blocks: &BlockMap,
+    last_prices: &LastPriceMap,
+    stats: &StoreStats,
+    layout: BlockLayout,
+    date: u32,
+    symbol_id: u16,
+    records: &[OHLCV],
+) {
+    if records.is_empty() {
+        return;
+    }
+
+    if !blocks.contains_key(&symbol_id) {
+        blocks
+            .entry(symbol_id)
+            .or_insert_with(|| DashMap::with_hasher(RandomState::new()));
+    }
+    let Some(symbol_blocks) = blocks.get(&symbol_id) else {
+        return;
+    };
+
+    // entry 가드가 같은 날짜의 동시 병합을 직렬화
+    let (old_bytes, new_bytes) = match symbol_blocks.entry(date) {
+        Entry::Occupied(mut entry) => {
+            // 손상된 블록은 읽을 수 없으므로 새 레코드로 교체
+            let block = entry.get().try_merge(records).unwrap_or_else(|e| {
+                tracing::warn!(error = %e, "replacing corrupt block");
+                CompressedBlock::new(date, symbol_id, records, entry.get().layout)
+            });
+            let sizes = (entry.get().data.len() as u64, block.data.len() as u64);
+            entry.insert(block);
+            sizes
+        }
+        Entry::Vacant(entry) => {
+            let block = CompressedBlock::new(date, symbol_id, records, layout);
+            let size = block.data.len() as u64;
+            entry.insert(block);
+            (0, size)
+        }
+    };
+    drop(symbol_blocks);
+
+    stats
+        .total_records
+        .fetch_add(records.len() as u64, Ordering::Relaxed);
+    stats
+        .compressed_bytes
+        .fetch_add(new_bytes, Ordering::Relaxed);
+    stats
+        .compressed_bytes
+        .fetch_sub(old_bytes, Ordering::Relaxed);
+
+    stats.bump_generation(symbol_id);
+
+    if let Some(newest) = records.iter().max_by_key(|rec| rec.ts) {
+        update_last_price(last_prices, newest);
+    }
+}
+
+/// 더 최신 바일 때만 최신가 캐시 갱신
+fn update_last_price(last_prices: &LastPriceMap, rec: &OHLCV) {
+    let mut entry = last_prices.entry(rec.symbol_id).or_insert(*rec);
+    if rec.ts > entry.ts {
+        *entry = *rec;
+    }
+}
+
+/// 실시간 봉 구독자 레지스트리
+#[derive(Default)]
+struct Subscribers {
+    next_id: AtomicU64,
+    by_symbol: DashMap<u16, Vec<Subscriber<OHLCV>>, RandomState>,
+    all: Mutex<Vec<Subscriber<(String, OHLCV)>>>,
+}
+
+impl Subscribers {
+    fn register<T>(&self, symbol: Option<&str>, tx: Sender<T>) -> Subscriber<T> {
+        Subscriber {
+            id: self.next_id.fetch_add(1, Ordering::Relaxed),
+            symbol: symbol.map(str::to_string),
+            tx,
+            dropped: 0,
+        }
+    }
+
+    /// 심볼 구독자와 전체 구독자에게 전달 (끊긴 구독자는 제거)
+    fn publish(&self, symbol: &str, symbol_id: u16, bars: &[OHLCV], stats: &StoreStats) {
+        if let Some(mut subscribers) = self.by_symbol.get_mut(&symbol_id) {
+            for bar in bars {
+                subscribers.retain_mut(|sub| sub.offer(*bar, stats));
+            }
+        }
+        let mut all = self.all.lock();
+        for bar in bars {
+            all.retain_mut(|sub| sub.offer((symbol.to_string(), *bar), stats));
+        }
+    }
+
+    fn snapshot(&self) -> Vec<SubscriberStats> {
+        let mut result: Vec<SubscriberStats> = self
+            .by_symbol
+            .iter()
+            .flat_map(|entry| {
+                entry
+                    .value()
+                    .iter()
+                    .map(Subscriber::stats)
+                    .collect::<Vec<_>>()
+            })
+            .chain(self.all.lock().iter().map(Subscriber::stats))
+            .collect();
+        result.sort_unstable_by_key(|s| s.id);
+        result
+    }
+}
+
+struct Subscriber<T> {
+    id: u64,
+    symbol: Option<String>,
+    tx: Sender<T>,
+    dropped: u64,
+}
+
+impl<T> Subscriber<T> {
+    /// 막히지 않게 전달 시도 (가득 차면 버리고 집계), 수신자가 사라졌으면 false
+    fn offer(&mut self, value: T, stats: &StoreStats) -> bool {
+        match self.tx.try_send(value) {
+            Ok(()) => true,
+            Err(TrySendError::Full(_)) => {
+                self.dropped += 1;
+                stats.dropped_bars.fetch_add(1, Ordering::Relaxed);
+                true
+            }
+            Err(TrySendError::Disconnected(_)) => false,
+        }
+    }
+
+    fn stats(&self) -> SubscriberStats {
+        SubscriberStats {
+            id: self.id,
+            symbol: self.symbol.clone(),
+            dropped: self.dropped,
+        }
+    }
+}
+
+/// 실시간 집계 스레드가 봉을 내보낼 곳
+struct TickPipeline {
+    symbol: String,
+    symbol_id: u16,
+    blocks: Arc<BlockMap>,
+    last_prices: Arc<LastPriceMap>,
+    stats: Arc<StoreStats>,
+    subscribers: Arc<Subscribers>,
+    dirty: Arc<DirtySet>,
+    layout: BlockLayout,
+    /// 완성된 봉을 기록할 WAL (`open`/`recover`로 연 경우에만)
+    #[cfg(feature = "persistence")]
+    wal: Option<Arc<Wal>>,
+    /// WAL 레코드에 함께 기록하는 가격 배율
+    #[cfg(feature = "persistence")]
+    scale: u32,
+    gate: Arc<RwLock<()>>,
+}
+
+impl TickPipeline {
+    /// WAL에 기록하고 블록에 저장한 뒤 구독자에게 전달 (끊긴 구독자는 제거)
+    fn publish(&self, bars: Vec<OHLCV>) {
+        if bars.is_empty() {
+            return;
+        }
+
+        // 블록 파일에 쓰이기 전에 WAL이 비워지지 않도록 기록부터 dirty 표시까지 잠금 유지
+        let _gate = self.gate.read();
+        #[cfg(feature = "persistence")]
+        if let Some(wal) = &self.wal {
+            let entry = WalEntry {
+                symbol: self.symbol.clone(),
+                granularity: self.layout.granularity,
+                scale: self.scale,
+                records: bars.clone(),
+            };
+            if let Err(e) = wal.append(&entry) {
+                tracing::error!(symbol = %self.symbol, error = %e, "failed to log live bars");
+            }
+        }
+
+        let mut daily: BTreeMap<u32, Vec<OHLCV>> = BTreeMap::new();
+        for bar in &bars {
+            daily.entry(ts_to_date(bar.ts)).or_default().push(*bar);
+        }
+        for (date, records) in daily {
+            store_batch(
+                &self.blocks,
+                &self.last_prices,
+                &self.stats,
+                self.layout,
+                date,
+                self.symbol_id,
+                &records,
+            );
+            self.dirty.lock().insert((self.symbol_id, date));
+        }
+        drop(_gate);
+
+        self.subscribers
+            .publish(&self.symbol, self.symbol_id, &bars, &self.stats);
+    }
+}
+
+/// 실시간 틱 데이터를 1분 바로 집계 (입력 채널이 모두 닫히면 남은 봉을 내보내고 종료)
+///
+/// idle_timeout 동안 틱이 없으면 진행 중인 봉을 완성
+fn aggregate_ticks_to_minutes(
+    ticks: Receiver<Tick>,
+    finalize: Receiver<Sender<()>>,
+    pipeline: &TickPipeline,
+    tolerance_nanos: u64,
+    idle_timeout: Duration,
+) {
+    let mut aggregator = TickAggregator::new(tolerance_nanos);
+    let accept = |aggregator: &mut TickAggregator, mut tick: Tick| {
+        tick.symbol_id = pipeline.symbol_id;
+        if aggregator.is_late(&tick) {
+            pipeline.stats.late_ticks.fetch_add(1, Ordering::Relaxed);
+            return Vec::new();
+        }
+        aggregator.push(&tick)
+    };
+    loop {
+        let bars = select! {
+            recv(ticks) -> tick => match tick {
+                Ok(tick) => accept(&mut aggregator, tick),
+                Err(_) => {
+                    pipeline.publish(aggregator.flush());
+                    return;
+                }
+            },
+            // 진행 중인 분 마감 요청 (끊겼으면 스토어가 닫힌 것).
+            // select!는 준비된 채널 중 아무거나 고르므로 요청 전에 보낸 틱부터 반영
+            recv(finalize) -> done => {
+                let mut bars = Vec::new();
+                for tick in ticks.try_iter() {
+                    bars.extend(accept(&mut aggregator, tick));
+                }
+                bars.extend(aggregator.flush());
+                pipeline.publish(bars);
+                match done {
+                    Ok(done) => {
+                        done.send(()).ok();
+                        continue;
+                    }
+                    Err(_) => return,
+                }
+            },
+            default(idle_timeout) => aggregator.flush(),
+        };
+        pipeline.publish(bars);
+    }
+}
+
+/// 일일 틱 배치를 틱 블록으로 저장 (기존 블록이 있으면 병합)
+fn store_ticks(tick_blocks: &TickBlockMap, date: u32, symbol_id: u16, ticks: &[Tick]) {
+    if ticks.is_empty() {
+        return;
+    }
+    let symbol_blocks = tick_blocks
+        .entry(symbol_id)
+        .or_insert_with(|| DashMap::with_hasher(RandomState::new()));
+    match symbol_blocks.entry(date) {
+        Entry::Occupied(mut entry) => {
+            let block = entry.get().merge(ticks);
+            entry.insert(block);
+        }
+        Entry::Vacant(entry) => {
+            entry.insert(TickBlock::new(date, symbol_id, ticks));
+        }
+    }
+}
+
+/// (파일 크기, 수정 시각 epoch nanos, 모르면 0)
+fn file_stamp(path: &str) -> Result<(u64, u64)> {
+    let meta = std::fs::metadata(path)?;
+    let mtime = meta
+        .modified()
+        .ok()
+        .and_then(|t| t.duration_since(std::time::UNIX_EPOCH).ok())
+        .map_or(0, |d| d.as_nanos() as u64);
+    Ok((meta.len(), mtime))
+}
+
+/// 파일 내용의 XXH64 (매핑해서 읽음)
+#[cfg(feature = "persistence")]
+fn file_hash(path: &str) -> Result<u64> {
+    let file = std::fs::File::open(path)?;
+    if file.metadata()?.len() == 0 {
+        return Ok(xxh64(&[], 0));
+    }
+    // SAFETY: 읽기 전용 매핑이고 해시를 계산하는 동안만 유지
+    let mmap = unsafe { memmap2::Mmap::map(&file)? };
+    Ok(xxh64(&mmap, 0))
+}
+
+/// 파일 내용의 XXH64 (memmap2 없이 빌드하면 통째로 읽음)
+#[cfg(not(feature = "persistence"))]
+fn file_hash(path: &str) -> Result<u64> {
+    Ok(xxh64(&std::fs::read(path)?, 0))
+}
+
+/// CSV 라인 스트림 (확장자와 무관하게 매직 바이트로 gzip 판별, 연속 멤버도 이어서 읽음)
+fn open_lines(path: &str) -> std::io::Result<std::io::Lines<Box<dyn std::io::BufRead>>> {
+    use std::io::{BufRead, BufReader};
+
+    const GZIP_MAGIC: [u8; 2] = [0x1f, 0x8b];
+    let mut file = BufReader::new(std::fs::File::open(path)?);
+    let reader: Box<dyn BufRead> = if file.fill_buf()?.starts_with(&GZIP_MAGIC) {
+        Box::new(BufReader::new(flate2::bufread::MultiGzDecoder::new(file)))
+    } else {
+        Box::new(file)
+    };
+    Ok(reader.lines())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const MINUTE: u64 = 60_000_000_000;
+    // 2024-01-02 00:00:00 UTC
+    const DAY_START: u64 = 1_704_153_600_000_000_000;
+
+    fn temp_path(name: &str) -> std::path::PathBuf {
+        std::env::temp_dir().join(format!("fx-store-{}-{}", std::process::id(), name))
+    }
+
+    fn bar(ts: u64, close: u32) -> OHLCV {
+        OHLCV {
+            ts,
+            open: close,
+            high: close,
+            low: close,
+            close,
+            volume: 1,
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn latest_tracks_inserts() {
+        let store = FxStore::new();
+        assert!(store.latest("EURUSD").is_none());
+
+        store
+            .insert("EURUSD", bar(DAY_START + MINUTE, 110_000))
+            .unwrap();
+        store.insert("EURUSD", bar(DAY_START, 100_000)).unwrap();
+
+        // 과거 바 삽입은 최신가를 되돌리지 않음
+        let latest = store.latest("EURUSD").unwrap();
+        assert_eq!({ latest.ts }, DAY_START + MINUTE);
+        assert_eq!({ latest.close }, 110_000);
+        assert_eq!(store.stats().last_price_hits, 1);
+    }
+
+    #[test]
+    fn latest_falls_back_to_block_scan() {
+        let store = FxStore::new();
+        store
+            .insert("EURUSD", bar(DAY_START + 5 * MINUTE, 120_000))
+            .unwrap();
+        store.last_prices.clear();
+
+        let latest = store.latest("EURUSD").unwrap();
+        assert_eq!({ latest.close }, 120_000);
+        let stats = store.stats();
+        assert_eq!(stats.last_price_misses, 1);
+        assert_eq!(stats.last_price_entries, 1);
+    }
+
+    #[test]
+    fn latest_with_concurrent_inserts() {
+        let store = Arc::new(FxStore::new());
+        store.insert("EURUSD", bar(DAY_START, 100_000)).unwrap();
+
+        let writer = {
+            let store = Arc::clone(&store);
+            std::thread::spawn(move || {
+                for i in 1..200u64 {
+                    store
+                        .insert("EURUSD", bar(DAY_START + i * MINUTE, 100_000 + i as u32))
+                        .unwrap();
+                }
+            })
+        };
+
+        let readers: Vec<_> = (0..4)
+            .map(|_| {
+                let store = Arc::clone(&store);
+                std::thread::spawn(move || {
+                    let mut last_ts = 0;
+                    for _ in 0..1000 {
+                        let ts = store.latest("EURUSD").unwrap().ts;
+                        assert!(ts >= last_ts, "latest went backwards");
+                        last_ts = ts;
+                    }
+                })
+            })
+            .collect();
+
+        writer.join().unwrap();
+        for reader in readers {
+            reader.join().unwrap();
+        }
+
+        let latest = store.latest("EURUSD").unwrap();
+        assert_eq!({ latest.ts }, DAY_START + 199 * MINUTE);
+        assert_eq!({ latest.close }, 100_199);
+    }
+
+    #[test]
+    fn drop_joins_compress_workers() {
+        let store = FxStore::with_config(StoreConfig {
+            compress_workers: 3,
+            ..StoreConfig::default()
+        });
+        store.insert("EURUSD", bar(DAY_START, 100_000)).unwrap();
+        let blocks = Arc::clone(&store.blocks);
+        drop(store);
+        // 워커가 들고 있던 블록 맵 참조까지 해제됨
+        assert_eq!(Arc::strong_count(&blocks), 1);
+    }
+
+    #[cfg(feature = "persistence")]
+    #[test]
+    fn recover_replays_wal_after_crash() {
+        let wal_path = temp_path("crash.wal");
+        let csv_path = temp_path("crash.csv");
+        std::fs::remove_file(&wal_path).ok();
+        std::fs::write(
+            &csv_path,
+            "header\n\
+             20240102 000000,1.10000,1.10010,1.09990,1.10005,10\n\
+             20240102 000100,1.10005,1.10020,1.10000,1.10015,20\n\
+             20240103 000000,1.10100,1.10110,1.10090,1.10105,30\n",
+        )
+        .unwrap();
+
+        {
+            let store = FxStore::recover(&wal_path).unwrap();
+            store
+                .import_csv(csv_path.to_str().unwrap(), "EURUSD")
+                .unwrap();
+            store.insert("GBPUSD", bar(DAY_START, 127_000)).unwrap();
+            // flush/checkpoint 없이 drop = 크래시
+        }
+
+        let store = FxStore::recover(&wal_path).unwrap();
+        let eurusd: Vec<OHLCV> = store
+            .query_range("EURUSD", DAY_START, DAY_START + 2 * 1440 * MINUTE)
+            .collect();
+        assert_eq!(eurusd.len(), 3);
+        assert_eq!(eurusd.iter().map(|rec| rec.volume as u64).sum::<u64>(), 60);
+        assert_eq!({ store.latest("GBPUSD").unwrap().close }, 127_000);
+
+        // 블록 파일이 없으니 체크포인트는 거부하고 WAL을 남김
+        assert!(matches!(
+            store.checkpoint(),
+            Err(FxStoreError::NotPersistent)
+        ));
+        drop(store);
+        let store = FxStore::recover(&wal_path).unwrap();
+        assert_eq!(
+            store
+                .query_range("EURUSD", DAY_START, DAY_START + 2 * 1440 * MINUTE)
+                .count(),
+            3
+        );
+
+        std::fs::remove_file(&wal_path).ok();
+        std::fs::remove_file(&csv_path).ok();
+    }
+
+    #[cfg(feature = "persistence")]
+    #[test]
+    fn insert_batch_logs_once_and_merges_per_day() {
+        let wal_path = temp_path("batch.wal");
+        std::fs::remove_file(&wal_path).ok();
+        let day = 1440 * MINUTE;
+        let bars = [
+            bar(DAY_START, 100_000),
+            bar(DAY_START + day, 100_200),
+            bar(DAY_START + MINUTE, 100_100),
+        ];
+        {
+            let store = FxStore::recover(&wal_path).unwrap();
+            store.insert_batch("EURUSD", &bars).unwrap();
+            assert_eq!(store.query_range("EURUSD", 0, u64::MAX).count(), 3);
+            assert_eq!({ store.latest("EURUSD").unwrap().close }, 100_200);
+
+            // 하나라도 범위를 벗어나면 아무것도 넣지 않음
+            let bad = [bar(DAY_START + 2 * MINUTE, 1), bar(0, 1)];
+            assert!(matches!(
+                store.insert_batch("EURUSD", &bad),
+                Err(FxStoreError::TimestampOutOfRange(0))
+            ));
+            assert_eq!(store.query_range("EURUSD", 0, u64::MAX).count(), 3);
+        }
+
+        let entries = Wal::replay(&wal_path).unwrap();
+        assert_eq!(entries.len(), 1);
+        assert_eq!(entries[0].records.len(), 3);
+        let store = FxStore::recover(&wal_path).unwrap();
+        assert_eq!(store.query_range("EURUSD", 0, u64::MAX).count(), 3);
+        std::fs::remove_file(&wal_path).ok();
+    }
+
+    #[test]
+    fn asof_walks_back_across_days() {
+        let store = FxStore::new();
+        const DAY: u64 = 1440 * MINUTE;
+        store
+            .insert("EURUSD", bar(DAY_START + 10 * MINUTE, 100_010))
+            .unwrap();
+        store
+            .insert("EURUSD", bar(DAY_START + 20 * MINUTE, 100_020))
+            .unwrap();
+        store
+            .insert("EURUSD", bar(DAY_START + 3 * DAY, 100_300))
+            .unwrap();
+
+        assert!(store.query_asof("EURUSD", DAY_START).is_none());
+        assert!(store.query_asof("GBPUSD", DAY_START + DAY).is_none());
+        assert_eq!(
+            {
+                store
+                    .query_asof("EURUSD", DAY_START + 10 * MINUTE)
+                    .unwrap()
+                    .close
+            },
+            100_010
+        );
+        assert_eq!(
+            {
+                store
+                    .query_asof("EURUSD", DAY_START + 15 * MINUTE)
+                    .unwrap()
+                    .close
+            },
+            100_010
+        );
+        // 중간 날짜는 블록이 없으므로 첫날 마지막 바
+        assert_eq!(
+            {
+                store
+                    .query_asof("EURUSD", DAY_START + 2 * DAY)
+                    .unwrap()
+                    .close
+            },
+            100_020
+        );
+        // 셋째 날 블록은 있지만 해당 시점 이전 슬롯은 비어 있음
+        assert_eq!(
+            {
+                store
+                    .query_asof("EURUSD", DAY_START + 3 * DAY - MINUTE)
+                    .unwrap()
+                    .close
+            },
+            100_020
+        );
+
+        let many = store.query_asof_many(
+            "EURUSD",
+            &[DAY_START + 4 * DAY, DAY_START, DAY_START + 30 * MINUTE],
+        );
+        let closes: Vec<Option<u32>> = many.iter().map(|rec| rec.map(|r| r.close)).collect();
+        assert_eq!(closes, vec![Some(100_300), None, Some(100_020)]);
+    }
+
+    #[test]
+    fn import_validation_rejects_inverted_bars() {
+        let csv_path = temp_path("invalid.csv");
+        std::fs::write(
+            &csv_path,
+            "header\n\
+             20240102 000000,1.10000,1.10010,1.09990,1.10005,10\n\
+             20240102 000100,1.10005,1.09990,1.10020,1.10015,20\n",
+        )
+        .unwrap();
+        let path = csv_path.to_str().unwrap();
+
+        let store = FxStore::with_config(StoreConfig {
+            validate: true,
+            on_invalid: OnInvalid::Skip,
+            ..Default::default()
+        });
+        let report = store.import_csv(path, "EURUSD").unwrap();
+        assert_eq!((report.imported, report.rejected), (1, 1));
+
+        let strict = FxStore::with_config(StoreConfig {
+            validate: true,
+            on_invalid: OnInvalid::Error,
+            ..Default::default()
+        });
+        assert!(matches!(
+            strict.import_csv(path, "EURUSD"),
+            Err(FxStoreError::Parse { line: 3, .. })
+        ));
+
+        // 검증을 끄면 기존처럼 그대로 저장
+        let report = FxStore::new().import_csv(path, "EURUSD").unwrap();
+        assert_eq!((report.imported, report.rejected), (2, 0));
+
+        std::fs::remove_file(&csv_path).ok();
+    }
+
+    #[test]
+    fn query_range_multi_keeps_input_order() {
+        let store = FxStore::new();
+        store.insert("EURUSD", bar(DAY_START, 100_000)).unwrap();
+        store.insert("XAUUSD", bar(DAY_START, 200_000)).unwrap();
+        store
+            .insert("XAUUSD", bar(DAY_START + MINUTE, 200_100))
+            .unwrap();
+
+        let results =
+            store.query_range_multi(&["XAUUSD", "NOPE", "EURUSD"], DAY_START, DAY_START + MINUTE);
+        let counts: Vec<Option<usize>> = results.iter().map(|r| r.as_ref().map(Vec::len)).collect();
+        assert_eq!(counts, vec![Some(2), None, Some(1)]);
+    }
+
+    #[test]
+    fn import_converts_histdata_est_to_utc() {
+        let csv_path = temp_path("est.csv");
+        std::fs::write(
+            &csv_path,
+            "header\n\
+             20240102 170000,1.10000,1.10010,1.09990,1.10005,10\n\
+             20240102 200000,1.10005,1.10020,1.10000,1.10015,20\n",
+        )
+        .unwrap();
+
+        let store = FxStore::new();
+        let options = ImportOptions {
+            source_tz: Some(crate::types::histdata_est()),
+            ..Default::default()
+        };
+        store
+            .import_csv_with(csv_path.to_str().unwrap(), "EURUSD", &options)
+            .unwrap();
+        store.flush();
+
+        // 17:00 EST = 22:00 UTC, 20:00 EST = 다음날 01:00 UTC
+        let records: Vec<OHLCV> = store
+            .query_range("EURUSD", DAY_START, DAY_START + 2 * 1440 * MINUTE)
+            .collect();
+        let ts: Vec<u64> = records.iter().map(|rec| rec.ts).collect();
+        assert_eq!(
+            ts,
+            vec![DAY_START + 22 * 60 * MINUTE, DAY_START + 25 * 60 * MINUTE]
+        );
+
+        std::fs::remove_file(&csv_path).ok();
+    }
+
+    #[test]
+    fn streaming_import_buffers_one_day_at_a_time() {
+        use std::io::Write;
+
+        const DAYS: u64 = 20;
+        let csv_path = temp_path("stream.csv");
+        {
+            let mut file = std::io::BufWriter::new(std::fs::File::create(&csv_path).unwrap());
+            writeln!(file, "header").unwrap();
+            for minute in 0..DAYS * 1440 {
+                let dt =
+                    chrono::DateTime::from_timestamp_nanos((DAY_START + minute * MINUTE) as i64);
+                writeln!(
+                    file,
+                    "{},1.10000,1.10010,1.09990,1.10005,{}",
+                    dt.format("%Y%m%d %H%M%S"),
+                    minute % 100
+                )
+                .unwrap();
+            }
+        }
+
+        // 라인 버퍼는 하루(1440줄)를 넘지 않음
+        let file = std::fs::File::open(&csv_path).unwrap();
+        let lines = std::io::BufRead::lines(std::io::BufReader::new(file)).skip(1);
+        let chunk_sizes: Vec<usize> = DayChunks::new(lines, &CsvFormat::default(), 2)
+            .map(|c| c.unwrap().len())
+            .collect();
+        assert_eq!(chunk_sizes, vec![1440; DAYS as usize]);
+
+        let store = FxStore::new();
+        let report = store
+            .import_csv(csv_path.to_str().unwrap(), "EURUSD")
+            .unwrap();
+        store.flush();
+        assert_eq!(
+            (report.imported, report.days),
+            ((DAYS * 1440) as usize, DAYS as usize)
+        );
+
+        let records: Vec<OHLCV> = store
+            .query_range("EURUSD", DAY_START, DAY_START + DAYS * 1440 * MINUTE - 1)
+            .collect();
+        assert_eq!(records.len(), (DAYS * 1440) as usize);
+        assert!(records.windows(2).all(|w| w[0].ts + MINUTE == w[1].ts));
+
+        std::fs::remove_file(&csv_path).ok();
+    }
+
+    #[test]
+    fn import_merges_rows_in_the_same_minute() {
+        let csv_path = temp_path("seconds.csv");
+        std::fs::write(
+            &csv_path,
+            "20240102 000000,1.10000,1.10010,1.09990,1.10005,10\n\
+             20240102 000030,1.10005,1.10030,1.10000,1.10020,5\n\
+             20240102 000100,1.10020,1.10020,1.10010,1.10015,20\n\
+             20240102 000100,1.10020,1.10020,1.10010,1.10015,20\n",
+        )
+        .unwrap();
+
+        let store = FxStore::new();
+        let report = store
+            .import_csv(csv_path.to_str().unwrap(), "EURUSD")
+            .unwrap();
+        assert_eq!((report.imported, report.merged), (4, 2));
+
+        store.flush();
+        let bars: Vec<OHLCV> = store
+            .query_range("EURUSD", DAY_START, DAY_START + 1440 * MINUTE - 1)
+            .collect();
+        assert_eq!(bars.len(), 2);
+        assert_eq!(
+            ({ bars[0].open }, { bars[0].high }, { bars[0].close }, {
+                bars[0].volume
+            }),
+            (110_000, 110_030, 110_020, 15)
+        );
+        assert_eq!({ bars[1].volume }, 40);
+
+        std::fs::remove_file(&csv_path).ok();
+    }
+
+    #[test]
+    fn import_reports_bad_lines() {
+        let csv_path = temp_path("truncated.csv");
+        std::fs::write(
+            &csv_path,
+            "header\n\
+             20240102 000000,1.10000,1.10010,1.09990,1.10005,10\n\
+             2024\n\
+             20240102 000200,1.10005,1.10020,1.10000,1.10015,20\n",
+        )
+        .unwrap();
+
+        let store = FxStore::new();
+        let report = store
+            .import_csv(csv_path.to_str().unwrap(), "EURUSD")
+            .unwrap();
+        assert_eq!((report.imported, report.skipped), (2, 1));
+        assert_eq!(report.errors.len(), 1);
+        assert_eq!(report.errors[0].0, 3);
+
+        store.flush();
+        assert_eq!(
+            store
+                .query_range("EURUSD", DAY_START, DAY_START + 1440 * MINUTE - 1)
+                .count(),
+            2
+        );
+
+        std::fs::remove_file(&csv_path).ok();
+    }
+
+    #[test]
+    fn import_reads_gzip_csv_transparently() {
+        use flate2::Compression;
+        use flate2::write::GzEncoder;
+        use std::fmt::Write as _;
+        use std::io::Write as _;
+
+        let mut csv = String::from("header\n");
+        for minute in 0..2 * 1440u64 {
+            let dt = chrono::DateTime::from_timestamp_nanos((DAY_START + minute * MINUTE) as i64);
+            let c = 10_000 + (minute * 37) % 211;
+            writeln!(
+                csv,
+                "{},1.{:05},1.{:05},1.{:05},1.{:05},{}",
+                dt.format("%Y%m%d %H%M%S"),
+                c,
+                c + 12,
+                c - 9,
+                c + 3,
+                minute % 97
+            )
+            .unwrap();
+        }
+        let dir = tempfile::tempdir().unwrap();
+        let plain_path = dir.path().join("eurusd.csv");
+        std::fs::write(&plain_path, &csv).unwrap();
+        let gz_path = dir.path().join("eurusd.csv.gz");
+        let mut encoder = GzEncoder::new(
+            std::fs::File::create(&gz_path).unwrap(),
+            Compression::default(),
+        );
+        encoder.write_all(csv.as_bytes()).unwrap();
+        encoder.finish().unwrap();
+
+        let store = FxStore::new();
+        let plain = store
+            .import_csv(plain_path.to_str().unwrap(), "EURUSD")
+            .unwrap();
+        let gz = store
+            .import_csv(gz_path.to_str().unwrap(), "GBPUSD")
+            .unwrap();
+        store.flush();
+        assert_eq!((gz.imported, gz.skipped, gz.days), (2880, 0, 2));
+        assert_eq!(plain.imported, gz.imported);
+
+        let end = DAY_START + 2 * 1440 * MINUTE - 1;
+        let plain: Vec<OHLCV> = store.query_range("EURUSD", DAY_START, end).collect();
+        let gz: Vec<OHLCV> = store.query_range("GBPUSD", DAY_START, end).collect();
+        assert_eq!(gz.len(), 2880);
+        assert!(plain.iter().zip(&gz).all(|(a, b)| {
+            (a.ts, a.open, a.high, a.low, a.close, a.volume)
+                == (b.ts, b.open, b.high, b.low, b.close, b.volume)
+        }));
+    }
+
+    #[test]
+    fn import_reads_spread_column() {
+        let csv_path = temp_path("spread.csv");
+        std::fs::write(
+            &csv_path,
+            "time,open,high,low,close,volume,spread\n\
+             2024-01-02T00:00:00Z,1.10000,1.10010,1.09990,1.10005,10,0.00012\n\
+             2024-01-02T00:01:00Z,1.10005,1.10020,1.10000,1.10015,20,\n",
+        )
+        .unwrap();
+
+        let store = FxStore::new();
+        let report = store
+            .import_csv(csv_path.to_str().unwrap(), "EURUSD")
+            .unwrap();
+        store.flush();
+        assert_eq!(report.imported, 2);
+
+        let records: Vec<OHLCV> = store
+            .query_range("EURUSD", DAY_START, DAY_START + MINUTE)
+            .collect();
+        assert_eq!(
+            records.iter().map(|r| r.spread).collect::<Vec<_>>(),
+            [12, 0]
+        );
+        assert!((records[0].spread_f64(PRICE_SCALE) - 0.00012).abs() < 1e-12);
+
+        std::fs::remove_file(&csv_path).ok();
+    }
+
+    #[test]
+    fn volume_profile_buckets_volume_by_typical_price() {
+        let store = FxStore::new();
+        for i in 0..30 {
+            let mut rec = bar(DAY_START + i * MINUTE, 110_000);
+            rec.volume = 10;
+            store.insert("EURUSD", rec).unwrap();
+        }
+        let profile = store.volume_profile("EURUSD", DAY_START, u64::MAX, 0.0005);
+        assert_eq!(profile, [(1.1, 300)]);
+
+        // (1.10120 + 1.10080 + 1.10100) / 3 = 1.10100 → [1.1010, 1.1015), 1.09990 → [1.0995, 1.1000)
+        let mut wide = bar(DAY_START + 40 * MINUTE, 110_100);
+        wide.high = 110_120;
+        wide.low = 110_080;
+        store.insert("EURUSD", wide).unwrap();
+        store
+            .insert("EURUSD", bar(DAY_START + 50 * MINUTE, 109_990))
+            .unwrap();
+        let profile = store.volume_profile("EURUSD", DAY_START, u64::MAX, 0.0005);
+        assert_eq!(profile, [(1.0995, 1), (1.1, 300), (1.101, 1)]);
+
+        assert!(
+            store
+                .volume_profile("EURUSD", DAY_START, u64::MAX, 0.0)
+                .is_empty()
+        );
+        assert!(
+            store
+                .volume_profile("EURUSD", DAY_START, u64::MAX, f64::NAN)
+                .is_empty()
+        );
+        assert!(
+            store
+                .volume_profile("GBPUSD", DAY_START, u64::MAX, 0.0005)
+                .is_empty()
+        );
+    }
+
+    #[test]
+    fn pages_and_adjacent_ranges_cover_a_day_exactly_once() {
+        let store = FxStore::new();
+        for i in 0..1440 {
+            store
+                .insert("EURUSD", bar(DAY_START + i * MINUTE, 100_000 + i as u32))
+                .unwrap();
+        }
+        // 다음 날 자정 봉은 하루 구간에 들어가지 않아야 함
+        store
+            .insert("EURUSD", bar(DAY_START + NANOS_PER_DAY, 1))
+            .unwrap();
+        let day = TimeRange::day(ts_to_date(DAY_START));
+        let key = |rec: OHLCV| (rec.ts, rec.close);
+        let expected: Vec<(u64, u32)> = (0..1440)
+            .map(|i| (DAY_START + i * MINUTE, 100_000 + i as u32))
+            .collect();
+
+        let mut paged = Vec::new();
+        let mut after = None;
+        loop {
+            let page = store.query_page("EURUSD", day, after, 100);
+            let Some(last) = page.last() else {
+                break;
+            };
+            after = Some(last.ts);
+            paged.extend(page.into_iter().map(key));
+        }
+        assert_eq!(paged, expected);
+
+        // 앞 구간의 end를 다음 구간의 start로 써도 경계 봉이 겹치지 않음
+        let hours: Vec<(u64, u32)> = (0..24)
+            .flat_map(|hour| {
+                let start = DAY_START + hour * 60 * MINUTE;
+                store.query("EURUSD", TimeRange::new(start, start + 60 * MINUTE))
+            })
+            .map(key)
+            .collect();
+        assert_eq!(hours, expected);
+        assert_eq!(store.count("EURUSD", day), 1440);
+        assert_eq!(
+            store.count("EURUSD", TimeRange::new(DAY_START, DAY_START)),
+            0
+        );
+
+        let hourly = store.query_resampled("EURUSD", day, 3600, Alignment::UTC);
+        assert_eq!(hourly.len(), 24);
+        assert!(hourly.iter().all(|bar| bar.volume == 60));
+    }
+
+    #[cfg(feature = "parquet")]
+    #[test]
+    fn parquet_export_round_trips_through_the_parquet_reader() {
+        use crate::parquet_format::bar_schema;
+        use arrow_array::cast::AsArray;
+        use arrow_array::types::{Float64Type, TimestampNanosecondType, UInt16Type, UInt32Type};
+        use parquet::arrow::arrow_reader::ParquetRecordBatchReaderBuilder;
+
+        type Row = (i64, [f64; 4], u32, String);
+        fn rows(batches: &[RecordBatch]) -> Vec<Row> {
+            let mut rows = Vec::new();
+            for batch in batches {
+                let ts = batch.column(0).as_primitive::<TimestampNanosecondType>();
+                let price = |i: usize| batch.column(i).as_primitive::<Float64Type>();
+                let volume = batch.column(5).as_primitive::<UInt32Type>();
+                let symbols = batch.column(6).as_dictionary::<UInt16Type>();
+                let names = symbols.values().as_string::<i32>();
+                for i in 0..batch.num_rows() {
+                    rows.push((
+                        ts.value(i),
+                        [1, 2, 3, 4].map(|col| price(col).value(i)),
+                        volume.value(i),
+                        names
+                            .value(usize::from(symbols.keys().value(i)))
+                            .to_string(),
+                    ));
+                }
+            }
+            rows
+        }
+
+        let store = FxStore::new();
+        let usdjpy = store
+            .register_symbol("USDJPY", Granularity::Minute)
+            .unwrap();
+        store.set_scale_if_empty("USDJPY", usdjpy.id, 1_000);
+        store.add_alias("YEN", "USDJPY").unwrap();
+        // 사흘에 걸친 봉 (블록 3개)
+        for minute in (0..3 * 1440).step_by(7) {
+            let mut rec = bar(DAY_START + minute * MINUTE, 150_000 + minute as u32);
+            rec.high += 25;
+            rec.low -= 25;
+            rec.volume = minute as u32;
+            store.insert("USDJPY", rec).unwrap();
+        }
+        store.flush();
+
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("usdjpy.parquet");
+        let (start, end) = (DAY_START + 100 * MINUTE, DAY_START + 3000 * MINUTE);
+        let written = store.export_parquet("YEN", start, end, &path).unwrap();
+        let expected: Vec<Row> = store
+            .query_range("USDJPY", start, end)
+            .map(|rec| {
+                let price = |raw: u32| f64::from(raw) / 1_000.0;
+                (
+                    rec.ts as i64,
+                    [
+                        price(rec.open),
+                        price(rec.high),
+                        price(rec.low),
+                        price(rec.close),
+                    ],
+                    rec.volume,
+                    "USDJPY".to_string(),
+                )
+            })
+            .collect();
+        // 100..=3000분 중 7의 배수, 세 블록에 걸침
+        assert_eq!(written, 414);
+        assert_eq!(expected.len(), written);
+
+        let file = std::fs::File::open(&path).unwrap();
+        let reader = ParquetRecordBatchReaderBuilder::try_new(file).unwrap();
+        assert_eq!(reader.schema().fields(), bar_schema().fields());
+        let batches: Vec<RecordBatch> = reader
+            .build()
+            .unwrap()
+            .collect::<std::result::Result<_, _>>()
+            .unwrap();
+        assert_eq!(rows(&batches), expected);
+        assert_eq!(expected[0].1[3], 150.105);
+
+        let batch = store.to_arrow("USDJPY", start, end).unwrap();
+        assert_eq!(batch.schema(), bar_schema());
+        assert_eq!(rows(&[batch]), expected);
+
+        assert!(matches!(
+            store.export_parquet("GBPUSD", start, end, dir.path().join("none.parquet")),
+            Err(FxStoreError::UnknownSymbol(_))
+        ));
+        assert!(matches!(
+            store.to_arrow("USDJPY", end, start),
+            Err(FxStoreError::Query(QueryError::InvertedRange { .. }))
+        ));
+    }
+
+    #[cfg(feature = "parquet")]
+    #[test]
+    fn parquet_import_maps_columns_and_reports_bad_rows() {
+        use crate::parquet_format::ColumnMapping;
+        use arrow_array::{
+            ArrayRef, Float64Array, Int64Array, StringArray, TimestampMillisecondArray,
+        };
+        use parquet::arrow::ArrowWriter;
+        use std::sync::Arc;
+
+        fn write(path: &std::path::Path, columns: Vec<(&str, ArrayRef)>) {
+            let batch = RecordBatch::try_from_iter(columns).unwrap();
+            let mut writer =
+                ArrowWriter::try_new(std::fs::File::create(path).unwrap(), batch.schema(), None)
+                    .unwrap();
+            writer.write(&batch).unwrap();
+            writer.close().unwrap();
+        }
+        let ms = |minute: u64| ((DAY_START + minute * MINUTE) / 1_000_000) as i64;
+
+        let dir = tempfile::tempdir().unwrap();
+        let epoch = dir.path().join("epoch.parquet");
+        write(
+            &epoch,
+            vec![
+                (
+                    "Time",
+                    Arc::new(Int64Array::from(vec![
+                        Some(ms(0)),
+                        Some(ms(1)),
+                        None,
+                        Some(ms(2)),
+                        Some(ms(1440)),
+                    ])) as ArrayRef,
+                ),
+                ("note", Arc::new(StringArray::from(vec!["a"; 5]))),
+                (
+                    "O",
+                    Arc::new(Float64Array::from(vec![1.1, 1.2, 1.0, 1.3, 1.4])),
+                ),
+                (
+                    "H",
+                    Arc::new(Float64Array::from(vec![1.15, 1.25, 1.0, 1.2, 1.45])),
+                ),
+                (
+                    "L",
+                    Arc::new(Float64Array::from(vec![1.05, 1.15, 1.0, 1.25, 1.35])),
+                ),
+                (
+                    "C",
+                    Arc::new(Float64Array::from(vec![1.12, 1.22, 1.0, 1.3, 1.42])),
+                ),
+                (
+                    "Vol",
+                    Arc::new(Float64Array::from(vec![
+                        Some(2.5),
+                        Some(1.4),
+                        Some(1.0),
+                        Some(1.0),
+                        None,
+                    ])),
+                ),
+            ],
+        );
+
+        let store = FxStore::with_config(StoreConfig {
+            validate: true,
+            ..Default::default()
+        });
+        let report = store
+            .import_parquet(epoch.to_str().unwrap(), "EURUSD", None)
+            .unwrap();
+        assert_eq!(report.imported, 3);
+        assert_eq!(report.skipped, 1);
+        assert_eq!(report.rejected, 1);
+        assert_eq!(
+            report.errors[0],
+            (3, "parse error: missing Time".to_string())
+        );
+        assert_eq!(report.days, 2);
+        store.flush();
+        let end = DAY_START + 2 * 1440 * MINUTE;
+        let fields = |store: &FxStore| {
+            store
+                .query_range("EURUSD", DAY_START, end)
+                .map(|b| ({ b.ts }, { b.open }, { b.close }, { b.volume }))
+                .collect::<Vec<_>>()
+        };
+        assert_eq!(
+            fields(&store),
+            vec![
+                (DAY_START, 110_000, 112_000, 3),
+                (DAY_START + MINUTE, 120_000, 122_000, 1),
+                (DAY_START + 1440 * MINUTE, 140_000, 142_000, 0),
+            ]
+        );
+
+        // 시간대 없는 Timestamp는 매핑의 현지 시간대로, 명시 매핑의 빠진 컬럼은 오류
+        let local = dir.path().join("local.parquet");
+        let tokyo_nine = ms(9 * 60);
+        write(
+            &local,
+            vec![
+                (
+                    "stamp",
+                    Arc::new(TimestampMillisecondArray::from(vec![tokyo_nine])) as ArrayRef,
+                ),
+                ("bid", Arc::new(Float64Array::from(vec![1.1]))),
+            ],
+        );
+        let mapping = ColumnMapping {
+            ts: "stamp".to_string(),
+            open: "bid".to_string(),
+            high: "bid".to_string(),
+            low: "bid".to_string(),
+            close: "bid".to_string(),
+            volume: None,
+            epoch_unit: None,
+            source_tz: Some(chrono_tz::Asia::Tokyo),
+        };
+        let report = store
+            .import_parquet(local.to_str().unwrap(), "GBPUSD", Some(mapping.clone()))
+            .unwrap();
+        assert_eq!(report.imported, 1);
+        store.flush();
+        assert_eq!(
+            store
+                .query_range("GBPUSD", DAY_START, DAY_START + 1440 * MINUTE)
+                .map(|b| b.ts)
+                .collect::<Vec<_>>(),
+            vec![DAY_START]
+        );
+        assert!(matches!(
+            store.import_parquet(
+                local.to_str().unwrap(),
+                "GBPUSD",
+                Some(ColumnMapping {
+                    volume: Some("size".to_string()),
+                    ..mapping.clone()
+                })
+            ),
+            Err(FxStoreError::Parquet(reason)) if reason == "no column named size"
+        ));
+        assert!(matches!(
+            store.import_parquet(
+                local.to_str().unwrap(),
+                "GBPUSD",
+                Some(ColumnMapping {
+                    ts: "bid".to_string(),
+                    ..mapping
+                })
+            ),
+            Err(FxStoreError::Parquet(_))
+        ));
+
+        // 내보낸 파일은 추정한 매핑으로 그대로 다시 읽힘
+        let exported = dir.path().join("export.parquet");
+        store
+            .export_parquet("EURUSD", DAY_START, end, &exported)
+            .unwrap();
+        let copy = FxStore::new();
+        let report = copy
+            .import_parquet(exported.to_str().unwrap(), "EURUSD", None)
+            .unwrap();
+        assert_eq!(report.imported, 3);
+        copy.flush();
+        assert_eq!(fields(&copy), fields(&store));
+    }
+
+    #[test]
+    fn query_stats_reduces_across_blocks() {
+        let store = FxStore::new();
+        // 2일에 걸친 3개 + 범위 밖 1개
+        for (i, ts) in [
+            DAY_START + 10 * MINUTE,
+            DAY_START + 1439 * MINUTE,
+            DAY_START + 1441 * MINUTE,
+            DAY_START + 5000 * MINUTE,
+        ]
+        .into_iter()
+        .enumerate()
+        {
+            let mut rec = bar(ts, 110_000 + i as u32 * 100);
+            rec.low -= 50;
+            rec.high += 50;
+            rec.volume = u32::MAX;
+            store.insert("EURUSD", rec).unwrap();
+        }
+
+        let stats = store
+            .query_stats("EURUSD", DAY_START, DAY_START + 2 * 1440 * MINUTE - 1)
+            .unwrap();
+        assert_eq!(
+            stats,
+            RangeStats {
+                min_low: 109_950,
+                max_high: 110_250,
+                total_volume: 3 * u32::MAX as u64,
+                count: 3,
+            }
+        );
+        assert!(matches!(
+            store.query_stats("GBPUSD", 0, u64::MAX),
+            Err(FxStoreError::UnknownSymbol(symbol)) if symbol == "GBPUSD"
+        ));
+    }
+
+    #[test]
+    fn query_range_par_matches_sequential() {
+        let store = FxStore::new();
+        let records = (0..10u64).flat_map(|day| {
+            [0, 1, 700, 1439].map(|minute| {
+                let ts = DAY_START + (day * 1440 + minute) * MINUTE;
+                bar(ts, 110_000 + (day * 10 + minute) as u32)
+            })
+        });
+        for rec in records {
+            store.insert("EURUSD", rec).unwrap();
+        }
+
+        let (start, end) = (DAY_START + MINUTE, DAY_START + (9 * 1440 + 700) * MINUTE);
+        let sequential: Vec<u64> = store
+            .query_range("EURUSD", start, end)
+            .map(|r| r.ts)
+            .collect();
+        let parallel: Vec<u64> = store
+            .query_range_par("EURUSD", start, end)
+            .iter()
+            .map(|r| r.ts)
+            .collect();
+        assert_eq!(parallel, sequential);
+        assert_eq!(parallel.len(), 10 * 4 - 2);
+        assert!(store.query_range_par("GBPUSD", start, end).is_empty());
+    }
+
+    #[test]
+    fn warm_populates_block_cache() {
+        let store = FxStore::new();
+        for day in 0..5 {
+            store
+                .insert("EURUSD", bar(DAY_START + day * 1440 * MINUTE, 110_000))
+                .unwrap();
+        }
+
+        let (start, end) = (DAY_START, DAY_START + 5 * 1440 * MINUTE - 1);
+
+        assert_eq!(store.warm("EURUSD", start, end), 5);
+        assert_eq!(store.warm("EURUSD", start, end), 0);
+
+        let before = store.stats().cache_hits;
+        assert_eq!(store.query_range("EURUSD", start, end).count(), 5);
+        assert_eq!(store.stats().cache_hits - before, 5);
+    }
+
+    #[test]
+    fn cache_budget_evicts_oldest_blocks() {
+        let block_bytes = crate::block::BLOCK_SIZE * std::mem::size_of::<OHLCV>();
+        let store = FxStore::with_config(StoreConfig {
+            cache_bytes: Some(2 * block_bytes),
+            ..Default::default()
+        });
+        for day in 0..5 {
+            store
+                .insert("EURUSD", bar(DAY_START + day * 1440 * MINUTE, 110_000))
+                .unwrap();
+        }
+        let cached_dates = || {
+            let sym_id = store.symbol_id("EURUSD").unwrap();
+            let mut dates: Vec<u32> = store
+                .blocks
+                .get(&sym_id)
+                .unwrap()
+                .iter()
+                .filter(|entry| entry.value().is_cached())
+                .map(|entry| *entry.key())
+                .collect();
+            dates.sort_unstable();
+            dates
+        };
+
+        let (start, end) = (DAY_START, DAY_START + 5 * 1440 * MINUTE - 1);
+        assert_eq!(store.query_range("EURUSD", start, end).count(), 5);
+        assert_eq!(cached_dates(), [20240105, 20240106]);
+
+        // 캐시에서 밀려난 블록도 다시 해제해 읽음
+        assert_eq!(store.query_range("EURUSD", start, DAY_START).count(), 1);
+        assert_eq!(cached_dates(), [20240102, 20240106]);
+    }
+
+    #[test]
+    fn query_chunks_borrow_block_slots() {
+        let store = FxStore::new();
+        for minute in [0, 5, 6, 1440, 1441, 2000] {
+            store
+                .insert(
+                    "EURUSD",
+                    bar(DAY_START + minute * MINUTE, 110_000 + minute as u32),
+                )
+                .unwrap();
+        }
+
+        let (start, end) = (DAY_START + 5 * MINUTE, DAY_START + 1441 * MINUTE);
+        let lens: Vec<usize> = store
+            .query_chunks("EURUSD", start, end)
+            .map(|view| view.len())
+            .collect();
+        // 1일차: 슬롯 5..=6, 2일차: 슬롯 0..=1
+        assert_eq!(lens, [2, 2]);
+
+        let mut visited = Vec::new();
+        store.for_each_in_range("EURUSD", start, end, |rec| visited.push(rec.ts));
+        let expected: Vec<u64> = store
+            .query_range("EURUSD", start, end)
+            .map(|r| r.ts)
+            .collect();
+        assert_eq!(visited, expected);
+        assert_eq!(visited.len(), 4);
+    }
+
+    #[test]
+    fn gorilla_codec_is_transparent_to_queries() {
+        let store = FxStore::with_config(StoreConfig {
+            codec: BlockCodec::Gorilla,
+            ..Default::default()
+        });
+        for minute in [0, 1, 2, 90, 1439] {
+            store
+                .insert(
+                    "EURUSD",
+                    bar(DAY_START + minute * MINUTE, 110_000 + minute as u32),
+                )
+                .unwrap();
+        }
+
+        let closes: Vec<u32> = store
+            .query_range("EURUSD", DAY_START, DAY_START + 1440 * MINUTE)
+            .map(|r| r.close)
+            .collect();
+        assert_eq!(closes, [110_000, 110_001, 110_002, 110_090, 111_439]);
+    }
+
+    #[test]
+    fn return_correlation_of_scaled_and_inverse_series() {
+        let store = FxStore::new();
+        let mut state = 0x853c_49e6_748f_ea9bu64;
+        let mut close = 110_000.0f64;
+        for minute in 0..60u64 {
+            state ^= state << 13;
+            state ^= state >> 7;
+            state ^= state << 17;
+            close += (state % 41) as f64 - 20.0;
+            let ts = DAY_START + minute * MINUTE;
+
+            store.insert("EURUSD", bar(ts, close as u32)).unwrap();
+            // 같은 움직임을 배율만 바꿔서 (반올림 오차를 줄이려 큰 배율)
+            store
+                .insert("EURUSZ", bar(ts, (close * 3.0) as u32))
+                .unwrap();
+            store
+                .insert("USDEUR", bar(ts, (1e10 / close) as u32))
+                .unwrap();
+            // 짝수 분만 있는 심볼로 교집합 정렬 확인
+            if minute % 2 == 0 {
+                store.insert("EURUSE", bar(ts, close as u32)).unwrap();
+            }
+        }
+        let end = DAY_START + 60 * MINUTE;
+
+        let scaled = store
+            .return_correlation("EURUSD", "EURUSZ", DAY_START, end)
+            .unwrap();
+        assert!(scaled > 0.999, "{}", scaled);
+        let inverse = store
+            .return_correlation("EURUSD", "USDEUR", DAY_START, end)
+            .unwrap();
+        assert!(inverse < -0.999, "{}", inverse);
+        let sparse = store
+            .return_correlation("EURUSD", "EURUSE", DAY_START, end)
+            .unwrap();
+        assert!((sparse - 1.0).abs() < 1e-9, "{}", sparse);
+
+        // 겹치는 분이 2개 미만
+        assert_eq!(
+            store.return_correlation("EURUSD", "EURUSE", DAY_START, DAY_START),
+            None
+        );
+        assert_eq!(
+            store.return_correlation("EURUSD", "NZDUSD", DAY_START, end),
+            None
+        );
+    }
+
+    #[test]
+    fn tick_import_and_query_across_midnight() {
+        let csv_path = temp_path("ticks.csv");
+        std::fs::write(
+            &csv_path,
+            "20240101 235959500,1.10410,1.10420,0\n\
+             20240101 235959900,1.10412,1.10421,0\n\
+             20240102 000000100,1.10415,1.10423,3\n\
+             20240102 000000,1.1\n\
+             20240102 000001000,1.10400,1.10410,0\n",
+        )
+        .unwrap();
+
+        let store = FxStore::new();
+        let report = store
+            .import_ticks_csv(csv_path.to_str().unwrap(), "EURUSD")
+            .unwrap();
+        assert_eq!((report.imported, report.skipped, report.days), (4, 1, 2));
+        store.flush();
+
+        let ticks = store.query_ticks("EURUSD", DAY_START - 1_000_000_000, DAY_START + 500_000_000);
+        let bids: Vec<u32> = ticks.iter().map(|t| t.bid).collect();
+        assert_eq!(bids, [110_410, 110_412, 110_415]);
+        assert_eq!({ ticks[2].volume }, 3);
+        assert!(store.query_ticks("GBPUSD", 0, u64::MAX).is_empty());
+
+        std::fs::remove_file(&csv_path).ok();
+    }
+
+    #[test]
+    fn tick_pipeline_emits_bars_to_subscribers_and_blocks() {
+        let store = FxStore::with_config(StoreConfig {
+            tick_tolerance: Duration::from_secs(15),
+            tick_idle_timeout: Duration::from_millis(50),
+            ..Default::default()
+        });
+        let bars = store.stream_realtime("EURUSD").unwrap();
+        let ticks = store.tick_sender("EURUSD").unwrap();
+
+        let second = 1_000_000_000;
+        let quote =
+            |offset: u64, bid: f64| Tick::from_quote(DAY_START + offset, bid, bid, 1, 0).unwrap();
+        for tick in [
+            quote(5 * second, 1.1),
+            quote(30 * second, 1.3),
+            quote(MINUTE + 10 * second, 1.2),
+            // 허용 시간 안의 순서 뒤바뀐 틱
+            quote(59 * second, 1.25),
+            quote(2 * MINUTE, 1.0),
+            quote(2 * MINUTE + 30 * second, 1.05),
+            // 이미 내보낸 첫 분
+            quote(40 * second, 9.0),
+        ] {
+            ticks.send(tick).unwrap();
+        }
+
+        // 세 번째 봉은 유휴 타임아웃으로 완성
+        let received: Vec<OHLCV> = (0..3)
+            .map(|_| bars.recv_timeout(Duration::from_secs(5)).unwrap())
+            .collect();
+        let summary = |bars: &[OHLCV]| -> Vec<(u64, u32, u32, u32, u32, u64)> {
+            bars.iter()
+                .map(|b| (b.ts, b.open, b.high, b.low, b.close, b.total_volume()))
+                .collect()
+        };
+        let expected = [
+            (DAY_START, 110_000, 130_000, 110_000, 125_000, 3),
+            (DAY_START + MINUTE, 120_000, 120_000, 120_000, 120_000, 1),
+            (
+                DAY_START + 2 * MINUTE,
+                100_000,
+                105_000,
+                100_000,
+                105_000,
+                2,
+            ),
+        ];
+        assert_eq!(summary(&received), expected);
+
+        let stored: Vec<OHLCV> = store
+            .query_range("EURUSD", DAY_START, DAY_START + 3 * MINUTE)
+            .collect();
+        assert_eq!(summary(&stored), expected);
+        assert_eq!(store.stats().late_ticks, 1);
+    }
+
+    #[test]
+    fn insert_tick_stores_ticks_and_streams_bars() {
+        let store = FxStore::new();
+        let bars = store.stream_realtime("EURUSD").unwrap();
+        store
+            .insert_tick(
+                "EURUSD",
+                Tick::from_quote(DAY_START, 1.1, 1.1002, 1, 0).unwrap(),
+            )
+            .unwrap();
+        assert_eq!(store.query_ticks("EURUSD", 0, u64::MAX).len(), 1);
+
+        // 스토어가 닫히면 진행 중인 봉까지 받고 끊김
+        drop(store);
+        let bar = bars.recv_timeout(Duration::from_secs(5)).unwrap();
+        assert_eq!(({ bar.ts }, { bar.close }), (DAY_START, 110_010));
+        assert!(bars.recv().is_err());
+    }
+
+    #[test]
+    fn synthetic_ratio_of_flat_series() {
+        let store = FxStore::new();
+        for minute in 0..5 {
+            let ts = DAY_START + minute * MINUTE;
+            store.insert("XAUUSD", bar(ts, 204_000_000)).unwrap();
+            // 하나 빠진 분은 결과에서도 제외
+            if minute != 2 {
+                store.insert("XAGUSD", bar(ts, 2_500_000)).unwrap();
+            }
+        }
+
+        let spec = SyntheticSpec::Ratio {
+            a: "XAUUSD".into(),
+            b: "XAGUSD".into(),
+        };
+        let bars = store.synthetic(spec, DAY_START, DAY_START + 5 * MINUTE);
+        assert_eq!(bars.len(), 4);
+        assert!(bars.iter().all(|b| b.close == 8_160_000 && b.is_valid()));
+
+        let missing = SyntheticSpec::Ratio {
+            a: "XAUUSD".into(),
+            b: "NZDUSD".into(),
+        };
+        assert!(
+            store
+                .synthetic(missing, DAY_START, DAY_START + 5 * MINUTE)
+                .is_empty()
+        );
+    }
+
+    #[test]
+    fn synthetic_cross_registers_the_cross_at_the_quote_scale() {
+        let store = FxStore::new();
+        let usdjpy = store
+            .register_symbol("USDJPY", Granularity::Minute)
+            .unwrap();
+        store.set_scale_if_empty("USDJPY", usdjpy.id, 1_000);
+        for minute in 0..5 {
+            let ts = DAY_START + minute * MINUTE;
+            store.insert("EURUSD", bar(ts, 110_000)).unwrap();
+            // 한쪽 다리가 빈 분은 만들지 않음
+            if minute != 3 {
+                store.insert("USDJPY", bar(ts, 150_000)).unwrap();
+            }
+        }
+
+        let end = DAY_START + 5 * MINUTE;
+        let bars = store
+            .synthetic_cross("EURUSD", "USDJPY", DAY_START, end)
+            .unwrap();
+        let eurjpy = store.symbol("EURJPY").unwrap();
+        assert_eq!(eurjpy.scale, 1_000);
+        assert_eq!(bars.len(), 4);
+        assert!(
+            bars.iter()
+                .all(|b| b.close == 165_000 && b.symbol_id == eurjpy.id)
+        );
+        assert!(bars.iter().all(|b| b.ts != DAY_START + 3 * MINUTE));
+        assert_eq!(eurjpy.to_price(bars[0].close), 165.0);
+
+        assert!(matches!(
+            store.synthetic_cross("EURUSD", "NZDUSD", DAY_START, end),
+            Err(FxStoreError::UnknownSymbol(s)) if s == "NZDUSD"
+        ));
+        store.insert("XAUXAG", bar(DAY_START, 1)).unwrap();
+        assert!(matches!(
+            store.synthetic_cross("USDJPY", "XAUXAG", DAY_START, end),
+            Err(FxStoreError::Incompatible(_))
+        ));
+    }
+
+    #[test]
+    fn slow_and_dropped_subscribers_do_not_stall_ingestion() {
+        let store = FxStore::with_config(StoreConfig {
+            subscriber_capacity: 2,
+            ..Default::default()
+        });
+        let slow = store.stream_realtime("EURUSD").unwrap();
+        drop(store.stream_realtime("EURUSD").unwrap());
+        let all = store.subscribe_all();
+
+        let ticks = store.tick_sender("EURUSD").unwrap();
+        for minute in 0..3 {
+            let tick = Tick::from_quote(DAY_START + minute * MINUTE, 1.1, 1.1, 1, 0).unwrap();
+            ticks.send(tick).unwrap();
+        }
+        // 세 봉을 한 번에 완성
+        ticks
+            .send(Tick::from_quote(DAY_START + 3 * MINUTE + 5_000_000_000, 1.1, 1.1, 1, 0).unwrap())
+            .unwrap();
+
+        let deadline = std::time::Instant::now() + Duration::from_secs(5);
+        while store.stats().dropped_bars < 2 && std::time::Instant::now() < deadline {
+            std::thread::sleep(Duration::from_millis(5));
+        }
+
+        let stats = store.stats();
+        assert_eq!(stats.dropped_bars, 2);
+        // 버려진 수신자는 해제됨
+        let dropped: Vec<(Option<&str>, u64)> = stats
+            .subscribers
+            .iter()
+            .map(|s| (s.symbol.as_deref(), s.dropped))
+            .collect();
+        assert_eq!(dropped, [(Some("EURUSD"), 1), (None, 1)]);
+
+        let received: Vec<u64> = slow.try_iter().map(|b| b.ts).collect();
+        assert_eq!(received, [DAY_START, DAY_START + MINUTE]);
+        let (symbol, bar) = all.try_recv().unwrap();
+        assert_eq!((symbol.as_str(), { bar.ts }), ("EURUSD", DAY_START));
+    }
+
+    #[test]
+    fn strict_import_reports_malformed_line() {
+        let csv_path = temp_path("malformed.csv");
+        std::fs::write(
+            &csv_path,
+            "20240102 000000,1.10000,1.10010,1.09990,1.10005,10\n\
+             20240102 000100,1.10005,oops,1.10000,1.10015,20\n",
+        )
+        .unwrap();
+        let path = csv_path.to_str().unwrap();
+
+        let report = FxStore::new().import_csv(path, "EURUSD").unwrap();
+        assert_eq!((report.imported, report.skipped), (1, 1));
+
+        let strict = FxStore::with_config(StoreConfig {
+            on_invalid: OnInvalid::Error,
+            ..Default::default()
+        });
+        match strict.import_csv(path, "EURUSD") {
+            Err(FxStoreError::Parse { line, reason }) => {
+                assert_eq!(line, 2);
+                assert!(!reason.is_empty());
+            }
+            other => panic!("expected parse error, got {:?}", other),
+        }
+        assert!(matches!(
+            strict.import_csv("/nonexistent/fx.csv", "EURUSD"),
+            Err(FxStoreError::Io(_))
+        ));
+
+        std::fs::remove_file(&csv_path).ok();
+    }
+
+    #[test]
+    fn try_query_range_distinguishes_unknown_symbol_from_empty_range() {
+        let store = FxStore::new();
+        store.insert("EURUSD", bar(DAY_START, 110_000)).unwrap();
+
+        assert!(matches!(
+            store.try_query_range("EURUSX", 0, u64::MAX),
+            Err(FxStoreError::UnknownSymbol(symbol)) if symbol == "EURUSX"
+        ));
+        assert_eq!(store.query_range("EURUSX", 0, u64::MAX).count(), 0);
+
+        let later = DAY_START + 10 * MINUTE;
+        assert_eq!(
+            store
+                .try_query_range("EURUSD", later, later + MINUTE)
+                .unwrap()
+                .count(),
+            0
+        );
+        assert_eq!(
+            store.try_query_range("EURUSD", 0, later).unwrap().count(),
+            1
+        );
+
+        assert!(matches!(
+            store.try_query_range("EURUSD", later, DAY_START),
+            Err(FxStoreError::Query(QueryError::InvertedRange { .. }))
+        ));
+        assert_eq!(store.query_range("EURUSD", later, DAY_START).count(), 0);
+    }
+
+    #[test]
+    fn hourly_symbol_uses_24_slot_blocks() {
+        use std::fmt::Write;
+
+        let mut csv = String::new();
+        for hour in 0..48u64 {
+            let ts = DAY_START + hour * 60 * MINUTE;
+            let dt = chrono::DateTime::from_timestamp_nanos(ts as i64);
+            let close = 2050 + hour;
+            writeln!(
+                csv,
+                "{},{}.0,{}.5,{}.0,{}.0,{}",
+                dt.format("%Y%m%d %H%M%S"),
+                close,
+                close,
+                close - 1,
+                close,
+                hour + 1
+            )
+            .unwrap();
+            // 같은 시간 슬롯에 떨어지는 30분 행은 병합
+            if hour == 0 {
+                csv.push_str("20240102 003000,2050.0,2051.0,2049.0,2050.5,100\n");
+            }
+        }
+        let csv_path = temp_path("hourly.csv");
+        std::fs::write(&csv_path, csv).unwrap();
+
+        let store = FxStore::new();
+        let sym = store.register_symbol("XAUUSD", Granularity::Hour).unwrap();
+        assert_eq!(
+            store
+                .register_symbol("XAUUSD", Granularity::Second)
+                .unwrap()
+                .granularity,
+            Granularity::Hour
+        );
+        let report = store
+            .import_csv(csv_path.to_str().unwrap(), "XAUUSD")
+            .unwrap();
+        assert_eq!((report.imported, report.merged), (49, 1));
+        store.flush();
+
+        let blocks = store.blocks.get(&sym.id).unwrap();
+        assert_eq!(blocks.len(), 2);
+        for block in blocks.iter() {
+            assert_eq!(block.try_decompress_shared().unwrap().len(), 24);
+        }
+        drop(blocks);
+
+        let bars: Vec<OHLCV> = store.query_range("XAUUSD", 0, u64::MAX).collect();
+        assert_eq!(bars.len(), 48);
+        assert!(bars.windows(2).all(|w| w[1].ts - w[0].ts == 60 * MINUTE));
+        assert_eq!(({ bars[0].close }, { bars[0].volume }), (205_050_000, 101));
+        assert_eq!({ bars[47].close }, 209_700_000);
+
+        std::fs::remove_file(&csv_path).ok();
+    }
+
+    #[test]
+    fn import_detects_three_decimal_scale() {
+        let csv_path = temp_path("usdjpy.csv");
+        std::fs::write(
+            &csv_path,
+            "20240102 000000,150.123,150.200,150.100,150.150,10\n\
+             20240102 000100,150.150,150.155,150.101,150.107,20\n",
+        )
+        .unwrap();
+
+        let store = FxStore::new();
+        let options = ImportOptions {
+            detect_scale: true,
+            ..Default::default()
+        };
+        store
+            .import_csv_with(csv_path.to_str().unwrap(), "USDJPY", &options)
+            .unwrap();
+        store.flush();
+
+        let symbol = store.symbol("USDJPY").unwrap();
+        assert_eq!(symbol.scale, 1_000);
+        let records: Vec<OHLCV> = store
+            .query_range("USDJPY", DAY_START, DAY_START + 2 * MINUTE)
+            .collect();
+        let prices: Vec<[f64; 4]> = records
+            .iter()
+            .map(|rec| [rec.open, rec.high, rec.low, rec.close].map(|p| symbol.to_price(p)))
+            .collect();
+        assert_eq!(
+            prices,
+            vec![
+                [150.123, 150.2, 150.1, 150.15],
+                [150.15, 150.155, 150.101, 150.107]
+            ]
+        );
+
+        std::fs::remove_file(&csv_path).ok();
+    }
+
+    #[test]
+    fn retention_keeps_newest_days_on_import_and_insert() {
+        use std::fmt::Write;
+
+        const DAY: u64 = 1440 * MINUTE;
+        let csv_path = temp_path("retention.csv");
+        let mut csv = String::from("time,open,high,low,close,volume\n");
+        for day in 0..10 {
+            for minute in 0..3 {
+                let ts = chrono::DateTime::from_timestamp_nanos(
+                    (DAY_START + day * DAY + minute * MINUTE) as i64,
+                );
+                writeln!(csv, "{},1.1,1.1,1.1,1.1,1", ts.to_rfc3339()).unwrap();
+            }
+        }
+        std::fs::write(&csv_path, csv).unwrap();
+
+        let store = FxStore::new();
+        store.set_retention("EURUSD", 5);
+        store
+            .import_csv(csv_path.to_str().unwrap(), "EURUSD")
+            .unwrap();
+
+        let stored_days = |store: &FxStore| {
+            let mut days: Vec<u64> = store
+                .query_range("EURUSD", DAY_START, DAY_START + 20 * DAY)
+                .map(|rec| (rec.ts - DAY_START) / DAY)
+                .collect();
+            days.dedup();
+            days
+        };
+        assert_eq!(stored_days(&store), [5, 6, 7, 8, 9]);
+        assert_eq!(store.stats().blocks_by_symbol["EURUSD"], 5);
+        assert_eq!(store.stats().total_records, 15);
+
+        // 새 날짜가 들어오면 가장 오래된 날짜가 밀려남
+        store
+            .insert("EURUSD", bar(DAY_START + 10 * DAY, 110_000))
+            .unwrap();
+        assert_eq!(stored_days(&store), [6, 7, 8, 9, 10]);
+
+        // 해제하면 더 이상 삭제하지 않음
+        store.set_retention("EURUSD", 0);
+        store
+            .insert("EURUSD", bar(DAY_START + 11 * DAY, 110_000))
+            .unwrap();
+        assert_eq!(stored_days(&store), [6, 7, 8, 9, 10, 11]);
+
+        std::fs::remove_file(&csv_path).ok();
+    }
+
+    #[cfg(feature = "persistence")]
+    #[test]
+    fn flush_now_persists_blocks_for_a_fresh_open() {
+        let dir = temp_path("flush-dir");
+        std::fs::remove_dir_all(&dir).ok();
+        let csv_path = temp_path("flush.csv");
+        std::fs::write(
+            &csv_path,
+            "time,open,high,low,close,volume\n\
+             2024-01-02T00:00:00Z,150.123,150.2,150.1,150.15,10\n\
+             2024-01-03T00:00:00Z,150.15,150.155,150.101,150.107,20\n",
+        )
+        .unwrap();
+        let config = || StoreConfig {
+            flush_interval: Duration::ZERO,
+            ..Default::default()
+        };
+
+        {
+            let store = FxStore::open(&dir, config()).unwrap();
+            let options = ImportOptions {
+                detect_scale: true,
+                ..Default::default()
+            };
+            store
+                .import_csv_with(csv_path.to_str().unwrap(), "USDJPY", &options)
+                .unwrap();
+            assert_eq!(store.flush_now().unwrap(), 2);
+            // 바뀐 블록이 없으면 다시 쓰지 않음
+            assert_eq!(store.flush_now().unwrap(), 0);
+            assert_eq!(std::fs::metadata(dir.join(WAL_FILE)).unwrap().len(), 0);
+
+            store
+                .insert("USDJPY", bar(DAY_START + MINUTE, 150_120))
+                .unwrap();
+            assert_eq!(store.flush_now().unwrap(), 1);
+        }
+
+        let store = FxStore::open(&dir, config()).unwrap();
+        assert_eq!(store.symbol("USDJPY").unwrap().scale, 1_000);
+        // 메타데이터는 블록 요약에서, 최신가는 마지막 블록 하나만 해제해 채움
+        let sym_id = store.symbol("USDJPY").unwrap().id;
+        let first_day = ts_to_date(DAY_START);
+        assert!(
+            !store
+                .blocks
+                .get(&sym_id)
+                .unwrap()
+                .get(&first_day)
+                .unwrap()
+                .is_cached()
+        );
+        let counts: Vec<u32> = store
+            .blocks_info("USDJPY")
+            .iter()
+            .map(|info| info.record_count)
+            .collect();
+        assert_eq!(counts, [2, 1]);
+        assert_eq!(store.latest("USDJPY").map(|rec| rec.close), Some(150_107));
+        assert_eq!(store.stats.last_price_misses.load(Ordering::Relaxed), 0);
+        let closes: Vec<u32> = store
+            .query_range("USDJPY", DAY_START, DAY_START + 2 * 1440 * MINUTE)
+            .map(|rec| rec.close)
+            .collect();
+        assert_eq!(closes, [150_150, 150_120, 150_107]);
+        assert_eq!(store.stats().total_records, 3);
+
+        std::fs::remove_file(&csv_path).ok();
+        std::fs::remove_dir_all(&dir).ok();
+    }
+
+    #[cfg(feature = "persistence")]
+    #[test]
+    fn blocks_keep_their_codec_across_config_changes() {
+        let dir = temp_path("codec-change-dir");
+        std::fs::remove_dir_all(&dir).ok();
+        let config = |codec| StoreConfig {
+            codec,
+            flush_interval: Duration::ZERO,
+            ..Default::default()
+        };
+
+        // 날마다 다른 방식으로 써도 다시 열면 모두 읽힘
+        for (day, codec) in BlockCodec::ALL.into_iter().enumerate() {
+            let store = FxStore::open(&dir, config(codec)).unwrap();
+            let ts = DAY_START + day as u64 * NANOS_PER_DAY;
+            store
+                .insert("EURUSD", bar(ts, 110_000 + day as u32))
+                .unwrap();
+            store.flush_now().unwrap();
+        }
+
+        let store = FxStore::open(&dir, config(BlockCodec::ZstdColumns)).unwrap();
+        let codecs: Vec<BlockCodec> = (0..4)
+            .map(|day| {
+                let date = ts_to_date(DAY_START + day * NANOS_PER_DAY);
+                store
+                    .blocks
+                    .get(&0)
+                    .unwrap()
+                    .get(&date)
+                    .unwrap()
+                    .layout
+                    .codec
+            })
+            .collect();
+        assert_eq!(codecs, BlockCodec::ALL);
+        let closes: Vec<u32> = store
+            .query_range("EURUSD", DAY_START, DAY_START + 4 * NANOS_PER_DAY)
+            .map(|rec| rec.close)
+            .collect();
+        assert_eq!(closes, [110_000, 110_001, 110_002, 110_003]);
+
+        drop(store);
+        std::fs::remove_dir_all(&dir).ok();
+    }
+
+    /// 메모리의 블록 데이터 한 바이트를 뒤집음 (체크섬은 그대로)
+    fn corrupt_block(store: &FxStore, sym_id: u16, date: u32) {
+        let blocks = store.blocks.get(&sym_id).unwrap();
+        let mut block = blocks.get_mut(&date).unwrap();
+        let mut data = block.data.to_vec();
+        let mid = data.len() / 2;
+        data[mid] ^= 0x01;
+        block.data = Arc::new(data);
+        block.evict();
+    }
+
+    #[test]
+    fn undecodable_blocks_with_valid_checksums_do_not_panic() {
+        let bad_date = ts_to_date(DAY_START + NANOS_PER_DAY);
+        let end = DAY_START + 2 * NANOS_PER_DAY;
+        for on_corrupt in [OnCorrupt::Skip, OnCorrupt::Error] {
+            let store = FxStore::with_config(StoreConfig {
+                on_corrupt,
+                ..Default::default()
+            });
+            for day in 0..2 {
+                let ts = DAY_START + day * NANOS_PER_DAY;
+                store
+                    .insert("EURUSD", bar(ts, 110_000 + day as u32))
+                    .unwrap();
+            }
+            store.flush();
+            // 체크섬은 통과하고 해제에서만 걸리는 블록
+            {
+                let blocks = store.blocks.get(&0).unwrap();
+                let mut block = blocks.get_mut(&bad_date).unwrap();
+                block.data = Arc::new(block.data[..block.data.len() / 2].to_vec());
+                block.checksum = xxh64(&block.data, 0);
+                block.evict();
+            }
+
+            let closes: Vec<u32> = store
+                .query_range("EURUSD", DAY_START, end)
+                .map(|rec| rec.close)
+                .collect();
+            assert_eq!(closes, [110_000]);
+            assert_eq!(store.query_range_par("EURUSD", DAY_START, end).len(), 1);
+            assert_eq!(store.query_daily("EURUSD", DAY_START, end).len(), 1);
+            assert_eq!(store.corrupt_days("EURUSD", DAY_START, end), [bad_date]);
+            let stats = store.query_stats("EURUSD", DAY_START, end);
+            match on_corrupt {
+                OnCorrupt::Skip => assert_eq!(stats.unwrap().count, 1),
+                OnCorrupt::Error => assert!(matches!(
+                    stats,
+                    Err(FxStoreError::BlockCorrupt { date, .. }) if date == bad_date
+                )),
+            }
+
+            // 복구로 지운 날짜의 최신가는 남은 블록에서 다시 찾음
+            assert_eq!({ store.latest("EURUSD").unwrap().close }, 110_001);
+            assert_eq!(store.scrub(true).unwrap().removed, 1);
+            assert_eq!({ store.latest("EURUSD").unwrap().close }, 110_000);
+        }
+    }
+
+    #[cfg(feature = "persistence")]
+    #[test]
+    fn corrupt_blocks_are_skipped_reported_and_repaired() {
+        let dir = temp_path("corrupt-dir");
+        std::fs::remove_dir_all(&dir).ok();
+        let config = |on_corrupt| StoreConfig {
+            on_corrupt,
+            flush_interval: Duration::ZERO,
+            ..Default::default()
+        };
+        let bad_date = ts_to_date(DAY_START + NANOS_PER_DAY);
+        let end = DAY_START + 3 * NANOS_PER_DAY;
+
+        {
+            let store = FxStore::open(&dir, config(OnCorrupt::Skip)).unwrap();
+            for day in 0..3 {
+                store
+                    .insert(
+                        "EURUSD",
+                        bar(DAY_START + day * NANOS_PER_DAY, 110_000 + day as u32),
+                    )
+                    .unwrap();
+            }
+            store.flush_now().unwrap();
+            // 깨진 데이터가 원래 체크섬과 함께 블록 파일에 기록됨
+            corrupt_block(&store, 0, bad_date);
+            store.dirty.lock().insert((0, bad_date));
+            store.flush_now().unwrap();
+
+            let strict = FxStore::with_config(config(OnCorrupt::Error));
+            strict.insert("EURUSD", bar(DAY_START, 110_000)).unwrap();
+            strict.flush();
+            corrupt_block(&strict, 0, ts_to_date(DAY_START));
+            assert!(matches!(
+                strict
+                    .try_query_range("EURUSD", DAY_START, end)
+                    .map(|it| it.count()),
+                Err(FxStoreError::BlockCorrupt { symbol_id: 0, .. })
+            ));
+            assert!(strict.query_stats("EURUSD", DAY_START, end).is_err());
+        }
+
+        // 열 때는 해제하지 않으므로 손상은 처음 읽을 때 드러남
+        let strict = FxStore::open(&dir, config(OnCorrupt::Error)).unwrap();
+        assert!(matches!(
+            strict
+                .try_query_range("EURUSD", DAY_START, end)
+                .map(|it| it.count()),
+            Err(FxStoreError::BlockCorrupt { date, .. }) if date == bad_date
+        ));
+        drop(strict);
+
+        let store = FxStore::open(&dir, config(OnCorrupt::Skip)).unwrap();
+        let closes: Vec<u32> = store
+            .query_range("EURUSD", DAY_START, end)
+            .map(|rec| rec.close)
+            .collect();
+        assert_eq!(closes, [110_000, 110_002]);
+        assert_eq!(store.count_range("EURUSD", DAY_START, end), 2);
+        assert_eq!(store.corrupt_days("EURUSD", DAY_START, end), [bad_date]);
+        assert!(
+            store
+                .corrupt_days("EURUSD", DAY_START, DAY_START)
+                .is_empty()
+        );
+        assert_eq!(
+            store.time_bounds("EURUSD").map(|(_, last)| last),
+            Some(end - NANOS_PER_DAY)
+        );
+
+        let report = store.scrub(false).unwrap();
+        assert_eq!(report.blocks_checked, 3);
+        assert_eq!(report.corrupt.len(), 1);
+        assert_eq!(
+            (report.corrupt[0].symbol.as_str(), report.corrupt[0].date),
+            ("EURUSD", bad_date)
+        );
+        assert_eq!(report.removed, 0);
+
+        let report = store.scrub(true).unwrap();
+        assert_eq!(report.removed, 1);
+        assert_eq!(store.block_count("EURUSD"), 2);
+        assert!(store.corrupt_days("EURUSD", DAY_START, end).is_empty());
+        drop(store);
+
+        // 삭제가 블록 파일에 남아 다시 열어도 깨끗하고, 그 날짜를 다시 채울 수 있음
+        let store = FxStore::open(&dir, config(OnCorrupt::Error)).unwrap();
+        assert_eq!(store.block_count("EURUSD"), 2);
+        store
+            .insert("EURUSD", bar(DAY_START + NANOS_PER_DAY, 110_001))
+            .unwrap();
+        assert_eq!(store.count_range("EURUSD", DAY_START, end), 3);
+        assert!(store.scrub(false).unwrap().corrupt.is_empty());
+
+        drop(store);
+        std::fs::remove_dir_all(&dir).ok();
+    }
+
+    #[test]
+    fn blocks_info_lists_each_imported_day() {
+        let csv_path = temp_path("blocks-info.csv");
+        std::fs::write(
+            &csv_path,
+            "time,open,high,low,close,volume\n\
+             2024-01-02T00:00:00Z,1.10000,1.10020,1.09990,1.10010,10\n\
+             2024-01-02T00:01:00Z,1.10010,1.10030,1.10000,1.10020,10\n\
+             2024-01-02T00:02:00Z,1.10020,1.10040,1.10010,1.10030,10\n\
+             2024-01-03T00:00:00Z,1.20000,1.20000,1.19000,1.19500,10\n",
+        )
+        .unwrap();
+        let store = FxStore::new();
+        store
+            .import_csv(csv_path.to_str().unwrap(), "EURUSD")
+            .unwrap();
+        store.flush();
+        std::fs::remove_file(&csv_path).ok();
+
+        let infos = store.blocks_info("EURUSD");
+        let summary: Vec<(u32, u32, u32, u32)> = infos
+            .iter()
+            .map(|info| (info.date, info.record_count, info.min_price, info.max_price))
+            .collect();
+        assert_eq!(
+            summary,
+            [
+                (20240102, 3, 109_990, 110_040),
+                (20240103, 1, 119_000, 120_000)
+            ]
+        );
+        assert!(infos.iter().all(|info| info.compressed_bytes > 0));
+        assert!(store.blocks_info("GBPUSD").is_empty());
+    }
+
+    #[test]
+    fn merging_into_a_corrupt_block_replaces_it() {
+        let store = FxStore::new();
+        store.insert("EURUSD", bar(DAY_START, 110_000)).unwrap();
+        store.flush();
+        corrupt_block(&store, 0, ts_to_date(DAY_START));
+
+        store
+            .insert("EURUSD", bar(DAY_START + MINUTE, 110_010))
+            .unwrap();
+        store.flush();
+        let closes: Vec<u32> = store
+            .query_range("EURUSD", DAY_START, DAY_START + NANOS_PER_DAY)
+            .map(|rec| rec.close)
+            .collect();
+        assert_eq!(closes, [110_010]);
+    }
+
+    #[cfg(feature = "persistence")]
+    #[test]
+    fn background_flush_writes_dirty_blocks() {
+        let dir = temp_path("bg-flush-dir");
+        std::fs::remove_dir_all(&dir).ok();
+        let config = StoreConfig {
+            flush_interval: Duration::from_millis(20),
+            ..Default::default()
+        };
+
+        let store = FxStore::open(&dir, config).unwrap();
+        store.insert("EURUSD", bar(DAY_START, 110_000)).unwrap();
+        let wal_len = || std::fs::metadata(dir.join(WAL_FILE)).unwrap().len();
+        let deadline = Instant::now() + Duration::from_secs(5);
+        while wal_len() > 0 && Instant::now() < deadline {
+            std::thread::sleep(Duration::from_millis(10));
+        }
+        assert_eq!(wal_len(), 0);
+
+        // WAL 없이 블록 파일만으로 복원
+        drop(store);
+        assert_eq!(wal_len(), 0);
+        let store = FxStore::open(&dir, StoreConfig::default()).unwrap();
+        assert_eq!(store.latest("EURUSD").map(|rec| rec.close), Some(110_000));
+
+        std::fs::remove_dir_all(&dir).ok();
+    }
+
+    #[test]
+    fn register_symbol_stops_when_ids_run_out() {
+        let store = FxStore::new();
+        store
+            .next_symbol_id
+            .store(u32::from(u16::MAX), Ordering::Relaxed);
+        let last = store
+            .register_symbol("XAU/USD", Granularity::Minute)
+            .unwrap();
+        assert_eq!((last.id, last.base.as_str()), (u16::MAX, "XAU"));
+
+        assert!(matches!(
+            store.insert("EURUSD", bar(DAY_START, 100_000)),
+            Err(FxStoreError::SymbolLimit(name)) if name == "EURUSD"
+        ));
+        assert!(!store.has_symbol("EURUSD"));
+        assert_eq!(store.symbol_name(0), None);
+        // 이미 있는 심볼은 계속 쓸 수 있고, 짧거나 비ASCII 이름도 패닉하지 않음
+        store.insert("XAU/USD", bar(DAY_START, 2_050_000)).unwrap();
+        assert!(store.register_symbol("éU", Granularity::Minute).is_err());
+    }
+
+    #[test]
+    fn aliases_and_renames_resolve_to_the_same_blocks() {
+        let store = FxStore::new();
+        store.insert("XAUUSD", bar(DAY_START, 2_050_000)).unwrap();
+        store.flush();
+
+        store.add_alias("GOLD", "XAUUSD").unwrap();
+        store
+            .insert("GOLD", bar(DAY_START + MINUTE, 2_051_000))
+            .unwrap();
+        store.flush();
+        let closes = |symbol: &str| -> Vec<u32> {
+            store
+                .query_range(symbol, DAY_START, DAY_START + 1440 * MINUTE)
+                .map(|rec| rec.close)
+                .collect()
+        };
+        assert_eq!(closes("GOLD"), [2_050_000, 2_051_000]);
+        assert_eq!(closes("XAUUSD"), closes("GOLD"));
+        assert_eq!(store.get_symbols(), ["XAUUSD"]);
+        assert!(matches!(
+            store.add_alias("SILVER", "XAGUSD"),
+            Err(FxStoreError::UnknownSymbol(_))
+        ));
+        assert!(matches!(
+            store.add_alias("XAUUSD", "XAUUSD"),
+            Err(FxStoreError::SymbolExists(_))
+        ));
+
+        let id = store.symbol("XAUUSD").unwrap().id;
+        assert_eq!(store.symbol_name(id).as_deref(), Some("XAUUSD"));
+        assert_eq!(store.symbol_name(id + 1), None);
+        store.rename_symbol("XAUUSD", "XAU/USD").unwrap();
+        assert_eq!(store.symbol("XAU/USD").unwrap().id, id);
+        assert_eq!(store.symbol("XAU/USD").unwrap().name, "XAU/USD");
+        assert_eq!(store.symbol_name(id).as_deref(), Some("XAU/USD"));
+        assert!(!store.has_symbol("XAUUSD"));
+        assert_eq!(closes("XAU/USD"), [2_050_000, 2_051_000]);
+        // 별칭은 새 이름을 따라감
+        assert_eq!(closes("GOLD"), closes("XAU/USD"));
+        assert!(matches!(
+            store.rename_symbol("XAU/USD", "GOLD"),
+            Err(FxStoreError::SymbolExists(_))
+        ));
+    }
+
+    #[cfg(feature = "persistence")]
+    #[test]
+    fn rename_rewrites_the_block_file_under_the_new_name() {
+        let dir = temp_path("rename-dir");
+        std::fs::remove_dir_all(&dir).ok();
+        let config = || StoreConfig {
+            flush_interval: Duration::ZERO,
+            ..Default::default()
+        };
+
+        {
+            let store = FxStore::open(&dir, config()).unwrap();
+            store.insert("EUR/USD", bar(DAY_START, 110_000)).unwrap();
+            store.flush_now().unwrap();
+            store.rename_symbol("EUR/USD", "EURUSD").unwrap();
+            store
+                .insert("EURUSD", bar(DAY_START + MINUTE, 110_010))
+                .unwrap();
+        }
+
+        let store = FxStore::open(&dir, config()).unwrap();
+        assert_eq!(store.get_symbols(), ["EURUSD"]);
+        let closes: Vec<u32> = store
+            .query_range("EURUSD", DAY_START, DAY_START + 1440 * MINUTE)
+            .map(|rec| rec.close)
+            .collect();
+        assert_eq!(closes, [110_000, 110_010]);
+
+        std::fs::remove_dir_all(&dir).ok();
+    }
+
+    #[test]
+    fn forward_fill_bridges_a_three_minute_gap() {
+        let store = FxStore::new();
+        // 0..=3분, (4, 5, 6분 비어 있음), 7..=9분
+        for minute in (0..4).chain(7..10) {
+            store
+                .insert(
+                    "EURUSD",
+                    bar(DAY_START + minute * MINUTE, 100_000 + minute as u32),
+                )
+                .unwrap();
+        }
+        store.flush();
+        let end = DAY_START + 9 * MINUTE;
+
+        let sparse = store.query_range_filled("EURUSD", DAY_START, end, FillPolicy::None);
+        assert_eq!(sparse.len(), 7);
+
+        let filled = store.query_range_filled(
+            "EURUSD",
+            DAY_START,
+            end,
+            FillPolicy::ForwardFill {
+                max_gap_secs: u64::MAX,
+            },
+        );
+        assert_eq!(filled.len(), 10);
+        assert!(
+            filled
+                .windows(2)
+                .all(|pair| { pair[1].ts } - { pair[0].ts } == MINUTE)
+        );
+        for rec in &filled[4..7] {
+            assert_eq!(
+                ({ rec.open }, { rec.high }, { rec.low }, { rec.close }),
+                (100_003, 100_003, 100_003, 100_003)
+            );
+            assert_eq!({ rec.volume }, 0);
+        }
+        assert_eq!({ filled[7].close }, 100_007);
+
+        // 직전 봉을 이어받아 범위 첫머리도 채움
+        let from_gap = store.query_range_filled(
+            "EURUSD",
+            DAY_START + 5 * MINUTE,
+            end,
+            FillPolicy::ForwardFill {
+                max_gap_secs: u64::MAX,
+            },
+        );
+        assert_eq!(from_gap.len(), 5);
+        assert_eq!({ from_gap[0].close }, 100_003);
+    }
+
+    #[tokio::test]
+    async fn async_query_leaves_the_runtime_free() {
+        // #[tokio::test]는 단일 스레드 런타임: 쿼리가 워커를 막으면 다른 작업도 멈춤
+        let store = Arc::new(FxStore::new());
+        store.insert("EURUSD", bar(DAY_START, 100_000)).unwrap();
+        store.flush();
+        let sym_id = store.symbol_id("EURUSD").unwrap();
+
+        // 다른 스레드가 블록 맵을 잡고 있는 동안 쿼리는 블로킹 풀에서 대기
+        let (locked_tx, locked_rx) = std::sync::mpsc::channel();
+        let (release_tx, release_rx) = std::sync::mpsc::channel::<()>();
+        let holder = {
+            let store = Arc::clone(&store);
+            std::thread::spawn(move || {
+                let _guard = store.blocks.get_mut(&sym_id);
+                locked_tx.send(()).unwrap();
+                release_rx.recv().ok();
+            })
+        };
+        locked_rx.recv().unwrap();
+
+        let slow = tokio::spawn({
+            let store = Arc::clone(&store);
+            async move {
+                store
+                    .query_range_async("EURUSD", DAY_START, DAY_START + MINUTE)
+                    .await
+            }
+        });
+        let other = tokio::spawn({
+            let store = Arc::clone(&store);
+            async move { store.get_symbols() }
+        });
+        assert_eq!(other.await.unwrap(), vec!["EURUSD".to_string()]);
+        assert!(!slow.is_finished());
+
+        release_tx.send(()).unwrap();
+        holder.join().unwrap();
+        assert_eq!(slow.await.unwrap().unwrap().len(), 1);
+
+        let unknown = store
+            .query_range_async("EURUSX", DAY_START, DAY_START)
+            .await;
+        assert!(matches!(unknown, Err(FxStoreError::UnknownSymbol(_))));
+    }
+
+    fn day_batch(date: u32) -> CompressJob {
+        CompressJob::Batch {
+            date,
+            symbol_id: 0,
+            layout: BlockLayout::default(),
+            records: vec![bar(date_to_ts(date), 100_000)],
+        }
+    }
+
+    #[test]
+    fn full_compress_queue_follows_overflow_policy() {
+        // 워커 없이 대기열만: 용량 2
+        let queue = CompressQueue::new(1, 2, OverflowPolicy::DropOldest);
+        let dropped: u64 = (0..5)
+            .map(|day| queue.send(day_batch(20240101 + day)).unwrap())
+            .sum();
+        assert_eq!(dropped, 3);
+        assert_eq!(queue.len(), 2);
+        // 남은 것은 가장 최근 두 배치
+        let (tx, rx) = &queue.shards[0];
+        let dates: Vec<u32> = rx
+            .try_iter()
+            .map(|job| match job {
+                CompressJob::Batch { date, .. } => date,
+                _ => unreachable!(),
+            })
+            .collect();
+        assert_eq!(dates, vec![20240104, 20240105]);
+
+        // 자리를 만들 때 Flush는 버리지 않고 그 뒤 배치부터 버림
+        let (done, ack) = bounded(1);
+        tx.send(CompressJob::Flush(done)).unwrap();
+        tx.send(day_batch(20240106)).unwrap();
+        assert_eq!(queue.send(day_batch(20240107)).unwrap(), 1);
+        assert_eq!(queue.send(day_batch(20240108)).unwrap(), 1);
+        let kinds: Vec<String> = rx
+            .try_iter()
+            .map(|job| match job {
+                CompressJob::Flush(done) => {
+                    done.send(()).unwrap();
+                    "flush".to_string()
+                }
+                CompressJob::Batch { date, .. } => date.to_string(),
+                _ => unreachable!(),
+            })
+            .collect();
+        assert_eq!(kinds, ["flush", "20240108"]);
+        assert!(ack.try_recv().is_ok());
+
+        let queue = CompressQueue::new(1, 2, OverflowPolicy::Error);
+        assert_eq!(queue.send(day_batch(20240101)).unwrap(), 0);
+        assert_eq!(queue.send(day_batch(20240102)).unwrap(), 0);
+        assert!(matches!(
+            queue.send(day_batch(20240103)),
+            Err(FxStoreError::QueueFull)
+        ));
+    }
+
+    #[test]
+    fn concurrent_imports_keep_every_day_under_backpressure() {
+        use std::fmt::Write;
+
+        // 3년 x 4심볼, 하루 4개 봉. 작은 대기열로 임포트가 압축 워커를 기다리게 함
+        const DAYS: u64 = 3 * 365 + 1;
+        let store = Arc::new(FxStore::with_config(StoreConfig {
+            compress_queue: 4,
+            compress_workers: 2,
+            ..StoreConfig::default()
+        }));
+        let symbols = ["EURUSD", "GBPUSD", "USDJPY", "XAUUSD"];
+
+        let imports: Vec<_> = symbols
+            .iter()
+            .enumerate()
+            .map(|(i, &symbol)| {
+                let path = temp_path(&format!("stress-{}.csv", symbol));
+                let mut csv = String::from("time,open,high,low,close,volume\n");
+                for day in 0..DAYS {
+                    for hour in [0, 6, 12, 18] {
+                        let ts = DAY_START + day * NANOS_PER_DAY + hour * 60 * MINUTE;
+                        let time = chrono::DateTime::from_timestamp_nanos(ts as i64).to_rfc3339();
+                        let price = format!("1.{:05}", 10_000 + i * 1000 + day as usize % 500);
+                        writeln!(csv, "{time},{price},{price},{price},{price},1").unwrap();
+                    }
+                }
+                std::fs::write(&path, csv).unwrap();
+
+                let store = Arc::clone(&store);
+                std::thread::spawn(move || {
+                    let report = store.import_csv(path.to_str().unwrap(), symbol).unwrap();
+                    std::fs::remove_file(&path).ok();
+                    report
+                })
+            })
+            .collect();
+        for import in imports {
+            let report = import.join().unwrap();
+            assert_eq!(report.imported, DAYS as usize * 4);
+            assert_eq!(report.dropped_batches, 0);
+        }
+        store.flush();
+
+        let stats = store.stats();
+        assert_eq!(stats.dropped_batches, 0);
+        assert_eq!(stats.compress_queue_depth, 0);
+        for symbol in symbols {
+            assert_eq!(stats.blocks_by_symbol[symbol], DAYS, "{}", symbol);
+            let end = DAY_START + DAYS * NANOS_PER_DAY;
+            assert_eq!(
+                store.query_range(symbol, DAY_START, end).count(),
+                DAYS as usize * 4
+            );
+        }
+    }
+
+    #[test]
+    fn concurrent_importers_of_one_day_keep_the_union() {
+        use std::fmt::Write;
+
+        const IMPORTERS: u64 = 8;
+        const MINUTES_EACH: u64 = 1440 / IMPORTERS;
+        for round in 0..4 {
+            let store = Arc::new(FxStore::with_config(StoreConfig {
+                compress_queue: 2,
+                compress_workers: 4,
+                ..StoreConfig::default()
+            }));
+            let start = Arc::new(std::sync::Barrier::new(IMPORTERS as usize));
+
+            // 같은 날의 분 범위를 나눠 가진 파일 8개 + 각자 새 심볼 하나를 동시에 임포트
+            let importers: Vec<_> = (0..IMPORTERS)
+                .map(|i| {
+                    let mut csv = String::from("time,open,high,low,close,volume\n");
+                    for minute in i * MINUTES_EACH..(i + 1) * MINUTES_EACH {
+                        let ts = DAY_START + minute * MINUTE;
+                        let time = chrono::DateTime::from_timestamp_nanos(ts as i64).to_rfc3339();
+                        let price = format!("1.{:05}", 10_000 + minute);
+                        writeln!(csv, "{time},{price},{price},{price},{price},1").unwrap();
+                    }
+                    let path = temp_path(&format!("same-day-{}-{}.csv", round, i));
+                    std::fs::write(&path, &csv).unwrap();
+
+                    let store = Arc::clone(&store);
+                    let start = Arc::clone(&start);
+                    std::thread::spawn(move || {
+                        start.wait();
+                        let own = format!("AB{}USD", i);
+                        store.insert(&own, bar(DAY_START, i as u32 + 1)).unwrap();
+                        store.import_csv(path.to_str().unwrap(), "XAUUSD").unwrap();
+                        std::fs::remove_file(&path).ok();
+                    })
+                })
+                .collect();
+            for importer in importers {
+                importer.join().unwrap();
+            }
+            store.flush();
+
+            let closes: Vec<u32> = store
+                .query_range("XAUUSD", DAY_START, DAY_START + NANOS_PER_DAY)
+                .map(|rec| rec.close)
+                .collect();
+            let expected: Vec<u32> = (0..1440).map(|minute| 110_000 + minute).collect();
+            assert_eq!(closes, expected, "round {}", round);
+            assert_eq!(store.block_count("XAUUSD"), 1);
+
+            let ids: HashSet<u16> = store
+                .get_symbols()
+                .iter()
+                .map(|name| store.symbol(name).unwrap().id)
+                .collect();
+            assert_eq!(ids.len(), IMPORTERS as usize + 1, "round {}", round);
+            for i in 0..IMPORTERS {
+                let own: Vec<u32> = store
+                    .query_range(&format!("AB{}USD", i), 0, u64::MAX)
+                    .map(|rec| rec.close)
+                    .collect();
+                assert_eq!(own, [i as u32 + 1], "round {}", round);
+            }
+        }
+    }
+
+    #[test]
+    fn price_filter_skips_blocks_outside_their_band() {
+        // 10일, d일의 가격은 100_000 + d * 1000 부근
+        let store = FxStore::new();
+        for day in 0..10u64 {
+            for minute in 0..30 {
+                let close = 100_000 + day as u32 * 1000 + minute as u32;
+                store
+                    .insert(
+                        "EURUSD",
+                        bar(DAY_START + day * NANOS_PER_DAY + minute * MINUTE, close),
+                    )
+                    .unwrap();
+            }
+        }
+        store.flush();
+        let block = store
+            .blocks
+            .get(&0)
+            .unwrap()
+            .get(&20240102)
+            .unwrap()
+            .clone();
+        assert_eq!((block.min_price, block.max_price), (100_000, 100_029));
+        assert_eq!(
+            (block.min_ts, block.max_ts),
+            (DAY_START, DAY_START + 29 * MINUTE)
+        );
+
+        let end = DAY_START + 10 * NANOS_PER_DAY;
+        let before = store.stats().blocks_decompressed;
+        let records = store
+            .query_range_filtered("EURUSD", DAY_START, end, 103_010, 104_005)
+            .unwrap();
+        // 3일차 20개 + 4일차 6개, 해제한 블록은 그 두 날뿐
+        assert_eq!(records.len(), 26);
+        assert!(
+            records
+                .iter()
+                .all(|rec| (103_010..=104_005).contains(&{ rec.close }))
+        );
+        assert_eq!(store.stats().blocks_decompressed - before, 2);
+
+        // 시각 범위로도 해제 없이 건너뜀 (하루의 뒷부분은 비어 있음)
+        let evening = DAY_START + 12 * 60 * MINUTE;
+        let before = store.stats().blocks_decompressed;
+        assert_eq!(
+            store
+                .query_range("EURUSD", evening, evening + MINUTE)
+                .count(),
+            0
+        );
+        assert_eq!(store.stats().blocks_decompressed, before);
+    }
+
+    #[cfg(feature = "persistence")]
+    #[test]
+    fn snapshot_round_trip_remaps_symbols_and_merges_existing_days() {
+        let fields = |recs: &[OHLCV]| -> Vec<(u64, u32, u32, u32, u32, u32, u16)> {
+            recs.iter()
+                .map(|r| (r.ts, r.open, r.high, r.low, r.close, r.volume, r.symbol_id))
+                .collect()
+        };
+        let path = temp_path("xauusd.fxs");
+        let source = FxStore::new();
+        source
+            .register_symbol("XAUUSD", Granularity::Minute)
+            .unwrap();
+        source.symbols.get_mut("XAUUSD").unwrap().scale = 100;
+        for day in 0..3 {
+            for i in 0..30 {
+                let ts = DAY_START + day * NANOS_PER_DAY + i * MINUTE;
+                source
+                    .insert("XAUUSD", bar(ts, 205_000 + i as u32))
+                    .unwrap();
+                source.insert("EURUSD", bar(ts, 110_000)).unwrap();
+            }
+        }
+        source.flush();
+
+        // 첫 두 날만
+        let exported = source
+            .export_snapshot(&path, &["XAUUSD"], 20240102, 20240103)
+            .unwrap();
+        assert_eq!(exported, 2);
+        let end = DAY_START + 2 * NANOS_PER_DAY - 1;
+        let expected: Vec<OHLCV> = source.query_range("XAUUSD", DAY_START, end).collect();
+
+        // GBPUSD가 먼저 id 0을 차지해 XAUUSD는 다른 id로
+        let target = FxStore::new();
+        target
+            .register_symbol("GBPUSD", Granularity::Minute)
+            .unwrap();
+        let report = target.import_snapshot(&path).unwrap();
+        assert_eq!((report.symbols, report.blocks, report.records), (1, 2, 60));
+        let sym = target.symbol("XAUUSD").unwrap();
+        assert_ne!(sym.id, source.symbol("XAUUSD").unwrap().id);
+        assert_eq!(sym.scale, 100);
+        let imported: Vec<OHLCV> = target.query_range("XAUUSD", DAY_START, end).collect();
+        let remapped: Vec<OHLCV> = expected
+            .iter()
+            .map(|rec| OHLCV {
+                symbol_id: sym.id,
+                ..*rec
+            })
+            .collect();
+        assert_eq!(fields(&imported), fields(&remapped));
+        assert_eq!(
+            target.latest("XAUUSD").map(|r| r.ts),
+            Some(DAY_START + NANOS_PER_DAY + 29 * MINUTE)
+        );
+
+        // 기존 날짜는 병합: 같은 슬롯은 스냅샷 봉, 나머지 슬롯은 유지
+        let local = FxStore::new();
+        local
+            .register_symbol("XAUUSD", Granularity::Minute)
+            .unwrap();
+        local.symbols.get_mut("XAUUSD").unwrap().scale = 100;
+        local.insert("XAUUSD", bar(DAY_START, 1)).unwrap();
+        local
+            .insert("XAUUSD", bar(DAY_START + 600 * MINUTE, 2))
+            .unwrap();
+        let report = local.import_snapshot(&path).unwrap();
+        assert_eq!(report.merged, 1);
+        let day: Vec<OHLCV> = local.query_range("XAUUSD", DAY_START, end).collect();
+        assert_eq!(day.len(), 61);
+        assert_eq!({ day[0].close }, 205_000);
+        assert_eq!({ day[30].close }, 2);
+
+        // 봉이 있는 심볼과 스케일이 다르면 거부
+        let other = FxStore::new();
+        other.insert("XAUUSD", bar(DAY_START, 1)).unwrap();
+        other.flush();
+        assert!(matches!(
+            other.import_snapshot(&path),
+            Err(FxStoreError::Incompatible(_))
+        ));
+        std::fs::remove_file(&path).ok();
+    }
+
+    #[cfg(feature = "persistence")]
+    #[test]
+    fn same_file_is_imported_once_unless_forced() {
+        let dir = temp_path("dedup-dir");
+        std::fs::remove_dir_all(&dir).ok();
+        let csv_path = temp_path("dedup.csv");
+        std::fs::write(
+            &csv_path,
+            "time,open,high,low,close,volume\n\
+             2024-01-02T00:00:00Z,1.1,1.1,1.1,1.1,10\n\
+             2024-01-02T00:01:00Z,1.2,1.2,1.2,1.2,20\n",
+        )
+        .unwrap();
+        let path = csv_path.to_str().unwrap();
+
+        {
+            let store = FxStore::open(&dir, StoreConfig::default()).unwrap();
+            let first = store.import_csv(path, "EURUSD").unwrap();
+            assert_eq!((first.imported, first.already_imported), (2, false));
+            let second = store.import_csv(path, "EURUSD").unwrap();
+            assert_eq!((second.imported, second.already_imported), (0, true));
+            // 다른 심볼로는 별개의 임포트
+            assert_eq!(store.import_csv(path, "GBPUSD").unwrap().imported, 2);
+        }
+
+        // 다시 열어도 기록이 남아 있음
+        let store = FxStore::open(&dir, StoreConfig::default()).unwrap();
+        let again = store.import_csv(path, "EURUSD").unwrap();
+        assert_eq!((again.imported, again.already_imported), (0, true));
+        let options = ImportOptions {
+            force: true,
+            ..Default::default()
+        };
+        let forced = store.import_csv_with(path, "EURUSD", &options).unwrap();
+        assert_eq!((forced.imported, forced.already_imported), (2, false));
+
+        // 내용이 바뀌면 다시 임포트
+        std::fs::write(
+            &csv_path,
+            "time,open,high,low,close,volume\n2024-01-02T00:02:00Z,1.3,1.3,1.3,1.3,5\n",
+        )
+        .unwrap();
+        assert_eq!(store.import_csv(path, "EURUSD").unwrap().imported, 1);
+
+        std::fs::remove_file(&csv_path).ok();
+        std::fs::remove_dir_all(&dir).ok();
+    }
+
+    #[cfg(feature = "persistence")]
+    #[test]
+    fn incremental_import_skips_stored_days_and_unchanged_files() {
+        let dir = temp_path("incremental-dir");
+        std::fs::remove_dir_all(&dir).ok();
+        let first = temp_path("incremental-1.csv");
+        let second = temp_path("incremental-2.csv");
+        std::fs::write(
+            &first,
+            "time,open,high,low,close,volume\n\
+             2024-01-02T00:00:00Z,1.1,1.1,1.1,1.1,10\n\
+             2024-01-03T00:00:00Z,1.2,1.2,1.2,1.2,10\n",
+        )
+        .unwrap();
+        // 3일은 이미 있고 4일만 새 날짜
+        std::fs::write(
+            &second,
+            "time,open,high,low,close,volume\n\
+             2024-01-03T00:01:00Z,9.9,9.9,9.9,9.9,10\n\
+             2024-01-04T00:00:00Z,1.3,1.3,1.3,1.3,10\n\
+             2024-01-04T00:01:00Z,1.4,1.4,1.4,1.4,10\n",
+        )
+        .unwrap();
+        let options = ImportOptions {
+            existing_days: ExistingDays::Skip,
+            ..Default::default()
+        };
+
+        {
+            let store = FxStore::open_or_create(&dir).unwrap();
+            store
+                .import_csv_with(first.to_str().unwrap(), "EURUSD", &options)
+                .unwrap();
+        }
+
+        let store = FxStore::open_or_create(&dir).unwrap();
+        let report = store
+            .import_csv_with(second.to_str().unwrap(), "EURUSD", &options)
+            .unwrap();
+        assert_eq!((report.imported, report.days_present), (2, 1));
+        store.flush();
+        let closes: Vec<u32> = store
+            .query_range("EURUSD", DAY_START, DAY_START + 3 * NANOS_PER_DAY)
+            .map(|rec| rec.close)
+            .collect();
+        assert_eq!(closes, [110_000, 120_000, 130_000, 140_000]);
+
+        // 크기와 수정 시각이 그대로면 내용을 읽지 않고 건너뜀
+        let stamp = std::fs::metadata(&first).unwrap().modified().unwrap();
+        std::fs::write(
+            &first,
+            "time,open,high,low,close,volume\n\
+             2024-01-02T00:00:00Z,1.5,1.5,1.5,1.5,10\n\
+             2024-01-03T00:00:00Z,1.6,1.6,1.6,1.6,10\n",
+        )
+        .unwrap();
+        std::fs::File::options()
+            .write(true)
+            .open(&first)
+            .unwrap()
+            .set_modified(stamp)
+            .unwrap();
+        let report = store.import_csv(first.to_str().unwrap(), "EURUSD").unwrap();
+        assert!(report.already_imported);
+
+        // 기본(병합)은 이미 있는 날짜에도 씀
+        let merge = store
+            .import_csv_with(
+                second.to_str().unwrap(),
+                "EURUSD",
+                &ImportOptions {
+                    force: true,
+                    ..Default::default()
+                },
+            )
+            .unwrap();
+        assert_eq!((merge.imported, merge.days_present), (3, 0));
+
+        for path in [&first, &second] {
+            std::fs::remove_file(path).ok();
+        }
+        std::fs::remove_dir_all(&dir).ok();
+    }
+
+    #[cfg(feature = "persistence")]
+    #[test]
+    fn live_bars_survive_a_crash_before_the_block_flush() {
+        let dir = temp_path("live-wal-dir");
+        std::fs::remove_dir_all(&dir).ok();
+        let config = || StoreConfig {
+            flush_interval: Duration::ZERO,
+            tick_tolerance: Duration::ZERO,
+            fsync_policy: FsyncPolicy::EveryWrite,
+            ..Default::default()
+        };
+
+        let store = FxStore::open(&dir, config()).unwrap();
+        store.insert("EURUSD", bar(DAY_START, 110_000)).unwrap();
+        for (minute, bid) in [(1, 1.1001), (2, 1.1002)] {
+            let tick = Tick::from_quote(DAY_START + minute * MINUTE, bid, bid, 1, 0).unwrap();
+            store.insert_tick("EURUSD", tick).unwrap();
+        }
+        // 2분 틱이 1분 봉을 닫음
+        let deadline = Instant::now() + Duration::from_secs(5);
+        while store.query_range("EURUSD", 0, u64::MAX).count() < 2 {
+            assert!(Instant::now() < deadline, "live bar was not published");
+            std::thread::sleep(Duration::from_millis(5));
+        }
+        // 블록 파일에 쓰지 않은 채 프로세스가 죽은 것처럼
+        std::mem::forget(store);
+
+        let store = FxStore::open(&dir, config()).unwrap();
+        let closes: Vec<u32> = store
+            .query_range("EURUSD", 0, u64::MAX)
+            .map(|rec| rec.close)
+            .collect();
+        assert_eq!(closes[..2], [110_000, 110_010]);
+        std::fs::remove_dir_all(&dir).ok();
+    }
+
+    #[cfg(feature = "persistence")]
+    #[test]
+    fn fsync_policy_parses_from_env_values() {
+        assert_eq!("always".parse(), Ok(FsyncPolicy::EveryWrite));
+        assert_eq!("seal".parse(), Ok(FsyncPolicy::OnSeal));
+        assert_eq!(
+            "250ms".parse(),
+            Ok(FsyncPolicy::Every(Duration::from_millis(250)))
+        );
+        assert!("sometimes".parse::<FsyncPolicy>().is_err());
+    }
+
+    #[cfg(feature = "persistence")]
+    #[test]
+    fn open_minute_is_finalized_into_a_stored_bar() {
+        let path = temp_path("finalize-open-minute");
+        let store = FxStore::open(
+            &path,
+            StoreConfig {
+                tick_tolerance: Duration::ZERO,
+                tick_idle_timeout: Duration::from_secs(3600),
+                ..Default::default()
+            },
+        )
+        .unwrap();
+        let bars = store.stream_realtime("EURUSD").unwrap();
+        for (secs, bid) in [(5, 1.1001), (30, 1.1005), (50, 1.1003)] {
+            let tick = Tick::from_quote(DAY_START + secs * 1_000_000_000, bid, bid, 1, 0).unwrap();
+            store.insert_tick("EURUSD", tick).unwrap();
+        }
+
+        assert_eq!(store.finalize_realtime(), 1);
+        let partial = bars.try_recv().expect("partial bar was not delivered");
+        assert_eq!(
+            ({ partial.ts }, { partial.open }, { partial.high }, {
+                partial.close
+            }),
+            (DAY_START, 110_010, 110_050, 110_030)
+        );
+        let stored: Vec<u64> = store
+            .query_range("EURUSD", 0, u64::MAX)
+            .map(|rec| rec.ts)
+            .collect();
+        assert_eq!(stored, [DAY_START]);
+
+        // 이미 마감한 분의 틱은 늦은 틱
+        store
+            .insert_tick(
+                "EURUSD",
+                Tick::from_quote(DAY_START + 55 * 1_000_000_000, 1.2, 1.2, 1, 0).unwrap(),
+            )
+            .unwrap();
+        assert_eq!(store.finalize_realtime(), 1);
+        assert!(bars.try_recv().is_err());
+        drop(store);
+        std::fs::remove_dir_all(&path).ok();
+    }
+
+    #[test]
+    fn count_range_matches_query_range_on_random_ranges() {
+        let store = FxStore::new();
+        for day in [0, 1, 3] {
+            for minute in (0..1440).step_by(37) {
+                let ts = DAY_START + day * NANOS_PER_DAY + minute * MINUTE;
+                store.insert("EURUSD", bar(ts, 110_000)).unwrap();
+            }
+        }
+        assert!(store.has_data("EURUSD", 20240103));
+        assert!(!store.has_data("EURUSD", 20240104));
+        assert!(!store.has_data("GBPUSD", 20240102));
+
+        // 날짜를 통째로 덮는 범위는 해제하지 않음
+        let before = store.stats();
+        assert_eq!(store.count_range("EURUSD", 0, u64::MAX), 3 * 39);
+        let after = store.stats();
+        assert_eq!(
+            (after.blocks_decompressed, after.cache_hits),
+            (before.blocks_decompressed, before.cache_hits)
+        );
+
+        let mut state: u64 = 0x9e37_79b9_7f4a_7c15;
+        let mut next = |bound: u64| {
+            state ^= state << 13;
+            state ^= state >> 7;
+            state ^= state << 17;
+            state % bound
+        };
+        let span = 5 * NANOS_PER_DAY;
+        for _ in 0..200 {
+            let a = DAY_START - NANOS_PER_DAY + next(span);
+            let b = a + next(span / 2);
+            assert_eq!(
+                store.count_range("EURUSD", a, b),
+                store.query_range("EURUSD", a, b).count() as u64,
+                "{}..={}",
+                a,
+                b
+            );
+        }
+        assert_eq!(store.count_range("EURUSD", 10, 5), 0);
+        assert!(matches!(
+            store.try_count_range("GBPUSD", 0, u64::MAX),
+            Err(FxStoreError::UnknownSymbol(_))
+        ));
+    }
+
+    #[test]
+    fn second_bars_resample_to_matching_minute_bars() {
+        // 2024-01-02 23:58:00 ~ 2024-01-03 00:01:59, 11초마다 한 칸씩 빠짐
+        let start = DAY_START + NANOS_PER_DAY - 2 * MINUTE;
+        let seconds: Vec<u64> = (0..240).filter(|s| s % 11 != 5).collect();
+        let cents = |s: u64| 205_000 + (s * 7 % 13);
+        let mut csv = String::new();
+        for &s in &seconds {
+            let ts = chrono::DateTime::from_timestamp_nanos((start + s * 1_000_000_000) as i64);
+            let price = |c: u64| format!("{}.{:02}", c / 100, c % 100);
+            csv.push_str(&format!(
+                "{},{},{},{},{},{}\n",
+                ts.format("%Y%m%d %H%M%S"),
+                price(cents(s)),
+                price(cents(s) + 5),
+                price(cents(s) - 3),
+                price(cents(s) + 1),
+                s + 1
+            ));
+        }
+        let csv_path = temp_path("seconds.csv");
+        std::fs::write(&csv_path, csv).unwrap();
+
+        let store = FxStore::new();
+        store
+            .register_symbol("XAUUSD", Granularity::Second)
+            .unwrap();
+        let report = store
+            .import_csv(csv_path.to_str().unwrap(), "XAUUSD")
+            .unwrap();
+        assert_eq!(report.imported, seconds.len());
+        store.flush();
+        for block in store
+            .blocks
+            .get(&store.symbol_id("XAUUSD").unwrap())
+            .unwrap()
+            .iter()
+        {
+            assert_eq!(block.try_decompress_shared().unwrap().len(), 86_400);
+        }
+
+        let stored: Vec<OHLCV> = store.query_range("XAUUSD", 0, u64::MAX).collect();
+        assert_eq!(stored.len(), seconds.len());
+        let minutes = store.query_resampled("XAUUSD", TimeRange::ALL, 60, Alignment::UTC);
+        assert_eq!(minutes.len(), 4);
+        for (m, bar) in minutes.iter().enumerate() {
+            let secs: Vec<u64> = seconds
+                .iter()
+                .copied()
+                .filter(|s| s / 60 == m as u64)
+                .collect();
+            let raw = |c: u64| c as u32 * 1_000;
+            assert_eq!({ bar.ts }, start + m as u64 * MINUTE);
+            assert_eq!(
+                ({ bar.open }, { bar.high }, { bar.low }, { bar.close }),
+                (
+                    raw(cents(secs[0])),
+                    raw(secs.iter().map(|&s| cents(s) + 5).max().unwrap()),
+                    raw(secs.iter().map(|&s| cents(s) - 3).min().unwrap()),
+                    raw(cents(*secs.last().unwrap()) + 1),
+                )
+            );
+            assert_eq!(bar.total_volume(), secs.iter().map(|s| s + 1).sum::<u64>());
+        }
+
+        // 일봉 롤업은 날짜마다 하나
+        let daily = store.query_daily("XAUUSD", 0, u64::MAX);
+        let days: Vec<(u64, u32, u32)> = daily
+            .iter()
+            .map(|bar| (bar.ts, bar.open, bar.close))
+            .collect();
+        assert_eq!(
+            days,
+            [
+                (DAY_START, minutes[0].open, minutes[1].close),
+                (DAY_START + NANOS_PER_DAY, minutes[2].open, minutes[3].close),
+            ]
+        );
+        std::fs::remove_file(&csv_path).ok();
+    }
+
+    #[test]
+    fn timestamps_beyond_i64_nanos_are_rejected_and_ranges_stay_ordered() {
+        let store = FxStore::new();
+        store.insert("EURUSD", bar(DAY_START, 110_000)).unwrap();
+
+        for ts in [0, MAX_TS + 1, u64::MAX] {
+            assert!(matches!(
+                store.insert("EURUSD", bar(ts, 110_000)),
+                Err(FxStoreError::TimestampOutOfRange(rejected)) if rejected == ts
+            ));
+            assert!(matches!(
+                store.insert_tick("EURUSD", Tick::from_quote(ts, 1.1, 1.1, 1, 0).unwrap()),
+                Err(FxStoreError::TimestampOutOfRange(rejected)) if rejected == ts
+            ));
+        }
+        store.insert("EURUSD", bar(MAX_TS, 110_000)).unwrap();
+
+        // 범위 끝이 i64를 넘어도 u64 그대로 비교
+        let all: Vec<u64> = store
+            .query_range("EURUSD", 0, u64::MAX)
+            .map(|rec| rec.ts)
+            .collect();
+        assert_eq!(all, [DAY_START, MAX_TS]);
+        assert_eq!(store.count_range("EURUSD", MAX_TS, u64::MAX), 1);
+        assert_eq!(store.query_ticks("EURUSD", 0, u64::MAX).len(), 0);
+
+        assert!(matches!(
+            store.try_query_range("EURUSD", DAY_START + 1, DAY_START),
+            Err(FxStoreError::Query(QueryError::InvertedRange { .. }))
+        ));
+        assert!(matches!(
+            store.try_count_range("EURUSD", u64::MAX, 0),
+            Err(FxStoreError::Query(QueryError::InvertedRange { .. }))
+        ));
+        assert_eq!(store.query_range("EURUSD", u64::MAX, 0).count(), 0);
+    }
+
+    #[test]
+    fn inverted_ranges_within_one_day_fail_instead_of_panicking() {
+        let store = FxStore::new();
+        for minute in 0..10 {
+            let ts = DAY_START + minute * MINUTE;
+            store.insert("EURUSD", bar(ts, 110_000)).unwrap();
+            store
+                .insert_tick("EURUSD", Tick::from_quote(ts, 1.1, 1.1001, 1, 0).unwrap())
+                .unwrap();
+        }
+        store.flush();
+        let (start, end) = (DAY_START + 8 * MINUTE, DAY_START + 2 * MINUTE);
+
+        assert!(matches!(
+            store.try_query_range("EURUSD", start, end),
+            Err(FxStoreError::Query(QueryError::InvertedRange { .. }))
+        ));
+        assert!(matches!(
+            store.query_stats("EURUSD", start, end),
+            Err(FxStoreError::Query(QueryError::InvertedRange { .. }))
+        ));
+        assert_eq!(store.query_ticks("EURUSD", end, start).len(), 7);
+        assert!(store.query_ticks("EURUSD", start, end).is_empty());
+        assert_eq!(store.query_chunks("EURUSD", start, end).count(), 0);
+    }
+
+    #[test]
+    fn replay_paces_bars_by_their_timestamps() {
+        let store = FxStore::new();
+        for minute in 0..30 {
+            store
+                .insert(
+                    "EURUSD",
+                    bar(DAY_START + minute * MINUTE, 110_000 + minute as u32),
+                )
+                .unwrap();
+        }
+        store.flush();
+
+        // 29분 간격 × 1/1000 ≈ 1.74초
+        let started = Instant::now();
+        let bars = store
+            .replay(
+                "EURUSD",
+                DAY_START,
+                DAY_START + NANOS_PER_DAY - 1,
+                ReplaySpeed::Multiplier(1000.0),
+            )
+            .unwrap();
+        let mut arrivals = Vec::new();
+        for bar in bars.iter() {
+            arrivals.push(({ bar.ts }, started.elapsed()));
+        }
+
+        assert_eq!(arrivals.len(), 30);
+        assert!(arrivals.windows(2).all(|w| w[0].0 + MINUTE == w[1].0));
+        for (i, (_, elapsed)) in arrivals.iter().enumerate() {
+            let expected = Duration::from_millis(60 * i as u64);
+            assert!(*elapsed >= expected, "bar {} at {:?}", i, elapsed);
+            assert!(
+                *elapsed < expected + Duration::from_millis(500),
+                "bar {} at {:?}",
+                i,
+                elapsed
+            );
+        }
+    }
+
+    #[test]
+    fn replay_stops_when_dropped_and_can_feed_live_subscribers() {
+        let store = FxStore::with_config(StoreConfig {
+            subscriber_capacity: 4,
+            ..Default::default()
+        });
+        for minute in 0..1440 {
+            store
+                .insert("EURUSD", bar(DAY_START + minute * MINUTE, 110_000))
+                .unwrap();
+        }
+        store.flush();
+        let end = DAY_START + NANOS_PER_DAY - 1;
+
+        // 수신자를 버리면 남은 블록을 보내지 않고 끝남
+        let bars = store
+            .replay("EURUSD", DAY_START, end, ReplaySpeed::AsFastAsPossible)
+            .unwrap();
+        assert_eq!({ bars.recv().unwrap().ts }, DAY_START);
+        drop(bars);
+
+        let live = store.stream_realtime("EURUSD").unwrap();
+        let bars = store
+            .replay_broadcast(
+                "EURUSD",
+                DAY_START,
+                DAY_START + 9 * MINUTE,
+                ReplaySpeed::Realtime,
+            )
+            .unwrap();
+        let first = bars.recv().unwrap();
+        assert_eq!({ live.recv_timeout(Duration::from_secs(1)).unwrap().ts }, {
+            first.ts
+        });
+        drop(bars);
+
+        let replayed: Vec<u64> = store
+            .replay("EURUSD", DAY_START, end, ReplaySpeed::Multiplier(f64::NAN))
+            .unwrap()
+            .iter()
+            .map(|bar| bar.ts)
+            .collect();
+        assert_eq!(replayed.len(), 1440);
+        assert!(replayed.windows(2).all(|w| w[0] < w[1]));
+
+        assert!(matches!(
+            store.replay("EURUSX", DAY_START, end, ReplaySpeed::Realtime),
+            Err(FxStoreError::UnknownSymbol(_))
+        ));
+        assert!(matches!(
+            store.replay("EURUSD", end, DAY_START, ReplaySpeed::Realtime),
+            Err(FxStoreError::Query(QueryError::InvertedRange { .. }))
+        ));
+    }
+
+    #[test]
+    fn basket_weights_descaled_closes_and_skips_or_fills_gaps() {
+        let store = FxStore::new();
+        let usdjpy = store
+            .register_symbol("USDJPY", Granularity::Minute)
+            .unwrap();
+        store.set_scale_if_empty("USDJPY", usdjpy.id, 1_000);
+        for minute in 0..6 {
+            let ts = DAY_START + minute * MINUTE;
+            store.insert("EURUSD", bar(ts, 110_000)).unwrap();
+            if minute != 3 {
+                store.insert("GBPUSD", bar(ts, 130_000)).unwrap();
+            }
+            store.insert("USDJPY", bar(ts, 150_000)).unwrap();
+        }
+        let end = DAY_START + 5 * MINUTE;
+
+        // 같은 가중치의 평평한 두 시리즈 → 평균 1.2, 한쪽이 빈 3분은 제외
+        let pair = |a: &str, b: &str| [(a.to_string(), 0.5), (b.to_string(), 0.5)];
+        let bars = store.basket(&pair("EURUSD", "GBPUSD"), DAY_START, end, FillPolicy::Drop);
+        assert_eq!(bars.len(), 5);
+        assert!(
+            bars.iter()
+                .all(|b| b.close == 120_000 && b.open == 120_000 && b.is_valid())
+        );
+        assert!(bars.iter().all(|b| b.ts != DAY_START + 3 * MINUTE));
+
+        let filled = store.basket(
+            &pair("EURUSD", "GBPUSD"),
+            DAY_START,
+            end,
+            FillPolicy::ForwardFill { max_gap_secs: 60 },
+        );
+        assert_eq!(filled.len(), 6);
+        assert!(filled.iter().all(|b| b.close == 120_000));
+
+        // 스케일이 다른 종목도 실수 가격으로 합산: 150 × 0.01 - 1.1 × 0.5 = 0.95
+        let mixed = [("USDJPY".to_string(), 0.01), ("EURUSD".to_string(), -0.5)];
+        let bars = store.basket(&mixed, DAY_START, end, FillPolicy::None);
+        assert_eq!({ bars[0].close }, 95_000);
+
+        let missing = pair("EURUSD", "NZDUSD");
+        assert!(
+            store
+                .basket(&missing, DAY_START, end, FillPolicy::Drop)
+                .is_empty()
+        );
+    }
 }
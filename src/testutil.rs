@@ -0,0 +1,200 @@
+//! 테스트용 합성 데이터 (`cfg(test)` 또는 `test-util` 기능)
+//!
+//! 생성기는 시드만으로 결정되므로 실패한 테스트를 같은 봉으로 다시 돌릴 수 있음
+
+use crate::store::FxStore;
+use crate::types::{NANOS_PER_DAY, OHLCV, PRICE_SCALE};
+use std::fmt::Write as _;
+use std::path::Path;
+use std::sync::atomic::{AtomicU64, Ordering};
+
+pub const MINUTE: u64 = 60_000_000_000;
+
+/// 2024-01-02 00:00:00 UTC (화요일), `build_store`가 쓰는 첫 봉 시각
+pub const START_TS: u64 = 1_704_153_600_000_000_000;
+
+/// `build_store`가 채우는 심볼 (시드는 인덱스)
+pub const SYMBOLS: [&str; 2] = ["EURUSD", "GBPUSD"];
+
+/// `start_ts`부터 1분 간격으로 `count`개인 랜덤워크 1분봉
+///
+/// 1.10000 근처에서 시작해 봉마다 ±2pip 안에서 움직이고, open은 직전 close,
+/// high/low는 open·close를 감쌈. 주말도 쉬지 않음
+pub fn generate_bars(symbol_id: u16, start_ts: u64, count: usize, seed: u64) -> Vec<OHLCV> {
+    generate(symbol_id, start_ts, count, seed, false)
+}
+
+/// `generate_bars`와 같지만 토·일요일(UTC)은 건너뛰어 월요일 00:00부터 이어짐
+pub fn generate_weekday_bars(symbol_id: u16, start_ts: u64, count: usize, seed: u64) -> Vec<OHLCV> {
+    generate(symbol_id, start_ts, count, seed, true)
+}
+
+fn generate(
+    symbol_id: u16,
+    start_ts: u64,
+    count: usize,
+    seed: u64,
+    skip_weekends: bool,
+) -> Vec<OHLCV> {
+    let mut rng = Rng::new(seed);
+    let mut close = 110_000i64;
+    let mut ts = start_ts;
+    let mut bars = Vec::with_capacity(count);
+    while bars.len() < count {
+        if skip_weekends && is_weekend(ts) {
+            ts = (ts / NANOS_PER_DAY + 1) * NANOS_PER_DAY;
+            continue;
+        }
+        let open = close;
+        close = (open + rng.below(41) as i64 - 20).max(1_000);
+        let high = open.max(close) + rng.below(15) as i64;
+        let low = (open.min(close) - rng.below(15) as i64).max(1);
+        bars.push(OHLCV {
+            ts,
+            open: open as u32,
+            high: high as u32,
+            low: low as u32,
+            close: close as u32,
+            volume: 1 + rng.below(500) as u32,
+            symbol_id,
+            ..Default::default()
+        });
+        ts += MINUTE;
+    }
+    bars
+}
+
+/// 토·일요일(UTC)인지 (1970-01-01은 목요일)
+fn is_weekend(ts: u64) -> bool {
+    (ts / NANOS_PER_DAY + 3) % 7 >= 5
+}
+
+/// HISTDATA ASCII M1 (`YYYYMMDD HHMMSS,open,high,low,close,volume`, UTC)로 쓰기
+///
+/// 기본 `StoreConfig`(source_tz UTC)로 `import_csv`하면 같은 봉으로 돌아옴
+pub fn write_histdata_csv(path: impl AsRef<Path>, bars: &[OHLCV]) -> std::io::Result<()> {
+    let price = |raw: u32| format!("{}.{:05}", raw / PRICE_SCALE, raw % PRICE_SCALE);
+    let mut csv = String::with_capacity(bars.len() * 56);
+    for bar in bars {
+        let ts = chrono::DateTime::from_timestamp_nanos(bar.ts as i64);
+        let _ = writeln!(
+            csv,
+            "{},{},{},{},{},{}",
+            ts.format("%Y%m%d %H%M%S"),
+            price(bar.open),
+            price(bar.high),
+            price(bar.low),
+            price(bar.close),
+            bar.total_volume()
+        );
+    }
+    std::fs::write(path, csv)
+}
+
+/// `SYMBOLS`마다 `START_TS`부터 `bars_per_symbol`개를 임포트하고 압축까지 끝낸 스토어
+pub fn build_store(bars_per_symbol: usize) -> FxStore {
+    let series: Vec<(&str, Vec<OHLCV>)> = SYMBOLS
+        .iter()
+        .enumerate()
+        .map(|(i, &symbol)| {
+            (
+                symbol,
+                generate_bars(0, START_TS, bars_per_symbol, i as u64),
+            )
+        })
+        .collect();
+    let series: Vec<(&str, &[OHLCV])> = series
+        .iter()
+        .map(|(symbol, bars)| (*symbol, bars.as_slice()))
+        .collect();
+    load_store(&series)
+}
+
+/// 심볼별 봉을 CSV 임포트 경로로 넣고 `flush`한 스토어
+pub fn load_store(series: &[(&str, &[OHLCV])]) -> FxStore {
+    let store = FxStore::new();
+    for (symbol, bars) in series {
+        let path = temp_csv(symbol);
+        write_histdata_csv(&path, bars).expect("write test csv");
+        store
+            .import_csv(path.to_str().unwrap(), symbol)
+            .expect("import test csv");
+        std::fs::remove_file(&path).ok();
+    }
+    store.flush();
+    store
+}
+
+/// 병렬로 도는 테스트끼리 겹치지 않는 임시 CSV 경로
+fn temp_csv(symbol: &str) -> std::path::PathBuf {
+    static NEXT: AtomicU64 = AtomicU64::new(0);
+    let n = NEXT.fetch_add(1, Ordering::Relaxed);
+    std::env::temp_dir().join(format!(
+        "fx-store-testutil-{}-{}-{}.csv",
+        std::process::id(),
+        n,
+        symbol
+    ))
+}
+
+/// xorshift64* (외부 크레이트 없이 결정적인 난수)
+struct Rng(u64);
+
+impl Rng {
+    fn new(seed: u64) -> Self {
+        // 0은 xorshift의 고정점이라 섞어서 피함
+        Self(seed.wrapping_mul(0x9e37_79b9_7f4a_7c15) | 1)
+    }
+
+    fn next(&mut self) -> u64 {
+        self.0 ^= self.0 >> 12;
+        self.0 ^= self.0 << 25;
+        self.0 ^= self.0 >> 27;
+        self.0.wrapping_mul(0x2545_f491_4f6c_dd1d)
+    }
+
+    fn below(&mut self, n: u64) -> u64 {
+        self.next() % n
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn generated_bars_are_deterministic_and_well_formed() {
+        let bars = generate_bars(3, START_TS, 5000, 7);
+        let again = generate_bars(3, START_TS, 5000, 7);
+        let key = |bar: &OHLCV| (bar.ts, bar.open, bar.high, bar.low, bar.close, bar.volume);
+        assert!(bars.iter().map(key).eq(again.iter().map(key)));
+        assert!(
+            !bars
+                .iter()
+                .map(key)
+                .eq(generate_bars(3, START_TS, 5000, 8).iter().map(key))
+        );
+
+        for pair in bars.windows(2) {
+            assert_eq!(pair[1].ts - pair[0].ts, MINUTE);
+            assert_eq!({ pair[1].open }, { pair[0].close });
+        }
+        for bar in &bars {
+            assert!(bar.high >= bar.open.max(bar.close));
+            assert!(bar.low <= bar.open.min(bar.close));
+            assert!(bar.volume > 0);
+            assert_eq!({ bar.symbol_id }, 3);
+        }
+    }
+
+    #[test]
+    fn weekday_bars_skip_saturday_and_sunday() {
+        // 2024-01-05(금) 23:58부터: 금요일 2개 뒤 월요일 00:00으로
+        let friday = START_TS + 3 * NANOS_PER_DAY + 1438 * MINUTE;
+        let bars = generate_weekday_bars(0, friday, 4, 1);
+        let monday = START_TS + 6 * NANOS_PER_DAY;
+        let ts: Vec<u64> = bars.iter().map(|bar| bar.ts).collect();
+        assert_eq!(ts, [friday, friday + MINUTE, monday, monday + MINUTE]);
+        assert!(generate_bars(0, friday, 4, 1)[2].ts < monday);
+    }
+}
@@ -1,56 +1,370 @@
+use crate::error::{FxStoreError, QueryError};
+use chrono::offset::LocalResult;
+use chrono::{NaiveDateTime, Offset, TimeZone};
+use chrono_tz::Tz;
 use serde::{Deserialize, Serialize};
 
+/// 기본 가격 스케일 (5자리 정밀도), 심볼별 값은 `Symbol::scale`
+pub const PRICE_SCALE: u32 = 100_000;
+
+/// 실수 가격 → 스케일된 정수 (가장 가까운 정수로 반올림)
+///
+/// 음수나 NaN, u32를 넘는 값은 `PriceOverflow`
+#[inline]
+pub fn scale_price(price: f64, scale: u32) -> crate::error::Result<u32> {
+    let scaled = (price * f64::from(scale)).round();
+    if (0.0..=f64::from(u32::MAX)).contains(&scaled) {
+        Ok(scaled as u32)
+    } else {
+        Err(FxStoreError::PriceOverflow {
+            line: 0,
+            price: price.to_string(),
+        })
+    }
+}
+
 /// 40-byte 고정폭 OHLCV (캐시라인 최적화)
+#[allow(clippy::upper_case_acronyms)]
 #[repr(C, packed)]
 #[derive(Copy, Clone, Debug, Default, Serialize, Deserialize)]
 pub struct OHLCV {
     pub ts: u64,   // epoch nanos
-    pub open: u32, // 가격 * 100000 (5자리 정밀도)
+    pub open: u32, // 가격 * 심볼 스케일 (기본 100000, 5자리 정밀도)
     pub high: u32,
     pub low: u32,
     pub close: u32,
     pub volume: u32,
     pub symbol_id: u16,
-    pub _pad: [u8; 10], // Padding to make it 40 bytes total
+    pub spread: u16,    // ask - bid, 가격과 같은 스케일 (0이면 미기록)
+    pub volume_hi: u32, // 집계 봉 거래량의 상위 32비트 (`total_volume`)
+    pub _pad: [u8; 4],  // Padding to make it 40 bytes total
 }
 
 const _: () = assert!(std::mem::size_of::<OHLCV>() == 40);
 
 impl OHLCV {
+    /// UTC 기준 `YYYYMMDD HHMMSS` 문자열 + 실수 가격으로 생성 (형식이 틀리면 `Parse`)
     #[inline]
-    pub fn from_fx(dt: &str, o: f64, h: f64, l: f64, c: f64, v: u32, sym: u16) -> Self {
-        use chrono::NaiveDateTime;
-
-        let ts = NaiveDateTime::parse_from_str(dt, "%Y%m%d %H%M%S")
-            .unwrap()
-            .and_utc()
-            .timestamp_nanos_opt()
-            .unwrap() as u64;
+    pub fn from_fx(
+        dt: &str,
+        o: f64,
+        h: f64,
+        l: f64,
+        c: f64,
+        v: u32,
+        sym: u16,
+    ) -> crate::error::Result<Self> {
+        let ts = parse_fx_datetime(dt, Tz::UTC).ok_or_else(|| FxStoreError::Parse {
+            line: 0,
+            reason: format!("invalid datetime: {:?}", dt),
+        })?;
+        Self::from_prices(ts, o, h, l, c, v, sym)
+    }
 
-        Self {
+    /// UTC epoch nanos + 실수 가격으로 생성 (범위 밖 가격은 `PriceOverflow`)
+    #[inline]
+    pub fn from_prices(
+        ts: u64,
+        o: f64,
+        h: f64,
+        l: f64,
+        c: f64,
+        v: u32,
+        sym: u16,
+    ) -> crate::error::Result<Self> {
+        let price = |p: f64| scale_price(p, PRICE_SCALE);
+        Ok(Self {
             ts,
-            open: (o * 100000.0) as u32,
-            high: (h * 100000.0) as u32,
-            low: (l * 100000.0) as u32,
-            close: (c * 100000.0) as u32,
+            open: price(o)?,
+            high: price(h)?,
+            low: price(l)?,
+            close: price(c)?,
             volume: v,
             symbol_id: sym,
-            _pad: [0; 10],
-        }
+            spread: 0,
+            volume_hi: 0,
+            _pad: [0; 4],
+        })
+    }
+
+    /// 스프레드 설정 (실수 가격 차이를 심볼의 `scale`배로, u16 범위를 넘으면 포화)
+    #[inline]
+    pub fn with_spread(mut self, spread: f64, scale: u32) -> Self {
+        self.spread = (spread * f64::from(scale))
+            .round()
+            .clamp(0.0, f64::from(u16::MAX)) as u16;
+        self
+    }
+
+    /// 64비트 거래량 (volume_hi:volume)
+    #[inline]
+    pub fn total_volume(&self) -> u64 {
+        (self.volume_hi as u64) << 32 | self.volume as u64
+    }
+
+    #[inline]
+    pub fn set_total_volume(&mut self, volume: u64) {
+        self.volume = volume as u32;
+        self.volume_hi = (volume >> 32) as u32;
+    }
+
+    /// 실수 스프레드 (`scale`은 심볼의 가격 배율)
+    #[inline]
+    pub fn spread_f64(&self, scale: u32) -> f64 {
+        f64::from(self.spread) / f64::from(scale)
+    }
+
+    /// OHLC 불변식: low <= open/close <= high
+    #[inline]
+    pub fn is_valid(&self) -> bool {
+        let (open, high, low, close) = (self.open, self.high, self.low, self.close);
+        low <= high && (low..=high).contains(&open) && (low..=high).contains(&close)
     }
 
+    /// 스케일된 정수 가격
     #[inline]
-    pub fn price_f64(&self, field: PriceField) -> f64 {
-        let val = match field {
+    pub fn price(&self, field: PriceField) -> u32 {
+        match field {
             PriceField::Open => self.open,
             PriceField::High => self.high,
             PriceField::Low => self.low,
             PriceField::Close => self.close,
-        };
-        val as f64 / 100000.0
+        }
+    }
+
+    /// 실수 가격 (`scale`은 심볼의 가격 배율)
+    #[inline]
+    pub fn price_f64(&self, field: PriceField, scale: u32) -> f64 {
+        f64::from(self.price(field)) / f64::from(scale)
     }
 }
 
+/// 32-byte 고정폭 틱 (가격 스케일은 OHLCV와 동일)
+#[repr(C, packed)]
+#[derive(Copy, Clone, Debug, Default, Serialize, Deserialize)]
+pub struct Tick {
+    pub ts: u64, // epoch nanos
+    pub bid: u32,
+    pub ask: u32,
+    pub last: u32, // 체결가 (0이면 미기록)
+    pub volume: u32,
+    pub symbol_id: u16,
+    pub _pad: [u8; 6],
+}
+
+const _: () = assert!(std::mem::size_of::<Tick>() == 32);
+
+impl Tick {
+    /// UTC epoch nanos + 실수 호가로 생성 (체결가 없음)
+    #[inline]
+    pub fn from_quote(
+        ts: u64,
+        bid: f64,
+        ask: f64,
+        volume: u32,
+        sym: u16,
+    ) -> crate::error::Result<Self> {
+        Self::from_quote_at(PRICE_SCALE, ts, bid, ask, volume, sym)
+    }
+
+    /// 심볼 스케일을 지정한 `from_quote` (범위 밖 호가는 `PriceOverflow`)
+    #[inline]
+    pub fn from_quote_at(
+        scale: u32,
+        ts: u64,
+        bid: f64,
+        ask: f64,
+        volume: u32,
+        sym: u16,
+    ) -> crate::error::Result<Self> {
+        Ok(Self {
+            ts,
+            bid: scale_price(bid, scale)?,
+            ask: scale_price(ask, scale)?,
+            volume,
+            symbol_id: sym,
+            ..Default::default()
+        })
+    }
+
+    /// 봉 집계 기준가: 체결가, 없으면 중간가
+    #[inline]
+    pub fn price(&self) -> u32 {
+        if self.last != 0 {
+            self.last
+        } else {
+            ((u64::from(self.bid) + u64::from(self.ask)) / 2) as u32
+        }
+    }
+
+    /// ask - bid (u16 범위로 포화)
+    #[inline]
+    pub fn spread(&self) -> u16 {
+        self.ask.saturating_sub(self.bid).min(u32::from(u16::MAX)) as u16
+    }
+}
+
+/// HISTDATA 파일의 기준 시간대 (IANA `EST`: UTC-5 고정, 서머타임 미적용)
+///
+/// 서머타임을 따르는 미 동부 현지 시각 파일은 `chrono_tz::America::New_York`
+pub fn histdata_est() -> Tz {
+    Tz::EST
+}
+
+/// "YYYYMMDD HHMMSS" (source_tz 기준 현지 시각) → UTC epoch nanos
+pub(crate) fn parse_fx_datetime(dt: &str, source_tz: Tz) -> Option<u64> {
+    let local = NaiveDateTime::parse_from_str(dt, "%Y%m%d %H%M%S").ok()?;
+    local_to_ts(&local, source_tz)
+}
+
+/// `tz` 현지 시각 → UTC epoch nanos (오프셋은 시각마다 그 시점의 규칙으로)
+///
+/// 서머타임이 끝나 두 번 나오는 시각은 앞쪽(서머타임), 시작하며 건너뛴 시각은
+/// 바뀌기 전 오프셋으로 해석
+pub(crate) fn local_to_ts(local: &NaiveDateTime, tz: Tz) -> Option<u64> {
+    let dt = match tz.from_local_datetime(local) {
+        LocalResult::Single(dt) | LocalResult::Ambiguous(dt, _) => dt,
+        LocalResult::None => {
+            let before = tz.from_local_datetime(&(*local - chrono::Duration::hours(3)));
+            let offset = before.earliest()?.offset().fix();
+            offset
+                .from_local_datetime(local)
+                .single()?
+                .with_timezone(&tz)
+        }
+    };
+    u64::try_from(dt.timestamp_nanos_opt()?).ok()
+}
+
+/// 하루의 나노초
+pub const NANOS_PER_DAY: u64 = 86_400 * 1_000_000_000;
+
+/// 저장할 수 있는 가장 늦은 시각 (i64 epoch nanos의 끝, 2262-04-11)
+///
+/// 이보다 큰 u64 ts는 `as i64`에서 음수가 되므로 받지 않음
+pub const MAX_TS: u64 = i64::MAX as u64;
+
+/// 반열린 시간 구간 `[start, end)` (epoch nanos)
+///
+/// 앞 페이지의 `end`를 다음 페이지의 `start`로 넘겨도 경계 봉이 두 번 나오지 않음.
+/// 양끝을 포함하는 기존 `(start_ts, end_ts)` 인자는 `TimeRange::inclusive`로 변환
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub struct TimeRange {
+    pub start: u64,
+    pub end: u64,
+}
+
+impl TimeRange {
+    /// 저장 가능한 모든 시각
+    pub const ALL: TimeRange = TimeRange {
+        start: 0,
+        end: MAX_TS + 1,
+    };
+
+    pub fn new(start: u64, end: u64) -> Self {
+        Self { start, end }
+    }
+
+    /// 양끝 포함 구간 `[start, last]`
+    pub fn inclusive(start: u64, last: u64) -> Self {
+        Self {
+            start,
+            end: last.saturating_add(1),
+        }
+    }
+
+    /// `date`(YYYYMMDD) 하루 (UTC, u64로 나타낼 수 없는 먼 날짜는 끝이 포화)
+    pub fn day(date: u32) -> Self {
+        let start = date_to_ts(date);
+        Self {
+            start,
+            end: start.saturating_add(NANOS_PER_DAY),
+        }
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.start >= self.end
+    }
+
+    pub fn contains(&self, ts: u64) -> bool {
+        self.start <= ts && ts < self.end
+    }
+
+    /// 구간에 속하는 마지막 시각 (양끝 포함 API에 넘길 때, 빈 구간이면 None)
+    pub fn last(&self) -> Option<u64> {
+        (!self.is_empty()).then(|| self.end - 1)
+    }
+
+    /// `ts` 바로 다음부터 (`ts`를 마지막으로 받은 봉의 시각으로 넘기는 커서 페이지용)
+    pub fn after(self, ts: u64) -> Self {
+        Self {
+            start: self.start.max(ts.saturating_add(1)),
+            ..self
+        }
+    }
+
+    /// 구간 길이 (nanos)
+    pub fn span(&self) -> u64 {
+        self.end.saturating_sub(self.start)
+    }
+
+    /// 비어 있으면 `InvertedRange`, `max_span`보다 길면 `RangeTooLarge`
+    pub fn check(&self, max_span: Option<u64>) -> Result<(), QueryError> {
+        if self.is_empty() {
+            return Err(QueryError::InvertedRange {
+                start: self.start,
+                end: self.end,
+            });
+        }
+        match max_span {
+            Some(max) if self.span() > max => Err(QueryError::RangeTooLarge {
+                span: self.span(),
+                max,
+            }),
+            _ => Ok(()),
+        }
+    }
+}
+
+/// epoch nanos → UTC 시각 (`MAX_TS`보다 크면 `MAX_TS`로 포화)
+#[cfg(any(test, feature = "api"))]
+pub(crate) fn ts_to_datetime(ts: u64) -> chrono::DateTime<chrono::Utc> {
+    chrono::DateTime::from_timestamp_nanos(i64::try_from(ts).unwrap_or(i64::MAX))
+}
+
+/// UTC epoch nanos → YYYYMMDD (Hinnant civil_from_days, 문자열 변환 없음)
+#[inline]
+pub(crate) fn ts_to_date(ts: u64) -> u32 {
+    // 0000-03-01 기준 일수, 400년(146097일) 주기
+    let z = ts / NANOS_PER_DAY + 719_468;
+    let era = z / 146_097;
+    let doe = z - era * 146_097;
+    let yoe = (doe - doe / 1460 + doe / 36_524 - doe / 146_096) / 365;
+    let doy = doe - (365 * yoe + yoe / 4 - yoe / 100);
+    let mp = (5 * doy + 2) / 153;
+    let day = doy - (153 * mp + 2) / 5 + 1;
+    let month = if mp < 10 { mp + 3 } else { mp - 9 };
+    let year = yoe + era * 400 + u64::from(month <= 2);
+    (year * 10_000 + month * 100 + day) as u32
+}
+
+/// YYYYMMDD → 그날 00:00 UTC의 epoch nanos (1970 이전은 0, u64 범위 초과는 포화)
+#[inline]
+pub(crate) fn date_to_ts(date: u32) -> u64 {
+    let (year, month, day) = (
+        i64::from(date / 10_000),
+        i64::from(date / 100 % 100),
+        i64::from(date % 100),
+    );
+    let year = year - i64::from(month <= 2);
+    let era = year.div_euclid(400);
+    let yoe = year - era * 400;
+    let doy = (153 * ((month + 9) % 12) + 2) / 5 + day - 1;
+    let doe = yoe * 365 + yoe / 4 - yoe / 100 + doy;
+    let days = era * 146_097 + doe - 719_468;
+    u64::try_from(days).map_or(0, |days| days.saturating_mul(NANOS_PER_DAY))
+}
+
 #[derive(Copy, Clone)]
 pub enum PriceField {
     Open,
@@ -59,6 +373,53 @@ pub enum PriceField {
     Close,
 }
 
+impl PriceField {
+    /// `OHLCV` 내 바이트 오프셋
+    pub fn offset(self) -> usize {
+        match self {
+            PriceField::Open => std::mem::offset_of!(OHLCV, open),
+            PriceField::High => std::mem::offset_of!(OHLCV, high),
+            PriceField::Low => std::mem::offset_of!(OHLCV, low),
+            PriceField::Close => std::mem::offset_of!(OHLCV, close),
+        }
+    }
+}
+
+/// 봉 간격 (일일 블록의 슬롯 크기)
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub enum Granularity {
+    Second,
+    #[default]
+    Minute,
+    Hour,
+}
+
+impl Granularity {
+    /// 슬롯 하나의 길이 (초)
+    pub fn seconds(self) -> u64 {
+        match self {
+            Granularity::Second => 1,
+            Granularity::Minute => 60,
+            Granularity::Hour => 3_600,
+        }
+    }
+
+    pub fn nanos(self) -> u64 {
+        self.seconds() * 1_000_000_000
+    }
+
+    /// 하루 블록의 슬롯 수
+    pub fn slots_per_day(self) -> usize {
+        (86_400 / self.seconds()) as usize
+    }
+
+    /// 하루 안의 슬롯 인덱스
+    #[inline]
+    pub fn slot(self, ts: u64) -> usize {
+        ((ts / 1_000_000_000 % 86_400) / self.seconds()) as usize
+    }
+}
+
 /// 심볼 메타데이터
 #[derive(Clone, Debug, Serialize, Deserialize)]
 pub struct Symbol {
@@ -66,4 +427,188 @@ pub struct Symbol {
     pub name: String,
     pub base: String,
     pub quote: String,
+    pub granularity: Granularity,
+    /// 정수 가격 = 실수 가격 * scale (10의 거듭제곱)
+    pub scale: u32,
+}
+
+impl Symbol {
+    /// 스케일된 정수 가격 → 실수
+    #[inline]
+    pub fn to_price(&self, raw: u32) -> f64 {
+        raw as f64 / self.scale as f64
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::{DateTime, NaiveDate};
+
+    fn chrono_date(ts: u64) -> u32 {
+        let dt = DateTime::from_timestamp_nanos(ts as i64);
+        dt.format("%Y%m%d").to_string().parse().unwrap()
+    }
+
+    #[test]
+    fn local_times_follow_the_zone_rules_at_each_timestamp() {
+        let utc = |s: &str| {
+            DateTime::parse_from_rfc3339(s)
+                .unwrap()
+                .timestamp_nanos_opt()
+                .unwrap() as u64
+        };
+        let ny = chrono_tz::America::New_York;
+
+        // 2024-03-10 02:00 EST에 서머타임 시작, 11-03 02:00 EDT에 종료
+        assert_eq!(
+            parse_fx_datetime("20240308 120000", ny),
+            Some(utc("2024-03-08T17:00:00Z"))
+        );
+        assert_eq!(
+            parse_fx_datetime("20240311 120000", ny),
+            Some(utc("2024-03-11T16:00:00Z"))
+        );
+        // 건너뛴 02:30은 EST로, 두 번 나오는 01:30은 앞쪽(EDT)으로
+        assert_eq!(
+            parse_fx_datetime("20240310 023000", ny),
+            Some(utc("2024-03-10T07:30:00Z"))
+        );
+        assert_eq!(
+            parse_fx_datetime("20241103 013000", ny),
+            Some(utc("2024-11-03T05:30:00Z"))
+        );
+
+        // HISTDATA는 여름에도 UTC-5
+        assert_eq!(
+            parse_fx_datetime("20240711 120000", histdata_est()),
+            Some(utc("2024-07-11T17:00:00Z"))
+        );
+    }
+
+    #[test]
+    fn scale_price_rounds_and_rejects_out_of_range() {
+        // 1.15 * 10^5 = 114999.99999999999
+        assert_eq!(scale_price(1.15, PRICE_SCALE).unwrap(), 115_000);
+        assert_eq!(scale_price(150.1235, 1_000).unwrap(), 150_124);
+        assert_eq!(scale_price(0.0, PRICE_SCALE).unwrap(), 0);
+        for price in [42_950.0, -0.1, f64::NAN, f64::INFINITY] {
+            assert!(
+                matches!(
+                    scale_price(price, PRICE_SCALE),
+                    Err(FxStoreError::PriceOverflow { line: 0, .. })
+                ),
+                "{}",
+                price
+            );
+        }
+    }
+
+    #[test]
+    fn constructors_return_errors_instead_of_panicking() {
+        let bar = OHLCV::from_fx("20240102 000000", 1.1, 1.1, 1.1, 1.1, 1, 0).unwrap();
+        assert_eq!({ bar.ts }, 1_704_153_600_000_000_000);
+        assert!(matches!(
+            OHLCV::from_fx("2024-01-02", 1.1, 1.1, 1.1, 1.1, 1, 0),
+            Err(FxStoreError::Parse { line: 0, .. })
+        ));
+        assert!(matches!(
+            OHLCV::from_prices(0, 1.1, 50_000.0, 1.1, 1.1, 1, 0),
+            Err(FxStoreError::PriceOverflow { .. })
+        ));
+        assert!(matches!(
+            Tick::from_quote_at(1_000, 0, 150.0, 5_000_000.0, 1, 0),
+            Err(FxStoreError::PriceOverflow { .. })
+        ));
+    }
+
+    #[test]
+    fn float_accessors_use_the_symbol_scale() {
+        let bar = OHLCV {
+            close: 151_234,
+            ..Default::default()
+        }
+        .with_spread(0.012, 1_000);
+        assert_eq!({ bar.spread }, 12);
+        assert_eq!(bar.spread_f64(1_000), 0.012);
+        assert_eq!(bar.price_f64(PriceField::Close, 1_000), 151.234);
+        assert_eq!(bar.price_f64(PriceField::Close, PRICE_SCALE), 1.51234);
+    }
+
+    #[test]
+    fn time_range_is_half_open() {
+        let range = TimeRange::new(10, 20);
+        assert!(range.contains(10) && range.contains(19) && !range.contains(20));
+        assert_eq!(range.last(), Some(19));
+        assert_eq!(TimeRange::inclusive(10, 19), range);
+        assert_eq!(range.after(14), TimeRange::new(15, 20));
+        assert_eq!(range.after(3), range);
+
+        let empty = TimeRange::new(20, 20);
+        assert!(empty.is_empty());
+        assert_eq!(empty.last(), None);
+        assert_eq!(
+            empty.check(None),
+            Err(QueryError::InvertedRange { start: 20, end: 20 })
+        );
+        assert_eq!(
+            range.check(Some(5)),
+            Err(QueryError::RangeTooLarge { span: 10, max: 5 })
+        );
+        assert!(TimeRange::ALL.contains(MAX_TS));
+
+        let day = TimeRange::day(20240102);
+        assert_eq!(ts_to_date(day.start), 20240102);
+        assert_eq!(ts_to_date(day.end), 20240103);
+        let far = TimeRange::day(99991231);
+        assert_eq!((far.start, far.end), (u64::MAX, u64::MAX));
+    }
+
+    #[test]
+    fn date_math_matches_chrono_for_every_day() {
+        // 1970-01-01 ~ 2262-04-11 (i64 nanos 한계)
+        let last_day = i64::MAX as u64 / NANOS_PER_DAY;
+        for day in 0..last_day {
+            let start = day * NANOS_PER_DAY;
+            let date = chrono_date(start);
+            assert_eq!(ts_to_date(start), date);
+            assert_eq!(ts_to_date(start + NANOS_PER_DAY - 1), date);
+            assert_eq!(date_to_ts(date), start);
+        }
+    }
+
+    #[test]
+    fn date_math_boundaries() {
+        let ts = |y, m, d| {
+            let date = NaiveDate::from_ymd_opt(y, m, d).unwrap();
+            date.and_hms_opt(0, 0, 0)
+                .unwrap()
+                .and_utc()
+                .timestamp_nanos_opt()
+                .unwrap() as u64
+        };
+
+        assert_eq!(ts_to_date(0), 19700101);
+        assert_eq!(ts_to_date(ts(2000, 2, 29)), 20000229);
+        assert_eq!(ts_to_date(ts(2024, 2, 29)), 20240229);
+        assert_eq!(ts_to_date(ts(2100, 3, 1) - 1), 21000228);
+        assert_eq!(ts_to_date(ts(2100, 3, 1)), 21000301);
+        // 2038-01-19 03:14:08 UTC (i32 초 오버플로 지점)
+        assert_eq!(ts_to_date((1u64 << 31) * 1_000_000_000), 20380119);
+        assert_eq!(ts_to_date(u64::MAX), 25540721);
+
+        assert_eq!(date_to_ts(21000301), ts(2100, 3, 1));
+        assert_eq!(date_to_ts(19691231), 0);
+        assert_eq!(date_to_ts(99991231), u64::MAX);
+    }
+
+    #[test]
+    fn timestamps_past_i64_nanos_saturate() {
+        assert_eq!(
+            ts_to_datetime(MAX_TS).to_rfc3339(),
+            "2262-04-11T23:47:16.854775807+00:00"
+        );
+        assert_eq!(ts_to_datetime(u64::MAX), ts_to_datetime(MAX_TS));
+        assert_eq!(ts_to_date(MAX_TS), 22620411);
+    }
 }
@@ -0,0 +1,404 @@
+use crate::error::Result;
+use crate::types::{Granularity, OHLCV};
+use crossbeam::channel::{RecvTimeoutError, Sender, bounded};
+use parking_lot::Mutex;
+use serde::{Deserialize, Serialize};
+use std::fs::{File, OpenOptions};
+use std::io::{BufReader, BufWriter, ErrorKind, Read, Write};
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::thread::JoinHandle;
+use std::time::Duration;
+use xxhash_rust::xxh64::xxh64;
+
+/// 엔트리 페이로드 상한 (길이 필드가 깨져도 이보다 크게 할당하지 않음)
+pub const MAX_ENTRY_BYTES: u32 = 64 << 20;
+
+/// 엔트리 하나에 담는 봉 수 (봉당 40바이트, 더 큰 배치는 여러 엔트리로 나눔)
+const MAX_ENTRY_RECORDS: usize = 1 << 20;
+
+/// 엔트리 헤더: 페이로드 길이(u32 LE) + 페이로드의 XXH64(u64 LE, 시드 0)
+const HEADER_LEN: usize = 12;
+
+/// WAL 엔트리 (헤더 + bincode)
+///
+/// symbol_id는 프로세스마다 달라질 수 있으므로 심볼 이름, 슬롯 간격, 가격 스케일을 함께 기록
+#[derive(Debug, Serialize, Deserialize)]
+pub struct WalEntry {
+    pub symbol: String,
+    pub granularity: Granularity,
+    /// 심볼 가격 스케일 (`Symbol::scale`)
+    pub scale: u32,
+    pub records: Vec<OHLCV>,
+}
+
+/// `WalEntry`와 같은 bincode 표현 (나눠 쓸 때 봉을 복사하지 않으려고)
+#[derive(Serialize)]
+struct WalEntryRef<'a> {
+    symbol: &'a str,
+    granularity: Granularity,
+    scale: u32,
+    records: &'a [OHLCV],
+}
+
+/// WAL을 디스크까지 동기화(fsync)하는 시점
+///
+/// 어느 정책이든 append는 OS까지 flush하므로 프로세스 크래시에는 유실이 없고,
+/// 정책은 OS/전원 장애 때 잃을 수 있는 구간을 정함
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum FsyncPolicy {
+    /// 엔트리마다 (가장 안전, 가장 느림)
+    EveryWrite,
+    /// 백그라운드 스레드가 간격마다 (그 사이 기록이 있을 때만)
+    Every(Duration),
+    /// 블록이 블록 파일에 기록돼 WAL을 비울 때만
+    #[default]
+    OnSeal,
+}
+
+impl std::str::FromStr for FsyncPolicy {
+    type Err = String;
+
+    /// `always`, `seal`, 또는 밀리초 간격 (`100`, `100ms`)
+    fn from_str(s: &str) -> std::result::Result<Self, Self::Err> {
+        match s {
+            "always" => Ok(Self::EveryWrite),
+            "seal" => Ok(Self::OnSeal),
+            _ => s
+                .trim_end_matches("ms")
+                .parse()
+                .map(|ms| Self::Every(Duration::from_millis(ms)))
+                .map_err(|_| format!("expected always, seal or milliseconds, got {:?}", s)),
+        }
+    }
+}
+
+/// 기록 스레드와 동기화 스레드가 공유하는 파일
+struct WalFile {
+    writer: Mutex<BufWriter<File>>,
+    /// 마지막 fsync 뒤에 기록이 있음
+    unsynced: AtomicBool,
+}
+
+impl WalFile {
+    fn sync(&self) -> Result<()> {
+        let mut writer = self.writer.lock();
+        writer.flush()?;
+        self.unsynced.store(false, Ordering::Relaxed);
+        writer.get_ref().sync_data()?;
+        Ok(())
+    }
+}
+
+/// 크래시 복구용 append-only 로그
+pub struct Wal {
+    path: PathBuf,
+    file: Arc<WalFile>,
+    fsync: FsyncPolicy,
+    /// `FsyncPolicy::Every` 동기화 스레드 (Sender를 버리면 종료)
+    sync_thread: Option<(Sender<()>, JoinHandle<()>)>,
+}
+
+impl Wal {
+    pub fn open(path: impl AsRef<Path>) -> Result<Self> {
+        Self::open_with(path, FsyncPolicy::default())
+    }
+
+    pub fn open_with(path: impl AsRef<Path>, fsync: FsyncPolicy) -> Result<Self> {
+        // 간격 0은 엔트리마다
+        let fsync = match fsync {
+            FsyncPolicy::Every(interval) if interval.is_zero() => FsyncPolicy::EveryWrite,
+            fsync => fsync,
+        };
+        let path = path.as_ref().to_path_buf();
+        let file = OpenOptions::new().create(true).append(true).open(&path)?;
+        let file = Arc::new(WalFile {
+            writer: Mutex::new(BufWriter::new(file)),
+            unsynced: AtomicBool::new(false),
+        });
+
+        let sync_thread = match fsync {
+            FsyncPolicy::Every(interval) => {
+                let (stop_tx, stop_rx) = bounded::<()>(0);
+                let thread_file = Arc::clone(&file);
+                let handle = std::thread::spawn(move || {
+                    while let Err(RecvTimeoutError::Timeout) = stop_rx.recv_timeout(interval) {
+                        if !thread_file.unsynced.load(Ordering::Relaxed) {
+                            continue;
+                        }
+                        // 실패하면 표시가 남아 다음 주기에 다시 시도
+                        if let Err(e) = thread_file.sync() {
+                            thread_file.unsynced.store(true, Ordering::Relaxed);
+                            tracing::warn!(error = %e, "WAL fsync failed");
+                        }
+                    }
+                });
+                Some((stop_tx, handle))
+            }
+            _ => None,
+        };
+
+        Ok(Self {
+            path,
+            file,
+            fsync,
+            sync_thread,
+        })
+    }
+
+    /// 로그를 읽고, 유효한 엔트리 뒤의 깨진 꼬리는 잘라낸 뒤 이어 쓰기용으로 열기
+    ///
+    /// 꼬리를 남겨 두면 그 뒤에 붙는 엔트리가 다음 재생에서 읽히지 않음
+    pub fn recover(path: impl AsRef<Path>, fsync: FsyncPolicy) -> Result<(Self, Vec<WalEntry>)> {
+        let path = path.as_ref();
+        let (entries, valid_len) = read_entries(path)?;
+        if std::fs::metadata(path).map_or(0, |meta| meta.len()) > valid_len {
+            let file = OpenOptions::new().write(true).open(path)?;
+            file.set_len(valid_len)?;
+            file.sync_data()?;
+        }
+        Ok((Self::open_with(path, fsync)?, entries))
+    }
+
+    /// 엔트리 기록 후 OS로 flush하고 `FsyncPolicy`에 따라 디스크까지 동기화
+    ///
+    /// `MAX_ENTRY_RECORDS`보다 많은 봉은 같은 심볼의 엔트리 여러 개로 나눠 기록
+    pub fn append(&self, entry: &WalEntry) -> Result<()> {
+        let mut writer = self.file.writer.lock();
+        for records in entry.records.chunks(MAX_ENTRY_RECORDS) {
+            let payload = bincode::serialize(&WalEntryRef {
+                symbol: &entry.symbol,
+                granularity: entry.granularity,
+                scale: entry.scale,
+                records,
+            })?;
+            writer.write_all(&(payload.len() as u32).to_le_bytes())?;
+            writer.write_all(&xxh64(&payload, 0).to_le_bytes())?;
+            writer.write_all(&payload)?;
+        }
+        writer.flush()?;
+        match self.fsync {
+            FsyncPolicy::EveryWrite => writer.get_ref().sync_data()?,
+            FsyncPolicy::Every(_) => self.file.unsynced.store(true, Ordering::Relaxed),
+            FsyncPolicy::OnSeal => {}
+        }
+        Ok(())
+    }
+
+    /// 디스크까지 동기화
+    pub fn sync(&self) -> Result<()> {
+        self.file.sync()
+    }
+
+    /// 로그 비우기 (블록이 영속화된 뒤에만 호출)
+    pub fn truncate(&self) -> Result<()> {
+        let mut writer = self.file.writer.lock();
+        writer.flush()?;
+        writer.get_ref().set_len(0)?;
+        self.file.unsynced.store(false, Ordering::Relaxed);
+        writer.get_ref().sync_data()?;
+        Ok(())
+    }
+
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    /// 로그 전체 읽기 (잘렸거나 체크섬이 맞지 않는 첫 엔트리 앞까지만)
+    pub fn replay(path: impl AsRef<Path>) -> Result<Vec<WalEntry>> {
+        Ok(read_entries(path.as_ref())?.0)
+    }
+}
+
+impl Drop for Wal {
+    /// 동기화 스레드를 멈추고 남은 기록을 디스크까지 동기화
+    fn drop(&mut self) {
+        if let Some((stop, handle)) = self.sync_thread.take() {
+            drop(stop);
+            handle.join().ok();
+            if self.file.unsynced.load(Ordering::Relaxed) {
+                self.file.sync().ok();
+            }
+        }
+    }
+}
+
+/// 유효한 엔트리와 그 끝 위치 (바이트)
+fn read_entries(path: &Path) -> Result<(Vec<WalEntry>, u64)> {
+    let file = match File::open(path) {
+        Ok(file) => file,
+        Err(e) if e.kind() == ErrorKind::NotFound => return Ok((Vec::new(), 0)),
+        Err(e) => return Err(e.into()),
+    };
+    let mut reader = BufReader::new(file);
+    let mut entries = Vec::new();
+    let mut valid_len = 0u64;
+
+    loop {
+        let mut header = [0u8; HEADER_LEN];
+        match reader.read_exact(&mut header) {
+            Ok(()) => {}
+            Err(e) if e.kind() == ErrorKind::UnexpectedEof => break,
+            Err(e) => return Err(e.into()),
+        }
+        let len = u32::from_le_bytes(header[..4].try_into().unwrap());
+        let checksum = u64::from_le_bytes(header[4..].try_into().unwrap());
+        if len > MAX_ENTRY_BYTES {
+            tracing::warn!(
+                offset = valid_len,
+                len,
+                "WAL entry length out of range, stopping replay"
+            );
+            break;
+        }
+
+        // 실제로 읽힌 만큼만 할당
+        let mut payload = Vec::new();
+        reader
+            .by_ref()
+            .take(u64::from(len))
+            .read_to_end(&mut payload)?;
+        if payload.len() < len as usize {
+            tracing::warn!(
+                offset = valid_len,
+                "WAL ends in a partial entry, stopping replay"
+            );
+            break;
+        }
+        if xxh64(&payload, 0) != checksum {
+            tracing::warn!(
+                offset = valid_len,
+                "WAL entry checksum mismatch, stopping replay"
+            );
+            break;
+        }
+        match bincode::deserialize(&payload) {
+            Ok(entry) => entries.push(entry),
+            Err(e) => {
+                tracing::warn!(offset = valid_len, error = %e, "undecodable WAL entry, stopping replay");
+                break;
+            }
+        }
+        valid_len += (HEADER_LEN + payload.len()) as u64;
+    }
+
+    Ok((entries, valid_len))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn entry(symbol: &str, closes: &[u32]) -> WalEntry {
+        WalEntry {
+            symbol: symbol.to_string(),
+            granularity: Granularity::Minute,
+            scale: 100_000,
+            records: closes
+                .iter()
+                .enumerate()
+                .map(|(i, &close)| OHLCV {
+                    ts: 1_704_153_600_000_000_000 + i as u64 * 60_000_000_000,
+                    close,
+                    ..Default::default()
+                })
+                .collect(),
+        }
+    }
+
+    fn closes(entries: &[WalEntry]) -> Vec<Vec<u32>> {
+        entries
+            .iter()
+            .map(|entry| entry.records.iter().map(|rec| rec.close).collect())
+            .collect()
+    }
+
+    #[test]
+    fn replay_stops_at_the_first_bad_entry_and_recover_trims_it() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("test.wal");
+        {
+            let wal = Wal::open(&path).unwrap();
+            wal.append(&entry("EURUSD", &[1, 2])).unwrap();
+            wal.append(&entry("EURUSD", &[3])).unwrap();
+            wal.append(&entry("GBPUSD", &[4])).unwrap();
+        }
+        let bytes = std::fs::read(&path).unwrap();
+        let first = HEADER_LEN + u32::from_le_bytes(bytes[..4].try_into().unwrap()) as usize;
+        assert_eq!(
+            closes(&Wal::replay(&path).unwrap()),
+            [vec![1, 2], vec![3], vec![4]]
+        );
+
+        // 두 번째 엔트리 페이로드 한 바이트를 뒤집으면 그 앞까지만
+        let mut flipped = bytes.clone();
+        flipped[first + HEADER_LEN + 3] ^= 0xff;
+        std::fs::write(&path, &flipped).unwrap();
+        assert_eq!(closes(&Wal::replay(&path).unwrap()), [vec![1, 2]]);
+
+        // 길이 필드가 깨져도 큰 버퍼를 할당하지 않고 멈춤
+        let mut huge = bytes.clone();
+        huge[first..first + 4].copy_from_slice(&u32::MAX.to_le_bytes());
+        std::fs::write(&path, &huge).unwrap();
+        assert_eq!(closes(&Wal::replay(&path).unwrap()), [vec![1, 2]]);
+
+        // 잘린 꼬리는 recover가 잘라내 새 엔트리가 다음 재생에서 읽힘
+        std::fs::write(&path, &bytes[..bytes.len() - 5]).unwrap();
+        let (wal, entries) = Wal::recover(&path, FsyncPolicy::OnSeal).unwrap();
+        assert_eq!(closes(&entries), [vec![1, 2], vec![3]]);
+        wal.append(&entry("GBPUSD", &[5])).unwrap();
+        drop(wal);
+        assert_eq!(
+            closes(&Wal::replay(&path).unwrap()),
+            [vec![1, 2], vec![3], vec![5]]
+        );
+    }
+
+    #[test]
+    fn large_batches_are_split_into_bounded_entries() {
+        let size = bincode::serialized_size(&entry("EURUSD", &vec![0; MAX_ENTRY_RECORDS])).unwrap();
+        assert!(size <= u64::from(MAX_ENTRY_BYTES));
+
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("big.wal");
+        let wal = Wal::open(&path).unwrap();
+        wal.append(&entry("EURUSD", &vec![7; MAX_ENTRY_RECORDS + 1]))
+            .unwrap();
+        let entries = Wal::replay(&path).unwrap();
+        assert_eq!(
+            entries.iter().map(|e| e.records.len()).collect::<Vec<_>>(),
+            [MAX_ENTRY_RECORDS, 1]
+        );
+        assert_eq!(
+            { entries[1].records[0].ts },
+            1_704_153_600_000_000_000 + MAX_ENTRY_RECORDS as u64 * 60_000_000_000
+        );
+    }
+
+    #[test]
+    fn interval_policy_syncs_from_a_timer() {
+        let dir = tempfile::tempdir().unwrap();
+        let wal = Wal::open_with(
+            dir.path().join("timer.wal"),
+            FsyncPolicy::Every(Duration::from_millis(10)),
+        )
+        .unwrap();
+        wal.append(&entry("EURUSD", &[1])).unwrap();
+        assert!(wal.file.unsynced.load(Ordering::Relaxed));
+
+        // 다음 append 없이도 동기화됨
+        let deadline = std::time::Instant::now() + Duration::from_secs(5);
+        while wal.file.unsynced.load(Ordering::Relaxed) {
+            assert!(std::time::Instant::now() < deadline, "WAL was never synced");
+            std::thread::sleep(Duration::from_millis(5));
+        }
+        assert!(wal.sync_thread.is_some());
+        assert!(
+            Wal::open(dir.path().join("seal.wal"))
+                .unwrap()
+                .sync_thread
+                .is_none()
+        );
+    }
+}
@@ -0,0 +1,131 @@
+//! `testutil`의 합성 봉으로 임포트부터 HTTP 응답까지 확인하는 통합 테스트
+
+use fx_store::prelude::*;
+use fx_store::testutil::{
+    MINUTE, START_TS, build_store, generate_bars, generate_weekday_bars, load_store,
+    write_histdata_csv,
+};
+
+const DAY: u64 = 1440 * MINUTE;
+
+/// 비교용 봉 필드 (packed 구조체라 필드를 복사해서 비교)
+fn fields(bar: &OHLCV) -> (u64, u32, u32, u32, u32, u64) {
+    (
+        bar.ts,
+        bar.open,
+        bar.high,
+        bar.low,
+        bar.close,
+        bar.total_volume(),
+    )
+}
+
+#[test]
+fn imported_histdata_queries_back_unchanged() {
+    let bars = generate_bars(0, START_TS, 3 * 1440, 11);
+    let path = std::env::temp_dir().join(format!("fx-store-it-{}.csv", std::process::id()));
+    write_histdata_csv(&path, &bars).unwrap();
+
+    let store = FxStore::new();
+    let report = store.import_csv(path.to_str().unwrap(), "EURUSD").unwrap();
+    std::fs::remove_file(&path).ok();
+    store.flush();
+
+    assert_eq!(report.imported, bars.len());
+    assert_eq!(report.skipped, 0);
+    assert_eq!(store.price_scale("EURUSD"), PRICE_SCALE);
+    let stored: Vec<OHLCV> = store.query("EURUSD", TimeRange::ALL).collect();
+    assert!(stored.iter().map(fields).eq(bars.iter().map(fields)));
+}
+
+#[test]
+fn ranges_include_start_and_exclude_end() {
+    let store = build_store(1440);
+    let window = TimeRange::new(START_TS + 10 * MINUTE, START_TS + 20 * MINUTE);
+    let bars: Vec<OHLCV> = store.query("EURUSD", window).collect();
+    let ts: Vec<u64> = bars.iter().map(|bar| bar.ts).collect();
+    assert_eq!(ts.len(), 10);
+    assert_eq!(ts[0], START_TS + 10 * MINUTE);
+    assert_eq!(ts[9], START_TS + 19 * MINUTE);
+    assert_eq!(store.count("EURUSD", window), 10);
+
+    // 이어 붙인 창은 봉을 빠뜨리거나 겹치지 않음
+    let next = TimeRange::new(window.end, window.end + 10 * MINUTE);
+    assert_eq!(
+        store.query("EURUSD", next).next().map(|bar| bar.ts),
+        Some(window.end)
+    );
+
+    let inclusive = TimeRange::inclusive(START_TS + 10 * MINUTE, START_TS + 20 * MINUTE);
+    assert_eq!(store.count("EURUSD", inclusive), 11);
+
+    // 데이터 밖으로 걸친 범위는 있는 봉만, 완전히 밖이면 비어 있음
+    let straddle = TimeRange::new(START_TS - DAY, START_TS + 5 * MINUTE);
+    assert_eq!(store.count("EURUSD", straddle), 5);
+    assert_eq!(
+        store.count("EURUSD", TimeRange::new(START_TS + DAY, START_TS + 2 * DAY)),
+        0
+    );
+    assert_eq!(store.count("EURUSD", TimeRange::ALL), 1440);
+
+    assert!(matches!(
+        store.try_count_range("EURUSD", START_TS + MINUTE, START_TS),
+        Err(FxStoreError::Query(QueryError::InvertedRange { .. }))
+    ));
+}
+
+#[test]
+fn multi_day_results_stay_ordered_across_blocks_and_weekends() {
+    // 화요일부터 평일 5일치: 화~금 뒤 주말을 건너뛰어 월요일
+    let bars = generate_weekday_bars(0, START_TS, 5 * 1440, 5);
+    let store = load_store(&[("EURUSD", &bars), ("GBPUSD", &bars[..1440])]);
+    assert_eq!(store.block_count("EURUSD"), 5);
+    assert_eq!(store.block_count("GBPUSD"), 1);
+
+    let stored: Vec<OHLCV> = store.query("EURUSD", TimeRange::ALL).collect();
+    assert!(stored.windows(2).all(|pair| pair[0].ts < pair[1].ts));
+    assert!(stored.iter().map(fields).eq(bars.iter().map(fields)));
+
+    // 금요일 23:00 ~ 월요일 01:00은 주말 없이 120개
+    let friday_close = START_TS + 4 * DAY - 60 * MINUTE;
+    let monday_open = START_TS + 6 * DAY;
+    let across = TimeRange::new(friday_close, monday_open + 60 * MINUTE);
+    let ts: Vec<u64> = store.query("EURUSD", across).map(|bar| bar.ts).collect();
+    assert_eq!(ts.len(), 120);
+    assert_eq!(ts[59], monday_open - 2 * DAY - MINUTE);
+    assert_eq!(ts[60], monday_open);
+}
+
+#[cfg(feature = "api")]
+#[tokio::test]
+async fn history_endpoint_serves_the_built_store() {
+    use axum::body::{Body, to_bytes};
+    use axum::http::{Request, StatusCode};
+    use std::sync::Arc;
+    use tower::ServiceExt;
+
+    let store = Arc::new(build_store(1440));
+    let expected: Vec<OHLCV> = store
+        .query("GBPUSD", TimeRange::new(START_TS, START_TS + 60 * MINUTE))
+        .collect();
+    let app = create_app(Arc::clone(&store));
+
+    let uri = "/history/GBPUSD?start=2024-01-02T00:00:00Z&end=2024-01-02T01:00:00Z";
+    let response = app
+        .oneshot(Request::get(uri).body(Body::empty()).unwrap())
+        .await
+        .unwrap();
+    assert_eq!(response.status(), StatusCode::OK);
+    let body = to_bytes(response.into_body(), usize::MAX).await.unwrap();
+    let body: serde_json::Value = serde_json::from_slice(&body).unwrap();
+
+    let data = body["data"].as_array().unwrap();
+    assert_eq!(data.len(), 60);
+    for (candle, bar) in data.iter().zip(&expected) {
+        assert_eq!(candle["symbol"], "GBPUSD");
+        assert_eq!(candle["timestamp"], (bar.ts / 1_000_000_000) as i64);
+        assert_eq!(candle["close"], bar.close as f64 / PRICE_SCALE as f64);
+        assert_eq!(candle["volume"], bar.total_volume());
+    }
+    assert!(body["next_cursor"].is_null());
+}
@@ -0,0 +1,87 @@
+//! `fx_store::prelude`만으로 스토어를 임베드하는 통합 테스트
+
+use fx_store::prelude::*;
+
+const MINUTE: u64 = 60_000_000_000;
+// 2024-01-02 00:00:00 UTC
+const DAY_START: u64 = 1_704_153_600_000_000_000;
+
+fn bars(count: u64) -> Vec<OHLCV> {
+    (0..count)
+        .map(|i| {
+            let close = 1.1 + i as f64 * 0.0001;
+            OHLCV::from_prices(
+                DAY_START + i * MINUTE,
+                close,
+                close + 0.0002,
+                close - 0.0002,
+                close,
+                10,
+                0,
+            )
+            .unwrap()
+        })
+        .collect()
+}
+
+#[test]
+fn embedded_store_queries_through_the_prelude() {
+    let store = FxStore::with_config(StoreConfig::default());
+    for bar in bars(120) {
+        store.insert("EURUSD", bar).unwrap();
+    }
+    store.flush();
+
+    let first_hour = TimeRange::new(DAY_START, DAY_START + 60 * MINUTE);
+    let records: Vec<OHLCV> = store.query("EURUSD", first_hour).collect();
+    assert_eq!(records.len(), 60);
+    assert_eq!(store.count("EURUSD", TimeRange::ALL), 120);
+    assert_eq!(
+        store.symbol("EURUSD").unwrap().granularity,
+        Granularity::default()
+    );
+
+    let sma = TechnicalIndicators::sma(&records, 10, PRICE_SCALE);
+    assert_eq!(sma.len(), 51);
+    let (min, max) = SimdFilter::minmax(&records, PriceField::Close);
+    assert_eq!(
+        (min, max),
+        (
+            scale_price(1.1, PRICE_SCALE).unwrap(),
+            scale_price(1.1059, PRICE_SCALE).unwrap()
+        )
+    );
+    assert_eq!(SimdFilter::count_in_range(&records, min, max), 60);
+
+    let hourly = resample(&records, 3600, Alignment::default());
+    assert_eq!(hourly.len(), 1);
+    assert_eq!({ hourly[0].close }, max);
+}
+
+#[test]
+fn unknown_symbols_surface_as_store_errors() {
+    let store = FxStore::new();
+    let result: Result<u64> = store.try_count_range("GBPUSD", 0, u64::MAX);
+    assert!(matches!(result, Err(FxStoreError::UnknownSymbol(symbol)) if symbol == "GBPUSD"));
+}
+
+#[cfg(feature = "api")]
+#[tokio::test]
+async fn create_app_serves_an_embedded_store() {
+    use axum::body::{Body, to_bytes};
+    use axum::http::{Request, StatusCode};
+    use std::sync::Arc;
+    use tower::ServiceExt;
+
+    let store = Arc::new(FxStore::new());
+    store.insert("EURUSD", bars(1)[0]).unwrap();
+
+    let response = create_app(store)
+        .oneshot(Request::get("/symbols").body(Body::empty()).unwrap())
+        .await
+        .unwrap();
+    assert_eq!(response.status(), StatusCode::OK);
+    let body = to_bytes(response.into_body(), usize::MAX).await.unwrap();
+    let body: serde_json::Value = serde_json::from_slice(&body).unwrap();
+    assert_eq!(body["symbols"], serde_json::json!(["EURUSD"]));
+}